@@ -390,6 +390,10 @@ TOC = [
                 "module: wezterm.gui",
                 "config/lua/wezterm.gui",
             ),
+            Gen(
+                "module: wezterm.http",
+                "config/lua/wezterm.http",
+            ),
             Gen(
                 "module: wezterm.mux",
                 "config/lua/wezterm.mux",
@@ -15,7 +15,9 @@ use anyhow::{bail, Context as _, Error};
 use config::keyassignment::{PaneDirection, ScrollbackEraseMode};
 use mux::client::{ClientId, ClientInfo};
 use mux::pane::PaneId;
+use mux::recording::RecordingOptions;
 use mux::renderable::{RenderableDimensions, StableCursorPosition};
+use mux::ssh::SshForward;
 use mux::tab::{PaneNode, SerdeUrl, SplitRequest, TabId};
 use mux::window::WindowId;
 use portable_pty::CommandBuilder;
@@ -34,7 +36,10 @@ use termwiz::image::{ImageData, TextureCoordinate};
 use termwiz::surface::{Line, SequenceNo};
 use thiserror::Error;
 use wezterm_term::color::ColorPalette;
-use wezterm_term::{Alert, ClipboardSelection, StableRowIndex, TerminalSize};
+use wezterm_term::{
+    Alert, ClipboardSelection, CommandBlock, SemanticType, SemanticZone, StableRowIndex,
+    TerminalSize,
+};
 
 #[derive(Error, Debug)]
 #[error("Corrupt Response: {0}")]
@@ -441,7 +446,7 @@ macro_rules! pdu {
 /// The overall version of the codec.
 /// This must be bumped when backwards incompatible changes
 /// are made to the types and protocol.
-pub const CODEC_VERSION: usize = 45;
+pub const CODEC_VERSION: usize = 54;
 
 // Defines the Pdu enum.
 // Each struct has an explicit identifying number.
@@ -502,6 +507,31 @@ pdu! {
     GetPaneDirection: 60,
     GetPaneDirectionResponse: 61,
     AdjustPaneSize: 62,
+    GetCommandBlocks: 63,
+    GetCommandBlocksResponse: 64,
+    SubscribePaneOutput: 65,
+    SubscribePaneOutputResponse: 66,
+    UnsubscribePaneOutput: 67,
+    PaneOutput: 68,
+    GetSemanticZones: 69,
+    GetSemanticZonesResponse: 70,
+    GetSemanticZoneText: 71,
+    GetSemanticZoneTextResponse: 72,
+    ListSshForwards: 73,
+    ListSshForwardsResponse: 74,
+    AddSshForward: 75,
+    AddSshForwardResponse: 76,
+    RemoveSshForward: 77,
+    SaveSession: 78,
+    SaveSessionResponse: 79,
+    RestoreSession: 80,
+    RestoreSessionResponse: 81,
+    ListSessions: 82,
+    ListSessionsResponse: 83,
+    SetPaneRecording: 84,
+    SetPaneRecordingResponse: 85,
+    GetPanePalette: 86,
+    GetPanePaletteResponse: 87,
 }
 
 impl Pdu {
@@ -522,6 +552,57 @@ impl Pdu {
         }
     }
 
+    /// Returns true if this type of Pdu may only be sent by a client
+    /// that has full access to the mux.
+    /// This is an allowlist of the requests that only examine the
+    /// state of the mux, so that newly added Pdus are denied to
+    /// restricted clients until they are explicitly considered here.
+    pub fn requires_full_access(&self) -> bool {
+        !matches!(
+            self,
+            Self::Ping(_)
+                | Self::GetCodecVersion(_)
+                | Self::SetClientId(_)
+                | Self::SetFocusedPane(_)
+                | Self::GetClientList(_)
+                | Self::ListPanes(_)
+                | Self::GetLines(_)
+                | Self::GetPaneRenderChanges(_)
+                | Self::GetPaneRenderableDimensions(_)
+                | Self::GetPanePalette(_)
+                | Self::SearchScrollbackRequest(_)
+                | Self::GetImageCell(_)
+                | Self::GetPaneDirection(_)
+                | Self::GetCommandBlocks(_)
+                | Self::GetSemanticZones(_)
+                | Self::GetSemanticZoneText(_)
+                | Self::SubscribePaneOutput(_)
+                | Self::UnsubscribePaneOutput(_)
+                | Self::ListSshForwards(_)
+                | Self::ListSessions(_)
+        )
+    }
+
+    /// Returns the pane whose content is examined by this Pdu, so that
+    /// access to it can be checked for clients with a restricted view
+    pub fn viewed_pane_id(&self) -> Option<PaneId> {
+        match self {
+            Self::GetLines(GetLines { pane_id, .. })
+            | Self::GetPaneRenderChanges(GetPaneRenderChanges { pane_id })
+            | Self::GetPaneRenderableDimensions(GetPaneRenderableDimensions { pane_id })
+            | Self::SearchScrollbackRequest(SearchScrollbackRequest { pane_id, .. })
+            | Self::GetImageCell(GetImageCell { pane_id, .. })
+            | Self::GetPaneDirection(GetPaneDirection { pane_id, .. })
+            | Self::GetCommandBlocks(GetCommandBlocks { pane_id })
+            | Self::GetSemanticZones(GetSemanticZones { pane_id })
+            | Self::GetSemanticZoneText(GetSemanticZoneText { pane_id, .. })
+            | Self::GetPanePalette(GetPanePalette { pane_id })
+            | Self::SubscribePaneOutput(SubscribePaneOutput { pane_id, .. })
+            | Self::SetFocusedPane(SetFocusedPane { pane_id }) => Some(*pane_id),
+            _ => None,
+        }
+    }
+
     pub fn stream_decode(buffer: &mut Vec<u8>) -> anyhow::Result<Option<DecodedPdu>> {
         let mut cursor = Cursor::new(buffer.as_slice());
         match Self::decode(&mut cursor) {
@@ -853,7 +934,11 @@ pub struct GetClientListResponse {
 pub struct Resize {
     pub containing_tab_id: TabId,
     pub pane_id: PaneId,
+    /// The size of the pane, measured in the cells of its tab
     pub size: TerminalSize,
+    /// The size of a cell of the pane, if that differs from the
+    /// cells of its tab; see `Tab::set_pane_cell_size`
+    pub cell_size: Option<TerminalSize>,
 }
 
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
@@ -881,6 +966,190 @@ pub struct GetPaneDirectionResponse {
     pub pane_id: Option<PaneId>,
 }
 
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct GetCommandBlocks {
+    pub pane_id: PaneId,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct GetCommandBlocksResponse {
+    pub pane_id: PaneId,
+    pub blocks: Vec<CommandBlock>,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct GetSemanticZones {
+    pub pane_id: PaneId,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct GetSemanticZonesResponse {
+    pub pane_id: PaneId,
+    pub zones: Vec<SemanticZone>,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct GetSemanticZoneText {
+    pub pane_id: PaneId,
+    /// If specified, only zones of this type are considered
+    pub semantic_type: Option<SemanticType>,
+    /// The index of the zone amongst those being considered.
+    /// Negative values count backwards from the most recent zone,
+    /// so -1 is the most recent zone.
+    pub index: isize,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct GetSemanticZoneTextResponse {
+    pub pane_id: PaneId,
+    pub zone: SemanticZone,
+    pub text: String,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct ListSshForwards {
+    /// If specified, only the forwards of this ssh domain are
+    /// listed, otherwise those of all established ssh domains
+    pub domain: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct ListSshForwardsResponse {
+    pub forwards: Vec<SshForward>,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct AddSshForward {
+    pub domain: String,
+    /// One of `Local`, `Remote` or `Dynamic`
+    pub kind: String,
+    /// In the form accepted by `ssh -L`, `ssh -R` or `ssh -D`
+    pub spec: String,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct AddSshForwardResponse {
+    pub forward: SshForward,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct RemoveSshForward {
+    pub domain: String,
+    pub id: usize,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct SaveSession {
+    /// The name of the session; the default session
+    /// is used if not specified
+    pub name: Option<String>,
+    /// Save the scrollback text of each pane as well as the layout.
+    /// If not specified, `session_save_scrollback` is used
+    pub scrollback: Option<bool>,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct SaveSessionResponse {
+    /// The path of the file that the session was saved to
+    pub path: String,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct RestoreSession {
+    /// The name of the session; the default session
+    /// is used if not specified
+    pub name: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct RestoreSessionResponse {
+    pub window_ids: Vec<WindowId>,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct ListSessions {}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct ListSessionsResponse {
+    pub names: Vec<String>,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct SetPaneRecording {
+    pub pane_id: PaneId,
+    /// Start recording with these options, or stop
+    /// recording if not specified
+    pub start: Option<RecordingOptions>,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct SetPaneRecordingResponse {
+    /// The path of the recording that was started or stopped,
+    /// on the host where the server is running
+    pub path: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct GetPanePalette {
+    pub pane_id: PaneId,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct GetPanePaletteResponse {
+    pub palette: ColorPalette,
+}
+
+/// Selects what is delivered by a pane output subscription
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone, Copy)]
+pub enum PaneOutputFormat {
+    /// The bytes read from the pty, including any escape sequences.
+    /// Only panes whose pty is read by the server produce raw output.
+    Raw,
+    /// Logical lines of text, reported once the cursor has moved
+    /// past them
+    Text,
+    /// As for `Text`, but including only the cells that were marked
+    /// as command output by the shell integration semantic zones
+    SemanticOutput,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct SubscribePaneOutput {
+    pub pane_id: PaneId,
+    pub format: PaneOutputFormat,
+    /// For the text formats, the first row to report.
+    /// If omitted, reporting begins at the row holding the cursor.
+    pub start_row: Option<StableRowIndex>,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct SubscribePaneOutputResponse {
+    pub pane_id: PaneId,
+    /// The first row that will be reported by a text subscription
+    pub start_row: StableRowIndex,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct UnsubscribePaneOutput {
+    pub pane_id: PaneId,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
+pub enum PaneOutputData {
+    Raw(Vec<u8>),
+    Lines(Vec<String>),
+    /// The pane was removed; no further output will be delivered
+    Closed,
+}
+
+/// Sent unilaterally by the server to clients that have
+/// subscribed to the output of a pane
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct PaneOutput {
+    pub pane_id: PaneId,
+    pub data: PaneOutputData,
+}
+
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
 pub struct ActivatePaneDirection {
     pub pane_id: PaneId,
@@ -1250,6 +1519,70 @@ mod test {
         );
     }
 
+    fn round_trip(pdu: Pdu) -> Pdu {
+        let mut encoded = Vec::new();
+        pdu.encode(&mut encoded, 0x42).unwrap();
+        let decoded = Pdu::decode(encoded.as_slice()).unwrap();
+        assert_eq!(decoded.serial, 0x42);
+        decoded.pdu
+    }
+
+    #[test]
+    fn test_pdu_semantic_zones() {
+        let zone = SemanticZone {
+            start_y: 10,
+            start_x: 2,
+            end_y: 12,
+            end_x: 40,
+            semantic_type: SemanticType::Output,
+        };
+
+        let request = || {
+            Pdu::GetSemanticZoneText(GetSemanticZoneText {
+                pane_id: 3,
+                semantic_type: Some(SemanticType::Output),
+                index: -1,
+            })
+        };
+        assert_eq!(round_trip(request()), request());
+
+        let response = || {
+            Pdu::GetSemanticZoneTextResponse(GetSemanticZoneTextResponse {
+                pane_id: 3,
+                zone,
+                text: "hello\nworld".to_string(),
+            })
+        };
+        assert_eq!(round_trip(response()), response());
+
+        let zones = || {
+            Pdu::GetSemanticZonesResponse(GetSemanticZonesResponse {
+                pane_id: 3,
+                zones: vec![zone],
+            })
+        };
+        assert_eq!(round_trip(zones()), zones());
+    }
+
+    #[test]
+    fn test_pdu_command_blocks() {
+        let blocks = || {
+            Pdu::GetCommandBlocksResponse(GetCommandBlocksResponse {
+                pane_id: 3,
+                blocks: vec![CommandBlock {
+                    prompt_y: 5,
+                    output_start_y: Some(6),
+                    end_y: Some(9),
+                    exit_status: Some(1),
+                    cwd: Some("/tmp".to_string()),
+                    start_time_ms: Some(1000),
+                    end_time_ms: Some(1500),
+                }],
+            })
+        };
+        assert_eq!(round_trip(blocks()), blocks());
+    }
+
     #[test]
     fn test_bogus_pdu() {
         let mut encoded = Vec::new();
@@ -0,0 +1,64 @@
+use serde::{Deserialize, Serialize};
+use wezterm_dynamic::{FromDynamic, ToDynamic};
+
+/// Controls what a client that connects to the mux server
+/// is permitted to do
+#[derive(
+    Debug, Clone, Default, PartialEq, Eq, Hash, Deserialize, Serialize, FromDynamic, ToDynamic,
+)]
+pub enum ClientAccess {
+    /// The client can see and interact with everything
+    #[default]
+    Full,
+    /// The client can see everything, but cannot send input
+    /// to panes or otherwise change the state of the mux
+    ReadOnly,
+    /// The client can only see the windows in the listed
+    /// workspaces, and cannot change the state of the mux
+    ViewWorkspaces(Vec<String>),
+}
+
+impl ClientAccess {
+    /// Returns true if the client may send input to panes
+    /// and change the state of the mux
+    pub fn can_write(&self) -> bool {
+        matches!(self, Self::Full)
+    }
+
+    /// Returns true if the client may see the windows
+    /// in the specified workspace
+    pub fn can_view_workspace(&self, workspace: &str) -> bool {
+        match self {
+            Self::Full | Self::ReadOnly => true,
+            Self::ViewWorkspaces(workspaces) => workspaces.iter().any(|w| w == workspace),
+        }
+    }
+}
+
+impl std::fmt::Display for ClientAccess {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Full => write!(fmt, "full"),
+            Self::ReadOnly => write!(fmt, "read-only"),
+            Self::ViewWorkspaces(workspaces) => write!(fmt, "view:{}", workspaces.join(",")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn view_workspaces() {
+        let access = ClientAccess::ViewWorkspaces(vec!["demo".to_string(), "pair".to_string()]);
+        assert!(!access.can_write());
+        assert!(access.can_view_workspace("demo"));
+        assert!(!access.can_view_workspace("default"));
+        assert_eq!(access.to_string(), "view:demo,pair");
+
+        assert!(ClientAccess::Full.can_write());
+        assert!(!ClientAccess::ReadOnly.can_write());
+        assert!(ClientAccess::ReadOnly.can_view_workspace("default"));
+    }
+}
@@ -160,6 +160,14 @@ pub struct Palette {
     pub visual_bell: Option<RgbaColor>,
     /// The color to use for the cursor when a dead key or leader state is active
     pub compose_cursor: Option<RgbaColor>,
+    /// The colors of the command gutter markers.  If unspecified, the
+    /// ANSI green, red and yellow colors are used respectively.
+    pub command_gutter_success: Option<RgbaColor>,
+    pub command_gutter_failure: Option<RgbaColor>,
+    pub command_gutter_running: Option<RgbaColor>,
+    /// The color of the frame drawn around panes that receive
+    /// broadcast input.  If unspecified, the ANSI red color is used.
+    pub broadcast_input: Option<RgbaColor>,
 
     pub copy_mode_active_highlight_fg: Option<ColorSpec>,
     pub copy_mode_active_highlight_bg: Option<ColorSpec>,
@@ -217,6 +225,10 @@ impl Palette {
             split: overlay!(split),
             visual_bell: overlay!(visual_bell),
             compose_cursor: overlay!(compose_cursor),
+            command_gutter_success: overlay!(command_gutter_success),
+            command_gutter_failure: overlay!(command_gutter_failure),
+            command_gutter_running: overlay!(command_gutter_running),
+            broadcast_input: overlay!(broadcast_input),
             copy_mode_active_highlight_fg: overlay!(copy_mode_active_highlight_fg),
             copy_mode_active_highlight_bg: overlay!(copy_mode_active_highlight_bg),
             copy_mode_inactive_highlight_fg: overlay!(copy_mode_inactive_highlight_fg),
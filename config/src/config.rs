@@ -14,6 +14,7 @@ use crate::keyassignment::{
     KeyAssignment, KeyTable, KeyTableEntry, KeyTables, MouseEventTrigger, SpawnCommand,
 };
 use crate::keys::{Key, LeaderKey, Mouse};
+use crate::layout::WorkspaceLayout;
 use crate::lua::make_lua_context;
 use crate::ssh::{SshBackend, SshDomain};
 use crate::tls::{TlsDomainClient, TlsDomainServer};
@@ -203,6 +204,16 @@ pub struct Config {
     )]
     pub scrollback_lines: usize,
 
+    /// How many lines to retain in compressed form once they have
+    /// scrolled beyond `scrollback_lines`.  0 disables this.
+    #[dynamic(default, validate = "validate_scrollback_lines")]
+    pub cold_scrollback_lines: usize,
+
+    /// When true, the compressed cold scrollback is written to
+    /// a file in the cache directory rather than held in memory
+    #[dynamic(default)]
+    pub scrollback_spill_to_disk: bool,
+
     /// If no `prog` is specified on the command line, use this
     /// instead of running the user's shell.
     /// For example, to have `wezterm` always run `top` by default,
@@ -368,6 +379,24 @@ pub struct Config {
     #[dynamic(default)]
     pub ssh_backend: SshBackend,
 
+    /// Whether ssh connections to the same host are shared between
+    /// ssh domains, `wezterm ssh` windows and the multiplexer client
+    #[dynamic(default)]
+    pub ssh_session_sharing: bool,
+
+    /// How long a shared ssh connection that has no open channels
+    /// is kept around in case it is needed again
+    #[dynamic(default = "default_ssh_session_idle_timeout")]
+    pub ssh_session_idle_timeout: Duration,
+
+    /// How often shared ssh connections are checked to verify
+    /// that they are still usable
+    #[dynamic(
+        default = "default_ssh_session_liveness_interval",
+        validate = "validate_ssh_session_liveness_interval"
+    )]
+    pub ssh_session_liveness_interval: Duration,
+
     /// When running in server mode, defines configuration for
     /// each of the endpoints that we'll listen for connections
     #[dynamic(default)]
@@ -512,6 +541,15 @@ pub struct Config {
     #[dynamic(try_from = "crate::units::PixelUnit", default = "default_half_cell")]
     pub min_scroll_bar_height: Dimension,
 
+    /// If true, show a marker in the left padding beside each shell
+    /// prompt, colored according to the exit status of the command that
+    /// was run there.  Requires OSC 133 shell integration.
+    #[dynamic(default)]
+    pub enable_command_gutter: bool,
+
+    #[dynamic(try_from = "crate::units::PixelUnit", default = "default_half_cell")]
+    pub command_gutter_width: Dimension,
+
     /// If false, do not try to use a Wayland protocol connection
     /// when starting the gui frontend, and instead use X11.
     /// This option is only considered on X11/Wayland systems and
@@ -737,6 +775,13 @@ pub struct Config {
     #[dynamic(default = "default_update_interval")]
     pub check_for_updates_interval_seconds: u64,
 
+    /// When true, the `wezterm.http` lua module is permitted to make
+    /// network requests and connect to local sockets.
+    /// This is off by default so that a config or plugin can't talk
+    /// to the network without the user opting in.
+    #[dynamic(default)]
+    pub enable_http_client: bool,
+
     /// When set to true, use the CSI-U encoding scheme as described
     /// in http://www.leonerd.org.uk/hacks/fixterms/
     /// This is off by default because @wez and @jsgf find the shift-space
@@ -863,6 +908,33 @@ pub struct Config {
     #[dynamic(default)]
     pub default_workspace: Option<String>,
 
+    /// Named layouts of tabs and panes that are used to
+    /// populate new workspaces
+    #[dynamic(default)]
+    pub workspace_layouts: HashMap<String, WorkspaceLayout>,
+
+    /// When enabled, the windows, tabs and panes that were saved to the
+    /// default session are restored when the gui starts, and the
+    /// default session is periodically saved while it is running
+    #[dynamic(default)]
+    pub restore_session_on_startup: bool,
+
+    /// How often the default session is saved when
+    /// `restore_session_on_startup` is enabled
+    #[dynamic(default = "default_session_autosave_interval")]
+    pub session_autosave_interval: Duration,
+
+    /// Whether the scrollback text of each pane is saved
+    /// along with the layout of a session
+    #[dynamic(default)]
+    pub session_save_scrollback: bool,
+
+    /// The programs that are re-run in the restored panes of a
+    /// session if they were running in the foreground when it
+    /// was saved
+    #[dynamic(default = "default_session_restore_processes")]
+    pub session_restore_processes: Vec<String>,
+
     #[dynamic(default)]
     pub xcursor_theme: Option<String>,
 
@@ -875,6 +947,9 @@ pub struct Config {
     #[dynamic(default)]
     pub quote_dropped_files: DroppedFileQuoting,
 
+    #[dynamic(default = "default_true")]
+    pub upload_dropped_files_over_ssh: bool,
+
     #[dynamic(default)]
     pub ui_key_cap_rendering: UIKeyCapRendering,
 
@@ -1004,7 +1079,9 @@ impl Config {
         Ok(())
     }
 
-    pub fn load_with_overrides(overrides: &wezterm_dynamic::Value) -> LoadedConfig {
+    /// Returns the list of locations that are considered when loading
+    /// the configuration, in order of preference
+    fn config_file_candidates() -> Vec<PathPossibility> {
         // Note that the directories crate has methods for locating project
         // specific config directories, but only returns one of them, not
         // multiple.  In addition, it spawns a lot of subprocesses,
@@ -1040,6 +1117,25 @@ impl Config {
             paths.insert(0, PathPossibility::required(path.clone()));
         }
 
+        paths
+    }
+
+    /// Returns the path of the configuration file that would be loaded,
+    /// without loading it.  Returns None if the configuration would be
+    /// skipped or no configuration file exists.
+    pub fn locate_config_file() -> Option<PathBuf> {
+        if CONFIG_SKIP.load(Ordering::Relaxed) {
+            return None;
+        }
+        Self::config_file_candidates()
+            .into_iter()
+            .find(|item| item.is_required || item.path.exists())
+            .map(|item| item.path)
+    }
+
+    pub fn load_with_overrides(overrides: &wezterm_dynamic::Value) -> LoadedConfig {
+        let paths = Self::config_file_candidates();
+
         for path_item in &paths {
             if CONFIG_SKIP.load(Ordering::Relaxed) {
                 break;
@@ -1843,6 +1939,39 @@ fn default_stateless_process_list() -> Vec<String> {
     .collect()
 }
 
+fn default_ssh_session_idle_timeout() -> Duration {
+    Duration::from_secs(300)
+}
+
+fn default_ssh_session_liveness_interval() -> Duration {
+    Duration::from_secs(30)
+}
+
+fn validate_ssh_session_liveness_interval(value: &Duration) -> Result<(), String> {
+    if *value < Duration::from_secs(1) {
+        Err(format!(
+            "Illegal value {value:?} for ssh_session_liveness_interval; \
+             it must be at least 1 second"
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+fn default_session_autosave_interval() -> Duration {
+    Duration::from_secs(60)
+}
+
+fn default_session_restore_processes() -> Vec<String> {
+    [
+        "vi", "vim", "nvim", "emacs", "nano", "hx", "less", "more", "man", "top", "htop", "btop",
+        "tail", "watch",
+    ]
+    .iter()
+    .map(|s| s.to_string())
+    .collect()
+}
+
 fn default_status_update_interval() -> u64 {
     1_000
 }
@@ -379,9 +379,27 @@ pub struct FontAttributes {
     pub scale: Option<NotNan<f64>>,
     #[dynamic(default)]
     pub assume_emoji_presentation: Option<bool>,
+    /// OpenType variation axis values, keyed by axis tag,
+    /// such as `wght` or `CASL`
+    #[dynamic(default, validate = "validate_font_variations")]
+    pub variations: Option<BTreeMap<String, NotNan<f64>>>,
 }
 impl_lua_conversion_dynamic!(FontAttributes);
 
+pub(crate) fn validate_font_variations(
+    variations: &Option<BTreeMap<String, NotNan<f64>>>,
+) -> Result<(), String> {
+    for tag in variations.iter().flat_map(|v| v.keys()) {
+        if tag.is_empty() || tag.len() > 4 || !tag.bytes().all(|b| b.is_ascii_graphic()) {
+            return Err(format!(
+                "invalid variation axis tag `{tag}`; \
+                 axis tags are up to 4 ASCII characters, such as `wght`"
+            ));
+        }
+    }
+    Ok(())
+}
+
 impl std::fmt::Display for FontAttributes {
     fn fmt(&self, fmt: &mut std::fmt::Formatter) -> Result<(), std::fmt::Error> {
         write!(
@@ -407,6 +425,7 @@ impl FontAttributes {
             freetype_load_flags: None,
             scale: None,
             assume_emoji_presentation: None,
+            variations: None,
         }
     }
 
@@ -424,6 +443,7 @@ impl FontAttributes {
             freetype_load_flags: None,
             scale: None,
             assume_emoji_presentation: None,
+            variations: None,
         }
     }
 }
@@ -443,6 +463,7 @@ impl Default for FontAttributes {
             freetype_load_flags: None,
             scale: None,
             assume_emoji_presentation: None,
+            variations: None,
         }
     }
 }
@@ -720,4 +741,21 @@ mod test {
             assert_eq!(style.font[0].family, "Inconsolata");
         }
     }
+
+    #[test]
+    fn test_validate_variations() {
+        fn vars(tags: &[&str]) -> Option<BTreeMap<String, NotNan<f64>>> {
+            Some(
+                tags.iter()
+                    .map(|tag| (tag.to_string(), NotNan::new(1.0).unwrap()))
+                    .collect(),
+            )
+        }
+        assert!(validate_font_variations(&None).is_ok());
+        assert!(validate_font_variations(&vars(&["wght", "CASL", "opsz"])).is_ok());
+        assert!(validate_font_variations(&vars(&["ab"])).is_ok());
+        assert!(validate_font_variations(&vars(&["weight"])).is_err());
+        assert!(validate_font_variations(&vars(&[""])).is_err());
+        assert!(validate_font_variations(&vars(&["w t"])).is_err());
+    }
 }
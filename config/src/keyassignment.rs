@@ -1,6 +1,7 @@
 use crate::default_true;
 use crate::keys::KeyNoAction;
 use crate::window::WindowLevel;
+use crate::SerialLineSettings;
 use luahelper::impl_lua_conversion_dynamic;
 use ordered_float::NotNan;
 use portable_pty::CommandBuilder;
@@ -460,6 +461,34 @@ pub struct QuickSelectArguments {
     pub scope_lines: Option<usize>,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, FromDynamic, ToDynamic)]
+pub struct SearchAllPanesArguments {
+    /// The initial search pattern.  Its type determines the initial
+    /// match type, which can be changed from within the overlay.
+    #[dynamic(default)]
+    pub pattern: Pattern,
+    /// Start out using fuzzy matching rather than the pattern's match type
+    #[dynamic(default)]
+    pub fuzzy: bool,
+    /// How many lines before and after each match to show as context
+    #[dynamic(default = "default_search_context_lines")]
+    pub context_lines: usize,
+}
+
+fn default_search_context_lines() -> usize {
+    1
+}
+
+impl Default for SearchAllPanesArguments {
+    fn default() -> Self {
+        Self {
+            pattern: Pattern::default(),
+            fuzzy: false,
+            context_lines: default_search_context_lines(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, FromDynamic, ToDynamic)]
 pub struct PromptInputLine {
     pub action: Box<KeyAssignment>,
@@ -563,6 +592,8 @@ pub enum KeyAssignment {
         confirm: bool,
     },
     ReloadConfiguration,
+    SaveSession,
+    RestoreSession,
     MoveTabRelative(isize),
     MoveTab(usize),
     ScrollByPage(NotNan<f64>),
@@ -610,6 +641,10 @@ pub enum KeyAssignment {
     SwitchToWorkspace {
         name: Option<String>,
         spawn: Option<SpawnCommand>,
+        /// The name of an entry in `workspace_layouts` that is
+        /// used to populate the workspace if it doesn't exist
+        #[dynamic(default)]
+        layout: Option<String>,
     },
     SwitchWorkspaceRelative(isize),
 
@@ -646,9 +681,124 @@ pub enum KeyAssignment {
     PromptInputLine(PromptInputLine),
     InputSelector(InputSelector),
     Confirmation(Confirmation),
+    SearchAllPanes(SearchAllPanesArguments),
+    ToggleBroadcastInput(BroadcastInputScope),
+    TogglePaneBroadcastMark,
+    SerialPort(SerialPortAction),
+    TogglePaneRecording(PaneRecordingArgs),
+    PlayAsciicast(PlayAsciicast),
+    ExportPane(ExportPaneArgs),
+    ShowGlyphInspector,
+    IncreasePaneFontSize,
+    DecreasePaneFontSize,
+    ResetPaneFontSize,
 }
 impl_lua_conversion_dynamic!(KeyAssignment);
 
+/// Which panes receive a copy of the input that is sent
+/// to the active pane while broadcasting is enabled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromDynamic, ToDynamic)]
+pub enum BroadcastInputScope {
+    /// The panes in the active tab
+    CurrentTab,
+    /// The panes in all tabs of all windows in the active workspace
+    CurrentWorkspace,
+    /// The panes that were marked via TogglePaneBroadcastMark
+    MarkedPanes,
+}
+
+/// Runtime controls for a pane that is connected to a serial port
+#[derive(Debug, Clone, PartialEq, Eq, FromDynamic, ToDynamic)]
+pub enum SerialPortAction {
+    /// Changes the line settings of the port
+    Configure(SerialLineSettings),
+    ToggleDtr,
+    ToggleRts,
+    /// Holds the line in the break condition for the
+    /// specified number of milliseconds
+    SendBreak(u64),
+    /// Starts or stops logging the received data to a file
+    ToggleLogging,
+    /// Shows or hides a live hex dump of the received data
+    ToggleHexView,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, FromDynamic, ToDynamic)]
+pub struct PaneRecordingArgs {
+    /// Also record the input that is sent to the pane
+    #[dynamic(default)]
+    pub capture_input: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, FromDynamic, ToDynamic)]
+pub struct PlayAsciicast {
+    /// The asciicast file to play
+    pub path: String,
+    /// The initial playback speed
+    #[dynamic(default = "default_play_speed")]
+    pub speed: f32,
+    /// Shorten periods of inactivity to at most this many seconds.
+    /// If not specified, the limit stored in the recording is used.
+    #[dynamic(default)]
+    pub idle_time_limit: Option<f32>,
+    /// Open the player paused on the first frame
+    #[dynamic(default)]
+    pub start_paused: bool,
+}
+
+fn default_play_speed() -> f32 {
+    1.0
+}
+
+/// The formats that the content of a pane can be exported to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromDynamic, ToDynamic)]
+pub enum ExportFormat {
+    /// Plain, unattributed text
+    Text,
+    /// Text with escape sequences for the colors and styles
+    Ansi,
+    Html,
+    Svg,
+}
+impl_lua_conversion_dynamic!(ExportFormat);
+
+impl Default for ExportFormat {
+    fn default() -> Self {
+        Self::Html
+    }
+}
+
+/// Which portion of a pane is exported by ExportPane
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromDynamic, ToDynamic)]
+pub enum ExportContent {
+    /// The selected text, or the viewport if there is no selection
+    Selection,
+    Viewport,
+    /// The viewport and all of the scrollback
+    Scrollback,
+}
+
+impl Default for ExportContent {
+    fn default() -> Self {
+        Self::Selection
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, FromDynamic, ToDynamic)]
+pub struct ExportPaneArgs {
+    #[dynamic(default)]
+    pub format: ExportFormat,
+    #[dynamic(default)]
+    pub content: ExportContent,
+    /// Write the export to this file.  If not specified,
+    /// it is copied to the clipboard
+    #[dynamic(default)]
+    pub path: Option<String>,
+    /// Where to copy the export when no path is specified
+    #[dynamic(default)]
+    pub destination: ClipboardCopyDestination,
+}
+
 #[derive(Debug, Clone, PartialEq, FromDynamic, ToDynamic)]
 pub struct SplitPane {
     pub direction: PaneDirection,
@@ -720,6 +870,7 @@ pub enum CopyModeAssignment {
     JumpBackward { prev_char: bool },
     JumpAgain,
     JumpReverse,
+    DownloadSelectedPath,
 }
 
 pub type KeyTable = HashMap<(KeyCode, Modifiers), KeyTableEntry>;
@@ -0,0 +1,166 @@
+use crate::keyassignment::{PaneDirection, SpawnCommand, SpawnTabDomain, SplitSize};
+use luahelper::impl_lua_conversion_dynamic;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use wezterm_dynamic::{FromDynamic, ToDynamic};
+
+/// Describes the tabs and panes that are created when
+/// a new workspace is built from a layout
+#[derive(Debug, Clone, PartialEq, FromDynamic, ToDynamic)]
+pub struct WorkspaceLayout {
+    /// Describes the layout in the launcher and command palette
+    #[dynamic(default)]
+    pub label: Option<String>,
+    /// The working directory of panes that don't specify their own
+    #[dynamic(default)]
+    pub cwd: Option<PathBuf>,
+    /// The domain of panes that don't specify their own
+    #[dynamic(default)]
+    pub domain: Option<SpawnTabDomain>,
+    pub tabs: Vec<TabLayout>,
+}
+impl_lua_conversion_dynamic!(WorkspaceLayout);
+
+#[derive(Debug, Clone, PartialEq, FromDynamic, ToDynamic)]
+pub struct TabLayout {
+    #[dynamic(default)]
+    pub title: Option<String>,
+    /// The first pane of the tab, which is split
+    /// to produce the rest of its panes
+    #[dynamic(flatten)]
+    pub pane: PaneLayout,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, FromDynamic, ToDynamic)]
+pub struct PaneLayout {
+    /// The command line to run; the default program
+    /// of the domain is used if omitted
+    #[dynamic(default)]
+    pub args: Option<Vec<String>>,
+    #[dynamic(default)]
+    pub cwd: Option<PathBuf>,
+    #[dynamic(default)]
+    pub set_environment_variables: HashMap<String, String>,
+    #[dynamic(default)]
+    pub domain: Option<SpawnTabDomain>,
+    /// Whether this pane is focused once the layout is built.
+    /// Its tab is also made the active tab.
+    #[dynamic(default)]
+    pub active: bool,
+    /// The panes that are split off from this one, in order
+    #[dynamic(default)]
+    pub splits: Vec<SplitLayout>,
+}
+
+#[derive(Debug, Clone, PartialEq, FromDynamic, ToDynamic)]
+pub struct SplitLayout {
+    /// Where the new pane is placed relative to the pane
+    /// that is split; one of `Left`, `Right`, `Up` or `Down`
+    #[dynamic(validate = "validate_split_direction")]
+    pub direction: PaneDirection,
+    /// The size of the new pane
+    #[dynamic(default)]
+    pub size: SplitSize,
+    #[dynamic(flatten)]
+    pub pane: PaneLayout,
+}
+
+fn validate_split_direction(direction: &PaneDirection) -> Result<(), String> {
+    match direction {
+        PaneDirection::Up | PaneDirection::Down | PaneDirection::Left | PaneDirection::Right => {
+            Ok(())
+        }
+        PaneDirection::Next | PaneDirection::Prev => Err(format!(
+            "{direction:?} is not a valid split direction; \
+             use one of Left, Right, Up or Down"
+        )),
+    }
+}
+
+impl WorkspaceLayout {
+    /// Returns the text used to describe the layout
+    /// named `name` in the launcher and command palette
+    pub fn label(&self, name: &str) -> String {
+        self.label.clone().unwrap_or_else(|| name.to_string())
+    }
+
+    /// Returns the index of the tab that holds the active pane
+    pub fn active_tab(&self) -> usize {
+        self.tabs
+            .iter()
+            .position(|tab| tab.pane.contains_active())
+            .unwrap_or(0)
+    }
+}
+
+impl PaneLayout {
+    fn contains_active(&self) -> bool {
+        self.active || self.splits.iter().any(|split| split.pane.contains_active())
+    }
+
+    /// Produces the command that spawns this pane, filling in
+    /// the defaults specified by the workspace layout
+    pub fn spawn_command(&self, layout: &WorkspaceLayout) -> SpawnCommand {
+        SpawnCommand {
+            label: None,
+            args: self.args.clone(),
+            cwd: self.cwd.clone().or_else(|| layout.cwd.clone()),
+            set_environment_variables: self.set_environment_variables.clone(),
+            domain: self
+                .domain
+                .clone()
+                .or_else(|| layout.domain.clone())
+                .unwrap_or(SpawnTabDomain::DefaultDomain),
+            position: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn tab(pane: PaneLayout) -> TabLayout {
+        TabLayout { title: None, pane }
+    }
+
+    #[test]
+    fn active_tab_and_defaults() {
+        let nested = PaneLayout {
+            cwd: Some("/tmp".into()),
+            active: true,
+            ..Default::default()
+        };
+        let layout = WorkspaceLayout {
+            label: None,
+            cwd: Some("/src".into()),
+            domain: Some(SpawnTabDomain::DomainName("unix".to_string())),
+            tabs: vec![
+                tab(PaneLayout::default()),
+                tab(PaneLayout {
+                    splits: vec![SplitLayout {
+                        direction: PaneDirection::Right,
+                        size: SplitSize::default(),
+                        pane: nested.clone(),
+                    }],
+                    ..Default::default()
+                }),
+            ],
+        };
+        assert_eq!(layout.active_tab(), 1);
+        assert_eq!(layout.label("dev"), "dev");
+
+        let spawn = layout.tabs[0].pane.spawn_command(&layout);
+        assert_eq!(spawn.cwd, Some("/src".into()));
+        assert_eq!(spawn.domain, SpawnTabDomain::DomainName("unix".to_string()));
+
+        let spawn = nested.spawn_command(&layout);
+        assert_eq!(spawn.cwd, Some("/tmp".into()));
+    }
+
+    #[test]
+    fn split_direction() {
+        assert!(validate_split_direction(&PaneDirection::Up).is_ok());
+        assert!(validate_split_direction(&PaneDirection::Next).is_err());
+    }
+}
@@ -16,10 +16,10 @@ use std::path::{Path, PathBuf};
 use std::rc::Rc;
 use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
 use wezterm_dynamic::{FromDynamic, FromDynamicOptions, ToDynamic, UnknownFieldAction, Value};
 use wezterm_term::UnicodeVersion;
 
+mod access;
 mod background;
 mod bell;
 mod cell;
@@ -32,6 +32,7 @@ mod font;
 mod frontend;
 pub mod keyassignment;
 mod keys;
+mod layout;
 pub mod lua;
 pub mod meta;
 mod scheme_data;
@@ -42,10 +43,12 @@ mod tls;
 mod units;
 mod unix;
 mod version;
+mod watcher;
 pub mod window;
 mod wsl;
 
 pub use crate::config::*;
+pub use access::*;
 pub use background::*;
 pub use bell::*;
 pub use cell::*;
@@ -56,6 +59,7 @@ pub use exec_domain::*;
 pub use font::*;
 pub use frontend::*;
 pub use keys::*;
+pub use layout::*;
 pub use serial::*;
 pub use ssh::*;
 pub use terminal::*;
@@ -63,6 +67,7 @@ pub use tls::*;
 pub use units::*;
 pub use unix::*;
 pub use version::*;
+pub use watcher::*;
 pub use wsl::*;
 
 type ErrorCallback = fn(&str);
@@ -435,6 +440,29 @@ pub fn use_test_configuration() {
     CONFIG.use_test();
 }
 
+/// Returns the configuration that `use_test_configuration` would
+/// use, without making it the current configuration.  It only uses
+/// the fonts that are bundled in the repo, at a fixed DPI, so that
+/// tests produce consistent results on every system.
+pub fn test_configuration() -> Config {
+    let mut config = Config::default_config();
+    config.font_locator = FontLocatorSelection::ConfigDirsOnly;
+    let exe_name = std::env::current_exe().unwrap();
+    let exe_dir = exe_name.parent().unwrap();
+    config.font_dirs.push(exe_dir.join("../../../assets/fonts"));
+    // If we're building for a specific target, the dir
+    // level is one deeper.
+    #[cfg(target_os = "macos")]
+    config
+        .font_dirs
+        .push(exe_dir.join("../../../../assets/fonts"));
+    // Specify the same DPI used on non-mac systems so
+    // that we have consistent values regardless of the
+    // operating system that we're running tests on
+    config.dpi.replace(96.0);
+    config
+}
+
 pub fn use_this_configuration(config: Config) {
     CONFIG.use_this_config(config);
 }
@@ -474,7 +502,7 @@ struct ConfigInner {
     error: Option<String>,
     warnings: Vec<String>,
     generation: usize,
-    watcher: Option<notify::RecommendedWatcher>,
+    watcher: Option<PathWatcher>,
     subscribers: HashMap<usize, Box<dyn Fn() -> bool + Send>>,
 }
 
@@ -510,55 +538,20 @@ impl ConfigInner {
 
     fn watch_path(&mut self, path: PathBuf) {
         if self.watcher.is_none() {
-            let (tx, rx) = std::sync::mpsc::channel();
-            const DELAY: Duration = Duration::from_millis(200);
-            let watcher = notify::recommended_watcher(tx).unwrap();
-            let path = path.clone();
-
-            std::thread::spawn(move || {
-                // block until we get an event
-                use notify::EventKind;
-
-                fn extract_path(event: notify::Event) -> Vec<PathBuf> {
-                    match event.kind {
-                        EventKind::Modify(_) | EventKind::Create(_) | EventKind::Remove(_) => {
-                            event.paths
-                        }
-                        _ => vec![],
-                    }
+            match PathWatcher::new(|paths| {
+                log::debug!("paths {:?} changed, reload config", paths);
+                reload();
+            }) {
+                Ok(watcher) => {
+                    self.watcher.replace(watcher);
                 }
-
-                while let Ok(event) = rx.recv() {
-                    log::debug!("event:{:?}", event);
-                    match event {
-                        Ok(event) => {
-                            let mut paths = extract_path(event);
-                            if !paths.is_empty() {
-                                // Grace period to allow events to settle
-                                std::thread::sleep(DELAY);
-                                // Drain any other immediately ready events
-                                while let Ok(Ok(event)) = rx.try_recv() {
-                                    paths.append(&mut extract_path(event));
-                                }
-                                paths.sort();
-                                paths.dedup();
-                                log::debug!("paths {:?} changed, reload config", path);
-                                reload();
-                            }
-                        }
-                        Err(_) => {
-                            reload();
-                        }
-                    }
+                Err(err) => {
+                    log::error!("Unable to watch config files for changes: {:#}", err);
                 }
-            });
-            self.watcher.replace(watcher);
+            }
         }
         if let Some(watcher) = self.watcher.as_mut() {
-            use notify::Watcher;
-            watcher
-                .watch(&path, notify::RecursiveMode::NonRecursive)
-                .ok();
+            watcher.watch(&path, false).ok();
         }
     }
 
@@ -666,22 +659,7 @@ impl ConfigInner {
     }
 
     fn use_test(&mut self) {
-        let mut config = Config::default_config();
-        config.font_locator = FontLocatorSelection::ConfigDirsOnly;
-        let exe_name = std::env::current_exe().unwrap();
-        let exe_dir = exe_name.parent().unwrap();
-        config.font_dirs.push(exe_dir.join("../../../assets/fonts"));
-        // If we're building for a specific target, the dir
-        // level is one deeper.
-        #[cfg(target_os = "macos")]
-        config
-            .font_dirs
-            .push(exe_dir.join("../../../../assets/fonts"));
-        // Specify the same DPI used on non-mac systems so
-        // that we have consistent values regardless of the
-        // operating system that we're running tests on
-        config.dpi.replace(96.0);
-        self.config = Arc::new(config);
+        self.config = Arc::new(test_configuration());
         self.error.take();
         self.generation += 1;
     }
@@ -809,6 +787,17 @@ impl ConfigHandle {
     }
 }
 
+/// Wraps a configuration that isn't the current configuration,
+/// such as one that is constructed by a test
+impl From<Config> for ConfigHandle {
+    fn from(config: Config) -> Self {
+        Self {
+            config: Arc::new(config),
+            generation: 0,
+        }
+    }
+}
+
 impl std::ops::Deref for ConfigHandle {
     type Target = Config;
     fn deref(&self) -> &Config {
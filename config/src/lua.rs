@@ -9,8 +9,10 @@ use luahelper::{from_lua_value_dynamic, lua_value_to_dynamic, to_lua};
 use mlua::{FromLua, IntoLuaMulti, Lua, Table, Value, Variadic};
 use ordered_float::NotNan;
 use portable_pty::CommandBuilder;
+use std::collections::BTreeMap;
 use std::convert::TryFrom;
 use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Mutex;
 use wezterm_dynamic::{
     FromDynamic, FromDynamicOptions, ToDynamic, UnknownFieldAction, Value as DynValue,
@@ -19,6 +21,8 @@ use wezterm_dynamic::{
 pub use mlua;
 
 static LUA_REGISTRY_USER_CALLBACK_COUNT: &str = "wezterm-user-callback-count";
+static LUA_REGISTRY_CONTEXT_ID: &str = "wezterm-lua-context-id";
+static NEXT_LUA_CONTEXT_ID: AtomicUsize = AtomicUsize::new(1);
 
 pub type SetupFunc = fn(&Lua) -> anyhow::Result<()>;
 
@@ -351,6 +355,10 @@ end
         )?;
 
         lua.set_named_registry_value(LUA_REGISTRY_USER_CALLBACK_COUNT, 0)?;
+        lua.set_named_registry_value(
+            LUA_REGISTRY_CONTEXT_ID,
+            NEXT_LUA_CONTEXT_ID.fetch_add(1, Ordering::Relaxed),
+        )?;
         wezterm_mod.set("action_callback", lua.create_function(action_callback)?)?;
         wezterm_mod.set("exec_domain", lua.create_function(exec_domain)?)?;
 
@@ -498,6 +506,8 @@ struct LuaFontAttributes {
     pub scale: Option<NotNan<f64>>,
     #[dynamic(default)]
     pub assume_emoji_presentation: Option<bool>,
+    #[dynamic(default, validate = "crate::font::validate_font_variations")]
+    pub variations: Option<BTreeMap<String, NotNan<f64>>>,
 }
 impl<'lua> FromLua<'lua> for LuaFontAttributes {
     fn from_lua(value: Value<'lua>, _lua: &'lua Lua) -> Result<Self, mlua::Error> {
@@ -585,6 +595,7 @@ fn font<'lua>(
             },
             scale: attrs.scale,
             assume_emoji_presentation: attrs.assume_emoji_presentation,
+            variations: attrs.variations,
         }));
 
     Ok(text_style)
@@ -634,12 +645,21 @@ fn font_with_fallback<'lua>(
                 },
                 scale: attrs.scale,
                 assume_emoji_presentation: attrs.assume_emoji_presentation,
+                variations: attrs.variations,
             }));
     }
 
     Ok(text_style)
 }
 
+/// Returns an identifier for the lua context that is unique within
+/// this process; contexts that are created later have larger ids.
+/// The callbacks registered by `wrap_callback` can only be called
+/// via the context that registered them, and this allows finding it.
+pub fn lua_context_id(lua: &Lua) -> Option<usize> {
+    lua.named_registry_value(LUA_REGISTRY_CONTEXT_ID).ok()
+}
+
 pub fn wrap_callback<'lua>(lua: &'lua Lua, callback: mlua::Function) -> mlua::Result<String> {
     let callback_count: i32 = lua.named_registry_value(LUA_REGISTRY_USER_CALLBACK_COUNT)?;
     let user_event_id = format!("user-defined-{}", callback_count);
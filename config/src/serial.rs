@@ -1,4 +1,7 @@
 use crate::config::validate_domain_name;
+use anyhow::bail;
+use std::path::PathBuf;
+use std::str::FromStr;
 use wezterm_dynamic::{FromDynamic, ToDynamic};
 
 #[derive(Default, Debug, Clone, FromDynamic, ToDynamic)]
@@ -16,4 +19,94 @@ pub struct SerialDomain {
 
     /// Set the baud rate.  The default is 9600 baud.
     pub baud: Option<usize>,
+
+    /// The number of data bits per character, from 5 to 8.
+    /// The default is 8.
+    pub data_bits: Option<u8>,
+
+    /// The default is no parity.
+    pub parity: Option<SerialParity>,
+
+    /// The default is one stop bit.
+    pub stop_bits: Option<SerialStopBits>,
+
+    /// The default is XON/XOFF software flow control.
+    pub flow_control: Option<SerialFlowControl>,
+
+    /// If set, the data received from the port is appended to this
+    /// file, with each line prefixed by the time at which it arrived.
+    pub log_file: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromDynamic, ToDynamic)]
+pub enum SerialParity {
+    None,
+    Odd,
+    Even,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromDynamic, ToDynamic)]
+pub enum SerialStopBits {
+    One,
+    Two,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromDynamic, ToDynamic)]
+pub enum SerialFlowControl {
+    None,
+    XonXoff,
+    RtsCts,
+}
+
+impl FromStr for SerialParity {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "none" | "n" => Ok(Self::None),
+            "odd" | "o" => Ok(Self::Odd),
+            "even" | "e" => Ok(Self::Even),
+            _ => bail!("invalid parity `{s}`; expected none, odd or even"),
+        }
+    }
+}
+
+impl FromStr for SerialStopBits {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "1" => Ok(Self::One),
+            "2" => Ok(Self::Two),
+            _ => bail!("invalid stop bits `{s}`; expected 1 or 2"),
+        }
+    }
+}
+
+impl FromStr for SerialFlowControl {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "none" => Ok(Self::None),
+            "xonxoff" | "xon/xoff" | "software" => Ok(Self::XonXoff),
+            "rtscts" | "rts/cts" | "hardware" => Ok(Self::RtsCts),
+            _ => bail!("invalid flow control `{s}`; expected none, xonxoff or rtscts"),
+        }
+    }
+}
+
+/// Line settings that can be applied to an open serial port.
+/// Settings that are not specified are left unchanged.
+#[derive(Default, Debug, Clone, PartialEq, Eq, FromDynamic, ToDynamic)]
+pub struct SerialLineSettings {
+    pub baud: Option<u32>,
+    pub data_bits: Option<u8>,
+    pub parity: Option<SerialParity>,
+    pub stop_bits: Option<SerialStopBits>,
+    pub flow_control: Option<SerialFlowControl>,
+    /// Asserts (true) or clears (false) the DTR line
+    pub dtr: Option<bool>,
+    /// Asserts (true) or clears (false) the RTS line
+    pub rts: Option<bool>,
 }
@@ -104,6 +104,21 @@ pub struct SshDomain {
 
     #[dynamic(default)]
     pub assume_shell: Shell,
+
+    /// Local port forwards to establish once connected, in the
+    /// `[bind_address:]port:host:hostport` form used by `ssh -L`
+    #[dynamic(default)]
+    pub local_forwards: Vec<String>,
+
+    /// Remote port forwards to establish once connected, in the
+    /// `[bind_address:]port:host:hostport` form used by `ssh -R`
+    #[dynamic(default)]
+    pub remote_forwards: Vec<String>,
+
+    /// Local SOCKS5 proxies to establish once connected, in the
+    /// `[bind_address:]port` form used by `ssh -D`
+    #[dynamic(default)]
+    pub dynamic_forwards: Vec<String>,
 }
 impl_lua_conversion_dynamic!(SshDomain);
 
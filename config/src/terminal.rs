@@ -1,6 +1,7 @@
 //! Bridge our gui config into the terminal crate configuration
 
-use crate::{configuration, ConfigHandle, NewlineCanon};
+use crate::{configuration, ConfigHandle, NewlineCanon, CACHE_DIR};
+use std::path::PathBuf;
 use std::sync::Mutex;
 use termwiz::cell::UnicodeVersion;
 use wezterm_term::color::ColorPalette;
@@ -52,6 +53,18 @@ impl wezterm_term::TerminalConfiguration for TermConfig {
         self.configuration().scrollback_lines
     }
 
+    fn cold_scrollback_size(&self) -> usize {
+        self.configuration().cold_scrollback_lines
+    }
+
+    fn scrollback_spill_directory(&self) -> Option<PathBuf> {
+        if self.configuration().scrollback_spill_to_disk {
+            Some(CACHE_DIR.join("scrollback"))
+        } else {
+            None
+        }
+    }
+
     fn enable_csi_u_key_encoding(&self) -> bool {
         self.configuration().enable_csi_u_key_encoding
     }
@@ -24,6 +24,16 @@ pub struct TlsDomainServer {
     /// to the trust store.
     #[dynamic(default)]
     pub pem_root_certs: Vec<PathBuf>,
+
+    /// What a client whose certificate CN matches the user
+    /// running the server is permitted to do
+    #[dynamic(default)]
+    pub client_access: ClientAccess,
+
+    /// Additional certificate CNs that are permitted to connect,
+    /// together with what each of them is permitted to do
+    #[dynamic(default)]
+    pub peer_access: HashMap<String, ClientAccess>,
 }
 
 #[derive(Default, Debug, Clone, FromDynamic, ToDynamic)]
@@ -62,6 +62,11 @@ pub struct UnixDomain {
     /// instead.
     #[dynamic(default)]
     pub overlay_lag_indicator: bool,
+
+    /// What clients that connect to the socket of this domain
+    /// are permitted to do when it is served by the mux server
+    #[dynamic(default)]
+    pub client_access: ClientAccess,
 }
 
 impl Default for UnixDomain {
@@ -78,6 +83,7 @@ impl Default for UnixDomain {
             local_echo_threshold_ms: None,
             proxy_command: None,
             overlay_lag_indicator: false,
+            client_access: ClientAccess::default(),
         }
     }
 }
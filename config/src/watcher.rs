@@ -0,0 +1,75 @@
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Grace period to allow a burst of filesystem events to settle
+/// before we report them
+const DELAY: Duration = Duration::from_millis(200);
+
+/// Watches a set of paths for changes, calling a function with
+/// the debounced list of changed paths.
+/// The function is called from a background thread.
+/// Dropping the PathWatcher stops watching and terminates that thread.
+pub struct PathWatcher {
+    watcher: notify::RecommendedWatcher,
+}
+
+impl PathWatcher {
+    pub fn new<F>(on_change: F) -> anyhow::Result<Self>
+    where
+        F: Fn(Vec<PathBuf>) + Send + 'static,
+    {
+        let (tx, rx) = std::sync::mpsc::channel();
+        let watcher = notify::recommended_watcher(tx)?;
+
+        std::thread::spawn(move || {
+            fn extract_path(event: notify::Event) -> Vec<PathBuf> {
+                match event.kind {
+                    EventKind::Modify(_) | EventKind::Create(_) | EventKind::Remove(_) => {
+                        event.paths
+                    }
+                    _ => vec![],
+                }
+            }
+
+            // block until we get an event; recv fails once the
+            // watcher, and thus the sender, has been dropped
+            while let Ok(event) = rx.recv() {
+                log::debug!("event:{:?}", event);
+                match event {
+                    Ok(event) => {
+                        let mut paths = extract_path(event);
+                        if !paths.is_empty() {
+                            std::thread::sleep(DELAY);
+                            // Drain any other immediately ready events
+                            while let Ok(Ok(event)) = rx.try_recv() {
+                                paths.append(&mut extract_path(event));
+                            }
+                            paths.sort();
+                            paths.dedup();
+                            log::debug!("paths {:?} changed", paths);
+                            on_change(paths);
+                        }
+                    }
+                    Err(err) => {
+                        // We don't know what changed, but something might have
+                        log::debug!("watch error: {:#}", err);
+                        on_change(err.paths);
+                    }
+                }
+            }
+        });
+
+        Ok(Self { watcher })
+    }
+
+    pub fn watch(&mut self, path: &Path, recursive: bool) -> anyhow::Result<()> {
+        let mode = if recursive {
+            RecursiveMode::Recursive
+        } else {
+            RecursiveMode::NonRecursive
+        };
+        self.watcher.watch(path, mode)?;
+        Ok(())
+    }
+}
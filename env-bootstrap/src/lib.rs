@@ -200,6 +200,7 @@ fn register_lua_modules() {
         mux_lua::register,
         procinfo_funcs::register,
         filesystem::register,
+        http_funcs::register,
         serde_funcs::register,
         plugin::register,
         ssh_funcs::register,
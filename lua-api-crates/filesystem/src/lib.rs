@@ -3,10 +3,14 @@ use config::lua::get_or_create_module;
 use config::lua::mlua::{self, Lua};
 use smol::prelude::*;
 
+mod watch;
+
 pub fn register(lua: &Lua) -> anyhow::Result<()> {
+    watch::register(lua)?;
     let wezterm_mod = get_or_create_module(lua, "wezterm")?;
     wezterm_mod.set("read_dir", lua.create_async_function(read_dir)?)?;
     wezterm_mod.set("glob", lua.create_async_function(glob)?)?;
+    wezterm_mod.set("watch_path", lua.create_function(watch::watch_path)?)?;
     Ok(())
 }
 
@@ -0,0 +1,225 @@
+use config::lua::mlua::{self, Lua, UserData, UserDataMethods, UserDataRef};
+use config::lua::{emit_event, is_event_emission, wrap_callback};
+use config::{ConfigSubscription, PathWatcher};
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+lazy_static::lazy_static! {
+    static ref CONFIG_SUBSCRIPTION: Mutex<Option<ConfigSubscription>> = Mutex::new(None);
+    static ref ACTIVE_WATCHES: Mutex<Vec<ActiveWatch>> = Mutex::new(vec![]);
+}
+
+const PENDING_WATCHES: &str = "wezterm-pending-path-watches";
+
+/// A watch that is currently delivering events.
+/// It is associated with the configuration generation that
+/// requested it, so that we can stop it when the config is reloaded.
+struct ActiveWatch {
+    generation: usize,
+    path: PathBuf,
+    /// Shared with the watcher, so that watching the same path again
+    /// can redirect its events to the new callback
+    user_event_id: Arc<Mutex<String>>,
+    _watcher: PathWatcher,
+}
+
+/// Keeps track of `watch_path` state
+#[derive(Debug, Clone)]
+struct PathWatch {
+    path: PathBuf,
+    /// The name of the registry entry that will resolve to
+    /// their callback function
+    user_event_id: String,
+}
+
+impl UserData for PathWatch {
+    fn add_methods<'lua, M: UserDataMethods<'lua, Self>>(_methods: &mut M) {}
+}
+
+impl PathWatch {
+    /// Start watching the path.
+    /// Changes are delivered to the callback on the main thread,
+    /// but only while the configuration generation remains the same
+    /// as the one that started the watch; the next config reload will
+    /// re-evaluate `wezterm.watch_path` and set up a fresh watch.
+    /// If the path is already being watched for this generation,
+    /// the existing watch is reused and delivers to the new callback.
+    fn start(self, generation: usize) -> anyhow::Result<()> {
+        let mut active = ACTIVE_WATCHES.lock().unwrap();
+        if let Some(existing) = active
+            .iter()
+            .find(|watch| watch.generation == generation && watch.path == self.path)
+        {
+            *existing.user_event_id.lock().unwrap() = self.user_event_id;
+            return Ok(());
+        }
+
+        let user_event_id = Arc::new(Mutex::new(self.user_event_id));
+        let watcher_event_id = Arc::clone(&user_event_id);
+        let mut watcher = PathWatcher::new(move |paths| {
+            let user_event_id = watcher_event_id.lock().unwrap().clone();
+            promise::spawn::spawn_into_main_thread(async move {
+                config::with_lua_config_on_main_thread(move |lua| async move {
+                    if let Some(lua) = lua {
+                        if config::configuration().generation() == generation {
+                            let paths: Vec<String> = paths
+                                .iter()
+                                .map(|p| p.to_string_lossy().to_string())
+                                .collect();
+                            let args = lua.pack_multi(paths)?;
+                            emit_event(&lua, (user_event_id, args)).await?;
+                        }
+                    }
+                    Ok(())
+                })
+                .await
+            })
+            .detach();
+        })?;
+        watcher.watch(&self.path, false)?;
+
+        active.push(ActiveWatch {
+            generation,
+            path: self.path,
+            user_event_id,
+            _watcher: watcher,
+        });
+        Ok(())
+    }
+}
+
+/// We contrive to call this from the main thread in response to the
+/// config being reloaded.
+/// It stops the watches belonging to earlier generations and starts
+/// the watches that were requested while evaluating the new config.
+fn start_all(lua: Option<Rc<mlua::Lua>>) -> mlua::Result<()> {
+    let generation = config::configuration().generation();
+    ACTIVE_WATCHES
+        .lock()
+        .unwrap()
+        .retain(|watch| watch.generation == generation);
+
+    if let Some(lua) = lua {
+        let pending: Vec<UserDataRef<PathWatch>> = lua.named_registry_value(PENDING_WATCHES)?;
+        lua.set_named_registry_value(PENDING_WATCHES, Vec::<PathWatch>::new())?;
+        for watch in pending {
+            let path = watch.path.clone();
+            if let Err(err) = watch.clone().start(generation) {
+                log::error!("watch_path: unable to watch {}: {:#}", path.display(), err);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Called by the config subsystem when the config is reloaded.
+fn config_was_reloaded() -> bool {
+    if promise::spawn::is_scheduler_configured() {
+        promise::spawn::spawn_into_main_thread(async move {
+            promise::spawn::spawn(async move {
+                config::with_lua_config_on_main_thread(|lua| async move {
+                    start_all(lua)?;
+                    Ok(())
+                })
+                .await
+            })
+            .detach();
+        })
+        .detach();
+    }
+
+    true
+}
+
+pub fn register(lua: &Lua) -> anyhow::Result<()> {
+    {
+        let mut sub = CONFIG_SUBSCRIPTION.lock().unwrap();
+        if sub.is_none() {
+            sub.replace(config::subscribe_to_config_reload(config_was_reloaded));
+        }
+    }
+    lua.set_named_registry_value(PENDING_WATCHES, Vec::<PathWatch>::new())?;
+    Ok(())
+}
+
+/// Queues a watch to be started once the config has been loaded,
+/// replacing any earlier request for the same path
+fn add_pending(pending: &mut Vec<PathWatch>, watch: PathWatch) {
+    pending.retain(|w| w.path != watch.path);
+    pending.push(watch);
+}
+
+pub fn watch_path<'lua>(
+    lua: &'lua Lua,
+    (path, func): (String, mlua::Function),
+) -> mlua::Result<()> {
+    let user_event_id = wrap_callback(lua, func)?;
+    let watch = PathWatch {
+        path: PathBuf::from(path),
+        user_event_id,
+    };
+
+    if is_event_emission(lua)? {
+        let generation = config::configuration().generation();
+        let path = watch.path.display().to_string();
+        watch
+            .start(generation)
+            .map_err(|err| mlua::Error::external(format!("unable to watch {path}: {err:#}")))?;
+    } else {
+        let pending: Vec<UserDataRef<PathWatch>> = lua.named_registry_value(PENDING_WATCHES)?;
+        let mut pending: Vec<PathWatch> = pending.into_iter().map(|w| w.clone()).collect();
+        add_pending(&mut pending, watch);
+        lua.set_named_registry_value(PENDING_WATCHES, pending)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn watch(path: &str, user_event_id: &str) -> PathWatch {
+        PathWatch {
+            path: PathBuf::from(path),
+            user_event_id: user_event_id.to_string(),
+        }
+    }
+
+    #[test]
+    fn pending_replaces_same_path() {
+        let mut pending = vec![];
+        add_pending(&mut pending, watch("/a", "user-defined-0"));
+        add_pending(&mut pending, watch("/b", "user-defined-1"));
+        add_pending(&mut pending, watch("/a", "user-defined-2"));
+
+        let ids: Vec<(&str, &str)> = pending
+            .iter()
+            .map(|w| (w.path.to_str().unwrap(), w.user_event_id.as_str()))
+            .collect();
+        assert_eq!(
+            ids,
+            vec![("/b", "user-defined-1"), ("/a", "user-defined-2")]
+        );
+    }
+
+    #[test]
+    fn start_reuses_watcher_for_same_path() {
+        // A generation that no real config will have, so that
+        // this doesn't interfere with other watches
+        let generation = usize::MAX;
+        let dir = env!("CARGO_MANIFEST_DIR");
+
+        watch(dir, "user-defined-0").start(generation).unwrap();
+        watch(dir, "user-defined-1").start(generation).unwrap();
+
+        let mut active = ACTIVE_WATCHES.lock().unwrap();
+        let ids: Vec<String> = active
+            .iter()
+            .filter(|w| w.generation == generation)
+            .map(|w| w.user_event_id.lock().unwrap().clone())
+            .collect();
+        assert_eq!(ids, vec!["user-defined-1".to_string()]);
+        active.retain(|w| w.generation != generation);
+    }
+}
@@ -0,0 +1,638 @@
+//! A small blocking HTTP client.
+//! Requests over TCP are made with `http_req`, which uses the platform
+//! TLS implementation and certificate store for `https`.  `http_req`
+//! has no way to talk to a unix domain socket, so the single request
+//! per connection that is sent to one of those is framed here.
+use anyhow::{anyhow, Context};
+use http_req::request::{Method, Request as HttpRequest};
+use http_req::uri::Uri;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use url::Url;
+use wezterm_uds::UnixStream;
+
+/// The largest response header block that we're willing to buffer
+const MAX_HEAD_SIZE: usize = 64 * 1024;
+const MAX_HEADERS: usize = 128;
+
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub method: String,
+    pub url: Url,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    /// When set, connect to this unix domain socket rather than
+    /// to the host in the url
+    pub unix_socket: Option<PathBuf>,
+    /// How long the whole request may take
+    pub timeout: Duration,
+    /// The largest response body that we're willing to buffer
+    pub max_body_size: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    pub status: u16,
+    pub reason: String,
+    /// The header names are lowercased
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        header(&self.headers, name)
+    }
+
+    pub fn is_json(&self) -> bool {
+        match self.header("content-type") {
+            Some(content_type) => {
+                let mime = content_type
+                    .split(';')
+                    .next()
+                    .unwrap_or("")
+                    .trim()
+                    .to_ascii_lowercase();
+                mime == "application/json" || mime.ends_with("+json")
+            }
+            None => false,
+        }
+    }
+}
+
+fn header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(k, _)| k.eq_ignore_ascii_case(name))
+        .map(|(_, v)| v.as_str())
+}
+
+fn body_too_large(limit: usize) -> anyhow::Error {
+    anyhow!("the response body is larger than the limit of {limit} bytes")
+}
+
+/// Collects a response body, refusing to grow it beyond the limit
+struct BodyWriter {
+    body: Vec<u8>,
+    limit: usize,
+    exceeded: bool,
+}
+
+impl BodyWriter {
+    fn new(limit: usize) -> Self {
+        Self {
+            body: vec![],
+            limit,
+            exceeded: false,
+        }
+    }
+}
+
+impl Write for BodyWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.body.len().saturating_add(buf.len()) > self.limit {
+            self.exceeded = true;
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                body_too_large(self.limit),
+            ));
+        }
+        self.body.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A unix domain socket whose reads and writes only wait for what
+/// remains of the time allowed for the whole exchange, so that a
+/// service that trickles out its reply can't keep us blocked
+struct DeadlineStream {
+    stream: UnixStream,
+    deadline: Instant,
+}
+
+impl DeadlineStream {
+    fn connect(path: &Path, timeout: Duration) -> anyhow::Result<Self> {
+        let stream = UnixStream::connect(path)
+            .with_context(|| format!("connecting to {}", path.display()))?;
+        Ok(Self {
+            stream,
+            deadline: Instant::now() + timeout,
+        })
+    }
+
+    fn remaining(&self) -> std::io::Result<Duration> {
+        let remaining = self.deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                "timed out waiting for the service",
+            ))
+        } else {
+            Ok(remaining)
+        }
+    }
+}
+
+impl Read for DeadlineStream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let remaining = self.remaining()?;
+        self.stream.set_read_timeout(Some(remaining))?;
+        self.stream.read(buf)
+    }
+}
+
+impl Write for DeadlineStream {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let remaining = self.remaining()?;
+        self.stream.set_write_timeout(Some(remaining))?;
+        self.stream.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+fn request_method(method: &str) -> anyhow::Result<Method> {
+    Ok(match method {
+        "GET" => Method::GET,
+        "HEAD" => Method::HEAD,
+        "POST" => Method::POST,
+        "PUT" => Method::PUT,
+        "DELETE" => Method::DELETE,
+        "OPTIONS" => Method::OPTIONS,
+        "PATCH" => Method::PATCH,
+        _ => anyhow::bail!("unsupported request method {method}"),
+    })
+}
+
+fn send_tcp(request: &Request) -> anyhow::Result<Response> {
+    match request.url.scheme() {
+        "http" | "https" => {}
+        scheme => anyhow::bail!("unsupported url scheme {scheme}"),
+    }
+    let uri = Uri::try_from(request.url.as_str())
+        .map_err(|err| anyhow!("invalid url {}: {err}", request.url))?;
+    let user_agent = format!("wezterm/{}", config::wezterm_version());
+    let content_length = request.body.len().to_string();
+
+    let mut http_request = HttpRequest::new(&uri);
+    http_request
+        .method(request_method(&request.method)?)
+        .connect_timeout(Some(request.timeout))
+        .read_timeout(Some(request.timeout))
+        .write_timeout(Some(request.timeout))
+        .timeout(Some(request.timeout));
+    if header(&request.headers, "User-Agent").is_none() {
+        http_request.header("User-Agent", &user_agent);
+    }
+    if !request.body.is_empty() || matches!(request.method.as_str(), "POST" | "PUT" | "PATCH") {
+        http_request
+            .header("Content-Length", &content_length)
+            .body(&request.body);
+    }
+    for (name, value) in &request.headers {
+        check_header_text(name)?;
+        check_header_text(value)?;
+        http_request.header(name, value);
+    }
+
+    let mut body = BodyWriter::new(request.max_body_size);
+    let result = http_request.send(&mut body);
+    if body.exceeded {
+        return Err(body_too_large(request.max_body_size));
+    }
+    let response = result.map_err(|err| anyhow!("requesting {}: {err}", request.url))?;
+
+    let mut headers: Vec<(String, String)> = response
+        .headers()
+        .iter()
+        .map(|(name, value)| {
+            let name: &str = name.as_ref();
+            (name.to_ascii_lowercase(), value.trim().to_string())
+        })
+        .collect();
+    // Make the order predictable
+    headers.sort();
+
+    Ok(Response {
+        status: response.status_code().into(),
+        reason: response.reason().to_string(),
+        headers,
+        body: body.body,
+    })
+}
+
+/// Rejects header values that would allow injecting additional
+/// headers or requests
+fn check_header_text(text: &str) -> anyhow::Result<()> {
+    if text.contains(['\r', '\n']) {
+        anyhow::bail!("invalid header {text:?}");
+    }
+    Ok(())
+}
+
+fn write_request<W: Write>(w: &mut W, request: &Request) -> anyhow::Result<()> {
+    let mut target = request.url.path().to_string();
+    if let Some(query) = request.url.query() {
+        target.push('?');
+        target.push_str(query);
+    }
+    check_header_text(&request.method)?;
+
+    let mut head = format!("{} {target} HTTP/1.1\r\n", request.method);
+
+    let mut add_default = |name: &str, value: String| {
+        if header(&request.headers, name).is_none() {
+            head.push_str(&format!("{name}: {value}\r\n"));
+        }
+    };
+    let host = match (request.url.host_str(), request.url.port()) {
+        (Some(host), Some(port)) => format!("{host}:{port}"),
+        (Some(host), None) => host.to_string(),
+        (None, _) => "localhost".to_string(),
+    };
+    add_default("Host", host);
+    add_default(
+        "User-Agent",
+        format!("wezterm/{}", config::wezterm_version()),
+    );
+    add_default("Connection", "close".to_string());
+    if !request.body.is_empty() || matches!(request.method.as_str(), "POST" | "PUT" | "PATCH") {
+        add_default("Content-Length", request.body.len().to_string());
+    }
+
+    for (name, value) in &request.headers {
+        check_header_text(name)?;
+        check_header_text(value)?;
+        head.push_str(&format!("{name}: {value}\r\n"));
+    }
+    head.push_str("\r\n");
+
+    w.write_all(head.as_bytes())?;
+    w.write_all(&request.body)?;
+    w.flush()?;
+    Ok(())
+}
+
+fn read_chunked_body<R: BufRead>(r: &mut R, max_body_size: usize) -> anyhow::Result<Vec<u8>> {
+    let mut body = vec![];
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if r.read_line(&mut line)? == 0 {
+            anyhow::bail!("connection closed in the middle of a chunked response");
+        }
+        // Ignore any chunk extensions
+        let size = line.split(';').next().unwrap_or("").trim();
+        let size = usize::from_str_radix(size, 16)
+            .with_context(|| format!("invalid chunk size {size:?}"))?;
+        if size == 0 {
+            // Skip the trailers, which are terminated by an empty line
+            loop {
+                line.clear();
+                if r.read_line(&mut line)? == 0 || line.trim().is_empty() {
+                    return Ok(body);
+                }
+            }
+        }
+        let start = body.len();
+        if start.saturating_add(size) > max_body_size {
+            return Err(body_too_large(max_body_size));
+        }
+        body.resize(start + size, 0);
+        r.read_exact(&mut body[start..])?;
+        // Each chunk is followed by a CRLF
+        line.clear();
+        r.read_line(&mut line)?;
+    }
+}
+
+fn read_response<R: BufRead>(
+    r: &mut R,
+    method: &str,
+    max_body_size: usize,
+) -> anyhow::Result<Response> {
+    loop {
+        let mut head = vec![];
+        loop {
+            if r.read_until(b'\n', &mut head)? == 0 {
+                anyhow::bail!("connection closed before the response headers were received");
+            }
+            if head.ends_with(b"\r\n\r\n") || head.ends_with(b"\n\n") {
+                break;
+            }
+            if head.len() > MAX_HEAD_SIZE {
+                anyhow::bail!("the response headers are too large");
+            }
+        }
+
+        let mut headers = [httparse::EMPTY_HEADER; MAX_HEADERS];
+        let mut parsed = httparse::Response::new(&mut headers);
+        if parsed.parse(&head)?.is_partial() {
+            anyhow::bail!("incomplete response headers");
+        }
+        let status = parsed.code.unwrap_or(0);
+        // Skip over informational responses such as 100 Continue
+        if (100..200).contains(&status) {
+            continue;
+        }
+        let reason = parsed.reason.unwrap_or("").to_string();
+        let headers: Vec<(String, String)> = parsed
+            .headers
+            .iter()
+            .map(|h| {
+                (
+                    h.name.to_ascii_lowercase(),
+                    String::from_utf8_lossy(h.value).trim().to_string(),
+                )
+            })
+            .collect();
+
+        let chunked = header(&headers, "transfer-encoding")
+            .map(|te| te.to_ascii_lowercase().contains("chunked"))
+            .unwrap_or(false);
+        let content_length = match header(&headers, "content-length") {
+            Some(len) => Some(
+                len.parse::<usize>()
+                    .with_context(|| format!("invalid content-length {len:?}"))?,
+            ),
+            None => None,
+        };
+
+        let body = if method == "HEAD" || status == 204 || status == 304 {
+            vec![]
+        } else if chunked {
+            read_chunked_body(r, max_body_size)?
+        } else if let Some(len) = content_length {
+            if len > max_body_size {
+                return Err(body_too_large(max_body_size));
+            }
+            let mut body = vec![0; len];
+            r.read_exact(&mut body)
+                .context("connection closed before the response body was received")?;
+            body
+        } else {
+            let mut body = BodyWriter::new(max_body_size);
+            let result = std::io::copy(r, &mut body);
+            if body.exceeded {
+                return Err(body_too_large(max_body_size));
+            }
+            result?;
+            body.body
+        };
+
+        return Ok(Response {
+            status,
+            reason,
+            headers,
+            body,
+        });
+    }
+}
+
+/// Performs the request, blocking until the response has been received
+/// or the timeout has expired
+pub fn send(request: &Request) -> anyhow::Result<Response> {
+    match &request.unix_socket {
+        Some(path) => {
+            let mut stream = DeadlineStream::connect(path, request.timeout)?;
+            write_request(&mut stream, request)?;
+            read_response(
+                &mut BufReader::new(stream),
+                &request.method,
+                request.max_body_size,
+            )
+        }
+        None => send_tcp(request),
+    }
+}
+
+/// Sends a line of text to a unix domain socket, and returns the
+/// first line of the reply, without the line ending.
+/// This is the framing used by services that speak newline
+/// delimited JSON.
+pub fn exchange_line(
+    path: &Path,
+    line: &str,
+    timeout: Duration,
+    max_size: usize,
+) -> anyhow::Result<String> {
+    let mut stream = DeadlineStream::connect(path, timeout)?;
+    stream.write_all(line.as_bytes())?;
+    stream.write_all(b"\n")?;
+    stream.flush()?;
+
+    let mut reply = vec![];
+    BufReader::new(stream)
+        .take(max_size as u64 + 1)
+        .read_until(b'\n', &mut reply)?;
+    if reply.is_empty() {
+        anyhow::bail!("{} closed the connection without replying", path.display());
+    }
+    if reply.len() > max_size {
+        return Err(body_too_large(max_size));
+    }
+    let reply = String::from_utf8(reply).context("the reply is not valid UTF-8")?;
+    Ok(reply.trim_end_matches(['\r', '\n']).to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Cursor;
+    use std::net::TcpListener;
+
+    fn request(method: &str, url: &str, body: &[u8]) -> Request {
+        Request {
+            method: method.to_string(),
+            url: Url::parse(url).unwrap(),
+            headers: vec![("X-Token".to_string(), "secret".to_string())],
+            body: body.to_vec(),
+            unix_socket: None,
+            timeout: Duration::from_secs(5),
+            max_body_size: 1024,
+        }
+    }
+
+    #[test]
+    fn request_head() {
+        let mut out = vec![];
+        write_request(
+            &mut out,
+            &request("POST", "http://localhost:8080/status?verbose=1", b"hello"),
+        )
+        .unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(
+            out,
+            format!(
+                "POST /status?verbose=1 HTTP/1.1\r\n\
+                 Host: localhost:8080\r\n\
+                 User-Agent: wezterm/{}\r\n\
+                 Connection: close\r\n\
+                 Content-Length: 5\r\n\
+                 X-Token: secret\r\n\
+                 \r\n\
+                 hello",
+                config::wezterm_version()
+            )
+        );
+
+        let mut req = request("GET", "http://localhost/", b"");
+        req.headers = vec![("X-Evil".to_string(), "a\r\nHost: b".to_string())];
+        assert!(write_request(&mut vec![], &req).is_err());
+    }
+
+    #[test]
+    fn content_length_response() {
+        let mut r = Cursor::new(
+            b"HTTP/1.1 100 Continue\r\n\r\n\
+              HTTP/1.1 200 OK\r\n\
+              Content-Type: application/json; charset=utf-8\r\n\
+              Content-Length: 11\r\n\
+              \r\n\
+              {\"a\": true}trailing garbage"
+                .to_vec(),
+        );
+        let response = read_response(&mut r, "GET", 1024).unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.reason, "OK");
+        assert_eq!(response.header("Content-Length"), Some("11"));
+        assert!(response.is_json());
+        assert_eq!(response.body, b"{\"a\": true}");
+    }
+
+    #[test]
+    fn chunked_response() {
+        let mut r = Cursor::new(
+            b"HTTP/1.1 404 Not Found\r\n\
+              Transfer-Encoding: chunked\r\n\
+              \r\n\
+              4;ext=1\r\nnot \r\n\
+              5\r\nfound\r\n\
+              0\r\n\
+              X-Trailer: yes\r\n\
+              \r\n"
+                .to_vec(),
+        );
+        let response = read_response(&mut r, "GET", 1024).unwrap();
+        assert_eq!(response.status, 404);
+        assert!(!response.is_json());
+        assert_eq!(response.body, b"not found");
+    }
+
+    #[test]
+    fn body_size_limit() {
+        let mut r = Cursor::new(
+            b"HTTP/1.1 200 OK\r\n\
+              Content-Length: 1000000000000\r\n\
+              \r\n\
+              short"
+                .to_vec(),
+        );
+        let err = read_response(&mut r, "GET", 1024).unwrap_err();
+        assert!(err.to_string().contains("limit of 1024 bytes"), "{err:#}");
+
+        let mut r = Cursor::new(
+            b"HTTP/1.1 200 OK\r\n\
+              Transfer-Encoding: chunked\r\n\
+              \r\n\
+              4\r\nabcd\r\n\
+              4\r\nefgh\r\n\
+              0\r\n\r\n"
+                .to_vec(),
+        );
+        assert!(read_response(&mut r, "GET", 6).is_err());
+
+        let mut r = Cursor::new(b"HTTP/1.1 200 OK\r\n\r\nabcdefgh".to_vec());
+        assert!(read_response(&mut r, "GET", 6).is_err());
+        r.set_position(0);
+        assert_eq!(read_response(&mut r, "GET", 8).unwrap().body, b"abcdefgh");
+    }
+
+    #[test]
+    fn round_trip() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut head = String::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line == "\r\n" {
+                    break;
+                }
+                head.push_str(&line);
+            }
+            let mut body = [0u8; 4];
+            reader.read_exact(&mut body).unwrap();
+            stream
+                .write_all(b"HTTP/1.1 201 Created\r\n\r\ncreated")
+                .unwrap();
+            (head, body)
+        });
+
+        let response = send(&request(
+            "PUT",
+            &format!("http://127.0.0.1:{port}/item"),
+            b"data",
+        ))
+        .unwrap();
+        assert_eq!(response.status, 201);
+        assert_eq!(response.body, b"created");
+
+        let (head, body) = server.join().unwrap();
+        assert!(head.starts_with("PUT /item HTTP/1.1\r\n"), "{head}");
+        assert!(head.contains("X-Token: secret\r\n"), "{head}");
+        assert_eq!(&body, b"data");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unix_socket() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sock");
+        let listener = std::os::unix::net::UnixListener::bind(&path).unwrap();
+        let server = std::thread::spawn(move || {
+            for _ in 0..2 {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                let mut stream = stream;
+                if line.starts_with("GET ") {
+                    // Consume the rest of the request before replying
+                    let mut header = String::new();
+                    while header != "\r\n" {
+                        header.clear();
+                        reader.read_line(&mut header).unwrap();
+                    }
+                    stream
+                        .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok")
+                        .unwrap();
+                } else {
+                    stream.write_all(b"{\"echo\":").unwrap();
+                    stream.write_all(line.trim_end().as_bytes()).unwrap();
+                    stream.write_all(b"}\n").unwrap();
+                }
+            }
+        });
+
+        let mut req = request("GET", "http://localhost/v1/status", b"");
+        req.unix_socket = Some(path.clone());
+        assert_eq!(send(&req).unwrap().body, b"ok");
+
+        let reply = exchange_line(&path, "[1,2]", Duration::from_secs(5), 1024).unwrap();
+        assert_eq!(reply, "{\"echo\":[1,2]}");
+
+        server.join().unwrap();
+    }
+}
@@ -0,0 +1,220 @@
+use anyhow::anyhow;
+use config::lua::get_or_create_sub_module;
+use config::lua::mlua::{self, Lua, Table, Value as LuaValue};
+use serde_funcs::{json_value_to_lua_value, lua_value_to_json_value};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::time::Duration;
+
+mod client;
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+const DEFAULT_MAX_BODY_SIZE: usize = 16 * 1024 * 1024;
+
+pub fn register(lua: &Lua) -> anyhow::Result<()> {
+    let http_mod = get_or_create_sub_module(lua, "http")?;
+    http_mod.set("request", lua.create_async_function(request)?)?;
+    http_mod.set("get", lua.create_async_function(get)?)?;
+    http_mod.set("post", lua.create_async_function(post)?)?;
+    http_mod.set("unix_json", lua.create_async_function(unix_json)?)?;
+    Ok(())
+}
+
+fn check_enabled() -> mlua::Result<()> {
+    if config::configuration().enable_http_client {
+        Ok(())
+    } else {
+        Err(mlua::Error::external(
+            "wezterm.http is disabled; set config.enable_http_client = true to enable it",
+        ))
+    }
+}
+
+fn get_timeout(opts: Option<&Table>) -> mlua::Result<Duration> {
+    let seconds: Option<f64> = match opts {
+        Some(opts) => opts.get("timeout")?,
+        None => None,
+    };
+    match seconds {
+        Some(seconds) if seconds > 0. && seconds.is_finite() => {
+            Ok(Duration::from_secs_f64(seconds))
+        }
+        Some(seconds) => Err(mlua::Error::external(format!(
+            "invalid timeout {seconds}; it must be a positive number of seconds"
+        ))),
+        None => Ok(DEFAULT_TIMEOUT),
+    }
+}
+
+fn get_max_body_size(opts: Option<&Table>) -> mlua::Result<usize> {
+    let size: Option<usize> = match opts {
+        Some(opts) => opts.get("max_body_size")?,
+        None => None,
+    };
+    Ok(size.unwrap_or(DEFAULT_MAX_BODY_SIZE))
+}
+
+fn make_request(
+    url: Option<String>,
+    method: &str,
+    opts: Option<Table>,
+) -> mlua::Result<client::Request> {
+    let opt = |name: &str| -> mlua::Result<Option<String>> {
+        match &opts {
+            Some(opts) => opts.get(name),
+            None => Ok(None),
+        }
+    };
+
+    let url = match url.or(opt("url")?) {
+        Some(url) => url,
+        None => return Err(mlua::Error::external("a url is required")),
+    };
+    let url = url::Url::parse(&url)
+        .map_err(|err| mlua::Error::external(format!("invalid url {url}: {err:#}")))?;
+    let method = opt("method")?
+        .unwrap_or_else(|| method.to_string())
+        .to_ascii_uppercase();
+    let unix_socket = opt("unix_socket")?.map(PathBuf::from);
+    let timeout = get_timeout(opts.as_ref())?;
+    let max_body_size = get_max_body_size(opts.as_ref())?;
+
+    let mut headers: Vec<(String, String)> = vec![];
+    let mut body = vec![];
+    if let Some(opts) = &opts {
+        let user_headers: Option<HashMap<String, String>> = opts.get("headers")?;
+        if let Some(user_headers) = user_headers {
+            headers.extend(user_headers);
+            // Make the order predictable
+            headers.sort();
+        }
+
+        let user_body: Option<mlua::String> = opts.get("body")?;
+        let json: LuaValue = opts.get("json")?;
+        match (user_body, json) {
+            (None, LuaValue::Nil) => {}
+            (Some(user_body), LuaValue::Nil) => body = user_body.as_bytes().to_vec(),
+            (None, json) => {
+                let json = lua_value_to_json_value(json, &mut HashSet::new())?;
+                body = serde_json::to_vec(&json)
+                    .map_err(|err| mlua::Error::external(format!("{err:#}")))?;
+                if !headers
+                    .iter()
+                    .any(|(k, _)| k.eq_ignore_ascii_case("content-type"))
+                {
+                    headers.push(("Content-Type".to_string(), "application/json".to_string()));
+                }
+            }
+            (Some(_), _) => {
+                return Err(mlua::Error::external(
+                    "only one of body or json may be specified",
+                ))
+            }
+        }
+    }
+
+    Ok(client::Request {
+        method,
+        url,
+        headers,
+        body,
+        unix_socket,
+        timeout,
+        max_body_size,
+    })
+}
+
+/// Runs a blocking operation on the smol blocking thread pool, giving
+/// up after the timeout so that a stalled service can't hold up the
+/// lua code that is waiting on it.  The operation itself must also
+/// honor the timeout, so that it doesn't tie up the thread after
+/// we've stopped waiting for it.
+async fn run_with_timeout<T: Send + 'static>(
+    timeout: Duration,
+    func: impl FnOnce() -> anyhow::Result<T> + Send + 'static,
+) -> mlua::Result<T> {
+    smol::future::or(smol::unblock(func), async move {
+        smol::Timer::after(timeout).await;
+        Err(anyhow!("timed out after {timeout:?}"))
+    })
+    .await
+    .map_err(|err| mlua::Error::external(format!("{err:#}")))
+}
+
+fn response_to_lua<'lua>(lua: &'lua Lua, response: client::Response) -> mlua::Result<Table<'lua>> {
+    let result = lua.create_table()?;
+    result.set("status", response.status)?;
+    result.set("reason", response.reason.as_str())?;
+    result.set("ok", (200..300).contains(&response.status))?;
+
+    // Repeated headers are combined as described by RFC 9110
+    let mut headers: HashMap<&str, String> = HashMap::new();
+    for (name, value) in &response.headers {
+        headers
+            .entry(name.as_str())
+            .and_modify(|existing| {
+                existing.push_str(", ");
+                existing.push_str(value);
+            })
+            .or_insert_with(|| value.to_string());
+    }
+    result.set("headers", headers)?;
+
+    if response.is_json() {
+        if let Ok(json) = serde_json::from_slice(&response.body) {
+            result.set("json", json_value_to_lua_value(lua, json)?)?;
+        }
+    }
+    result.set("body", lua.create_string(&response.body)?)?;
+    Ok(result)
+}
+
+async fn perform<'lua>(lua: &'lua Lua, request: client::Request) -> mlua::Result<Table<'lua>> {
+    check_enabled()?;
+    let timeout = request.timeout;
+    let response = run_with_timeout(timeout, move || client::send(&request)).await?;
+    response_to_lua(lua, response)
+}
+
+async fn request<'lua>(lua: &'lua Lua, opts: Table<'lua>) -> mlua::Result<Table<'lua>> {
+    let request = make_request(None, "GET", Some(opts))?;
+    perform(lua, request).await
+}
+
+async fn get<'lua>(
+    lua: &'lua Lua,
+    (url, opts): (String, Option<Table<'lua>>),
+) -> mlua::Result<Table<'lua>> {
+    let request = make_request(Some(url), "GET", opts)?;
+    perform(lua, request).await
+}
+
+async fn post<'lua>(
+    lua: &'lua Lua,
+    (url, opts): (String, Option<Table<'lua>>),
+) -> mlua::Result<Table<'lua>> {
+    let request = make_request(Some(url), "POST", opts)?;
+    perform(lua, request).await
+}
+
+async fn unix_json<'lua>(
+    lua: &'lua Lua,
+    (path, value, opts): (String, LuaValue<'lua>, Option<Table<'lua>>),
+) -> mlua::Result<LuaValue<'lua>> {
+    check_enabled()?;
+    let timeout = get_timeout(opts.as_ref())?;
+    let max_size = get_max_body_size(opts.as_ref())?;
+    let json = lua_value_to_json_value(value, &mut HashSet::new())?;
+    let line =
+        serde_json::to_string(&json).map_err(|err| mlua::Error::external(format!("{err:#}")))?;
+    let path = PathBuf::from(path);
+
+    let reply = run_with_timeout(timeout, move || {
+        client::exchange_line(&path, &line, timeout, max_size)
+    })
+    .await?;
+
+    let reply = serde_json::from_str(&reply)
+        .map_err(|err| mlua::Error::external(format!("invalid json reply {reply:?}: {err:#}")))?;
+    json_value_to_lua_value(lua, reply)
+}
@@ -1,4 +1,5 @@
 use super::*;
+use luahelper::to_lua;
 use mlua::UserDataRef;
 use mux::domain::{Domain, DomainId, DomainState};
 use std::sync::Arc;
@@ -74,6 +75,35 @@ impl UserData for MuxDomain {
             Ok(domain.domain_label().await)
         });
 
+        methods.add_async_method("ssh_forwards", |lua, this, _: ()| async move {
+            let mux = get_mux()?;
+            let domain = this.resolve(&mux)?;
+            let forwards = mux::ssh::list_ssh_forwards(Some(domain.domain_name()))
+                .await
+                .map_err(|err| mlua::Error::external(format!("{err:#}")))?;
+            to_lua(lua, forwards)
+        });
+
+        methods.add_async_method(
+            "add_ssh_forward",
+            |lua, this, (kind, spec): (String, String)| async move {
+                let mux = get_mux()?;
+                let domain = this.resolve(&mux)?;
+                let forward = mux::ssh::add_ssh_forward(domain.domain_name(), &kind, &spec)
+                    .await
+                    .map_err(|err| mlua::Error::external(format!("{err:#}")))?;
+                to_lua(lua, forward)
+            },
+        );
+
+        methods.add_async_method("remove_ssh_forward", |_, this, id: usize| async move {
+            let mux = get_mux()?;
+            let domain = this.resolve(&mux)?;
+            mux::ssh::remove_ssh_forward(domain.domain_name(), id)
+                .await
+                .map_err(|err| mlua::Error::external(format!("{err:#}")))
+        });
+
         methods.add_method("has_any_panes", |_, this, _: ()| {
             let mux = get_mux()?;
             let domain = this.resolve(&mux)?;
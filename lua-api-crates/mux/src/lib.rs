@@ -5,6 +5,7 @@ use luahelper::impl_lua_conversion_dynamic;
 use mlua::UserDataRef;
 use mux::domain::{DomainId, SplitSource};
 use mux::pane::{Pane, PaneId};
+use mux::session_state;
 use mux::tab::{SplitDirection, SplitRequest, SplitSize, Tab, TabId};
 use mux::window::{Window, WindowId};
 use mux::Mux;
@@ -158,6 +159,41 @@ pub fn register(lua: &Lua) -> anyhow::Result<()> {
         })?,
     )?;
 
+    mux_mod.set(
+        "save_session",
+        lua.create_function(|_, (name, scrollback): (Option<String>, Option<bool>)| {
+            let name = name.as_deref().unwrap_or(session_state::DEFAULT_SESSION);
+            let scrollback =
+                scrollback.unwrap_or_else(|| config::configuration().session_save_scrollback);
+            let path = session_state::save_session(name, scrollback)
+                .map_err(|err| mlua::Error::external(format!("{err:#}")))?;
+            Ok(path.display().to_string())
+        })?,
+    )?;
+
+    mux_mod.set(
+        "restore_session",
+        lua.create_async_function(|_, name: Option<String>| async move {
+            let name = name.as_deref().unwrap_or(session_state::DEFAULT_SESSION);
+            let session = session_state::load_session(name)
+                .map_err(|err| mlua::Error::external(format!("{err:#}")))?;
+            let window_ids = session_state::restore_session(&session)
+                .await
+                .map_err(|err| mlua::Error::external(format!("{err:#}")))?;
+            Ok(window_ids
+                .into_iter()
+                .map(MuxWindow)
+                .collect::<Vec<MuxWindow>>())
+        })?,
+    )?;
+
+    mux_mod.set(
+        "list_sessions",
+        lua.create_function(|_, _: ()| {
+            session_state::list_sessions().map_err(|err| mlua::Error::external(format!("{err:#}")))
+        })?,
+    )?;
+
     mux_mod.set(
         "set_default_domain",
         lua.create_function(|_, domain: UserDataRef<MuxDomain>| {
@@ -248,6 +284,7 @@ impl SpawnWindow {
                 self.domain,
                 cmd_builder,
                 cwd,
+                None,
                 size,
                 None,
                 self.workspace.unwrap_or_else(|| mux.active_workspace()),
@@ -299,6 +336,7 @@ impl SpawnTab {
                 self.domain,
                 cmd_builder,
                 cwd,
+                None,
                 size,
                 pane,
                 String::new(),
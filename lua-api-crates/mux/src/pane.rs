@@ -1,11 +1,15 @@
 use super::*;
+use config::keyassignment::ExportFormat;
+use config::SerialLineSettings;
 use luahelper::mlua::LuaSerdeExt;
 use luahelper::{dynamic_to_lua_value, from_lua, to_lua};
 use mlua::Value;
-use mux::pane::CachePolicy;
+use mux::pane::{get_text_from_semantic_zone, CachePolicy};
+use mux::recording::RecordingOptions;
 use std::cmp::Ordering;
 use std::sync::Arc;
 use termwiz::cell::SemanticType;
+use termwiz_funcs::export::export_lines;
 use termwiz_funcs::lines_to_escapes;
 use url_funcs::Url;
 use wezterm_term::{SemanticZone, StableRowIndex};
@@ -22,65 +26,20 @@ impl MuxPane {
     fn get_text_from_semantic_zone(&self, zone: SemanticZone) -> mlua::Result<String> {
         let mux = get_mux()?;
         let pane = self.resolve(&mux)?;
+        Ok(get_text_from_semantic_zone(&*pane, &zone))
+    }
 
-        let mut last_was_wrapped = false;
-        let first_row = zone.start_y;
-        let last_row = zone.end_y;
-
-        fn cols_for_row(zone: &SemanticZone, row: StableRowIndex) -> std::ops::Range<usize> {
-            if row < zone.start_y || row > zone.end_y {
-                0..0
-            } else if zone.start_y == zone.end_y {
-                // A single line zone
-                if zone.start_x <= zone.end_x {
-                    zone.start_x..zone.end_x.saturating_add(1)
-                } else {
-                    zone.end_x..zone.start_x.saturating_add(1)
-                }
-            } else if row == zone.end_y {
-                // last line of multi-line
-                0..zone.end_x.saturating_add(1)
-            } else if row == zone.start_y {
-                // first line of multi-line
-                zone.start_x..usize::max_value()
-            } else {
-                // some "middle" line of multi-line
-                0..usize::max_value()
-            }
-        }
-
-        let mut s = String::new();
-        for line in pane.get_logical_lines(zone.start_y..zone.end_y + 1) {
-            if !s.is_empty() && !last_was_wrapped {
-                s.push('\n');
-            }
-            let last_idx = line.physical_lines.len().saturating_sub(1);
-            for (idx, phys) in line.physical_lines.iter().enumerate() {
-                let this_row = line.first_row + idx as StableRowIndex;
-                if this_row >= first_row && this_row <= last_row {
-                    let last_phys_idx = phys.len().saturating_sub(1);
-
-                    let cols = cols_for_row(&zone, this_row);
-                    let last_col_idx = cols.end.saturating_sub(1).min(last_phys_idx);
-                    let col_span = phys.columns_as_str(cols);
-                    // Only trim trailing whitespace if we are the last line
-                    // in a wrapped sequence
-                    if idx == last_idx {
-                        s.push_str(col_span.trim_end());
-                    } else {
-                        s.push_str(&col_span);
-                    }
-
-                    last_was_wrapped = last_col_idx == last_phys_idx
-                        && phys
-                            .get_cell(last_col_idx)
-                            .map(|c| c.attrs().wrapped())
-                            .unwrap_or(false);
-                }
-            }
-        }
-
-        Ok(s)
+    /// Exports the last `nlines` lines of the viewport, which
+    /// defaults to the height of the viewport
+    fn export_lines(&self, nlines: Option<usize>, format: ExportFormat) -> mlua::Result<String> {
+        let mux = get_mux()?;
+        let pane = self.resolve(&mux)?;
+        let dims = pane.get_dimensions();
+        let nlines = nlines.unwrap_or(dims.viewport_rows);
+        let bottom_row = dims.physical_top + dims.viewport_rows as isize;
+        let top_row = bottom_row.saturating_sub(nlines as isize);
+        let (_first_row, lines) = pane.get_lines(top_row..bottom_row);
+        export_lines(lines, format, &pane.palette()).map_err(mlua::Error::external)
     }
 }
 
@@ -249,6 +208,14 @@ impl UserData for MuxPane {
             Ok(text)
         });
 
+        methods.add_method("get_lines_as_html", |_, this, nlines: Option<usize>| {
+            this.export_lines(nlines, ExportFormat::Html)
+        });
+
+        methods.add_method("get_lines_as_svg", |_, this, nlines: Option<usize>| {
+            this.export_lines(nlines, ExportFormat::Svg)
+        });
+
         methods.add_method(
             "get_logical_lines_as_text",
             |_, this, nlines: Option<usize>| {
@@ -433,6 +400,58 @@ impl UserData for MuxPane {
             let pane = this.resolve(&mux)?;
             Ok(pane.tty_name())
         });
+
+        methods.add_async_method("start_recording", |_, this, options: Value| async move {
+            let options: Option<RecordingOptions> = from_lua(options)?;
+            let mux = get_mux()?;
+            let pane = this.resolve(&mux)?;
+            pane.start_recording(options.unwrap_or_default())
+                .await
+                .map_err(|e| mlua::Error::external(format!("{:#}", e)))
+        });
+
+        methods.add_async_method("stop_recording", |_, this, _: ()| async move {
+            let mux = get_mux()?;
+            let pane = this.resolve(&mux)?;
+            pane.stop_recording()
+                .await
+                .map_err(|e| mlua::Error::external(format!("{:#}", e)))
+        });
+
+        methods.add_method("is_recording", |_, this, _: ()| {
+            let mux = get_mux()?;
+            let pane = this.resolve(&mux)?;
+            Ok(pane.is_recording())
+        });
+
+        methods.add_method("get_serial_settings", |lua, this, _: ()| {
+            let mux = get_mux()?;
+            let pane = this.resolve(&mux)?;
+            let settings = mux::serial::serial_control_for_pane(&*pane)
+                .and_then(|control| mux::serial::line_settings(&control))
+                .map_err(|e| mlua::Error::external(format!("{:#}", e)))?;
+            to_lua(lua, settings)
+        });
+
+        methods.add_method("set_serial_settings", |_, this, settings: Value| {
+            let settings: SerialLineSettings = from_lua(settings)?;
+            let mux = get_mux()?;
+            let pane = this.resolve(&mux)?;
+            mux::serial::serial_control_for_pane(&*pane)
+                .and_then(|control| mux::serial::apply_line_settings(&control, &settings))
+                .map_err(|e| mlua::Error::external(format!("{:#}", e)))
+        });
+
+        methods.add_async_method("send_serial_break", |_, this, ms: Option<u64>| async move {
+            let mux = get_mux()?;
+            let pane = this.resolve(&mux)?;
+            let control = mux::serial::serial_control_for_pane(&*pane)
+                .map_err(|e| mlua::Error::external(format!("{:#}", e)))?;
+            let duration = std::time::Duration::from_millis(ms.unwrap_or(250));
+            smol::unblock(move || control.send_break(duration))
+                .await
+                .map_err(|e| mlua::Error::external(format!("{:#}", e)))
+        });
     }
 }
 
@@ -461,6 +480,7 @@ impl SplitPane {
         let source = SplitSource::Spawn {
             command,
             command_dir,
+            initial_output: None,
         };
 
         let size = if self.size == 0.0 {
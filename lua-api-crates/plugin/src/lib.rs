@@ -1,13 +1,56 @@
 use anyhow::{anyhow, Context};
-use config::lua::get_or_create_sub_module;
-use config::lua::mlua::{self, Lua, Value};
+use config::lua::mlua::{self, Lua, Table, Value};
+use config::lua::{get_or_create_module, get_or_create_sub_module};
 use git2::build::CheckoutBuilder;
-use git2::{Remote, Repository};
-use luahelper::to_lua;
-use std::path::PathBuf;
+use git2::{Oid, Remote, Repository, StatusOptions};
+use luahelper::{from_lua, to_lua};
+use std::path::{Path, PathBuf};
 use tempfile::TempDir;
 use wezterm_dynamic::{FromDynamic, ToDynamic};
 
+mod lockfile;
+
+pub use lockfile::{lock_file_path, LockFile, LockedPlugin};
+
+/// Selects the revision of a plugin to use.  At most one of the
+/// fields may be set; when none are set, the default branch is used.
+#[derive(FromDynamic, ToDynamic, Debug, Clone, Default, PartialEq, Eq)]
+pub struct PluginPin {
+    #[dynamic(default)]
+    pub tag: Option<String>,
+    #[dynamic(default)]
+    pub branch: Option<String>,
+    #[dynamic(default)]
+    pub rev: Option<String>,
+}
+
+impl PluginPin {
+    fn validate(&self) -> anyhow::Result<()> {
+        let count = [&self.tag, &self.branch, &self.rev]
+            .iter()
+            .filter(|field| field.is_some())
+            .count();
+        if count > 1 {
+            anyhow::bail!("only one of tag, branch or rev may be specified");
+        }
+        Ok(())
+    }
+}
+
+impl std::fmt::Display for PluginPin {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if let Some(tag) = &self.tag {
+            write!(fmt, "tag {tag}")
+        } else if let Some(branch) = &self.branch {
+            write!(fmt, "branch {branch}")
+        } else if let Some(rev) = &self.rev {
+            write!(fmt, "rev {rev}")
+        } else {
+            write!(fmt, "default branch")
+        }
+    }
+}
+
 #[derive(FromDynamic, ToDynamic, Debug)]
 struct RepoSpec {
     url: String,
@@ -54,12 +97,17 @@ fn get_remote(repo: &Repository) -> anyhow::Result<Option<Remote>> {
 
 impl RepoSpec {
     fn parse(url: String) -> anyhow::Result<Self> {
+        Self::parse_in(url, &Self::plugins_dir())
+    }
+
+    /// Parses the url of a plugin whose checkout is kept in `plugins_dir`
+    fn parse_in(url: String, plugins_dir: &Path) -> anyhow::Result<Self> {
         let component = compute_repo_dir(&url);
         if component.starts_with('.') {
             anyhow::bail!("invalid repo spec {url}");
         }
 
-        let plugin_dir = RepoSpec::plugins_dir().join(&component);
+        let plugin_dir = plugins_dir.join(&component);
 
         Ok(Self {
             url,
@@ -76,8 +124,6 @@ impl RepoSpec {
             .ok_or_else(|| anyhow!("{path:?} isn't unicode"))?
             .to_string();
 
-        let plugin_dir = RepoSpec::plugins_dir().join(&component);
-
         let repo = Repository::open(&path)?;
         let remote = get_remote(&repo)?.ok_or_else(|| anyhow!("no remotes!?"))?;
         let url = remote.url();
@@ -86,7 +132,7 @@ impl RepoSpec {
             return Ok(Self {
                 component,
                 url,
-                plugin_dir,
+                plugin_dir: path,
             });
         }
         anyhow::bail!("Unable to create a complete RepoSpec for repo at {path:?}");
@@ -97,67 +143,196 @@ impl RepoSpec {
     }
 
     fn checkout_path(&self) -> PathBuf {
-        Self::plugins_dir().join(&self.component)
+        self.plugin_dir.clone()
     }
 
     fn is_checked_out(&self) -> bool {
         self.checkout_path().exists()
     }
 
-    fn update(&self) -> anyhow::Result<()> {
-        let path = self.checkout_path();
-        let repo = Repository::open(&path)?;
-        let mut remote = get_remote(&repo)?.ok_or_else(|| anyhow!("no remotes!?"))?;
+    fn remote_name(repo: &Repository) -> anyhow::Result<String> {
+        let remote = get_remote(repo)?.ok_or_else(|| anyhow!("no remotes!?"))?;
+        Ok(remote.name().unwrap_or("origin").to_string())
+    }
+
+    /// Fetches all branches and tags from the remote, and updates
+    /// the remote HEAD to reflect its current default branch
+    fn fetch(&self, repo: &Repository) -> anyhow::Result<()> {
+        let mut remote = get_remote(repo)?.ok_or_else(|| anyhow!("no remotes!?"))?;
+        let name = remote.name().unwrap_or("origin").to_string();
+
         remote.connect(git2::Direction::Fetch).context("connect")?;
-        let branch = remote
+        let default_branch = remote
             .default_branch()
-            .context("get default branch")?
-            .as_str()
-            .ok_or_else(|| anyhow!("default branch is not utf8"))?
-            .to_string();
+            .ok()
+            .and_then(|branch| branch.as_str().map(|s| s.to_string()));
+
+        let refspecs = [
+            format!("+refs/heads/*:refs/remotes/{name}/*"),
+            "+refs/tags/*:refs/tags/*".to_string(),
+        ];
+        remote
+            .fetch(&refspecs, None, None)
+            .with_context(|| format!("fetching {}", self.url))?;
+
+        if let Some(branch) = default_branch
+            .as_deref()
+            .and_then(|branch| branch.strip_prefix("refs/heads/"))
+        {
+            repo.reference_symbolic(
+                &format!("refs/remotes/{name}/HEAD"),
+                &format!("refs/remotes/{name}/{branch}"),
+                true,
+                "update remote HEAD",
+            )
+            .context("update remote HEAD")?;
+        }
+        Ok(())
+    }
 
-        remote.fetch(&[branch], None, None).context("fetch")?;
-        let mut merge_info = None;
-        repo.fetchhead_foreach(|refname, _remote_url, target_oid, was_merge| {
-            if was_merge {
-                merge_info.replace((refname.to_string(), *target_oid));
-                return true;
+    /// Resolves the pin, or the locked commit if specified, to a commit
+    /// using only the objects that are already present in the checkout.
+    /// Returns None if it cannot be resolved without fetching.
+    fn resolve(
+        &self,
+        repo: &Repository,
+        pin: &PluginPin,
+        locked: Option<&str>,
+    ) -> anyhow::Result<Option<Oid>> {
+        let spec = if let Some(commit) = locked {
+            commit.to_string()
+        } else if let Some(rev) = &pin.rev {
+            rev.to_string()
+        } else if let Some(tag) = &pin.tag {
+            format!("refs/tags/{tag}")
+        } else if let Some(branch) = &pin.branch {
+            format!("refs/remotes/{}/{branch}", Self::remote_name(repo)?)
+        } else {
+            let remote_head = format!("refs/remotes/{}/HEAD", Self::remote_name(repo)?);
+            if repo.find_reference(&remote_head).is_ok() {
+                remote_head
+            } else {
+                "HEAD".to_string()
             }
-            false
-        })
-        .context("fetchhead_foreach")?;
+        };
 
-        let (refname, target_oid) = merge_info.ok_or_else(|| anyhow!("No merge info!?"))?;
-        let commit = repo
-            .find_annotated_commit(target_oid)
-            .context("find_annotated_commit")?;
+        let oid = match repo.revparse_single(&spec) {
+            Ok(object) => object
+                .peel_to_commit()
+                .with_context(|| format!("{spec} is not a commit"))?
+                .id(),
+            Err(err) if err.code() == git2::ErrorCode::NotFound => return Ok(None),
+            Err(err) => return Err(err).with_context(|| format!("resolving {spec}")),
+        };
 
-        let (analysis, _preference) = repo.merge_analysis(&[&commit]).context("merge_analysis")?;
-        if analysis.is_up_to_date() {
-            log::debug!("{} is up to date!", self.component);
-            return Ok(());
+        // Verify that what we resolved is what was asked for, so that
+        // an abbreviated or mistyped hash can't silently select some
+        // other object
+        let expected_prefix = locked.or(pin.rev.as_deref());
+        if let Some(prefix) = expected_prefix {
+            if !oid.to_string().starts_with(&prefix.to_ascii_lowercase()) {
+                anyhow::bail!("{} resolved {prefix} to unexpected commit {oid}", self.url);
+            }
         }
-        if analysis.is_fast_forward() {
-            log::debug!("{} can fast forward!", self.component);
-            let mut reference = repo.find_reference(&refname).context("find_reference")?;
-            reference
-                .set_target(target_oid, "fast forward")
-                .context("set_target")?;
-            repo.checkout_head(Some(CheckoutBuilder::new().force()))
-                .context("checkout_head")?;
+
+        Ok(Some(oid))
+    }
+
+    /// Detaches the checkout at the specified commit.
+    /// Refuses to move a checkout that has local changes, rather
+    /// than discarding them.
+    fn check_out_commit(&self, repo: &Repository, oid: Oid) -> anyhow::Result<()> {
+        if repo.head().ok().and_then(|head| head.target()) == Some(oid) {
             return Ok(());
         }
-
-        log::debug!("{} will merge", self.component);
-        repo.merge(&[&commit], None, Some(CheckoutBuilder::new().safe()))
-            .context("merge")?;
+        let statuses = repo
+            .statuses(Some(
+                StatusOptions::new()
+                    .include_untracked(false)
+                    .include_ignored(false),
+            ))
+            .context("statuses")?;
+        if !statuses.is_empty() {
+            anyhow::bail!(
+                "{} has local changes in {}; commit or discard them \
+                 so that it can be moved to {oid}",
+                self.url,
+                self.checkout_path().display()
+            );
+        }
+        let commit = repo.find_commit(oid).context("find_commit")?;
+        repo.checkout_tree(commit.as_object(), Some(CheckoutBuilder::new().safe()))
+            .context("checkout_tree")?;
+        repo.set_head_detached(oid).context("set_head_detached")?;
+        log::info!("Checked out {} at {oid}", self.url);
         Ok(())
     }
 
+    /// Ensures that the checkout exists and is at the locked commit,
+    /// or at the pinned revision if there is no lock.  The network
+    /// is only used if the required commit is not already present.
+    /// Returns the commit that was checked out.
+    fn sync(&self, pin: &PluginPin, locked: Option<&str>) -> anyhow::Result<Oid> {
+        if !self.is_checked_out() {
+            self.check_out()?;
+        }
+        let repo = Repository::open(self.checkout_path())?;
+
+        let oid = match self.resolve(&repo, pin, locked)? {
+            Some(oid) => oid,
+            None => {
+                self.fetch(&repo)?;
+                self.resolve(&repo, pin, locked)?
+                    .ok_or_else(|| match locked {
+                        Some(commit) => anyhow!("{} has no commit {commit}", self.url),
+                        None => anyhow!("{} has no {pin}", self.url),
+                    })?
+            }
+        };
+
+        self.check_out_commit(&repo, oid)?;
+        Ok(oid)
+    }
+
+    /// Fetches the latest changes and moves the checkout to the
+    /// newest commit that matches the pin.
+    /// Returns the commit that was checked out.
+    fn update(&self, pin: &PluginPin) -> anyhow::Result<Oid> {
+        if !self.is_checked_out() {
+            return self.sync(pin, None);
+        }
+        let repo = Repository::open(self.checkout_path())?;
+        self.fetch(&repo)?;
+        let oid = self
+            .resolve(&repo, pin, None)?
+            .ok_or_else(|| anyhow!("{} has no {pin}", self.url))?;
+        self.check_out_commit(&repo, oid)?;
+        Ok(oid)
+    }
+
+    /// Fetches the latest changes without moving the checkout.
+    /// Returns the commit that is checked out.
+    fn refresh(&self) -> anyhow::Result<Oid> {
+        let repo = Repository::open(self.checkout_path())?;
+        self.fetch(&repo)?;
+        let head = repo.head().context("head")?;
+        head.target()
+            .ok_or_else(|| anyhow!("{} has no HEAD commit", self.url))
+    }
+
+    fn head_commit(&self) -> Option<String> {
+        let repo = Repository::open(self.checkout_path()).ok()?;
+        let head = repo.head().ok()?;
+        head.target().map(|oid| oid.to_string())
+    }
+
     fn check_out(&self) -> anyhow::Result<()> {
-        let plugins_dir = Self::plugins_dir();
-        std::fs::create_dir_all(&plugins_dir)?;
-        let target_dir = TempDir::new_in(&plugins_dir)?;
+        let plugins_dir = self
+            .plugin_dir
+            .parent()
+            .ok_or_else(|| anyhow!("{:?} has no parent", self.plugin_dir))?;
+        std::fs::create_dir_all(plugins_dir)?;
+        let target_dir = TempDir::new_in(plugins_dir)?;
         log::debug!("Cloning {} into temporary dir {target_dir:?}", self.url);
         Repository::clone_recurse(&self.url, target_dir.path())?;
         let target_dir = target_dir.keep();
@@ -184,11 +359,76 @@ impl RepoSpec {
     }
 }
 
-fn require_plugin(lua: &Lua, url: String) -> anyhow::Result<Value> {
+/// Local directories are used in place rather than being cloned
+fn is_local_path(url: &str) -> bool {
+    !url.contains("://") && Path::new(url).is_absolute()
+}
+
+/// Returns the lockfile for the config that is being evaluated by `lua`,
+/// or None if it isn't loaded from a file
+fn lua_lock_file_path(lua: &Lua) -> anyhow::Result<Option<PathBuf>> {
+    let wezterm_mod = get_or_create_module(lua, "wezterm")?;
+    let config_file: String = wezterm_mod.get("config_file")?;
+    if config_file.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(lock_file_path(Path::new(&config_file))))
+    }
+}
+
+fn require_local_plugin(lua: &Lua, dir: &Path) -> anyhow::Result<Value> {
+    let init = dir.join("plugin").join("init.lua");
+    let init_str = init
+        .to_str()
+        .ok_or_else(|| anyhow!("{init:?} isn't unicode"))?
+        .to_string();
+
+    let package: Table = lua.globals().get("package")?;
+    let loaded: Table = package.get("loaded")?;
+    let value: Value = loaded.get(init_str.as_str())?;
+    if !matches!(value, Value::Nil) {
+        return Ok(value);
+    }
+
+    let wezterm_mod = get_or_create_module(lua, "wezterm")?;
+    let watch: mlua::Function = wezterm_mod.get("add_to_config_reload_watch_list")?;
+    watch.call::<_, ()>(init_str.as_str())?;
+
+    let dofile: mlua::Function = lua.globals().get("dofile")?;
+    let value: Value = dofile
+        .call(init_str.as_str())
+        .with_context(|| format!("loading plugin from {}", dir.display()))?;
+    loaded.set(init_str.as_str(), value.clone())?;
+    Ok(value)
+}
+
+fn require_plugin(lua: &Lua, url: String, pin: PluginPin) -> anyhow::Result<Value> {
+    pin.validate()?;
+    if is_local_path(&url) {
+        return require_local_plugin(lua, Path::new(&url));
+    }
+
     let spec = RepoSpec::parse(url)?;
 
-    if !spec.is_checked_out() {
-        spec.check_out()?;
+    let lock_path = lua_lock_file_path(lua)?;
+    let mut lock = match &lock_path {
+        Some(path) => LockFile::load(path)?,
+        None => LockFile::default(),
+    };
+    // A lock entry recorded for a different pin is stale
+    let locked = lock
+        .find(&spec.url)
+        .filter(|entry| entry.pin() == pin)
+        .map(|entry| entry.commit.clone());
+
+    let commit = spec.sync(&pin, locked.as_deref())?;
+
+    if let Some(path) = &lock_path {
+        if lock.record(&spec.url, &pin, &commit.to_string()) {
+            if let Err(err) = lock.save(path) {
+                log::warn!("Unable to record {} in lockfile: {err:#}", spec.url);
+            }
+        }
     }
 
     let require: mlua::Function = lua.globals().get("require")?;
@@ -206,10 +446,13 @@ fn require_plugin(lua: &Lua, url: String) -> anyhow::Result<Value> {
 }
 
 fn list_plugins() -> anyhow::Result<Vec<RepoSpec>> {
+    list_plugins_in(&RepoSpec::plugins_dir())
+}
+
+fn list_plugins_in(plugins_dir: &Path) -> anyhow::Result<Vec<RepoSpec>> {
     let mut plugins = vec![];
 
-    let plugins_dir = RepoSpec::plugins_dir();
-    std::fs::create_dir_all(&plugins_dir)?;
+    std::fs::create_dir_all(plugins_dir)?;
 
     for entry in plugins_dir.read_dir()? {
         let entry = entry?;
@@ -221,12 +464,156 @@ fn list_plugins() -> anyhow::Result<Vec<RepoSpec>> {
     Ok(plugins)
 }
 
+/// Describes a plugin that is either checked out, recorded in the
+/// lockfile, or both
+#[derive(Debug, Clone)]
+pub struct PluginStatus {
+    pub url: String,
+    pub plugin_dir: PathBuf,
+    pub pin: PluginPin,
+    /// The commit recorded in the lockfile
+    pub locked: Option<String>,
+    /// The commit that is currently checked out
+    pub checked_out: Option<String>,
+}
+
+/// Returns the status of the plugins that are checked out together
+/// with those recorded in the lockfile, ordered by url
+pub fn plugin_status(lock_path: Option<&Path>) -> anyhow::Result<Vec<PluginStatus>> {
+    plugin_status_in(lock_path, &RepoSpec::plugins_dir())
+}
+
+fn plugin_status_in(
+    lock_path: Option<&Path>,
+    plugins_dir: &Path,
+) -> anyhow::Result<Vec<PluginStatus>> {
+    let lock = match lock_path {
+        Some(path) => LockFile::load(path)?,
+        None => LockFile::default(),
+    };
+
+    let mut urls: Vec<String> = list_plugins_in(plugins_dir)?
+        .into_iter()
+        .map(|spec| spec.url)
+        .collect();
+    for entry in &lock.plugins {
+        if !urls.contains(&entry.url) {
+            urls.push(entry.url.clone());
+        }
+    }
+    urls.sort();
+
+    urls.into_iter()
+        .map(|url| -> anyhow::Result<PluginStatus> {
+            let spec = RepoSpec::parse_in(url, plugins_dir)?;
+            let entry = lock.find(&spec.url);
+            Ok(PluginStatus {
+                pin: entry.map(|entry| entry.pin()).unwrap_or_default(),
+                locked: entry.map(|entry| entry.commit.clone()),
+                checked_out: spec.head_commit(),
+                plugin_dir: spec.checkout_path(),
+                url: spec.url,
+            })
+        })
+        .collect()
+}
+
+/// Updates the specified plugins, or all of them if `urls` is empty,
+/// to the newest commit that matches their pin, recording the new
+/// commits in the lockfile.
+/// Plugins that are not in the lockfile may have been installed by
+/// some other config with some other pin, so they are only fetched;
+/// their checkout is moved when a config that requires them is loaded.
+/// Returns the outcome for each plugin, which is the commit that is
+/// checked out.
+pub fn update_plugins(
+    lock_path: Option<&Path>,
+    urls: &[String],
+) -> anyhow::Result<Vec<(String, anyhow::Result<String>)>> {
+    update_plugins_in(lock_path, urls, &RepoSpec::plugins_dir())
+}
+
+fn update_plugins_in(
+    lock_path: Option<&Path>,
+    urls: &[String],
+    plugins_dir: &Path,
+) -> anyhow::Result<Vec<(String, anyhow::Result<String>)>> {
+    let plugins = plugin_status_in(lock_path, plugins_dir)?;
+    for url in urls {
+        if !plugins.iter().any(|p| &p.url == url) {
+            anyhow::bail!("{url} is not a known plugin");
+        }
+    }
+
+    let mut lock = match lock_path {
+        Some(path) => LockFile::load(path)?,
+        None => LockFile::default(),
+    };
+
+    let mut results = vec![];
+    for plugin in plugins {
+        if !urls.is_empty() && !urls.contains(&plugin.url) {
+            continue;
+        }
+        let result = RepoSpec::parse_in(plugin.url.clone(), plugins_dir)
+            .and_then(|spec| {
+                if plugin.locked.is_some() {
+                    spec.update(&plugin.pin)
+                } else {
+                    spec.refresh()
+                }
+            })
+            .map(|oid| oid.to_string());
+        if let Ok(commit) = &result {
+            if plugin.locked.is_some() {
+                lock.record(&plugin.url, &plugin.pin, commit);
+            }
+        }
+        results.push((plugin.url, result));
+    }
+
+    if let Some(path) = lock_path {
+        if lock != LockFile::load(path)? {
+            lock.save(path)?;
+        }
+    }
+
+    Ok(results)
+}
+
+/// Removes the checkout of a plugin and its entry in the lockfile
+pub fn remove_plugin(lock_path: Option<&Path>, url: &str) -> anyhow::Result<()> {
+    let spec = RepoSpec::parse(url.to_string())?;
+    let mut found = false;
+
+    if spec.is_checked_out() {
+        let path = spec.checkout_path();
+        std::fs::remove_dir_all(&path).with_context(|| format!("removing {}", path.display()))?;
+        found = true;
+    }
+
+    if let Some(path) = lock_path {
+        let mut lock = LockFile::load(path)?;
+        if lock.remove(url) {
+            lock.save(path)?;
+            found = true;
+        }
+    }
+
+    if !found {
+        anyhow::bail!("{url} is not a known plugin");
+    }
+    Ok(())
+}
+
 pub fn register(lua: &Lua) -> anyhow::Result<()> {
     let plugin_mod = get_or_create_sub_module(lua, "plugin")?;
     plugin_mod.set(
         "require",
-        lua.create_function(|lua: &Lua, repo_spec: String| {
-            require_plugin(lua, repo_spec).map_err(|e| mlua::Error::external(format!("{e:#}")))
+        lua.create_function(|lua: &Lua, (repo_spec, pin): (String, Value)| {
+            let pin: Option<PluginPin> = from_lua(pin)?;
+            require_plugin(lua, repo_spec, pin.unwrap_or_default())
+                .map_err(|e| mlua::Error::external(format!("{e:#}")))
         })?,
     )?;
 
@@ -240,12 +627,15 @@ pub fn register(lua: &Lua) -> anyhow::Result<()> {
 
     plugin_mod.set(
         "update_all",
-        lua.create_function(|_, _: ()| {
-            let plugins = list_plugins().map_err(|e| mlua::Error::external(format!("{e:#}")))?;
-            for p in plugins {
-                match p.update() {
-                    Ok(_) => log::info!("Updated {p:?}"),
-                    Err(err) => log::error!("Failed to update {p:?}: {err:#}"),
+        lua.create_function(|lua, _: ()| {
+            let lock_path =
+                lua_lock_file_path(lua).map_err(|e| mlua::Error::external(format!("{e:#}")))?;
+            let results = update_plugins(lock_path.as_deref(), &[])
+                .map_err(|e| mlua::Error::external(format!("{e:#}")))?;
+            for (url, result) in results {
+                match result {
+                    Ok(commit) => log::info!("Updated {url} to {commit}"),
+                    Err(err) => log::error!("Failed to update {url}: {err:#}"),
                 }
             }
             Ok(())
@@ -272,4 +662,134 @@ mod test {
             assert_eq!(&result, expect, "for input {input}");
         }
     }
+
+    /// Commits `content` as the plugin of `repo`, returning the commit
+    fn commit(repo: &Repository, content: &str) -> Oid {
+        let dir = repo.workdir().unwrap().join("plugin");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("init.lua"), content).unwrap();
+
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("plugin/init.lua")).unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let sig = git2::Signature::now("wezterm", "wezterm@example.com").unwrap();
+        let parent = repo.head().ok().map(|head| head.peel_to_commit().unwrap());
+        let parents: Vec<&git2::Commit> = parent.iter().collect();
+        repo.commit(Some("HEAD"), &sig, &sig, content, &tree, &parents)
+            .unwrap()
+    }
+
+    fn head(spec: &RepoSpec) -> String {
+        spec.head_commit().unwrap()
+    }
+
+    #[test]
+    fn sync_and_update() {
+        let origin_dir = tempfile::tempdir().unwrap();
+        let origin = Repository::init(origin_dir.path()).unwrap();
+        let first = commit(&origin, "return 1");
+        origin
+            .tag_lightweight("v1", &origin.find_object(first, None).unwrap(), false)
+            .unwrap();
+        let second = commit(&origin, "return 2");
+
+        let plugins_dir = tempfile::tempdir().unwrap();
+        let url = format!("file://{}", origin_dir.path().display());
+        let spec = RepoSpec::parse_in(url, plugins_dir.path()).unwrap();
+
+        let tag = PluginPin {
+            tag: Some("v1".to_string()),
+            ..Default::default()
+        };
+        let default = PluginPin::default();
+
+        // Cloned, then moved to the pin
+        assert_eq!(spec.sync(&tag, None).unwrap(), first);
+        assert_eq!(head(&spec), first.to_string());
+        assert_eq!(spec.sync(&default, None).unwrap(), second);
+
+        // An abbreviated rev is resolved, and checked against what it
+        // resolves to
+        let rev = PluginPin {
+            rev: Some(first.to_string()[0..10].to_string()),
+            ..Default::default()
+        };
+        assert_eq!(spec.sync(&rev, None).unwrap(), first);
+
+        // The locked commit takes precedence over the pin
+        let locked = first.to_string();
+        assert_eq!(spec.sync(&default, Some(&locked)).unwrap(), first);
+        assert!(spec
+            .sync(&default, Some("0123456789012345678901234567890123456789"))
+            .is_err());
+
+        // New commits aren't fetched while the pin can be resolved
+        // from what is already present, but are picked up by update
+        let third = commit(&origin, "return 3");
+        assert_eq!(spec.sync(&default, None).unwrap(), second);
+        assert_eq!(spec.update(&default).unwrap(), third);
+        assert_eq!(spec.update(&tag).unwrap(), first);
+
+        // Local changes to the checkout are not discarded
+        let init = spec.checkout_path().join("plugin").join("init.lua");
+        std::fs::write(&init, "return 'local'").unwrap();
+        assert!(spec.sync(&default, None).is_err());
+        assert_eq!(head(&spec), first.to_string());
+        assert_eq!(std::fs::read_to_string(&init).unwrap(), "return 'local'");
+        // but it can still be used at the commit that it is at
+        assert_eq!(spec.sync(&tag, None).unwrap(), first);
+    }
+
+    #[test]
+    fn update_leaves_unlocked_plugins_in_place() {
+        let origin_dir = tempfile::tempdir().unwrap();
+        let origin = Repository::init(origin_dir.path()).unwrap();
+        let first = commit(&origin, "return 1");
+        origin
+            .tag_lightweight("v1", &origin.find_object(first, None).unwrap(), false)
+            .unwrap();
+        commit(&origin, "return 2");
+
+        let plugins_dir = tempfile::tempdir().unwrap();
+        let url = format!("file://{}", origin_dir.path().display());
+        let spec = RepoSpec::parse_in(url.clone(), plugins_dir.path()).unwrap();
+        let tag = PluginPin {
+            tag: Some("v1".to_string()),
+            ..Default::default()
+        };
+        spec.sync(&tag, None).unwrap();
+        let third = commit(&origin, "return 3");
+
+        // Some other config installed the plugin at its tag, so its
+        // checkout is left alone, but the new commits are fetched
+        let lock_dir = tempfile::tempdir().unwrap();
+        let lock_path = lock_dir.path().join("wezterm.lock");
+        let results = update_plugins_in(Some(&lock_path), &[], plugins_dir.path()).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].1.as_ref().unwrap(), &first.to_string());
+        assert_eq!(head(&spec), first.to_string());
+        assert!(LockFile::load(&lock_path).unwrap().plugins.is_empty());
+        let repo = Repository::open(spec.checkout_path()).unwrap();
+        assert_eq!(
+            spec.resolve(&repo, &PluginPin::default(), None).unwrap(),
+            Some(third)
+        );
+
+        // Once it is locked, it is moved to the newest commit
+        let mut lock = LockFile::default();
+        lock.record(&url, &PluginPin::default(), &first.to_string());
+        lock.save(&lock_path).unwrap();
+        let results = update_plugins_in(Some(&lock_path), &[], plugins_dir.path()).unwrap();
+        assert_eq!(results[0].1.as_ref().unwrap(), &third.to_string());
+        assert_eq!(head(&spec), third.to_string());
+        assert_eq!(
+            LockFile::load(&lock_path)
+                .unwrap()
+                .find(&url)
+                .unwrap()
+                .commit,
+            third.to_string()
+        );
+    }
 }
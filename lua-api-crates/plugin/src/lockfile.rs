@@ -0,0 +1,164 @@
+use crate::PluginPin;
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const HEADER: &str = "\
+# This file is maintained by wezterm and records the exact revision of
+# each plugin used by the configuration. Commit it alongside the
+# configuration so that other machines use the same plugin revisions.
+# Run `wezterm plugin update` to move the plugins to newer revisions.
+";
+
+/// Returns the location of the lockfile for the specified config file.
+/// It lives alongside the config file and shares its name, so
+/// `wezterm.lua` is paired with `wezterm.lock`.
+pub fn lock_file_path(config_file: &Path) -> PathBuf {
+    config_file.with_extension("lock")
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct LockedPlugin {
+    pub url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub branch: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rev: Option<String>,
+    /// The full hash of the commit that the pin resolved to
+    pub commit: String,
+}
+
+impl LockedPlugin {
+    pub fn pin(&self) -> PluginPin {
+        PluginPin {
+            tag: self.tag.clone(),
+            branch: self.branch.clone(),
+            rev: self.rev.clone(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct LockFile {
+    #[serde(default, rename = "plugin")]
+    pub plugins: Vec<LockedPlugin>,
+}
+
+impl LockFile {
+    /// Loads the lockfile, returning an empty lockfile if it doesn't exist
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        match std::fs::read_to_string(path) {
+            Ok(data) => {
+                toml::from_str(&data).with_context(|| format!("parsing {}", path.display()))
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err).with_context(|| format!("reading {}", path.display())),
+        }
+    }
+
+    /// Saves the lockfile, replacing the file atomically so that
+    /// a concurrent reader never observes a partial file
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let data = format!("{HEADER}\n{}", toml::to_string(self)?);
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let mut file = tempfile::NamedTempFile::new_in(dir)
+            .with_context(|| format!("creating temporary file in {}", dir.display()))?;
+        file.write_all(data.as_bytes())?;
+        file.persist(path)
+            .with_context(|| format!("saving {}", path.display()))?;
+        Ok(())
+    }
+
+    pub fn find(&self, url: &str) -> Option<&LockedPlugin> {
+        self.plugins.iter().find(|p| p.url == url)
+    }
+
+    /// Records the commit used for a plugin.
+    /// Returns true if the lockfile was changed.
+    pub fn record(&mut self, url: &str, pin: &PluginPin, commit: &str) -> bool {
+        let entry = LockedPlugin {
+            url: url.to_string(),
+            tag: pin.tag.clone(),
+            branch: pin.branch.clone(),
+            rev: pin.rev.clone(),
+            commit: commit.to_string(),
+        };
+        if let Some(existing) = self.plugins.iter_mut().find(|p| p.url == url) {
+            if *existing == entry {
+                return false;
+            }
+            *existing = entry;
+        } else {
+            self.plugins.push(entry);
+            // Keep the file in a stable order so that it diffs nicely
+            self.plugins.sort_by(|a, b| a.url.cmp(&b.url));
+        }
+        true
+    }
+
+    /// Removes the entry for a plugin.
+    /// Returns true if the lockfile was changed.
+    pub fn remove(&mut self, url: &str) -> bool {
+        let len = self.plugins.len();
+        self.plugins.retain(|p| p.url != url);
+        self.plugins.len() != len
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn record_and_round_trip() {
+        let mut lock = LockFile::default();
+        let tagged = PluginPin {
+            tag: Some("v1.0".to_string()),
+            ..Default::default()
+        };
+        assert!(lock.record("https://example.com/b", &tagged, "bbbb"));
+        assert!(lock.record("https://example.com/a", &PluginPin::default(), "aaaa"));
+        assert!(!lock.record("https://example.com/b", &tagged, "bbbb"));
+        assert!(lock.record("https://example.com/b", &tagged, "cccc"));
+
+        let text = toml::to_string(&lock).unwrap();
+        assert_eq!(
+            text,
+            "[[plugin]]\n\
+             url = \"https://example.com/a\"\n\
+             commit = \"aaaa\"\n\
+             \n\
+             [[plugin]]\n\
+             url = \"https://example.com/b\"\n\
+             tag = \"v1.0\"\n\
+             commit = \"cccc\"\n"
+        );
+
+        let parsed: LockFile = toml::from_str(&format!("{HEADER}\n{text}")).unwrap();
+        assert_eq!(parsed, lock);
+        assert_eq!(parsed.find("https://example.com/b").unwrap().pin(), tagged);
+
+        assert!(lock.remove("https://example.com/a"));
+        assert!(!lock.remove("https://example.com/a"));
+        assert_eq!(lock.plugins.len(), 1);
+    }
+
+    #[test]
+    fn save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = lock_file_path(&dir.path().join("wezterm.lua"));
+        assert_eq!(path.file_name().unwrap(), "wezterm.lock");
+        assert_eq!(LockFile::load(&path).unwrap(), LockFile::default());
+
+        let mut lock = LockFile::default();
+        lock.record("file:///plugin", &PluginPin::default(), "abcd");
+        lock.save(&path).unwrap();
+        assert_eq!(LockFile::load(&path).unwrap(), lock);
+    }
+}
@@ -80,7 +80,10 @@ fn toml_decode(lua: &Lua, text: String) -> mlua::Result<LuaValue> {
     json_value_to_lua_value(lua, value)
 }
 
-fn json_value_to_lua_value<'lua>(lua: &'lua Lua, value: JValue) -> mlua::Result<LuaValue<'lua>> {
+pub fn json_value_to_lua_value<'lua>(
+    lua: &'lua Lua,
+    value: JValue,
+) -> mlua::Result<LuaValue<'lua>> {
     Ok(match value {
         JValue::Null => LuaValue::Nil,
         JValue::Bool(b) => LuaValue::Boolean(b),
@@ -145,7 +148,10 @@ fn dyn_to_json(value: DynValue) -> anyhow::Result<JValue> {
     })
 }
 
-fn lua_value_to_json_value(value: LuaValue, visited: &mut HashSet<usize>) -> mlua::Result<JValue> {
+pub fn lua_value_to_json_value(
+    value: LuaValue,
+    visited: &mut HashSet<usize>,
+) -> mlua::Result<JValue> {
     if let LuaValue::Table(_) = &value {
         let ptr = value.to_pointer() as usize;
         if visited.contains(&ptr) {
@@ -1,6 +1,10 @@
 use bstr::BString;
-use config::lua::get_or_create_module;
-use config::lua::mlua::{self, Lua};
+use config::lua::mlua::{self, Lua, MetaMethod, Table, UserData, UserDataMethods};
+use config::lua::{emit_event, get_or_create_module, wrap_callback};
+use smol::prelude::*;
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::Duration;
 
 pub fn register(lua: &Lua) -> anyhow::Result<()> {
     let wezterm_mod = get_or_create_module(lua, "wezterm")?;
@@ -50,7 +54,154 @@ async fn run_child_process<'lua>(
     ))
 }
 
-async fn background_child_process<'lua>(_: &'lua Lua, args: Vec<String>) -> mlua::Result<()> {
+/// How often, and how many times, to look for the lua context that
+/// registered the callbacks of a child process before giving up
+const CONTEXT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+const CONTEXT_POLL_ATTEMPTS: usize = 100;
+
+/// How the current lua context relates to the one that registered
+/// the callbacks of a child process
+#[derive(Debug, PartialEq, Eq)]
+enum ContextState {
+    /// It is the current context
+    Current,
+    /// It hasn't become current yet; this happens when the child is
+    /// spawned while the config is being evaluated
+    Pending,
+    /// It was replaced before it could be found
+    Replaced,
+}
+
+impl ContextState {
+    fn new(current_id: Option<usize>, context_id: usize) -> Self {
+        match current_id {
+            Some(id) if id == context_id => Self::Current,
+            Some(id) if id > context_id => Self::Replaced,
+            _ => Self::Pending,
+        }
+    }
+}
+
+/// Keeps track of the callbacks passed to `background_child_process`
+struct ChildCallbacks {
+    /// The registry entries that resolve to their callback functions
+    on_exit: Option<String>,
+    on_stdout: Option<String>,
+    /// Identifies the lua context that registered the callbacks,
+    /// which is the only one that can call them
+    context_id: Option<usize>,
+    /// That lua context, once it has been found.  Holding on to it
+    /// keeps the callbacks working if the config is reloaded while
+    /// the child is still running.
+    lua: RefCell<Option<Rc<Lua>>>,
+}
+
+impl ChildCallbacks {
+    /// Returns the lua context that registered the callbacks.
+    /// If the child was spawned while the config was being evaluated,
+    /// that context only becomes current once evaluation completes,
+    /// so wait for that to happen.
+    async fn lua(&self) -> Option<Rc<Lua>> {
+        if let Some(lua) = self.lua.borrow().as_ref() {
+            return Some(Rc::clone(lua));
+        }
+        let context_id = self.context_id?;
+        for _ in 0..CONTEXT_POLL_ATTEMPTS {
+            let lua = config::with_lua_config_on_main_thread(|lua| async move { Ok(lua) })
+                .await
+                .ok()
+                .flatten();
+            let current_id = lua
+                .as_ref()
+                .and_then(|lua| config::lua::lua_context_id(lua));
+            match ContextState::new(current_id, context_id) {
+                ContextState::Current => {
+                    self.lua.borrow_mut().clone_from(&lua);
+                    return lua;
+                }
+                ContextState::Replaced => return None,
+                ContextState::Pending => {
+                    smol::Timer::after(CONTEXT_POLL_INTERVAL).await;
+                }
+            }
+        }
+        None
+    }
+
+    /// Calls one of the callbacks on the main thread.
+    /// Errors are logged rather than propagated so that a failing
+    /// callback doesn't stop us from draining the output of the child.
+    async fn emit<T>(&self, user_event_id: &Option<String>, args: T)
+    where
+        T: for<'lua> mlua::IntoLuaMulti<'lua> + 'static,
+    {
+        let user_event_id = match user_event_id {
+            Some(id) => id.clone(),
+            None => return,
+        };
+        let lua = match self.lua().await {
+            Some(lua) => lua,
+            None => {
+                log::debug!(
+                    "background_child_process: skipping {} as the config \
+                     that registered it is no longer loaded",
+                    user_event_id
+                );
+                return;
+            }
+        };
+        let result: mlua::Result<bool> = async {
+            let args = lua.pack_multi(args)?;
+            emit_event(&lua, (user_event_id, args)).await
+        }
+        .await;
+        if let Err(err) = result {
+            log::error!("background_child_process callback: {:#}", err);
+        }
+    }
+
+    async fn monitor(self, mut child: smol::process::Child) -> anyhow::Result<()> {
+        if let Some(stdout) = child.stdout.take() {
+            let mut reader = smol::io::BufReader::new(stdout);
+            let mut line = vec![];
+            while reader.read_until(b'\n', &mut line).await? > 0 {
+                if line.last() == Some(&b'\n') {
+                    line.pop();
+                    if line.last() == Some(&b'\r') {
+                        line.pop();
+                    }
+                }
+                let text = String::from_utf8_lossy(&line).into_owned();
+                self.emit(&self.on_stdout, text).await;
+                line.clear();
+            }
+        }
+        let status = child.status().await?;
+        self.emit(&self.on_exit, (status.success(), status.code()))
+            .await;
+        Ok(())
+    }
+}
+
+/// The handle returned from `background_child_process`
+#[derive(Clone, Copy, Debug)]
+struct ChildProcess {
+    pid: u32,
+}
+
+impl UserData for ChildProcess {
+    fn add_methods<'lua, M: UserDataMethods<'lua, Self>>(methods: &mut M) {
+        methods.add_method("pid", |_, this, _: ()| Ok(this.pid));
+        methods.add_meta_method(MetaMethod::ToString, |_, this, _: ()| {
+            Ok(format!("ChildProcess(pid={})", this.pid))
+        });
+    }
+}
+
+async fn background_child_process<'lua>(
+    lua: &'lua Lua,
+    (args, opts): (Vec<String>, Option<Table<'lua>>),
+) -> mlua::Result<ChildProcess> {
     let mut cmd = smol::process::Command::new(&args[0]);
 
     if args.len() > 1 {
@@ -63,9 +214,54 @@ async fn background_child_process<'lua>(_: &'lua Lua, args: Vec<String>) -> mlua
         cmd.creation_flags(winapi::um::winbase::CREATE_NO_WINDOW);
     }
 
-    cmd.stdin(smol::process::Stdio::null())
-        .spawn()
-        .map_err(mlua::Error::external)?;
+    let mut on_exit = None;
+    let mut on_stdout = None;
+    if let Some(opts) = opts {
+        if let Some(func) = opts.get::<_, Option<mlua::Function>>("on_exit")? {
+            on_exit.replace(wrap_callback(lua, func)?);
+        }
+        if let Some(func) = opts.get::<_, Option<mlua::Function>>("on_stdout")? {
+            on_stdout.replace(wrap_callback(lua, func)?);
+        }
+    }
 
-    Ok(())
+    cmd.stdin(smol::process::Stdio::null());
+    if on_stdout.is_some() {
+        cmd.stdout(smol::process::Stdio::piped());
+    }
+
+    let child = cmd.spawn().map_err(mlua::Error::external)?;
+    let handle = ChildProcess { pid: child.id() };
+
+    if on_exit.is_some() || on_stdout.is_some() {
+        let callbacks = ChildCallbacks {
+            on_exit,
+            on_stdout,
+            context_id: config::lua::lua_context_id(lua),
+            lua: RefCell::new(None),
+        };
+        promise::spawn::spawn(async move {
+            if let Err(err) = callbacks.monitor(child).await {
+                log::error!("background_child_process pid {}: {:#}", handle.pid, err);
+            }
+        })
+        .detach();
+    }
+
+    Ok(handle)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn context_state() {
+        assert_eq!(ContextState::new(Some(3), 3), ContextState::Current);
+        // Spawned while evaluating a newer config
+        assert_eq!(ContextState::new(Some(2), 3), ContextState::Pending);
+        assert_eq!(ContextState::new(None, 3), ContextState::Pending);
+        // The config was reloaded before the first callback
+        assert_eq!(ContextState::new(Some(4), 3), ContextState::Replaced);
+    }
 }
@@ -0,0 +1,525 @@
+//! Renders lines from a terminal as standalone HTML and SVG, preserving
+//! their colors, styles and hyperlinks
+use crate::lines_to_escapes;
+use config::keyassignment::ExportFormat;
+use config::ConfigHandle;
+use std::fmt::Write;
+use termwiz::cell::{CellAttributes, Intensity, Underline};
+use termwiz::color::{ColorAttribute, SrgbaTuple};
+use termwiz::surface::Line;
+use wezterm_term::color::ColorPalette;
+
+/// Controls the presentation of the exported text
+#[derive(Debug, Clone)]
+pub struct ExportOptions {
+    /// The CSS font-family list to use for the text
+    pub font_family: String,
+    /// The size of the text, in pixels
+    pub font_size: f64,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            font_family: "monospace".to_string(),
+            font_size: 16.,
+        }
+    }
+}
+
+impl ExportOptions {
+    /// Uses the primary font and font size from the configuration
+    pub fn from_config(config: &ConfigHandle) -> Self {
+        let mut font_family = String::new();
+        for attr in &config.font.font {
+            write!(font_family, "'{}', ", attr.family.replace('\'', "")).ok();
+        }
+        font_family.push_str("monospace");
+        Self {
+            font_family,
+            font_size: config.font_size * 96. / 72.,
+        }
+    }
+
+    fn cell_width(&self) -> f64 {
+        self.font_size * 0.6
+    }
+
+    fn cell_height(&self) -> f64 {
+        self.font_size * 1.2
+    }
+}
+
+/// Renders the lines in the requested format, using the colors of
+/// `palette` and the font from the configuration.
+/// Html and Svg produce complete documents.
+pub fn export_lines(
+    lines: Vec<Line>,
+    format: ExportFormat,
+    palette: &ColorPalette,
+) -> anyhow::Result<String> {
+    let options = ExportOptions::from_config(&config::configuration());
+    match format {
+        ExportFormat::Text => Ok(lines_to_text(&lines)),
+        ExportFormat::Ansi => lines_to_escapes(lines),
+        ExportFormat::Html => Ok(lines_to_html(&lines, palette, &options)),
+        ExportFormat::Svg => Ok(lines_to_svg(&lines, palette, &options)),
+    }
+}
+
+fn lines_to_text(lines: &[Line]) -> String {
+    let mut text = String::new();
+    for line in lines {
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text.push_str(line.as_str().trim_end());
+    }
+    text
+}
+
+/// The resolved appearance of a run of cells
+#[derive(Debug, Clone, PartialEq)]
+struct Style {
+    fg: SrgbaTuple,
+    bg: SrgbaTuple,
+    bold: bool,
+    italic: bool,
+    underline: Underline,
+    underline_color: SrgbaTuple,
+    strikethrough: bool,
+    overline: bool,
+    invisible: bool,
+    link: Option<String>,
+}
+
+impl Style {
+    fn new(attrs: &CellAttributes, palette: &ColorPalette) -> Self {
+        let mut fg = palette.resolve_fg(attrs.foreground());
+        let mut bg = palette.resolve_bg(attrs.background());
+        if attrs.reverse() {
+            std::mem::swap(&mut fg, &mut bg);
+        }
+        if attrs.intensity() == Intensity::Half {
+            fg = fg.interpolate(bg, 0.5);
+        }
+        let underline_color = match attrs.underline_color() {
+            ColorAttribute::Default => fg,
+            color => palette.resolve_fg(color),
+        };
+        Self {
+            fg,
+            bg,
+            bold: attrs.intensity() == Intensity::Bold,
+            italic: attrs.italic(),
+            underline: attrs.underline(),
+            underline_color,
+            strikethrough: attrs.strikethrough(),
+            overline: attrs.overline(),
+            invisible: attrs.invisible(),
+            link: attrs
+                .hyperlink()
+                .and_then(|link| exportable_link(link.uri())),
+        }
+    }
+
+    fn decoration_lines(&self) -> Vec<&'static str> {
+        let mut lines = vec![];
+        if self.underline != Underline::None {
+            lines.push("underline");
+        }
+        if self.strikethrough {
+            lines.push("line-through");
+        }
+        if self.overline {
+            lines.push("overline");
+        }
+        lines
+    }
+
+    fn css(&self, palette: &ColorPalette) -> String {
+        let mut css = String::new();
+        if self.fg != palette.foreground || self.link.is_some() {
+            write!(css, "color:{};", self.fg.to_rgb_string()).ok();
+        }
+        if self.bg != palette.background {
+            write!(css, "background-color:{};", self.bg.to_rgb_string()).ok();
+        }
+        if self.bold {
+            css.push_str("font-weight:bold;");
+        }
+        if self.italic {
+            css.push_str("font-style:italic;");
+        }
+        let lines = self.decoration_lines();
+        if lines.is_empty() {
+            if self.link.is_some() {
+                css.push_str("text-decoration:none;");
+            }
+        } else {
+            write!(css, "text-decoration-line:{};", lines.join(" ")).ok();
+            let style = match self.underline {
+                Underline::Double => "double",
+                Underline::Curly => "wavy",
+                Underline::Dotted => "dotted",
+                Underline::Dashed => "dashed",
+                Underline::None | Underline::Single => "solid",
+            };
+            write!(css, "text-decoration-style:{style};").ok();
+            if self.underline_color != self.fg {
+                write!(
+                    css,
+                    "text-decoration-color:{};",
+                    self.underline_color.to_rgb_string()
+                )
+                .ok();
+            }
+        }
+        if self.invisible {
+            css.push_str("visibility:hidden;");
+        }
+        css
+    }
+}
+
+/// A sequence of cells that share the same style
+struct Run {
+    /// The column at which the run starts
+    col: usize,
+    /// The number of columns occupied by the run
+    width: usize,
+    text: String,
+    style: Style,
+    /// The run consists of a single double-width grapheme
+    wide: bool,
+}
+
+fn line_runs(line: &Line, palette: &ColorPalette) -> Vec<Run> {
+    let mut runs: Vec<Run> = vec![];
+    for cell in line.visible_cells() {
+        let style = Style::new(cell.attrs(), palette);
+        let wide = cell.width() > 1;
+        match runs.last_mut() {
+            Some(run)
+                if !wide
+                    && !run.wide
+                    && run.style == style
+                    && run.col + run.width == cell.cell_index() =>
+            {
+                run.text.push_str(cell.str());
+                run.width += cell.width();
+            }
+            _ => runs.push(Run {
+                col: cell.cell_index(),
+                width: cell.width(),
+                text: cell.str().to_string(),
+                style,
+                wide,
+            }),
+        }
+    }
+
+    // Trailing blanks are just padding, unless they are colored
+    while let Some(run) = runs.last_mut() {
+        if run.style.bg != palette.background || run.style.link.is_some() {
+            break;
+        }
+        let trimmed = run.text.trim_end_matches(' ');
+        if !trimmed.is_empty() {
+            let removed = run.text.len() - trimmed.len();
+            run.text.truncate(trimmed.len());
+            run.width -= removed;
+            break;
+        }
+        runs.pop();
+    }
+    runs
+}
+
+/// The schemes of the hyperlinks that are carried over into the
+/// exported document; any other link, such as a `javascript:` url
+/// that a program wrote into the terminal, is exported as plain text
+const EXPORTABLE_LINK_SCHEMES: &[&str] = &["http", "https", "file", "mailto"];
+
+fn exportable_link(uri: &str) -> Option<String> {
+    // Browsers ignore control characters when parsing the scheme,
+    // so don't try to reason about links that contain them
+    if uri.chars().any(|c| c.is_control()) {
+        return None;
+    }
+    let (scheme, _) = uri.split_once(':')?;
+    if EXPORTABLE_LINK_SCHEMES
+        .iter()
+        .any(|allowed| scheme.eq_ignore_ascii_case(allowed))
+    {
+        Some(uri.to_string())
+    } else {
+        None
+    }
+}
+
+fn escape_xml(s: &str, out: &mut String) {
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+}
+
+/// Formats a length without a long tail of decimal places
+fn px(value: f64) -> String {
+    let s = format!("{value:.2}");
+    s.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// Renders the lines as a `<pre>` element with inline styles,
+/// suitable for pasting into a rich text document
+pub fn lines_to_html_fragment(
+    lines: &[Line],
+    palette: &ColorPalette,
+    options: &ExportOptions,
+) -> String {
+    let mut html = String::new();
+    write!(
+        html,
+        "<pre style=\"font-family:{};font-size:{}px;line-height:1.2;\
+         color:{};background-color:{};padding:0.5em;\">",
+        options.font_family.replace('"', ""),
+        px(options.font_size),
+        palette.foreground.to_rgb_string(),
+        palette.background.to_rgb_string()
+    )
+    .ok();
+
+    for (idx, line) in lines.iter().enumerate() {
+        if idx > 0 {
+            html.push('\n');
+        }
+        for run in line_runs(line, palette) {
+            let mut css = run.style.css(palette);
+            if run.wide {
+                write!(css, "display:inline-block;width:{}ch;", run.width).ok();
+            }
+            let tag = if run.style.link.is_some() {
+                "a"
+            } else {
+                "span"
+            };
+            if css.is_empty() && tag == "span" {
+                escape_xml(&run.text, &mut html);
+                continue;
+            }
+            write!(html, "<{tag}").ok();
+            if let Some(link) = &run.style.link {
+                html.push_str(" href=\"");
+                escape_xml(link, &mut html);
+                html.push('"');
+            }
+            write!(html, " style=\"{css}\">").ok();
+            escape_xml(&run.text, &mut html);
+            write!(html, "</{tag}>").ok();
+        }
+    }
+
+    html.push_str("</pre>");
+    html
+}
+
+/// Renders the lines as a complete HTML document
+pub fn lines_to_html(lines: &[Line], palette: &ColorPalette, options: &ExportOptions) -> String {
+    format!(
+        "<!DOCTYPE html>\n\
+         <html>\n\
+         <head>\n\
+         <meta charset=\"utf-8\">\n\
+         <meta name=\"generator\" content=\"wezterm\">\n\
+         <title>wezterm</title>\n\
+         </head>\n\
+         <body style=\"margin:0;background-color:{}\">\n\
+         {}\n\
+         </body>\n\
+         </html>\n",
+        palette.background.to_rgb_string(),
+        lines_to_html_fragment(lines, palette, options)
+    )
+}
+
+/// Renders the lines as an SVG image, with each run of text
+/// positioned on the cell grid
+pub fn lines_to_svg(lines: &[Line], palette: &ColorPalette, options: &ExportOptions) -> String {
+    let cell_width = options.cell_width();
+    let cell_height = options.cell_height();
+    let padding = cell_width;
+    let cols = lines.iter().map(|line| line.len()).max().unwrap_or(0);
+    let width = cols as f64 * cell_width + 2. * padding;
+    let height = lines.len() as f64 * cell_height + 2. * padding;
+
+    let mut svg = String::new();
+    write!(
+        svg,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" \
+         viewBox=\"0 0 {w} {h}\">\n\
+         <rect width=\"100%\" height=\"100%\" fill=\"{bg}\"/>\n\
+         <g font-family=\"{family}\" font-size=\"{size}\" fill=\"{fg}\" \
+         xml:space=\"preserve\">\n",
+        w = px(width),
+        h = px(height),
+        bg = palette.background.to_rgb_string(),
+        fg = palette.foreground.to_rgb_string(),
+        family = options.font_family.replace('"', ""),
+        size = px(options.font_size),
+    )
+    .ok();
+
+    for (row, line) in lines.iter().enumerate() {
+        let top = padding + row as f64 * cell_height;
+        // Place the baseline so that descenders fit inside the cell
+        let baseline = top + options.font_size;
+        for run in line_runs(line, palette) {
+            let x = padding + run.col as f64 * cell_width;
+            let run_width = run.width as f64 * cell_width;
+            if run.style.bg != palette.background {
+                writeln!(
+                    svg,
+                    "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+                    px(x),
+                    px(top),
+                    px(run_width),
+                    px(cell_height),
+                    run.style.bg.to_rgb_string()
+                )
+                .ok();
+            }
+            if run.style.invisible || run.text.trim().is_empty() {
+                continue;
+            }
+
+            if let Some(link) = &run.style.link {
+                svg.push_str("<a href=\"");
+                escape_xml(link, &mut svg);
+                svg.push_str("\">");
+            }
+            write!(
+                svg,
+                "<text x=\"{}\" y=\"{}\" textLength=\"{}\"",
+                px(x),
+                px(baseline),
+                px(run_width)
+            )
+            .ok();
+            if run.style.fg != palette.foreground {
+                write!(svg, " fill=\"{}\"", run.style.fg.to_rgb_string()).ok();
+            }
+            if run.style.bold {
+                svg.push_str(" font-weight=\"bold\"");
+            }
+            if run.style.italic {
+                svg.push_str(" font-style=\"italic\"");
+            }
+            let decoration = run.style.decoration_lines();
+            if !decoration.is_empty() {
+                write!(svg, " text-decoration=\"{}\"", decoration.join(" ")).ok();
+            }
+            svg.push('>');
+            escape_xml(&run.text, &mut svg);
+            svg.push_str("</text>");
+            if run.style.link.is_some() {
+                svg.push_str("</a>");
+            }
+            svg.push('\n');
+        }
+    }
+
+    svg.push_str("</g>\n</svg>\n");
+    svg
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::Arc;
+    use termwiz::color::AnsiColor;
+    use termwiz::hyperlink::Hyperlink;
+    use termwiz::surface::SEQ_ZERO;
+
+    fn styled_line() -> Line {
+        let mut line = Line::from_text("plain ", &CellAttributes::blank(), SEQ_ZERO, None);
+        let mut attrs = CellAttributes::blank();
+        attrs
+            .set_foreground(AnsiColor::Red)
+            .set_intensity(Intensity::Bold);
+        line.append_line(Line::from_text("<red>", &attrs, SEQ_ZERO, None), SEQ_ZERO);
+        let mut attrs = CellAttributes::blank();
+        attrs.set_hyperlink(Some(Arc::new(Hyperlink::new("https://wezfurlong.org/"))));
+        line.append_line(Line::from_text("link", &attrs, SEQ_ZERO, None), SEQ_ZERO);
+        line.append_line(
+            Line::from_text("\u{1f600}   ", &CellAttributes::blank(), SEQ_ZERO, None),
+            SEQ_ZERO,
+        );
+        line
+    }
+
+    #[test]
+    fn html() {
+        let palette = ColorPalette::default();
+        let html = lines_to_html_fragment(&[styled_line()], &palette, &ExportOptions::default());
+        let red = palette.colors.0[1].to_rgb_string();
+        assert!(html.starts_with("<pre style=\""));
+        assert!(html.contains(&format!(
+            "\">plain <span style=\"color:{red};font-weight:bold;\">&lt;red&gt;</span>"
+        )));
+        assert!(html.contains("<a href=\"https://wezfurlong.org/\" style=\""));
+        assert!(html.contains(">link</a>"));
+        assert!(html.contains("display:inline-block;width:2ch;\">\u{1f600}</span></pre>"));
+    }
+
+    #[test]
+    fn svg() {
+        let palette = ColorPalette::default();
+        let svg = lines_to_svg(&[styled_line()], &palette, &ExportOptions::default());
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        // "<red>" starts at column 6; 6 * 9.6 + 9.6 padding
+        assert!(svg.contains("<text x=\"67.2\" y=\"25.6\" textLength=\"48\""));
+        assert!(svg.contains(" font-weight=\"bold\">&lt;red&gt;</text>"));
+        assert!(svg.contains("<a href=\"https://wezfurlong.org/\"><text"));
+        assert!(svg.ends_with("</g>\n</svg>\n"));
+    }
+
+    #[test]
+    fn unsafe_links_are_plain_text() {
+        for uri in [
+            "javascript:alert(1)",
+            "JavaScript:alert(1)",
+            " javascript:alert(1)",
+            "java\tscript:alert(1)",
+            "data:text/html,<script>alert(1)</script>",
+            "no-scheme",
+        ] {
+            assert_eq!(exportable_link(uri), None, "{uri:?}");
+
+            let mut attrs = CellAttributes::blank();
+            attrs.set_hyperlink(Some(Arc::new(Hyperlink::new(uri))));
+            let line = Line::from_text("link", &attrs, SEQ_ZERO, None);
+            let palette = ColorPalette::default();
+            let options = ExportOptions::default();
+            let html = lines_to_html_fragment(&[line.clone()], &palette, &options);
+            assert!(!html.contains("href"), "{html}");
+            let svg = lines_to_svg(&[line], &palette, &options);
+            assert!(!svg.contains("<a "), "{svg}");
+        }
+
+        for uri in [
+            "https://wezfurlong.org/",
+            "HTTP://example.com/",
+            "file:///etc/hosts",
+            "mailto:someone@example.com",
+        ] {
+            assert_eq!(exportable_link(uri).as_deref(), Some(uri));
+        }
+    }
+}
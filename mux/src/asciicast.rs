@@ -0,0 +1,304 @@
+//! Types for reading and writing recordings in the asciicast v2 format
+use anyhow::Context;
+use chrono::serde::ts_seconds_option;
+use chrono::{DateTime, Utc};
+use config::ConfigHandle;
+use portable_pty::PtySize;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use wezterm_term::color::ColorPalette;
+
+/// See <https://github.com/asciinema/asciinema/blob/develop/doc/asciicast-v2.md>
+/// for file format specification
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct Header {
+    /// Must be 2 or higher
+    pub version: u32,
+    /// Initial terminal width (number of columns)
+    pub width: u32,
+    /// Initial terminal height (number of columns)
+    pub height: u32,
+    /// Unix timestamp of starting time of session
+    #[serde(
+        default,
+        with = "ts_seconds_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub timestamp: Option<DateTime<Utc>>,
+    /// Duration of the whole recording in seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duration: Option<f32>,
+    /// Used to reduce terminal inactivity (delays between frames)
+    /// to a maximum of this amount.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle_time_limit: Option<f32>,
+    /// Command that was recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    /// Title of the asciicast
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Map of captured environment variables
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub env: HashMap<String, String>,
+    /// Color theme of the recorded terminal
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub theme: Option<Theme>,
+}
+
+impl Header {
+    pub fn new(config: &ConfigHandle, size: PtySize, prog: &[&OsStr]) -> Self {
+        let mut env = HashMap::new();
+        env.insert("TERM".to_string(), config.term.to_string());
+        env.insert(
+            "WEZTERM_VERSION".to_string(),
+            config::wezterm_version().to_string(),
+        );
+        env.insert(
+            "WEZTERM_TARGET_TRIPLE".to_string(),
+            config::wezterm_target_triple().to_string(),
+        );
+        if let Ok(shell) = std::env::var("SHELL") {
+            env.insert("SHELL".to_string(), shell);
+        }
+        if let Ok(lang) = std::env::var("LANG") {
+            env.insert("LANG".to_string(), lang);
+        }
+
+        let palette: ColorPalette = config.resolved_palette.clone().into();
+        let ansi_colors: Vec<String> = palette.colors.0[0..16]
+            .iter()
+            .map(|c| c.to_rgb_string())
+            .collect();
+
+        let theme = Theme {
+            fg: palette.foreground.to_rgb_string(),
+            bg: palette.background.to_rgb_string(),
+            palette: ansi_colors.join(":"),
+        };
+
+        let command = if prog.is_empty() {
+            None
+        } else {
+            let args: Vec<String> = prog
+                .iter()
+                .map(|s| s.to_string_lossy().to_string())
+                .collect();
+            Some(shell_words::join(&args))
+        };
+
+        Header {
+            version: 2,
+            height: size.rows.into(),
+            width: size.cols.into(),
+            timestamp: Some(Utc::now()),
+            env,
+            command,
+            theme: Some(theme),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct Theme {
+    /// Normal text color
+    pub fg: String,
+    /// Normal background color
+    pub bg: String,
+    /// List of 8 or 16 colors separated by a colon character
+    pub palette: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone)]
+pub struct Event(pub f32, pub String, pub String);
+
+impl Event {
+    pub fn write<W: Write>(mut w: W, elapsed: f32, code: &str, data: &str) -> std::io::Result<()> {
+        let event = Event(elapsed, code.to_string(), data.to_string());
+        writeln!(w, "{}", serde_json::to_string(&event)?)
+    }
+}
+
+/// A recording that has been read into memory
+#[derive(Debug, Default, Clone)]
+pub struct Cast {
+    pub header: Header,
+    pub events: Vec<Event>,
+}
+
+impl Cast {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let file = std::fs::File::open(path)
+            .with_context(|| format!("reading cast file {}", path.display()))?;
+        Self::parse(BufReader::new(file))
+            .with_context(|| format!("parsing cast file {}", path.display()))
+    }
+
+    pub fn parse<R: BufRead>(reader: R) -> anyhow::Result<Self> {
+        let mut lines = reader.lines();
+        let header_line = lines.next().context("missing Header line")??;
+        let header: Header = serde_json::from_str(&header_line).context("parsing Header")?;
+        anyhow::ensure!(
+            header.version >= 2,
+            "unsupported asciicast version {}",
+            header.version
+        );
+
+        let mut events = vec![];
+        for (idx, line) in lines.enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let event: Event = serde_json::from_str(&line)
+                .with_context(|| format!("parsing event on line {}", idx + 2))?;
+            events.push(event);
+        }
+
+        Ok(Self { header, events })
+    }
+
+    /// The time of the last event in the recording, in seconds
+    pub fn duration(&self) -> f32 {
+        self.events.last().map(|event| event.0).unwrap_or(0.)
+    }
+}
+
+/// Writes the events of a recording.  The data passed to `output`
+/// and `input` may end with an incomplete utf8 sequence that
+/// straddles the buffer boundary; JSON requires strings to be utf-8
+/// so the valid portions are written through and the remainder is
+/// buffered until the next call.
+pub struct CastWriter<W: Write> {
+    out: W,
+    output: Vec<u8>,
+    input: Vec<u8>,
+}
+
+impl<W: Write> CastWriter<W> {
+    /// Writes the header and returns the writer for the events
+    pub fn new(mut out: W, header: &Header) -> anyhow::Result<Self> {
+        writeln!(out, "{}", serde_json::to_string(header)?)?;
+        Ok(Self {
+            out,
+            output: vec![],
+            input: vec![],
+        })
+    }
+
+    pub fn output(&mut self, elapsed: f32, data: &[u8]) -> std::io::Result<()> {
+        Self::write_utf8(&mut self.out, &mut self.output, elapsed, "o", data)
+    }
+
+    pub fn input(&mut self, elapsed: f32, data: &[u8]) -> std::io::Result<()> {
+        Self::write_utf8(&mut self.out, &mut self.input, elapsed, "i", data)
+    }
+
+    /// Records that the terminal was resized to `cols` x `rows`
+    pub fn resize(&mut self, elapsed: f32, cols: usize, rows: usize) -> std::io::Result<()> {
+        Event::write(&mut self.out, elapsed, "r", &format!("{cols}x{rows}"))
+    }
+
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        self.out.flush()
+    }
+
+    /// Returns a reference to the underlying writer
+    pub fn get_ref(&self) -> &W {
+        &self.out
+    }
+
+    fn write_utf8(
+        out: &mut W,
+        buffer: &mut Vec<u8>,
+        elapsed: f32,
+        code: &str,
+        data: &[u8],
+    ) -> std::io::Result<()> {
+        buffer.extend_from_slice(data);
+        let mut text = String::new();
+        loop {
+            match std::str::from_utf8(buffer) {
+                Ok(valid) => {
+                    text.push_str(valid);
+                    buffer.clear();
+                    break;
+                }
+                Err(error) => {
+                    let valid_len = error.valid_up_to();
+                    text.push_str(unsafe { std::str::from_utf8_unchecked(&buffer[0..valid_len]) });
+                    match error.error_len() {
+                        // Invalid sequence: skip it and carry on with the rest
+                        Some(invalid_sequence_length) => {
+                            buffer.drain(0..valid_len + invalid_sequence_length);
+                        }
+                        // Incomplete sequence: wait for the rest of it
+                        None => {
+                            buffer.drain(0..valid_len);
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+        if !text.is_empty() {
+            Event::write(&mut *out, elapsed, code, &text)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn events(writer: &CastWriter<Vec<u8>>) -> Vec<(String, String)> {
+        Cast::parse(writer.get_ref().as_slice())
+            .unwrap()
+            .events
+            .into_iter()
+            .map(|event| (event.1, event.2))
+            .collect()
+    }
+
+    fn event(code: &str, data: &str) -> (String, String) {
+        (code.to_string(), data.to_string())
+    }
+
+    #[test]
+    fn cast_writer_utf8() {
+        let header = Header {
+            version: 2,
+            ..Default::default()
+        };
+        let mut writer = CastWriter::new(vec![], &header).unwrap();
+
+        // A sequence that straddles two reads is written with the second
+        let euro = "€".as_bytes();
+        writer.output(0.1, &[b'a', euro[0], euro[1]]).unwrap();
+        writer.output(0.2, &euro[2..]).unwrap();
+        // Input and output are buffered separately
+        writer.input(0.3, &euro[0..1]).unwrap();
+        writer.output(0.4, b"b").unwrap();
+        writer.input(0.5, &euro[1..]).unwrap();
+        // Invalid sequences are skipped, and the rest is written
+        writer.output(0.6, b"c\xffd\xfe\xfee").unwrap();
+        writer.resize(0.7, 100, 30).unwrap();
+
+        assert_eq!(
+            events(&writer),
+            vec![
+                event("o", "a"),
+                event("o", "€"),
+                event("o", "b"),
+                event("i", "€"),
+                event("o", "cde"),
+                event("r", "100x30"),
+            ]
+        );
+    }
+}
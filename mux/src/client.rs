@@ -1,6 +1,7 @@
 use crate::PaneId;
 use chrono::serde::ts_seconds;
 use chrono::{DateTime, Utc};
+use config::ClientAccess;
 use serde::*;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
@@ -52,16 +53,19 @@ pub struct ClientInfo {
     pub last_input: DateTime<Utc>,
     /// The currently-focused pane
     pub focused_pane_id: Option<PaneId>,
+    /// What the client is permitted to do
+    pub access: ClientAccess,
 }
 
 impl ClientInfo {
-    pub fn new(client_id: Arc<ClientId>) -> Self {
+    pub fn new(client_id: Arc<ClientId>, access: ClientAccess) -> Self {
         Self {
             client_id,
             connected_at: Utc::now(),
             active_workspace: None,
             last_input: Utc::now(),
             focused_pane_id: None,
+            access,
         }
     }
 
@@ -9,7 +9,7 @@ use crate::localpane::LocalPane;
 use crate::pane::{alloc_pane_id, Pane, PaneId};
 use crate::tab::{SplitRequest, Tab, TabId};
 use crate::window::WindowId;
-use crate::Mux;
+use crate::{Mux, MuxNotification};
 use anyhow::{bail, Context, Error};
 use async_trait::async_trait;
 use config::keyassignment::{SpawnCommand, SpawnTabDomain};
@@ -42,6 +42,8 @@ pub enum SplitSource {
     Spawn {
         command: Option<CommandBuilder>,
         command_dir: Option<String>,
+        /// Parsed by the new pane ahead of anything written by its process
+        initial_output: Option<String>,
     },
     MovePane(PaneId),
 }
@@ -54,10 +56,11 @@ pub trait Domain: Downcast + Send + Sync {
         size: TerminalSize,
         command: Option<CommandBuilder>,
         command_dir: Option<String>,
+        initial_output: Option<String>,
         window: WindowId,
     ) -> anyhow::Result<Arc<Tab>> {
         let pane = self
-            .spawn_pane(size, command, command_dir)
+            .spawn_pane(size, command, command_dir, initial_output)
             .await
             .context("spawn")?;
 
@@ -102,8 +105,9 @@ pub trait Domain: Downcast + Send + Sync {
             SplitSource::Spawn {
                 command,
                 command_dir,
+                initial_output,
             } => {
-                self.spawn_pane(split_size.second, command, command_dir)
+                self.spawn_pane(split_size.second, command, command_dir, initial_output)
                     .await?
             }
             SplitSource::MovePane(src_pane_id) => {
@@ -141,11 +145,14 @@ pub trait Domain: Downcast + Send + Sync {
         Ok(pane)
     }
 
+    /// Spawn a new pane within this domain.  If `initial_output` is set,
+    /// the new pane parses it ahead of anything written by its process.
     async fn spawn_pane(
         &self,
         size: TerminalSize,
         command: Option<CommandBuilder>,
         command_dir: Option<String>,
+        initial_output: Option<String>,
     ) -> anyhow::Result<Arc<dyn Pane>>;
 
     /// The mux will call this method on the domain of the pane that
@@ -203,6 +210,7 @@ pub struct LocalDomain {
     pty_system: Mutex<Box<dyn PtySystem + Send>>,
     id: DomainId,
     name: String,
+    serial_log_file: Option<PathBuf>,
 }
 
 impl LocalDomain {
@@ -232,6 +240,7 @@ impl LocalDomain {
             pty_system: Mutex::new(pty_system),
             id,
             name: name.to_string(),
+            serial_log_file: None,
         }
     }
 
@@ -249,8 +258,22 @@ impl LocalDomain {
         if let Some(baud) = serial_domain.baud {
             serial.set_baud_rate(baud as u32);
         }
+        if let Some(bits) = serial_domain.data_bits {
+            serial.set_char_size(crate::serial::char_size(bits)?);
+        }
+        if let Some(parity) = serial_domain.parity {
+            serial.set_parity(crate::serial::parity(parity));
+        }
+        if let Some(stop_bits) = serial_domain.stop_bits {
+            serial.set_stop_bits(crate::serial::stop_bits(stop_bits));
+        }
+        if let Some(flow_control) = serial_domain.flow_control {
+            serial.set_flow_control(crate::serial::flow_control(flow_control));
+        }
         let pty_system = Box::new(serial);
-        Ok(Self::with_pty_system(&serial_domain.name, pty_system))
+        let mut domain = Self::with_pty_system(&serial_domain.name, pty_system);
+        domain.serial_log_file = serial_domain.log_file;
+        Ok(domain)
     }
 
     #[cfg(unix)]
@@ -495,12 +518,14 @@ impl LocalDomain {
 /// awkward at the moment.
 #[derive(Clone)]
 pub(crate) struct WriterWrapper {
+    pane_id: PaneId,
     writer: Arc<Mutex<Box<dyn Write + Send>>>,
 }
 
 impl WriterWrapper {
-    pub fn new(writer: Box<dyn Write + Send>) -> Self {
+    pub fn new(pane_id: PaneId, writer: Box<dyn Write + Send>) -> Self {
         Self {
+            pane_id,
             writer: Arc::new(Mutex::new(writer)),
         }
     }
@@ -508,7 +533,22 @@ impl WriterWrapper {
 
 impl std::io::Write for WriterWrapper {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-        self.writer.lock().write(buf)
+        let size = self.writer.lock().write(buf)?;
+        if Mux::try_get().map_or(false, |mux| mux.has_input_tap(self.pane_id)) {
+            // Writes can happen from within a notification callback,
+            // so the notification is always deferred
+            let notification = MuxNotification::PaneRawInput {
+                pane_id: self.pane_id,
+                data: Arc::new(buf[..size].to_vec()),
+            };
+            promise::spawn::spawn_into_main_thread(async move {
+                if let Some(mux) = Mux::try_get() {
+                    mux.notify(notification);
+                }
+            })
+            .detach();
+        }
+        Ok(size)
     }
 
     fn flush(&mut self) -> std::io::Result<()> {
@@ -592,6 +632,7 @@ impl Domain for LocalDomain {
         size: TerminalSize,
         command: Option<CommandBuilder>,
         command_dir: Option<String>,
+        initial_output: Option<String>,
     ) -> anyhow::Result<Arc<dyn Pane>> {
         let pane_id = alloc_pane_id();
         let cmd = self
@@ -616,7 +657,7 @@ impl Domain for LocalDomain {
             self.name
         );
         let child_result = pair.slave.spawn_command(cmd);
-        let mut writer = WriterWrapper::new(pair.master.take_writer()?);
+        let mut writer = WriterWrapper::new(pane_id, pair.master.take_writer()?);
 
         let mut terminal = wezterm_term::Terminal::new(
             size,
@@ -659,7 +700,13 @@ impl Domain for LocalDomain {
         };
 
         let mux = Mux::get();
-        mux.add_pane(&pane)?;
+        mux.add_pane_with_output(&pane, initial_output)?;
+
+        if let Some(path) = &self.serial_log_file {
+            if let Err(err) = crate::serial::start_logging(pane.pane_id(), path) {
+                log::error!("{err:#}");
+            }
+        }
 
         Ok(pane)
     }
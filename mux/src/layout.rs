@@ -0,0 +1,170 @@
+//! Building the tabs and panes of a workspace from one of the
+//! `workspace_layouts` defined in the config.
+//!
+//! Each tab is spawned with its first pane, which is then split
+//! via `Mux::split_pane` (and thus `Tab::split_and_insert`) to
+//! produce the rest of the panes of the tab.
+use crate::domain::SplitSource;
+use crate::pane::Pane;
+use crate::tab::{SplitDirection, SplitRequest, SplitSize};
+use crate::window::WindowId;
+use crate::Mux;
+use anyhow::{anyhow, Context};
+use config::keyassignment::{PaneDirection, SpawnCommand, SplitSize as ConfigSplitSize};
+use config::{PaneLayout, WorkspaceLayout};
+use portable_pty::CommandBuilder;
+use std::sync::Arc;
+use wezterm_term::TerminalSize;
+
+/// Produces the command builder and working directory that
+/// are passed to the domain when spawning `spawn`
+fn command_for_spawn(
+    spawn: &SpawnCommand,
+) -> anyhow::Result<(Option<CommandBuilder>, Option<String>)> {
+    let cwd = match &spawn.cwd {
+        Some(cwd) => Some(
+            cwd.to_str()
+                .map(|s| s.to_owned())
+                .ok_or_else(|| anyhow!("layout cwd {cwd:?} must be unicode"))?,
+        ),
+        None => None,
+    };
+
+    if spawn.args.is_none() && spawn.cwd.is_none() && spawn.set_environment_variables.is_empty() {
+        return Ok((None, cwd));
+    }
+
+    let mut builder = spawn
+        .args
+        .as_ref()
+        .map(|args| CommandBuilder::from_argv(args.iter().map(Into::into).collect()))
+        .unwrap_or_else(CommandBuilder::new_default_prog);
+    for (k, v) in spawn.set_environment_variables.iter() {
+        builder.env(k, v);
+    }
+    if let Some(cwd) = &spawn.cwd {
+        builder.cwd(cwd);
+    }
+    Ok((Some(builder), cwd))
+}
+
+fn split_request(direction: &PaneDirection, size: &ConfigSplitSize) -> SplitRequest {
+    SplitRequest {
+        direction: match direction {
+            PaneDirection::Left | PaneDirection::Right => SplitDirection::Horizontal,
+            _ => SplitDirection::Vertical,
+        },
+        target_is_second: matches!(direction, PaneDirection::Right | PaneDirection::Down),
+        top_level: false,
+        size: match size {
+            ConfigSplitSize::Percent(n) => SplitSize::Percent(*n),
+            ConfigSplitSize::Cells(n) => SplitSize::Cells(*n),
+        },
+    }
+}
+
+/// Creates a new window in `workspace` holding the tabs and panes
+/// described by `layout`, and returns the id of that window.
+pub async fn spawn_workspace_layout(
+    layout: &WorkspaceLayout,
+    workspace: String,
+    size: TerminalSize,
+) -> anyhow::Result<WindowId> {
+    let mux = Mux::get();
+    if layout.tabs.is_empty() {
+        anyhow::bail!("layout has no tabs");
+    }
+
+    let mut window_id = None;
+    for tab_layout in &layout.tabs {
+        let spawn = tab_layout.pane.spawn_command(layout);
+        let (command, command_dir) = command_for_spawn(&spawn)?;
+        let (tab, pane, id) = mux
+            .spawn_tab_or_window(
+                window_id,
+                spawn.domain,
+                command,
+                command_dir,
+                None,
+                size,
+                None,
+                workspace.clone(),
+                None,
+            )
+            .await
+            .context("spawn_tab_or_window")?;
+        window_id = Some(id);
+        if let Some(title) = &tab_layout.title {
+            tab.set_title(title);
+        }
+
+        // Each entry is a pane layout together with the pane that
+        // was spawned for it, whose splits are yet to be made
+        let mut pending: Vec<(&PaneLayout, Arc<dyn Pane>)> = vec![(&tab_layout.pane, pane)];
+        let mut active = None;
+        while let Some((pane_layout, pane)) = pending.pop() {
+            for split in &pane_layout.splits {
+                let spawn = split.pane.spawn_command(layout);
+                let (command, command_dir) = command_for_spawn(&spawn)?;
+                let (new_pane, _size) = mux
+                    .split_pane(
+                        pane.pane_id(),
+                        split_request(&split.direction, &split.size),
+                        SplitSource::Spawn {
+                            command,
+                            command_dir,
+                            initial_output: None,
+                        },
+                        spawn.domain,
+                    )
+                    .await
+                    .context("split_pane")?;
+                pending.push((&split.pane, new_pane));
+            }
+            if pane_layout.active {
+                active.replace(pane);
+            }
+        }
+
+        if let Some(pane) = active {
+            tab.set_active_pane(&pane);
+        }
+    }
+
+    let window_id = window_id.ok_or_else(|| anyhow!("layout has no tabs"))?;
+    if let Some(mut window) = mux.get_window_mut(window_id) {
+        let active_tab = layout.active_tab();
+        if active_tab < window.len() {
+            window.set_active_without_saving(active_tab);
+        }
+    }
+    Ok(window_id)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn split_requests() {
+        let request = split_request(&PaneDirection::Right, &ConfigSplitSize::Percent(30));
+        assert_eq!(request.direction, SplitDirection::Horizontal);
+        assert!(request.target_is_second);
+        assert!(!request.top_level);
+        assert_eq!(request.size, SplitSize::Percent(30));
+
+        let request = split_request(&PaneDirection::Left, &ConfigSplitSize::Cells(20));
+        assert_eq!(request.direction, SplitDirection::Horizontal);
+        assert!(!request.target_is_second);
+        assert_eq!(request.size, SplitSize::Cells(20));
+
+        let request = split_request(&PaneDirection::Down, &ConfigSplitSize::Percent(50));
+        assert_eq!(request.direction, SplitDirection::Vertical);
+        assert!(request.target_is_second);
+
+        let request = split_request(&PaneDirection::Up, &ConfigSplitSize::Cells(5));
+        assert_eq!(request.direction, SplitDirection::Vertical);
+        assert!(!request.target_is_second);
+        assert_eq!(request.size, SplitSize::Cells(5));
+    }
+}
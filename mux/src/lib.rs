@@ -5,7 +5,7 @@ use crate::tab::{SplitRequest, Tab, TabId};
 use crate::window::{Window, WindowId};
 use anyhow::{anyhow, Context, Error};
 use config::keyassignment::SpawnTabDomain;
-use config::{configuration, ExitBehavior, GuiPosition};
+use config::{configuration, ClientAccess, ExitBehavior, GuiPosition};
 use domain::{Domain, DomainId, DomainState, SplitSource};
 use filedescriptor::{poll, pollfd, socketpair, AsRawSocketDescriptor, FileDescriptor, POLLIN};
 #[cfg(unix)]
@@ -29,19 +29,28 @@ use std::time::{Duration, Instant};
 use termwiz::escape::csi::{DecPrivateMode, DecPrivateModeCode, Device, Mode};
 use termwiz::escape::{Action, CSI};
 use thiserror::*;
+use url::Url;
 use wezterm_term::{Clipboard, ClipboardSelection, DownloadHandler, TerminalSize};
 #[cfg(windows)]
 use winapi::um::winsock2::{SOL_SOCKET, SO_RCVBUF, SO_SNDBUF};
 
 pub mod activity;
+pub mod asciicast;
 pub mod client;
 pub mod connui;
 pub mod domain;
+pub mod layout;
 pub mod localpane;
 pub mod pane;
+pub mod player;
+pub mod recording;
 pub mod renderable;
+pub mod serial;
+pub mod session_state;
+pub mod sftp;
 pub mod ssh;
 pub mod ssh_agent;
+pub mod ssh_pool;
 pub mod tab;
 pub mod termwiztermtab;
 pub mod tmux;
@@ -56,8 +65,24 @@ pub const DEFAULT_WORKSPACE: &str = "default";
 #[derive(Clone, Debug)]
 pub enum MuxNotification {
     PaneOutput(PaneId),
+    /// The bytes most recently read from the pty of a pane.
+    /// This is only produced for panes that have an output tap;
+    /// see `Mux::add_output_tap`.
+    PaneRawOutput {
+        pane_id: PaneId,
+        data: Arc<Vec<u8>>,
+    },
+    /// The bytes most recently written to the pty of a pane.
+    /// This is only produced for panes that have an input tap;
+    /// see `Mux::add_input_tap`.
+    PaneRawInput {
+        pane_id: PaneId,
+        data: Arc<Vec<u8>>,
+    },
     PaneAdded(PaneId),
     PaneRemoved(PaneId),
+    /// A recording of the pane was started or stopped
+    PaneRecordingChanged(PaneId),
     WindowCreated(WindowId),
     WindowRemoved(WindowId),
     WindowInvalidated(WindowId),
@@ -111,6 +136,8 @@ pub struct Mux {
     clients: RwLock<HashMap<ClientId, ClientInfo>>,
     identity: RwLock<Option<Arc<ClientId>>>,
     num_panes_by_workspace: RwLock<HashMap<String, usize>>,
+    output_taps: RwLock<HashMap<PaneId, usize>>,
+    input_taps: RwLock<HashMap<PaneId, usize>>,
     main_thread_id: std::thread::ThreadId,
     agent: Option<AgentProxy>,
 }
@@ -336,6 +363,12 @@ fn read_from_pane_pty(
                     );
                     break;
                 }
+                if Mux::try_get().map_or(false, |mux| mux.has_output_tap(pane_id)) {
+                    Mux::notify_from_any_thread(MuxNotification::PaneRawOutput {
+                        pane_id,
+                        data: Arc::new(buf[..size].to_vec()),
+                    });
+                }
             }
         }
     }
@@ -446,6 +479,8 @@ impl Mux {
             clients: RwLock::new(HashMap::new()),
             identity: RwLock::new(None),
             num_panes_by_workspace: RwLock::new(HashMap::new()),
+            output_taps: RwLock::new(HashMap::new()),
+            input_taps: RwLock::new(HashMap::new()),
             main_thread_id: std::thread::current().id(),
             agent,
         }
@@ -511,6 +546,14 @@ impl Mux {
         Some((domain, window, tab, pane_id))
     }
 
+    /// Records the pane that a client without full access is looking
+    /// at, without changing the focus of the panes themselves
+    pub fn record_view_for_client(&self, client_id: &ClientId, pane_id: PaneId) {
+        if let Some(info) = self.clients.write().get_mut(client_id) {
+            info.update_focused_pane(pane_id);
+        }
+    }
+
     pub fn record_focus_for_client(&self, client_id: &ClientId, pane_id: PaneId) {
         let mut prior = None;
         if let Some(info) = self.clients.write().get_mut(client_id) {
@@ -564,10 +607,10 @@ impl Mux {
         Ok(())
     }
 
-    pub fn register_client(&self, client_id: Arc<ClientId>) {
+    pub fn register_client(&self, client_id: Arc<ClientId>, access: ClientAccess) {
         self.clients
             .write()
-            .insert((*client_id).clone(), ClientInfo::new(client_id));
+            .insert((*client_id).clone(), ClientInfo::new(client_id, access));
     }
 
     pub fn iter_clients(&self) -> Vec<ClientInfo> {
@@ -719,6 +762,50 @@ impl Mux {
         .detach();
     }
 
+    /// Arrange for `MuxNotification::PaneRawOutput` to be generated
+    /// for the raw pty output of the specified pane.
+    /// Taps are reference counted; each call must be balanced
+    /// by a call to `remove_output_tap`.
+    pub fn add_output_tap(&self, pane_id: PaneId) {
+        *self.output_taps.write().entry(pane_id).or_insert(0) += 1;
+    }
+
+    pub fn remove_output_tap(&self, pane_id: PaneId) {
+        let mut taps = self.output_taps.write();
+        if let Some(count) = taps.get_mut(&pane_id) {
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                taps.remove(&pane_id);
+            }
+        }
+    }
+
+    pub fn has_output_tap(&self, pane_id: PaneId) -> bool {
+        self.output_taps.read().contains_key(&pane_id)
+    }
+
+    /// Arrange for `MuxNotification::PaneRawInput` to be generated
+    /// for the data written to the pty of the specified pane.
+    /// Taps are reference counted; each call must be balanced
+    /// by a call to `remove_input_tap`.
+    pub fn add_input_tap(&self, pane_id: PaneId) {
+        *self.input_taps.write().entry(pane_id).or_insert(0) += 1;
+    }
+
+    pub fn remove_input_tap(&self, pane_id: PaneId) {
+        let mut taps = self.input_taps.write();
+        if let Some(count) = taps.get_mut(&pane_id) {
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                taps.remove(&pane_id);
+            }
+        }
+    }
+
+    pub fn has_input_tap(&self, pane_id: PaneId) -> bool {
+        self.input_taps.read().contains_key(&pane_id)
+    }
+
     pub fn default_domain(&self) -> Arc<dyn Domain> {
         self.default_domain.read().as_ref().map(Arc::clone).unwrap()
     }
@@ -772,6 +859,16 @@ impl Mux {
     }
 
     pub fn add_pane(&self, pane: &Arc<dyn Pane>) -> Result<(), Error> {
+        self.add_pane_with_output(pane, None)
+    }
+
+    /// Adds a pane to the mux, arranging for `initial_output` to be
+    /// parsed by the pane ahead of anything written by its process
+    pub fn add_pane_with_output(
+        &self,
+        pane: &Arc<dyn Pane>,
+        initial_output: Option<String>,
+    ) -> Result<(), Error> {
         if self.panes.read().contains_key(&pane.pane_id()) {
             return Ok(());
         }
@@ -787,7 +884,10 @@ impl Mux {
         self.panes.write().insert(pane.pane_id(), Arc::clone(pane));
         let pane_id = pane.pane_id();
         if let Some(reader) = pane.reader()? {
-            let banner = self.banner.read().clone();
+            let mut banner = initial_output;
+            if let Some(global) = self.banner.read().as_ref() {
+                banner.get_or_insert_with(String::new).push_str(global);
+            }
             let pane = Arc::downgrade(pane);
             thread::spawn(move || read_from_pane_pty(pane, banner, reader));
         }
@@ -1155,29 +1255,11 @@ impl Mux {
         target_domain: DomainId,
         policy: CachePolicy,
     ) -> Option<String> {
-        command_dir.or_else(|| {
-            match pane {
-                Some(pane) if pane.domain_id() == target_domain => pane
-                    .get_current_working_dir(policy)
-                    .and_then(|url| {
-                        percent_decode_str(url.path())
-                            .decode_utf8()
-                            .ok()
-                            .map(|path| path.into_owned())
-                    })
-                    .map(|path| {
-                        // On Windows the file URI can produce a path like:
-                        // `/C:\Users` which is valid in a file URI, but the leading slash
-                        // is not liked by the windows file APIs, so we strip it off here.
-                        let bytes = path.as_bytes();
-                        if bytes.len() > 2 && bytes[0] == b'/' && bytes[2] == b':' {
-                            path[1..].to_owned()
-                        } else {
-                            path
-                        }
-                    }),
-                _ => None,
-            }
+        command_dir.or_else(|| match pane {
+            Some(pane) if pane.domain_id() == target_domain => pane
+                .get_current_working_dir(policy)
+                .and_then(|url| cwd_from_url(&url)),
+            _ => None,
         })
     }
 
@@ -1210,6 +1292,7 @@ impl Mux {
             SplitSource::Spawn {
                 command,
                 command_dir,
+                initial_output,
             } => SplitSource::Spawn {
                 command,
                 command_dir: self.resolve_cwd(
@@ -1218,6 +1301,7 @@ impl Mux {
                     domain.domain_id(),
                     CachePolicy::FetchImmediate,
                 ),
+                initial_output,
             },
             other => other,
         };
@@ -1307,6 +1391,7 @@ impl Mux {
         domain: SpawnTabDomain,
         command: Option<CommandBuilder>,
         command_dir: Option<String>,
+        initial_output: Option<String>,
         size: TerminalSize,
         current_pane_id: Option<PaneId>,
         workspace_for_new_window: String,
@@ -1366,7 +1451,13 @@ impl Mux {
         );
 
         let tab = domain
-            .spawn(size, command.clone(), cwd.clone(), window_id)
+            .spawn(
+                size,
+                command.clone(),
+                cwd.clone(),
+                initial_output,
+                window_id,
+            )
             .await
             .with_context(|| {
                 format!(
@@ -1396,6 +1487,26 @@ impl Mux {
     }
 }
 
+/// Converts the working directory reported by a pane into a path
+/// that can be used as the working directory of a new command
+pub(crate) fn cwd_from_url(url: &Url) -> Option<String> {
+    percent_decode_str(url.path())
+        .decode_utf8()
+        .ok()
+        .map(|path| path.into_owned())
+        .map(|path| {
+            // On Windows the file URI can produce a path like:
+            // `/C:\Users` which is valid in a file URI, but the leading slash
+            // is not liked by the windows file APIs, so we strip it off here.
+            let bytes = path.as_bytes();
+            if bytes.len() > 2 && bytes[0] == b'/' && bytes[2] == b':' {
+                path[1..].to_owned()
+            } else {
+                path
+            }
+        })
+}
+
 pub struct IdentityHolder {
     prior: Option<Arc<ClientId>>,
 }
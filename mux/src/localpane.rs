@@ -1,8 +1,9 @@
-use crate::domain::DomainId;
+use crate::domain::{DomainId, DomainState};
 use crate::pane::{
     CachePolicy, CloseReason, ForEachPaneLogicalLine, LogicalLine, Pane, PaneId, Pattern,
     SearchResult, WithPaneLines,
 };
+use crate::recording::RecordingOptions;
 use crate::renderable::*;
 use crate::tmux::{TmuxDomain, TmuxDomainState};
 use crate::{Domain, Mux, MuxNotification};
@@ -31,8 +32,9 @@ use url::Url;
 use wezterm_dynamic::Value;
 use wezterm_term::color::ColorPalette;
 use wezterm_term::{
-    Alert, AlertHandler, Clipboard, DownloadHandler, KeyCode, KeyModifiers, MouseEvent, Progress,
-    SemanticZone, StableRowIndex, Terminal, TerminalConfiguration, TerminalSize,
+    Alert, AlertHandler, Clipboard, CommandBlock, DownloadHandler, KeyCode, KeyModifiers,
+    MouseEvent, Progress, SemanticZone, StableRowIndex, Terminal, TerminalConfiguration,
+    TerminalSize,
 };
 
 const PROC_INFO_CACHE_TTL: Duration = Duration::from_millis(300);
@@ -643,186 +645,213 @@ impl Pane for LocalPane {
         term.get_semantic_zones()
     }
 
+    fn get_command_blocks(&self) -> anyhow::Result<Vec<CommandBlock>> {
+        let term = self.terminal.lock();
+        Ok(term.get_command_blocks())
+    }
+
+    async fn start_recording(&self, options: RecordingOptions) -> anyhow::Result<String> {
+        let path = crate::recording::start_recording(self, &options)?;
+        Ok(path.display().to_string())
+    }
+
+    async fn stop_recording(&self) -> anyhow::Result<Option<String>> {
+        Ok(crate::recording::stop_recording(self.pane_id).map(|path| path.display().to_string()))
+    }
+
+    fn is_recording(&self) -> bool {
+        crate::recording::is_recording(self.pane_id)
+    }
+
     async fn search(
         &self,
         pattern: Pattern,
         range: Range<StableRowIndex>,
         limit: Option<u32>,
     ) -> anyhow::Result<Vec<SearchResult>> {
-        let term = self.terminal.lock();
-        let screen = term.screen();
+        search_terminal(&self.terminal.lock(), pattern, range, limit)
+    }
+}
 
-        enum CompiledPattern {
-            CaseSensitiveString(String),
-            CaseInSensitiveString(String),
-            Regex(Regex),
-        }
+/// Searches the lines of the terminal in the specified range,
+/// returning up to `limit` matches
+pub(crate) fn search_terminal(
+    term: &Terminal,
+    pattern: Pattern,
+    range: Range<StableRowIndex>,
+    limit: Option<u32>,
+) -> anyhow::Result<Vec<SearchResult>> {
+    let screen = term.screen();
+
+    enum CompiledPattern {
+        CaseSensitiveString(String),
+        CaseInSensitiveString(String),
+        Regex(Regex),
+    }
 
-        let pattern = match pattern {
-            Pattern::CaseSensitiveString(s) => CompiledPattern::CaseSensitiveString(s),
-            Pattern::CaseInSensitiveString(s) => {
-                // normalize the case so we match everything lowercase
-                CompiledPattern::CaseInSensitiveString(s.to_lowercase())
-            }
-            Pattern::Regex(r) => CompiledPattern::Regex(Regex::new(&r)?),
-        };
+    let pattern = match pattern {
+        Pattern::CaseSensitiveString(s) => CompiledPattern::CaseSensitiveString(s),
+        Pattern::CaseInSensitiveString(s) => {
+            // normalize the case so we match everything lowercase
+            CompiledPattern::CaseInSensitiveString(s.to_lowercase())
+        }
+        Pattern::Regex(r) => CompiledPattern::Regex(Regex::new(&r)?),
+    };
 
-        let mut results = vec![];
-        let mut uniq_matches: HashMap<String, usize> = HashMap::new();
+    let mut results = vec![];
+    let mut uniq_matches: HashMap<String, usize> = HashMap::new();
 
-        screen.for_each_logical_line_in_stable_range(range, |sr, lines| {
-            if let Some(limit) = limit {
-                if results.len() == limit as usize {
-                    // We've reach the limit, stop iteration.
-                    return false;
-                }
+    screen.for_each_logical_line_in_stable_range(range, |sr, lines| {
+        if let Some(limit) = limit {
+            if results.len() == limit as usize {
+                // We've reach the limit, stop iteration.
+                return false;
             }
+        }
 
-            if lines.is_empty() {
-                // Nothing to do on this iteration, carry on with the next.
-                return true;
+        if lines.is_empty() {
+            // Nothing to do on this iteration, carry on with the next.
+            return true;
+        }
+        let haystack = if lines.len() == 1 {
+            lines[0].as_str()
+        } else {
+            let mut s = String::new();
+            for line in lines {
+                s.push_str(&line.as_str());
             }
-            let haystack = if lines.len() == 1 {
-                lines[0].as_str()
-            } else {
-                let mut s = String::new();
-                for line in lines {
-                    s.push_str(&line.as_str());
-                }
-                Cow::Owned(s)
-            };
-            let stable_idx = sr.start;
+            Cow::Owned(s)
+        };
+        let stable_idx = sr.start;
 
-            if haystack.is_empty() {
-                return true;
-            }
+        if haystack.is_empty() {
+            return true;
+        }
 
-            let haystack = match &pattern {
-                CompiledPattern::CaseInSensitiveString(_) => Cow::Owned(haystack.to_lowercase()),
-                _ => haystack,
-            };
-            let mut coords = None;
-
-            match &pattern {
-                CompiledPattern::CaseInSensitiveString(s)
-                | CompiledPattern::CaseSensitiveString(s) => {
-                    for (idx, s) in haystack.match_indices(s) {
-                        found_match(
-                            s,
-                            idx,
-                            lines,
-                            stable_idx,
-                            &mut uniq_matches,
-                            &mut coords,
-                            &mut results,
-                        );
-                    }
+        let haystack = match &pattern {
+            CompiledPattern::CaseInSensitiveString(_) => Cow::Owned(haystack.to_lowercase()),
+            _ => haystack,
+        };
+        let mut coords = None;
+
+        match &pattern {
+            CompiledPattern::CaseInSensitiveString(s) | CompiledPattern::CaseSensitiveString(s) => {
+                for (idx, s) in haystack.match_indices(s) {
+                    found_match(
+                        s,
+                        idx,
+                        lines,
+                        stable_idx,
+                        &mut uniq_matches,
+                        &mut coords,
+                        &mut results,
+                    );
                 }
-                CompiledPattern::Regex(re) => {
-                    // Allow for the regex to contain captures
-                    for capture_res in re.captures_iter(&haystack) {
-                        if let Ok(c) = capture_res {
-                            // Look for the captures in reverse order, as index==0 is
-                            // the whole matched string.  We can't just call
-                            // `c.iter().rev()` as the capture iterator isn't double-ended.
-                            for idx in (0..c.len()).rev() {
-                                if let Some(m) = c.get(idx) {
-                                    found_match(
-                                        m.as_str(),
-                                        m.start(),
-                                        lines,
-                                        stable_idx,
-                                        &mut uniq_matches,
-                                        &mut coords,
-                                        &mut results,
-                                    );
-                                    break;
-                                }
+            }
+            CompiledPattern::Regex(re) => {
+                // Allow for the regex to contain captures
+                for capture_res in re.captures_iter(&haystack) {
+                    if let Ok(c) = capture_res {
+                        // Look for the captures in reverse order, as index==0 is
+                        // the whole matched string.  We can't just call
+                        // `c.iter().rev()` as the capture iterator isn't double-ended.
+                        for idx in (0..c.len()).rev() {
+                            if let Some(m) = c.get(idx) {
+                                found_match(
+                                    m.as_str(),
+                                    m.start(),
+                                    lines,
+                                    stable_idx,
+                                    &mut uniq_matches,
+                                    &mut coords,
+                                    &mut results,
+                                );
+                                break;
                             }
                         }
                     }
                 }
             }
+        }
 
-            // Keep iterating
-            true
-        });
+        // Keep iterating
+        true
+    });
 
-        #[derive(Copy, Clone, Debug)]
-        struct Coord {
-            byte_idx: usize,
-            grapheme_idx: usize,
-            stable_row: StableRowIndex,
-        }
+    #[derive(Copy, Clone, Debug)]
+    struct Coord {
+        byte_idx: usize,
+        grapheme_idx: usize,
+        stable_row: StableRowIndex,
+    }
 
-        fn found_match(
-            text: &str,
-            byte_idx: usize,
-            lines: &[&Line],
-            stable_idx: StableRowIndex,
-            uniq_matches: &mut HashMap<String, usize>,
-            coords: &mut Option<Vec<Coord>>,
-            results: &mut Vec<SearchResult>,
-        ) {
-            if coords.is_none() {
-                coords.replace(make_coords(lines, stable_idx));
-            }
-            let coords = coords.as_ref().unwrap();
-
-            let match_id = match uniq_matches.get(text).copied() {
-                Some(id) => id,
-                None => {
-                    let id = uniq_matches.len();
-                    uniq_matches.insert(text.to_owned(), id);
-                    id
-                }
-            };
-            let (start_x, start_y) = haystack_idx_to_coord(byte_idx, coords);
-            let (end_x, end_y) = haystack_idx_to_coord(byte_idx + text.len(), coords);
-            results.push(SearchResult {
-                start_x,
-                start_y,
-                end_x,
-                end_y,
-                match_id,
-            });
+    fn found_match(
+        text: &str,
+        byte_idx: usize,
+        lines: &[&Line],
+        stable_idx: StableRowIndex,
+        uniq_matches: &mut HashMap<String, usize>,
+        coords: &mut Option<Vec<Coord>>,
+        results: &mut Vec<SearchResult>,
+    ) {
+        if coords.is_none() {
+            coords.replace(make_coords(lines, stable_idx));
         }
+        let coords = coords.as_ref().unwrap();
+
+        let match_id = match uniq_matches.get(text).copied() {
+            Some(id) => id,
+            None => {
+                let id = uniq_matches.len();
+                uniq_matches.insert(text.to_owned(), id);
+                id
+            }
+        };
+        let (start_x, start_y) = haystack_idx_to_coord(byte_idx, coords);
+        let (end_x, end_y) = haystack_idx_to_coord(byte_idx + text.len(), coords);
+        results.push(SearchResult {
+            start_x,
+            start_y,
+            end_x,
+            end_y,
+            match_id,
+        });
+    }
 
-        fn make_coords(lines: &[&Line], stable_row: StableRowIndex) -> Vec<Coord> {
-            let mut byte_idx = 0;
-            let mut coords = vec![];
+    fn make_coords(lines: &[&Line], stable_row: StableRowIndex) -> Vec<Coord> {
+        let mut byte_idx = 0;
+        let mut coords = vec![];
 
-            for (row_idx, line) in lines.iter().enumerate() {
-                for cell in line.visible_cells() {
-                    coords.push(Coord {
-                        byte_idx,
-                        grapheme_idx: cell.cell_index(),
-                        stable_row: stable_row + row_idx as StableRowIndex,
-                    });
-                    byte_idx += cell.str().len();
-                }
+        for (row_idx, line) in lines.iter().enumerate() {
+            for cell in line.visible_cells() {
+                coords.push(Coord {
+                    byte_idx,
+                    grapheme_idx: cell.cell_index(),
+                    stable_row: stable_row + row_idx as StableRowIndex,
+                });
+                byte_idx += cell.str().len();
             }
-
-            coords
         }
 
-        fn haystack_idx_to_coord(idx: usize, coords: &[Coord]) -> (usize, StableRowIndex) {
-            let c = coords
-                .binary_search_by(|ele| ele.byte_idx.cmp(&idx))
-                .or_else(|i| -> Result<usize, usize> { Ok(i) })
-                .unwrap();
-            let coord = coords.get(c).map(|c| *c).unwrap_or_else(|| {
-                let last = coords.last().unwrap();
-                Coord {
-                    grapheme_idx: last.grapheme_idx + 1,
-                    ..*last
-                }
-            });
-            (coord.grapheme_idx, coord.stable_row)
-        }
+        coords
+    }
 
-        Ok(results)
+    fn haystack_idx_to_coord(idx: usize, coords: &[Coord]) -> (usize, StableRowIndex) {
+        let c = coords
+            .binary_search_by(|ele| ele.byte_idx.cmp(&idx))
+            .or_else(|i| -> Result<usize, usize> { Ok(i) })
+            .unwrap();
+        let coord = coords.get(c).map(|c| *c).unwrap_or_else(|| {
+            let last = coords.last().unwrap();
+            Coord {
+                grapheme_idx: last.grapheme_idx + 1,
+                ..*last
+            }
+        });
+        (coord.grapheme_idx, coord.stable_row)
     }
+
+    Ok(results)
 }
 
 struct LocalPaneDCSHandler {
@@ -856,13 +885,34 @@ impl wezterm_term::DeviceControlHandler for LocalPaneDCSHandler {
                 {
                     log::info!("tmux -CC mode requested");
 
-                    // Create a new domain to host these tmux tabs
-                    let domain = TmuxDomain::new(self.pane_id);
-                    let tmux_domain = Arc::clone(&domain.inner);
-
-                    let domain: Arc<dyn Domain> = Arc::new(domain);
+                    // Reuse the domain of an earlier tmux client in this pane
+                    // that has since detached, otherwise create a new domain
+                    // to host these tmux tabs
                     let mux = Mux::get();
-                    mux.add_domain(&domain);
+                    let detached = mux.iter_domains().into_iter().find_map(|domain| {
+                        let tmux = domain.downcast_ref::<TmuxDomain>()?;
+                        if tmux.inner.pane_id == self.pane_id
+                            && domain.state() == DomainState::Detached
+                        {
+                            Some(Arc::clone(&tmux.inner))
+                        } else {
+                            None
+                        }
+                    });
+                    let tmux_domain = match detached {
+                        Some(tmux_domain) => {
+                            tmux_domain.reattach();
+                            tmux_domain
+                        }
+                        None => {
+                            let domain = TmuxDomain::new(self.pane_id);
+                            let tmux_domain = Arc::clone(&domain.inner);
+
+                            let domain: Arc<dyn Domain> = Arc::new(domain);
+                            mux.add_domain(&domain);
+                            tmux_domain
+                        }
+                    };
 
                     if let Some(pane) = mux.get_pane(self.pane_id) {
                         let pane = pane.downcast_ref::<LocalPane>().unwrap();
@@ -1022,6 +1072,12 @@ impl LocalPane {
         }
     }
 
+    /// Returns a handle to the serial port that this pane is
+    /// connected to, or None if it isn't connected to one
+    pub fn serial_control(&self) -> Option<portable_pty::serial::SerialControl> {
+        portable_pty::serial::serial_control(&**self.pty.lock())
+    }
+
     #[cfg(unix)]
     fn get_leader(&self, policy: CachePolicy) -> CachedLeaderInfo {
         let mut leader = self.leader.lock();
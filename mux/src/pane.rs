@@ -1,4 +1,5 @@
 use crate::domain::DomainId;
+use crate::recording::RecordingOptions;
 use crate::renderable::*;
 use crate::ExitBehavior;
 use async_trait::async_trait;
@@ -17,8 +18,8 @@ use url::Url;
 use wezterm_dynamic::Value;
 use wezterm_term::color::ColorPalette;
 use wezterm_term::{
-    Clipboard, DownloadHandler, KeyCode, KeyModifiers, MouseEvent, Progress, SemanticZone,
-    StableRowIndex, TerminalConfiguration, TerminalSize,
+    Clipboard, CommandBlock, DownloadHandler, KeyCode, KeyModifiers, MouseEvent, Progress,
+    SemanticType, SemanticZone, StableRowIndex, TerminalConfiguration, TerminalSize,
 };
 
 static PANE_ID: ::std::sync::atomic::AtomicUsize = ::std::sync::atomic::AtomicUsize::new(0);
@@ -239,6 +240,16 @@ pub trait Pane: Downcast + Send + Sync {
     fn reader(&self) -> anyhow::Result<Option<Box<dyn std::io::Read + Send>>>;
     fn writer(&self) -> MappedMutexGuard<dyn std::io::Write>;
     fn resize(&self, size: TerminalSize) -> anyhow::Result<()>;
+    /// Resizes the pane to occupy `size`, measured in the cells of its
+    /// tab.  `cell_size` is the size of a cell of the pane when that
+    /// differs from the cells of the tab; see `Tab::set_pane_cell_size`.
+    fn resize_in_tab(
+        &self,
+        size: TerminalSize,
+        cell_size: Option<TerminalSize>,
+    ) -> anyhow::Result<()> {
+        self.resize(crate::tab::size_in_pane_cells(cell_size, size))
+    }
     /// Called as a hint that the pane is being resized as part of
     /// a zoom-to-fill-all-the-tab-space operation.
     fn set_zoomed(&self, _zoomed: bool) {}
@@ -299,11 +310,44 @@ pub trait Pane: Downcast + Send + Sync {
         Ok(vec![])
     }
 
+    /// Returns the lines in the specified range, like `get_lines`,
+    /// but waits for lines that are not held locally to be
+    /// retrieved rather than returning placeholders for them.
+    async fn fetch_lines(
+        &self,
+        lines: Range<StableRowIndex>,
+    ) -> anyhow::Result<(StableRowIndex, Vec<Line>)> {
+        Ok(self.get_lines(lines))
+    }
+
+    /// Starts recording the output of the pane in the asciicast
+    /// format.  Returns the path of the recording, which is on the
+    /// host where the process of the pane is running.
+    async fn start_recording(&self, _options: RecordingOptions) -> anyhow::Result<String> {
+        anyhow::bail!("recording is not supported for this type of pane")
+    }
+
+    /// Stops recording the pane, returning the path of the
+    /// recording if it was being recorded
+    async fn stop_recording(&self) -> anyhow::Result<Option<String>> {
+        Ok(None)
+    }
+
+    fn is_recording(&self) -> bool {
+        false
+    }
+
     /// Retrieve the set of semantic zones
     fn get_semantic_zones(&self) -> anyhow::Result<Vec<SemanticZone>> {
         Ok(vec![])
     }
 
+    /// Retrieve the set of commands that were run at shell prompts,
+    /// as reported via OSC 133 shell integration
+    fn get_command_blocks(&self) -> anyhow::Result<Vec<CommandBlock>> {
+        Ok(vec![])
+    }
+
     /// Returns true if the terminal has grabbed the mouse and wants to
     /// give the embedded application a chance to process events.
     /// In practice this controls whether the gui will perform local
@@ -540,6 +584,98 @@ pub fn impl_get_lines_via_with_lines<P: Pane + ?Sized>(
     (collector.first, collector.lines)
 }
 
+/// Picks a zone from `zones`, considering only those of `semantic_type`
+/// if it is specified.  `index` is the index amongst the zones being
+/// considered; negative values count backwards from the most recent
+/// zone, so -1 is the most recent zone.
+pub fn resolve_semantic_zone(
+    mut zones: Vec<SemanticZone>,
+    semantic_type: Option<SemanticType>,
+    index: isize,
+) -> anyhow::Result<SemanticZone> {
+    if let Some(semantic_type) = semantic_type {
+        zones.retain(|zone| zone.semantic_type == semantic_type);
+    }
+    let resolved = if index < 0 {
+        zones.len().checked_sub(index.unsigned_abs())
+    } else {
+        Some(index as usize)
+    };
+    resolved
+        .and_then(|idx| zones.get(idx))
+        .copied()
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "zone index {} is out of range; there are {} zones",
+                index,
+                zones.len()
+            )
+        })
+}
+
+/// Returns the text that is covered by the specified semantic zone.
+/// Lines that were wrapped are joined together.
+pub fn get_text_from_semantic_zone<P: Pane + ?Sized>(pane: &P, zone: &SemanticZone) -> String {
+    let mut last_was_wrapped = false;
+    let first_row = zone.start_y;
+    let last_row = zone.end_y;
+
+    fn cols_for_row(zone: &SemanticZone, row: StableRowIndex) -> std::ops::Range<usize> {
+        if row < zone.start_y || row > zone.end_y {
+            0..0
+        } else if zone.start_y == zone.end_y {
+            // A single line zone
+            if zone.start_x <= zone.end_x {
+                zone.start_x..zone.end_x.saturating_add(1)
+            } else {
+                zone.end_x..zone.start_x.saturating_add(1)
+            }
+        } else if row == zone.end_y {
+            // last line of multi-line
+            0..zone.end_x.saturating_add(1)
+        } else if row == zone.start_y {
+            // first line of multi-line
+            zone.start_x..usize::max_value()
+        } else {
+            // some "middle" line of multi-line
+            0..usize::max_value()
+        }
+    }
+
+    let mut s = String::new();
+    for line in pane.get_logical_lines(zone.start_y..zone.end_y + 1) {
+        if !s.is_empty() && !last_was_wrapped {
+            s.push('\n');
+        }
+        let last_idx = line.physical_lines.len().saturating_sub(1);
+        for (idx, phys) in line.physical_lines.iter().enumerate() {
+            let this_row = line.first_row + idx as StableRowIndex;
+            if this_row >= first_row && this_row <= last_row {
+                let last_phys_idx = phys.len().saturating_sub(1);
+
+                let cols = cols_for_row(zone, this_row);
+                let last_col_idx = cols.end.saturating_sub(1).min(last_phys_idx);
+                let col_span = phys.columns_as_str(cols);
+                // Only trim trailing whitespace if we are the last line
+                // in a wrapped sequence
+                if idx == last_idx {
+                    s.push_str(col_span.trim_end());
+                } else {
+                    s.push_str(&col_span);
+                }
+
+                last_was_wrapped = last_col_idx == last_phys_idx
+                    && phys
+                        .get_cell(last_col_idx)
+                        .map(|c| c.attrs().wrapped())
+                        .unwrap_or(false);
+            }
+        }
+    }
+
+    s
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -1083,4 +1219,69 @@ mod test {
             _ => unreachable!(),
         }
     }
+
+    fn zone(
+        start_y: StableRowIndex,
+        start_x: usize,
+        end_y: StableRowIndex,
+        end_x: usize,
+        semantic_type: SemanticType,
+    ) -> SemanticZone {
+        SemanticZone {
+            start_y,
+            start_x,
+            end_y,
+            end_x,
+            semantic_type,
+        }
+    }
+
+    #[test]
+    fn resolve_zones() {
+        use SemanticType::*;
+        let zones = vec![
+            zone(0, 0, 0, 1, Prompt),
+            zone(0, 2, 0, 4, Input),
+            zone(1, 0, 3, 4, Output),
+            zone(4, 0, 4, 1, Prompt),
+        ];
+
+        assert_eq!(
+            resolve_semantic_zone(zones.clone(), None, 1).unwrap(),
+            zones[1]
+        );
+        assert_eq!(
+            resolve_semantic_zone(zones.clone(), None, -1).unwrap(),
+            zones[3]
+        );
+        assert_eq!(
+            resolve_semantic_zone(zones.clone(), Some(Prompt), 1).unwrap(),
+            zones[3]
+        );
+        assert_eq!(
+            resolve_semantic_zone(zones.clone(), Some(Prompt), -2).unwrap(),
+            zones[0]
+        );
+        assert!(resolve_semantic_zone(zones.clone(), Some(Output), 1).is_err());
+        assert!(resolve_semantic_zone(zones.clone(), None, 4).is_err());
+        assert!(resolve_semantic_zone(zones, None, -5).is_err());
+    }
+
+    #[test]
+    fn zone_text() {
+        use SemanticType::*;
+        // The output wraps onto a second line
+        let pane = FakePane {
+            lines: Mutex::new(physical_lines_from_text("$ cmd\nhelloworld\nbye  \n$ ", 5)),
+        };
+
+        assert_eq!(
+            get_text_from_semantic_zone(&pane, &zone(0, 2, 0, 4, Input)),
+            "cmd"
+        );
+        assert_eq!(
+            get_text_from_semantic_zone(&pane, &zone(1, 0, 3, 4, Output)),
+            "helloworld\nbye"
+        );
+    }
 }
@@ -0,0 +1,865 @@
+//! A pane that plays back a recording in the asciicast v2 format.
+//! The events of the recording are fed into a `Terminal` by a
+//! background thread, and the bottom row of the pane shows a
+//! scrubber that reflects the state of the playback.
+//! Keyboard input to the pane controls the playback.
+
+use crate::asciicast::{Cast, Event};
+use crate::domain::{alloc_domain_id, Domain, DomainId, DomainState};
+use crate::localpane::search_terminal;
+use crate::pane::{
+    alloc_pane_id, CachePolicy, CloseReason, ForEachPaneLogicalLine, LogicalLine, Pane, PaneId,
+    Pattern, SearchResult, WithPaneLines,
+};
+use crate::renderable::*;
+use crate::tab::Tab;
+use crate::window::WindowId;
+use crate::{Mux, MuxNotification};
+use anyhow::{anyhow, bail};
+use async_trait::async_trait;
+use config::keyassignment::PlayAsciicast;
+use parking_lot::{Condvar, MappedMutexGuard, Mutex, MutexGuard};
+use portable_pty::CommandBuilder;
+use rangeset::RangeSet;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+use termwiz::cell::{unicode_column_width, CellAttributes};
+use termwiz::surface::{Line, SequenceNo};
+use url::Url;
+use wezterm_term::color::ColorPalette;
+use wezterm_term::{
+    KeyCode, KeyModifiers, MouseEvent, StableRowIndex, Terminal, TerminalSize, TerminalSnapshot,
+};
+
+const DOMAIN_NAME: &str = "AsciicastPlayer";
+
+/// A snapshot of the terminal is retained after at least this
+/// many seconds of recorded time have been played...
+const SNAPSHOT_INTERVAL: f32 = 10.0;
+/// ...or after this many events, whichever comes first.
+/// Seeking backwards restores the closest prior snapshot and
+/// replays the events from that point.
+const SNAPSHOT_EVENTS: usize = 5000;
+/// Snapshots include the scrollback, so only this many are retained.
+/// When there would be more, every other one is discarded and the
+/// spacing between them is doubled.
+const MAX_SNAPSHOTS: usize = 32;
+
+/// The idle time limit that is used when toggling it on for a
+/// recording that doesn't specify one
+const DEFAULT_IDLE_TIME_LIMIT: f32 = 2.0;
+
+const SPEEDS: &[f32] = &[0.25, 0.5, 1.0, 1.5, 2.0, 4.0, 8.0, 16.0];
+
+/// How far the arrow keys seek, in seconds
+const SEEK_STEP: f32 = 5.0;
+const SEEK_STEP_LARGE: f32 = 30.0;
+
+/// How often the scrubber is refreshed while playing
+const STATUS_INTERVAL: Duration = Duration::from_millis(250);
+/// How long the playback thread sleeps while paused, before
+/// checking whether the pane has been closed
+const IDLE_INTERVAL: Duration = Duration::from_secs(1);
+
+struct PlayerDomain {
+    domain_id: DomainId,
+}
+
+#[async_trait(?Send)]
+impl Domain for PlayerDomain {
+    async fn spawn_pane(
+        &self,
+        _size: TerminalSize,
+        _command: Option<CommandBuilder>,
+        _command_dir: Option<String>,
+        _initial_output: Option<String>,
+    ) -> anyhow::Result<Arc<dyn Pane>> {
+        bail!("cannot spawn panes in the asciicast player domain");
+    }
+
+    fn spawnable(&self) -> bool {
+        false
+    }
+
+    fn domain_id(&self) -> DomainId {
+        self.domain_id
+    }
+
+    fn domain_name(&self) -> &str {
+        DOMAIN_NAME
+    }
+
+    async fn attach(&self, _window_id: Option<WindowId>) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn detachable(&self) -> bool {
+        false
+    }
+
+    fn detach(&self) -> anyhow::Result<()> {
+        bail!("detach not implemented for the asciicast player domain");
+    }
+
+    fn state(&self) -> DomainState {
+        DomainState::Attached
+    }
+}
+
+fn player_domain(mux: &Arc<Mux>) -> Arc<dyn Domain> {
+    if let Some(domain) = mux.get_domain_by_name(DOMAIN_NAME) {
+        return domain;
+    }
+    let domain: Arc<dyn Domain> = Arc::new(PlayerDomain {
+        domain_id: alloc_domain_id(),
+    });
+    mux.add_domain(&domain);
+    domain
+}
+
+/// Loads the recording specified by `args` and opens a new tab in
+/// the window that plays it back.  Returns the new tab and pane.
+pub async fn spawn_player_tab(
+    window_id: WindowId,
+    args: &PlayAsciicast,
+) -> anyhow::Result<(Arc<Tab>, Arc<dyn Pane>)> {
+    let path = PathBuf::from(&args.path);
+    let cast = smol::unblock(move || Cast::load(&path)).await?;
+    let name = std::path::Path::new(&args.path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| args.path.clone());
+
+    let mux = Mux::get();
+    let domain = player_domain(&mux);
+    let size = mux
+        .get_active_tab_for_window(window_id)
+        .map(|tab| tab.get_size())
+        .ok_or_else(|| anyhow!("invalid window id {window_id}"))?;
+
+    let pane = PlayerPane::new(domain.domain_id(), size, cast, name, args);
+    let pane: Arc<dyn Pane> = pane;
+
+    let tab = Arc::new(Tab::new(&size));
+    tab.assign_pane(&pane);
+
+    mux.add_tab_and_active_pane(&tab)?;
+    mux.add_tab_to_window(&tab, window_id)?;
+
+    let mut window = mux
+        .get_window_mut(window_id)
+        .ok_or_else(|| anyhow!("invalid window id {window_id}"))?;
+    let tab_idx = window.len().saturating_sub(1);
+    window.save_and_then_set_active(tab_idx);
+
+    Ok((tab, pane))
+}
+
+struct Playback {
+    /// The time of each event on the playback timeline, which is
+    /// the recorded time with the idle periods capped
+    times: Vec<f32>,
+    idle_time_limit: Option<f32>,
+    /// The limit to use when idle time limiting is toggled on
+    default_idle_time_limit: f32,
+    /// Index of the next event to be applied to the terminal
+    next_event: usize,
+    /// When set, the terminal is moved to this event index rather
+    /// than to the one that corresponds to `position`
+    target_event: Option<usize>,
+    /// The playback position on the timeline as of `anchor`
+    position: f32,
+    anchor: Instant,
+    speed: f32,
+    paused: bool,
+    /// Snapshots of the terminal, ordered by the index of the
+    /// next event to be applied after restoring them
+    snapshots: Vec<(usize, TerminalSnapshot)>,
+    /// The multiple of SNAPSHOT_INTERVAL and SNAPSHOT_EVENTS that
+    /// separates snapshots, which grows as they are thinned out
+    snapshot_spacing: usize,
+}
+
+impl Playback {
+    fn duration(&self) -> f32 {
+        self.times.last().copied().unwrap_or(0.)
+    }
+
+    fn position(&self) -> f32 {
+        let position = if self.paused {
+            self.position
+        } else {
+            self.position + self.anchor.elapsed().as_secs_f32() * self.speed
+        };
+        position.clamp(0., self.duration())
+    }
+
+    fn set_position(&mut self, position: f32) {
+        self.position = position.clamp(0., self.duration());
+        self.anchor = Instant::now();
+    }
+
+    fn is_finished(&self) -> bool {
+        self.next_event >= self.times.len() && self.position() >= self.duration()
+    }
+
+    fn compute_times(events: &[Event], idle_time_limit: Option<f32>) -> Vec<f32> {
+        let mut times = Vec::with_capacity(events.len());
+        let mut recorded = 0.;
+        let mut adjusted = 0.;
+        for event in events {
+            let mut delay = (event.0 - recorded).max(0.);
+            if let Some(limit) = idle_time_limit {
+                delay = delay.min(limit);
+            }
+            recorded = event.0;
+            adjusted += delay;
+            times.push(adjusted);
+        }
+        times
+    }
+
+    fn time_of(&self, event_idx: usize) -> f32 {
+        match event_idx {
+            0 => 0.,
+            idx => self.times[idx - 1],
+        }
+    }
+
+    fn set_paused(&mut self, paused: bool) {
+        if !paused && self.is_finished() {
+            self.set_position(0.);
+        } else {
+            let position = self.position();
+            self.set_position(position);
+        }
+        self.paused = paused;
+    }
+
+    fn set_speed(&mut self, speed: f32) {
+        let position = self.position();
+        self.set_position(position);
+        self.speed = speed;
+    }
+
+    fn seek(&mut self, position: f32) {
+        self.set_position(position);
+        let position = self.position;
+        self.target_event = Some(self.times.partition_point(|t| *t <= position));
+    }
+
+    fn step(&mut self, forward: bool) {
+        self.set_paused(true);
+        let idx = match self.target_event.unwrap_or(self.next_event) {
+            idx if forward => (idx + 1).min(self.times.len()),
+            idx => idx.saturating_sub(1),
+        };
+        self.target_event = Some(idx);
+        self.set_position(self.time_of(idx));
+    }
+
+    fn set_idle_time_limit(&mut self, events: &[Event], idle_time_limit: Option<f32>) {
+        // Preserve the time elapsed since the most recent event,
+        // subject to the new limit
+        let since_event = self.position() - self.time_of(self.next_event);
+        self.idle_time_limit = idle_time_limit;
+        self.times = Self::compute_times(events, idle_time_limit);
+        let since_event = match idle_time_limit {
+            Some(limit) => since_event.min(limit),
+            None => since_event,
+        };
+        self.set_position(self.time_of(self.next_event) + since_event);
+    }
+
+    /// Updates the terminal so that the events prior to `idx`,
+    /// and only those, have been applied
+    fn move_to_event(&mut self, events: &[Event], term: &mut Terminal, idx: usize) {
+        if idx < self.next_event {
+            let (snapshot_idx, snapshot) = self
+                .snapshots
+                .iter()
+                .rev()
+                .find(|(snapshot_idx, _)| *snapshot_idx <= idx)
+                .expect("the initial snapshot to be present");
+            term.restore_snapshot(snapshot);
+            self.next_event = *snapshot_idx;
+        }
+
+        while self.next_event < idx {
+            apply_event(term, &events[self.next_event]);
+            self.next_event += 1;
+            self.maybe_snapshot(events, term);
+        }
+    }
+
+    fn maybe_snapshot(&mut self, events: &[Event], term: &Terminal) {
+        let last_idx = self.snapshots.last().map(|(idx, _)| *idx).unwrap_or(0);
+        if self.next_event <= last_idx {
+            return;
+        }
+        let recorded = |idx: usize| if idx == 0 { 0. } else { events[idx - 1].0 };
+        let spacing = self.snapshot_spacing;
+        if self.next_event - last_idx >= SNAPSHOT_EVENTS * spacing
+            || recorded(self.next_event) - recorded(last_idx) >= SNAPSHOT_INTERVAL * spacing as f32
+        {
+            self.snapshots.push((self.next_event, term.snapshot()));
+            if self.snapshots.len() > MAX_SNAPSHOTS {
+                thin_snapshots(&mut self.snapshots);
+                self.snapshot_spacing *= 2;
+            }
+        }
+    }
+}
+
+/// Discards every other snapshot, retaining the initial one
+fn thin_snapshots<T>(snapshots: &mut Vec<(usize, T)>) {
+    let mut position = 0;
+    snapshots.retain(|_| {
+        let keep = position % 2 == 0;
+        position += 1;
+        keep
+    });
+}
+
+fn apply_event(term: &mut Terminal, event: &Event) {
+    match event.1.as_str() {
+        "o" => term.advance_bytes(&event.2),
+        "r" => {
+            let dims = event
+                .2
+                .split_once('x')
+                .and_then(|(cols, rows)| Some((cols.parse().ok()?, rows.parse().ok()?)));
+            match dims {
+                Some((cols, rows)) if cols > 0 && rows > 0 => {
+                    term.resize(scaled_size(term.get_size(), cols, rows));
+                }
+                _ => log::warn!("ignoring invalid resize event {:?}", event.2),
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Returns a size with the specified dimensions in cells and the
+/// same cell size in pixels as `size`
+fn scaled_size(size: TerminalSize, cols: usize, rows: usize) -> TerminalSize {
+    TerminalSize {
+        cols,
+        rows,
+        pixel_width: size.pixel_width / size.cols.max(1) * cols,
+        pixel_height: size.pixel_height / size.rows.max(1) * rows,
+        dpi: size.dpi,
+    }
+}
+
+fn format_time(secs: f32) -> String {
+    let tenths = (secs * 10.).floor() as u64;
+    let mins = tenths / 600;
+    let secs = (tenths % 600) as f32 / 10.;
+    format!("{mins:02}:{secs:04.1}")
+}
+
+/// The text of the scrubber and the title of the pane, as computed
+/// by the playback thread.  This is kept separately from the
+/// playback state so that rendering never waits for a seek.
+struct Status {
+    text: String,
+    title: String,
+    seqno: SequenceNo,
+}
+
+pub struct PlayerPane {
+    pane_id: PaneId,
+    domain_id: DomainId,
+    name: String,
+    events: Vec<Event>,
+    terminal: Mutex<Terminal>,
+    playback: Mutex<Playback>,
+    wakeup: Condvar,
+    status: Mutex<Status>,
+    dead: Mutex<bool>,
+    writer: Mutex<Vec<u8>>,
+}
+
+impl PlayerPane {
+    fn new(
+        domain_id: DomainId,
+        size: TerminalSize,
+        cast: Cast,
+        name: String,
+        args: &PlayAsciicast,
+    ) -> Arc<Self> {
+        let pane_id = alloc_pane_id();
+        let term_size = scaled_size(
+            size,
+            cast.header.width.max(1) as usize,
+            cast.header.height.max(1) as usize,
+        );
+        let terminal = Terminal::new(
+            term_size,
+            Arc::new(config::TermConfig::new()),
+            "WezTerm",
+            config::wezterm_version(),
+            Box::new(std::io::sink()),
+        );
+
+        let idle_time_limit = args.idle_time_limit.or(cast.header.idle_time_limit);
+        let playback = Playback {
+            times: Playback::compute_times(&cast.events, idle_time_limit),
+            idle_time_limit,
+            default_idle_time_limit: idle_time_limit.unwrap_or(DEFAULT_IDLE_TIME_LIMIT),
+            next_event: 0,
+            target_event: None,
+            position: 0.,
+            anchor: Instant::now(),
+            speed: if args.speed > 0. { args.speed } else { 1. },
+            paused: args.start_paused,
+            snapshots: vec![(0, terminal.snapshot())],
+            snapshot_spacing: 1,
+        };
+
+        let pane = Arc::new(Self {
+            pane_id,
+            domain_id,
+            name,
+            events: cast.events,
+            terminal: Mutex::new(terminal),
+            playback: Mutex::new(playback),
+            wakeup: Condvar::new(),
+            status: Mutex::new(Status {
+                text: String::new(),
+                title: String::new(),
+                seqno: 0,
+            }),
+            dead: Mutex::new(false),
+            writer: Mutex::new(Vec::new()),
+        });
+
+        let weak = Arc::downgrade(&pane);
+        std::thread::spawn(move || run_playback(weak));
+
+        pane
+    }
+
+    /// Brings the terminal and the scrubber up to date with the
+    /// playback position.  Returns how long to wait before the
+    /// next update is due.
+    fn update(&self, playback: &mut Playback) -> Duration {
+        let mut term = self.terminal.lock();
+        let position = playback.position();
+        let target = match playback.target_event.take() {
+            Some(idx) => idx,
+            None if playback.paused => playback.next_event,
+            None => playback.times.partition_point(|t| *t <= position),
+        };
+        let mut changed = target != playback.next_event;
+        playback.move_to_event(&self.events, &mut term, target);
+
+        if !playback.paused && playback.is_finished() {
+            playback.set_paused(true);
+        }
+
+        let cols = term.get_size().cols;
+        let (text, title) = self.render_status(playback, cols);
+        {
+            let mut status = self.status.lock();
+            if status.text != text || status.title != title {
+                term.increment_seqno();
+                status.text = text;
+                status.title = title;
+                status.seqno = term.current_seqno();
+                changed = true;
+            }
+        }
+        drop(term);
+
+        if changed {
+            Mux::notify_from_any_thread(MuxNotification::PaneOutput(self.pane_id));
+        }
+
+        if playback.paused {
+            return IDLE_INTERVAL;
+        }
+        match playback.times.get(playback.next_event) {
+            Some(next) => {
+                let delay = ((next - playback.position()) / playback.speed).max(0.);
+                Duration::from_secs_f32(delay).min(STATUS_INTERVAL)
+            }
+            None => STATUS_INTERVAL,
+        }
+    }
+
+    fn render_status(&self, playback: &Playback, cols: usize) -> (String, String) {
+        let icon = if playback.paused {
+            '\u{f03e4}'
+        } else {
+            '\u{f040a}'
+        };
+        let position = playback.position();
+        let duration = playback.duration();
+
+        let left = format!(
+            " {icon} {} / {} ",
+            format_time(position),
+            format_time(duration)
+        );
+        let idle = match playback.idle_time_limit {
+            Some(limit) => format!("  idle {limit}s"),
+            None => String::new(),
+        };
+        let right = format!(
+            " {}x{idle}  {}/{} ",
+            playback.speed,
+            playback.next_event,
+            self.events.len()
+        );
+        let hint =
+            " space:pause \u{2190}\u{2192}:seek \u{2191}\u{2193}:speed ,.:step i:idle q:quit ";
+
+        let fixed = unicode_column_width(&left, None) + unicode_column_width(&right, None);
+        let mut text = left;
+        let mut bar_width = cols.saturating_sub(fixed);
+        let show_hint = bar_width >= unicode_column_width(hint, None) + 20;
+        if show_hint {
+            bar_width -= unicode_column_width(hint, None);
+        }
+        if bar_width >= 4 {
+            let fraction = if duration > 0. {
+                position / duration
+            } else {
+                1.
+            };
+            let filled = ((bar_width - 1) as f32 * fraction).round() as usize;
+            text.push_str(&"\u{2501}".repeat(filled));
+            text.push('\u{25cf}');
+            text.push_str(&"\u{2500}".repeat(bar_width - 1 - filled));
+        }
+        text.push_str(&right);
+        if show_hint {
+            text.push_str(hint);
+        }
+        let width = unicode_column_width(&text, None);
+        text.push_str(&" ".repeat(cols.saturating_sub(width)));
+
+        let title = format!("{icon} {}", self.name);
+        (text, title)
+    }
+
+    /// Applies a change to the playback and wakes up the
+    /// playback thread to bring the display up to date
+    fn control<F: FnOnce(&mut Playback, &[Event])>(&self, f: F) {
+        let mut playback = self.playback.lock();
+        f(&mut playback, &self.events);
+        self.wakeup.notify_one();
+    }
+
+    /// The stable index of the row that holds the scrubber,
+    /// which immediately follows the rows of the terminal
+    fn status_row(term: &Terminal) -> StableRowIndex {
+        let screen = term.screen();
+        screen.visible_row_to_stable_row(0) + screen.physical_rows as StableRowIndex
+    }
+
+    fn status_line(&self) -> Line {
+        let status = self.status.lock();
+        let mut attrs = CellAttributes::default();
+        attrs.set_reverse(true);
+        Line::from_text(&status.text, &attrs, status.seqno, None)
+    }
+}
+
+fn run_playback(pane: Weak<PlayerPane>) {
+    loop {
+        let pane = match pane.upgrade() {
+            Some(pane) => pane,
+            None => return,
+        };
+        if pane.is_dead() {
+            return;
+        }
+        let mut playback = pane.playback.lock();
+        let wait = pane.update(&mut playback);
+        pane.wakeup.wait_for(&mut playback, wait);
+    }
+}
+
+#[async_trait(?Send)]
+impl Pane for PlayerPane {
+    fn pane_id(&self) -> PaneId {
+        self.pane_id
+    }
+
+    fn get_cursor_position(&self) -> StableCursorPosition {
+        terminal_get_cursor_position(&mut self.terminal.lock())
+    }
+
+    fn get_current_seqno(&self) -> SequenceNo {
+        self.terminal.lock().current_seqno()
+    }
+
+    fn get_changed_since(
+        &self,
+        lines: Range<StableRowIndex>,
+        seqno: SequenceNo,
+    ) -> RangeSet<StableRowIndex> {
+        let mut term = self.terminal.lock();
+        let status_row = Self::status_row(&term);
+        let mut changed =
+            terminal_get_dirty_lines(&mut term, lines.start..lines.end.min(status_row), seqno);
+        if lines.contains(&status_row) && self.status.lock().seqno > seqno {
+            changed.add(status_row);
+        }
+        changed
+    }
+
+    fn for_each_logical_line_in_stable_range_mut(
+        &self,
+        lines: Range<StableRowIndex>,
+        for_line: &mut dyn ForEachPaneLogicalLine,
+    ) {
+        let mut term = self.terminal.lock();
+        let status_row = Self::status_row(&term);
+        terminal_for_each_logical_line_in_stable_range_mut(
+            &mut term,
+            lines.start..lines.end.min(status_row),
+            for_line,
+        );
+    }
+
+    fn get_logical_lines(&self, lines: Range<StableRowIndex>) -> Vec<LogicalLine> {
+        crate::pane::impl_get_logical_lines_via_get_lines(self, lines)
+    }
+
+    fn with_lines_mut(&self, lines: Range<StableRowIndex>, with_lines: &mut dyn WithPaneLines) {
+        let mut term = self.terminal.lock();
+        let status_row = Self::status_row(&term);
+        let mut status = if lines.contains(&status_row) {
+            Some(self.status_line())
+        } else {
+            None
+        };
+
+        let term_lines = lines.start..lines.end.min(status_row);
+        if term_lines.start >= term_lines.end {
+            if let Some(status) = status.as_mut() {
+                with_lines.with_lines_mut(status_row, &mut [status]);
+            }
+            return;
+        }
+
+        term.screen_mut()
+            .with_stable_lines_mut(&term_lines, |first, term_lines| {
+                let mut all: Vec<&mut Line> =
+                    term_lines.iter_mut().map(|line| &mut **line).collect();
+                if let Some(status) = status.as_mut() {
+                    all.push(status);
+                }
+                with_lines.with_lines_mut(first, &mut all);
+            });
+    }
+
+    fn get_lines(&self, lines: Range<StableRowIndex>) -> (StableRowIndex, Vec<Line>) {
+        let mut term = self.terminal.lock();
+        let status_row = Self::status_row(&term);
+        if lines.start >= status_row {
+            return if lines.contains(&status_row) {
+                (status_row, vec![self.status_line()])
+            } else {
+                (lines.start, vec![])
+            };
+        }
+
+        let (first, mut result) =
+            terminal_get_lines(&mut term, lines.start..lines.end.min(status_row));
+        if lines.contains(&status_row) {
+            result.push(self.status_line());
+        }
+        (first, result)
+    }
+
+    fn get_dimensions(&self) -> RenderableDimensions {
+        let mut dims = terminal_get_dimensions(&mut self.terminal.lock());
+        dims.viewport_rows += 1;
+        dims.scrollback_rows += 1;
+        dims
+    }
+
+    fn get_title(&self) -> String {
+        self.status.lock().title.clone()
+    }
+
+    fn can_close_without_prompting(&self, _reason: CloseReason) -> bool {
+        true
+    }
+
+    fn send_paste(&self, _text: &str) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn reader(&self) -> anyhow::Result<Option<Box<dyn std::io::Read + Send>>> {
+        Ok(None)
+    }
+
+    fn writer(&self) -> MappedMutexGuard<dyn std::io::Write> {
+        MutexGuard::map(self.writer.lock(), |writer| {
+            let w: &mut dyn std::io::Write = writer;
+            w
+        })
+    }
+
+    fn resize(&self, _size: TerminalSize) -> anyhow::Result<()> {
+        // The size of the terminal is dictated by the recording
+        Ok(())
+    }
+
+    fn key_down(&self, key: KeyCode, mods: KeyModifiers) -> anyhow::Result<()> {
+        let seek_step = if mods.contains(KeyModifiers::SHIFT) {
+            SEEK_STEP_LARGE
+        } else {
+            SEEK_STEP
+        };
+        match key {
+            KeyCode::Char(' ') | KeyCode::Char('k') => {
+                self.control(|playback, _| playback.set_paused(!playback.paused))
+            }
+            KeyCode::LeftArrow | KeyCode::Char('h') => {
+                self.control(|playback, _| playback.seek(playback.position() - seek_step))
+            }
+            KeyCode::RightArrow | KeyCode::Char('l') => {
+                self.control(|playback, _| playback.seek(playback.position() + seek_step))
+            }
+            KeyCode::UpArrow | KeyCode::Char('+') | KeyCode::Char('=') => {
+                self.control(|playback, _| {
+                    let speed = SPEEDS
+                        .iter()
+                        .copied()
+                        .find(|s| *s > playback.speed)
+                        .unwrap_or(playback.speed);
+                    playback.set_speed(speed);
+                })
+            }
+            KeyCode::DownArrow | KeyCode::Char('-') => self.control(|playback, _| {
+                let speed = SPEEDS
+                    .iter()
+                    .rev()
+                    .copied()
+                    .find(|s| *s < playback.speed)
+                    .unwrap_or(playback.speed);
+                playback.set_speed(speed);
+            }),
+            KeyCode::Char('.') => self.control(|playback, _| playback.step(true)),
+            KeyCode::Char(',') => self.control(|playback, _| playback.step(false)),
+            KeyCode::Char('i') => self.control(|playback, events| {
+                let limit = match playback.idle_time_limit {
+                    Some(_) => None,
+                    None => Some(playback.default_idle_time_limit),
+                };
+                playback.set_idle_time_limit(events, limit);
+            }),
+            KeyCode::Home | KeyCode::Char('g') => self.control(|playback, _| playback.seek(0.)),
+            KeyCode::End | KeyCode::Char('G') => {
+                self.control(|playback, _| playback.seek(playback.duration()))
+            }
+            KeyCode::Char(c @ '0'..='9') => self.control(|playback, _| {
+                let fraction = c.to_digit(10).unwrap_or(0) as f32 / 10.;
+                playback.seek(playback.duration() * fraction)
+            }),
+            KeyCode::Char('q') => {
+                self.kill();
+                let pane_id = self.pane_id;
+                promise::spawn::spawn_into_main_thread(async move {
+                    Mux::get().remove_pane(pane_id);
+                })
+                .detach();
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn key_up(&self, _key: KeyCode, _mods: KeyModifiers) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn mouse_event(&self, _event: MouseEvent) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn kill(&self) {
+        *self.dead.lock() = true;
+        self.wakeup.notify_one();
+    }
+
+    fn is_dead(&self) -> bool {
+        *self.dead.lock()
+    }
+
+    fn palette(&self) -> ColorPalette {
+        self.terminal.lock().palette()
+    }
+
+    fn domain_id(&self) -> DomainId {
+        self.domain_id
+    }
+
+    fn is_mouse_grabbed(&self) -> bool {
+        false
+    }
+
+    fn is_alt_screen_active(&self) -> bool {
+        self.terminal.lock().is_alt_screen_active()
+    }
+
+    fn get_current_working_dir(&self, _policy: CachePolicy) -> Option<Url> {
+        self.terminal.lock().get_current_dir().cloned()
+    }
+
+    async fn search(
+        &self,
+        pattern: Pattern,
+        range: Range<StableRowIndex>,
+        limit: Option<u32>,
+    ) -> anyhow::Result<Vec<SearchResult>> {
+        search_terminal(&self.terminal.lock(), pattern, range, limit)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn event(time: f32) -> Event {
+        Event(time, "o".to_string(), "x".to_string())
+    }
+
+    #[test]
+    fn idle_time_limit() {
+        let events = vec![event(0.5), event(1.0), event(11.0), event(11.5)];
+        assert_eq!(
+            Playback::compute_times(&events, None),
+            vec![0.5, 1.0, 11.0, 11.5]
+        );
+        assert_eq!(
+            Playback::compute_times(&events, Some(2.0)),
+            vec![0.5, 1.0, 3.0, 3.5]
+        );
+    }
+
+    #[test]
+    fn snapshot_thinning() {
+        let mut snapshots: Vec<(usize, ())> = (0..=MAX_SNAPSHOTS).map(|idx| (idx, ())).collect();
+        thin_snapshots(&mut snapshots);
+        assert_eq!(snapshots.len(), MAX_SNAPSHOTS / 2 + 1);
+        assert_eq!(snapshots[0].0, 0);
+        assert!(snapshots.iter().all(|(idx, _)| idx % 2 == 0));
+    }
+
+    #[test]
+    fn time_format() {
+        assert_eq!(format_time(0.), "00:00.0");
+        assert_eq!(format_time(65.25), "01:05.2");
+        assert_eq!(format_time(600.), "10:00.0");
+    }
+}
@@ -0,0 +1,340 @@
+//! Recording the output of live panes in the asciicast v2 format
+use crate::asciicast::{CastWriter, Header};
+use crate::pane::{Pane, PaneId};
+use crate::{Mux, MuxNotification};
+use anyhow::{bail, Context};
+use parking_lot::Mutex;
+use portable_pty::PtySize;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Sender};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Instant;
+use wezterm_dynamic::{FromDynamic, ToDynamic};
+
+lazy_static::lazy_static! {
+    static ref RECORDINGS: Mutex<HashMap<PaneId, Recording>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize, FromDynamic, ToDynamic)]
+pub struct RecordingOptions {
+    /// Where to save the recording.  If not specified, a file
+    /// in the `recordings` directory of the data directory is used
+    #[dynamic(default)]
+    pub path: Option<PathBuf>,
+    /// Also record the data that is sent to the pane as
+    /// asciicast input (`"i"`) events
+    #[dynamic(default)]
+    pub capture_input: bool,
+}
+
+struct Recording {
+    path: PathBuf,
+    capture_input: bool,
+    running: Arc<AtomicBool>,
+    events: Sender<CastEvent>,
+    writer: Option<JoinHandle<()>>,
+}
+
+/// The events that the notification callback passes to the thread
+/// that writes the recording, stamped with the time of the event
+enum CastEvent {
+    Output(f32, Arc<Vec<u8>>),
+    Input(f32, Arc<Vec<u8>>),
+    Resize(f32, usize, usize),
+    /// Flushes the recording and ends the thread
+    Finish,
+}
+
+/// Tracks the time and size of the pane as notifications arrive
+struct RecordingClock {
+    start: Instant,
+    cols: usize,
+    rows: usize,
+}
+
+impl RecordingClock {
+    fn elapsed(&self) -> f32 {
+        self.start.elapsed().as_secs_f32()
+    }
+
+    /// Returns a resize event if the pane has changed size
+    /// since the last event was recorded
+    fn check_size(&mut self, pane_id: PaneId) -> Option<CastEvent> {
+        let dims = Mux::try_get()
+            .and_then(|mux| mux.get_pane(pane_id))?
+            .get_dimensions();
+        if dims.cols != self.cols || dims.viewport_rows != self.rows {
+            self.cols = dims.cols;
+            self.rows = dims.viewport_rows;
+            Some(CastEvent::Resize(self.elapsed(), self.cols, self.rows))
+        } else {
+            None
+        }
+    }
+
+    /// Converts a notification about the pane into the events
+    /// that it adds to the recording
+    fn events(&mut self, pane_id: PaneId, n: &MuxNotification) -> Vec<CastEvent> {
+        match n {
+            MuxNotification::PaneRawOutput { data, .. } => {
+                let mut events: Vec<CastEvent> = self.check_size(pane_id).into_iter().collect();
+                events.push(CastEvent::Output(self.elapsed(), Arc::clone(data)));
+                events
+            }
+            MuxNotification::PaneRawInput { data, .. } => {
+                vec![CastEvent::Input(self.elapsed(), Arc::clone(data))]
+            }
+            MuxNotification::TabResized(_) => self.check_size(pane_id).into_iter().collect(),
+            _ => vec![],
+        }
+    }
+}
+
+struct RecordingState<W: Write> {
+    writer: CastWriter<W>,
+}
+
+impl<W: Write> RecordingState<W> {
+    /// Writes the event to the recording.  Returns false once
+    /// the recording is finished.
+    fn process(&mut self, event: CastEvent) -> std::io::Result<bool> {
+        match event {
+            CastEvent::Output(elapsed, data) => self.writer.output(elapsed, &data)?,
+            CastEvent::Input(elapsed, data) => self.writer.input(elapsed, &data)?,
+            CastEvent::Resize(elapsed, cols, rows) => self.writer.resize(elapsed, cols, rows)?,
+            CastEvent::Finish => {
+                self.writer.flush()?;
+                return Ok(false);
+            }
+        }
+        self.writer.flush()?;
+        Ok(true)
+    }
+}
+
+/// Returns true if the output of the pane is being recorded
+/// by this process
+pub fn is_recording(pane_id: PaneId) -> bool {
+    RECORDINGS.lock().contains_key(&pane_id)
+}
+
+/// Computes a fresh file name for a recording of the pane
+pub fn default_recording_path(pane_id: PaneId) -> PathBuf {
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+    config::DATA_DIR
+        .join("recordings")
+        .join(format!("wezterm-pane-{pane_id}-{stamp}.cast"))
+}
+
+/// Starts recording the output of the pane, whose pty must be
+/// managed by this process.  Returns the path of the recording.
+pub fn start_recording(pane: &dyn Pane, options: &RecordingOptions) -> anyhow::Result<PathBuf> {
+    let pane_id = pane.pane_id();
+    // Hold the lock until the recording is registered, so that
+    // concurrent requests can't both start recording the pane
+    let mut recordings = RECORDINGS.lock();
+    if let Some(recording) = recordings.get(&pane_id) {
+        bail!(
+            "pane {pane_id} is already being recorded to {}",
+            recording.path.display()
+        );
+    }
+
+    let path = options
+        .path
+        .clone()
+        .unwrap_or_else(|| default_recording_path(pane_id));
+    let file = create_file(&path)?;
+
+    let dims = pane.get_dimensions();
+    let size = PtySize {
+        rows: dims.viewport_rows as u16,
+        cols: dims.cols as u16,
+        pixel_width: 0,
+        pixel_height: 0,
+    };
+    let mut header = Header::new(&config::configuration(), size, &[]);
+    header.title = Some(pane.get_title());
+
+    let mut state = RecordingState {
+        writer: CastWriter::new(BufWriter::new(file), &header)?,
+    };
+    let mut clock = RecordingClock {
+        start: Instant::now(),
+        cols: dims.cols,
+        rows: dims.viewport_rows,
+    };
+    let running = Arc::new(AtomicBool::new(true));
+
+    // Notifications are delivered on whichever thread produced them,
+    // so the recording is written by a thread of its own
+    let (tx, rx) = channel();
+    let writer = {
+        let running = Arc::clone(&running);
+        std::thread::Builder::new()
+            .name(format!("recording pane {pane_id}"))
+            .spawn(move || {
+                while let Ok(event) = rx.recv() {
+                    match state.process(event) {
+                        Ok(true) => {}
+                        Ok(false) => break,
+                        Err(err) => {
+                            log::error!("recording pane {pane_id}: {err:#}");
+                            // Stopping joins this thread, so leave it to
+                            // the main thread
+                            promise::spawn::spawn_into_main_thread(async move {
+                                let current = RECORDINGS
+                                    .lock()
+                                    .get(&pane_id)
+                                    .map_or(false, |r| Arc::ptr_eq(&r.running, &running));
+                                if current {
+                                    stop_recording(pane_id);
+                                }
+                            })
+                            .detach();
+                            break;
+                        }
+                    }
+                }
+            })?
+    };
+
+    recordings.insert(
+        pane_id,
+        Recording {
+            path: path.clone(),
+            capture_input: options.capture_input,
+            running: Arc::clone(&running),
+            events: tx.clone(),
+            writer: Some(writer),
+        },
+    );
+    drop(recordings);
+
+    let mux = Mux::get();
+    mux.add_output_tap(pane_id);
+    if options.capture_input {
+        mux.add_input_tap(pane_id);
+    }
+
+    mux.subscribe(move |n| {
+        if !running.load(Ordering::Relaxed) {
+            return false;
+        }
+        match &n {
+            MuxNotification::PaneRemoved(id) if *id == pane_id => {
+                stop_recording(pane_id);
+                return false;
+            }
+            MuxNotification::PaneRawOutput { pane_id: id, .. }
+            | MuxNotification::PaneRawInput { pane_id: id, .. }
+                if *id != pane_id =>
+            {
+                return true;
+            }
+            _ => {}
+        }
+        for event in clock.events(pane_id, &n) {
+            if tx.send(event).is_err() {
+                // The writer has given up
+                return false;
+            }
+        }
+        true
+    });
+
+    mux.notify(MuxNotification::PaneRecordingChanged(pane_id));
+    Ok(path)
+}
+
+/// Stops recording the pane, returning the path of the
+/// recording, if there was one.  The recording is complete
+/// by the time that this returns.
+pub fn stop_recording(pane_id: PaneId) -> Option<PathBuf> {
+    let mut recording = RECORDINGS.lock().remove(&pane_id)?;
+    recording.running.store(false, Ordering::Relaxed);
+    // The writer drains the events that were already sent
+    // before it flushes and finishes
+    recording.events.send(CastEvent::Finish).ok();
+    if let Some(writer) = recording.writer.take() {
+        writer.join().ok();
+    }
+    if let Some(mux) = Mux::try_get() {
+        mux.remove_output_tap(pane_id);
+        if recording.capture_input {
+            mux.remove_input_tap(pane_id);
+        }
+    }
+    // This may be called from within a notification callback,
+    // so the notification has to be deferred
+    promise::spawn::spawn_into_main_thread(async move {
+        if let Some(mux) = Mux::try_get() {
+            mux.notify(MuxNotification::PaneRecordingChanged(pane_id));
+        }
+    })
+    .detach();
+    Some(recording.path)
+}
+
+fn create_file(path: &Path) -> anyhow::Result<File> {
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("creating directory {}", parent.display()))?;
+        }
+    }
+    File::create(path).with_context(|| format!("creating recording {}", path.display()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn recording_state() -> RecordingState<Vec<u8>> {
+        let header = Header {
+            version: 2,
+            width: 80,
+            height: 24,
+            ..Default::default()
+        };
+        RecordingState {
+            writer: CastWriter::new(vec![], &header).unwrap(),
+        }
+    }
+
+    #[test]
+    fn recording_state_writes_events() {
+        let mut state = recording_state();
+        assert!(state
+            .process(CastEvent::Output(0.5, Arc::new(b"hello".to_vec())))
+            .unwrap());
+        assert!(state.process(CastEvent::Resize(1.0, 100, 30)).unwrap());
+        assert!(state
+            .process(CastEvent::Input(1.5, Arc::new(b"ls\r".to_vec())))
+            .unwrap());
+        assert!(!state.process(CastEvent::Finish).unwrap());
+
+        let data = state.writer.get_ref().as_slice();
+        let cast = crate::asciicast::Cast::parse(data).unwrap();
+        assert_eq!(cast.header.width, 80);
+        let events: Vec<(f32, &str, &str)> = cast
+            .events
+            .iter()
+            .map(|event| (event.0, event.1.as_str(), event.2.as_str()))
+            .collect();
+        assert_eq!(
+            events,
+            vec![
+                (0.5, "o", "hello"),
+                (1.0, "r", "100x30"),
+                (1.5, "i", "ls\r")
+            ]
+        );
+    }
+}
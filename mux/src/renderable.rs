@@ -5,7 +5,7 @@ use serde::{Deserialize, Serialize};
 use std::ops::Range;
 use termwiz::surface::SequenceNo;
 use wezterm_dynamic::{FromDynamic, ToDynamic};
-use wezterm_term::{Line, StableRowIndex, Terminal};
+use wezterm_term::{Line, SemanticType, StableRowIndex, Terminal};
 
 /// Describes the location of the cursor
 #[derive(
@@ -91,10 +91,7 @@ where
     F: FnMut(StableRowIndex, &[&Line]),
 {
     let screen = term.screen_mut();
-    let phys_range = screen.stable_range(&lines);
-    let first = screen.phys_to_stable_row_index(phys_range.start);
-
-    screen.with_phys_lines(phys_range, |lines| func(first, lines));
+    screen.with_stable_lines(&lines, |first, lines| func(first, lines));
 }
 
 /// Implements Pane::with_lines_mut for Terminal
@@ -104,10 +101,9 @@ pub fn terminal_with_lines_mut(
     with_lines: &mut dyn WithPaneLines,
 ) {
     let screen = term.screen_mut();
-    let phys_range = screen.stable_range(&lines);
-    let first = screen.phys_to_stable_row_index(phys_range.start);
-
-    screen.with_phys_lines_mut(phys_range, |lines| with_lines.with_lines_mut(first, lines));
+    screen.with_stable_lines_mut(&lines, |first, lines| {
+        with_lines.with_lines_mut(first, lines)
+    });
 }
 
 /// Implements Pane::get_lines for Terminal
@@ -116,12 +112,7 @@ pub fn terminal_get_lines(
     lines: Range<StableRowIndex>,
 ) -> (StableRowIndex, Vec<Line>) {
     let screen = term.screen_mut();
-    let phys_range = screen.stable_range(&lines);
-
-    let first = screen.phys_to_stable_row_index(phys_range.start);
-    let lines = screen.lines_in_phys_range(phys_range);
-
-    (first, lines)
+    screen.lines_in_stable_range(&lines)
 }
 
 /// Implements Pane::get_dimensions for Terminal
@@ -131,12 +122,122 @@ pub fn terminal_get_dimensions(term: &mut Terminal) -> RenderableDimensions {
     RenderableDimensions {
         cols: screen.physical_cols,
         viewport_rows: screen.physical_rows,
-        scrollback_rows: screen.scrollback_rows(),
+        scrollback_rows: screen.scrollback_rows() + screen.cold_scrollback_rows(),
         physical_top: screen.visible_row_to_stable_row(0),
-        scrollback_top: screen.phys_to_stable_row_index(0),
+        scrollback_top: screen.scrollback_top(),
         dpi: screen.dpi,
         pixel_width: size.pixel_width,
         pixel_height: size.pixel_height,
         reverse_video: term.get_reverse_video(),
     }
 }
+
+/// Converts a run of physical lines into logical lines of text,
+/// joining together lines that were wrapped.
+/// If `output_only` is true, only cells that were marked as command
+/// output via semantic zones are included, and logical lines that
+/// have no output cells are omitted.
+/// A trailing logical line that wraps past the end of `lines` is
+/// incomplete and is not included; the second element of the returned
+/// tuple is the number of physical lines that were consumed.
+pub fn lines_to_text(lines: &[Line], output_only: bool) -> (Vec<String>, usize) {
+    let mut result = vec![];
+    let mut consumed = 0;
+    let mut text = String::new();
+    let mut has_output = false;
+
+    for (idx, line) in lines.iter().enumerate() {
+        if output_only {
+            for cell in line.visible_cells() {
+                if cell.attrs().semantic_type() == SemanticType::Output {
+                    text.push_str(cell.str());
+                    has_output = true;
+                }
+            }
+        } else {
+            text.push_str(&line.as_str());
+        }
+
+        if line.last_cell_was_wrapped() {
+            continue;
+        }
+
+        let logical = std::mem::take(&mut text);
+        if !output_only || has_output {
+            result.push(logical.trim_end().to_string());
+        }
+        has_output = false;
+        consumed = idx + 1;
+    }
+
+    (result, consumed)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use termwiz::cell::CellAttributes;
+    use termwiz::surface::SEQ_ZERO;
+
+    fn line(text: &str, wrapped: bool) -> Line {
+        let mut line = Line::from_text(text, &CellAttributes::default(), SEQ_ZERO, None);
+        if wrapped {
+            line.set_last_cell_was_wrapped(true, SEQ_ZERO);
+        }
+        line
+    }
+
+    fn zoned(text: &str, semantic_type: SemanticType) -> Line {
+        let mut attrs = CellAttributes::default();
+        attrs.set_semantic_type(semantic_type);
+        Line::from_text(text, &attrs, SEQ_ZERO, None)
+    }
+
+    /// Produces a line holding a prompt followed by output
+    fn prompt_and_output(prompt: &str, output: &str) -> Line {
+        let mut line = zoned(prompt, SemanticType::Prompt);
+        line.append_line(zoned(output, SemanticType::Output), SEQ_ZERO);
+        line
+    }
+
+    #[test]
+    fn wrapped_lines_are_joined() {
+        let lines = vec![
+            line("hello ", true),
+            line("world", false),
+            line("next", false),
+        ];
+        assert_eq!(
+            lines_to_text(&lines, false),
+            (vec!["hello world".to_string(), "next".to_string()], 3)
+        );
+    }
+
+    #[test]
+    fn trailing_whitespace_is_trimmed() {
+        let lines = vec![line("text   ", false), line("    ", false)];
+        assert_eq!(
+            lines_to_text(&lines, false),
+            (vec!["text".to_string(), "".to_string()], 2)
+        );
+    }
+
+    #[test]
+    fn incomplete_wrapped_line_is_not_consumed() {
+        let lines = vec![line("done", false), line("part", true)];
+        assert_eq!(lines_to_text(&lines, false), (vec!["done".to_string()], 1));
+    }
+
+    #[test]
+    fn output_only() {
+        let lines = vec![
+            prompt_and_output("$ ", "ls"),
+            zoned("$ no output here", SemanticType::Input),
+            prompt_and_output("", "file  "),
+        ];
+        assert_eq!(
+            lines_to_text(&lines, true),
+            (vec!["ls".to_string(), "file".to_string()], 3)
+        );
+    }
+}
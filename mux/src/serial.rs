@@ -0,0 +1,288 @@
+//! Changing the line settings of panes that are connected to a
+//! serial port, and logging the data that they receive.
+use crate::localpane::LocalPane;
+use crate::pane::{Pane, PaneId};
+use crate::{Mux, MuxNotification};
+use anyhow::{anyhow, bail, Context};
+use config::{SerialFlowControl, SerialLineSettings, SerialParity, SerialStopBits};
+use parking_lot::Mutex;
+use portable_pty::serial::{CharSize, FlowControl, Parity, SerialControl, StopBits};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::channel;
+use std::sync::Arc;
+
+lazy_static::lazy_static! {
+    static ref LOGS: Mutex<HashMap<PaneId, SerialLog>> = Mutex::new(HashMap::new());
+}
+
+struct SerialLog {
+    path: PathBuf,
+    running: Arc<AtomicBool>,
+}
+
+/// Returns the serial port control handle for the pane, or an error
+/// if the pane is not connected to a serial port
+pub fn serial_control_for_pane(pane: &dyn Pane) -> anyhow::Result<SerialControl> {
+    pane.downcast_ref::<LocalPane>()
+        .and_then(|pane| pane.serial_control())
+        .ok_or_else(|| anyhow!("pane {} is not connected to a serial port", pane.pane_id()))
+}
+
+pub(crate) fn char_size(data_bits: u8) -> anyhow::Result<CharSize> {
+    Ok(match data_bits {
+        5 => CharSize::Bits5,
+        6 => CharSize::Bits6,
+        7 => CharSize::Bits7,
+        8 => CharSize::Bits8,
+        _ => bail!("invalid data_bits {data_bits}; expected a value from 5 to 8"),
+    })
+}
+
+pub(crate) fn parity(parity: SerialParity) -> Parity {
+    match parity {
+        SerialParity::None => Parity::None,
+        SerialParity::Odd => Parity::Odd,
+        SerialParity::Even => Parity::Even,
+    }
+}
+
+pub(crate) fn stop_bits(stop_bits: SerialStopBits) -> StopBits {
+    match stop_bits {
+        SerialStopBits::One => StopBits::One,
+        SerialStopBits::Two => StopBits::Two,
+    }
+}
+
+pub(crate) fn flow_control(flow_control: SerialFlowControl) -> FlowControl {
+    match flow_control {
+        SerialFlowControl::None => FlowControl::None,
+        SerialFlowControl::XonXoff => FlowControl::XonXoff,
+        SerialFlowControl::RtsCts => FlowControl::RtsCts,
+    }
+}
+
+/// Applies the specified settings to the port; settings that
+/// are not specified are left unchanged
+pub fn apply_line_settings(
+    control: &SerialControl,
+    settings: &SerialLineSettings,
+) -> anyhow::Result<()> {
+    if let Some(baud) = settings.baud {
+        control.set_baud_rate(baud)?;
+    }
+    if let Some(bits) = settings.data_bits {
+        control.set_char_size(char_size(bits)?)?;
+    }
+    if let Some(p) = settings.parity {
+        control.set_parity(parity(p))?;
+    }
+    if let Some(bits) = settings.stop_bits {
+        control.set_stop_bits(stop_bits(bits))?;
+    }
+    if let Some(flow) = settings.flow_control {
+        control.set_flow_control(flow_control(flow))?;
+    }
+    if let Some(dtr) = settings.dtr {
+        control.set_dtr(dtr)?;
+    }
+    if let Some(rts) = settings.rts {
+        control.set_rts(rts)?;
+    }
+    Ok(())
+}
+
+/// Returns the current settings of the port
+pub fn line_settings(control: &SerialControl) -> anyhow::Result<SerialLineSettings> {
+    let settings = control.settings()?;
+    Ok(SerialLineSettings {
+        baud: Some(settings.baud),
+        data_bits: Some(match settings.char_size {
+            CharSize::Bits5 => 5,
+            CharSize::Bits6 => 6,
+            CharSize::Bits7 => 7,
+            CharSize::Bits8 => 8,
+        }),
+        parity: Some(match settings.parity {
+            Parity::None => SerialParity::None,
+            Parity::Odd => SerialParity::Odd,
+            Parity::Even => SerialParity::Even,
+        }),
+        stop_bits: Some(match settings.stop_bits {
+            StopBits::One => SerialStopBits::One,
+            StopBits::Two => SerialStopBits::Two,
+        }),
+        flow_control: Some(match settings.flow_control {
+            FlowControl::None => SerialFlowControl::None,
+            FlowControl::XonXoff => SerialFlowControl::XonXoff,
+            FlowControl::RtsCts => SerialFlowControl::RtsCts,
+        }),
+        dtr: Some(settings.dtr),
+        rts: Some(settings.rts),
+    })
+}
+
+/// Computes a fresh log file name for the pane in the data directory
+pub fn default_log_path(pane: &dyn Pane) -> PathBuf {
+    let name = Mux::try_get()
+        .and_then(|mux| mux.get_domain(pane.domain_id()))
+        .map(|domain| domain.domain_name().to_string())
+        .unwrap_or_else(|| "serial".to_string());
+    let name: String = name
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+    let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+    config::DATA_DIR
+        .join("serial")
+        .join(format!("{name}-{stamp}.log"))
+}
+
+/// Starts appending the data received by the pane to the file at
+/// `path`, prefixing each line with the time at which it arrived.
+/// Replaces any log that was already active for the pane.
+pub fn start_logging(pane_id: PaneId, path: &Path) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("creating directory {}", parent.display()))?;
+    }
+    let file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("opening serial log {}", path.display()))?;
+
+    let running = Arc::new(AtomicBool::new(true));
+    // The notification is delivered on whichever thread produced it,
+    // so the file is written by a thread of its own
+    let (tx, rx) = channel::<Arc<Vec<u8>>>();
+    {
+        let running = Arc::clone(&running);
+        let path = path.to_path_buf();
+        std::thread::Builder::new()
+            .name(format!("serial log {}", path.display()))
+            .spawn(move || {
+                let mut writer = LogWriter {
+                    file,
+                    at_line_start: true,
+                };
+                while let Ok(data) = rx.recv() {
+                    if !running.load(Ordering::Relaxed) {
+                        break;
+                    }
+                    if let Err(err) = writer.write(&data) {
+                        log::error!("writing serial log {}: {err:#}", path.display());
+                        // Unless it has already been replaced by another log
+                        let current = LOGS
+                            .lock()
+                            .get(&pane_id)
+                            .map_or(false, |log| Arc::ptr_eq(&log.running, &running));
+                        if current {
+                            stop_logging(pane_id);
+                        }
+                        break;
+                    }
+                }
+            })?;
+    }
+
+    stop_logging(pane_id);
+    LOGS.lock().insert(
+        pane_id,
+        SerialLog {
+            path: path.to_path_buf(),
+            running: Arc::clone(&running),
+        },
+    );
+
+    let mux = Mux::get();
+    mux.add_output_tap(pane_id);
+
+    mux.subscribe(move |n| {
+        if !running.load(Ordering::Relaxed) {
+            return false;
+        }
+        match n {
+            MuxNotification::PaneRawOutput { pane_id: id, data } if id == pane_id => {
+                // Fails once the writer has given up
+                tx.send(data).is_ok()
+            }
+            MuxNotification::PaneRemoved(id) if id == pane_id => {
+                stop_logging(pane_id);
+                false
+            }
+            _ => true,
+        }
+    });
+
+    Ok(())
+}
+
+/// Stops logging the pane, returning the path of the log that
+/// was active, if any
+pub fn stop_logging(pane_id: PaneId) -> Option<PathBuf> {
+    let log = LOGS.lock().remove(&pane_id)?;
+    log.running.store(false, Ordering::Relaxed);
+    if let Some(mux) = Mux::try_get() {
+        mux.remove_output_tap(pane_id);
+    }
+    Some(log.path)
+}
+
+struct LogWriter<W: Write> {
+    file: W,
+    at_line_start: bool,
+}
+
+impl<W: Write> LogWriter<W> {
+    fn write(&mut self, data: &[u8]) -> std::io::Result<()> {
+        let stamp = chrono::Local::now().format("[%Y-%m-%d %H:%M:%S%.3f] ");
+        let buf = self.stamp_lines(data, stamp.to_string().as_bytes());
+        self.file.write_all(&buf)?;
+        self.file.flush()
+    }
+
+    /// Prefixes each line that starts within `data` with `stamp`
+    fn stamp_lines(&mut self, data: &[u8], stamp: &[u8]) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(data.len() + 32);
+        for &b in data {
+            if self.at_line_start {
+                buf.extend_from_slice(stamp);
+                self.at_line_start = false;
+            }
+            buf.push(b);
+            if b == b'\n' {
+                self.at_line_start = true;
+            }
+        }
+        buf
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn log_lines_are_stamped() {
+        let mut writer = LogWriter {
+            file: vec![],
+            at_line_start: true,
+        };
+        assert_eq!(
+            writer.stamp_lines(b"one\ntw", b"> "),
+            b"> one\n> tw".to_vec()
+        );
+        // A line that continues in the next chunk isn't stamped again
+        assert_eq!(writer.stamp_lines(b"o\n", b"> "), b"o\n".to_vec());
+        assert_eq!(writer.stamp_lines(b"", b"> "), b"".to_vec());
+        assert_eq!(writer.stamp_lines(b"\n", b"> "), b"> \n".to_vec());
+
+        writer.write(b"three\n").unwrap();
+        let written = String::from_utf8(writer.file).unwrap();
+        assert!(written.starts_with('['));
+        assert!(written.ends_with("] three\n"));
+    }
+}
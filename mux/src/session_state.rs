@@ -0,0 +1,579 @@
+//! Saving the layout of the windows, tabs and panes of the mux to a
+//! file, so that it can be re-created after wezterm is restarted.
+//!
+//! The layout is saved as versioned JSON in the `sessions` directory
+//! of the data dir.  Restoring a session spawns fresh panes in the
+//! saved domains and working directories; the processes that were
+//! running in the original panes are not preserved, although the
+//! programs listed in `session_restore_processes` are re-run.
+use crate::domain::SplitSource;
+use crate::pane::{CachePolicy, Pane};
+use crate::renderable::StableRowIndex;
+use crate::tab::{PaneNode, SplitDirection, SplitRequest, SplitSize};
+use crate::window::WindowId;
+use crate::Mux;
+use anyhow::{anyhow, Context};
+use config::keyassignment::SpawnTabDomain;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use wezterm_term::TerminalSize;
+
+/// The version of the file format.  This must be bumped when
+/// incompatible changes are made to the types below.
+pub const SESSION_FORMAT_VERSION: u32 = 1;
+
+/// The name of the session that is used when none is specified
+pub const DEFAULT_SESSION: &str = "default";
+
+/// Limits the amount of scrollback that is saved for each pane
+const MAX_SAVED_LINES: StableRowIndex = 10_000;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SavedSession {
+    pub version: u32,
+    pub windows: Vec<SavedWindow>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SavedWindow {
+    pub workspace: String,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub active_tab: usize,
+    pub tabs: Vec<SavedTab>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SavedTab {
+    #[serde(default)]
+    pub title: String,
+    pub size: TerminalSize,
+    pub root: SavedPaneNode,
+}
+
+/// The split tree of a tab.  The sizes are those of the
+/// (first, second) children of the split.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum SavedPaneNode {
+    Split {
+        direction: SplitDirection,
+        first: TerminalSize,
+        second: TerminalSize,
+        left: Box<SavedPaneNode>,
+        right: Box<SavedPaneNode>,
+    },
+    Pane(SavedPane),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SavedPane {
+    /// The name of the domain that the pane belonged to
+    pub domain: String,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub cwd: Option<String>,
+    /// The argv of the foreground process, if known
+    #[serde(default)]
+    pub command: Option<Vec<String>>,
+    #[serde(default)]
+    pub is_active: bool,
+    #[serde(default)]
+    pub is_zoomed: bool,
+    /// The text of the scrollback and viewport, without attributes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scrollback: Option<String>,
+}
+
+impl SavedPaneNode {
+    /// Returns the top-left pane of the tree; it is the one that
+    /// occupies the space of the whole tree before it is split
+    fn first_pane(&self) -> &SavedPane {
+        match self {
+            Self::Split { left, .. } => left.first_pane(),
+            Self::Pane(pane) => pane,
+        }
+    }
+}
+
+/// Returns the path of the file that holds the named session
+pub fn session_path(name: &str) -> anyhow::Result<PathBuf> {
+    if name.is_empty() || name.starts_with('.') || name.contains(|c| c == '/' || c == '\\') {
+        anyhow::bail!("{name:?} is not a valid session name");
+    }
+    Ok(config::DATA_DIR
+        .join("sessions")
+        .join(format!("{name}.json")))
+}
+
+/// Returns the names of the sessions that have been saved
+pub fn list_sessions() -> anyhow::Result<Vec<String>> {
+    let dir = config::DATA_DIR.join("sessions");
+    let mut names = vec![];
+    let entries = match std::fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(names),
+        Err(err) => return Err(err).with_context(|| format!("reading {}", dir.display())),
+    };
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().map(|ext| ext == "json").unwrap_or(false) {
+            if let Some(name) = path.file_stem().and_then(|name| name.to_str()) {
+                names.push(name.to_string());
+            }
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+fn capture_scrollback(pane: &dyn Pane) -> Option<String> {
+    // The alternate screen belongs to a full screen program, which
+    // would only be confused by seeing its output again
+    if pane.is_alt_screen_active() {
+        return None;
+    }
+    let dims = pane.get_dimensions();
+    let bottom_row = dims.physical_top + dims.viewport_rows as StableRowIndex;
+    let top_row = (bottom_row - MAX_SAVED_LINES).max(dims.scrollback_top);
+    let mut text = String::new();
+    for line in pane.get_logical_lines(top_row..bottom_row) {
+        for cell in line.logical.visible_cells() {
+            text.push_str(cell.str());
+        }
+        let trimmed = text.trim_end().len();
+        text.truncate(trimmed);
+        text.push('\n');
+    }
+    let trimmed = text.trim_end().len();
+    text.truncate(trimmed);
+    if text.is_empty() {
+        None
+    } else {
+        Some(text)
+    }
+}
+
+fn capture_node(mux: &Mux, node: PaneNode, include_scrollback: bool) -> Option<SavedPaneNode> {
+    match node {
+        PaneNode::Empty => None,
+        PaneNode::Split { left, right, node } => {
+            let left = capture_node(mux, *left, include_scrollback);
+            let right = capture_node(mux, *right, include_scrollback);
+            match (left, right) {
+                (Some(left), Some(right)) => Some(SavedPaneNode::Split {
+                    direction: node.direction,
+                    first: node.first,
+                    second: node.second,
+                    left: Box::new(left),
+                    right: Box::new(right),
+                }),
+                (Some(node), None) | (None, Some(node)) => Some(node),
+                (None, None) => None,
+            }
+        }
+        PaneNode::Leaf(entry) => {
+            let pane = mux.get_pane(entry.pane_id)?;
+            let domain = mux.get_domain(pane.domain_id())?;
+            // Overlays and other panes that cannot be
+            // re-created are left out of the session
+            if !domain.spawnable() {
+                return None;
+            }
+            Some(SavedPaneNode::Pane(SavedPane {
+                domain: domain.domain_name().to_string(),
+                title: entry.title,
+                cwd: entry
+                    .working_dir
+                    .and_then(|url| crate::cwd_from_url(&url.url)),
+                command: pane
+                    .get_foreground_process_info(CachePolicy::AllowStale)
+                    .map(|info| info.argv)
+                    .filter(|argv| !argv.is_empty()),
+                is_active: entry.is_active_pane,
+                is_zoomed: entry.is_zoomed_pane,
+                scrollback: if include_scrollback {
+                    capture_scrollback(&*pane)
+                } else {
+                    None
+                },
+            }))
+        }
+    }
+}
+
+/// Captures the layout of all of the windows in the mux
+pub fn capture_session(mux: &Mux, include_scrollback: bool) -> SavedSession {
+    let mut window_ids = mux.iter_windows();
+    window_ids.sort();
+
+    let mut windows = vec![];
+    for window_id in window_ids {
+        let (workspace, title, active_idx, tabs) = match mux.get_window(window_id) {
+            Some(window) => (
+                window.get_workspace().to_string(),
+                window.get_title().to_string(),
+                window.get_active_idx(),
+                window.iter().map(Arc::clone).collect::<Vec<_>>(),
+            ),
+            None => continue,
+        };
+
+        let mut saved_tabs = vec![];
+        let mut active_tab = 0;
+        for (idx, tab) in tabs.iter().enumerate() {
+            if let Some(root) = capture_node(mux, tab.codec_pane_tree(), include_scrollback) {
+                if idx == active_idx {
+                    active_tab = saved_tabs.len();
+                }
+                saved_tabs.push(SavedTab {
+                    title: tab.get_title(),
+                    size: tab.get_size(),
+                    root,
+                });
+            }
+        }
+
+        if !saved_tabs.is_empty() {
+            windows.push(SavedWindow {
+                workspace,
+                title,
+                active_tab,
+                tabs: saved_tabs,
+            });
+        }
+    }
+
+    SavedSession {
+        version: SESSION_FORMAT_VERSION,
+        windows,
+    }
+}
+
+/// Saves the layout of the mux as the named session, returning
+/// the path of the file that was written.  The session is written
+/// to a temporary file first so that a failure part way through
+/// doesn't destroy a previously saved session.
+pub fn save_session(name: &str, include_scrollback: bool) -> anyhow::Result<PathBuf> {
+    let path = session_path(name)?;
+    let session = capture_session(&Mux::get(), include_scrollback);
+    if session.windows.is_empty() {
+        anyhow::bail!("there are no windows to save");
+    }
+    write_session(&path, &session)?;
+    Ok(path)
+}
+
+fn write_session(path: &Path, session: &SavedSession) -> anyhow::Result<()> {
+    let dir = path
+        .parent()
+        .ok_or_else(|| anyhow!("{} has no parent directory", path.display()))?;
+    config::create_user_owned_dirs(dir)?;
+
+    let temp = path.with_extension("json.tmp");
+    let mut file =
+        std::fs::File::create(&temp).with_context(|| format!("creating {}", temp.display()))?;
+    serde_json::to_writer_pretty(&mut file, session)?;
+    file.flush()?;
+    drop(file);
+    std::fs::rename(&temp, path)
+        .with_context(|| format!("renaming {} to {}", temp.display(), path.display()))?;
+    Ok(())
+}
+
+/// Parses a saved session, rejecting sessions that were
+/// written by a newer version of wezterm
+pub fn parse_session(data: &[u8]) -> anyhow::Result<SavedSession> {
+    #[derive(Deserialize)]
+    struct Version {
+        version: u32,
+    }
+    let Version { version } = serde_json::from_slice(data)?;
+    if version > SESSION_FORMAT_VERSION {
+        anyhow::bail!(
+            "the session has format version {version}, but this version \
+             of wezterm only understands version {SESSION_FORMAT_VERSION}"
+        );
+    }
+    Ok(serde_json::from_slice(data)?)
+}
+
+/// Loads the named session
+pub fn load_session(name: &str) -> anyhow::Result<SavedSession> {
+    let path = session_path(name)?;
+    let data = std::fs::read(&path).with_context(|| format!("reading {}", path.display()))?;
+    parse_session(&data).with_context(|| format!("parsing {}", path.display()))
+}
+
+/// Returns true if the saved foreground process should be re-run
+fn should_restore_command(argv: &[String], restore_processes: &[String]) -> bool {
+    let program = match argv.first() {
+        Some(program) => program,
+        None => return false,
+    };
+    let name = Path::new(program)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(program);
+    // Login shells are reported with a leading dash
+    let name = name.trim_start_matches('-');
+    restore_processes.iter().any(|p| p == name)
+}
+
+fn resolve_domain(mux: &Mux, name: &str) -> SpawnTabDomain {
+    match mux.get_domain_by_name(name) {
+        Some(domain) if domain.spawnable() => SpawnTabDomain::DomainName(name.to_string()),
+        _ => {
+            log::warn!("domain {name} of a saved pane is not available; using the default domain");
+            SpawnTabDomain::DefaultDomain
+        }
+    }
+}
+
+/// Returns the saved scrollback of a pane in the form that
+/// its terminal will parse it
+fn scrollback_output(saved: &SavedPane) -> Option<String> {
+    saved
+        .scrollback
+        .as_ref()
+        .map(|text| format!("{}\r\n", text.replace('\n', "\r\n")))
+}
+
+/// Re-runs the saved command of a freshly spawned pane, if configured
+fn restore_pane(pane: &Arc<dyn Pane>, saved: &SavedPane, restore_processes: &[String]) {
+    if let Some(argv) = &saved.command {
+        if should_restore_command(argv, restore_processes) {
+            // Type the command into the shell rather than spawning it
+            // directly, so that the shell remains once it exits
+            let command = format!("{}\r", shell_words::join(argv));
+            if let Err(err) = pane.writer().write_all(command.as_bytes()) {
+                log::error!("restoring command {argv:?}: {err:#}");
+            }
+        }
+    }
+}
+
+/// Re-creates the windows, tabs and panes of a saved session,
+/// alongside any windows that already exist.
+/// Returns the ids of the new windows.
+pub async fn restore_session(session: &SavedSession) -> anyhow::Result<Vec<WindowId>> {
+    let mux = Mux::get();
+    let restore_processes = config::configuration().session_restore_processes.clone();
+    let mut window_ids = vec![];
+
+    for window in &session.windows {
+        let mut window_id = None;
+
+        for saved_tab in &window.tabs {
+            let first = saved_tab.root.first_pane();
+            // The saved scrollback is passed along with the spawn so that
+            // it is shown before the shell has a chance to write its prompt
+            let (tab, pane, id) = mux
+                .spawn_tab_or_window(
+                    window_id,
+                    resolve_domain(&mux, &first.domain),
+                    None,
+                    first.cwd.clone(),
+                    scrollback_output(first),
+                    saved_tab.size,
+                    None,
+                    window.workspace.clone(),
+                    None,
+                )
+                .await?;
+            window_id = Some(id);
+            if !saved_tab.title.is_empty() {
+                tab.set_title(&saved_tab.title);
+            }
+
+            // Each entry is a part of the tree together with the
+            // pane that currently occupies the space of that part
+            let mut pending = vec![(&saved_tab.root, pane)];
+            let mut active = None;
+            while let Some((node, pane)) = pending.pop() {
+                match node {
+                    SavedPaneNode::Split {
+                        direction,
+                        first,
+                        second,
+                        left,
+                        right,
+                    } => {
+                        // Scale the saved size to the space that is
+                        // actually available, in case it differs
+                        let dims = pane.get_dimensions();
+                        let (saved_second, saved_total, available) = match direction {
+                            SplitDirection::Horizontal => {
+                                (second.cols, first.cols + second.cols + 1, dims.cols)
+                            }
+                            SplitDirection::Vertical => (
+                                second.rows,
+                                first.rows + second.rows + 1,
+                                dims.viewport_rows,
+                            ),
+                        };
+                        let request = SplitRequest {
+                            direction: *direction,
+                            target_is_second: true,
+                            top_level: false,
+                            size: SplitSize::Cells(saved_second * available / saved_total.max(1)),
+                        };
+                        let second_pane = right.first_pane();
+                        let (new_pane, _size) = mux
+                            .split_pane(
+                                pane.pane_id(),
+                                request,
+                                SplitSource::Spawn {
+                                    command: None,
+                                    command_dir: second_pane.cwd.clone(),
+                                    initial_output: scrollback_output(second_pane),
+                                },
+                                resolve_domain(&mux, &second_pane.domain),
+                            )
+                            .await?;
+                        pending.push((right, new_pane));
+                        pending.push((left, pane));
+                    }
+                    SavedPaneNode::Pane(saved) => {
+                        restore_pane(&pane, saved, &restore_processes);
+                        if saved.is_active {
+                            active.replace((pane, saved.is_zoomed));
+                        }
+                    }
+                }
+            }
+
+            if let Some((pane, zoomed)) = active {
+                tab.set_active_pane(&pane);
+                if zoomed {
+                    tab.set_zoomed(true);
+                }
+            }
+        }
+
+        if let Some(window_id) = window_id {
+            if let Some(mut mux_window) = mux.get_window_mut(window_id) {
+                if !window.title.is_empty() {
+                    mux_window.set_title(&window.title);
+                }
+                if window.active_tab < mux_window.len() {
+                    mux_window.set_active_without_saving(window.active_tab);
+                }
+            }
+            window_ids.push(window_id);
+        }
+    }
+
+    if let Err(err) = config::with_lua_config_on_main_thread(|lua| async {
+        if let Some(lua) = lua {
+            let args = lua.pack_multi(window_ids.clone())?;
+            config::lua::emit_event(&lua, ("session-restored".to_string(), args)).await?;
+        }
+        Ok(())
+    })
+    .await
+    {
+        log::error!("while processing session-restored event: {err:#}");
+    }
+
+    Ok(window_ids)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn pane(domain: &str, cwd: &str) -> SavedPaneNode {
+        SavedPaneNode::Pane(SavedPane {
+            domain: domain.to_string(),
+            title: String::new(),
+            cwd: Some(cwd.to_string()),
+            command: None,
+            is_active: false,
+            is_zoomed: false,
+            scrollback: None,
+        })
+    }
+
+    #[test]
+    fn round_trip() {
+        let size = TerminalSize::default();
+        let session = SavedSession {
+            version: SESSION_FORMAT_VERSION,
+            windows: vec![SavedWindow {
+                workspace: "default".to_string(),
+                title: "editing".to_string(),
+                active_tab: 0,
+                tabs: vec![SavedTab {
+                    title: String::new(),
+                    size,
+                    root: SavedPaneNode::Split {
+                        direction: SplitDirection::Horizontal,
+                        first: size,
+                        second: size,
+                        left: Box::new(pane("local", "/home/user")),
+                        right: Box::new(pane("SSH:host", "/srv")),
+                    },
+                }],
+            }],
+        };
+        let data = serde_json::to_vec(&session).unwrap();
+        let parsed = parse_session(&data).unwrap();
+        assert_eq!(parsed, session);
+        assert_eq!(
+            parsed.windows[0].tabs[0].root.first_pane().cwd.as_deref(),
+            Some("/home/user")
+        );
+    }
+
+    #[test]
+    fn newer_version_is_rejected() {
+        let data = format!(
+            r#"{{"version": {}, "windows": []}}"#,
+            SESSION_FORMAT_VERSION + 1
+        );
+        assert!(parse_session(data.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn session_names() {
+        assert!(session_path("work").is_ok());
+        assert!(session_path("").is_err());
+        assert!(session_path("../work").is_err());
+        assert!(session_path(".hidden").is_err());
+    }
+
+    #[test]
+    fn scrollback_is_parsed_as_lines() {
+        let saved = match pane("local", "/home/user") {
+            SavedPaneNode::Pane(saved) => SavedPane {
+                scrollback: Some("$ ls\nfile".to_string()),
+                ..saved
+            },
+            SavedPaneNode::Split { .. } => unreachable!(),
+        };
+        assert_eq!(
+            scrollback_output(&saved).as_deref(),
+            Some("$ ls\r\nfile\r\n")
+        );
+    }
+
+    #[test]
+    fn restore_commands() {
+        let allowed = vec!["vim".to_string(), "less".to_string()];
+        let argv = |args: &[&str]| args.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert!(should_restore_command(
+            &argv(&["/usr/bin/vim", "notes.txt"]),
+            &allowed
+        ));
+        assert!(!should_restore_command(&argv(&["-zsh"]), &allowed));
+        assert!(!should_restore_command(
+            &argv(&["rm", "-rf", "x"]),
+            &allowed
+        ));
+        assert!(!should_restore_command(&[], &allowed));
+    }
+}
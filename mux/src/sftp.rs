@@ -0,0 +1,266 @@
+//! Transferring files to and from the hosts of ssh domains using
+//! the sftp channel of their established sessions.
+use crate::pane::{CachePolicy, Pane};
+use crate::Mux;
+use anyhow::Context;
+use percent_encoding::percent_decode_str;
+use smol::io::{AsyncReadExt, AsyncWriteExt};
+use std::io::{Read, Write};
+use std::path::Path;
+use url::Url;
+use wezterm_ssh::{Sftp, Utf8Path, Utf8PathBuf};
+
+/// How much data to move per sftp request
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Formats a byte count for display in progress reports
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64;
+    let mut unit = "B";
+    for u in UNITS {
+        if size < 1024. {
+            break;
+        }
+        size /= 1024.;
+        unit = u;
+    }
+    format!("{size:.1} {unit}")
+}
+
+/// Returns the sftp handle of the ssh domain session that the pane
+/// belongs to, or None if the pane is not part of an ssh domain
+/// that has an established session.
+pub fn sftp_for_pane(pane: &dyn Pane) -> Option<Sftp> {
+    let mux = Mux::try_get()?;
+    let domain = mux.get_domain(pane.domain_id())?;
+    crate::ssh::domain_sftp(domain.domain_name())
+}
+
+/// Returns true if `url_host`, the host name that a shell reported via
+/// OSC 7, plausibly names `session_host`, the host that the ssh session
+/// is connected to.  The shell reports the name that the host knows
+/// itself by, which may be qualified differently from the name that
+/// was used to connect, so only the first labels of the names need
+/// to match.
+fn is_session_host(url_host: &str, session_host: &str) -> bool {
+    if url_host.is_empty() || url_host.eq_ignore_ascii_case("localhost") {
+        return true;
+    }
+    if url_host.eq_ignore_ascii_case(session_host) {
+        return true;
+    }
+    let is_ip = |host: &str| host.parse::<std::net::IpAddr>().is_ok();
+    if is_ip(url_host) || is_ip(session_host) {
+        return false;
+    }
+    let first_label = |host: &str| host.split('.').next().unwrap_or("").to_ascii_lowercase();
+    first_label(url_host) == first_label(session_host)
+}
+
+/// Extracts the remote path from the working directory that was
+/// reported by a pane via OSC 7.  Working directories on hosts other
+/// than `session_host`, such as those reported by shells on hosts that
+/// were reached by running ssh in the pane, are ignored.
+pub fn remote_path_from_url(url: &Url, session_host: &str) -> Option<Utf8PathBuf> {
+    if url.scheme() != "file" {
+        return None;
+    }
+    if !is_session_host(url.host_str().unwrap_or(""), session_host) {
+        return None;
+    }
+    let path = percent_decode_str(url.path()).decode_utf8().ok()?;
+    if path.is_empty() {
+        None
+    } else {
+        Some(Utf8PathBuf::from(path.into_owned()))
+    }
+}
+
+/// Returns the remote directory that is the working directory of
+/// the pane, falling back to the home directory of the remote user
+/// when the shell hasn't reported it, or reported one on another host.
+pub async fn remote_cwd(sftp: &Sftp, pane: &dyn Pane) -> anyhow::Result<Utf8PathBuf> {
+    let session_host = Mux::try_get()
+        .and_then(|mux| mux.get_domain(pane.domain_id()))
+        .and_then(|domain| crate::ssh::domain_host(domain.domain_name()))
+        .unwrap_or_default();
+    match pane
+        .get_current_working_dir(CachePolicy::AllowStale)
+        .and_then(|url| remote_path_from_url(&url, &session_host))
+    {
+        Some(path) => Ok(path),
+        None => Ok(sftp
+            .canonicalize(".")
+            .await
+            .context("resolving remote home directory")?),
+    }
+}
+
+/// Resolves a possibly relative remote path against `cwd`
+pub fn resolve_remote_path(cwd: &Utf8Path, path: &str) -> Utf8PathBuf {
+    let path = Utf8Path::new(path);
+    if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        cwd.join(path)
+    }
+}
+
+/// Uploads the local file at `local` into the remote directory `dir`,
+/// keeping its file name, or to `dir` itself when `dir` is not an
+/// existing directory.  `progress` is called with the number of bytes
+/// transferred so far and the total size.
+/// Returns the remote path that was written.
+pub async fn upload_file(
+    sftp: &Sftp,
+    local: &Path,
+    dir: &Utf8Path,
+    progress: &mut dyn FnMut(u64, u64),
+) -> anyhow::Result<Utf8PathBuf> {
+    let mut file =
+        std::fs::File::open(local).with_context(|| format!("opening {}", local.display()))?;
+    let meta = file.metadata()?;
+    if meta.is_dir() {
+        anyhow::bail!(
+            "{} is a directory; only files can be uploaded",
+            local.display()
+        );
+    }
+    let total = meta.len();
+
+    let is_dir = match sftp.metadata(dir.to_path_buf()).await {
+        Ok(meta) => meta.is_dir(),
+        Err(_) => false,
+    };
+    let remote = if is_dir {
+        let name = local
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| anyhow::anyhow!("{} has no usable file name", local.display()))?;
+        dir.join(name)
+    } else {
+        dir.to_path_buf()
+    };
+
+    let mut remote_file = sftp
+        .create(remote.clone())
+        .await
+        .with_context(|| format!("creating remote file {remote}"))?;
+
+    let mut buf = vec![0u8; CHUNK_SIZE];
+    let mut transferred = 0;
+    progress(transferred, total);
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        remote_file
+            .write_all(&buf[..n])
+            .await
+            .with_context(|| format!("writing to remote file {remote}"))?;
+        transferred += n as u64;
+        progress(transferred, total);
+    }
+    remote_file.close().await?;
+
+    Ok(remote)
+}
+
+/// Downloads the content of the remote file at `remote`, writing it
+/// to `dest` as it arrives.
+/// `progress` is called with the number of bytes transferred so far
+/// and the total size, which is 0 if the server didn't report it.
+/// Returns the number of bytes that were written.
+pub async fn download_file(
+    sftp: &Sftp,
+    remote: &Utf8Path,
+    dest: &mut dyn Write,
+    progress: &mut dyn FnMut(u64, u64),
+) -> anyhow::Result<u64> {
+    let meta = sftp
+        .metadata(remote.to_path_buf())
+        .await
+        .with_context(|| format!("querying remote file {remote}"))?;
+    if meta.is_dir() {
+        anyhow::bail!("{remote} is a directory; only files can be downloaded");
+    }
+    let total = meta.size.unwrap_or(0);
+
+    let mut file = sftp
+        .open(remote.to_path_buf())
+        .await
+        .with_context(|| format!("opening remote file {remote}"))?;
+
+    let mut buf = vec![0u8; CHUNK_SIZE];
+    let mut transferred = 0;
+    progress(transferred, total);
+    loop {
+        let n = file
+            .read(&mut buf)
+            .await
+            .with_context(|| format!("reading remote file {remote}"))?;
+        if n == 0 {
+            break;
+        }
+        dest.write_all(&buf[..n])?;
+        transferred += n as u64;
+        progress(transferred, total);
+    }
+    dest.flush()?;
+
+    Ok(transferred)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn remote_paths() {
+        let url = Url::parse("file://host/home/user/some%20dir").unwrap();
+        assert_eq!(
+            remote_path_from_url(&url, "host"),
+            Some(Utf8PathBuf::from("/home/user/some dir"))
+        );
+        let url = Url::parse("http://host/home").unwrap();
+        assert_eq!(remote_path_from_url(&url, "host"), None);
+
+        // Directories on other hosts are ignored
+        let url = Url::parse("file://other/home/user").unwrap();
+        assert_eq!(remote_path_from_url(&url, "host"), None);
+        let url = Url::parse("file:///home/user").unwrap();
+        assert_eq!(
+            remote_path_from_url(&url, "host"),
+            Some(Utf8PathBuf::from("/home/user"))
+        );
+
+        assert!(is_session_host("Host", "host.example.com"));
+        assert!(is_session_host("host.example.com", "host"));
+        assert!(is_session_host("localhost", "host"));
+        assert!(!is_session_host("other", "host.example.com"));
+        assert!(!is_session_host("host", "10.0.0.1"));
+        assert!(is_session_host("10.0.0.1", "10.0.0.1"));
+
+        let cwd = Utf8Path::new("/home/user");
+        assert_eq!(
+            resolve_remote_path(cwd, "notes.txt"),
+            Utf8PathBuf::from("/home/user/notes.txt")
+        );
+        assert_eq!(
+            resolve_remote_path(cwd, "/etc/hosts"),
+            Utf8PathBuf::from("/etc/hosts")
+        );
+    }
+
+    #[test]
+    fn sizes() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(3 * 1024 * 1024), "3.0 MiB");
+    }
+}
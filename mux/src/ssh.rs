@@ -2,6 +2,7 @@ use crate::connui::ConnectionUI;
 use crate::domain::{alloc_domain_id, Domain, DomainId, DomainState, WriterWrapper};
 use crate::localpane::LocalPane;
 use crate::pane::{alloc_pane_id, Pane, PaneId};
+use crate::ssh_pool::{self, PoolKey};
 use crate::Mux;
 use anyhow::{anyhow, bail, Context};
 use async_trait::async_trait;
@@ -9,11 +10,12 @@ use config::{Shell, SshBackend, SshDomain};
 use filedescriptor::{poll, pollfd, socketpair, AsRawSocketDescriptor, FileDescriptor, POLLIN};
 use portable_pty::cmdbuilder::CommandBuilder;
 use portable_pty::{ChildKiller, ExitStatus, MasterPty, PtySize};
+use serde::{Deserialize, Serialize};
 use smol::channel::{bounded, Receiver as AsyncReceiver};
 use std::cell::RefCell;
 use std::collections::{HashMap, VecDeque};
 use std::io::{BufWriter, Read, Write};
-use std::sync::mpsc::{channel, Receiver, Sender, TryRecvError};
+use std::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use termwiz::cell::{unicode_column_width, AttributeChange, Intensity};
@@ -22,11 +24,33 @@ use termwiz::lineedit::*;
 use termwiz::render::terminfo::TerminfoRenderer;
 use termwiz::surface::{Change, LineAttribute};
 use termwiz::terminal::{ScreenSize, Terminal, TerminalWaker};
+use wezterm_dynamic::{FromDynamic, ToDynamic};
 use wezterm_ssh::{
-    ConfigMap, HostVerificationFailed, Session, SessionEvent, SshChildProcess, SshPty,
+    ConfigMap, ConnectPrompt, ForwardId, ForwardInfo, ForwardKind, ForwardSpec, Forwarding,
+    HostVerificationFailed, Session, SessionEvent, Sftp, SshChildProcess, SshPty,
 };
 use wezterm_term::TerminalSize;
 
+lazy_static::lazy_static! {
+    /// Handles to the established ssh domain sessions, keyed by domain name
+    static ref DOMAIN_SESSIONS: Mutex<HashMap<String, DomainSession>> = Mutex::new(HashMap::new());
+}
+
+/// The parts of an ssh domain session that are used outside of the
+/// domain itself.  We don't hold the `Session` here, so that the
+/// session pool alone decides how long an idle connection lives.
+#[derive(Clone)]
+struct DomainSession {
+    session_id: usize,
+    forwarding: Forwarding,
+    sftp: Sftp,
+    /// The name of the remote host, as resolved from the ssh config
+    host: String,
+    /// The forwards that were added at runtime, so that they can
+    /// be re-established if the domain has to reconnect
+    added_forwards: Vec<(ForwardId, ForwardSpec)>,
+}
+
 #[derive(Default)]
 struct PasswordPromptHost {
     history: BasicHistory,
@@ -57,6 +81,45 @@ impl LineEditorHost for PasswordPromptHost {
     }
 }
 
+/// Prompts for host verification and authentication in a terminal
+struct TerminalPrompt<'a> {
+    term: &'a mut dyn Terminal,
+}
+
+impl<'a> ConnectPrompt for TerminalPrompt<'a> {
+    fn output_line(&mut self, line: &str) -> anyhow::Result<()> {
+        self.term
+            .render(&[Change::Text(format!("{}\r\n", line.replace('\n', "\r\n")))])?;
+        self.term.flush()?;
+        Ok(())
+    }
+
+    fn read_line(&mut self, prompt: &str, echo: bool) -> anyhow::Result<Option<String>> {
+        let mut editor = LineEditor::new(&mut *self.term);
+        let mut host = PasswordPromptHost::default();
+        host.echo = echo;
+        editor.set_prompt(prompt);
+        Ok(editor.read_line(&mut host)?)
+    }
+}
+
+impl ConnectPrompt for ConnectionUI {
+    fn output_line(&mut self, line: &str) -> anyhow::Result<()> {
+        self.output_str(&format!("{}\n", line));
+        Ok(())
+    }
+
+    fn read_line(&mut self, prompt: &str, echo: bool) -> anyhow::Result<Option<String>> {
+        let res = if echo {
+            self.input(prompt)
+        } else {
+            self.password(prompt)
+        };
+        // The prompt fails when the user closes the window
+        Ok(res.ok())
+    }
+}
+
 pub fn ssh_connect_with_ui(
     ssh_config: wezterm_ssh::ConfigMap,
     ui: &mut ConnectionUI,
@@ -77,43 +140,10 @@ pub fn ssh_connect_with_ui(
                     }
                 }
                 SessionEvent::HostVerify(verify) => {
-                    ui.output_str(&format!("{}\n", verify.message));
-                    let ok = if let Ok(line) = ui.input("Enter [y/n]> ") {
-                        match line.as_ref() {
-                            "y" | "Y" | "yes" | "YES" => true,
-                            "n" | "N" | "no" | "NO" | _ => false,
-                        }
-                    } else {
-                        false
-                    };
-                    smol::block_on(verify.answer(ok)).context("send verify response")?;
+                    smol::block_on(verify.prompt_and_answer(ui)).context("send verify response")?;
                 }
                 SessionEvent::Authenticate(auth) => {
-                    if !auth.username.is_empty() {
-                        ui.output_str(&format!("Authentication for {}\n", auth.username));
-                    }
-                    if !auth.instructions.is_empty() {
-                        ui.output_str(&format!("{}\n", auth.instructions));
-                    }
-                    let mut answers = vec![];
-                    for prompt in &auth.prompts {
-                        let mut prompt_lines = prompt.prompt.split('\n').collect::<Vec<_>>();
-                        let editor_prompt = prompt_lines.pop().unwrap();
-                        for line in &prompt_lines {
-                            ui.output_str(&format!("{}\n", line));
-                        }
-                        let res = if prompt.echo {
-                            ui.input(editor_prompt)
-                        } else {
-                            ui.password(editor_prompt)
-                        };
-                        if let Ok(line) = res {
-                            answers.push(line);
-                        } else {
-                            anyhow::bail!("Authentication was cancelled");
-                        }
-                    }
-                    smol::block_on(auth.answer(answers))?;
+                    smol::block_on(auth.prompt_and_answer(ui))?;
                 }
                 SessionEvent::HostVerificationFailed(failed) => {
                     let message = format_host_verification_for_terminal(failed);
@@ -130,6 +160,59 @@ pub fn ssh_connect_with_ui(
     })
 }
 
+/// Connects and authenticates a session for a command line tool that
+/// has no gui.  Banners are written to stderr, and the controlling
+/// terminal is only opened if the user needs to be prompted for host
+/// verification or authentication.
+pub fn ssh_connect_with_terminal(ssh_config: ConfigMap) -> anyhow::Result<Session> {
+    fn terminal(term: &mut Option<Box<dyn Terminal>>) -> anyhow::Result<&mut dyn Terminal> {
+        if term.is_none() {
+            let caps = termwiz::caps::Capabilities::new_from_env()?;
+            let new_term = termwiz::terminal::new_terminal(caps)
+                .context("a terminal is required in order to prompt for ssh authentication")?;
+            term.replace(Box::new(new_term));
+        }
+        Ok(&mut **term.as_mut().unwrap())
+    }
+
+    let (session, events) = Session::connect(ssh_config)?;
+    let mut term: Option<Box<dyn Terminal>> = None;
+
+    while let Ok(event) = smol::block_on(events.recv()) {
+        match event {
+            SessionEvent::Banner(banner) => {
+                if let Some(banner) = banner {
+                    eprintln!("{}", banner);
+                }
+            }
+            SessionEvent::HostVerify(verify) => {
+                let mut prompt = TerminalPrompt {
+                    term: terminal(&mut term)?,
+                };
+                smol::block_on(verify.prompt_and_answer(&mut prompt))
+                    .context("send verify response")?;
+            }
+            SessionEvent::Authenticate(auth) => {
+                let mut prompt = TerminalPrompt {
+                    term: terminal(&mut term)?,
+                };
+                smol::block_on(auth.prompt_and_answer(&mut prompt))?;
+            }
+            SessionEvent::HostVerificationFailed(failed) => {
+                let term = terminal(&mut term)?;
+                term.render(&format_host_verification_for_terminal(failed))?;
+                term.flush()?;
+                anyhow::bail!("Host key verification failed");
+            }
+            SessionEvent::Error(err) => {
+                anyhow::bail!("Error: {}", err);
+            }
+            SessionEvent::Authenticated => return Ok(session),
+        }
+    }
+    bail!("unable to authenticate session");
+}
+
 fn format_host_verification_for_terminal(failed: HostVerificationFailed) -> Vec<Change> {
     vec![
         AttributeChange::Intensity(Intensity::Bold).into(),
@@ -172,13 +255,15 @@ fn format_host_verification_for_terminal(failed: HostVerificationFailed) -> Vec<
 /// Represents a connection to remote host via ssh.
 /// The domain is created with the ssh config prior to making the
 /// connection.  The connection is established by the first spawn()
-/// call.
+/// call, unless a connection to the same host can be borrowed from
+/// the session pool in `ssh_pool`.
 /// In order to show the authentication dialog inline in that spawned
 /// pane, we play some tricks with wrapped versions of the pty, child
 /// and the reader and writer instances so that we can inject the
 /// interactive setup.  The bulk of that is driven by `connect_ssh_session`.
+/// The same wrappers allow a pane whose session is lost to be reattached
+/// to a new session, which is driven by `PaneLink::reconnect`.
 pub struct RemoteSshDomain {
-    session: Mutex<Option<Session>>,
     dom: SshDomain,
     id: DomainId,
     name: String,
@@ -213,6 +298,36 @@ pub fn ssh_domain_to_ssh_config(ssh_dom: &SshDomain) -> anyhow::Result<ConfigMap
     for (k, v) in &ssh_dom.ssh_option {
         ssh_config.insert(k.to_string(), v.to_string());
     }
+    // Record which options were set explicitly, so that they are
+    // also applied to any ProxyJump hops on the way to the host
+    let mut overrides: Vec<&str> = ssh_dom
+        .ssh_option
+        .keys()
+        .map(String::as_str)
+        .filter(|k| !k.starts_with("wezterm_ssh_"))
+        .collect();
+    if !overrides.is_empty() {
+        overrides.sort();
+        ssh_config.insert("wezterm_ssh_overrides".to_string(), overrides.join(","));
+    }
+
+    for (key, forwards) in [
+        ("localforward", &ssh_dom.local_forwards),
+        ("remoteforward", &ssh_dom.remote_forwards),
+        ("dynamicforward", &ssh_dom.dynamic_forwards),
+    ] {
+        for forward in forwards {
+            // Multiple forwards are combined using newlines,
+            // in the same way as when they are read from ssh_config
+            ssh_config
+                .entry(key.to_string())
+                .and_modify(|e| {
+                    e.push('\n');
+                    e.push_str(forward);
+                })
+                .or_insert_with(|| forward.to_string());
+        }
+    }
 
     if let Some(username) = &ssh_dom.username {
         ssh_config.insert("user".to_string(), username.to_string());
@@ -229,13 +344,173 @@ pub fn ssh_domain_to_ssh_config(ssh_dom: &SshDomain) -> anyhow::Result<ConfigMap
     Ok(ssh_config)
 }
 
+/// Describes a port forward that is established by an ssh domain session
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, FromDynamic, ToDynamic)]
+pub struct SshForward {
+    /// The name of the ssh domain
+    pub domain: String,
+    pub id: usize,
+    /// One of `Local`, `Remote` or `Dynamic`
+    pub kind: String,
+    /// The forward in the `[bind_address:]port[:host:hostport]` form
+    pub spec: String,
+    /// The port that is being listened on; this differs from the
+    /// port in the spec when that was 0
+    pub bound_port: u16,
+}
+
+impl SshForward {
+    fn new(domain: &str, info: ForwardInfo) -> Self {
+        Self {
+            domain: domain.to_string(),
+            id: info.id,
+            kind: info.spec.kind.to_string(),
+            spec: info.spec.to_string(),
+            bound_port: info.bound_port,
+        }
+    }
+}
+
+/// Records the session of an ssh domain so that its port forwards
+/// can be managed and files transferred at runtime.  This replaces
+/// any session that was previously recorded for the domain, in which
+/// case the forwards that were added to that session at runtime are
+/// re-established on the new one.
+pub fn register_domain_session(domain: &str, session: &Session, host: &str) {
+    let forwarding = session.forwarding();
+    let previous = {
+        let mut sessions = DOMAIN_SESSIONS.lock().unwrap();
+        if let Some(existing) = sessions.get(domain) {
+            if existing.session_id == session.id() {
+                return;
+            }
+        }
+        sessions.insert(
+            domain.to_string(),
+            DomainSession {
+                session_id: session.id(),
+                forwarding: forwarding.clone(),
+                sftp: session.sftp(),
+                host: host.to_string(),
+                added_forwards: vec![],
+            },
+        )
+    };
+
+    let added_forwards = match previous {
+        Some(previous) if !previous.added_forwards.is_empty() => previous.added_forwards,
+        _ => return,
+    };
+    let domain = domain.to_string();
+    // The new session may still be authenticating, so we
+    // wait for it in the background
+    std::thread::spawn(move || {
+        for (_, spec) in added_forwards {
+            match smol::block_on(forwarding.add(spec.clone())) {
+                Ok(info) => record_added_forward(&domain, info.id, spec),
+                Err(err) => log::error!("ssh domain {domain}: re-adding forward {spec}: {err:#}"),
+            }
+        }
+    });
+}
+
+fn record_added_forward(domain: &str, id: ForwardId, spec: ForwardSpec) {
+    if let Some(session) = DOMAIN_SESSIONS.lock().unwrap().get_mut(domain) {
+        session.added_forwards.push((id, spec));
+    }
+}
+
+fn domain_forwarding(domain: &str) -> anyhow::Result<Forwarding> {
+    DOMAIN_SESSIONS
+        .lock()
+        .unwrap()
+        .get(domain)
+        .map(|session| session.forwarding.clone())
+        .ok_or_else(|| anyhow!("ssh domain {domain} has no established session"))
+}
+
+/// Returns the sftp handle of the established session of
+/// an ssh domain, if any
+pub fn domain_sftp(domain: &str) -> Option<Sftp> {
+    DOMAIN_SESSIONS
+        .lock()
+        .unwrap()
+        .get(domain)
+        .map(|session| session.sftp.clone())
+}
+
+/// Returns the name of the host that the established session of
+/// the ssh domain is connected to
+pub fn domain_host(domain: &str) -> Option<String> {
+    DOMAIN_SESSIONS
+        .lock()
+        .unwrap()
+        .get(domain)
+        .map(|session| session.host.clone())
+}
+
+/// Returns the port forwards of the specified ssh domain, or of all of
+/// the ssh domains with established sessions when `domain` is None.
+pub async fn list_ssh_forwards(domain: Option<&str>) -> anyhow::Result<Vec<SshForward>> {
+    let sessions: Vec<(String, Forwarding)> = match domain {
+        Some(domain) => vec![(domain.to_string(), domain_forwarding(domain)?)],
+        None => DOMAIN_SESSIONS
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(domain, session)| (domain.to_string(), session.forwarding.clone()))
+            .collect(),
+    };
+
+    let mut result = vec![];
+    for (name, forwarding) in sessions {
+        match forwarding.list().await {
+            Ok(forwards) => {
+                result.extend(
+                    forwards
+                        .into_iter()
+                        .map(|info| SshForward::new(&name, info)),
+                );
+            }
+            Err(err) if domain.is_some() => return Err(err),
+            Err(err) => {
+                // The session has ended; forget about it
+                log::debug!("ssh domain {name}: {err:#}");
+                DOMAIN_SESSIONS.lock().unwrap().remove(&name);
+            }
+        }
+    }
+    result.sort_by(|a, b| a.domain.cmp(&b.domain).then(a.id.cmp(&b.id)));
+    Ok(result)
+}
+
+/// Establishes a new port forward on the session of an ssh domain.
+/// `kind` is one of `Local`, `Remote` or `Dynamic`, and `spec` is in
+/// the same form as accepted by `ssh -L`, `ssh -R` or `ssh -D`.
+pub async fn add_ssh_forward(domain: &str, kind: &str, spec: &str) -> anyhow::Result<SshForward> {
+    let kind: ForwardKind = kind.parse()?;
+    let spec = ForwardSpec::parse(kind, spec)?;
+    let info = domain_forwarding(domain)?.add(spec.clone()).await?;
+    record_added_forward(domain, info.id, spec);
+    Ok(SshForward::new(domain, info))
+}
+
+/// Stops listening for new connections for a port forward
+/// of the session of an ssh domain
+pub async fn remove_ssh_forward(domain: &str, id: usize) -> anyhow::Result<()> {
+    domain_forwarding(domain)?.remove(id).await?;
+    if let Some(session) = DOMAIN_SESSIONS.lock().unwrap().get_mut(domain) {
+        session.added_forwards.retain(|(added, _)| *added != id);
+    }
+    Ok(())
+}
+
 impl RemoteSshDomain {
     pub fn with_ssh_domain(dom: &SshDomain) -> anyhow::Result<Self> {
         let id = alloc_domain_id();
         Ok(Self {
             id,
             name: dom.name.clone(),
-            session: Mutex::new(None),
             dom: dom.clone(),
         })
     }
@@ -323,114 +598,322 @@ impl RemoteSshDomain {
 
         Ok((command_line, env))
     }
+}
 
-    async fn start_new_session(
-        &self,
+struct StartNewSessionResult {
+    pty: Box<dyn portable_pty::MasterPty + Send>,
+    child: Box<dyn portable_pty::Child + Send>,
+    writer: BoxedWriter,
+}
+
+/// How long to wait before trying again to reattach a pane whose
+/// session was lost; this doubles after each failed attempt, up to
+/// `MAX_RECONNECT_DELAY`
+const RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+/// The sending halves of the channels through which the wrapped pty,
+/// child and reader/writer of a pane receive the real ones, along with
+/// what we need to spawn the command of the pane again.
+/// Whoever is connecting the pane holds the link; once the pane is
+/// connected, it travels with the child so that the pane can be
+/// reattached to a new session if its session is lost.
+struct PaneLink {
+    domain: String,
+    ssh_config: ConfigMap,
+    command_line: Option<String>,
+    env: HashMap<String, String>,
+    size: Arc<Mutex<TerminalSize>>,
+    reader_tx: Sender<BoxedReader>,
+    writer_tx: Sender<BoxedWriter>,
+    pty_tx: Sender<SshPty>,
+    child_tx: smol::channel::Sender<ConnectedChild>,
+    killer: WrappedSshChildKiller,
+}
+
+/// A child that is running in a pane and the session that it runs on
+struct ConnectedChild {
+    child: SshChildProcess,
+    session: Session,
+    link: PaneLink,
+}
+
+impl PaneLink {
+    /// Creates the wrapped pty, child and writer for a new pane,
+    /// which are then connected to a session by way of the link
+    fn new(
+        domain: &str,
+        ssh_config: ConfigMap,
         command_line: Option<String>,
         env: HashMap<String, String>,
         size: TerminalSize,
-    ) -> anyhow::Result<StartNewSessionResult> {
-        let (session, events) = Session::connect(self.ssh_config().context("obtain ssh config")?)
-            .context("connect to ssh server")?;
-        self.session.lock().unwrap().replace(session.clone());
-
-        // We get to establish the session!
-        //
-        // Since we want spawn to return the Pane in which
-        // we'll carry out interactive auth, we generate
-        // some shim/wrapper versions of the pty, child
-        // and reader/writer.
-
-        let (stdout_read, stdout_write) = socketpair()?;
+    ) -> (Self, StartNewSessionResult) {
         let (reader_tx, reader_rx) = channel();
-        let (stdin_read, stdin_write) = socketpair()?;
         let (writer_tx, writer_rx) = channel();
+        let (pty_tx, pty_rx) = channel();
+        let (child_tx, child_rx) = smol::channel::unbounded();
+        let size = Arc::new(Mutex::new(size));
 
+        // The reader and writer have nothing to talk to until
+        // they are sent the ends of the authentication UI or
+        // of the real pty
         let pty_reader = PtyReader {
-            reader: Box::new(stdout_read),
+            reader: Box::new(std::io::empty()),
             rx: reader_rx,
         };
-
-        let pty_writer = PtyWriter {
-            writer: Box::new(stdin_write),
+        let writer = Box::new(PtyWriter {
+            writer: Box::new(std::io::sink()),
             rx: writer_rx,
-        };
-        let writer = Box::new(pty_writer);
-
-        let (child_tx, child_rx) = channel();
-
-        let child = Box::new(WrappedSshChild {
-            status: None,
-            rx: child_rx,
-            exited: None,
-            killer: WrappedSshChildKiller {
-                inner: Arc::new(Mutex::new(KillerInner {
-                    killer: None,
-                    pending_kill: false,
-                })),
-            },
         });
 
-        let (pty_tx, pty_rx) = channel();
-
-        let size = Arc::new(Mutex::new(size));
-
         let pty = Box::new(WrappedSshPty {
-            inner: RefCell::new(WrappedSshPtyInner::Connecting {
-                size: Arc::clone(&size),
+            inner: RefCell::new(WrappedSshPtyInner {
                 reader: Some(pty_reader),
+                pty: None,
                 connected: pty_rx,
+                size: Arc::clone(&size),
             }),
         });
 
+        let killer = WrappedSshChildKiller {
+            inner: Arc::new(Mutex::new(KillerInner {
+                killer: None,
+                pending_kill: false,
+            })),
+        };
+        let child = Box::new(WrappedSshChild::new(child_rx, killer.clone()));
+
+        let link = Self {
+            domain: domain.to_string(),
+            ssh_config,
+            command_line,
+            env,
+            size,
+            reader_tx,
+            writer_tx,
+            pty_tx,
+            child_tx,
+            killer,
+        };
+        (link, StartNewSessionResult { pty, child, writer })
+    }
+
+    fn host(&self) -> String {
+        self.ssh_config.get("hostname").cloned().unwrap_or_default()
+    }
+
+    fn kill_requested(&self) -> bool {
+        self.killer.inner.lock().unwrap().pending_kill
+    }
+
+    /// Requests a pty on a pooled session, if there is a usable one
+    async fn pooled_pty(&self) -> anyhow::Result<Option<(Session, SshPty, SshChildProcess)>> {
+        let key = PoolKey::new(&self.ssh_config, &self.domain);
+        let session = match ssh_pool::lookup(&key).await {
+            Some(session) => session,
+            None => return Ok(None),
+        };
+        register_domain_session(&self.domain, &session, &self.host());
+
+        let size = crate::terminal_size_to_pty_size(*self.size.lock().unwrap())
+            .context("compute pty size from terminal size")?;
+        match session
+            .request_pty(
+                &config::configuration().term,
+                size,
+                self.command_line.as_deref(),
+                Some(self.env.clone()),
+            )
+            .await
+            .context("request ssh pty")
+        {
+            Ok((pty, child)) => Ok(Some((session, pty, child))),
+            Err(err) => {
+                if err
+                    .root_cause()
+                    .downcast_ref::<wezterm_ssh::DeadSession>()
+                    .is_some()
+                {
+                    // The connection was lost since it was last checked,
+                    // so we'll try making a new one
+                    ssh_pool::remove(&key, &session);
+                    Ok(None)
+                } else {
+                    log::error!("{err:#?}");
+                    Err(err)
+                }
+            }
+        }
+    }
+
+    /// Makes a new connection and carries out the authentication
+    /// in the pane
+    fn start_new_session(self) -> anyhow::Result<()> {
+        let (session, events) = self.connect()?;
+
+        // We get to establish the session!
+        //
+        // The authentication UI is shown in the pane by way of
+        // a pair of sockets that stand in for the pty until we
+        // have the real one.
+        let (stdin_read, stdout_write) = self.open_shim()?;
+
         // And with those created, we can now spawn a new thread
         // to perform the blocking (from its perspective) terminal
         // UI to carry out any authentication.
-        let mut stdout_write = BufWriter::new(stdout_write);
         std::thread::spawn(move || {
-            if let Err(err) = connect_ssh_session(
-                session,
-                events,
-                stdin_read,
-                writer_tx,
-                &mut stdout_write,
-                reader_tx,
-                child_tx,
-                pty_tx,
-                size,
-                command_line,
-                env,
-            ) {
+            self.authenticate_in_pane(session, events, stdin_read, stdout_write);
+        });
+        Ok(())
+    }
+
+    /// Makes a new connection.  It is pooled right away, so that spawns
+    /// made while we are still authenticating will use it rather than
+    /// connecting again; their requests are serviced once we're
+    /// authenticated
+    fn connect(&self) -> anyhow::Result<(Session, smol::channel::Receiver<SessionEvent>)> {
+        let key = PoolKey::new(&self.ssh_config, &self.domain);
+        let (session, events) =
+            Session::connect(self.ssh_config.clone()).context("connect to ssh server")?;
+        ssh_pool::insert(key, &session);
+        register_domain_session(&self.domain, &session, &self.host());
+        Ok((session, events))
+    }
+
+    /// Creates the sockets through which the authentication UI is
+    /// shown in the pane and receives its input, and hands the pane
+    /// its ends of them
+    fn open_shim(&self) -> anyhow::Result<(FileDescriptor, BufWriter<FileDescriptor>)> {
+        let (stdout_read, stdout_write) = socketpair()?;
+        let (stdin_read, stdin_write) = socketpair()?;
+        self.reader_tx
+            .send(Box::new(stdout_read))
+            .map_err(|e| anyhow!("{:#}", e))?;
+        self.writer_tx
+            .send(Box::new(stdin_write))
+            .map_err(|e| anyhow!("{:#}", e))?;
+        Ok((stdin_read, BufWriter::new(stdout_write)))
+    }
+
+    /// Runs the authentication UI and then attaches the pane to a new
+    /// pty.  Returns the link if the pty couldn't be created.
+    fn authenticate_in_pane(
+        self,
+        session: Session,
+        events: smol::channel::Receiver<SessionEvent>,
+        stdin_read: FileDescriptor,
+        mut stdout_write: BufWriter<FileDescriptor>,
+    ) -> Option<Self> {
+        let link = match connect_ssh_session(self, session, events, stdin_read, &mut stdout_write) {
+            Ok(link) => link,
+            Err(err) => {
                 let _ = write!(stdout_write, "{:#}", err);
                 log::error!("Failed to connect ssh: {:#}", err);
+                None
             }
-            let _ = stdout_write.flush();
-        });
+        };
+        let _ = stdout_write.flush();
+        link
+    }
 
-        Ok(StartNewSessionResult { pty, child, writer })
+    /// Hands the real pty and child to the wrappers of the pane
+    fn attach(self, session: Session, pty: SshPty, child: SshChildProcess) -> anyhow::Result<()> {
+        // Obtain the real stdin/stdout for the pty
+        let reader = pty.try_clone_reader()?;
+        let writer = pty.take_writer()?;
+
+        // And send them to the wrapped reader/writer
+        self.writer_tx
+            .send(Box::new(writer))
+            .map_err(|e| anyhow!("{:#}", e))?;
+        self.reader_tx
+            .send(Box::new(reader))
+            .map_err(|e| anyhow!("{:#}", e))?;
+
+        // Likewise, send the real pty and child to the wrappers.
+        // The pty will be picked up when it is next polled or resized.
+        self.pty_tx.send(pty)?;
+        let child_tx = self.child_tx.clone();
+        child_tx
+            .try_send(ConnectedChild {
+                child,
+                session,
+                link: self,
+            })
+            .map_err(|e| anyhow!("{:#}", e))?;
+        Ok(())
     }
-}
 
-struct StartNewSessionResult {
-    pty: Box<dyn portable_pty::MasterPty + Send>,
-    child: Box<dyn portable_pty::Child + Send>,
-    writer: BoxedWriter,
+    /// Reattaches the pane to a new session after its session was lost.
+    /// Attempts are repeated until one succeeds or the pane is killed.
+    fn reconnect(self) {
+        let notice = format!("\r\n[connection to {} lost, reconnecting]\r\n", self.host());
+        self.reader_tx
+            .send(Box::new(std::io::Cursor::new(notice.into_bytes())))
+            .ok();
+
+        std::thread::spawn(move || {
+            let mut link = self;
+            let mut delay = RECONNECT_DELAY;
+            loop {
+                link = match link.try_reconnect() {
+                    Some(link) => link,
+                    None => return,
+                };
+                std::thread::sleep(delay);
+                delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+                if link.kill_requested() {
+                    return;
+                }
+            }
+        });
+    }
+
+    /// Returns the link if the attempt to reattach the pane should
+    /// be repeated later
+    fn try_reconnect(self) -> Option<Self> {
+        match smol::block_on(self.pooled_pty()) {
+            Ok(Some((session, pty, child))) => {
+                if let Err(err) = self.attach(session, pty, child) {
+                    log::error!("failed to reattach ssh pane: {:#}", err);
+                }
+                return None;
+            }
+            Ok(None) => {}
+            Err(err) => {
+                log::error!("ssh domain {}: reattaching pane: {:#}", self.domain, err);
+                return Some(self);
+            }
+        }
+
+        let (session, events) = match self.connect() {
+            Ok(connected) => connected,
+            Err(err) => {
+                log::error!("ssh domain {}: reconnecting: {:#}", self.domain, err);
+                return Some(self);
+            }
+        };
+        match self.open_shim() {
+            Ok((stdin_read, stdout_write)) => {
+                self.authenticate_in_pane(session, events, stdin_read, stdout_write)
+            }
+            Err(err) => {
+                log::error!("failed to reattach ssh pane: {:#}", err);
+                None
+            }
+        }
+    }
 }
 
-/// Carry out the authentication process and create the initial pty.
+/// Carry out the authentication process and attach the pane to a new
+/// pty.  Returns the link if the pty couldn't be created.
 fn connect_ssh_session(
+    link: PaneLink,
     session: Session,
     events: smol::channel::Receiver<SessionEvent>,
     mut stdin_read: FileDescriptor,
-    stdin_tx: Sender<BoxedWriter>,
     stdout_write: &mut BufWriter<FileDescriptor>,
-    stdout_tx: Sender<BoxedReader>,
-    child_tx: Sender<SshChildProcess>,
-    pty_tx: Sender<SshPty>,
-    size: Arc<Mutex<TerminalSize>>,
-    command_line: Option<String>,
-    env: HashMap<String, String>,
-) -> anyhow::Result<()> {
+) -> anyhow::Result<Option<PaneLink>> {
     struct StdoutShim<'a> {
         size: Arc<Mutex<TerminalSize>>,
         stdout: &'a mut BufWriter<FileDescriptor>,
@@ -568,6 +1051,7 @@ fn connect_ssh_session(
         }
     }
 
+    let size = Arc::clone(&link.size);
     let renderer = termwiz_funcs::new_wezterm_terminfo_renderer();
     let mut shim = TerminalShim {
         stdout: &mut StdoutShim {
@@ -598,46 +1082,13 @@ fn connect_ssh_session(
                 }
             }
             SessionEvent::HostVerify(verify) => {
-                shim.output_line(&verify.message)?;
-                let mut editor = LineEditor::new(&mut shim);
-                let mut host = PasswordPromptHost::default();
-                host.echo = true;
-                editor.set_prompt("Enter [y/n]> ");
-                let ok = if let Some(line) = editor.read_line(&mut host)? {
-                    match line.as_ref() {
-                        "y" | "Y" | "yes" | "YES" => true,
-                        "n" | "N" | "no" | "NO" | _ => false,
-                    }
-                } else {
-                    false
-                };
-                smol::block_on(verify.answer(ok)).context("send verify response")?;
+                let mut prompt = TerminalPrompt { term: &mut shim };
+                smol::block_on(verify.prompt_and_answer(&mut prompt))
+                    .context("send verify response")?;
             }
             SessionEvent::Authenticate(auth) => {
-                if !auth.username.is_empty() {
-                    shim.output_line(&format!("Authentication for {}", auth.username))?;
-                }
-                if !auth.instructions.is_empty() {
-                    shim.output_line(&auth.instructions)?;
-                }
-                let mut answers = vec![];
-                for prompt in &auth.prompts {
-                    let mut prompt_lines = prompt.prompt.split('\n').collect::<Vec<_>>();
-                    let editor_prompt = prompt_lines.pop().unwrap();
-                    for line in &prompt_lines {
-                        shim.output_line(line)?;
-                    }
-                    let mut editor = LineEditor::new(&mut shim);
-                    let mut host = PasswordPromptHost::default();
-                    editor.set_prompt(editor_prompt);
-                    host.echo = prompt.echo;
-                    if let Some(line) = editor.read_line(&mut host)? {
-                        answers.push(line);
-                    } else {
-                        anyhow::bail!("Authentication was cancelled");
-                    }
-                }
-                smol::block_on(auth.answer(answers))?;
+                let mut prompt = TerminalPrompt { term: &mut shim };
+                smol::block_on(auth.prompt_and_answer(&mut prompt))?;
             }
             SessionEvent::Error(err) => {
                 shim.output_line(&format!("Error: {}", err))?;
@@ -652,8 +1103,8 @@ fn connect_ssh_session(
                 match smol::block_on(session.request_pty(
                     &config::configuration().term,
                     crate::terminal_size_to_pty_size(*size.lock().unwrap())?,
-                    command_line.as_ref().map(|s| s.as_str()),
-                    Some(env),
+                    link.command_line.as_deref(),
+                    Some(link.env.clone()),
                 )) {
                     Err(err) => {
                         shim.output_line(&format!("Failed to spawn command: {:#}", err))?;
@@ -662,39 +1113,20 @@ fn connect_ssh_session(
                     Ok((pty, child)) => {
                         drop(shim);
 
-                        // Obtain the real stdin/stdout for the pty
-                        let reader = pty.try_clone_reader()?;
-                        let writer = pty.take_writer()?;
-
-                        // And send them to the wrapped reader/writer
-                        stdin_tx
-                            .send(Box::new(writer))
-                            .map_err(|e| anyhow!("{:#}", e))?;
-                        stdout_tx
-                            .send(Box::new(reader))
-                            .map_err(|e| anyhow!("{:#}", e))?;
-
-                        // Likewise, send the real pty and child to
-                        // the wrappers
-                        pty_tx.send(pty)?;
-                        child_tx.send(child)?;
+                        link.attach(session, pty, child)?;
 
                         // Now when we return, our stdin_read and
                         // stdout_write will close and that will cause
                         // the PtyReader and PtyWriter to recv the
-                        // the new reader/writer above and continue.
-                        //
-                        // The pty and child will be picked up when
-                        // they are next polled or resized.
-
-                        return Ok(());
+                        // the new reader/writer and continue.
+                        return Ok(None);
                     }
                 }
             }
         }
     }
 
-    Ok(())
+    Ok(Some(link))
 }
 
 #[async_trait(?Send)]
@@ -704,6 +1136,7 @@ impl Domain for RemoteSshDomain {
         size: TerminalSize,
         command: Option<CommandBuilder>,
         command_dir: Option<String>,
+        initial_output: Option<String>,
     ) -> anyhow::Result<Arc<dyn Pane>> {
         let pane_id = alloc_pane_id();
 
@@ -711,53 +1144,22 @@ impl Domain for RemoteSshDomain {
             .build_command(pane_id, command, command_dir)
             .context("build_command")?;
 
-        // This needs to be separate from the if let block below in order
-        // for the lock to be released at the appropriate time
-        let mut session: Option<Session> = self.session.lock().unwrap().as_ref().cloned();
-
-        let StartNewSessionResult { pty, child, writer } = if let Some(session) = session.take() {
-            match session
-                .request_pty(
-                    &config::configuration().term,
-                    crate::terminal_size_to_pty_size(size)
-                        .context("compute pty size from terminal size")?,
-                    command_line.as_ref().map(|s| s.as_str()),
-                    Some(env.clone()),
-                )
-                .await
-                .context("request ssh pty")
-            {
-                Ok((concrete_pty, concrete_child)) => {
-                    let pty = Box::new(concrete_pty);
-                    let child = Box::new(concrete_child);
-                    let writer = Box::new(pty.take_writer().context("take writer from pty")?);
+        let ssh_config = self.ssh_config().context("obtain ssh config")?;
+        let (link, StartNewSessionResult { pty, child, writer }) =
+            PaneLink::new(&self.name, ssh_config, command_line, env, size);
 
-                    StartNewSessionResult { pty, child, writer }
-                }
-                Err(err) => {
-                    if err
-                        .root_cause()
-                        .downcast_ref::<wezterm_ssh::DeadSession>()
-                        .is_some()
-                    {
-                        // Session died (perhaps they closed the initial tab?)
-                        // So we'll try making a new one
-                        self.start_new_session(command_line, env, size).await?
-                    } else {
-                        log::error!("{err:#?}");
-                        return Err(err);
-                    }
-                }
-            }
-        } else {
-            self.start_new_session(command_line, env, size).await?
-        };
+        match link.pooled_pty().await? {
+            Some((session, concrete_pty, concrete_child)) => link
+                .attach(session, concrete_pty, concrete_child)
+                .context("attach ssh pty")?,
+            None => link.start_new_session()?,
+        }
 
         // Wrap up the pty etc. in a LocalPane.  That allows for
         // eg: tmux integration to be tunnelled via the remote
         // session without duplicating a lot of logic over here.
 
-        let writer = WriterWrapper::new(writer);
+        let writer = WriterWrapper::new(pane_id, writer);
 
         let terminal = wezterm_term::Terminal::new(
             size,
@@ -777,7 +1179,7 @@ impl Domain for RemoteSshDomain {
             "RemoteSshDomain".to_string(),
         ));
         let mux = Mux::get();
-        mux.add_pane(&pane)?;
+        mux.add_pane_with_output(&pane, initial_output)?;
 
         Ok(pane)
     }
@@ -813,9 +1215,10 @@ impl Domain for RemoteSshDomain {
 #[derive(Debug)]
 struct KillerInner {
     killer: Option<Box<dyn ChildKiller + Send + Sync>>,
-    /// If we haven't populated `killer` by the time someone has called
-    /// `kill`, then we use this to remember to kill as soon as we recv
-    /// the child process.
+    /// Set by `kill`.  If we haven't populated `killer` by then,
+    /// we use this to remember to kill as soon as we recv the child
+    /// process, and it prevents the pane from being reattached if
+    /// its session is lost.
     pending_kill: bool,
 }
 
@@ -824,49 +1227,99 @@ struct WrappedSshChildKiller {
     inner: Arc<Mutex<KillerInner>>,
 }
 
+/// Waits for `child` to exit, checking on its session at the liveness
+/// interval meanwhile, as a session whose connection stalls won't
+/// otherwise end its channels.  Returns None if the session is lost.
+async fn wait_for_child(child: &mut SshChildProcess, session: &Session) -> Option<ExitStatus> {
+    loop {
+        let exited = async { Some(child.async_wait().await) };
+        let interval = async {
+            smol::Timer::after(ssh_pool::check_interval()).await;
+            None
+        };
+        if let Some(status) = smol::future::or(exited, interval).await {
+            // Channels also end when their session goes away
+            return match ssh_pool::check(session).await {
+                Ok(_) => Some(status.unwrap_or_else(|_| ExitStatus::with_exit_code(1))),
+                Err(_) => None,
+            };
+        }
+        if ssh_pool::check(session).await.is_err() {
+            return None;
+        }
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct WrappedSshChild {
-    status: Option<AsyncReceiver<ExitStatus>>,
-    rx: Receiver<SshChildProcess>,
+    status: AsyncReceiver<ExitStatus>,
     exited: Option<ExitStatus>,
     killer: WrappedSshChildKiller,
 }
 
 impl WrappedSshChild {
-    fn check_connected(&mut self) {
-        if self.status.is_none() {
-            match self.rx.try_recv() {
-                Ok(c) => {
-                    self.got_child(c);
-                }
-                Err(TryRecvError::Empty) => {}
-                Err(err) => {
-                    log::debug!("WrappedSshChild::check_connected err: {:#?}", err);
-                    self.exited.replace(ExitStatus::with_exit_code(1));
+    /// Waits for the children that are sent by the `PaneLink` of the
+    /// pane in turn.  A child also ends when its session is lost, in
+    /// which case the pane is reattached to a new session rather than
+    /// reporting that the child exited.
+    fn new(
+        children: smol::channel::Receiver<ConnectedChild>,
+        killer: WrappedSshChildKiller,
+    ) -> Self {
+        let (tx, rx) = bounded(1);
+        promise::spawn::spawn_into_main_thread(async move {
+            while let Ok(ConnectedChild {
+                mut child,
+                session,
+                link,
+            }) = children.recv().await
+            {
+                {
+                    let mut killer = link.killer.inner.lock().unwrap();
+                    killer.killer.replace(child.clone_killer());
+                    if killer.pending_kill {
+                        let _ = child.kill().ok();
+                    }
                 }
-            }
-        }
-    }
 
-    fn got_child(&mut self, mut child: SshChildProcess) {
-        {
-            let mut killer = self.killer.inner.lock().unwrap();
-            killer.killer.replace(child.clone_killer());
-            if killer.pending_kill {
-                let _ = child.kill().ok();
-            }
-        }
+                let status = wait_for_child(&mut child, &session).await;
+                link.killer.inner.lock().unwrap().killer.take();
 
-        let (tx, rx) = bounded(1);
-        promise::spawn::spawn_into_main_thread(async move {
-            if let Ok(status) = child.async_wait().await {
-                tx.send(status).await.ok();
-                let mux = Mux::get();
-                mux.prune_dead_windows();
+                match status {
+                    Some(status) => {
+                        tx.send(status).await.ok();
+                        break;
+                    }
+                    None if link.kill_requested() => {
+                        tx.send(ExitStatus::with_exit_code(1)).await.ok();
+                        break;
+                    }
+                    None => {}
+                }
+
+                log::warn!(
+                    "ssh domain {}: lost session {}, reattaching pane",
+                    link.domain,
+                    session.id()
+                );
+                // Let go of the session so that it is closed, and the
+                // old channel of this pane along with it, once all of
+                // the panes that were using it have noticed
+                ssh_pool::remove(&PoolKey::new(&link.ssh_config, &link.domain), &session);
+                drop(child);
+                drop(session);
+                link.reconnect();
             }
+            let mux = Mux::get();
+            mux.prune_dead_windows();
         })
         .detach();
-        self.status.replace(rx);
+
+        Self {
+            status: rx,
+            exited: None,
+            killer,
+        }
     }
 }
 
@@ -876,24 +1329,18 @@ impl portable_pty::Child for WrappedSshChild {
             return Ok(Some(status.clone()));
         }
 
-        self.check_connected();
-
-        if let Some(rx) = self.status.as_mut() {
-            match rx.try_recv() {
-                Ok(status) => {
-                    self.exited.replace(status.clone());
-                    Ok(Some(status))
-                }
-                Err(smol::channel::TryRecvError::Empty) => Ok(None),
-                Err(err) => {
-                    log::debug!("WrappedSshChild::try_wait err: {:#?}", err);
-                    let status = ExitStatus::with_exit_code(1);
-                    self.exited.replace(status.clone());
-                    Ok(Some(status))
-                }
+        match self.status.try_recv() {
+            Ok(status) => {
+                self.exited.replace(status.clone());
+                Ok(Some(status))
+            }
+            Err(smol::channel::TryRecvError::Empty) => Ok(None),
+            Err(err) => {
+                log::debug!("WrappedSshChild::try_wait err: {:#?}", err);
+                let status = ExitStatus::with_exit_code(1);
+                self.exited.replace(status.clone());
+                Ok(Some(status))
             }
-        } else {
-            Ok(None)
         }
     }
 
@@ -902,22 +1349,7 @@ impl portable_pty::Child for WrappedSshChild {
             return Ok(status.clone());
         }
 
-        if self.status.is_none() {
-            match smol::block_on(async { self.rx.recv() }) {
-                Ok(c) => {
-                    self.got_child(c);
-                }
-                Err(err) => {
-                    log::debug!("WrappedSshChild err: {:#?}", err);
-                    let status = ExitStatus::with_exit_code(1);
-                    self.exited.replace(status.clone());
-                    return Ok(status);
-                }
-            }
-        }
-
-        let rx = self.status.as_mut().unwrap();
-        match smol::block_on(rx.recv()) {
+        match smol::block_on(self.status.recv()) {
             Ok(status) => {
                 self.exited.replace(status.clone());
                 Ok(status)
@@ -943,13 +1375,7 @@ impl portable_pty::Child for WrappedSshChild {
 
 impl ChildKiller for WrappedSshChild {
     fn kill(&mut self) -> std::io::Result<()> {
-        let mut killer = self.killer.inner.lock().unwrap();
-        if let Some(killer) = killer.killer.as_mut() {
-            killer.kill()
-        } else {
-            killer.pending_kill = true;
-            Ok(())
-        }
+        self.killer.kill()
     }
 
     fn clone_killer(&self) -> Box<dyn ChildKiller + Send + Sync> {
@@ -960,11 +1386,10 @@ impl ChildKiller for WrappedSshChild {
 impl ChildKiller for WrappedSshChildKiller {
     fn kill(&mut self) -> std::io::Result<()> {
         let mut killer = self.inner.lock().unwrap();
-        if let Some(killer) = killer.killer.as_mut() {
-            killer.kill()
-        } else {
-            killer.pending_kill = true;
-            Ok(())
+        killer.pending_kill = true;
+        match killer.killer.as_mut() {
+            Some(killer) => killer.kill(),
+            None => Ok(()),
         }
     }
 
@@ -986,16 +1411,13 @@ impl WrappedSshPty {
     }
 }
 
-enum WrappedSshPtyInner {
-    Connecting {
-        reader: Option<PtyReader>,
-        connected: Receiver<SshPty>,
-        size: Arc<Mutex<TerminalSize>>,
-    },
-    Connected {
-        reader: Option<PtyReader>,
-        pty: SshPty,
-    },
+struct WrappedSshPtyInner {
+    reader: Option<PtyReader>,
+    /// The real pty, once we have one.  It is replaced by a new one
+    /// when the pane is reattached to a new session.
+    pty: Option<SshPty>,
+    connected: Receiver<SshPty>,
+    size: Arc<Mutex<TerminalSize>>,
 }
 
 struct PtyReader {
@@ -1010,76 +1432,56 @@ struct PtyWriter {
 
 impl WrappedSshPtyInner {
     fn check_connected(&mut self) -> anyhow::Result<()> {
-        match self {
-            Self::Connecting {
-                reader,
-                connected,
-                size,
-                ..
-            } => {
-                if let Ok(pty) = connected.try_recv() {
-                    let res = pty.resize(crate::terminal_size_to_pty_size(*size.lock().unwrap())?);
-                    *self = Self::Connected {
-                        pty,
-                        reader: reader.take(),
-                    };
-                    res
-                } else {
-                    Ok(())
-                }
-            }
-            _ => Ok(()),
+        let mut res = Ok(());
+        while let Ok(pty) = self.connected.try_recv() {
+            res = pty.resize(crate::terminal_size_to_pty_size(
+                *self.size.lock().unwrap(),
+            )?);
+            self.pty.replace(pty);
         }
+        res
     }
 
     fn is_connecting(&mut self) -> bool {
         self.check_connected().ok();
-        match self {
-            Self::Connecting { .. } => true,
-            Self::Connected { .. } => false,
-        }
+        self.pty.is_none()
     }
 }
 
 impl portable_pty::MasterPty for WrappedSshPty {
     fn resize(&self, new_size: PtySize) -> anyhow::Result<()> {
         let mut inner = self.inner.borrow_mut();
-        match &mut *inner {
-            WrappedSshPtyInner::Connecting { ref mut size, .. } => {
-                {
-                    let mut size = size.lock().unwrap();
-                    size.cols = new_size.cols as usize;
-                    size.rows = new_size.rows as usize;
-                    size.pixel_height = new_size.pixel_height as usize;
-                    size.pixel_width = new_size.pixel_width as usize;
-                }
-                inner.check_connected()
-            }
-            WrappedSshPtyInner::Connected { pty, .. } => pty.resize(new_size),
+        {
+            // Remember the size for the authentication UI and
+            // for any pty that we are yet to receive
+            let mut size = inner.size.lock().unwrap();
+            size.cols = new_size.cols as usize;
+            size.rows = new_size.rows as usize;
+            size.pixel_height = new_size.pixel_height as usize;
+            size.pixel_width = new_size.pixel_width as usize;
+        }
+        inner.check_connected()?;
+        match &inner.pty {
+            Some(pty) => pty.resize(new_size),
+            None => Ok(()),
         }
     }
 
     fn get_size(&self) -> anyhow::Result<PtySize> {
         let mut inner = self.inner.borrow_mut();
-        match &*inner {
-            WrappedSshPtyInner::Connecting { size, .. } => {
-                let size = crate::terminal_size_to_pty_size(*size.lock().unwrap())?;
-                inner.check_connected()?;
-                Ok(size)
-            }
-            WrappedSshPtyInner::Connected { pty, .. } => pty.get_size(),
+        inner.check_connected()?;
+        match &inner.pty {
+            Some(pty) => pty.get_size(),
+            None => crate::terminal_size_to_pty_size(*inner.size.lock().unwrap()),
         }
     }
 
     fn try_clone_reader(&self) -> anyhow::Result<Box<(dyn Read + Send + 'static)>> {
         let mut inner = self.inner.borrow_mut();
         inner.check_connected()?;
-        match &mut *inner {
-            WrappedSshPtyInner::Connected { ref mut reader, .. }
-            | WrappedSshPtyInner::Connecting { ref mut reader, .. } => match reader.take() {
-                Some(r) => Ok(Box::new(r)),
-                None => anyhow::bail!("reader already taken"),
-            },
+        match inner.reader.take() {
+            Some(r) => Ok(Box::new(r)),
+            None => anyhow::bail!("reader already taken"),
         }
     }
 
@@ -1112,7 +1514,8 @@ impl std::io::Write for PtyWriter {
         // socket and we won't discover the issue until we write
         // the next byte.
         // <https://github.com/wezterm/wezterm/issues/771>
-        if let Ok(writer) = self.rx.try_recv() {
+        // The most recent writer is the one to use.
+        while let Ok(writer) = self.rx.try_recv() {
             self.writer = writer;
         }
         self.writer.write(buf)
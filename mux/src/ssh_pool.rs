@@ -0,0 +1,323 @@
+//! Shares ssh sessions between everything that connects to the same
+//! host, in a similar way to the `ControlMaster` option of openssh.
+//! Ssh domains, `wezterm ssh` windows and the multiplexer client
+//! all look here before making a new connection.  The pool belongs to
+//! the process, so separate processes, such as `wezterm cli scp`, only
+//! share sessions among their own connections.
+//!
+//! The pool holds a reference to each session so that it outlives the
+//! panes that use it for a while, in case it is needed again.  Sessions
+//! are periodically verified by sending a keep alive, and those that are
+//! broken or that have had no open channels for longer than
+//! `ssh_session_idle_timeout` are dropped from the pool.
+//!
+//! The panes of ssh domains also check their session at that interval,
+//! so that a connection that stalls during a network blip is noticed.
+//! The remote processes end along with the connection, but the panes
+//! that were using it are reattached to a new session from the pool,
+//! where their commands are started again, and the forwards that an
+//! ssh domain added at runtime are re-established on it.
+use crate::connui::ConnectionUI;
+use std::collections::HashMap;
+use std::sync::{Mutex, Once};
+use std::time::{Duration, Instant};
+use wezterm_ssh::{ConfigMap, Session, SessionStatus};
+
+/// The ssh options that influence the connection that is made;
+/// configurations that agree on all of them can share a session
+const KEY_OPTIONS: &[&str] = &[
+    "hostname",
+    "port",
+    "user",
+    "proxycommand",
+    "proxyjump",
+    "forwardagent",
+    "localforward",
+    "remoteforward",
+    "dynamicforward",
+    "wezterm_ssh_backend",
+];
+
+/// How long to wait for a session to respond to a liveness check
+const CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The shortest interval between checks, so that an unreasonable
+/// `ssh_session_liveness_interval` can't make the reaper spin
+const MIN_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+lazy_static::lazy_static! {
+    static ref POOL: Mutex<HashMap<PoolKey, PooledSession>> = Mutex::new(HashMap::new());
+}
+
+static START_REAPER: Once = Once::new();
+
+/// Identifies the sessions that can be shared
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PoolKey {
+    options: Vec<Option<String>>,
+    /// When sharing is disabled, sessions are only
+    /// reused by the same owner
+    owner: Option<String>,
+}
+
+impl PoolKey {
+    /// Computes the key for a config.  `owner` names the user of the
+    /// session, such as the ssh domain, and is only significant when
+    /// `ssh_session_sharing` is disabled.
+    pub fn new(ssh_config: &ConfigMap, owner: &str) -> Self {
+        Self::with_sharing(
+            ssh_config,
+            owner,
+            config::configuration().ssh_session_sharing,
+        )
+    }
+
+    fn with_sharing(ssh_config: &ConfigMap, owner: &str, sharing: bool) -> Self {
+        Self {
+            options: KEY_OPTIONS
+                .iter()
+                .map(|option| ssh_config.get(*option).cloned())
+                .collect(),
+            owner: if sharing {
+                None
+            } else {
+                Some(owner.to_string())
+            },
+        }
+    }
+}
+
+struct PooledSession {
+    session: Session,
+    /// When the session was last handed out
+    last_used: Instant,
+    /// When the session was last verified to be usable
+    last_checked: Instant,
+    /// When the session was first seen to have no open channels
+    idle_since: Option<Instant>,
+}
+
+/// How often sessions are checked to verify that they are still usable
+pub(crate) fn check_interval() -> Duration {
+    config::configuration()
+        .ssh_session_liveness_interval
+        .max(MIN_CHECK_INTERVAL)
+}
+
+/// Returns true if a session that has had no channels for `idle_for`
+/// and that was last handed out `unused_for` ago should be closed
+fn idle_expired(idle_for: Duration, unused_for: Duration, timeout: Duration) -> bool {
+    idle_for >= timeout && unused_for >= timeout
+}
+
+/// Verifies that a session is still usable, giving up if the
+/// server doesn't respond in a reasonable time
+pub(crate) async fn check(session: &Session) -> anyhow::Result<SessionStatus> {
+    let timeout = async {
+        smol::Timer::after(CHECK_TIMEOUT).await;
+        Err(anyhow::anyhow!(
+            "timed out waiting for the server to respond"
+        ))
+    };
+    smol::future::or(session.check_alive(), timeout).await
+}
+
+/// Returns a usable session from the pool, if there is one.
+/// Sessions that haven't been checked recently are verified first,
+/// and those that turn out to be broken are discarded, so that the
+/// caller will go on to make a fresh connection.
+pub async fn lookup(key: &PoolKey) -> Option<Session> {
+    let (session, needs_check) = {
+        let mut pool = POOL.lock().unwrap();
+        let entry = pool.get_mut(key)?;
+        entry.last_used = Instant::now();
+        (
+            entry.session.clone(),
+            entry.last_checked.elapsed() >= check_interval(),
+        )
+    };
+
+    if needs_check {
+        if let Err(err) = check(&session).await {
+            log::warn!("discarding broken shared ssh session: {err:#}");
+            remove(key, &session);
+            return None;
+        }
+        if let Some(entry) = POOL.lock().unwrap().get_mut(key) {
+            entry.last_checked = Instant::now();
+        }
+    }
+
+    Some(session)
+}
+
+/// Makes a session available to be shared, replacing any
+/// session that was previously pooled for the same key
+pub fn insert(key: PoolKey, session: &Session) {
+    let now = Instant::now();
+    POOL.lock().unwrap().insert(
+        key,
+        PooledSession {
+            session: session.clone(),
+            last_used: now,
+            last_checked: now,
+            idle_since: None,
+        },
+    );
+    START_REAPER.call_once(|| {
+        std::thread::Builder::new()
+            .name("ssh-session-pool".to_string())
+            .spawn(reaper)
+            .ok();
+    });
+}
+
+/// Removes the session from the pool, unless it has
+/// already been replaced by a different session
+pub fn remove(key: &PoolKey, session: &Session) {
+    let mut pool = POOL.lock().unwrap();
+    if pool
+        .get(key)
+        .map(|entry| entry.session.id() == session.id())
+        .unwrap_or(false)
+    {
+        pool.remove(key);
+    }
+}
+
+/// Periodically checks the pooled sessions, dropping those
+/// that are broken or that have been idle for too long
+fn reaper() {
+    loop {
+        std::thread::sleep(check_interval());
+        let config = config::configuration();
+
+        let sessions: Vec<(PoolKey, Session)> = POOL
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(key, entry)| (key.clone(), entry.session.clone()))
+            .collect();
+
+        for (key, session) in sessions {
+            match smol::block_on(check(&session)) {
+                Err(err) => {
+                    log::warn!("discarding broken shared ssh session: {err:#}");
+                    remove(&key, &session);
+                }
+                Ok(status) => {
+                    let mut pool = POOL.lock().unwrap();
+                    let entry = match pool.get_mut(&key) {
+                        Some(entry) if entry.session.id() == session.id() => entry,
+                        _ => continue,
+                    };
+                    let now = Instant::now();
+                    entry.last_checked = now;
+                    if status.channels > 0 {
+                        entry.idle_since = None;
+                        continue;
+                    }
+                    let idle_since = *entry.idle_since.get_or_insert(now);
+                    let timeout = config.ssh_session_idle_timeout;
+                    if idle_expired(idle_since.elapsed(), entry.last_used.elapsed(), timeout) {
+                        log::debug!("closing idle shared ssh session {}", session.id());
+                        pool.remove(&key);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Returns a shared session for the config, making a new connection
+/// and authenticating it using `ui` if there is no usable session
+/// in the pool
+pub fn connect_with_ui(
+    ssh_config: ConfigMap,
+    owner: &str,
+    ui: &mut ConnectionUI,
+) -> anyhow::Result<Session> {
+    let key = PoolKey::new(&ssh_config, owner);
+    if let Some(session) = smol::block_on(lookup(&key)) {
+        if let Some(host) = ssh_config.get("hostname") {
+            ui.output_str(&format!("Reusing existing SSH connection to {}\n", host));
+        }
+        return Ok(session);
+    }
+    let session = crate::ssh::ssh_connect_with_ui(ssh_config, ui)?;
+    insert(key, &session);
+    Ok(session)
+}
+
+/// Returns a shared session for the config, making a new connection
+/// and authenticating it using the controlling terminal if there is
+/// no usable session in the pool
+pub fn connect_with_terminal(ssh_config: ConfigMap, owner: &str) -> anyhow::Result<Session> {
+    let key = PoolKey::new(&ssh_config, owner);
+    if let Some(session) = smol::block_on(lookup(&key)) {
+        return Ok(session);
+    }
+    let session = crate::ssh::ssh_connect_with_terminal(ssh_config)?;
+    insert(key, &session);
+    Ok(session)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn ssh_config(pairs: &[(&str, &str)]) -> ConfigMap {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn key_ignores_owner_when_sharing() {
+        let config = ssh_config(&[("hostname", "example.com"), ("user", "wez")]);
+        assert_eq!(
+            PoolKey::with_sharing(&config, "dom1", true),
+            PoolKey::with_sharing(&config, "dom2", true)
+        );
+        assert_ne!(
+            PoolKey::with_sharing(&config, "dom1", false),
+            PoolKey::with_sharing(&config, "dom2", false)
+        );
+        assert_eq!(
+            PoolKey::with_sharing(&config, "dom1", false),
+            PoolKey::with_sharing(&config, "dom1", false)
+        );
+    }
+
+    #[test]
+    fn key_considers_connection_options() {
+        let base = ssh_config(&[("hostname", "example.com"), ("user", "wez")]);
+        let key = PoolKey::with_sharing(&base, "dom", true);
+
+        let mut other_user = base.clone();
+        other_user.insert("user".to_string(), "root".to_string());
+        assert_ne!(key, PoolKey::with_sharing(&other_user, "dom", true));
+
+        let mut jump = base.clone();
+        jump.insert("proxyjump".to_string(), "bastion".to_string());
+        assert_ne!(key, PoolKey::with_sharing(&jump, "dom", true));
+
+        // Options that don't affect the connection don't matter
+        let mut identity = base.clone();
+        identity.insert("identityfile".to_string(), "~/.ssh/id_other".to_string());
+        assert_eq!(key, PoolKey::with_sharing(&identity, "dom", true));
+    }
+
+    #[test]
+    fn idle_expiry() {
+        let timeout = Duration::from_secs(300);
+        let long = Duration::from_secs(301);
+        let short = Duration::from_secs(10);
+        assert!(idle_expired(long, long, timeout));
+        assert!(idle_expired(timeout, timeout, timeout));
+        // Recently handed out, but no channel opened yet
+        assert!(!idle_expired(long, short, timeout));
+        assert!(!idle_expired(short, long, timeout));
+    }
+}
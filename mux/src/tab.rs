@@ -37,6 +37,17 @@ impl Recency {
     }
 }
 
+/// The cells of a pane that is rendered with a different
+/// cell size from the rest of its tab
+#[derive(Debug, Clone, Copy)]
+struct PaneCells {
+    /// The size of a single cell of the pane
+    cell: TerminalSize,
+    /// The space most recently given to the pane,
+    /// measured in the cells of the tab
+    tab_size: Option<TerminalSize>,
+}
+
 struct TabInner {
     id: TabId,
     pane: Option<Tree>,
@@ -46,6 +57,7 @@ struct TabInner {
     zoomed: Option<Arc<dyn Pane>>,
     title: String,
     recency: Recency,
+    pane_cells: HashMap<PaneId, PaneCells>,
 }
 
 /// A Tab is a container of Panes
@@ -478,7 +490,11 @@ fn adjust_y_size(tree: &mut Tree, mut y_adjust: isize, cell_dimensions: &Termina
     }
 }
 
-fn apply_sizes_from_splits(tree: &Tree, size: &TerminalSize) {
+fn apply_sizes_from_splits(
+    tree: &Tree,
+    size: &TerminalSize,
+    pane_cells: &mut HashMap<PaneId, PaneCells>,
+) {
     match tree {
         Tree::Empty => return,
         Tree::Node { data: None, .. } => return,
@@ -487,15 +503,55 @@ fn apply_sizes_from_splits(tree: &Tree, size: &TerminalSize) {
             right,
             data: Some(data),
         } => {
-            apply_sizes_from_splits(&*left, &data.first);
-            apply_sizes_from_splits(&*right, &data.second);
+            apply_sizes_from_splits(&*left, &data.first, pane_cells);
+            apply_sizes_from_splits(&*right, &data.second, pane_cells);
         }
         Tree::Leaf(pane) => {
-            pane.resize(*size).ok();
+            resize_pane(pane, *size, pane_cells).ok();
         }
     }
 }
 
+/// Converts `size`, which is measured in the cells of a tab, into
+/// the cells of a pane whose cells are `cell_size`.
+/// This is a NOP unless the pane has its own cell size; in that case
+/// the pane is given as many of its own cells as fit into the same
+/// pixel area.
+pub fn size_in_pane_cells(cell_size: Option<TerminalSize>, size: TerminalSize) -> TerminalSize {
+    let cell = match cell_size {
+        Some(cell) if cell.pixel_width > 0 && cell.pixel_height > 0 => cell,
+        _ => return size,
+    };
+    if size.pixel_width == 0 || size.pixel_height == 0 {
+        // There's no pixel area to fit the cells into
+        return size;
+    }
+    let cols = (size.pixel_width / cell.pixel_width).max(1);
+    let rows = (size.pixel_height / cell.pixel_height).max(1);
+    TerminalSize {
+        rows,
+        cols,
+        pixel_width: cols * cell.pixel_width,
+        pixel_height: rows * cell.pixel_height,
+        dpi: size.dpi,
+    }
+}
+
+/// Resize a pane to occupy `size`, measured in the cells of the tab
+fn resize_pane(
+    pane: &Arc<dyn Pane>,
+    size: TerminalSize,
+    pane_cells: &mut HashMap<PaneId, PaneCells>,
+) -> anyhow::Result<()> {
+    match pane_cells.get_mut(&pane.pane_id()) {
+        Some(cells) => {
+            cells.tab_size = Some(size);
+            pane.resize_in_tab(size, Some(cells.cell))
+        }
+        None => pane.resize_in_tab(size, None),
+    }
+}
+
 fn cell_dimensions(size: &TerminalSize) -> TerminalSize {
     TerminalSize {
         rows: 1,
@@ -630,6 +686,42 @@ impl Tab {
             .rebuild_splits_sizes_from_contained_panes()
     }
 
+    /// Records the size of a single cell of the specified pane, for
+    /// panes that are rendered with a different font size from the
+    /// rest of the tab.  The tab lays out its splits in its own cells,
+    /// and then fits as many of the pane's cells as it can into the
+    /// space allotted to it.
+    /// `cell_size` has rows and cols set to 1, and the pixel dimensions
+    /// of the cell.  Pass `None` to revert to the cells of the tab.
+    /// The cell size is forgotten when the pane leaves the tab.
+    /// The caller is responsible for resizing the tab.
+    pub fn set_pane_cell_size(&self, pane_id: PaneId, cell_size: Option<TerminalSize>) {
+        self.inner.lock().set_pane_cell_size(pane_id, cell_size)
+    }
+
+    pub fn get_pane_cell_size(&self, pane_id: PaneId) -> Option<TerminalSize> {
+        self.inner
+            .lock()
+            .pane_cells
+            .get(&pane_id)
+            .map(|cells| cells.cell)
+    }
+
+    /// Resizes a pane of this tab to occupy `size`, measured in the cells
+    /// of the tab, first recording its cell size as `set_pane_cell_size`
+    /// does.  This is used to apply a resize that a client made to its
+    /// own model of the tab.
+    pub fn resize_pane_in_tab(
+        &self,
+        pane: &Arc<dyn Pane>,
+        size: TerminalSize,
+        cell_size: Option<TerminalSize>,
+    ) -> anyhow::Result<()> {
+        let mut inner = self.inner.lock();
+        inner.set_pane_cell_size(pane.pane_id(), cell_size);
+        resize_pane(pane, size, &mut inner.pane_cells)
+    }
+
     /// Given split_index, the topological index of a split returned by
     /// iter_splits() as PositionedSplit::index, revised the split position
     /// by the provided delta; positive values move the split to the right/bottom,
@@ -764,6 +856,23 @@ impl TabInner {
             zoomed: None,
             title: String::new(),
             recency: Recency::default(),
+            pane_cells: HashMap::new(),
+        }
+    }
+
+    fn set_pane_cell_size(&mut self, pane_id: PaneId, cell_size: Option<TerminalSize>) {
+        match cell_size {
+            Some(cell) => {
+                let tab_size = self
+                    .pane_cells
+                    .get(&pane_id)
+                    .and_then(|cells| cells.tab_size);
+                self.pane_cells
+                    .insert(pane_id, PaneCells { cell, tab_size });
+            }
+            None => {
+                self.pane_cells.remove(&pane_id);
+            }
         }
     }
 
@@ -904,7 +1013,7 @@ impl TabInner {
             self.size_before_zoom = size;
             if let Some(pane) = self.get_active_pane() {
                 pane.set_zoomed(true);
-                pane.resize(size).ok();
+                resize_pane(&pane, size, &mut self.pane_cells).ok();
                 self.zoomed.replace(pane);
             }
         }
@@ -961,7 +1070,11 @@ impl TabInner {
                 Err(c) => {
                     self.pane.replace(c.tree());
                     let size = self.size;
-                    apply_sizes_from_splits(self.pane.as_mut().unwrap(), &size);
+                    apply_sizes_from_splits(
+                        self.pane.as_mut().unwrap(),
+                        &size,
+                        &mut self.pane_cells,
+                    );
                     break;
                 }
             }
@@ -992,7 +1105,11 @@ impl TabInner {
                 Err(c) => {
                     self.pane.replace(c.tree());
                     let size = self.size;
-                    apply_sizes_from_splits(self.pane.as_mut().unwrap(), &size);
+                    apply_sizes_from_splits(
+                        self.pane.as_mut().unwrap(),
+                        &size,
+                        &mut self.pane_cells,
+                    );
                     break;
                 }
             }
@@ -1144,7 +1261,7 @@ impl TabInner {
 
         if let Some(zoomed) = &self.zoomed {
             self.size = size;
-            zoomed.resize(size).ok();
+            resize_pane(zoomed, size, &mut self.pane_cells).ok();
         } else {
             let dims = cell_dimensions(&size);
             let (min_x, min_y) = compute_min_size(self.pane.as_mut().unwrap());
@@ -1176,7 +1293,7 @@ impl TabInner {
             self.size = size;
 
             // And then resize the individual panes to match
-            apply_sizes_from_splits(self.pane.as_mut().unwrap(), &size);
+            apply_sizes_from_splits(self.pane.as_mut().unwrap(), &size, &mut self.pane_cells);
         }
 
         Mux::try_get().map(|mux| mux.notify(MuxNotification::TabResized(self.id)));
@@ -1220,10 +1337,21 @@ impl TabInner {
             return;
         }
 
-        fn compute_size(node: &mut Tree) -> Option<TerminalSize> {
+        fn compute_size(
+            node: &mut Tree,
+            pane_cells: &HashMap<PaneId, PaneCells>,
+        ) -> Option<TerminalSize> {
             match node {
                 Tree::Empty => None,
                 Tree::Leaf(pane) => {
+                    // The dimensions of a pane with its own cell size
+                    // are in its own cells, rather than those of the tab
+                    if let Some(tab_size) = pane_cells
+                        .get(&pane.pane_id())
+                        .and_then(|cells| cells.tab_size)
+                    {
+                        return Some(tab_size);
+                    }
                     let dims = pane.get_dimensions();
                     let size = TerminalSize {
                         cols: dims.cols,
@@ -1236,10 +1364,10 @@ impl TabInner {
                 }
                 Tree::Node { left, right, data } => {
                     if let Some(data) = data {
-                        if let Some(first) = compute_size(left) {
+                        if let Some(first) = compute_size(left, pane_cells) {
                             data.first = first;
                         }
-                        if let Some(second) = compute_size(right) {
+                        if let Some(second) = compute_size(right, pane_cells) {
                             data.second = second;
                         }
                         Some(data.size())
@@ -1251,7 +1379,7 @@ impl TabInner {
         }
 
         if let Some(root) = self.pane.as_mut() {
-            if let Some(size) = compute_size(root) {
+            if let Some(size) = compute_size(root, &self.pane_cells) {
                 self.size = size;
             }
         }
@@ -1359,7 +1487,10 @@ impl TabInner {
 
             if cursor.is_leaf() {
                 // Apply our size to the tty
-                cursor.leaf_mut().map(|pane| pane.resize(pane_size));
+                let pane_cells = &mut self.pane_cells;
+                cursor
+                    .leaf_mut()
+                    .map(|pane| resize_pane(pane, pane_size, pane_cells));
             } else {
                 self.apply_pane_size(pane_size, &mut cursor);
             }
@@ -1666,13 +1797,13 @@ impl TabInner {
                         };
 
                         if let Some(unsplit) = cursor.leaf_mut() {
-                            unsplit.resize(size).ok();
+                            resize_pane(unsplit, size, &mut self.pane_cells).ok();
                         } else {
                             self.apply_pane_size(size, &mut cursor);
                         }
                     } else if !dead_panes.is_empty() {
                         // Apply our revised size to the tty
-                        pane.resize(pane_size).ok();
+                        resize_pane(&pane, pane_size, &mut self.pane_cells).ok();
                     }
 
                     pane_index += 1;
@@ -1696,6 +1827,12 @@ impl TabInner {
             self.active = active_idx.saturating_sub(removed_indices.len());
         }
 
+        // A pane that moves to another tab is laid out in the cells of
+        // that tab, unless its cell size is recorded there
+        for pane in &dead_panes {
+            self.pane_cells.remove(&pane.pane_id());
+        }
+
         if !dead_panes.is_empty() && kill {
             let to_kill: Vec<_> = dead_panes.iter().map(|p| p.pane_id()).collect();
             promise::spawn::spawn_into_main_thread(async move {
@@ -1854,7 +1991,7 @@ impl TabInner {
 
             // Advise the panes of their new sizes
             let size = self.size;
-            apply_sizes_from_splits(self.pane.as_mut().unwrap(), &size);
+            apply_sizes_from_splits(self.pane.as_mut().unwrap(), &size, &mut self.pane_cells);
         }
 
         // And update focus
@@ -2057,8 +2194,8 @@ impl TabInner {
                 (pane, existing_pane)
             };
 
-            pane1.resize(split_info.first)?;
-            pane2.resize(split_info.second.clone())?;
+            resize_pane(&pane1, split_info.first, &mut self.pane_cells)?;
+            resize_pane(&pane2, split_info.second.clone(), &mut self.pane_cells)?;
 
             *cursor.leaf_mut().unwrap() = pane1;
 
@@ -2267,7 +2404,18 @@ mod test {
         }
 
         fn get_dimensions(&self) -> RenderableDimensions {
-            unimplemented!();
+            let size = *self.size.lock();
+            RenderableDimensions {
+                cols: size.cols,
+                viewport_rows: size.rows,
+                scrollback_rows: size.rows,
+                physical_top: 0,
+                scrollback_top: 0,
+                dpi: size.dpi,
+                pixel_width: size.pixel_width,
+                pixel_height: size.pixel_height,
+                reverse_video: false,
+            }
         }
 
         fn get_title(&self) -> String {
@@ -2517,6 +2665,114 @@ mod test {
         assert_eq!(600, panes[2].pixel_height);
     }
 
+    #[test]
+    fn pane_cell_sizes() {
+        let size = TerminalSize {
+            rows: 24,
+            cols: 80,
+            pixel_width: 800,
+            pixel_height: 600,
+            dpi: 96,
+        };
+        let cell = TerminalSize {
+            rows: 1,
+            cols: 1,
+            pixel_width: 15,
+            pixel_height: 40,
+            dpi: 96,
+        };
+
+        assert_eq!(size_in_pane_cells(None, size), size);
+        let no_pixels = TerminalSize {
+            pixel_width: 0,
+            pixel_height: 0,
+            ..size
+        };
+        assert_eq!(size_in_pane_cells(Some(cell), no_pixels), no_pixels);
+
+        // Partial cells are left unused
+        assert_eq!(
+            size_in_pane_cells(Some(cell), size),
+            TerminalSize {
+                rows: 15,
+                cols: 53,
+                pixel_width: 795,
+                pixel_height: 600,
+                dpi: 96,
+            }
+        );
+
+        // There is always at least one cell
+        let tiny = TerminalSize {
+            rows: 1,
+            cols: 1,
+            pixel_width: 10,
+            pixel_height: 25,
+            dpi: 96,
+        };
+        assert_eq!(
+            size_in_pane_cells(Some(cell), tiny),
+            TerminalSize {
+                rows: 1,
+                cols: 1,
+                pixel_width: 15,
+                pixel_height: 40,
+                dpi: 96,
+            }
+        );
+    }
+
+    #[test]
+    fn pane_cell_size_in_tab() {
+        let size = TerminalSize {
+            rows: 24,
+            cols: 80,
+            pixel_width: 800,
+            pixel_height: 600,
+            dpi: 96,
+        };
+        let cell = TerminalSize {
+            rows: 1,
+            cols: 1,
+            pixel_width: 20,
+            pixel_height: 40,
+            dpi: 96,
+        };
+
+        let tab = Tab::new(&size);
+        tab.assign_pane(&FakePane::new(1, size));
+        let request = SplitRequest {
+            direction: SplitDirection::Horizontal,
+            top_level: false,
+            target_is_second: true,
+            size: Default::default(),
+        };
+        let split = tab.compute_split_size(0, request).unwrap();
+        tab.split_and_insert(0, request, FakePane::new(2, split.second))
+            .unwrap();
+
+        tab.set_pane_cell_size(2, Some(cell));
+        assert_eq!(tab.get_pane_cell_size(2), Some(cell));
+        tab.resize(size);
+
+        let panes = tab.iter_panes();
+        let second = panes[1].pane.get_dimensions();
+        assert_eq!(20, second.cols);
+        assert_eq!(15, second.viewport_rows);
+
+        // The splits are rebuilt from the space that the pane occupies
+        // in the tab, rather than from its own cells
+        tab.rebuild_splits_sizes_from_contained_panes();
+        let panes = tab.iter_panes();
+        assert_eq!(39, panes[0].width);
+        assert_eq!(40, panes[1].width);
+        assert_eq!(24, panes[1].height);
+
+        // The tab forgets the cell size of a pane that leaves it
+        tab.remove_pane(2).unwrap();
+        assert_eq!(tab.get_pane_cell_size(2), None);
+    }
+
     fn is_send_and_sync<T: Send + Sync>() -> bool {
         true
     }
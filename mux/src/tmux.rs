@@ -3,7 +3,7 @@ use crate::domain::{alloc_domain_id, Domain, DomainId, DomainState, SplitSource}
 use crate::pane::{Pane, PaneId};
 use crate::tab::{SplitRequest, Tab, TabId};
 use crate::tmux_commands::{
-    ListAllPanes, ListAllWindows, ListCommands, NewWindow, SplitPane, TmuxCommand,
+    DetachClient, ListAllWindows, ListCommands, NewWindow, SplitPane, TmuxCommand,
 };
 use crate::window::WindowId;
 use crate::{Mux, MuxWindowBuilder};
@@ -59,6 +59,7 @@ pub(crate) struct TmuxTab {
     pub tab_id: TabId, // local tab ID
     pub tmux_window_id: TmuxWindowId,
     pub layout_csum: String,
+    pub zoomed: bool,
     pub panes: HashSet<TmuxPaneId>, // tmux panes within tmux window
 }
 
@@ -72,9 +73,12 @@ pub(crate) struct TmuxDomainState {
     pub gui_tabs: Mutex<HashMap<TmuxWindowId, TmuxTab>>,
     pub remote_panes: Mutex<HashMap<TmuxPaneId, RefTmuxRemotePane>>,
     pub tmux_session: Mutex<Option<TmuxSessionId>>,
+    /// The name of the tmux session, which is also the name of
+    /// the workspace that holds its windows
+    pub tmux_session_name: Mutex<Option<String>>,
     pub support_commands: Mutex<HashMap<String, String>>,
     pub attach_state: Mutex<AttachState>,
-    pending_splits: Mutex<VecDeque<promise::Promise<TmuxPaneId>>>,
+    pub(crate) pending_splits: Mutex<VecDeque<promise::Promise<TmuxPaneId>>>,
     pub backlog: Mutex<HashMap<TmuxPaneId, Vec<u8>>>,
 }
 
@@ -131,8 +135,15 @@ impl TmuxDomainState {
 
                     // Force to quit the tmux mode
                     let pane_id = self.pane_id;
+                    let domain_id = self.domain_id;
                     promise::spawn::spawn_into_main_thread_with_low_priority(async move {
-                        if let Some(x) = Mux::get().get_pane(pane_id) {
+                        let mux = Mux::get();
+                        if let Some(domain) = mux.get_domain(domain_id) {
+                            if let Some(tmux_domain) = domain.downcast_ref::<TmuxDomain>() {
+                                tmux_domain.inner.remove_all_windows();
+                            }
+                        }
+                        if let Some(x) = mux.get_pane(pane_id) {
                             let _ = write!(x.writer(), "\n\n");
                         }
                     })
@@ -144,18 +155,27 @@ impl TmuxDomainState {
                     window,
                     layout,
                     visible_layout: _,
-                    raw_flags: _,
+                    raw_flags,
                 } => {
-                    let mut cmd_queue = self.cmd_queue.as_ref().lock();
-                    cmd_queue.push_back(Box::new(ListAllPanes {
-                        window_id: *window,
-                        prune: true,
-                        layout_csum: if let Some(l) = layout.get(0..4) {
-                            l.to_string()
-                        } else {
-                            "".to_string()
-                        },
-                    }));
+                    let domain_id = self.domain_id;
+                    let window = *window;
+                    let layout = layout.clone();
+                    // Older versions of tmux don't report the window flags
+                    let zoomed = raw_flags.as_ref().map(|flags| flags.contains('Z'));
+                    promise::spawn::spawn_into_main_thread(async move {
+                        let mux = Mux::get();
+                        let Some(domain) = mux.get_domain(domain_id) else {
+                            return;
+                        };
+                        let Some(tmux_domain) = domain.downcast_ref::<TmuxDomain>() else {
+                            return;
+                        };
+                        if let Err(err) = tmux_domain.inner.layout_changed(window, &layout, zoomed)
+                        {
+                            log::error!("Failed to apply tmux layout {layout}: {err:#}");
+                        }
+                    })
+                    .detach();
                 }
                 Event::Output { pane, text } => {
                     let pane_map = self.remote_panes.lock();
@@ -171,13 +191,71 @@ impl TmuxDomainState {
                         log::debug!("Tmux pane {} havn't been attached", pane);
                     }
                 }
-                Event::SessionChanged { session, name: _ } => {
-                    *self.tmux_session.lock() = Some(*session);
+                Event::SessionChanged { session, name } => {
+                    let prior = self.tmux_session.lock().replace(*session);
+                    *self.tmux_session_name.lock() = Some(name.clone());
+                    if prior == Some(*session) {
+                        continue;
+                    }
+
+                    if prior.is_some() {
+                        // The client switched to another session; drop the
+                        // windows of the old one and sync up with the new one
+                        // as though we were attaching for the first time
+                        *self.attach_state.lock() = AttachState::Init;
+                        let domain_id = self.domain_id;
+                        promise::spawn::spawn_into_main_thread(async move {
+                            let mux = Mux::get();
+                            if let Some(domain) = mux.get_domain(domain_id) {
+                                if let Some(tmux_domain) = domain.downcast_ref::<TmuxDomain>() {
+                                    tmux_domain.inner.remove_all_windows();
+                                }
+                            }
+                        })
+                        .detach();
+                    }
+
                     let mut cmd_queue = self.cmd_queue.as_ref().lock();
                     cmd_queue.push_back(Box::new(ListCommands));
 
-                    self.subscribe_notification();
-                    log::info!("tmux session changed:{}", session);
+                    if prior.is_none() {
+                        self.subscribe_notification();
+                    }
+                    log::info!("tmux session changed:{} {}", session, name);
+                }
+                Event::SessionRenamed { name } => {
+                    let prior = self.tmux_session_name.lock().replace(name.clone());
+                    let window_id = self.gui_window.lock().as_ref().map(|w| w.window_id);
+                    if let (Some(prior), Some(window_id)) = (prior, window_id) {
+                        let name = name.clone();
+                        let pane_id = self.pane_id;
+                        promise::spawn::spawn_into_main_thread(async move {
+                            let mux = Mux::get();
+                            // When the tmux tabs were added to the window that
+                            // is running tmux, the workspace belongs to the
+                            // user rather than to the session; leave it be
+                            if let Some((_domain, host_window, _tab)) = mux.resolve_pane_id(pane_id)
+                            {
+                                if host_window == window_id {
+                                    return;
+                                }
+                            }
+                            let moved = match mux.get_window_mut(window_id) {
+                                Some(mut window) if window.get_workspace() == prior => {
+                                    window.set_workspace(&name);
+                                    true
+                                }
+                                _ => false,
+                            };
+                            if moved
+                                && mux.active_workspace() == prior
+                                && mux.iter_windows_in_workspace(&prior).is_empty()
+                            {
+                                mux.set_active_workspace(&name);
+                            }
+                        })
+                        .detach();
+                    }
                 }
                 Event::WindowAdd { window } => {
                     // Only handle the new tab, the first empty window handled by sync_window_state
@@ -202,13 +280,18 @@ impl TmuxDomainState {
                         continue;
                     }
 
-                    // Split pane
-                    if !self.check_pane_attached(*window, *pane) {
-                        let mut pending_splits = self.pending_splits.lock();
-                        if let Some(mut promise) = pending_splits.pop_front() {
-                            promise.ok(*pane);
+                    let domain_id = self.domain_id;
+                    let window = *window;
+                    let pane = *pane;
+                    promise::spawn::spawn_into_main_thread(async move {
+                        let mux = Mux::get();
+                        if let Some(domain) = mux.get_domain(domain_id) {
+                            if let Some(tmux_domain) = domain.downcast_ref::<TmuxDomain>() {
+                                tmux_domain.inner.activate_pane(window, pane);
+                            }
                         }
-                    }
+                    })
+                    .detach();
                     log::info!("tmux window pane changed: {}:{}", window, pane);
                 }
                 Event::WindowRenamed { window, name } => {
@@ -271,23 +354,30 @@ impl TmuxDomainState {
         .detach();
     }
 
-    /// create a standalone window for tmux tabs
+    /// create a standalone window for tmux tabs.
+    /// The window is placed in a workspace named after the tmux session;
+    /// if the window that is running tmux is already in that workspace
+    /// then the tabs are added to it instead.
     pub fn create_gui_window(&self) {
         if self.gui_window.lock().is_none() {
             let mux = Mux::get();
-            let window_builder =
-                if let Some((_domain, window_id, _tab)) = mux.resolve_pane_id(self.pane_id) {
+            let workspace = self.tmux_session_name.lock().clone();
+            let window_builder = match mux.resolve_pane_id(self.pane_id) {
+                Some((_domain, window_id, _tab))
+                    if workspace.is_none()
+                        || mux
+                            .get_window(window_id)
+                            .map(|w| Some(w.get_workspace()) == workspace.as_deref())
+                            .unwrap_or(false) =>
+                {
                     MuxWindowBuilder {
                         window_id,
                         activity: Some(Activity::new()),
                         notified: false,
                     }
-                } else {
-                    mux.new_empty_window(
-                        None, /* TODO: pass session here */
-                        None, /* position */
-                    )
-                };
+                }
+                _ => mux.new_empty_window(workspace, None /* position */),
+            };
 
             log::info!("Tmux create window id {}", window_builder.window_id);
             {
@@ -297,6 +387,46 @@ impl TmuxDomainState {
         };
     }
 
+    /// Removes the local tabs of all of the tmux windows, along with
+    /// the window that holds them
+    pub fn remove_all_windows(&self) {
+        let mux = Mux::get();
+        let tabs: Vec<TabId> = self
+            .gui_tabs
+            .lock()
+            .drain()
+            .map(|(_, t)| t.tab_id)
+            .collect();
+        for tab_id in tabs {
+            mux.remove_tab(tab_id);
+        }
+        for (_, pane) in self.remote_panes.lock().drain() {
+            let pane = pane.lock();
+            let (lock, condvar) = &*pane.active_lock;
+            *lock.lock() = true;
+            condvar.notify_all();
+        }
+        self.backlog.lock().clear();
+        // Dropping the builder releases its Activity, allowing the
+        // now empty window to be pruned
+        self.gui_window.lock().take();
+        mux.prune_dead_windows();
+    }
+
+    /// Resets the state of a domain whose client detached, so that
+    /// it can be reused when tmux control mode is started again in
+    /// the same pane
+    pub fn reattach(&self) {
+        self.remove_all_windows();
+        *self.state.lock() = State::WaitForInitialGuard;
+        self.cmd_queue.lock().clear();
+        *self.tmux_session.lock() = None;
+        *self.tmux_session_name.lock() = None;
+        self.support_commands.lock().clear();
+        *self.attach_state.lock() = AttachState::Init;
+        self.pending_splits.lock().clear();
+    }
+
     /// create a tmux window
     pub fn create_tmux_window(&self) {
         let mut cmd_queue = self.cmd_queue.as_ref().lock();
@@ -346,6 +476,7 @@ impl TmuxDomain {
             gui_tabs: Mutex::new(HashMap::default()),
             remote_panes: Mutex::new(HashMap::default()),
             tmux_session: Mutex::new(None),
+            tmux_session_name: Mutex::new(None),
             support_commands: Mutex::new(HashMap::default()),
             attach_state: Mutex::new(AttachState::Init),
             pending_splits: Mutex::new(VecDeque::default()),
@@ -367,6 +498,7 @@ impl Domain for TmuxDomain {
         _size: TerminalSize,
         _command: Option<CommandBuilder>,
         _command_dir: Option<String>,
+        _initial_output: Option<String>,
         _window: WindowId,
     ) -> anyhow::Result<Arc<Tab>> {
         self.inner.create_tmux_window();
@@ -405,6 +537,7 @@ impl Domain for TmuxDomain {
         _size: TerminalSize,
         _command: Option<CommandBuilder>,
         _command_dir: Option<String>,
+        _initial_output: Option<String>,
     ) -> anyhow::Result<Arc<dyn Pane>> {
         anyhow::bail!("Spawn_pane not yet implemented for TmuxDomain");
     }
@@ -418,18 +551,31 @@ impl Domain for TmuxDomain {
     }
 
     async fn attach(&self, _window_id: Option<crate::WindowId>) -> anyhow::Result<()> {
+        if self.state() == DomainState::Detached {
+            anyhow::bail!(
+                "run `tmux -CC attach` in pane {} to reattach to tmux",
+                self.inner.pane_id
+            );
+        }
         Ok(())
     }
 
     fn detachable(&self) -> bool {
-        false
+        true
     }
 
     fn detach(&self) -> anyhow::Result<()> {
-        anyhow::bail!("detach not implemented for TmuxDomain");
+        let mut cmd_queue = self.inner.cmd_queue.lock();
+        cmd_queue.push_back(Box::new(DetachClient));
+        TmuxDomainState::schedule_send_next_command(self.inner.domain_id);
+        Ok(())
     }
 
     fn state(&self) -> DomainState {
-        DomainState::Attached
+        if *self.inner.state.lock() == State::Exit {
+            DomainState::Detached
+        } else {
+            DomainState::Attached
+        }
     }
 }
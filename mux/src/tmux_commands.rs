@@ -1,14 +1,17 @@
 use crate::domain::{DomainId, WriterWrapper};
 use crate::localpane::LocalPane;
 use crate::pane::{alloc_pane_id, PaneId};
-use crate::tab::{SplitDirection, SplitRequest, SplitSize, Tab, TabId};
+use crate::tab::{
+    PaneEntry, PaneNode, SplitDirection, SplitDirectionAndSize, SplitRequest, Tab, TabId,
+};
 use crate::tmux::{AttachState, TmuxDomain, TmuxDomainState, TmuxRemotePane, TmuxTab};
 use crate::tmux_pty::{TmuxChild, TmuxPty};
+use crate::window::WindowId;
 use crate::{Mux, MuxNotification, Pane};
 use anyhow::{anyhow, Context};
 use parking_lot::{Condvar, Mutex};
 use portable_pty::{MasterPty, PtySize};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::fmt::{Debug, Write};
 use std::io::Write as _;
 use std::sync::Arc;
@@ -44,8 +47,9 @@ struct WindowItem {
     window_width: u64,
     window_height: u64,
     window_active: bool,
+    window_zoomed: bool,
     window_name: String,
-    layout: Vec<WindowLayout>,
+    layout: LayoutNode,
     layout_csum: String,
     history_limit: isize,
 }
@@ -100,6 +104,7 @@ impl TmuxDomainState {
                     tab_id: *tab_id,
                     tmux_window_id: target.window_id,
                     layout_csum: target.layout_csum.clone(),
+                    zoomed: target.window_zoomed,
                     panes: HashSet::new(),
                 },
             );
@@ -124,13 +129,18 @@ impl TmuxDomainState {
 
         let mux = Mux::get();
         for p in to_remove {
-            let pane_map = self.remote_panes.lock();
-            let Some(pane) = pane_map.get(&p) else {
+            panes.remove(&p);
+            let Some(pane) = self.remote_panes.lock().remove(&p) else {
                 continue;
             };
-            let local_pane_id = pane.lock().local_pane_id;
+            let local_pane_id = {
+                let pane = pane.lock();
+                let (lock, condvar) = &*pane.active_lock;
+                *lock.lock() = true;
+                condvar.notify_all();
+                pane.local_pane_id
+            };
             mux.remove_pane(local_pane_id);
-            panes.remove(&p);
         }
 
         if panes.is_empty() {
@@ -197,7 +207,7 @@ impl TmuxDomainState {
             master_pane: ref_pane,
         };
 
-        let writer = WriterWrapper::new(pane_pty.take_writer()?);
+        let writer = WriterWrapper::new(local_pane_id, pane_pty.take_writer()?);
 
         let size = TerminalSize {
             rows: pane.pane_height as usize,
@@ -262,6 +272,14 @@ impl TmuxDomainState {
             None => anyhow::bail!("No tab {}", tab_id),
         };
 
+        // The layout change that tmux sent for the split may have
+        // been applied already, in which case the pane exists
+        if self.check_pane_attached(window_id, remote_id) {
+            return self
+                .local_pane(remote_id)
+                .ok_or_else(|| anyhow!("tmux pane {remote_id} has no local pane"));
+        }
+
         let p = PaneItem {
             session_id: 0,
             window_id: window_id,
@@ -389,107 +407,13 @@ impl TmuxDomainState {
 
             let _ = self.add_attached_window(window, &tab.tab_id())?;
 
-            let mut split_stack;
-            let mut split_direction;
-
-            let mut split_pane_index = 1;
-            for l in &window.layout {
-                match l {
-                    WindowLayout::SinglePane(x) => {
-                        let p = PaneItem {
-                            session_id: window.session_id,
-                            window_id: window.window_id,
-                            _pane_index: 0,
-                            cursor_x: 0,
-                            cursor_y: 0,
-                            pane_active: false,
-                            pane_id: x.pane_id,
-                            pane_width: x.pane_width,
-                            pane_height: x.pane_height,
-                            pane_left: x.pane_left,
-                            pane_top: x.pane_top,
-                        };
-                        let local_pane = self.create_pane(&p).context("failed to create pane")?;
-                        tab.assign_pane(&local_pane);
-                        self.add_attached_pane(p.window_id, p.pane_id)?;
-                        let _ = mux.add_pane(&local_pane);
-                        break;
-                    }
-
-                    WindowLayout::SplitHorizontal(x) => {
-                        split_direction = SplitDirection::Horizontal;
-                        split_stack = x;
-                    }
-
-                    WindowLayout::SplitVertical(x) => {
-                        split_direction = SplitDirection::Vertical;
-                        split_stack = x;
-                    }
-                }
-
-                for x in split_stack {
-                    let p = PaneItem {
-                        session_id: window.session_id,
-                        window_id: window.window_id,
-                        _pane_index: 0,
-                        cursor_x: 0,
-                        cursor_y: 0,
-                        pane_active: false,
-                        pane_id: x.pane_id,
-                        pane_width: x.pane_width,
-                        pane_height: x.pane_height,
-                        pane_left: x.pane_left,
-                        pane_top: x.pane_top,
-                    };
-                    let local_pane;
-                    if !self.check_pane_attached(p.window_id, p.pane_id) {
-                        local_pane = self.create_pane(&p).context("failed to create pane")?;
-                        self.add_attached_pane(p.window_id, p.pane_id)?;
-                        let _ = mux.add_pane(&local_pane);
-                        if let None = tab.get_active_pane() {
-                            tab.assign_pane(&local_pane);
-                            split_pane_index = tab.get_active_idx();
-                            continue;
-                        }
-
-                        split_pane_index = tab.split_and_insert(
-                            split_pane_index,
-                            SplitRequest {
-                                direction: split_direction,
-                                target_is_second: false,
-                                top_level: false,
-                                size: SplitSize::Cells(
-                                    if split_direction == SplitDirection::Horizontal {
-                                        p.pane_width as usize
-                                    } else {
-                                        p.pane_height as usize
-                                    },
-                                ),
-                            },
-                            local_pane.clone(),
-                        )? + 1;
-                    } else {
-                        let pane_map = self.remote_panes.lock();
-                        let local_pane_id = match pane_map.get(&p.pane_id) {
-                            Some(x) => x.lock().local_pane_id,
-                            None => anyhow::bail!("cannot find the local pane for {}", p.pane_id),
-                        };
-
-                        split_pane_index = match tab
-                            .iter_panes_ignoring_zoom()
-                            .iter()
-                            .find(|x| x.pane.pane_id() == local_pane_id)
-                        {
-                            Some(x) => x.index,
-                            None => {
-                                log::info!("invalid pane id {local_pane_id}");
-                                continue;
-                            }
-                        };
-                        continue;
-                    }
-                }
-            }
+            self.apply_layout(
+                &tab,
+                **gui_window_id,
+                window.window_id,
+                &window.layout,
+                Some(window.window_zoomed),
+            )?;
 
             mux.add_tab_to_window(&tab, **gui_window_id)?;
             gui_window_id.notify();
@@ -520,8 +444,6 @@ impl TmuxDomainState {
             if !window.window_active {
                 self.cmd_queue.lock().push_back(Box::new(ListAllPanes {
                     window_id: window.window_id,
-                    prune: false,
-                    layout_csum: window.layout_csum.clone(),
                 }));
             }
         }
@@ -531,8 +453,6 @@ impl TmuxDomainState {
             Some(window) => {
                 self.cmd_queue.lock().push_back(Box::new(ListAllPanes {
                     window_id: window.window_id,
-                    prune: false,
-                    layout_csum: window.layout_csum.clone(),
                 }));
             }
             None => {}
@@ -540,6 +460,16 @@ impl TmuxDomainState {
 
         if *self.attach_state.lock() == AttachState::Init {
             self.cmd_queue.lock().push_back(Box::new(AttachDone));
+
+            // Show the workspace of the session now that it has tabs
+            let workspace = mux
+                .get_window(**gui_window_id)
+                .map(|w| w.get_workspace().to_string());
+            if let Some(workspace) = workspace {
+                if workspace != mux.active_workspace() {
+                    mux.set_active_workspace(&workspace);
+                }
+            }
         }
 
         TmuxDomainState::schedule_send_next_command(self.domain_id);
@@ -547,6 +477,191 @@ impl TmuxDomainState {
         Ok(())
     }
 
+    /// Returns the local pane that mirrors the tmux pane `pane_id`
+    fn local_pane(&self, pane_id: TmuxPaneId) -> Option<Arc<dyn Pane>> {
+        let local_pane_id = self.remote_panes.lock().get(&pane_id)?.lock().local_pane_id;
+        Mux::get().get_pane(local_pane_id)
+    }
+
+    /// Makes the local pane that mirrors the tmux pane `pane_id`
+    /// the active pane of its tab
+    pub fn activate_pane(&self, window_id: TmuxWindowId, pane_id: TmuxPaneId) {
+        let Some(tab_id) = self.gui_tabs.lock().get(&window_id).map(|t| t.tab_id) else {
+            return;
+        };
+        if let (Some(tab), Some(pane)) = (Mux::get().get_tab(tab_id), self.local_pane(pane_id)) {
+            tab.set_active_pane(&pane);
+        }
+    }
+
+    /// Called when tmux reports that the layout of a window changed,
+    /// because panes were split, closed, resized or zoomed.
+    /// `layout` is the layout string including its checksum.
+    /// `zoomed` is taken from the window flags, when tmux reports them.
+    pub fn layout_changed(
+        &self,
+        window_id: TmuxWindowId,
+        layout: &str,
+        zoomed: Option<bool>,
+    ) -> anyhow::Result<()> {
+        let layout_csum = layout
+            .get(0..4)
+            .ok_or_else(|| anyhow!("missing layout checksum"))?;
+        let layout = layout.get(5..).ok_or_else(|| anyhow!("missing layout"))?;
+
+        let tab_id = {
+            let mut gui_tabs = self.gui_tabs.lock();
+            // Windows that aren't attached yet are synced by ListAllWindows
+            let Some(local_tab) = gui_tabs.get_mut(&window_id) else {
+                return Ok(());
+            };
+            // Zooming doesn't change the layout of the window
+            if local_tab.layout_csum == layout_csum
+                && zoomed.map_or(true, |zoomed| zoomed == local_tab.zoomed)
+            {
+                return Ok(());
+            }
+            local_tab.layout_csum = layout_csum.to_string();
+            if let Some(zoomed) = zoomed {
+                local_tab.zoomed = zoomed;
+            }
+            local_tab.tab_id
+        };
+
+        let layout = parse_layout_tree(layout)?;
+        let mux = Mux::get();
+        let tab = mux
+            .get_tab(tab_id)
+            .ok_or_else(|| anyhow!("invalid tab id {tab_id}"))?;
+        let mux_window_id = mux
+            .window_containing_tab(tab_id)
+            .ok_or_else(|| anyhow!("tab {tab_id} is not in a window"))?;
+        self.apply_layout(&tab, mux_window_id, window_id, &layout, zoomed)
+    }
+
+    /// Rebuilds the split tree of `tab` from the layout of the tmux
+    /// window `window_id`.  Local panes are created for the tmux panes
+    /// that are new to the window, and removed for those that are no
+    /// longer part of it.
+    /// tmux zooms the active pane; when `zoomed` is `None` the tab keeps
+    /// its zoomed pane, if that is still part of the window.
+    fn apply_layout(
+        &self,
+        tab: &Arc<Tab>,
+        mux_window_id: WindowId,
+        window_id: TmuxWindowId,
+        layout: &LayoutNode,
+        zoomed: Option<bool>,
+    ) -> anyhow::Result<()> {
+        let mux = Mux::get();
+        let tab_id = tab.tab_id();
+        let workspace = mux
+            .get_window(mux_window_id)
+            .map(|w| w.get_workspace().to_string())
+            .unwrap_or_else(|| mux.active_workspace());
+
+        let active_pane_id = tab.get_active_pane().map(|pane| pane.pane_id());
+        let zoomed_pane_id = match zoomed {
+            Some(true) => active_pane_id,
+            Some(false) => None,
+            None => tab.get_zoomed_pane().map(|pane| pane.pane_id()),
+        };
+        let window_size = layout.geometry();
+
+        let mut local_panes: HashMap<TmuxPaneId, Arc<dyn Pane>> = HashMap::new();
+        for geometry in layout.panes() {
+            let existing = match self.remote_panes.lock().get(&geometry.pane_id) {
+                Some(pane) => {
+                    // Record the size assigned by tmux; TmuxPty::resize
+                    // skips sizes that match it, so that resizing the local
+                    // pane to match doesn't echo a resize back to tmux
+                    let mut pane = pane.lock();
+                    pane.window_id = window_id;
+                    pane.pane_width = geometry.pane_width;
+                    pane.pane_height = geometry.pane_height;
+                    pane.pane_left = geometry.pane_left;
+                    pane.pane_top = geometry.pane_top;
+                    if Some(pane.local_pane_id) == zoomed_pane_id {
+                        // The zoomed pane fills the window
+                        pane.pane_width = window_size.pane_width;
+                        pane.pane_height = window_size.pane_height;
+                    }
+                    Some(pane.local_pane_id)
+                }
+                None => None,
+            };
+
+            let pane = match existing.and_then(|pane_id| mux.get_pane(pane_id)) {
+                Some(pane) => {
+                    // The pane may have been moved from another window
+                    for (id, tab) in self.gui_tabs.lock().iter_mut() {
+                        if *id == window_id {
+                            tab.panes.insert(geometry.pane_id);
+                        } else {
+                            tab.panes.remove(&geometry.pane_id);
+                        }
+                    }
+                    pane
+                }
+                None => {
+                    let pane = self
+                        .create_pane(&PaneItem {
+                            session_id: 0,
+                            window_id,
+                            pane_id: geometry.pane_id,
+                            _pane_index: 0,
+                            cursor_x: 0,
+                            cursor_y: 0,
+                            pane_width: geometry.pane_width,
+                            pane_height: geometry.pane_height,
+                            pane_left: geometry.pane_left,
+                            pane_top: geometry.pane_top,
+                            pane_active: false,
+                        })
+                        .context("failed to create pane")?;
+                    self.add_attached_pane(window_id, geometry.pane_id)?;
+                    mux.add_pane(&pane)?;
+                    pane
+                }
+            };
+            local_panes.insert(geometry.pane_id, pane);
+        }
+
+        let root = layout_to_pane_node(layout, &mut |geometry: &PaneLayout| {
+            let pane = &local_panes[&geometry.pane_id];
+            PaneEntry {
+                window_id: mux_window_id,
+                tab_id,
+                pane_id: pane.pane_id(),
+                title: pane.get_title(),
+                size: layout_size(geometry),
+                working_dir: None,
+                is_active_pane: Some(pane.pane_id()) == active_pane_id,
+                is_zoomed_pane: Some(pane.pane_id()) == zoomed_pane_id,
+                workspace: workspace.clone(),
+                cursor_pos: Default::default(),
+                physical_top: 0,
+                top_row: geometry.pane_top as usize,
+                left_col: geometry.pane_left as usize,
+                tty_name: None,
+            }
+        });
+
+        let by_local_id: HashMap<PaneId, Arc<dyn Pane>> = local_panes
+            .values()
+            .map(|pane| (pane.pane_id(), Arc::clone(pane)))
+            .collect();
+        tab.sync_with_pane_tree(layout_size(layout.geometry()), root, |entry| {
+            Arc::clone(&by_local_id[&entry.pane_id])
+        });
+
+        let pane_set: HashSet<TmuxPaneId> = local_panes.keys().copied().collect();
+        self.remove_detached_pane(window_id, &pane_set)?;
+        mux.notify(MuxNotification::TabResized(tab_id));
+
+        Ok(())
+    }
+
     pub fn subscribe_notification(&self) {
         let mux = Mux::get();
         let domain_id = self.domain_id;
@@ -622,6 +737,73 @@ impl TmuxDomainState {
     }
 }
 
+fn layout_size(geometry: &PaneLayout) -> TerminalSize {
+    TerminalSize {
+        rows: geometry.pane_height as usize,
+        cols: geometry.pane_width as usize,
+        pixel_width: 0,
+        pixel_height: 0,
+        dpi: 0,
+    }
+}
+
+/// Converts a tmux layout, whose splits may have any number of children,
+/// into the binary split tree that is used by `Tab`.
+/// A split with children `a, b, c` becomes `a | (b | c)`.
+fn layout_to_pane_node<F>(node: &LayoutNode, make_entry: &mut F) -> PaneNode
+where
+    F: FnMut(&PaneLayout) -> PaneEntry,
+{
+    match node {
+        LayoutNode::Pane(geometry) => PaneNode::Leaf(make_entry(geometry)),
+        LayoutNode::SplitHorizontal { children, .. } => {
+            split_to_pane_node(SplitDirection::Horizontal, children, make_entry)
+        }
+        LayoutNode::SplitVertical { children, .. } => {
+            split_to_pane_node(SplitDirection::Vertical, children, make_entry)
+        }
+    }
+}
+
+fn split_to_pane_node<F>(
+    direction: SplitDirection,
+    children: &[LayoutNode],
+    make_entry: &mut F,
+) -> PaneNode
+where
+    F: FnMut(&PaneLayout) -> PaneEntry,
+{
+    match children {
+        [] => PaneNode::Empty,
+        [only] => layout_to_pane_node(only, make_entry),
+        [first, rest @ ..] => {
+            // The second half of the split spans from the start of the
+            // second child to the end of the last one
+            let start = rest[0].geometry();
+            let end = rest[rest.len() - 1].geometry();
+            let second = match direction {
+                SplitDirection::Horizontal => TerminalSize {
+                    cols: (end.pane_left + end.pane_width - start.pane_left) as usize,
+                    ..layout_size(start)
+                },
+                SplitDirection::Vertical => TerminalSize {
+                    rows: (end.pane_top + end.pane_height - start.pane_top) as usize,
+                    ..layout_size(start)
+                },
+            };
+            PaneNode::Split {
+                left: Box::new(layout_to_pane_node(first, make_entry)),
+                right: Box::new(split_to_pane_node(direction, rest, make_entry)),
+                node: SplitDirectionAndSize {
+                    direction,
+                    first: layout_size(first.geometry()),
+                    second,
+                },
+            }
+        }
+    }
+}
+
 fn parse_sigil_number(text: &str) -> anyhow::Result<u64> {
     let num = text
         .get(1..)
@@ -634,8 +816,6 @@ fn parse_sigil_number(text: &str) -> anyhow::Result<u64> {
 #[derive(Debug)]
 pub(crate) struct ListAllPanes {
     pub window_id: TmuxWindowId,
-    pub prune: bool,
-    pub layout_csum: String,
 }
 
 impl TmuxCommand for ListAllPanes {
@@ -650,18 +830,8 @@ impl TmuxCommand for ListAllPanes {
             None => return "".to_string(),
         };
 
-        let mut gui_tabs = tmux_domain.inner.gui_tabs.lock();
-
-        let Some(local_tab) = gui_tabs.get_mut(&self.window_id) else {
+        if !tmux_domain.inner.check_window_attached(self.window_id) {
             return "".to_string();
-        };
-
-        if local_tab.layout_csum.eq(&self.layout_csum) {
-            if self.prune {
-                return "".to_string();
-            }
-        } else {
-            local_tab.layout_csum = self.layout_csum.clone();
         }
 
         format!(
@@ -679,7 +849,6 @@ impl TmuxCommand for ListAllPanes {
             anyhow::bail!("{error}");
         }
         let mut items = vec![];
-        let mut pane_set = HashSet::new();
         for line in result.output.split('\n') {
             if line.is_empty() {
                 continue;
@@ -728,8 +897,6 @@ impl TmuxCommand for ListAllPanes {
 
             let pane_active = pane_active == 1;
 
-            pane_set.insert(pane_id);
-
             items.push(PaneItem {
                 session_id,
                 window_id,
@@ -749,13 +916,7 @@ impl TmuxCommand for ListAllPanes {
         let mux = Mux::get();
         if let Some(domain) = mux.get_domain(domain_id) {
             if let Some(tmux_domain) = domain.downcast_ref::<TmuxDomain>() {
-                if !self.prune {
-                    return tmux_domain.inner.sync_pane_state(&items);
-                } else {
-                    return tmux_domain
-                        .inner
-                        .remove_detached_pane(self.window_id, &pane_set);
-                }
+                return tmux_domain.inner.sync_pane_state(&items);
             }
         }
         anyhow::bail!("Tmux domain lost");
@@ -775,6 +936,7 @@ impl TmuxCommand for ListAllWindows {
                 '#{{session_id}} #{{window_id}} \
                 #{{window_width}} #{{window_height}} \
                 #{{window_active}} \
+                #{{window_zoomed_flag}} \
                 #{{window_name}} \
                 #{{window_layout}} \
                 #{{history_limit}}' -t ${}\n",
@@ -811,6 +973,10 @@ impl TmuxCommand for ListAllWindows {
                 .next()
                 .ok_or_else(|| anyhow!("missing window_active"))?
                 .parse::<usize>()?;
+            let window_zoomed = fields
+                .next()
+                .ok_or_else(|| anyhow!("missing window_zoomed_flag"))?
+                .parse::<usize>()?;
 
             let window_name = fields
                 .next()
@@ -826,6 +992,7 @@ impl TmuxCommand for ListAllWindows {
                 .parse::<isize>()?;
 
             let window_active = window_active == 1;
+            let window_zoomed = window_zoomed == 1;
 
             if let Some(x) = self.window_id {
                 if x != window_id {
@@ -840,7 +1007,7 @@ impl TmuxCommand for ListAllWindows {
                 .get(5..)
                 .ok_or_else(|| anyhow!("missing window_layout"))?;
 
-            let layout = parse_layout(window_layout)?;
+            let layout = parse_layout_tree(window_layout)?;
 
             items.push(WindowItem {
                 session_id,
@@ -848,6 +1015,7 @@ impl TmuxCommand for ListAllWindows {
                 window_width,
                 window_height,
                 window_active,
+                window_zoomed,
                 window_name: window_name.to_string(),
                 layout,
                 layout_csum: layout_csum.to_string(),
@@ -1108,20 +1276,51 @@ pub(crate) struct SplitPane {
 
 impl TmuxCommand for SplitPane {
     fn get_command(&self, _domain_id: DomainId) -> String {
+        // Have tmux print the id of the new pane, so that we can
+        // resolve the pending split with it
         if self.direction == SplitDirection::Horizontal {
-            format!("split-window -h -t %{}\n", self.pane_id)
+            format!(
+                "split-window -h -P -F '#{{pane_id}}' -t %{}\n",
+                self.pane_id
+            )
         } else {
-            format!("split-window -v -t %{}\n", self.pane_id)
+            format!(
+                "split-window -v -P -F '#{{pane_id}}' -t %{}\n",
+                self.pane_id
+            )
         }
     }
 
     fn process_result(&self, domain_id: DomainId, result: &Guarded) -> anyhow::Result<()> {
+        let mux = Mux::get();
+        let domain = match mux.get_domain(domain_id) {
+            Some(d) => d,
+            None => anyhow::bail!("Tmux domain lost"),
+        };
+        let tmux_domain = match domain.downcast_ref::<TmuxDomain>() {
+            Some(t) => t,
+            None => anyhow::bail!("Tmux domain lost"),
+        };
+        let Some(mut promise) = tmux_domain.inner.pending_splits.lock().pop_front() else {
+            anyhow::bail!("split-window in domain={domain_id} has no pending split");
+        };
+
         if result.error {
             let error = format!("split-window in domain={domain_id} failed: {result:#?}");
             log::error!("{error}");
+            promise.err(anyhow!("{error}"));
             anyhow::bail!("{error}");
         }
-        Ok(())
+        match parse_sigil_number(result.output.trim()) {
+            Ok(pane_id) => {
+                promise.ok(pane_id);
+                Ok(())
+            }
+            Err(err) => {
+                promise.err(anyhow!("split-window in domain={domain_id}: {err:#}"));
+                Err(err)
+            }
+        }
     }
 }
 
@@ -1165,6 +1364,23 @@ impl TmuxCommand for SelectPane {
     }
 }
 
+#[derive(Debug)]
+pub(crate) struct DetachClient;
+impl TmuxCommand for DetachClient {
+    fn get_command(&self, _domain_id: DomainId) -> String {
+        "detach-client\n".to_owned()
+    }
+
+    fn process_result(&self, domain_id: DomainId, result: &Guarded) -> anyhow::Result<()> {
+        if result.error {
+            let error = format!("detach-client in domain={domain_id} failed: {result:#?}");
+            log::error!("{error}");
+            anyhow::bail!("{error}");
+        }
+        Ok(())
+    }
+}
+
 // This is a dummy command which indicates the attaching is done, it prevents the tmux output
 // the unexpected and unnecessary content when syncing with back end in attaching stage.
 #[derive(Debug)]
@@ -1196,3 +1412,74 @@ impl TmuxCommand for AttachDone {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entry(geometry: &PaneLayout) -> PaneEntry {
+        PaneEntry {
+            window_id: 0,
+            tab_id: 0,
+            pane_id: geometry.pane_id as PaneId,
+            title: String::new(),
+            size: layout_size(geometry),
+            working_dir: None,
+            is_active_pane: false,
+            is_zoomed_pane: false,
+            workspace: "default".to_string(),
+            cursor_pos: Default::default(),
+            physical_top: 0,
+            top_row: geometry.pane_top as usize,
+            left_col: geometry.pane_left as usize,
+            tty_name: None,
+        }
+    }
+
+    #[test]
+    fn layout_to_binary_tree() {
+        let layout = parse_layout_tree(
+            "100x21,0,0[100x10,0,0{33x10,0,0,1,33x10,34,0,2,32x10,68,0,3},100x10,0,11,4]",
+        )
+        .unwrap();
+        let root = layout_to_pane_node(&layout, &mut entry);
+        assert_eq!(root.root_size().unwrap().cols, 100);
+        assert_eq!(root.root_size().unwrap().rows, 21);
+
+        let PaneNode::Split { left, right, node } = root else {
+            panic!("expected a split");
+        };
+        assert_eq!(node.direction, SplitDirection::Vertical);
+        assert_eq!(node.first.rows, 10);
+        assert_eq!(node.second.rows, 10);
+        assert!(matches!(
+            *right,
+            PaneNode::Leaf(PaneEntry { pane_id: 4, .. })
+        ));
+
+        let PaneNode::Split { left, right, node } = *left else {
+            panic!("expected a split");
+        };
+        assert_eq!(node.direction, SplitDirection::Horizontal);
+        assert_eq!(node.first.cols, 33);
+        assert_eq!(node.second.cols, 66);
+        assert!(matches!(
+            *left,
+            PaneNode::Leaf(PaneEntry { pane_id: 1, .. })
+        ));
+
+        let PaneNode::Split { left, right, node } = *right else {
+            panic!("expected a split");
+        };
+        assert_eq!(node.first.cols, 33);
+        assert_eq!(node.second.cols, 32);
+        assert!(matches!(
+            *left,
+            PaneNode::Leaf(PaneEntry { pane_id: 2, .. })
+        ));
+        assert!(matches!(
+            *right,
+            PaneNode::Leaf(PaneEntry { pane_id: 3, .. })
+        ));
+    }
+}
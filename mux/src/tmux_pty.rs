@@ -124,11 +124,17 @@ impl ChildKiller for TmuxChild {
 
 impl MasterPty for TmuxPty {
     fn resize(&self, size: portable_pty::PtySize) -> Result<(), anyhow::Error> {
+        let pane_id = {
+            let pane = self.master_pane.lock();
+            // Applying a layout from tmux resizes the local pane to the
+            // size that tmux assigned it; don't echo that back to tmux
+            if pane.pane_width == size.cols as u64 && pane.pane_height == size.rows as u64 {
+                return Ok(());
+            }
+            pane.pane_id
+        };
         let mut cmd_queue = self.cmd_queue.lock();
-        cmd_queue.push_back(Box::new(Resize {
-            size,
-            pane_id: self.master_pane.lock().pane_id,
-        }));
+        cmd_queue.push_back(Box::new(Resize { size, pane_id }));
         TmuxDomainState::schedule_send_next_command(self.domain_id);
         Ok(())
     }
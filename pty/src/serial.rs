@@ -11,17 +11,26 @@ use crate::{
 };
 use anyhow::{ensure, Context};
 use filedescriptor::FileDescriptor;
-use serial2::{CharSize, FlowControl, Parity, SerialPort, StopBits};
+use serial2::SerialPort;
+pub use serial2::{CharSize, FlowControl, Parity, StopBits};
 use std::cell::RefCell;
 use std::ffi::{OsStr, OsString};
 use std::io::{Read, Result as IoResult, Write};
 #[cfg(unix)]
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 
 type Handle = Arc<SerialPort>;
 
+/// The state of the DTR and RTS output lines.  The port can only
+/// set them, so we track what we last set them to.
+struct ModemLines {
+    dtr: AtomicBool,
+    rts: AtomicBool,
+}
+
 pub struct SerialTty {
     port: OsString,
     baud: u32,
@@ -95,6 +104,11 @@ impl PtySystem for SerialTty {
             }),
             master: Box::new(Master {
                 port,
+                // Opening the port normally asserts DTR and RTS
+                lines: Arc::new(ModemLines {
+                    dtr: AtomicBool::new(true),
+                    rts: AtomicBool::new(true),
+                }),
                 took_writer: RefCell::new(false),
             }),
         })
@@ -191,9 +205,156 @@ impl ChildKiller for SerialChildKiller {
 
 struct Master {
     port: Handle,
+    lines: Arc<ModemLines>,
     took_writer: RefCell<bool>,
 }
 
+/// Returns a handle that can change the settings of the serial port
+/// behind `master`, or None if `master` is not a serial port
+pub fn serial_control(master: &dyn MasterPty) -> Option<SerialControl> {
+    let master = master.downcast_ref::<Master>()?;
+    Some(SerialControl {
+        port: Arc::clone(&master.port),
+        lines: Arc::clone(&master.lines),
+    })
+}
+
+/// Changes the line settings of an open serial port and
+/// controls its modem lines
+#[derive(Clone)]
+pub struct SerialControl {
+    port: Handle,
+    lines: Arc<ModemLines>,
+}
+
+impl SerialControl {
+    fn reconfigure(
+        &self,
+        func: impl FnOnce(&mut serial2::Settings) -> IoResult<()>,
+    ) -> anyhow::Result<()> {
+        let mut settings = self.port.get_configuration()?;
+        func(&mut settings)?;
+        self.port.set_configuration(&settings)?;
+        Ok(())
+    }
+
+    pub fn set_baud_rate(&self, baud: u32) -> anyhow::Result<()> {
+        self.reconfigure(|settings| settings.set_baud_rate(baud))
+            .with_context(|| format!("setting baud rate to {baud}"))
+    }
+
+    pub fn set_char_size(&self, char_size: CharSize) -> anyhow::Result<()> {
+        self.reconfigure(|settings| {
+            settings.set_char_size(char_size);
+            Ok(())
+        })
+    }
+
+    pub fn set_parity(&self, parity: Parity) -> anyhow::Result<()> {
+        self.reconfigure(|settings| {
+            settings.set_parity(parity);
+            Ok(())
+        })
+    }
+
+    pub fn set_stop_bits(&self, stop_bits: StopBits) -> anyhow::Result<()> {
+        self.reconfigure(|settings| {
+            settings.set_stop_bits(stop_bits);
+            Ok(())
+        })
+    }
+
+    pub fn set_flow_control(&self, flow_control: FlowControl) -> anyhow::Result<()> {
+        self.reconfigure(|settings| {
+            settings.set_flow_control(flow_control);
+            Ok(())
+        })
+    }
+
+    pub fn dtr(&self) -> bool {
+        self.lines.dtr.load(Ordering::Relaxed)
+    }
+
+    pub fn set_dtr(&self, dtr: bool) -> anyhow::Result<()> {
+        self.port.set_dtr(dtr)?;
+        self.lines.dtr.store(dtr, Ordering::Relaxed);
+        Ok(())
+    }
+
+    pub fn rts(&self) -> bool {
+        self.lines.rts.load(Ordering::Relaxed)
+    }
+
+    pub fn set_rts(&self, rts: bool) -> anyhow::Result<()> {
+        self.port.set_rts(rts)?;
+        self.lines.rts.store(rts, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Holds the line in the break condition for `duration`.
+    /// This blocks the calling thread for that long.
+    pub fn send_break(&self, duration: Duration) -> anyhow::Result<()> {
+        self.port.set_break(true)?;
+        std::thread::sleep(duration);
+        self.port.set_break(false)?;
+        Ok(())
+    }
+
+    /// Returns the current settings of the port
+    pub fn settings(&self) -> anyhow::Result<SerialSettings> {
+        let settings = self.port.get_configuration()?;
+        Ok(SerialSettings {
+            baud: settings.get_baud_rate()?,
+            char_size: settings.get_char_size()?,
+            parity: settings.get_parity()?,
+            stop_bits: settings.get_stop_bits()?,
+            flow_control: settings.get_flow_control()?,
+            dtr: self.dtr(),
+            rts: self.rts(),
+        })
+    }
+}
+
+/// A snapshot of the settings of a serial port
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SerialSettings {
+    pub baud: u32,
+    pub char_size: CharSize,
+    pub parity: Parity,
+    pub stop_bits: StopBits,
+    pub flow_control: FlowControl,
+    pub dtr: bool,
+    pub rts: bool,
+}
+
+impl std::fmt::Display for SerialSettings {
+    /// Formats the settings in the conventional form, eg: `115200 8N1`
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let bits = match self.char_size {
+            CharSize::Bits5 => 5,
+            CharSize::Bits6 => 6,
+            CharSize::Bits7 => 7,
+            CharSize::Bits8 => 8,
+        };
+        let parity = match self.parity {
+            Parity::None => 'N',
+            Parity::Odd => 'O',
+            Parity::Even => 'E',
+        };
+        let stop = match self.stop_bits {
+            StopBits::One => 1,
+            StopBits::Two => 2,
+        };
+        write!(fmt, "{} {bits}{parity}{stop}", self.baud)?;
+        match self.flow_control {
+            FlowControl::None => {}
+            FlowControl::XonXoff => write!(fmt, " XON/XOFF")?,
+            FlowControl::RtsCts => write!(fmt, " RTS/CTS")?,
+        }
+        Ok(())
+    }
+}
+
 struct MasterWriter {
     port: Handle,
 }
@@ -303,3 +464,32 @@ impl Read for Reader {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn settings_display() {
+        let mut settings = SerialSettings {
+            baud: 115200,
+            char_size: CharSize::Bits8,
+            parity: Parity::None,
+            stop_bits: StopBits::One,
+            flow_control: FlowControl::None,
+            dtr: true,
+            rts: true,
+        };
+        assert_eq!(settings.to_string(), "115200 8N1");
+
+        settings.baud = 9600;
+        settings.char_size = CharSize::Bits7;
+        settings.parity = Parity::Even;
+        settings.stop_bits = StopBits::Two;
+        settings.flow_control = FlowControl::RtsCts;
+        assert_eq!(settings.to_string(), "9600 7E2 RTS/CTS");
+
+        settings.flow_control = FlowControl::XonXoff;
+        assert_eq!(settings.to_string(), "9600 7E2 XON/XOFF");
+    }
+}
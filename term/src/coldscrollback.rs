@@ -0,0 +1,1014 @@
+//! The cold tier of the scrollback.
+//!
+//! Lines that fall off the top of the regular (hot) scrollback held by
+//! `Screen` are packed into fixed size blocks of compacted lines.
+//! When the `use_serde` feature is enabled, each block is serialized and
+//! deflated, and may optionally be written to an anonymous spill file so
+//! that the resident cost of a block is little more than its index entry.
+//! Blocks are inflated lazily, and only when something reads from them.
+//!
+//! Blocks are not rewrapped when the terminal is resized.  Each block
+//! remembers the width its lines were wrapped to, along with the shape
+//! of its logical lines, which is enough to work out how many rows it
+//! occupies at the new width.  The lines themselves are rewrapped when
+//! the block is next inflated.
+//!
+//! The space in the spill file that was occupied by discarded blocks is
+//! reused for new blocks, and each clone of the cold scrollback has a
+//! spill file of its own.
+use crate::config::TerminalConfiguration;
+use std::collections::VecDeque;
+use std::num::NonZeroUsize;
+use std::ops::Range;
+use std::sync::{Arc, Mutex};
+use wezterm_surface::{Line, SequenceNo};
+
+/// How many lines are packed together into a block
+const LINES_PER_BLOCK: usize = 1024;
+/// Blocks end with a complete logical line where possible, so that
+/// they can be rewrapped independently of each other; a logical line
+/// that is longer than this is split across blocks regardless
+const MAX_TAIL_LINES: usize = 4 * LINES_PER_BLOCK;
+/// How many inflated blocks to keep around to make scrolling
+/// through and repeatedly rendering the cold region cheaper
+const INFLATED_CACHE_SIZE: usize = 4;
+#[cfg(feature = "use_serde")]
+const COMPRESSION_LEVEL: u8 = 6;
+
+/// A region of the spill file
+#[cfg(feature = "use_serde")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Extent {
+    offset: u64,
+    len: u64,
+}
+
+#[cfg(feature = "use_serde")]
+impl Extent {
+    fn end(&self) -> u64 {
+        self.offset + self.len
+    }
+}
+
+/// Keeps track of the space in the spill file, so that the space
+/// used by blocks that have been discarded can be used again
+#[cfg(feature = "use_serde")]
+#[derive(Debug, Default)]
+struct ExtentAllocator {
+    /// Unused regions, ordered by offset; adjacent regions are merged
+    free: Vec<Extent>,
+    /// The size of the file
+    len: u64,
+}
+
+#[cfg(feature = "use_serde")]
+impl ExtentAllocator {
+    /// Returns a region of `len` bytes, preferring to reuse free space
+    /// over growing the file
+    fn allocate(&mut self, len: u64) -> Extent {
+        if let Some(idx) = self.free.iter().position(|free| free.len >= len) {
+            let free = &mut self.free[idx];
+            let extent = Extent {
+                offset: free.offset,
+                len,
+            };
+            free.offset += len;
+            free.len -= len;
+            if free.len == 0 {
+                self.free.remove(idx);
+            }
+            return extent;
+        }
+        let extent = Extent {
+            offset: self.len,
+            len,
+        };
+        self.len += len;
+        extent
+    }
+
+    /// Makes a region available for reuse.  Free space at the end of
+    /// the file is given back; the new size of the file is returned
+    /// when that happens.
+    fn release(&mut self, extent: Extent) -> Option<u64> {
+        if extent.len == 0 {
+            return None;
+        }
+        let idx = self
+            .free
+            .partition_point(|free| free.offset < extent.offset);
+        self.free.insert(idx, extent);
+        if idx + 1 < self.free.len() && self.free[idx].end() == self.free[idx + 1].offset {
+            self.free[idx].len += self.free[idx + 1].len;
+            self.free.remove(idx + 1);
+        }
+        if idx > 0 && self.free[idx - 1].end() == self.free[idx].offset {
+            self.free[idx - 1].len += self.free[idx].len;
+            self.free.remove(idx);
+        }
+        match self.free.last() {
+            Some(last) if last.end() == self.len => {
+                self.len = last.offset;
+                self.free.pop();
+                Some(self.len)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(feature = "use_serde")]
+struct SpillFile {
+    file: Mutex<std::fs::File>,
+    dir: std::path::PathBuf,
+    space: ExtentAllocator,
+}
+
+#[cfg(feature = "use_serde")]
+impl SpillFile {
+    fn create(dir: &std::path::Path) -> anyhow::Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        let file = tempfile::tempfile_in(dir)?;
+        log::debug!("spilling cold scrollback into {}", dir.display());
+        Ok(Self {
+            file: Mutex::new(file),
+            dir: dir.to_path_buf(),
+            space: ExtentAllocator::default(),
+        })
+    }
+
+    fn write(&mut self, data: &[u8]) -> anyhow::Result<Extent> {
+        use std::io::{Seek, SeekFrom, Write};
+
+        let extent = self.space.allocate(data.len() as u64);
+        let result = {
+            let file = self.file.get_mut().unwrap();
+            file.seek(SeekFrom::Start(extent.offset))
+                .and_then(|_| file.write_all(data))
+        };
+        match result {
+            Ok(()) => Ok(extent),
+            Err(err) => {
+                self.release(extent);
+                Err(err.into())
+            }
+        }
+    }
+
+    fn read(&self, extent: Extent) -> anyhow::Result<Vec<u8>> {
+        use std::io::{Read, Seek, SeekFrom};
+
+        let mut file = self.file.lock().unwrap();
+        file.seek(SeekFrom::Start(extent.offset))?;
+        let mut buf = vec![0u8; extent.len as usize];
+        file.read_exact(&mut buf)?;
+        Ok(buf)
+    }
+
+    fn release(&mut self, extent: Extent) {
+        if let Some(len) = self.space.release(extent) {
+            self.set_len(len);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.space = ExtentAllocator::default();
+        self.set_len(0);
+    }
+
+    fn set_len(&mut self, len: u64) {
+        if let Err(err) = self.file.get_mut().unwrap().set_len(len) {
+            log::error!("failed to truncate scrollback spill file: {:#}", err);
+        }
+    }
+}
+
+#[derive(Clone)]
+enum BlockData {
+    /// Compacted lines held directly in memory; used when we
+    /// have no means to serialize them
+    #[cfg(not(feature = "use_serde"))]
+    Lines(Arc<Vec<Line>>),
+    /// Serialized and deflated lines held in memory
+    #[cfg(feature = "use_serde")]
+    Compressed(Vec<u8>),
+    /// Serialized and deflated lines written to the spill file
+    #[cfg(feature = "use_serde")]
+    Spilled(Extent),
+}
+
+/// Describes the logical lines of a block in enough detail to tell
+/// how many rows they need at any width, without inflating the block
+#[derive(Clone, Default)]
+struct BlockShape {
+    /// For each logical line, its length in cells and the end of
+    /// its runs in `runs`
+    lines: Vec<(u32, u32)>,
+    /// The widths of the visible cells of the logical lines, up to
+    /// the last non-blank cell of each, as `(width, count)` runs
+    runs: Vec<(u8, u32)>,
+}
+
+impl BlockShape {
+    fn new(lines: &[Line]) -> Self {
+        /// Adds to the runs from `start` onwards
+        fn push_run(runs: &mut Vec<(u8, u32)>, start: usize, width: u8, count: u32) {
+            let len = runs.len();
+            match runs.last_mut() {
+                Some((w, c)) if len > start && *w == width => *c += count,
+                _ => runs.push((width, count)),
+            }
+        }
+
+        let mut shape = Self::default();
+        let mut len = 0;
+        let mut start = 0;
+        let mut blanks = vec![];
+        let mut continued = false;
+
+        for line in lines {
+            if !continued {
+                // This is the length that `Line::append_line` builds on
+                len = line.len();
+                start = shape.runs.len();
+            }
+            for cell in line.visible_cells() {
+                // A zero width cell still occupies a column once stored
+                let width = cell.width().clamp(1, u8::MAX as usize);
+                if continued {
+                    len += width;
+                }
+                let width = width as u8;
+                if cell.str() == " " {
+                    push_run(&mut blanks, 0, width, 1);
+                } else {
+                    for (width, count) in blanks.drain(..) {
+                        push_run(&mut shape.runs, start, width, count);
+                    }
+                    push_run(&mut shape.runs, start, width, 1);
+                }
+            }
+
+            continued = line.last_cell_was_wrapped();
+            if !continued {
+                shape.end_line(len, &mut blanks);
+            }
+        }
+        if continued {
+            shape.end_line(len, &mut blanks);
+        }
+        shape
+    }
+
+    fn end_line(&mut self, len: usize, blanks: &mut Vec<(u8, u32)>) {
+        // Trailing blanks are dropped by `Line::wrap`
+        blanks.clear();
+        self.lines.push((len as u32, self.runs.len() as u32));
+    }
+
+    /// Returns the number of rows that `rewrap_lines` produces for
+    /// the lines of the block at `physical_cols`
+    fn num_rows(&self, physical_cols: usize) -> usize {
+        let mut start = 0;
+        let mut total = 0;
+        for &(len, end) in &self.lines {
+            let runs = &self.runs[start..end as usize];
+            start = end as usize;
+            total += if len as usize <= physical_cols || runs.is_empty() {
+                1
+            } else {
+                Self::wrapped_rows(runs, physical_cols)
+            };
+        }
+        total
+    }
+
+    /// Follows the same greedy placement of cells as `Line::wrap`
+    fn wrapped_rows(runs: &[(u8, u32)], physical_cols: usize) -> usize {
+        let mut rows = 0;
+        let mut used = 0;
+        for &(width, count) in runs {
+            let width = width as usize;
+            let mut count = count as usize;
+            if width > physical_cols {
+                // Each of these cells gets a row to itself
+                rows += count;
+                used = width;
+                continue;
+            }
+            while count > 0 {
+                if rows == 0 || used + width > physical_cols {
+                    rows += 1;
+                    used = 0;
+                }
+                let fit = ((physical_cols - used) / width).min(count);
+                used += fit * width;
+                count -= fit;
+            }
+        }
+        rows
+    }
+}
+
+#[derive(Clone)]
+struct Block {
+    id: usize,
+    /// The number of rows the block occupies at the current width
+    num_lines: usize,
+    /// The width that the stored lines were wrapped to
+    cols: usize,
+    /// The number of stored lines
+    stored_lines: usize,
+    shape: BlockShape,
+    /// The sequence number of the most recent rewrap that this
+    /// block has yet to be brought up to date with
+    rewrap_seqno: Option<SequenceNo>,
+    data: BlockData,
+}
+
+pub(crate) struct ColdScrollback {
+    /// Packed blocks, oldest first
+    blocks: VecDeque<Block>,
+    /// Evicted lines that have yet to fill up a block
+    tail: Vec<Line>,
+    /// The total number of lines, including those in `tail`
+    num_lines: usize,
+    /// The width that the lines are presented at
+    physical_cols: usize,
+    next_block_id: usize,
+    #[cfg(feature = "use_serde")]
+    spill: Option<SpillFile>,
+    inflated: Mutex<lru::LruCache<usize, Arc<Vec<Line>>>>,
+}
+
+impl std::fmt::Debug for ColdScrollback {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let mut fmt = fmt.debug_struct("ColdScrollback");
+        fmt.field("num_lines", &self.num_lines)
+            .field("num_blocks", &self.blocks.len());
+        #[cfg(feature = "use_serde")]
+        fmt.field("spilled", &self.spill.is_some());
+        fmt.finish()
+    }
+}
+
+impl Clone for ColdScrollback {
+    fn clone(&self) -> Self {
+        #[allow(unused_mut)]
+        let mut blocks = self.blocks.clone();
+        #[cfg(feature = "use_serde")]
+        let spill = self.clone_spill(&mut blocks);
+        Self {
+            blocks,
+            tail: self.tail.clone(),
+            num_lines: self.num_lines,
+            physical_cols: self.physical_cols,
+            next_block_id: self.next_block_id,
+            #[cfg(feature = "use_serde")]
+            spill,
+            inflated: Mutex::new(new_cache()),
+        }
+    }
+}
+
+fn new_cache() -> lru::LruCache<usize, Arc<Vec<Line>>> {
+    lru::LruCache::new(NonZeroUsize::new(INFLATED_CACHE_SIZE).unwrap())
+}
+
+impl ColdScrollback {
+    pub fn new(physical_cols: usize) -> Self {
+        Self {
+            blocks: VecDeque::new(),
+            tail: vec![],
+            num_lines: 0,
+            physical_cols,
+            next_block_id: 0,
+            #[cfg(feature = "use_serde")]
+            spill: None,
+            inflated: Mutex::new(new_cache()),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.num_lines
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.num_lines == 0
+    }
+
+    /// Discard all of the cold lines
+    pub fn clear(&mut self) {
+        self.blocks.clear();
+        self.tail.clear();
+        self.num_lines = 0;
+        self.inflated.lock().unwrap().clear();
+        #[cfg(feature = "use_serde")]
+        if let Some(spill) = self.spill.as_mut() {
+            spill.clear();
+        }
+    }
+
+    /// Append a line that was evicted from the hot scrollback.
+    /// If that takes us past the configured capacity, the oldest
+    /// block is discarded.
+    pub fn push(&mut self, mut line: Line, config: &dyn TerminalConfiguration) {
+        let capacity = config.cold_scrollback_size();
+        if capacity == 0 {
+            if !self.is_empty() {
+                self.clear();
+            }
+            return;
+        }
+
+        line.compress_for_scrollback();
+        self.tail.push(line);
+        self.num_lines += 1;
+
+        let line_ended = !self
+            .tail
+            .last()
+            .map(Line::last_cell_was_wrapped)
+            .unwrap_or(false);
+        if self.tail.len() >= MAX_TAIL_LINES || (self.tail.len() >= LINES_PER_BLOCK && line_ended) {
+            self.pack_tail(config);
+        }
+
+        self.trim(capacity);
+    }
+
+    fn trim(&mut self, capacity: usize) {
+        // Whole blocks are discarded at a time, so we may hold up
+        // to a block's worth of lines more than the capacity
+        while let Some(block) = self.blocks.front() {
+            if self.num_lines - block.num_lines < capacity {
+                break;
+            }
+            self.num_lines -= block.num_lines;
+            self.inflated.lock().unwrap().pop(&block.id);
+            if let Some(block) = self.blocks.pop_front() {
+                self.release(&block.data);
+            }
+        }
+        if self.blocks.is_empty() && self.num_lines > capacity {
+            let excess = self.num_lines - capacity;
+            self.tail.drain(0..excess);
+            self.num_lines -= excess;
+        }
+    }
+
+    /// Gives back the storage used by a block that is no longer needed
+    #[cfg(feature = "use_serde")]
+    fn release(&mut self, data: &BlockData) {
+        if let (BlockData::Spilled(extent), Some(spill)) = (data, self.spill.as_mut()) {
+            spill.release(*extent);
+        }
+    }
+
+    #[cfg(not(feature = "use_serde"))]
+    fn release(&mut self, _data: &BlockData) {}
+
+    fn pack_tail(&mut self, config: &dyn TerminalConfiguration) {
+        let lines = std::mem::take(&mut self.tail);
+        let num_lines = lines.len();
+        let shape = BlockShape::new(&lines);
+        match self.encode(lines, config) {
+            Ok(data) => {
+                let id = self.next_block_id;
+                self.next_block_id += 1;
+                self.blocks.push_back(Block {
+                    id,
+                    num_lines,
+                    cols: self.physical_cols,
+                    stored_lines: num_lines,
+                    shape,
+                    rewrap_seqno: None,
+                    data,
+                });
+            }
+            Err(err) => {
+                log::error!(
+                    "failed to pack {} lines of cold scrollback, discarding them: {:#}",
+                    num_lines,
+                    err
+                );
+                // Keep the line count consistent with what we can return;
+                // this shifts the cold region down, which is the best we
+                // can do without the lost lines.
+                self.num_lines -= num_lines;
+            }
+        }
+    }
+
+    #[cfg(not(feature = "use_serde"))]
+    fn encode(
+        &mut self,
+        lines: Vec<Line>,
+        _config: &dyn TerminalConfiguration,
+    ) -> anyhow::Result<BlockData> {
+        Ok(BlockData::Lines(Arc::new(lines)))
+    }
+
+    #[cfg(feature = "use_serde")]
+    fn encode(
+        &mut self,
+        lines: Vec<Line>,
+        config: &dyn TerminalConfiguration,
+    ) -> anyhow::Result<BlockData> {
+        let mut serialized = vec![];
+        let mut encode = varbincode::Serializer::new(&mut serialized);
+        serde::Serialize::serialize(&lines, &mut encode)?;
+        drop(encode);
+        let compressed = miniz_oxide::deflate::compress_to_vec(&serialized, COMPRESSION_LEVEL);
+
+        if self.spill.is_none() {
+            if let Some(dir) = config.scrollback_spill_directory() {
+                self.spill.replace(SpillFile::create(&dir)?);
+            }
+        }
+
+        match self.spill.as_mut() {
+            Some(spill) => Ok(BlockData::Spilled(spill.write(&compressed)?)),
+            None => Ok(BlockData::Compressed(compressed)),
+        }
+    }
+
+    #[cfg(not(feature = "use_serde"))]
+    fn decode(&self, data: &BlockData) -> anyhow::Result<Arc<Vec<Line>>> {
+        match data {
+            BlockData::Lines(lines) => Ok(Arc::clone(lines)),
+        }
+    }
+
+    #[cfg(feature = "use_serde")]
+    fn decode(&self, data: &BlockData) -> anyhow::Result<Arc<Vec<Line>>> {
+        let read_buf;
+        let compressed = match data {
+            BlockData::Compressed(data) => data.as_slice(),
+            BlockData::Spilled(extent) => {
+                let spill = self
+                    .spill
+                    .as_ref()
+                    .ok_or_else(|| anyhow::anyhow!("block was spilled but there is no file"))?;
+                read_buf = spill.read(*extent)?;
+                read_buf.as_slice()
+            }
+        };
+
+        let serialized = miniz_oxide::inflate::decompress_to_vec(compressed)
+            .map_err(|e| anyhow::anyhow!("decompressing cold scrollback: {:?}", e))?;
+        let mut decode = varbincode::Deserializer::new(serialized.as_slice());
+        let lines: Vec<Line> = serde::Deserialize::deserialize(&mut decode)?;
+        Ok(Arc::new(lines))
+    }
+
+    /// Copies the spilled blocks into a spill file of their own, so
+    /// that changes made to a clone of the scrollback don't affect the
+    /// original.  If that isn't possible, the clone holds the blocks in
+    /// memory instead.
+    #[cfg(feature = "use_serde")]
+    fn clone_spill(&self, blocks: &mut VecDeque<Block>) -> Option<SpillFile> {
+        let spill = self.spill.as_ref()?;
+        let mut copy = match SpillFile::create(&spill.dir) {
+            Ok(copy) => Some(copy),
+            Err(err) => {
+                log::error!(
+                    "failed to create scrollback spill file, \
+                     keeping a copy of the cold scrollback in memory: {:#}",
+                    err
+                );
+                None
+            }
+        };
+
+        for block in blocks.iter_mut() {
+            let extent = match block.data {
+                BlockData::Spilled(extent) => extent,
+                BlockData::Compressed(_) => continue,
+            };
+            let data = spill.read(extent).unwrap_or_else(|err| {
+                log::error!("failed to read scrollback spill file: {:#}", err);
+                vec![]
+            });
+            block.data = match copy.as_mut().map(|copy| copy.write(&data)) {
+                Some(Ok(extent)) => BlockData::Spilled(extent),
+                Some(Err(err)) => {
+                    log::error!("failed to write scrollback spill file: {:#}", err);
+                    BlockData::Compressed(data)
+                }
+                None => BlockData::Compressed(data),
+            };
+        }
+
+        copy
+    }
+
+    fn inflate(&self, block: &Block) -> anyhow::Result<Arc<Vec<Line>>> {
+        if let Some(lines) = self.inflated.lock().unwrap().get(&block.id) {
+            return Ok(Arc::clone(lines));
+        }
+        let mut lines = self.decode(&block.data)?;
+        if let Some(seqno) = block.rewrap_seqno {
+            let stored = Arc::try_unwrap(lines).unwrap_or_else(|lines| (*lines).clone());
+            let rewrapped = if block.cols == self.physical_cols {
+                stored
+                    .into_iter()
+                    .map(|mut line| {
+                        line.update_last_change_seqno(seqno);
+                        line
+                    })
+                    .collect()
+            } else {
+                rewrap_lines(stored, self.physical_cols, seqno)
+            };
+            debug_assert_eq!(rewrapped.len(), block.num_lines);
+            lines = Arc::new(rewrapped);
+        }
+        self.inflated
+            .lock()
+            .unwrap()
+            .put(block.id, Arc::clone(&lines));
+        Ok(lines)
+    }
+
+    /// Calls `func` with successive runs of lines that intersect `range`,
+    /// where the range is expressed relative to the oldest cold line.
+    /// The first parameter to `func` is the index of the first line in the
+    /// slice.  Iteration stops early if `func` returns false.
+    pub fn for_each_run_in_range<F>(&self, range: Range<usize>, mut func: F)
+    where
+        F: FnMut(usize, &[Line]) -> bool,
+    {
+        let range = range.start..range.end.min(self.num_lines);
+        let mut block_start = 0;
+
+        for block in &self.blocks {
+            let block_range = block_start..block_start + block.num_lines;
+            block_start = block_range.end;
+
+            if block_range.end <= range.start {
+                continue;
+            }
+            if block_range.start >= range.end {
+                return;
+            }
+
+            let lines = match self.inflate(block) {
+                Ok(lines) => lines,
+                Err(err) => {
+                    log::error!("failed to inflate cold scrollback: {:#}", err);
+                    continue;
+                }
+            };
+
+            let first = range.start.max(block_range.start);
+            let last = range.end.min(block_range.end);
+            if !func(
+                first,
+                &lines[first - block_range.start..last - block_range.start],
+            ) {
+                return;
+            }
+        }
+
+        let tail_range = block_start..block_start + self.tail.len();
+        let first = range.start.max(tail_range.start);
+        let last = range.end.min(tail_range.end);
+        if first < last {
+            func(
+                first,
+                &self.tail[first - tail_range.start..last - tail_range.start],
+            );
+        }
+    }
+
+    /// Returns copies of the lines in `range`, where the range is
+    /// expressed relative to the oldest cold line.
+    pub fn lines_in_range(&self, range: Range<usize>) -> Vec<Line> {
+        let mut result = vec![];
+        self.for_each_run_in_range(range, |_, lines| {
+            result.extend_from_slice(lines);
+            true
+        });
+        result
+    }
+
+    /// Replaces the lines starting at `first`, which is relative to the
+    /// oldest cold line, with `lines`.  Only the blocks whose lines are
+    /// changed by this are packed again.
+    pub fn write_back(&mut self, first: usize, lines: &[Line], config: &dyn TerminalConfiguration) {
+        let end = (first + lines.len()).min(self.num_lines);
+        let mut block_start = 0;
+
+        for idx in 0..self.blocks.len() {
+            let block_range = block_start..block_start + self.blocks[idx].num_lines;
+            block_start = block_range.end;
+
+            if block_range.end <= first {
+                continue;
+            }
+            if block_range.start >= end {
+                return;
+            }
+
+            let current = match self.inflate(&self.blocks[idx]) {
+                Ok(lines) => lines,
+                Err(err) => {
+                    log::error!("failed to inflate cold scrollback: {:#}", err);
+                    continue;
+                }
+            };
+            let lo = first.max(block_range.start);
+            let hi = end.min(block_range.end);
+            let replacement = &lines[lo - first..hi - first];
+            if current[lo - block_range.start..hi - block_range.start] == *replacement {
+                continue;
+            }
+
+            let mut updated = (*current).clone();
+            for (target, line) in updated[lo - block_range.start..hi - block_range.start]
+                .iter_mut()
+                .zip(replacement)
+            {
+                *target = line.clone();
+                target.compress_for_scrollback();
+            }
+            // The block is stored at the current width from now on
+            let shape = BlockShape::new(&updated);
+            let stored_lines = updated.len();
+            match self.encode(updated, config) {
+                Ok(data) => {
+                    let block = &mut self.blocks[idx];
+                    block.cols = self.physical_cols;
+                    block.stored_lines = stored_lines;
+                    block.shape = shape;
+                    block.rewrap_seqno = None;
+                    let previous = std::mem::replace(&mut block.data, data);
+                    let id = block.id;
+                    self.inflated.lock().unwrap().pop(&id);
+                    self.release(&previous);
+                }
+                Err(err) => log::error!("failed to update cold scrollback: {:#}", err),
+            }
+        }
+
+        let tail_start = block_start;
+        for idx in first.max(tail_start)..end {
+            self.tail[idx - tail_start] = lines[idx - first].clone();
+        }
+    }
+
+    /// Returns the smallest index such that the lines from there to `end`
+    /// are all wrapped, which is the start of the logical line that
+    /// contains the line at `end`
+    pub fn wrapped_run_start(&self, end: usize) -> usize {
+        let mut start = end.min(self.num_lines);
+
+        let tail_start = self.num_lines - self.tail.len();
+        while start > tail_start {
+            if !self.tail[start - 1 - tail_start].last_cell_was_wrapped() {
+                return start;
+            }
+            start -= 1;
+        }
+
+        let mut block_end = tail_start;
+        for block in self.blocks.iter().rev() {
+            let block_start = block_end - block.num_lines;
+            block_end = block_start;
+            if block_start >= start {
+                continue;
+            }
+            let lines = match self.inflate(block) {
+                Ok(lines) => lines,
+                Err(err) => {
+                    log::error!("failed to inflate cold scrollback: {:#}", err);
+                    return start;
+                }
+            };
+            while start > block_start {
+                if !lines[start - 1 - block_start].last_cell_was_wrapped() {
+                    return start;
+                }
+                start -= 1;
+            }
+        }
+
+        start
+    }
+
+    /// If the last cold line is wrapped, its logical line continues in
+    /// the hot scrollback; returns the index of the first cold line of
+    /// that logical line
+    pub fn trailing_wrapped_start(&self) -> Option<usize> {
+        let start = self.wrapped_run_start(self.num_lines);
+        if start < self.num_lines {
+            Some(start)
+        } else {
+            None
+        }
+    }
+
+    /// Removes and returns the lines from `at` onwards
+    fn split_off(&mut self, at: usize) -> Vec<Line> {
+        if at >= self.num_lines {
+            return vec![];
+        }
+        let result = self.lines_in_range(at..self.num_lines);
+
+        let mut to_remove = self.num_lines - at;
+        if to_remove <= self.tail.len() {
+            self.tail.truncate(self.tail.len() - to_remove);
+        } else {
+            to_remove -= self.tail.len();
+            self.tail.clear();
+            while to_remove > 0 {
+                let block = match self.blocks.pop_back() {
+                    Some(block) => block,
+                    None => break,
+                };
+                if block.num_lines > to_remove {
+                    // Keep the start of the block as the new tail
+                    match self.inflate(&block) {
+                        Ok(lines) => {
+                            self.tail = lines[..block.num_lines - to_remove].to_vec();
+                        }
+                        Err(err) => log::error!("failed to inflate cold scrollback: {:#}", err),
+                    }
+                    to_remove = 0;
+                } else {
+                    to_remove -= block.num_lines;
+                }
+                self.inflated.lock().unwrap().pop(&block.id);
+                self.release(&block.data);
+            }
+        }
+
+        self.num_lines = self
+            .blocks
+            .iter()
+            .map(|block| block.num_lines)
+            .sum::<usize>()
+            + self.tail.len();
+        result
+    }
+
+    /// Removes and returns the start of the logical line that continues
+    /// in the hot scrollback, if any, so that it can be rewrapped
+    /// together with its continuation
+    pub fn take_trailing_wrapped(&mut self) -> Vec<Line> {
+        match self.trailing_wrapped_start() {
+            Some(start) => self.split_off(start),
+            None => vec![],
+        }
+    }
+
+    /// Rewraps the cold lines to fit `physical_cols` columns.
+    /// The last cold line is expected to end its logical line;
+    /// use `take_trailing_wrapped` to remove one that doesn't first.
+    ///
+    /// Only the row counts of the packed blocks are updated here;
+    /// their lines are rewrapped when they are next inflated.
+    pub fn rewrap(
+        &mut self,
+        physical_cols: usize,
+        seqno: SequenceNo,
+        config: &dyn TerminalConfiguration,
+    ) {
+        self.physical_cols = physical_cols;
+        self.inflated.lock().unwrap().clear();
+
+        for block in self.blocks.iter_mut() {
+            block.num_lines = if block.cols == physical_cols {
+                block.stored_lines
+            } else {
+                block.shape.num_rows(physical_cols)
+            };
+            block.rewrap_seqno.replace(seqno);
+        }
+        self.num_lines = self.blocks.iter().map(|block| block.num_lines).sum();
+
+        let tail = std::mem::take(&mut self.tail);
+        for line in rewrap_lines(tail, physical_cols, seqno) {
+            self.push(line, config);
+        }
+    }
+}
+
+/// Rewraps `lines` to fit `physical_cols` columns.  A logical line that
+/// is still wrapped at the end of `lines` continues after them, so its
+/// last row remains wrapped.
+fn rewrap_lines(lines: Vec<Line>, physical_cols: usize, seqno: SequenceNo) -> Vec<Line> {
+    let mut result = Vec::with_capacity(lines.len());
+    let mut logical_line: Option<Line> = None;
+
+    for mut line in lines {
+        line.update_last_change_seqno(seqno);
+        let was_wrapped = line.last_cell_was_wrapped();
+        if was_wrapped {
+            line.set_last_cell_was_wrapped(false, seqno);
+        }
+
+        let line = match logical_line.take() {
+            None => line,
+            Some(mut prior) => {
+                prior.append_line(line, seqno);
+                prior
+            }
+        };
+
+        if was_wrapped {
+            logical_line.replace(line);
+        } else {
+            push_wrapped(&mut result, line, physical_cols, seqno);
+        }
+    }
+
+    if let Some(line) = logical_line {
+        push_wrapped(&mut result, line, physical_cols, seqno);
+        if let Some(last) = result.last_mut() {
+            last.set_last_cell_was_wrapped(true, seqno);
+        }
+    }
+
+    for line in &mut result {
+        line.compress_for_scrollback();
+    }
+    result
+}
+
+fn push_wrapped(result: &mut Vec<Line>, line: Line, physical_cols: usize, seqno: SequenceNo) {
+    if line.len() <= physical_cols {
+        result.push(line);
+    } else {
+        result.extend(line.wrap(physical_cols, seqno));
+    }
+}
+
+#[cfg(all(test, feature = "use_serde"))]
+mod test {
+    use super::*;
+
+    fn extent(offset: u64, len: u64) -> Extent {
+        Extent { offset, len }
+    }
+
+    #[test]
+    fn allocator_appends_when_nothing_is_free() {
+        let mut space = ExtentAllocator::default();
+        assert_eq!(space.allocate(10), extent(0, 10));
+        assert_eq!(space.allocate(5), extent(10, 5));
+        assert_eq!(space.len, 15);
+    }
+
+    #[test]
+    fn allocator_reuses_released_space() {
+        let mut space = ExtentAllocator::default();
+        let first = space.allocate(10);
+        let _second = space.allocate(10);
+        assert_eq!(space.release(first), None);
+
+        // Fits into the hole left by the first extent
+        assert_eq!(space.allocate(4), extent(0, 4));
+        assert_eq!(space.allocate(6), extent(4, 6));
+        assert!(space.free.is_empty());
+        // Too big for any hole, so the file grows
+        assert_eq!(space.allocate(3), extent(20, 3));
+        assert_eq!(space.len, 23);
+    }
+
+    #[test]
+    fn allocator_merges_and_shrinks() {
+        let mut space = ExtentAllocator::default();
+        let a = space.allocate(10);
+        let b = space.allocate(10);
+        let c = space.allocate(10);
+        let d = space.allocate(10);
+
+        assert_eq!(space.release(a), None);
+        assert_eq!(space.release(c), None);
+        assert_eq!(space.release(b), None);
+        assert_eq!(space.free, vec![extent(0, 30)]);
+
+        // Releasing the last extent gives back everything
+        assert_eq!(space.release(d), Some(0));
+        assert!(space.free.is_empty());
+        assert_eq!(space.len, 0);
+    }
+
+    #[test]
+    fn allocator_bounded_by_live_data() {
+        // Blocks are discarded oldest first and new ones are added,
+        // which must not grow the file without bound
+        let mut space = ExtentAllocator::default();
+        let mut live = VecDeque::new();
+        for i in 0..1000u64 {
+            live.push_back(space.allocate(100 + (i % 7) * 10));
+            if live.len() > 8 {
+                let oldest = live.pop_front().unwrap();
+                space.release(oldest);
+            }
+        }
+        assert!(space.len <= 8 * 160 * 2, "file grew to {}", space.len);
+    }
+}
@@ -1,5 +1,6 @@
 use crate::color::ColorPalette;
 use downcast_rs::{impl_downcast, Downcast};
+use std::path::PathBuf;
 use wezterm_bidi::ParagraphDirectionHint;
 use wezterm_cell::UnicodeVersion;
 use wezterm_surface::{Line, SequenceNo};
@@ -147,6 +148,21 @@ pub trait TerminalConfiguration: Downcast + std::fmt::Debug + Send + Sync {
         3500
     }
 
+    /// Returns the number of rows that are retained in the cold tier
+    /// of the scrollback once they have been evicted from the regular
+    /// scrollback.  Cold rows are held in compressed blocks and are
+    /// decompressed on demand.  Returning 0 disables the cold tier.
+    fn cold_scrollback_size(&self) -> usize {
+        0
+    }
+
+    /// If this returns a directory, compressed cold scrollback blocks
+    /// are written to an anonymous file in that directory rather than
+    /// being held in memory.
+    fn scrollback_spill_directory(&self) -> Option<PathBuf> {
+        None
+    }
+
     /// Return true if the embedding application wants to use CSI-u encoding
     /// for keys that would otherwise be ambiguous.
     /// <http://www.leonerd.org.uk/hacks/fixterms/>
@@ -32,6 +32,8 @@ pub use crate::input::*;
 pub use wezterm_cell::*;
 pub use wezterm_surface::line::*;
 
+mod coldscrollback;
+
 pub mod screen;
 pub use crate::screen::*;
 
@@ -122,6 +124,73 @@ pub struct SemanticZone {
     pub semantic_type: SemanticType,
 }
 
+/// Describes a command that was run at a shell prompt, as delimited by
+/// the OSC 133 semantic prompt markers.
+/// The shell integration emits `A` when the prompt is drawn, `C` when
+/// the command starts producing output and `D;status` when it completes.
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[derive(Debug, Clone, Eq, PartialEq, FromDynamic, ToDynamic)]
+pub struct CommandBlock {
+    /// The row on which the prompt was started
+    pub prompt_y: StableRowIndex,
+    /// The row on which the command output started, if the command
+    /// has been submitted
+    pub output_start_y: Option<StableRowIndex>,
+    /// The row on which the command finished
+    pub end_y: Option<StableRowIndex>,
+    /// The exit status reported by the shell
+    pub exit_status: Option<i32>,
+    /// The working directory (OSC 7) in effect when the prompt was shown
+    pub cwd: Option<String>,
+    /// Milliseconds since the unix epoch at which the command started
+    pub start_time_ms: Option<u64>,
+    /// Milliseconds since the unix epoch at which the command finished
+    pub end_time_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum CommandBlockStatus {
+    /// The prompt is showing; no command has been run yet
+    Prompt,
+    /// The command has started but has not yet finished
+    Running,
+    Succeeded,
+    Failed(i32),
+    /// The command finished but the shell didn't report a status
+    Unknown,
+}
+
+impl CommandBlock {
+    pub fn status(&self) -> CommandBlockStatus {
+        match (self.output_start_y, self.end_y, self.exit_status) {
+            (_, _, Some(0)) => CommandBlockStatus::Succeeded,
+            (_, _, Some(status)) => CommandBlockStatus::Failed(status),
+            (_, Some(_), None) => CommandBlockStatus::Unknown,
+            (Some(_), None, None) => CommandBlockStatus::Running,
+            (None, None, None) => CommandBlockStatus::Prompt,
+        }
+    }
+
+    /// Returns the wall clock duration of the command, if it has both
+    /// started and finished
+    pub fn duration(&self) -> Option<std::time::Duration> {
+        match (self.start_time_ms, self.end_time_ms) {
+            (Some(start), Some(end)) => {
+                Some(std::time::Duration::from_millis(end.saturating_sub(start)))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the range of rows occupied by the output of this command.
+    /// `last_row` is used as the end for a command that is still running.
+    pub fn output_range(&self, last_row: StableRowIndex) -> Option<Range<StableRowIndex>> {
+        let start = self.output_start_y?;
+        let end = self.end_y.unwrap_or(last_row).max(start);
+        Some(start..end)
+    }
+}
+
 pub mod color;
 
 #[cfg(test)]
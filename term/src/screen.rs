@@ -1,5 +1,6 @@
 #![allow(clippy::range_plus_one)]
 use super::*;
+use crate::coldscrollback::ColdScrollback;
 use crate::config::BidiMode;
 use log::debug;
 use std::collections::VecDeque;
@@ -23,6 +24,12 @@ pub struct Screen {
     /// would otherwise have exceeded the line capacity
     lines: VecDeque<Line>,
 
+    /// Lines that have been evicted from the top of `lines` are retained
+    /// here in compressed form when the cold scrollback is enabled.
+    /// They occupy the stable row indices immediately prior to the
+    /// first entry in `lines`.
+    cold: ColdScrollback,
+
     /// Whenever we scroll a line off the top of the scrollback, we
     /// increment this.  We use this offset to translate between
     /// PhysRowIndex and StableRowIndex.
@@ -78,6 +85,7 @@ impl Screen {
 
         Screen {
             lines,
+            cold: ColdScrollback::new(physical_cols),
             config: Arc::clone(config),
             allow_scrollback,
             physical_rows,
@@ -97,6 +105,14 @@ impl Screen {
         scrollback_size(&self.config, self.allow_scrollback)
     }
 
+    fn cold_scrollback_size(&self) -> usize {
+        if self.allow_scrollback {
+            self.config.cold_scrollback_size()
+        } else {
+            0
+        }
+    }
+
     fn rewrap_lines(
         &mut self,
         physical_cols: usize,
@@ -215,7 +231,7 @@ impl Screen {
         // pre-prune blank lines that range from the cursor position to the end of the display;
         // this avoids growing the scrollback size when rapidly switching between normal and
         // maximized states.
-        let cursor_phys = self.phys_row(cursor.y);
+        let mut cursor_phys = self.phys_row(cursor.y);
         for _ in cursor_phys + 1..self.lines.len() {
             if self.lines.back().map(Line::is_whitespace).unwrap_or(false) {
                 self.lines.pop_back();
@@ -231,6 +247,16 @@ impl Screen {
             // screen (hence the check for allow_scrollback), to avoid
             // conflicting screen updates with full screen apps.
             if self.allow_scrollback {
+                // A logical line that starts in the cold tier of the
+                // scrollback is rewrapped along with its continuation in
+                // the hot lines, and the rest of the cold tier on its own
+                let carried = self.cold.take_trailing_wrapped();
+                cursor_phys += carried.len();
+                for line in carried.into_iter().rev() {
+                    self.lines.push_front(line);
+                }
+                self.cold.rewrap(physical_cols, seqno, &*self.config);
+
                 self.rewrap_lines(physical_cols, physical_rows, cursor.x, cursor_phys, seqno)
             } else {
                 for line in &mut self.lines {
@@ -336,6 +362,20 @@ impl Screen {
         self.lines.len()
     }
 
+    /// Returns the number of rows held in the cold tier of the scrollback.
+    /// These are not included in `scrollback_rows` and cannot be
+    /// addressed by PhysRowIndex; use the stable row index based
+    /// accessors to read them.
+    pub fn cold_scrollback_rows(&self) -> usize {
+        self.cold.len()
+    }
+
+    /// Returns the StableRowIndex of the earliest row that we remember,
+    /// including those in the cold tier of the scrollback.
+    pub fn scrollback_top(&self) -> StableRowIndex {
+        self.phys_to_stable_row_index(0) - self.cold.len() as StableRowIndex
+    }
+
     /// Sets a line dirty.  The line is relative to the visible origin.
     #[inline]
     pub fn dirty_line(&mut self, idx: VisibleRowIndex, seqno: SequenceNo) {
@@ -698,6 +738,10 @@ impl Screen {
             phys_scroll.start
         };
 
+        // Lines that fall off the top of the scrollback are moved into
+        // the cold tier, if it is enabled
+        let retain_cold = remove_idx == 0 && scrollback_ok && self.cold_scrollback_size() > 0;
+
         let default_blank = CellAttributes::blank();
         // To avoid thrashing the heap, prefer to move lines that were
         // scrolled off the top and re-use them at the bottom.
@@ -705,6 +749,10 @@ impl Screen {
         let (to_remove, to_add) = {
             for _ in 0..to_move {
                 let mut line = self.lines.remove(remove_idx).unwrap();
+                if retain_cold {
+                    let evicted = std::mem::replace(&mut line, Line::new(seqno));
+                    self.cold.push(evicted, &*self.config);
+                }
                 let line = if default_blank == blank_attr {
                     Line::new(seqno)
                 } else {
@@ -726,7 +774,11 @@ impl Screen {
 
         // Perform the removal
         for _ in 0..to_remove {
-            self.lines.remove(remove_idx);
+            if let Some(line) = self.lines.remove(remove_idx) {
+                if retain_cold {
+                    self.cold.push(line, &*self.config);
+                }
+            }
         }
 
         if remove_idx == 0 && scrollback_ok {
@@ -768,6 +820,7 @@ impl Screen {
                 self.stable_row_index_offset += 1;
             }
         }
+        self.cold.clear();
     }
 
     /// ```text
@@ -906,6 +959,200 @@ impl Screen {
             .collect()
     }
 
+    /// Clamps a stable range that extends into the cold tier of the
+    /// scrollback so that it starts no earlier than the earliest row
+    /// that we remember, preserving its length in the same way that
+    /// `stable_range` does for the hot rows.
+    /// Returns the portion of the range that is cold, expressed relative
+    /// to the start of the cold tier, along with the remaining hot range.
+    /// Returns None if the range doesn't touch the cold tier.
+    fn split_cold_range(
+        &self,
+        range: &Range<StableRowIndex>,
+    ) -> Option<(Range<usize>, Range<StableRowIndex>)> {
+        let hot_top = self.phys_to_stable_row_index(0);
+        if self.cold.is_empty() || range.start >= hot_top {
+            return None;
+        }
+        let cold_top = self.scrollback_top();
+        let range = if range.start < cold_top {
+            cold_top..cold_top + (range.end - range.start)
+        } else {
+            range.clone()
+        };
+        let cold_range =
+            (range.start - cold_top) as usize..(range.end.min(hot_top) - cold_top) as usize;
+        Some((cold_range, hot_top..range.end.max(hot_top)))
+    }
+
+    /// Returns copies of the lines in the specified stable range,
+    /// including any that are held in the cold tier of the scrollback,
+    /// along with the StableRowIndex of the first of them.
+    /// The range is clamped in the same way as `stable_range`.
+    pub fn lines_in_stable_range(
+        &self,
+        range: &Range<StableRowIndex>,
+    ) -> (StableRowIndex, Vec<Line>) {
+        match self.split_cold_range(range) {
+            None => {
+                let phys_range = self.stable_range(range);
+                (
+                    self.phys_to_stable_row_index(phys_range.start),
+                    self.lines_in_phys_range(phys_range),
+                )
+            }
+            Some((cold_range, hot_range)) => {
+                let first = self.scrollback_top() + cold_range.start as StableRowIndex;
+                let mut lines = self.cold.lines_in_range(cold_range);
+                if hot_range.end > hot_range.start {
+                    lines.extend(self.lines_in_phys_range(self.stable_range(&hot_range)));
+                }
+                (first, lines)
+            }
+        }
+    }
+
+    /// Calls `func` with the lines in the specified stable range, including
+    /// any that are held in the cold tier of the scrollback, along with the
+    /// StableRowIndex of the first of them.
+    pub fn with_stable_lines<F>(&self, range: &Range<StableRowIndex>, mut func: F)
+    where
+        F: FnMut(StableRowIndex, &[&Line]),
+    {
+        match self.split_cold_range(range) {
+            None => {
+                let phys_range = self.stable_range(range);
+                let first = self.phys_to_stable_row_index(phys_range.start);
+                self.with_phys_lines(phys_range, |lines| func(first, lines));
+            }
+            Some((cold_range, hot_range)) => {
+                let first = self.scrollback_top() + cold_range.start as StableRowIndex;
+                let cold_lines = self.cold.lines_in_range(cold_range);
+                let mut lines: Vec<&Line> = cold_lines.iter().collect();
+                if hot_range.end > hot_range.start {
+                    let phys_range = self.stable_range(&hot_range);
+                    lines.extend(
+                        self.lines
+                            .iter()
+                            .skip(phys_range.start)
+                            .take(phys_range.end - phys_range.start),
+                    );
+                }
+                func(first, &lines);
+            }
+        }
+    }
+
+    /// Calls `func` with mutable references to the lines in the specified
+    /// stable range.  Lines that are held in the cold tier of the
+    /// scrollback are passed as temporary copies, which are written
+    /// back to the cold tier if they were changed.
+    pub fn with_stable_lines_mut<F>(&mut self, range: &Range<StableRowIndex>, mut func: F)
+    where
+        F: FnMut(StableRowIndex, &mut [&mut Line]),
+    {
+        match self.split_cold_range(range) {
+            None => {
+                let phys_range = self.stable_range(range);
+                let first = self.phys_to_stable_row_index(phys_range.start);
+                self.with_phys_lines_mut(phys_range, |lines| func(first, lines));
+            }
+            Some((cold_range, hot_range)) => {
+                let first = self.scrollback_top() + cold_range.start as StableRowIndex;
+                let mut cold_lines = self.cold.lines_in_range(cold_range.clone());
+                let phys_range = if hot_range.end > hot_range.start {
+                    self.stable_range(&hot_range)
+                } else {
+                    0..0
+                };
+                {
+                    let mut lines: Vec<&mut Line> = cold_lines.iter_mut().collect();
+                    lines.extend(
+                        self.lines
+                            .iter_mut()
+                            .skip(phys_range.start)
+                            .take(phys_range.end - phys_range.start),
+                    );
+                    func(first, &mut lines);
+                }
+                self.cold
+                    .write_back(cold_range.start, &cold_lines, &*self.config);
+            }
+        }
+    }
+
+    /// If a logical line starts in the cold tier of the scrollback and
+    /// continues into the hot lines, returns the index of its first cold
+    /// line, relative to the start of the cold tier, along with the
+    /// number of hot lines that belong to it.
+    fn straddling_logical_line(&self) -> Option<(usize, usize)> {
+        let cold_start = self.cold.trailing_wrapped_start()?;
+        let mut num_hot = 0;
+        for line in &self.lines {
+            num_hot += 1;
+            if !line.last_cell_was_wrapped() {
+                break;
+            }
+        }
+        Some((cold_start, num_hot))
+    }
+
+    /// Calls `f` with each of the logical lines held entirely in the
+    /// cold tier of the scrollback that intersect `stable_range`.
+    /// `straddle_start` is the start of the logical line that continues
+    /// into the hot lines, if any; that line is left to the caller.
+    /// Returns false if `f` asked to stop the iteration.
+    fn for_each_cold_logical_line<F>(
+        &self,
+        stable_range: &Range<StableRowIndex>,
+        straddle_start: Option<usize>,
+        mut f: F,
+    ) -> bool
+    where
+        F: FnMut(Range<StableRowIndex>, Vec<Line>) -> bool,
+    {
+        let cold_range = match self.split_cold_range(stable_range) {
+            Some((cold_range, _)) => cold_range,
+            None => return true,
+        };
+        let cold_top = self.scrollback_top();
+        let start = self.cold.wrapped_run_start(cold_range.start);
+        let end = straddle_start.unwrap_or_else(|| self.cold.len());
+
+        let mut logical_line: Vec<Line> = vec![];
+        let mut logical_start = start;
+        let mut continue_iteration = true;
+
+        self.cold.for_each_run_in_range(start..end, |first, lines| {
+            for (idx, line) in lines.iter().enumerate() {
+                if logical_line.is_empty() {
+                    logical_start = first + idx;
+                    if logical_start >= cold_range.end {
+                        return false;
+                    }
+                }
+                logical_line.push(line.clone());
+                if !line.last_cell_was_wrapped() {
+                    let start = cold_top + logical_start as StableRowIndex;
+                    let range = start..start + logical_line.len() as StableRowIndex;
+                    if !f(range, std::mem::take(&mut logical_line)) {
+                        continue_iteration = false;
+                        return false;
+                    }
+                }
+            }
+            true
+        });
+
+        if continue_iteration && !logical_line.is_empty() && logical_start < cold_range.end {
+            let start = cold_top + logical_start as StableRowIndex;
+            let range = start..start + logical_line.len() as StableRowIndex;
+            continue_iteration = f(range, logical_line);
+        }
+
+        continue_iteration
+    }
+
     pub fn get_changed_stable_rows(
         &self,
         stable_lines: Range<StableRowIndex>,
@@ -995,7 +1242,58 @@ impl Screen {
     ) where
         F: FnMut(Range<StableRowIndex>, &mut [&mut Line]) -> bool,
     {
+        // Cold lines are passed as copies; the ones that were
+        // changed are written back afterwards
+        let straddle = self.straddling_logical_line();
+        let cold_top = self.scrollback_top();
+        let mut changed = vec![];
+        let continue_iteration = self.for_each_cold_logical_line(
+            &stable_range,
+            straddle.map(|(cold_start, _)| cold_start),
+            |range, mut lines| {
+                let original = lines.clone();
+                let continue_iteration = {
+                    let mut lines: Vec<&mut Line> = lines.iter_mut().collect();
+                    f(range.clone(), &mut lines)
+                };
+                if lines != original {
+                    changed.push(((range.start - cold_top) as usize, lines));
+                }
+                continue_iteration
+            },
+        );
+        for (first, lines) in changed {
+            self.cold.write_back(first, &lines, &*self.config);
+        }
+        if !continue_iteration {
+            return;
+        }
+
+        let hot_top = self.phys_to_stable_row_index(0);
+        let mut first_hot_row = 0;
+        if let Some((cold_start, num_hot)) = straddle {
+            let range =
+                cold_top + cold_start as StableRowIndex..hot_top + num_hot as StableRowIndex;
+            if range.start < stable_range.end && range.end > stable_range.start {
+                let mut cold_lines = self.cold.lines_in_range(cold_start..self.cold.len());
+                let continue_iteration = {
+                    let mut lines: Vec<&mut Line> = cold_lines.iter_mut().collect();
+                    lines.extend(self.lines.iter_mut().take(num_hot));
+                    f(range, &mut lines)
+                };
+                self.cold.write_back(cold_start, &cold_lines, &*self.config);
+                if !continue_iteration {
+                    return;
+                }
+            }
+            first_hot_row = num_hot;
+        }
+        if !self.cold.is_empty() && stable_range.end <= hot_top {
+            return;
+        }
+
         let mut phys_range = self.stable_range(&stable_range);
+        phys_range.start = phys_range.start.max(first_hot_row);
 
         // Avoid pathological cases where we have eg: a really long logical line
         // (such as 1.5MB of json) that we previously wrapped.  We don't want to
@@ -1005,7 +1303,7 @@ impl Screen {
 
         // Look backwards to find the start of the first logical line
         let mut back_len = 0;
-        while phys_range.start > 0 {
+        while phys_range.start > first_hot_row {
             let prior = &mut self.lines[phys_range.start - 1];
             if !prior.last_cell_was_wrapped() {
                 break;
@@ -1074,7 +1372,40 @@ impl Screen {
     ) where
         F: FnMut(Range<StableRowIndex>, &[&Line]) -> bool,
     {
+        let straddle = self.straddling_logical_line();
+        let continue_iteration = self.for_each_cold_logical_line(
+            &stable_range,
+            straddle.map(|(cold_start, _)| cold_start),
+            |range, lines| {
+                let lines: Vec<&Line> = lines.iter().collect();
+                f(range, &lines)
+            },
+        );
+        if !continue_iteration {
+            return;
+        }
+
+        let hot_top = self.phys_to_stable_row_index(0);
+        let mut first_hot_row = 0;
+        if let Some((cold_start, num_hot)) = straddle {
+            let start = self.scrollback_top() + cold_start as StableRowIndex;
+            let range = start..hot_top + num_hot as StableRowIndex;
+            if range.start < stable_range.end && range.end > stable_range.start {
+                let cold_lines = self.cold.lines_in_range(cold_start..self.cold.len());
+                let mut lines: Vec<&Line> = cold_lines.iter().collect();
+                lines.extend(self.lines.iter().take(num_hot));
+                if !f(range, &lines) {
+                    return;
+                }
+            }
+            first_hot_row = num_hot;
+        }
+        if !self.cold.is_empty() && stable_range.end <= hot_top {
+            return;
+        }
+
         let mut phys_range = self.stable_range(&stable_range);
+        phys_range.start = phys_range.start.max(first_hot_row);
 
         // Avoid pathological cases where we have eg: a really long logical line
         // (such as 1.5MB of json) that we previously wrapped.  We don't want to
@@ -1084,7 +1415,7 @@ impl Screen {
 
         // Look backwards to find the start of the first logical line
         let mut back_len = 0;
-        while phys_range.start > 0 {
+        while phys_range.start > first_hot_row {
             let prior = &self.lines[phys_range.start - 1];
             if !prior.last_cell_was_wrapped() {
                 break;
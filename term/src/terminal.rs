@@ -71,6 +71,8 @@ pub enum Alert {
     OutputSinceFocusLost,
     /// A change to the progress bar state
     Progress(Progress),
+    /// A command block was started or finished via OSC 133
+    CommandBlocksChanged,
 }
 
 pub trait AlertHandler: Send + Sync {
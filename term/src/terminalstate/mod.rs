@@ -42,6 +42,7 @@ lazy_static::lazy_static! {
     };
 }
 
+#[derive(Clone)]
 pub(crate) struct TabStop {
     tabs: Vec<bool>,
     tab_width: usize,
@@ -141,6 +142,7 @@ pub(crate) struct SavedCursor {
     // TODO: selective_erase when supported
 }
 
+#[derive(Clone)]
 struct ScreenOrAlt {
     /// The primary screen + scrollback
     screen: Screen,
@@ -243,6 +245,38 @@ impl ScreenOrAlt {
     }
 }
 
+/// A copy of the display state of a terminal, as captured by
+/// `TerminalState::snapshot`
+#[derive(Clone)]
+pub struct TerminalSnapshot {
+    screen: ScreenOrAlt,
+    pen: CellAttributes,
+    cursor: CursorPosition,
+    wrap_next: bool,
+    insert: bool,
+    dec_auto_wrap: bool,
+    reverse_wraparound_mode: bool,
+    reverse_video_mode: bool,
+    dec_origin_mode: bool,
+    top_and_bottom_margins: Range<VisibleRowIndex>,
+    left_and_right_margins: Range<usize>,
+    left_and_right_margin_mode: bool,
+    cursor_visible: bool,
+    g0_charset: CharSet,
+    g1_charset: CharSet,
+    shift_out: bool,
+    newline_mode: bool,
+    tabs: TabStop,
+    title: String,
+    icon_title: Option<String>,
+    progress: Progress,
+    palette: Option<ColorPalette>,
+    current_dir: Option<Url>,
+    command_blocks: Vec<CommandBlock>,
+    user_vars: HashMap<String, String>,
+    unicode_version: UnicodeVersion,
+}
+
 /// Manages the state for the terminal
 pub struct TerminalState {
     config: Arc<dyn TerminalConfiguration>,
@@ -350,6 +384,10 @@ pub struct TerminalState {
 
     current_dir: Option<Url>,
 
+    /// The commands delimited by OSC 133 semantic prompt markers,
+    /// ordered by prompt row
+    command_blocks: Vec<CommandBlock>,
+
     term_program: String,
     term_version: String,
 
@@ -400,6 +438,13 @@ struct UnicodeVersionStackEntry {
     label: Option<String>,
 }
 
+fn unix_time_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
 fn default_color_map() -> HashMap<u16, RgbColor> {
     let mut color_map = HashMap::new();
     // Match colors to the VT340 color table:
@@ -563,6 +608,7 @@ impl TerminalState {
             alert_handler: None,
             download_handler: None,
             current_dir: None,
+            command_blocks: vec![],
             term_program: term_program.to_string(),
             term_version: term_version.to_string(),
             writer,
@@ -744,6 +790,86 @@ impl TerminalState {
         self.screen_mut().erase_scrollback();
     }
 
+    /// Captures a copy of the screen contents and of the state that
+    /// affects how subsequent output is interpreted, so that it can
+    /// later be reinstated via `restore_snapshot`.
+    /// The input related modes, handlers and images held only by
+    /// the kitty image state are not included.
+    pub fn snapshot(&self) -> TerminalSnapshot {
+        TerminalSnapshot {
+            screen: self.screen.clone(),
+            pen: self.pen.clone(),
+            cursor: self.cursor,
+            wrap_next: self.wrap_next,
+            insert: self.insert,
+            dec_auto_wrap: self.dec_auto_wrap,
+            reverse_wraparound_mode: self.reverse_wraparound_mode,
+            reverse_video_mode: self.reverse_video_mode,
+            dec_origin_mode: self.dec_origin_mode,
+            top_and_bottom_margins: self.top_and_bottom_margins.clone(),
+            left_and_right_margins: self.left_and_right_margins.clone(),
+            left_and_right_margin_mode: self.left_and_right_margin_mode,
+            cursor_visible: self.cursor_visible,
+            g0_charset: self.g0_charset,
+            g1_charset: self.g1_charset,
+            shift_out: self.shift_out,
+            newline_mode: self.newline_mode,
+            tabs: self.tabs.clone(),
+            title: self.title.clone(),
+            icon_title: self.icon_title.clone(),
+            progress: self.progress.clone(),
+            palette: self.palette.clone(),
+            current_dir: self.current_dir.clone(),
+            command_blocks: self.command_blocks.clone(),
+            user_vars: self.user_vars.clone(),
+            unicode_version: self.unicode_version.clone(),
+        }
+    }
+
+    /// Reinstates a snapshot previously captured by `snapshot`.
+    /// All lines are marked as changed so that the display is
+    /// fully invalidated.
+    pub fn restore_snapshot(&mut self, snapshot: &TerminalSnapshot) {
+        let snapshot = snapshot.clone();
+        self.increment_seqno();
+        self.screen = snapshot.screen;
+        self.pen = snapshot.pen;
+        self.cursor = snapshot.cursor;
+        self.cursor.seqno = self.seqno;
+        self.wrap_next = snapshot.wrap_next;
+        self.insert = snapshot.insert;
+        self.dec_auto_wrap = snapshot.dec_auto_wrap;
+        self.reverse_wraparound_mode = snapshot.reverse_wraparound_mode;
+        self.reverse_video_mode = snapshot.reverse_video_mode;
+        self.dec_origin_mode = snapshot.dec_origin_mode;
+        self.top_and_bottom_margins = snapshot.top_and_bottom_margins;
+        self.left_and_right_margins = snapshot.left_and_right_margins;
+        self.left_and_right_margin_mode = snapshot.left_and_right_margin_mode;
+        self.cursor_visible = snapshot.cursor_visible;
+        self.g0_charset = snapshot.g0_charset;
+        self.g1_charset = snapshot.g1_charset;
+        self.shift_out = snapshot.shift_out;
+        self.newline_mode = snapshot.newline_mode;
+        self.tabs = snapshot.tabs;
+        self.title = snapshot.title;
+        self.icon_title = snapshot.icon_title;
+        self.progress = snapshot.progress;
+        self.palette = snapshot.palette;
+        self.current_dir = snapshot.current_dir;
+        self.command_blocks = snapshot.command_blocks;
+        self.user_vars = snapshot.user_vars;
+        self.unicode_version = snapshot.unicode_version;
+        self.accumulating_title.take();
+
+        let seqno = self.seqno;
+        self.screen.screen.for_each_phys_line_mut(|_, line| {
+            line.update_last_change_seqno(seqno);
+        });
+        self.screen.alt_screen.for_each_phys_line_mut(|_, line| {
+            line.update_last_change_seqno(seqno);
+        });
+    }
+
     /// Returns true if the associated application has enabled any of the
     /// supported mouse reporting modes.
     /// This is useful for the hosting GUI application to decide how best
@@ -2748,6 +2874,101 @@ impl TerminalState {
         Ok(zones)
     }
 
+    /// Returns the set of command blocks whose prompt is still present
+    /// in the scrollback of the primary screen.
+    /// Command blocks are recorded from the OSC 133 `A`, `C` and `D`
+    /// markers emitted by the shell integration.
+    pub fn get_command_blocks(&self) -> Vec<CommandBlock> {
+        let first_row = self.screen.screen.phys_to_stable_row_index(0);
+        self.command_blocks
+            .iter()
+            .filter(|block| block.prompt_y >= first_row)
+            .cloned()
+            .collect()
+    }
+
+    fn cursor_stable_row(&self) -> StableRowIndex {
+        self.screen().visible_row_to_stable_row(self.cursor.y)
+    }
+
+    fn notify_command_blocks_changed(&mut self) {
+        if let Some(handler) = self.alert_handler.as_mut() {
+            handler.alert(Alert::CommandBlocksChanged);
+        }
+    }
+
+    /// Called when the shell starts drawing a new prompt
+    pub(crate) fn command_block_prompt_started(&mut self) {
+        if self.screen.alt_screen_is_active {
+            return;
+        }
+        let row = self.cursor_stable_row();
+        let now = unix_time_millis();
+
+        // A command that never reported its status is implicitly
+        // finished by the arrival of the next prompt
+        if let Some(last) = self.command_blocks.last_mut() {
+            if last.output_start_y.is_some() && last.end_y.is_none() {
+                last.end_y.replace(row);
+                last.end_time_ms.replace(now);
+            }
+        }
+
+        // Discard blocks that have scrolled out of the scrollback, as well
+        // as any that are being overwritten by this prompt, such as when
+        // the shell redraws its prompt or the screen has been cleared
+        let first_row = self.screen.screen.phys_to_stable_row_index(0);
+        self.command_blocks
+            .retain(|block| block.prompt_y >= first_row && block.prompt_y < row);
+
+        self.command_blocks.push(CommandBlock {
+            prompt_y: row,
+            output_start_y: None,
+            end_y: None,
+            exit_status: None,
+            cwd: self.current_dir.as_ref().map(|url| url.to_string()),
+            start_time_ms: None,
+            end_time_ms: None,
+        });
+        self.notify_command_blocks_changed();
+    }
+
+    /// Called when the user has submitted a command and its output begins
+    pub(crate) fn command_block_output_started(&mut self) {
+        if self.screen.alt_screen_is_active {
+            return;
+        }
+        let row = self.cursor_stable_row();
+        if let Some(last) = self.command_blocks.last_mut() {
+            if last.output_start_y.is_none() {
+                last.output_start_y.replace(row);
+                last.start_time_ms.replace(unix_time_millis());
+                self.notify_command_blocks_changed();
+            }
+        }
+    }
+
+    /// Called when the shell reports the exit status of the command
+    pub(crate) fn command_block_finished(&mut self, status: i32) {
+        if self.screen.alt_screen_is_active {
+            return;
+        }
+        let row = self.cursor_stable_row();
+        if let Some(last) = self.command_blocks.last_mut() {
+            if last.exit_status.is_none() {
+                // Some shells report a status for an empty command line;
+                // treat the prompt row as both the start and end of output
+                let start = *last.output_start_y.get_or_insert(row);
+                last.end_y.replace(row.max(start));
+                last.exit_status.replace(status);
+                let now = unix_time_millis();
+                last.start_time_ms.get_or_insert(now);
+                last.end_time_ms.replace(now);
+                self.notify_command_blocks_changed();
+            }
+        }
+    }
+
     #[inline]
     pub fn get_reverse_video(&self) -> bool {
         self.reverse_video_mode
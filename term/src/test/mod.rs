@@ -9,6 +9,7 @@ mod csi;
 // mod selection; FIXME: port to render layer
 use crate::color::ColorPalette;
 use k9::assert_equal as assert_eq;
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use wezterm_escape_parser::csi::{Edit, EraseInDisplay, EraseInLine};
 use wezterm_escape_parser::{OneBased, OperatingSystemCommand, CSI};
@@ -45,12 +46,22 @@ struct TestTerm {
 #[derive(Debug)]
 struct TestTermConfig {
     scrollback: usize,
+    cold_scrollback: usize,
+    spill_directory: Option<PathBuf>,
 }
 impl TerminalConfiguration for TestTermConfig {
     fn scrollback_size(&self) -> usize {
         self.scrollback
     }
 
+    fn cold_scrollback_size(&self) -> usize {
+        self.cold_scrollback
+    }
+
+    fn scrollback_spill_directory(&self) -> Option<PathBuf> {
+        self.spill_directory.clone()
+    }
+
     fn color_palette(&self) -> ColorPalette {
         ColorPalette::default()
     }
@@ -58,6 +69,27 @@ impl TerminalConfiguration for TestTermConfig {
 
 impl TestTerm {
     fn new(height: usize, width: usize, scrollback: usize) -> Self {
+        Self::with_cold_scrollback(height, width, scrollback, 0)
+    }
+
+    fn with_cold_scrollback(
+        height: usize,
+        width: usize,
+        scrollback: usize,
+        cold_scrollback: usize,
+    ) -> Self {
+        Self::with_config(
+            height,
+            width,
+            TestTermConfig {
+                scrollback,
+                cold_scrollback,
+                spill_directory: None,
+            },
+        )
+    }
+
+    fn with_config(height: usize, width: usize, config: TestTermConfig) -> Self {
         let _ = env_logger::Builder::new()
             .is_test(true)
             .filter_level(log::LevelFilter::Trace)
@@ -71,7 +103,7 @@ impl TestTerm {
                 pixel_height: height * 16,
                 dpi: 0,
             },
-            Arc::new(TestTermConfig { scrollback }),
+            Arc::new(config),
             "WezTerm",
             "O_o",
             Box::new(Vec::new()),
@@ -479,6 +511,51 @@ fn test_semantic() {
     );
 }
 
+#[test]
+fn test_command_blocks() {
+    use wezterm_escape_parser::osc::FinalTermSemanticPrompt;
+    let mut term = TestTerm::new(10, 20, 0);
+
+    let prompt = OperatingSystemCommand::FinalTermSemanticPrompt(
+        FinalTermSemanticPrompt::FreshLineAndStartPrompt {
+            aid: None,
+            cl: None,
+        },
+    );
+    let output = OperatingSystemCommand::FinalTermSemanticPrompt(
+        FinalTermSemanticPrompt::MarkEndOfInputAndStartOfOutput { aid: None },
+    );
+    let status = |status| {
+        OperatingSystemCommand::FinalTermSemanticPrompt(FinalTermSemanticPrompt::CommandStatus {
+            status,
+            aid: None,
+        })
+    };
+
+    term.print(format!("{prompt}$ true\r\n{output}"));
+    term.print(format!("{}", status(0)));
+    term.print(format!("{prompt}$ false\r\n{output}oops\r\n"));
+    term.print(format!("{}", status(1)));
+    term.print(format!("{prompt}$ sleep 10\r\n{output}"));
+
+    let blocks = term.get_command_blocks();
+    assert_eq!(blocks.len(), 3);
+
+    assert_eq!(blocks[0].prompt_y, 0);
+    assert_eq!(blocks[0].output_start_y, Some(1));
+    assert_eq!(blocks[0].status(), CommandBlockStatus::Succeeded);
+
+    assert_eq!(blocks[1].prompt_y, 1);
+    assert_eq!(blocks[1].output_start_y, Some(2));
+    assert_eq!(blocks[1].end_y, Some(3));
+    assert_eq!(blocks[1].status(), CommandBlockStatus::Failed(1));
+    assert_eq!(blocks[1].output_range(10), Some(2..3));
+
+    assert_eq!(blocks[2].prompt_y, 3);
+    assert_eq!(blocks[2].status(), CommandBlockStatus::Running);
+    assert_eq!(blocks[2].output_range(10), Some(4..10));
+}
+
 #[test]
 fn issue_1161() {
     let mut term = TestTerm::new(1, 5, 0);
@@ -494,6 +571,272 @@ fn issue_1161() {
     );
 }
 
+#[test]
+fn test_cold_scrollback() {
+    let mut term = TestTerm::with_cold_scrollback(3, 20, 2, 2000);
+    for i in 0..3000 {
+        term.print(format!("line {i}\r\n"));
+    }
+
+    let screen = term.screen();
+    // Whole blocks are discarded, so we may retain a little more than
+    // the configured cold capacity
+    let cold_rows = screen.cold_scrollback_rows();
+    assert!(cold_rows >= 2000, "cold_rows={cold_rows}");
+    assert_eq!(screen.scrollback_rows(), 5);
+
+    // The cold rows are contiguous with the hot rows
+    let top = screen.scrollback_top();
+    let first_line = 3000 + 1 - (cold_rows + 5);
+    let (first, lines) = screen.lines_in_stable_range(&(top..top + 7 + cold_rows as isize));
+    assert_eq!(first, top);
+    assert_eq!(lines.len(), cold_rows + 5);
+    assert_eq!(lines[0].as_str().trim_end(), format!("line {first_line}"));
+    assert_eq!(
+        lines[cold_rows].as_str().trim_end(),
+        format!("line {}", first_line + cold_rows)
+    );
+
+    let mut found = None;
+    screen.for_each_logical_line_in_stable_range(top..top + cold_rows as isize, |range, lines| {
+        if lines[0].as_str().trim_end() == "line 2500" {
+            found.replace(range.start);
+            return false;
+        }
+        true
+    });
+    assert_eq!(found, Some(top + (2500 - first_line) as isize));
+
+    term.erase_scrollback();
+    assert_eq!(term.screen().cold_scrollback_rows(), 0);
+}
+
+/// Returns the text of all of the rows that the screen remembers,
+/// including those in the cold tier of the scrollback
+fn all_stable_lines(term: &TestTerm) -> Vec<String> {
+    let screen = term.screen();
+    let top = screen.scrollback_top();
+    let len = screen.cold_scrollback_rows() + screen.scrollback_rows();
+    let (_, lines) = screen.lines_in_stable_range(&(top..top + len as StableRowIndex));
+    lines
+        .iter()
+        .map(|line| line.as_str().trim_end().to_string())
+        .collect()
+}
+
+#[test]
+fn test_cold_scrollback_write_back() {
+    let mut term = TestTerm::with_cold_scrollback(3, 20, 2, 2000);
+    for i in 0..100 {
+        term.print(format!("line {i}\r\n"));
+    }
+    let top = term.screen().scrollback_top();
+    assert!(term.screen().cold_scrollback_rows() > 10);
+
+    let seqno = term.current_seqno();
+    term.screen_mut()
+        .with_stable_lines_mut(&(top + 1..top + 2), |_, lines| {
+            lines[0].set_cell(0, Cell::new('L', CellAttributes::default()), seqno);
+        });
+    term.screen_mut()
+        .for_each_logical_line_in_stable_range_mut(top + 2..top + 3, |_, lines| {
+            lines[0].set_cell(0, Cell::new('X', CellAttributes::default()), seqno);
+            false
+        });
+
+    let lines = all_stable_lines(&term);
+    assert_eq!(lines[0], "line 0");
+    assert_eq!(lines[1], "Line 1");
+    assert_eq!(lines[2], "Xine 2");
+    assert_eq!(lines[3], "line 3");
+}
+
+#[test]
+fn test_cold_scrollback_rewrap() {
+    let mut term = TestTerm::with_cold_scrollback(3, 20, 2, 2000);
+    for i in 0..100 {
+        term.print(format!("row {i:03} abcdefghij\r\n"));
+    }
+    let cold_rows = term.screen().cold_scrollback_rows();
+    assert!(cold_rows > 10);
+
+    let size = |cols| TerminalSize {
+        rows: 3,
+        cols,
+        pixel_width: 0,
+        pixel_height: 0,
+        dpi: 0,
+    };
+
+    term.resize(size(10));
+    assert_eq!(term.screen().cold_scrollback_rows(), cold_rows * 2);
+    let lines = all_stable_lines(&term);
+    assert_eq!(lines[0], "row 000 ab");
+    assert_eq!(lines[1], "cdefghij");
+    assert_eq!(lines[2], "row 001 ab");
+
+    term.resize(size(20));
+    assert_eq!(term.screen().cold_scrollback_rows(), cold_rows);
+    let lines = all_stable_lines(&term);
+    assert_eq!(lines[0], "row 000 abcdefghij");
+    assert_eq!(lines[1], "row 001 abcdefghij");
+}
+
+/// Returns the words of each non-blank logical line that the
+/// screen remembers
+fn all_logical_words(term: &TestTerm) -> Vec<Vec<String>> {
+    let screen = term.screen();
+    let top = screen.scrollback_top();
+    let len = screen.cold_scrollback_rows() + screen.scrollback_rows() + screen.physical_rows;
+    let mut result = vec![];
+    screen.for_each_logical_line_in_stable_range(top..top + len as StableRowIndex, |_, lines| {
+        let text: String = lines
+            .iter()
+            .map(|line| line.as_str().into_owned())
+            .collect();
+        let words: Vec<String> = text.split_whitespace().map(str::to_string).collect();
+        if !words.is_empty() {
+            result.push(words);
+        }
+        true
+    });
+    result
+}
+
+#[test]
+fn test_cold_scrollback_lazy_rewrap() {
+    let mut term = TestTerm::with_cold_scrollback(3, 20, 2, 5000);
+    for i in 0..2000 {
+        match i % 4 {
+            0 => term.print(format!("row {i} 日本語のテキスト\r\n")),
+            1 => term.print(format!("row {i} {}\r\n", "abcdefghij ".repeat(5))),
+            _ => term.print(format!("row {i}\r\n")),
+        }
+    }
+    let cold_rows = term.screen().cold_scrollback_rows();
+    assert!(cold_rows > 2048, "cold_rows={cold_rows}");
+    let words = all_logical_words(&term);
+
+    let size = |cols| TerminalSize {
+        rows: 3,
+        cols,
+        pixel_width: 0,
+        pixel_height: 0,
+        dpi: 0,
+    };
+
+    for cols in [7, 33, 20] {
+        term.resize(size(cols));
+        // The rows counted for blocks that have yet to be rewrapped
+        // match the rows they produce when they are read
+        let screen = term.screen();
+        let top = screen.scrollback_top();
+        let len = screen.cold_scrollback_rows() + screen.scrollback_rows();
+        let (_, lines) = screen.lines_in_stable_range(&(top..top + len as StableRowIndex));
+        assert_eq!(lines.len(), len);
+        // Rows as printed at the original width can overhang it by
+        // the wide cell that didn't fit, so only check the others
+        if cols != 20 {
+            for line in &lines {
+                assert!(
+                    line.len() <= cols,
+                    "{:?} is wider than {cols}",
+                    line.as_str()
+                );
+            }
+        }
+        // Narrowing may discard the oldest blocks to stay within capacity
+        let rewrapped = all_logical_words(&term);
+        assert!(rewrapped.len() > 1000);
+        assert!(words.ends_with(&rewrapped));
+    }
+}
+
+#[test]
+fn test_cold_scrollback_straddling_logical_line() {
+    let mut term = TestTerm::with_cold_scrollback(3, 10, 2, 2000);
+    for i in 0..50 {
+        term.print(format!("line {i}\r\n"));
+    }
+    // Spans 6 rows; the first 4 end up in the cold tier
+    // and the last 2 in the hot scrollback
+    term.print("x".repeat(60));
+    term.print("\r\nafter 1\r\nafter 2\r\n");
+
+    let screen = term.screen();
+    let hot_top = screen.phys_to_stable_row_index(0);
+    let hot_lines = screen.lines_in_phys_range(0..3);
+    assert_eq!(hot_lines[0].as_str(), "xxxxxxxxxx");
+    assert_eq!(hot_lines[2].as_str().trim_end(), "after 1");
+
+    let check_range = |range: Range<StableRowIndex>| {
+        let mut found = vec![];
+        screen.for_each_logical_line_in_stable_range(range, |range, lines| {
+            if lines[0].as_str().starts_with('x') {
+                found.push((range, lines.len()));
+            }
+            true
+        });
+        assert_eq!(found, vec![(hot_top - 4..hot_top + 2, 6)]);
+    };
+    // Starting in the cold tier, in the hot scrollback and spanning both
+    check_range(hot_top - 10..hot_top - 3);
+    check_range(hot_top + 1..hot_top + 2);
+    check_range(screen.scrollback_top()..hot_top + 5);
+
+    term.resize(TerminalSize {
+        rows: 3,
+        cols: 20,
+        pixel_width: 0,
+        pixel_height: 0,
+        dpi: 0,
+    });
+    let lines = all_stable_lines(&term);
+    let idx = lines.iter().position(|line| line.starts_with('x')).unwrap();
+    assert_eq!(lines[idx], "x".repeat(20));
+    assert_eq!(lines[idx + 1], "x".repeat(20));
+    assert_eq!(lines[idx + 2], "x".repeat(20));
+    assert_eq!(lines[idx + 3], "after 1");
+}
+
+#[cfg(feature = "use_serde")]
+#[test]
+fn test_cold_scrollback_spill() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut term = TestTerm::with_config(
+        3,
+        20,
+        TestTermConfig {
+            scrollback: 2,
+            cold_scrollback: 3000,
+            spill_directory: Some(dir.path().to_path_buf()),
+        },
+    );
+    for i in 0..5000 {
+        term.print(format!("line {i}\r\n"));
+    }
+    let before = all_stable_lines(&term);
+
+    // Changes to a clone don't affect the original
+    let copy = term.screen().clone();
+    term.erase_scrollback();
+    for i in 0..5000 {
+        term.print(format!("other {i}\r\n"));
+    }
+    let top = copy.scrollback_top();
+    let len = copy.cold_scrollback_rows() + copy.scrollback_rows();
+    let (_, lines) = copy.lines_in_stable_range(&(top..top + len as StableRowIndex));
+    let lines: Vec<String> = lines
+        .iter()
+        .map(|line| line.as_str().trim_end().to_string())
+        .collect();
+    assert_eq!(lines, before);
+
+    let lines = all_stable_lines(&term);
+    assert!(lines[0].starts_with("other "), "{}", lines[0]);
+    assert_eq!(lines[lines.len() - 2], "other 4999");
+}
+
 #[test]
 fn basic_output() {
     let mut term = TestTerm::new(5, 10, 0);
@@ -1373,3 +1716,27 @@ fn test_hyperlinks() {
         Compare::TEXT | Compare::ATTRS,
     );
 }
+
+#[test]
+fn test_snapshot_restore() {
+    let mut term = TestTerm::new(3, 5, 0);
+    term.print("hello\r\nthe");
+    let snapshot = term.snapshot();
+
+    // Switch to the alt screen and scribble over it
+    term.set_mode("?1049", true);
+    term.cup(0, 0);
+    term.print("gone");
+    assert_visible_contents(&term, file!(), line!(), &["gone", "", ""]);
+
+    let seqno = term.current_seqno();
+    term.restore_snapshot(&snapshot);
+    assert!(!term.is_alt_screen_active());
+    assert_visible_contents(&term, file!(), line!(), &["hello", "the", ""]);
+    term.assert_cursor_pos(3, 1, None, None);
+    term.assert_dirty_lines(seqno, &[0, 1, 2], None);
+
+    // Output continues from the restored state
+    term.print("!");
+    assert_visible_contents(&term, file!(), line!(), &["hello", "the!", ""]);
+}
@@ -11,7 +11,6 @@ use mux::client::ClientId;
 use mux::connui::ConnectionUI;
 use mux::domain::DomainId;
 use mux::pane::PaneId;
-use mux::ssh::ssh_connect_with_ui;
 use mux::Mux;
 use openssl::ssl::{SslConnector, SslFiletype, SslMethod};
 use openssl::x509::X509;
@@ -30,6 +29,7 @@ use std::os::unix::process::CommandExt;
 #[cfg(windows)]
 use std::os::windows::io::{AsRawSocket, AsSocket, BorrowedSocket, RawSocket};
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
 use thiserror::Error;
@@ -51,10 +51,14 @@ enum ReaderMessage {
     Readable,
 }
 
+/// Where `PaneOutput` PDUs are delivered, if anywhere
+type PaneOutputSink = Arc<Mutex<Option<Sender<PaneOutput>>>>;
+
 #[derive(Clone)]
 pub struct Client {
     sender: Sender<ReaderMessage>,
     local_domain_id: Option<DomainId>,
+    pane_output: PaneOutputSink,
     pub client_id: ClientId,
     client_domain_config: ClientDomainConfig,
     pub is_reconnectable: bool,
@@ -340,14 +344,21 @@ enum NotReconnectableError {
 fn client_thread(
     reconnectable: &mut Reconnectable,
     local_domain_id: Option<DomainId>,
+    pane_output: &PaneOutputSink,
     rx: &mut Receiver<ReaderMessage>,
 ) -> anyhow::Result<()> {
-    block_on(client_thread_async(reconnectable, local_domain_id, rx))
+    block_on(client_thread_async(
+        reconnectable,
+        local_domain_id,
+        pane_output,
+        rx,
+    ))
 }
 
 async fn client_thread_async(
     reconnectable: &mut Reconnectable,
     local_domain_id: Option<DomainId>,
+    pane_output: &PaneOutputSink,
     rx: &mut Receiver<ReaderMessage>,
 ) -> anyhow::Result<()> {
     let mut next_serial = 1u64;
@@ -401,7 +412,15 @@ async fn client_thread_async(
                             decoded.serial,
                             decoded.pdu.pdu_name()
                         );
-                        if decoded.serial == 0 {
+                        if let DecodedPdu {
+                            serial: 0,
+                            pdu: Pdu::PaneOutput(output),
+                        } = decoded
+                        {
+                            if let Some(tx) = pane_output.lock().unwrap().as_ref() {
+                                tx.try_send(output).ok();
+                            }
+                        } else if decoded.serial == 0 {
                             process_unilateral(local_domain_id, decoded)
                                 .context("processing unilateral PDU from server")
                                 .map_err(|e| {
@@ -682,7 +701,8 @@ impl Reconnectable {
     ) -> anyhow::Result<()> {
         let ssh_config = mux::ssh::ssh_domain_to_ssh_config(&ssh_dom)?;
 
-        let sess = ssh_connect_with_ui(ssh_config, ui)?;
+        let sess = mux::ssh_pool::connect_with_ui(ssh_config, &ssh_dom.name, ui)?;
+        mux::ssh::register_domain_session(&ssh_dom.name, &sess);
         let proxy_bin = Self::wezterm_bin_path(&ssh_dom.remote_wezterm_path);
 
         let cmd = if let Some(cmd) = ssh_dom.override_proxy_command.clone() {
@@ -877,7 +897,8 @@ impl Reconnectable {
                     ssh_config.insert("port".to_string(), port.to_string());
                 }
 
-                let sess = ssh_connect_with_ui(ssh_config, ui)?;
+                let sess =
+                    mux::ssh_pool::connect_with_ui(ssh_config, &ssh_params.host_and_port, ui)?;
 
                 let creds = ui.run_and_log_error(|| {
                     // The `tlscreds` command will start the server if needed and then
@@ -1044,6 +1065,9 @@ impl Client {
         let is_local = reconnectable.is_local();
         let (sender, mut receiver) = unbounded();
         let client_id = ClientId::new();
+        let pane_output: PaneOutputSink = Arc::new(Mutex::new(None));
+
+        let thread_pane_output = Arc::clone(&pane_output);
 
         thread::spawn(move || {
             const BASE_INTERVAL: Duration = Duration::from_secs(1);
@@ -1051,7 +1075,12 @@ impl Client {
 
             let mut backoff = BASE_INTERVAL;
             loop {
-                if let Err(e) = client_thread(&mut reconnectable, local_domain_id, &mut receiver) {
+                if let Err(e) = client_thread(
+                    &mut reconnectable,
+                    local_domain_id,
+                    &thread_pane_output,
+                    &mut receiver,
+                ) {
                     if !reconnectable.reconnectable() || local_domain_id.is_none() {
                         log::debug!("client thread ended: {}", e);
                         break;
@@ -1108,6 +1137,9 @@ impl Client {
                 }
             }
 
+            // Let anyone waiting on pane output know that no more is coming
+            thread_pane_output.lock().unwrap().take();
+
             async fn detach(local_domain_id: DomainId) -> anyhow::Result<()> {
                 if let Some(mux) = Mux::try_get() {
                     let client_domain = mux
@@ -1134,6 +1166,7 @@ impl Client {
         Self {
             sender,
             local_domain_id,
+            pane_output,
             is_reconnectable,
             is_local,
             client_id,
@@ -1219,6 +1252,15 @@ impl Client {
         self.local_domain_id
     }
 
+    /// Returns a receiver for the `PaneOutput` PDUs that are sent
+    /// by the server for subscriptions made via `subscribe_pane_output`.
+    /// Only the most recently returned receiver is fed.
+    pub fn pane_output_receiver(&self) -> Receiver<PaneOutput> {
+        let (tx, rx) = unbounded();
+        self.pane_output.lock().unwrap().replace(tx);
+        rx
+    }
+
     fn compute_unix_domain(
         prefer_mux: bool,
         class_name: &str,
@@ -1389,4 +1431,37 @@ impl Client {
         GetPaneDirectionResponse
     );
     rpc!(adjust_pane_size, AdjustPaneSize, UnitResponse);
+    rpc!(
+        get_command_blocks,
+        GetCommandBlocks,
+        GetCommandBlocksResponse
+    );
+    rpc!(
+        subscribe_pane_output,
+        SubscribePaneOutput,
+        SubscribePaneOutputResponse
+    );
+    rpc!(unsubscribe_pane_output, UnsubscribePaneOutput, UnitResponse);
+    rpc!(
+        get_semantic_zones,
+        GetSemanticZones,
+        GetSemanticZonesResponse
+    );
+    rpc!(
+        get_semantic_zone_text,
+        GetSemanticZoneText,
+        GetSemanticZoneTextResponse
+    );
+    rpc!(list_ssh_forwards, ListSshForwards, ListSshForwardsResponse);
+    rpc!(add_ssh_forward, AddSshForward, AddSshForwardResponse);
+    rpc!(remove_ssh_forward, RemoveSshForward, UnitResponse);
+    rpc!(save_session, SaveSession, SaveSessionResponse);
+    rpc!(restore_session, RestoreSession, RestoreSessionResponse);
+    rpc!(list_sessions, ListSessions, ListSessionsResponse);
+    rpc!(
+        set_pane_recording,
+        SetPaneRecording,
+        SetPaneRecordingResponse
+    );
+    rpc!(get_pane_palette, GetPanePalette, GetPanePaletteResponse);
 }
@@ -756,6 +756,7 @@ impl Domain for ClientDomain {
         _size: TerminalSize,
         _command: Option<CommandBuilder>,
         _command_dir: Option<String>,
+        _initial_output: Option<String>,
     ) -> anyhow::Result<Arc<dyn Pane>> {
         anyhow::bail!("spawn_pane not implemented for ClientDomain")
     }
@@ -819,6 +820,7 @@ impl Domain for ClientDomain {
         size: TerminalSize,
         command: Option<CommandBuilder>,
         command_dir: Option<String>,
+        _initial_output: Option<String>,
         window: WindowId,
     ) -> anyhow::Result<Arc<Tab>> {
         let inner = self
@@ -887,6 +889,7 @@ impl Domain for ClientDomain {
             SplitSource::Spawn {
                 command,
                 command_dir,
+                initial_output: _,
             } => (command, command_dir, None),
             SplitSource::MovePane(move_pane_id) => (None, None, Some(move_pane_id)),
         };
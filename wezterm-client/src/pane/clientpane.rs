@@ -11,8 +11,9 @@ use mux::pane::{
     alloc_pane_id, CachePolicy, CloseReason, ForEachPaneLogicalLine, LogicalLine, Pane, PaneId,
     Pattern, SearchResult, WithPaneLines,
 };
+use mux::recording::RecordingOptions;
 use mux::renderable::{RenderableDimensions, StableCursorPosition};
-use mux::tab::TabId;
+use mux::tab::{size_in_pane_cells, TabId};
 use mux::{Mux, MuxNotification};
 use parking_lot::{MappedMutexGuard, Mutex, MutexGuard};
 use rangeset::RangeSet;
@@ -27,8 +28,8 @@ use url::Url;
 use wezterm_dynamic::Value;
 use wezterm_term::color::ColorPalette;
 use wezterm_term::{
-    Alert, Clipboard, KeyCode, KeyModifiers, Line, MouseEvent, Progress, StableRowIndex,
-    TerminalConfiguration, TerminalSize,
+    Alert, Clipboard, CommandBlock, KeyCode, KeyModifiers, Line, MouseEvent, Progress,
+    StableRowIndex, TerminalConfiguration, TerminalSize,
 };
 
 pub struct ClientPane {
@@ -49,6 +50,9 @@ pub struct ClientPane {
     config: Mutex<Option<Arc<dyn TerminalConfiguration>>>,
     unseen_output: Mutex<bool>,
     progress: Mutex<Progress>,
+    command_blocks: Arc<Mutex<Vec<CommandBlock>>>,
+    /// Whether we asked the server to record this pane
+    recording: Mutex<bool>,
 }
 
 impl ClientPane {
@@ -113,6 +117,9 @@ impl ClientPane {
         })
         .detach();
 
+        let command_blocks = Arc::new(Mutex::new(vec![]));
+        Self::fetch_command_blocks(client, remote_pane_id, local_pane_id, &command_blocks);
+
         Self {
             client: Arc::clone(client),
             mouse,
@@ -131,9 +138,39 @@ impl ClientPane {
             user_vars: Mutex::new(HashMap::new()),
             config: Mutex::new(None),
             progress: Mutex::new(Progress::default()),
+            command_blocks,
+            recording: Mutex::new(false),
         }
     }
 
+    /// Asynchronously refreshes our copy of the command blocks from
+    /// the server, notifying the mux once they have been updated
+    fn fetch_command_blocks(
+        client: &Arc<ClientInner>,
+        remote_pane_id: PaneId,
+        local_pane_id: PaneId,
+        command_blocks: &Arc<Mutex<Vec<CommandBlock>>>,
+    ) {
+        let client = Arc::clone(client);
+        let command_blocks = Arc::clone(command_blocks);
+        promise::spawn::spawn(async move {
+            let response = client
+                .client
+                .get_command_blocks(GetCommandBlocks {
+                    pane_id: remote_pane_id,
+                })
+                .await?;
+            *command_blocks.lock() = response.blocks;
+            let mux = Mux::get();
+            mux.notify(MuxNotification::Alert {
+                pane_id: local_pane_id,
+                alert: Alert::CommandBlocksChanged,
+            });
+            Ok::<(), anyhow::Error>(())
+        })
+        .detach();
+    }
+
     pub async fn process_unilateral(&self, pdu: Pdu) -> anyhow::Result<()> {
         match pdu {
             Pdu::GetPaneRenderChangesResponse(mut delta) => {
@@ -199,6 +236,17 @@ impl ClientPane {
                             alert: Alert::Progress(progress.clone()),
                         });
                     }
+                    Alert::CommandBlocksChanged => {
+                        // Local subscribers are notified once the
+                        // updated blocks have been fetched
+                        Self::fetch_command_blocks(
+                            &self.client,
+                            self.remote_pane_id,
+                            self.local_pane_id,
+                            &self.command_blocks,
+                        );
+                        return Ok(());
+                    }
                     _ => {}
                 }
                 mux.notify(MuxNotification::Alert {
@@ -387,6 +435,17 @@ impl Pane for ClientPane {
     }
 
     fn resize(&self, size: TerminalSize) -> anyhow::Result<()> {
+        self.resize_in_tab(size, None)
+    }
+
+    fn resize_in_tab(
+        &self,
+        tab_size: TerminalSize,
+        cell_size: Option<TerminalSize>,
+    ) -> anyhow::Result<()> {
+        // The server lays out its copy of the tab in the cells of the
+        // tab, so it is told both the size in the tab and the cell size
+        let size = size_in_pane_cells(cell_size, tab_size);
         let render = self.renderable.lock();
         let mut inner = render.inner.borrow_mut();
 
@@ -415,7 +474,8 @@ impl Pane for ClientPane {
                     .resize(Resize {
                         containing_tab_id: remote_tab_id,
                         pane_id: remote_pane_id,
-                        size,
+                        size: tab_size,
+                        cell_size,
                     })
                     .await
             })
@@ -447,6 +507,64 @@ impl Pane for ClientPane {
         }
     }
 
+    async fn fetch_lines(
+        &self,
+        lines: Range<StableRowIndex>,
+    ) -> anyhow::Result<(StableRowIndex, Vec<Line>)> {
+        let first = lines.start;
+        let response = self
+            .client
+            .client
+            .get_lines(GetLines {
+                pane_id: self.remote_pane_id,
+                lines: vec![lines],
+            })
+            .await?;
+        let mut lines = hydrate_lines(
+            Arc::clone(&self.client),
+            self.remote_pane_id,
+            response.lines,
+        )
+        .await;
+        lines.sort_by_key(|(idx, _)| *idx);
+        let first = lines.first().map(|(idx, _)| *idx).unwrap_or(first);
+        Ok((first, lines.into_iter().map(|(_, line)| line).collect()))
+    }
+
+    async fn start_recording(&self, options: RecordingOptions) -> anyhow::Result<String> {
+        let response = self
+            .client
+            .client
+            .set_pane_recording(SetPaneRecording {
+                pane_id: self.remote_pane_id,
+                start: Some(options),
+            })
+            .await?;
+        *self.recording.lock() = true;
+        Mux::get().notify(MuxNotification::PaneRecordingChanged(self.local_pane_id));
+        response
+            .path
+            .ok_or_else(|| anyhow::anyhow!("server did not report the recording path"))
+    }
+
+    async fn stop_recording(&self) -> anyhow::Result<Option<String>> {
+        let response = self
+            .client
+            .client
+            .set_pane_recording(SetPaneRecording {
+                pane_id: self.remote_pane_id,
+                start: None,
+            })
+            .await?;
+        *self.recording.lock() = false;
+        Mux::get().notify(MuxNotification::PaneRecordingChanged(self.local_pane_id));
+        Ok(response.path)
+    }
+
+    fn is_recording(&self) -> bool {
+        *self.recording.lock()
+    }
+
     fn key_down(&self, key: KeyCode, mods: KeyModifiers) -> anyhow::Result<()> {
         let input_serial;
         {
@@ -550,6 +668,10 @@ impl Pane for ClientPane {
         false
     }
 
+    fn get_command_blocks(&self) -> anyhow::Result<Vec<CommandBlock>> {
+        Ok(self.command_blocks.lock().clone())
+    }
+
     fn get_current_working_dir(&self, _policy: CachePolicy) -> Option<Url> {
         self.renderable.lock().inner.borrow().working_dir.clone()
     }
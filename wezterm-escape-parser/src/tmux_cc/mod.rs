@@ -129,7 +129,7 @@ pub enum Event {
     },
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct PaneLayout {
     pub pane_id: TmuxPaneId,
     pub pane_width: u64,
@@ -145,6 +145,53 @@ pub enum WindowLayout {
     SinglePane(PaneLayout),
 }
 
+/// The tree of splits and panes described by a tmux layout string.
+/// The geometry of a split is held in a `PaneLayout` whose `pane_id`
+/// is always 0.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LayoutNode {
+    Pane(PaneLayout),
+    /// The children are arranged from left to right
+    SplitHorizontal {
+        geometry: PaneLayout,
+        children: Vec<LayoutNode>,
+    },
+    /// The children are arranged from top to bottom
+    SplitVertical {
+        geometry: PaneLayout,
+        children: Vec<LayoutNode>,
+    },
+}
+
+impl LayoutNode {
+    pub fn geometry(&self) -> &PaneLayout {
+        match self {
+            Self::Pane(geometry)
+            | Self::SplitHorizontal { geometry, .. }
+            | Self::SplitVertical { geometry, .. } => geometry,
+        }
+    }
+
+    /// Returns the panes in the layout, in the order in which
+    /// they appear in the layout string
+    pub fn panes(&self) -> Vec<PaneLayout> {
+        let mut panes = vec![];
+        self.collect_panes(&mut panes);
+        panes
+    }
+
+    fn collect_panes(&self, panes: &mut Vec<PaneLayout>) {
+        match self {
+            Self::Pane(pane) => panes.push(*pane),
+            Self::SplitHorizontal { children, .. } | Self::SplitVertical { children, .. } => {
+                for child in children {
+                    child.collect_panes(panes);
+                }
+            }
+        }
+    }
+}
+
 fn parse_pane_id(pair: Pair<Rule>) -> Result<TmuxPaneId> {
     match pair.as_rule() {
         Rule::pane_id => {
@@ -836,6 +883,36 @@ pub fn parse_layout(layout: &str) -> Result<Vec<WindowLayout>> {
     Ok(result)
 }
 
+fn parse_layout_node(pair: Pair<Rule>) -> Result<LayoutNode> {
+    let rule = pair.as_rule();
+    match rule {
+        Rule::layout_pane => Ok(LayoutNode::Pane(parse_layout_pane(pair)?)),
+        Rule::layout_split_horizontal | Rule::layout_split_vertical => {
+            let mut pairs = pair.into_inner();
+            let geometry = parse_layout_pane(
+                pairs
+                    .next()
+                    .ok_or_else(|| format_err!("wrong layout format"))?,
+            )?;
+            let children = pairs.map(parse_layout_node).collect::<Result<Vec<_>>>()?;
+            if rule == Rule::layout_split_horizontal {
+                Ok(LayoutNode::SplitHorizontal { geometry, children })
+            } else {
+                Ok(LayoutNode::SplitVertical { geometry, children })
+            }
+        }
+        _ => bail!("unexpected {:?} in layout", rule),
+    }
+}
+
+/// Parses a tmux layout string, without its leading checksum,
+/// into the tree of splits and panes that it describes
+pub fn parse_layout_tree(layout: &str) -> Result<LayoutNode> {
+    let mut pairs = parser::TmuxParser::parse(Rule::layout_window, layout)?;
+    let pair = pairs.next().ok_or_else(|| format_err!("empty layout"))?;
+    parse_layout_node(pair)
+}
+
 pub struct Parser {
     buffer: Vec<u8>,
     begun: Option<Guarded>,
@@ -1173,4 +1250,58 @@ here
         assert!(matches!(&layout[1], WindowLayout::SplitVertical(_x)));
         assert!(matches!(&layout[2], WindowLayout::SplitHorizontal(_x)));
     }
+
+    #[test]
+    fn test_parse_layout_tree() {
+        let pane = |pane_width, pane_height, pane_left, pane_top, pane_id| PaneLayout {
+            pane_id,
+            pane_width,
+            pane_height,
+            pane_left,
+            pane_top,
+        };
+
+        assert_eq!(
+            parse_layout_tree("158x40,0,0,72").unwrap(),
+            LayoutNode::Pane(pane(158, 40, 0, 0, 72))
+        );
+
+        let layout = parse_layout_tree(
+            "158x40,0,0{79x40,0,0[79x20,0,0,74,79x19,0,21{39x19,0,21,76,39x19,40,21,77}],78x40,80,0,75}",
+        )
+        .unwrap();
+        assert_eq!(
+            layout,
+            LayoutNode::SplitHorizontal {
+                geometry: pane(158, 40, 0, 0, 0),
+                children: vec![
+                    LayoutNode::SplitVertical {
+                        geometry: pane(79, 40, 0, 0, 0),
+                        children: vec![
+                            LayoutNode::Pane(pane(79, 20, 0, 0, 74)),
+                            LayoutNode::SplitHorizontal {
+                                geometry: pane(79, 19, 0, 21, 0),
+                                children: vec![
+                                    LayoutNode::Pane(pane(39, 19, 0, 21, 76)),
+                                    LayoutNode::Pane(pane(39, 19, 40, 21, 77)),
+                                ],
+                            },
+                        ],
+                    },
+                    LayoutNode::Pane(pane(78, 40, 80, 0, 75)),
+                ],
+            }
+        );
+        assert_eq!(
+            layout
+                .panes()
+                .iter()
+                .map(|pane| pane.pane_id)
+                .collect::<Vec<_>>(),
+            vec![74, 76, 77, 75]
+        );
+        assert_eq!(layout.geometry().pane_width, 158);
+
+        assert!(parse_layout_tree("158x40,0,0{").is_err());
+    }
 }
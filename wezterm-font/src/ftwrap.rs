@@ -208,6 +208,70 @@ impl Face {
         }
     }
 
+    /// Applies OpenType variation axis values, keyed by axis tag,
+    /// on top of the currently selected named instance.
+    /// Values outside of the range supported by an axis are clamped,
+    /// and axes that the font doesn't have are ignored.
+    pub fn set_variation_coordinates(
+        &mut self,
+        variations: &[(String, f64)],
+    ) -> anyhow::Result<()> {
+        let mut mm = std::ptr::null_mut();
+
+        let axes: Vec<FT_Var_Axis> = unsafe {
+            ft_result(FT_Get_MM_Var(self.face, &mut mm), ())
+                .context("FT_Get_MM_Var: the font has no variation axes")?;
+            let axes = from_raw_parts((*mm).axis, (*mm).num_axis as usize).to_vec();
+            FT_Done_MM_Var(self.lib, mm);
+            axes
+        };
+
+        // Start from the coordinates of the current instance, so that
+        // only the axes that were specified are changed
+        let mut coords: Vec<FT_Fixed> = axes.iter().map(|axis| axis.def).collect();
+        unsafe {
+            ft_result(
+                FT_Get_Var_Design_Coordinates(
+                    self.face,
+                    coords.len() as FT_UInt,
+                    coords.as_mut_ptr(),
+                ),
+                (),
+            )
+            .context("FT_Get_Var_Design_Coordinates")?;
+        }
+
+        for (tag, value) in variations {
+            let ft_tag = crate::hbwrap::hb_tag_from_str(tag).map(|t| t as FT_ULong);
+            match axes.iter().position(|axis| Some(axis.tag) == ft_tag) {
+                Some(idx) => {
+                    let axis = &axes[idx];
+                    let value = value.clamp(axis.minimum.to_num(), axis.maximum.to_num());
+                    coords[idx] = FT_Fixed::from_num(value);
+                }
+                None => {
+                    log::warn!(
+                        "{} has no `{}` variation axis; ignoring it",
+                        self.family_name(),
+                        tag
+                    );
+                }
+            }
+        }
+
+        unsafe {
+            ft_result(
+                FT_Set_Var_Design_Coordinates(
+                    self.face,
+                    coords.len() as FT_UInt,
+                    coords.as_mut_ptr(),
+                ),
+                (),
+            )
+            .context("FT_Set_Var_Design_Coordinates")
+        }
+    }
+
     pub fn get_glyph_name(&self, glyph_index: u32) -> Option<String> {
         let mut buf = [0u8; 128];
         let res = unsafe {
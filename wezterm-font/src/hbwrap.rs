@@ -316,6 +316,23 @@ impl Font {
         }
     }
 
+    /// Applies OpenType variation axis values, keyed by axis tag.
+    /// Axes that the font doesn't have are ignored by harfbuzz.
+    pub fn set_variations(&mut self, variations: &[(String, f64)]) {
+        let variations: Vec<hb_variation_t> = variations
+            .iter()
+            .filter_map(|(tag, value)| {
+                Some(hb_variation_t {
+                    tag: hb_tag_from_str(tag)?,
+                    value: *value as f32,
+                })
+            })
+            .collect();
+        unsafe {
+            hb_font_set_variations(self.font, variations.as_ptr(), variations.len() as c_uint);
+        }
+    }
+
     pub fn set_synthetic_bold(&mut self, x_embolden: f32, y_embolden: f32, in_place: bool) {
         unsafe {
             hb_font_set_synthetic_bold(
@@ -1167,6 +1184,18 @@ pub const fn hb_tag(c1: u8, c2: u8, c3: u8, c4: u8) -> hb_tag_t {
     ((c1 as u32) << 24) | ((c2 as u32) << 16) | ((c3 as u32) << 8) | (c4 as u32)
 }
 
+/// Parses an OpenType tag such as `wght`, padding shorter
+/// tags with spaces as described by the OpenType spec
+pub fn hb_tag_from_str(tag: &str) -> Option<hb_tag_t> {
+    let bytes = tag.as_bytes();
+    if bytes.is_empty() || bytes.len() > 4 || !bytes.iter().all(u8::is_ascii_graphic) {
+        return None;
+    }
+    let mut padded = [b' '; 4];
+    padded[..bytes.len()].copy_from_slice(bytes);
+    Some(hb_tag(padded[0], padded[1], padded[2], padded[3]))
+}
+
 pub fn hb_color(b: u8, g: u8, r: u8, a: u8) -> hb_tag_t {
     hb_tag(b, g, r, a)
 }
@@ -42,6 +42,9 @@ pub struct ParsedFont {
     pub freetype_render_target: Option<FreeTypeLoadTarget>,
     pub freetype_load_flags: Option<FreeTypeLoadFlags>,
     pub scale: Option<f64>,
+    /// OpenType variation axis values to apply on top of the
+    /// selected instance, as (axis tag, design value) pairs
+    pub variations: Vec<(String, f64)>,
 }
 
 impl std::fmt::Debug for ParsedFont {
@@ -63,6 +66,7 @@ impl std::fmt::Debug for ParsedFont {
             .field("freetype_render_target", &self.freetype_render_target)
             .field("freetype_load_flags", &self.freetype_load_flags)
             .field("scale", &self.scale)
+            .field("variations", &self.variations)
             .finish()
     }
 }
@@ -88,6 +92,7 @@ impl Clone for ParsedFont {
             freetype_load_flags: self.freetype_load_flags,
             is_built_in_fallback: self.is_built_in_fallback,
             scale: self.scale,
+            variations: self.variations.clone(),
             palettes: self.palettes.clone(),
         }
     }
@@ -267,6 +272,22 @@ impl Names {
     }
 }
 
+/// Formats a table key for use in the lua code that we suggest
+/// to the user, quoting it if it isn't a valid identifier
+fn lua_table_key(key: &str) -> String {
+    let is_ident = key
+        .chars()
+        .next()
+        .map(|c| c.is_ascii_alphabetic() || c == '_')
+        .unwrap_or(false)
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if is_ident {
+        key.to_string()
+    } else {
+        format!("[\"{}\"]", key)
+    }
+}
+
 impl ParsedFont {
     pub fn from_locator(handle: &FontDataHandle) -> anyhow::Result<Self> {
         let lib = crate::ftwrap::Library::new()?;
@@ -337,6 +358,7 @@ impl ParsedFont {
                 && p.freetype_load_flags.is_none()
                 && p.harfbuzz_features.is_none()
                 && p.scale.is_none()
+                && p.variations.is_empty()
             {
                 code.push_str(&format!("  \"{}\",\n", p.names.family));
             } else {
@@ -374,6 +396,16 @@ impl ParsedFont {
                     }
                     code.push('}');
                 }
+                if !p.variations.is_empty() {
+                    code.push_str(", variations={");
+                    for (idx, (tag, value)) in p.variations.iter().enumerate() {
+                        if idx > 0 {
+                            code.push_str(", ");
+                        }
+                        code.push_str(&format!("{}={}", lua_table_key(tag), value));
+                    }
+                    code.push('}');
+                }
                 code.push_str("},\n")
             }
             code.push_str("\n");
@@ -529,6 +561,7 @@ impl ParsedFont {
             freetype_load_target: None,
             freetype_load_flags: None,
             scale: None,
+            variations: vec![],
             palettes,
         })
     }
@@ -781,6 +814,12 @@ impl ParsedFont {
         self.freetype_load_target = attr.freetype_load_target;
         self.freetype_load_flags = attr.freetype_load_flags;
         self.scale = attr.scale.map(|f| *f);
+        self.variations = attr
+            .variations
+            .iter()
+            .flatten()
+            .map(|(tag, value)| (tag.clone(), **value))
+            .collect();
 
         self.synthesize_italic = self.style == FontStyle::Normal && attr.style != FontStyle::Normal;
         self.synthesize_bold = attr.weight >= FontWeight::DEMIBOLD
@@ -384,6 +384,11 @@ impl FreeTypeRasterizer {
         log::trace!("Rasterizier wants {:?}", parsed);
         let lib = ftwrap::Library::new()?;
         let mut face = lib.face_from_locator(&parsed.handle)?;
+        if !parsed.variations.is_empty() {
+            if let Err(err) = face.set_variation_coordinates(&parsed.variations) {
+                log::warn!("{}: {:#}", parsed.lua_name(), err);
+            }
+        }
         let has_color = unsafe {
             (((*face.face).face_flags as u32) & (ftwrap::FT_FACE_FLAG_COLOR as u32)) != 0
         };
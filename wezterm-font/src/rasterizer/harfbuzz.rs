@@ -20,6 +20,9 @@ impl HarfbuzzRasterizer {
     pub fn from_locator(parsed: &ParsedFont) -> anyhow::Result<Self> {
         let mut font = Font::from_locator(&parsed.handle)?;
         font.set_ot_funcs();
+        if !parsed.variations.is_empty() {
+            font.set_variations(&parsed.variations);
+        }
 
         if parsed.synthesize_italic {
             font.set_synthetic_slant(FAKE_ITALIC_SKEW as f32);
@@ -157,10 +157,19 @@ impl HarfbuzzShaper {
                 if opt_pair.is_none() {
                     let handle = &self.handles[font_idx];
                     log::trace!("shaper wants {} {:?}", font_idx, handle);
-                    let face = self.lib.face_from_locator(&handle.handle)?;
+                    let mut face = self.lib.face_from_locator(&handle.handle)?;
+                    if !handle.variations.is_empty() {
+                        // This must happen before creating the harfbuzz font,
+                        // which picks up the variation coordinates of the face
+                        if let Err(err) = face.set_variation_coordinates(&handle.variations) {
+                            log::warn!("{}: {:#}", handle.lua_name(), err);
+                        }
+                    }
 
                     let font = if USE_OT_FACE {
-                        harfbuzz::Font::from_locator(&handle.handle)?
+                        let mut font = harfbuzz::Font::from_locator(&handle.handle)?;
+                        font.set_variations(&handle.variations);
+                        font
                     } else {
                         let (load_flags, _) = ftwrap::compute_load_flags_from_config(
                             handle.freetype_load_flags,
@@ -862,6 +871,7 @@ mod test {
                     harfbuzz_features: None,
                     scale: None,
                     assume_emoji_presentation: None,
+                    variations: None,
                 },
                 14,
             )
@@ -1,6 +1,6 @@
 use clap::builder::ValueParser;
 use clap::{Parser, ValueHint};
-use config::{GuiPosition, SshParameters};
+use config::{GuiPosition, SerialFlowControl, SerialParity, SerialStopBits, SshParameters};
 use std::ffi::OsString;
 use std::path::PathBuf;
 
@@ -174,6 +174,28 @@ pub struct SerialCommand {
     #[arg(long = "baud")]
     pub baud: Option<usize>,
 
+    /// Set the number of data bits, from 5 to 8.  The default is 8.
+    #[arg(long = "data-bits")]
+    pub data_bits: Option<u8>,
+
+    /// Set the parity to none, odd or even.  The default is none.
+    #[arg(long = "parity")]
+    pub parity: Option<SerialParity>,
+
+    /// Set the number of stop bits to 1 or 2.  The default is 1.
+    #[arg(long = "stop-bits")]
+    pub stop_bits: Option<SerialStopBits>,
+
+    /// Set the flow control to none, xonxoff or rtscts.
+    /// The default is xonxoff.
+    #[arg(long = "flow-control")]
+    pub flow_control: Option<SerialFlowControl>,
+
+    /// Append the received data to this file, with each
+    /// line prefixed by the time at which it arrived.
+    #[arg(long = "log-file", value_hint=ValueHint::FilePath)]
+    pub log_file: Option<PathBuf>,
+
     /// Override the default windowing system class.
     /// The default is "org.wezfurlong.wezterm".
     /// Under X11 and Windows this changes the window class.
@@ -271,3 +293,61 @@ pub struct ShowKeysCommand {
     #[arg(long)]
     pub key_table: Option<String>,
 }
+
+/// The `wezterm cli` subcommands that need the font and rendering
+/// machinery of the gui.  `wezterm cli` delegates these to `wezterm-gui`,
+/// so the options here must match those of `wezterm cli`.
+#[derive(Debug, Parser, Clone)]
+pub struct GuiCliCommand {
+    /// Don't automatically start the server
+    #[arg(long = "no-auto-start")]
+    pub no_auto_start: bool,
+
+    /// Prefer connecting to a background mux server.
+    #[arg(long = "prefer-mux")]
+    pub prefer_mux: bool,
+
+    /// The class of the gui instance to connect to
+    #[arg(long = "class")]
+    pub class: Option<String>,
+
+    #[command(subcommand)]
+    pub sub: GuiCliSubCommand,
+}
+
+#[derive(Debug, Parser, Clone)]
+pub enum GuiCliSubCommand {
+    #[command(name = "screenshot")]
+    Screenshot(ScreenshotCommand),
+}
+
+#[derive(Debug, Parser, Clone)]
+pub struct ScreenshotCommand {
+    /// Specify the target pane.
+    /// The default is to use the current pane based on the
+    /// environment variable WEZTERM_PANE.
+    #[arg(long)]
+    pub pane_id: Option<usize>,
+
+    /// The first line to render.
+    /// 0 is the first line of terminal screen.
+    /// Negative numbers proceed backwards into the scrollback.
+    /// The default is the first line of the terminal screen.
+    #[arg(long, allow_hyphen_values = true)]
+    pub start_line: Option<isize>,
+
+    /// The last line to render.
+    /// 0 is the first line of terminal screen.
+    /// Negative numbers proceed backwards into the scrollback.
+    /// The default is the bottom of the terminal screen.
+    #[arg(long, allow_hyphen_values = true)]
+    pub end_line: Option<isize>,
+
+    /// Don't draw the cursor
+    #[arg(long)]
+    pub no_cursor: bool,
+
+    /// Where to save the PNG image
+    #[arg(long, short = 'o', value_parser, value_hint=ValueHint::FilePath)]
+    pub output: PathBuf,
+}
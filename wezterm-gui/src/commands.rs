@@ -300,6 +300,27 @@ impl CommandDef {
                         action: KeyAssignment::SwitchToWorkspace {
                             name: Some(workspace.clone()),
                             spawn: None,
+                            layout: None,
+                        },
+                        menubar: &["Window", "Workspace"],
+                        icon: None,
+                    });
+                }
+            }
+            let workspaces = mux.iter_workspaces();
+            let mut layouts: Vec<_> = config.workspace_layouts.iter().collect();
+            layouts.sort_by(|a, b| a.0.cmp(b.0));
+            for (name, layout) in layouts {
+                if !workspaces.contains(name) {
+                    result.push(ExpandedCommand {
+                        brief: format!("Create workspace from layout {}", layout.label(name))
+                            .into(),
+                        doc: "".into(),
+                        keys: vec![],
+                        action: KeyAssignment::SwitchToWorkspace {
+                            name: Some(name.clone()),
+                            spawn: None,
+                            layout: Some(name.clone()),
                         },
                         menubar: &["Window", "Workspace"],
                         icon: None,
@@ -313,6 +334,7 @@ impl CommandDef {
                 action: KeyAssignment::SwitchToWorkspace {
                     name: None,
                     spawn: None,
+                    layout: None,
                 },
                 menubar: &["Window", "Workspace"],
                 icon: None,
@@ -796,6 +818,14 @@ pub fn derive_command_from_key_assignment(action: &KeyAssignment) -> Option<Comm
             menubar: &[],
             icon: Some("oct_search"),
         },
+        SearchAllPanes(_) => CommandDef {
+            brief: "Search all panes".into(),
+            doc: "Searches the output of every pane in the current workspace".into(),
+            keys: vec![],
+            args: &[ArgType::ActiveWindow],
+            menubar: &["Edit"],
+            icon: Some("oct_search"),
+        },
         ShowDebugOverlay => CommandDef {
             brief: "Show debug overlay".into(),
             doc: "Activates the debug overlay and Lua REPL".into(),
@@ -940,6 +970,36 @@ pub fn derive_command_from_key_assignment(action: &KeyAssignment) -> Option<Comm
             menubar: &["View", "Font Size"],
             icon: Some("md_format_size"),
         },
+        DecreasePaneFontSize => CommandDef {
+            brief: "Decrease font size of the pane".into(),
+            doc: "Scales the font size of the active pane smaller by 10%, \
+                  leaving the other panes unchanged"
+                .into(),
+            keys: vec![],
+            args: &[ArgType::ActivePane],
+            menubar: &["View", "Font Size"],
+            icon: Some("md_format_size"),
+        },
+        IncreasePaneFontSize => CommandDef {
+            brief: "Increase font size of the pane".into(),
+            doc: "Scales the font size of the active pane larger by 10%, \
+                  leaving the other panes unchanged"
+                .into(),
+            keys: vec![],
+            args: &[ArgType::ActivePane],
+            menubar: &["View", "Font Size"],
+            icon: Some("md_format_size"),
+        },
+        ResetPaneFontSize => CommandDef {
+            brief: "Reset font size of the pane".into(),
+            doc: "Restores the font size of the active pane to match \
+                  the rest of the window"
+                .into(),
+            keys: vec![],
+            args: &[ArgType::ActivePane],
+            menubar: &["View", "Font Size"],
+            icon: Some("md_format_size"),
+        },
         ResetFontAndWindowSize => CommandDef {
             brief: "Reset the window and font size".into(),
             doc: "Restores the original window and font size".into(),
@@ -1676,6 +1736,142 @@ pub fn derive_command_from_key_assignment(action: &KeyAssignment) -> Option<Comm
             menubar: &["Shell", "Detach"],
             icon: Some("md_pipe_disconnected"),
         },
+        ToggleBroadcastInput(scope) => {
+            let (brief, doc) = match scope {
+                BroadcastInputScope::CurrentTab => (
+                    "Toggle broadcasting input to the current tab",
+                    "Toggles sending the input of the active pane to \
+                     all of the panes in the current tab",
+                ),
+                BroadcastInputScope::CurrentWorkspace => (
+                    "Toggle broadcasting input to the current workspace",
+                    "Toggles sending the input of the active pane to \
+                     all of the panes in the current workspace",
+                ),
+                BroadcastInputScope::MarkedPanes => (
+                    "Toggle broadcasting input to marked panes",
+                    "Toggles sending the input of the active pane to \
+                     the panes that are marked for broadcast",
+                ),
+            };
+            CommandDef {
+                brief: brief.into(),
+                doc: doc.into(),
+                keys: vec![],
+                args: &[ArgType::ActivePane],
+                menubar: &["Shell", "Broadcast Input"],
+                icon: Some("md_broadcast"),
+            }
+        }
+        TogglePaneBroadcastMark => CommandDef {
+            brief: "Toggle broadcast mark on the current pane".into(),
+            doc: "Marks or unmarks the current pane as one that receives \
+                  broadcast input when broadcasting to marked panes"
+                .into(),
+            keys: vec![],
+            args: &[ArgType::ActivePane],
+            menubar: &["Shell", "Broadcast Input"],
+            icon: Some("md_checkbox_marked_outline"),
+        },
+        SerialPort(action) => {
+            let (brief, doc): (String, String) = match action {
+                SerialPortAction::Configure(_) => (
+                    "Configure serial port".into(),
+                    "Changes the line settings of the serial port of the current pane".into(),
+                ),
+                SerialPortAction::ToggleDtr => (
+                    "Toggle serial DTR line".into(),
+                    "Asserts or clears the DTR line of the serial port".into(),
+                ),
+                SerialPortAction::ToggleRts => (
+                    "Toggle serial RTS line".into(),
+                    "Asserts or clears the RTS line of the serial port".into(),
+                ),
+                SerialPortAction::SendBreak(ms) => (
+                    "Send serial break".into(),
+                    format!("Holds the serial line in the break condition for {ms}ms"),
+                ),
+                SerialPortAction::ToggleLogging => (
+                    "Toggle serial logging".into(),
+                    "Starts or stops logging the data received by the serial port to a file"
+                        .into(),
+                ),
+                SerialPortAction::ToggleHexView => (
+                    "Toggle serial hex view".into(),
+                    "Shows or hides a live hex dump of the data received by the serial port"
+                        .into(),
+                ),
+            };
+            CommandDef {
+                brief: brief.into(),
+                doc: doc.into(),
+                keys: vec![],
+                args: &[ArgType::ActivePane],
+                menubar: &["Shell", "Serial Port"],
+                icon: Some("md_serial_port"),
+            }
+        }
+        TogglePaneRecording(args) => CommandDef {
+            brief: if args.capture_input {
+                "Toggle recording of the current pane, including input".into()
+            } else {
+                "Toggle recording of the current pane".into()
+            },
+            doc: "Starts or stops recording the output of the current pane \
+                  to an asciicast file"
+                .into(),
+            keys: vec![],
+            args: &[ArgType::ActivePane],
+            menubar: &["Shell"],
+            icon: Some("md_record_rec"),
+        },
+        PlayAsciicast(args) => CommandDef {
+            brief: format!("Play {}", args.path).into(),
+            doc: "Opens a new tab that plays back an asciicast recording".into(),
+            keys: vec![],
+            args: &[],
+            menubar: &[],
+            icon: Some("md_play_box_outline"),
+        },
+        ExportPane(args) => CommandDef {
+            brief: match &args.path {
+                Some(path) => format!("Export {:?} of pane to {path}", args.content).into(),
+                None => format!("Copy {:?} of pane as {:?}", args.content, args.format).into(),
+            },
+            doc: "Renders the pane content with its colors and styles and \
+                  copies it to the clipboard or saves it to a file"
+                .into(),
+            keys: vec![],
+            args: &[ArgType::ActivePane],
+            menubar: &[],
+            icon: Some("md_export"),
+        },
+        ShowGlyphInspector => CommandDef {
+            brief: "Inspect glyphs and font fallback".into(),
+            doc: "Shows which font rendered each cell of the pane, the fallback \
+                  fonts that were considered, and how the text was shaped"
+                .into(),
+            keys: vec![],
+            args: &[ArgType::ActivePane],
+            menubar: &["Help"],
+            icon: Some("md_format_font"),
+        },
+        SaveSession => CommandDef {
+            brief: "Save session".into(),
+            doc: "Saves the layout of the windows, tabs and panes as the default session".into(),
+            keys: vec![],
+            args: &[],
+            menubar: &["Shell", "Session"],
+            icon: Some("md_content_save"),
+        },
+        RestoreSession => CommandDef {
+            brief: "Restore session".into(),
+            doc: "Re-creates the windows, tabs and panes of the default session".into(),
+            keys: vec![],
+            args: &[],
+            menubar: &["Shell", "Session"],
+            icon: Some("md_restore"),
+        },
         OpenUri(uri) => match uri.as_ref() {
             "https://wezterm.org/" => CommandDef {
                 brief: "Documentation".into(),
@@ -1858,6 +2054,7 @@ pub fn derive_command_from_key_assignment(action: &KeyAssignment) -> Option<Comm
         SwitchToWorkspace {
             name: None,
             spawn: None,
+            layout: None,
         } => CommandDef {
             brief: format!(
                 "Spawn the default program into a new \
@@ -1877,6 +2074,7 @@ pub fn derive_command_from_key_assignment(action: &KeyAssignment) -> Option<Comm
         SwitchToWorkspace {
             name: Some(name),
             spawn: None,
+            layout: None,
         } => CommandDef {
             brief: format!(
                 "Switch to workspace `{name}`, spawn the \
@@ -1896,6 +2094,7 @@ pub fn derive_command_from_key_assignment(action: &KeyAssignment) -> Option<Comm
         SwitchToWorkspace {
             name: Some(name),
             spawn: Some(prog),
+            layout: None,
         } => CommandDef {
             brief: format!(
                 "Switch to workspace `{name}`, spawn {prog:?} \
@@ -1915,6 +2114,7 @@ pub fn derive_command_from_key_assignment(action: &KeyAssignment) -> Option<Comm
         SwitchToWorkspace {
             name: None,
             spawn: Some(prog),
+            layout: None,
         } => CommandDef {
             brief: format!("Spawn the {prog:?} into a new workspace and switch to it").into(),
             doc: format!("Spawn the {prog:?} into a new workspace and switch to it").into(),
@@ -1923,6 +2123,28 @@ pub fn derive_command_from_key_assignment(action: &KeyAssignment) -> Option<Comm
             menubar: &["Window", "Workspace"],
             icon: None,
         },
+        SwitchToWorkspace {
+            name,
+            spawn: _,
+            layout: Some(layout),
+        } => {
+            let workspace = match name {
+                Some(name) => format!("workspace `{name}`"),
+                None => "a new workspace".to_string(),
+            };
+            CommandDef {
+                brief: format!("Switch to {workspace} using layout `{layout}`").into(),
+                doc: format!(
+                    "Switch to {workspace}, building its tabs and panes \
+                     from layout `{layout}` if it doesn't already exist"
+                )
+                .into(),
+                keys: vec![],
+                args: &[],
+                menubar: &["Window", "Workspace"],
+                icon: None,
+            }
+        }
         SwitchWorkspaceRelative(n) => {
             let (direction, amount) = if *n < 0 {
                 ("previous", -n)
@@ -2045,6 +2267,13 @@ fn compute_default_actions() -> Vec<KeyAssignment> {
         CloseCurrentTab { confirm: true },
         CloseCurrentPane { confirm: true },
         DetachDomain(SpawnTabDomain::CurrentPaneDomain),
+        SaveSession,
+        RestoreSession,
+        ToggleBroadcastInput(BroadcastInputScope::CurrentTab),
+        ToggleBroadcastInput(BroadcastInputScope::CurrentWorkspace),
+        ToggleBroadcastInput(BroadcastInputScope::MarkedPanes),
+        TogglePaneBroadcastMark,
+        TogglePaneRecording(PaneRecordingArgs::default()),
         ResetTerminal,
         // ----------------- Edit
         #[cfg(not(target_os = "macos"))]
@@ -2064,6 +2293,9 @@ fn compute_default_actions() -> Vec<KeyAssignment> {
         DecreaseFontSize,
         IncreaseFontSize,
         ResetFontSize,
+        DecreasePaneFontSize,
+        IncreasePaneFontSize,
+        ResetPaneFontSize,
         ResetFontAndWindowSize,
         ScrollByPage(NotNan::new(-1.0).unwrap()),
         ScrollByPage(NotNan::new(1.0).unwrap()),
@@ -2078,6 +2310,7 @@ fn compute_default_actions() -> Vec<KeyAssignment> {
         SetWindowLevel(WindowLevel::AlwaysOnTop),
         Hide,
         Search(Pattern::CurrentSelectionOrEmptyString),
+        SearchAllPanes(SearchAllPanesArguments::default()),
         PaneSelect(PaneSelectArguments {
             alphabet: String::new(),
             mode: PaneSelectMode::Activate,
@@ -2147,6 +2380,7 @@ fn compute_default_actions() -> Vec<KeyAssignment> {
         OpenUri("https://github.com/wezterm/wezterm/discussions/".to_string()),
         OpenUri("https://github.com/wezterm/wezterm/issues/".to_string()),
         ShowDebugOverlay,
+        ShowGlyphInspector,
         // ----------------- Misc
         OpenLinkAtMouseCursor,
     ];
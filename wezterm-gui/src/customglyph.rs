@@ -1,4 +1,4 @@
-use crate::glyphcache::{GlyphCache, SizedBlockKey};
+use crate::glyphcache::{CellMetricKey, GlyphCache, SizedBlockKey};
 use crate::utilsprites::RenderMetrics;
 use ::window::bitmaps::atlas::Sprite;
 use ::window::color::SrgbaPixel;
@@ -5054,7 +5054,8 @@ impl GlyphCache {
         metrics: &RenderMetrics,
         width: u8,
     ) -> anyhow::Result<Sprite> {
-        if let Some(sprite) = self.cursor_glyphs.get(&(shape, width)) {
+        let key = (shape, width, CellMetricKey::from(metrics));
+        if let Some(sprite) = self.cursor_glyphs.get(&key) {
             return Ok(sprite.clone());
         }
 
@@ -5134,7 +5135,7 @@ impl GlyphCache {
         }
 
         let sprite = self.atlas.allocate(&buffer)?;
-        self.cursor_glyphs.insert((shape, width), sprite.clone());
+        self.cursor_glyphs.insert(key, sprite.clone());
         Ok(sprite)
     }
 
@@ -5143,6 +5144,11 @@ impl GlyphCache {
         render_metrics: &RenderMetrics,
         key: SizedBlockKey,
     ) -> anyhow::Result<Sprite> {
+        let aa = if self.fonts.config().anti_alias_custom_block_glyphs {
+            PolyAA::AntiAlias
+        } else {
+            PolyAA::MoarPixels
+        };
         let metrics = match &key.block {
             BlockKey::PolyWithCustomMetrics {
                 underline_height,
@@ -5236,11 +5242,7 @@ impl GlyphCache {
                             style: style,
                         }],
                         &mut buffer,
-                        if config::configuration().anti_alias_custom_block_glyphs {
-                            PolyAA::AntiAlias
-                        } else {
-                            PolyAA::MoarPixels
-                        },
+                        aa,
                         BlendMode::default(),
                     );
                 };
@@ -5319,11 +5321,7 @@ impl GlyphCache {
                             style: PolyStyle::Outline,
                         }],
                         &mut buffer,
-                        if config::configuration().anti_alias_custom_block_glyphs {
-                            PolyAA::AntiAlias
-                        } else {
-                            PolyAA::MoarPixels
-                        },
+                        aa,
                         BlendMode::default(),
                     );
                 };
@@ -5468,11 +5466,7 @@ impl GlyphCache {
                             style: style,
                         }],
                         &mut buffer,
-                        if config::configuration().anti_alias_custom_block_glyphs {
-                            PolyAA::AntiAlias
-                        } else {
-                            PolyAA::MoarPixels
-                        },
+                        aa,
                         BlendMode::default(),
                     );
                 };
@@ -5600,11 +5594,7 @@ impl GlyphCache {
                                 style: style,
                             }],
                             &mut buffer,
-                            if config::configuration().anti_alias_custom_block_glyphs {
-                                PolyAA::AntiAlias
-                            } else {
-                                PolyAA::MoarPixels
-                            },
+                            aa,
                             blend_mode,
                         );
                     };
@@ -5769,11 +5759,7 @@ impl GlyphCache {
                                 style: style,
                             }],
                             &mut buffer,
-                            if config::configuration().anti_alias_custom_block_glyphs {
-                                PolyAA::AntiAlias
-                            } else {
-                                PolyAA::MoarPixels
-                            },
+                            aa,
                             blend_mode,
                         );
                     };
@@ -5981,17 +5967,7 @@ impl GlyphCache {
                 }
             }
             BlockKey::Poly(polys) | BlockKey::PolyWithCustomMetrics { polys, .. } => {
-                self.draw_polys(
-                    &metrics,
-                    polys,
-                    &mut buffer,
-                    if config::configuration().anti_alias_custom_block_glyphs {
-                        PolyAA::AntiAlias
-                    } else {
-                        PolyAA::MoarPixels
-                    },
-                    BlendMode::default(),
-                );
+                self.draw_polys(&metrics, polys, &mut buffer, aa, BlendMode::default());
             }
         }
 
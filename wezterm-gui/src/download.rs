@@ -57,9 +57,26 @@ fn resolve_file_name(name: Option<&str>) -> anyhow::Result<(PathBuf, File)> {
 }
 
 pub fn save_to_downloads(orig_name: Option<String>, data: &[u8]) -> anyhow::Result<()> {
+    stream_to_downloads(orig_name, |file| {
+        Ok(file
+            .write_all(data)
+            .with_context(|| format!("writing {} of data", data.len()))?)
+    })
+}
+
+/// Like `save_to_downloads`, but `write` is responsible for writing
+/// the data into the file, so that it can be streamed into place.
+/// The file is removed if `write` fails.
+pub fn stream_to_downloads<F>(orig_name: Option<String>, write: F) -> anyhow::Result<()>
+where
+    F: FnOnce(&mut File) -> anyhow::Result<()>,
+{
     let (name, mut file) = resolve_file_name(orig_name.as_deref())?;
-    file.write_all(data)
-        .with_context(|| format!("writing {} of data to {}", data.len(), name.display()))?;
+    if let Err(err) = write(&mut file) {
+        drop(file);
+        std::fs::remove_file(&name).ok();
+        return Err(err.context(format!("writing to {}", name.display())));
+    }
 
     let url = format!("file://{}", name.display());
     wezterm_toast_notification::persistent_toast_notification_with_click_to_open_url(
@@ -93,6 +93,9 @@ impl GuiFrontEnd {
                 MuxNotification::PaneRemoved(_) => {}
                 MuxNotification::WindowInvalidated(_) => {}
                 MuxNotification::PaneOutput(_) => {}
+                MuxNotification::PaneRawOutput { .. } => {}
+                MuxNotification::PaneRawInput { .. } => {}
+                MuxNotification::PaneRecordingChanged(_) => {}
                 MuxNotification::PaneAdded(_) => {}
                 MuxNotification::Alert {
                     pane_id,
@@ -147,6 +150,7 @@ impl GuiFrontEnd {
                         | Alert::WindowTitleChanged(_)
                         | Alert::TabTitleChanged(_)
                         | Alert::IconTitleChanged(_)
+                        | Alert::CommandBlocksChanged
                         | Alert::SetUserVar { .. },
                 } => {}
                 MuxNotification::Empty => {
@@ -252,6 +256,7 @@ impl GuiFrontEnd {
                             SpawnTabDomain::DomainName("local".to_string()),
                             cmd,
                             cwd,
+                            None,
                             TerminalSize::default(),
                             pane_id,
                             workspace,
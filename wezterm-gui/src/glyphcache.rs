@@ -565,7 +565,7 @@ pub struct GlyphCache {
     frame_cache: HashMap<[u8; 32], Sprite>,
     line_glyphs: HashMap<LineKey, Sprite>,
     pub block_glyphs: HashMap<SizedBlockKey, Sprite>,
-    pub cursor_glyphs: HashMap<(Option<CursorShape>, u8), Sprite>,
+    pub cursor_glyphs: HashMap<(Option<CursorShape>, u8, CellMetricKey), Sprite>,
     pub color: HashMap<(RgbColor, NotNan<f32>), Sprite>,
     min_frame_duration: Duration,
 }
@@ -12,6 +12,7 @@ use config::keyassignment::{SpawnCommand, SpawnTabDomain};
 use config::{ConfigHandle, SerialDomain, SshDomain, SshMultiplexing};
 use mux::activity::Activity;
 use mux::domain::{Domain, LocalDomain};
+use mux::session_state::{self, DEFAULT_SESSION};
 use mux::Mux;
 use mux_lua::MuxDomain;
 use portable_pty::cmdbuilder::CommandBuilder;
@@ -45,6 +46,7 @@ mod overlay;
 mod quad;
 mod renderstate;
 mod resize_increment_calculator;
+mod screenshot;
 mod scripting;
 mod scrollbar;
 mod selection;
@@ -132,6 +134,10 @@ enum SubCommand {
 
     #[command(name = "show-keys", about = "Show key assignments")]
     ShowKeys(ShowKeysCommand),
+
+    /// The subset of `wezterm cli` that is delegated to us
+    #[command(name = "cli", hide = true)]
+    Cli(GuiCliCommand),
 }
 
 fn set_builtin_config_file() -> anyhow::Result<()> {
@@ -257,6 +263,11 @@ async fn async_run_serial(opts: SerialCommand) -> anyhow::Result<()> {
         name: format!("Serial Port {}", opts.port),
         port: Some(opts.port.clone()),
         baud: opts.baud,
+        data_bits: opts.data_bits,
+        parity: opts.parity,
+        stop_bits: opts.stop_bits,
+        flow_control: opts.flow_control,
+        log_file: opts.log_file,
     };
 
     let start_command = StartCommand {
@@ -447,6 +458,62 @@ async fn trigger_and_log_gui_attached(domain: MuxDomain) {
     }
 }
 
+/// Restores the default session if `restore_session_on_startup` is
+/// enabled and nothing else has populated the mux, and then starts
+/// saving the session periodically
+async fn restore_session_on_startup() {
+    let config = config::configuration();
+    if !config.restore_session_on_startup {
+        return;
+    }
+
+    // Something else, such as the gui-startup event, has already
+    // created windows.  Those are unrelated to the saved session, so
+    // saving them would replace the session with only part of it.
+    if !Mux::get().is_empty() {
+        log::info!("not restoring or saving the session as windows were already created");
+        return;
+    }
+
+    let path = session_state::session_path(DEFAULT_SESSION);
+    if path.map(|path| path.exists()).unwrap_or(false) {
+        let result = async {
+            let session = session_state::load_session(DEFAULT_SESSION)?;
+            session_state::restore_session(&session).await
+        }
+        .await;
+        if let Err(err) = result {
+            // Keep the saved session intact rather than autosaving
+            // whatever part of it was restored
+            let message = format!("while restoring the session: {:#}", err);
+            log::error!("{}", message);
+            persistent_toast_notification("Error", &message);
+            return;
+        }
+    }
+
+    promise::spawn::spawn(autosave_session()).detach();
+}
+
+async fn autosave_session() {
+    loop {
+        let interval = config::configuration().session_autosave_interval;
+        smol::Timer::after(interval).await;
+
+        let config = config::configuration();
+        // Don't replace the saved session with nothing
+        // while the last window is closing
+        if !config.restore_session_on_startup || Mux::get().is_empty() {
+            continue;
+        }
+        if let Err(err) =
+            session_state::save_session(DEFAULT_SESSION, config.session_save_scrollback)
+        {
+            log::error!("saving session: {err:#}");
+        }
+    }
+}
+
 fn cell_pixel_dims(config: &ConfigHandle, dpi: f64) -> anyhow::Result<(usize, usize)> {
     let fontconfig = Rc::new(FontConfiguration::new(Some(config.clone()), dpi as usize)?);
     let render_metrics = RenderMetrics::new(&fontconfig)?;
@@ -540,6 +607,10 @@ async fn async_run_terminal_gui(
             trigger_and_log_gui_attached(MuxDomain(domain.domain_id())).await;
         }
     }
+    if cmd.is_none() && domain.is_none() && !is_connecting {
+        restore_session_on_startup().await;
+    }
+
     spawn_tab_in_domain_if_mux_is_empty(cmd, is_connecting, domain, opts.workspace).await
 }
 
@@ -733,7 +804,7 @@ fn setup_mux(
     let mux = Arc::new(mux::Mux::new(Some(local_domain.clone())));
     Mux::set_mux(&mux);
     let client_id = Arc::new(mux::client::ClientId::new());
-    mux.register_client(client_id.clone());
+    mux.register_client(client_id.clone(), config::ClientAccess::Full);
     mux.replace_identity(Some(client_id));
     let default_workspace_name = default_workspace_name.unwrap_or(
         config
@@ -1328,5 +1399,6 @@ fn run() -> anyhow::Result<()> {
         ),
         SubCommand::LsFonts(cmd) => run_ls_fonts(config, &cmd),
         SubCommand::ShowKeys(cmd) => run_show_keys(config, &cmd),
+        SubCommand::Cli(cli) => screenshot::run_cli(config, cli),
     }
 }
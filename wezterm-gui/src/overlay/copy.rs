@@ -574,6 +574,18 @@ impl CopyRenderable {
         TermWindow::schedule_cancel_overlay_for_pane(self.window.clone(), self.delegate.pane_id());
     }
 
+    /// Treat the selected text as a path on the remote host of an
+    /// ssh domain and download it; this replaces copy mode with
+    /// the download progress overlay
+    fn download_selected_path(&self) {
+        let pane = Arc::clone(&self.delegate);
+        self.window
+            .notify(TermWindowNotif::Apply(Box::new(move |term_window| {
+                let path = term_window.selection_text(&pane);
+                term_window.download_remote_path(&pane, &path);
+            })));
+    }
+
     fn move_by_page(&mut self, amount: f64) {
         let dims = self.dimensions();
         let rows = (dims.dims.viewport_rows as f64 * amount) as isize;
@@ -1289,6 +1301,7 @@ impl Pane for CopyOverlay {
                     JumpBackward { prev_char } => render.jump(false, *prev_char),
                     JumpAgain => render.jump_again(false),
                     JumpReverse => render.jump_again(true),
+                    DownloadSelectedPath => render.download_selected_path(),
                 }
                 PerformAssignmentResult::Handled
             }
@@ -0,0 +1,1042 @@
+//! An overlay that searches the output of every pane in the
+//! current workspace and allows jumping to a match.
+use crate::overlay::selector::{matcher_pattern, matcher_score};
+use crate::selection::{SelectionCoordinate, SelectionRange};
+use crate::termwindow::{TermWindow, TermWindowNotif};
+use config::keyassignment::SearchAllPanesArguments;
+use mux::pane::{Pane, PaneId, Pattern, SearchResult};
+use mux::termwiztermtab::TermWizTerminal;
+use mux::window::WindowId as MuxWindowId;
+use mux::Mux;
+use std::ops::Range;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use termwiz::cell::{unicode_column_width, AttributeChange, CellAttributes, Intensity, Underline};
+use termwiz::color::ColorAttribute;
+use termwiz::input::{InputEvent, KeyCode, KeyEvent, Modifiers, MouseButtons, MouseEvent};
+use termwiz::surface::{Change, Position};
+use termwiz::terminal::Terminal;
+use termwiz_funcs::truncate_right;
+use wezterm_term::StableRowIndex;
+use window::WindowOps;
+
+/// How many rows to search in a single request; smaller chunks
+/// allow the results from multiple panes to trickle in while
+/// searching very large scrollbacks
+const SEARCH_CHUNK_SIZE: StableRowIndex = 2000;
+/// Stop searching a pane once it has produced this many matches
+const MAX_MATCHES_PER_PANE: usize = 500;
+/// How long to wait after the last keypress before starting a search
+const TYPING_DELAY: Duration = Duration::from_millis(150);
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// The title row, the input row and the status row
+const ROW_OVERHEAD: usize = 3;
+/// The screen row on which the results start
+const FIRST_RESULT_ROW: usize = 2;
+
+/// A pane that will be searched, along with the information
+/// used to describe it in the results
+pub struct SearchablePane {
+    pub window_id: MuxWindowId,
+    pub tab_idx: usize,
+    pub tab_title: String,
+    pub pane_idx: usize,
+    pub pane: Arc<dyn Pane>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum MatchType {
+    CaseSensitive,
+    CaseInsensitive,
+    Regex,
+    Fuzzy,
+}
+
+impl MatchType {
+    fn next(self) -> Self {
+        match self {
+            Self::CaseSensitive => Self::CaseInsensitive,
+            Self::CaseInsensitive => Self::Regex,
+            Self::Regex => Self::Fuzzy,
+            Self::Fuzzy => Self::CaseSensitive,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::CaseSensitive => "case-sensitive",
+            Self::CaseInsensitive => "ignore-case",
+            Self::Regex => "regex",
+            Self::Fuzzy => "fuzzy",
+        }
+    }
+
+    fn pattern(self, query: &str) -> Pattern {
+        match self {
+            Self::CaseSensitive => Pattern::CaseSensitiveString(query.to_string()),
+            Self::CaseInsensitive => Pattern::CaseInSensitiveString(query.to_string()),
+            Self::Regex => Pattern::Regex(query.to_string()),
+            Self::Fuzzy => Pattern::Regex(fuzzy_regex(query)),
+        }
+    }
+}
+
+/// Produce a regex that matches the non-whitespace characters of
+/// the query in order, with anything in between them.  The matching
+/// lines are subsequently ranked by the fuzzy matcher.
+fn fuzzy_regex(query: &str) -> String {
+    let chars: Vec<String> = query
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .map(|c| regex::escape(&c.to_string()))
+        .collect();
+    format!("(?i){}", chars.join(".*?"))
+}
+
+/// A single match, along with the lines surrounding it
+struct Found {
+    result: SearchResult,
+    /// The 1-based line number of the match within the scrollback
+    line_number: usize,
+    before: Vec<String>,
+    /// The matched line, split into the text before the match,
+    /// the matched text and the text after it
+    matched: (String, String, String),
+    after: Vec<String>,
+    score: u32,
+}
+
+/// A request to the search thread to search a chunk of the
+/// scrollback of a pane
+struct ChunkRequest {
+    generation: usize,
+    pane_idx: usize,
+    pane: Arc<dyn Pane>,
+    pattern: Pattern,
+    range: Range<StableRowIndex>,
+    scrollback_top: StableRowIndex,
+    context_lines: StableRowIndex,
+    fuzzy: Option<String>,
+}
+
+impl ChunkRequest {
+    async fn run(self) -> ChunkResult {
+        // Start the chunk at the start of the logical line that it
+        // begins in, so that a wrapped line is searched by one chunk
+        let mut range = self.range;
+        if let Some(line) = self
+            .pane
+            .get_logical_lines(range.start..range.start + 1)
+            .first()
+        {
+            range.start = line.first_row.clamp(self.scrollback_top, range.start);
+        }
+
+        let results = match self.pane.search(self.pattern, range.clone(), None).await {
+            Ok(results) => {
+                // A logical line that straddles the chunk is searched
+                // in its entirety, so only keep the matches that start
+                // within the chunk; the adjacent chunk has the others
+                let results = results
+                    .into_iter()
+                    .filter(|result| range.contains(&result.start_y))
+                    .collect();
+                collect_matches(
+                    &self.pane,
+                    results,
+                    self.scrollback_top,
+                    self.context_lines,
+                    self.fuzzy,
+                )
+                .await
+            }
+            Err(err) => Err(err),
+        };
+
+        ChunkResult {
+            generation: self.generation,
+            pane_idx: self.pane_idx,
+            range,
+            results,
+        }
+    }
+}
+
+struct ChunkResult {
+    generation: usize,
+    pane_idx: usize,
+    /// The range that was searched, which may start earlier
+    /// than the range that was requested
+    range: Range<StableRowIndex>,
+    results: anyhow::Result<Vec<Found>>,
+}
+
+/// Searches the chunks of scrollback, one at a time, away from the
+/// GUI thread.  Chunks belonging to a search that has since been
+/// superseded are skipped.
+fn search_thread(
+    requests: Receiver<ChunkRequest>,
+    results: Sender<ChunkResult>,
+    generation: Arc<AtomicUsize>,
+) {
+    while let Ok(request) = requests.recv() {
+        if request.generation != generation.load(Ordering::Relaxed) {
+            continue;
+        }
+        if results.send(smol::block_on(request.run())).is_err() {
+            break;
+        }
+    }
+}
+
+struct PaneProgress {
+    /// The start of the most recently searched chunk; searching
+    /// proceeds from the bottom of the scrollback towards the top
+    next_end: StableRowIndex,
+    scrollback_top: StableRowIndex,
+    done: bool,
+    found: Vec<Found>,
+}
+
+enum Row {
+    Header(usize),
+    Context(String),
+    Match { pane_idx: usize, found_idx: usize },
+}
+
+/// The rows that list the matches, along with the selected
+/// match and the scroll position
+#[derive(Default)]
+struct ResultRows {
+    rows: Vec<Row>,
+    /// Indices into `rows` of the Row::Match entries
+    match_rows: Vec<usize>,
+    active_idx: usize,
+    top_row: usize,
+    max_rows: usize,
+    context_lines: usize,
+}
+
+impl ResultRows {
+    fn reset(&mut self) {
+        self.active_idx = 0;
+        self.top_row = 0;
+    }
+
+    /// Returns the pane and found indices of the selected match
+    fn active_match(&self) -> Option<(usize, usize)> {
+        match self
+            .match_rows
+            .get(self.active_idx)
+            .and_then(|&row| self.rows.get(row))
+        {
+            Some(Row::Match {
+                pane_idx,
+                found_idx,
+            }) => Some((*pane_idx, *found_idx)),
+            _ => None,
+        }
+    }
+
+    /// Rebuilds the rows from the matches found so far, keeping
+    /// the selected match selected
+    fn rebuild(&mut self, progress: &[PaneProgress], fuzzy: bool) {
+        let active = self.active_match();
+
+        self.rows.clear();
+        self.match_rows.clear();
+
+        for (pane_idx, progress) in progress.iter().enumerate() {
+            if progress.found.is_empty() {
+                continue;
+            }
+            self.rows.push(Row::Header(pane_idx));
+
+            let mut order: Vec<usize> = (0..progress.found.len()).collect();
+            if fuzzy {
+                order.sort_by(|&a, &b| progress.found[b].score.cmp(&progress.found[a].score));
+            }
+
+            for found_idx in order {
+                let found = &progress.found[found_idx];
+                for line in &found.before {
+                    self.rows.push(Row::Context(line.clone()));
+                }
+                if active == Some((pane_idx, found_idx)) {
+                    self.active_idx = self.match_rows.len();
+                }
+                self.match_rows.push(self.rows.len());
+                self.rows.push(Row::Match {
+                    pane_idx,
+                    found_idx,
+                });
+                for line in &found.after {
+                    self.rows.push(Row::Context(line.clone()));
+                }
+            }
+        }
+
+        self.active_idx = self.active_idx.min(self.match_rows.len().saturating_sub(1));
+        self.scroll_to_active();
+    }
+
+    fn num_matches(&self) -> usize {
+        self.match_rows.len()
+    }
+
+    /// Returns the index of the match shown on the specified
+    /// row of the screen, if any
+    fn match_at(&self, y: usize) -> Option<usize> {
+        let offset = y.checked_sub(FIRST_RESULT_ROW)?;
+        if offset >= self.max_rows {
+            return None;
+        }
+        let row = self.top_row + offset;
+        self.match_rows.iter().position(|&r| r == row)
+    }
+
+    fn scroll_to_active(&mut self) {
+        if self.max_rows == 0 {
+            return;
+        }
+        let row = match self.match_rows.get(self.active_idx) {
+            Some(&row) => row,
+            None => return,
+        };
+        // Keep the context before the match visible too
+        let first = row.saturating_sub(self.context_lines);
+        let last = row + self.context_lines;
+        if first < self.top_row {
+            self.top_row = first;
+        } else if last >= self.top_row + self.max_rows {
+            self.top_row = (last + 1).saturating_sub(self.max_rows);
+        }
+    }
+
+    fn move_up(&mut self) {
+        self.active_idx = self.active_idx.saturating_sub(1);
+        self.scroll_to_active();
+    }
+
+    fn move_down(&mut self) {
+        self.active_idx = (self.active_idx + 1).min(self.num_matches().saturating_sub(1));
+        self.scroll_to_active();
+    }
+}
+
+struct GlobalSearchState {
+    panes: Vec<SearchablePane>,
+    progress: Vec<PaneProgress>,
+    query: String,
+    match_type: MatchType,
+    /// Shared with the search thread, so that it can skip
+    /// the chunks of superseded searches
+    generation: Arc<AtomicUsize>,
+    query_changed: Option<Instant>,
+    error: Option<String>,
+    results: ResultRows,
+    requests: Sender<ChunkRequest>,
+    rx: Receiver<ChunkResult>,
+    window: ::window::Window,
+}
+
+impl GlobalSearchState {
+    fn is_searching(&self) -> bool {
+        self.query_changed.is_some() || self.progress.iter().any(|p| !p.done)
+    }
+
+    fn start_search(&mut self) {
+        self.query_changed.take();
+        self.generation.fetch_add(1, Ordering::Relaxed);
+        self.error.take();
+        self.results.reset();
+        self.progress.clear();
+
+        for searchable in &self.panes {
+            let dims = searchable.pane.get_dimensions();
+            let end = dims.scrollback_top + dims.scrollback_rows as StableRowIndex;
+            self.progress.push(PaneProgress {
+                next_end: end,
+                scrollback_top: dims.scrollback_top,
+                done: self.query.is_empty(),
+                found: vec![],
+            });
+        }
+        if !self.query.is_empty() {
+            for pane_idx in 0..self.panes.len() {
+                self.search_next_chunk(pane_idx);
+            }
+        }
+        self.rebuild_rows();
+    }
+
+    fn search_next_chunk(&mut self, pane_idx: usize) {
+        let progress = &mut self.progress[pane_idx];
+        if progress.next_end <= progress.scrollback_top
+            || progress.found.len() >= MAX_MATCHES_PER_PANE
+        {
+            progress.done = true;
+            return;
+        }
+
+        let range = progress
+            .next_end
+            .saturating_sub(SEARCH_CHUNK_SIZE)
+            .max(progress.scrollback_top)..progress.next_end;
+
+        let request = ChunkRequest {
+            generation: self.generation.load(Ordering::Relaxed),
+            pane_idx,
+            pane: Arc::clone(&self.panes[pane_idx].pane),
+            pattern: self.match_type.pattern(&self.query),
+            range,
+            scrollback_top: progress.scrollback_top,
+            context_lines: self.results.context_lines as StableRowIndex,
+            fuzzy: if self.match_type == MatchType::Fuzzy {
+                Some(self.query.clone())
+            } else {
+                None
+            },
+        };
+        if self.requests.send(request).is_err() {
+            progress.done = true;
+        }
+    }
+
+    fn process_chunk(&mut self, chunk: ChunkResult) {
+        if chunk.generation != self.generation.load(Ordering::Relaxed) {
+            return;
+        }
+        let progress = &mut self.progress[chunk.pane_idx];
+        progress.next_end = chunk.range.start;
+        match chunk.results {
+            Ok(mut found) => {
+                // Chunks are searched from the bottom up, and we want the
+                // most recent output to be listed first
+                found.reverse();
+                progress.found.append(&mut found);
+                progress.found.truncate(MAX_MATCHES_PER_PANE);
+            }
+            Err(err) => {
+                log::debug!("search of {:?} failed: {:#}", chunk.range, err);
+                self.error.replace(format!("{err:#}"));
+                progress.done = true;
+            }
+        }
+        if !self.progress[chunk.pane_idx].done {
+            self.search_next_chunk(chunk.pane_idx);
+        }
+    }
+
+    fn rebuild_rows(&mut self) {
+        let fuzzy = self.match_type == MatchType::Fuzzy;
+        self.results.rebuild(&self.progress, fuzzy);
+    }
+
+    fn query_changed(&mut self) {
+        self.query_changed.replace(Instant::now());
+    }
+
+    fn render(&mut self, term: &mut TermWizTerminal) -> termwiz::Result<()> {
+        let size = term.get_screen_size()?;
+        let max_width = size.cols.saturating_sub(1);
+        self.results.max_rows = size.rows.saturating_sub(ROW_OVERHEAD);
+
+        let num_panes = self.progress.iter().filter(|p| !p.found.is_empty()).count();
+        let status = if let Some(err) = &self.error {
+            format!("Error: {err}")
+        } else if self.query.is_empty() {
+            format!("Type to search {} panes", self.panes.len())
+        } else {
+            format!(
+                "{} matches in {} of {} panes{}",
+                self.results.num_matches(),
+                num_panes,
+                self.panes.len(),
+                if self.is_searching() {
+                    " (searching...)"
+                } else {
+                    ""
+                }
+            )
+        };
+
+        let mut changes = vec![
+            Change::ClearScreen(ColorAttribute::Default),
+            Change::CursorPosition {
+                x: Position::Absolute(0),
+                y: Position::Absolute(0),
+            },
+            Change::Text(truncate_right(
+                &format!(
+                    "Search all panes ({}): Enter=jump  Esc=cancel  CTRL-R=match type  \
+                     CTRL-U=clear",
+                    self.match_type.label()
+                ),
+                max_width,
+            )),
+            Change::CursorPosition {
+                x: Position::Absolute(0),
+                y: Position::Absolute(FIRST_RESULT_ROW),
+            },
+        ];
+
+        let active_row = self
+            .results
+            .match_rows
+            .get(self.results.active_idx)
+            .copied();
+
+        for (row_idx, row) in self
+            .results
+            .rows
+            .iter()
+            .enumerate()
+            .skip(self.results.top_row)
+            .take(self.results.max_rows)
+        {
+            match row {
+                Row::Header(pane_idx) => {
+                    let searchable = &self.panes[*pane_idx];
+                    let tab_title = if searchable.tab_title.is_empty() {
+                        String::new()
+                    } else {
+                        format!(" {}", searchable.tab_title)
+                    };
+                    changes.push(AttributeChange::Intensity(Intensity::Bold).into());
+                    changes.push(Change::Text(truncate_right(
+                        &format!(
+                            "Tab {}{}, pane {}: {} ({} matches)",
+                            searchable.tab_idx + 1,
+                            tab_title,
+                            searchable.pane_idx,
+                            searchable.pane.get_title(),
+                            self.progress[*pane_idx].found.len()
+                        ),
+                        max_width,
+                    )));
+                }
+                Row::Context(text) => {
+                    changes.push(AttributeChange::Intensity(Intensity::Half).into());
+                    changes.push(Change::Text(truncate_right(
+                        &format!("        {text}"),
+                        max_width,
+                    )));
+                }
+                Row::Match {
+                    pane_idx,
+                    found_idx,
+                } => {
+                    let found = &self.progress[*pane_idx].found[*found_idx];
+                    let is_active = active_row == Some(row_idx);
+                    if is_active {
+                        changes.push(AttributeChange::Reverse(true).into());
+                    }
+                    let prefix = format!("{:>6}: {}", found.line_number, found.matched.0);
+                    let mut remain = max_width;
+                    for (text, highlight) in [
+                        (prefix.as_str(), false),
+                        (found.matched.1.as_str(), true),
+                        (found.matched.2.as_str(), false),
+                    ] {
+                        let text = truncate_right(text, remain);
+                        remain = remain.saturating_sub(unicode_column_width(&text, None));
+                        if highlight {
+                            changes.push(AttributeChange::Intensity(Intensity::Bold).into());
+                            changes.push(AttributeChange::Underline(Underline::Single).into());
+                        }
+                        changes.push(Change::Text(text));
+                        if highlight {
+                            changes.push(AttributeChange::Intensity(Intensity::Normal).into());
+                            changes.push(AttributeChange::Underline(Underline::None).into());
+                        }
+                    }
+                }
+            }
+            changes.push(Change::AllAttributes(CellAttributes::default()));
+            changes.push(Change::Text("\r\n".to_string()));
+        }
+
+        changes.push(Change::CursorPosition {
+            x: Position::Absolute(0),
+            y: Position::Absolute(size.rows.saturating_sub(1)),
+        });
+        changes.push(AttributeChange::Intensity(Intensity::Half).into());
+        changes.push(Change::Text(truncate_right(&status, max_width)));
+        changes.push(Change::AllAttributes(CellAttributes::default()));
+
+        let prompt = "Search: ";
+        changes.push(Change::CursorPosition {
+            x: Position::Absolute(0),
+            y: Position::Absolute(1),
+        });
+        changes.push(Change::Text(truncate_right(
+            &format!("{prompt}{}", self.query),
+            max_width,
+        )));
+
+        term.render(&changes)
+    }
+
+    /// Activate the pane containing the selected match and scroll
+    /// its viewport so that the match is visible and selected.
+    /// Returns false if there is no such match.
+    fn activate(&self) -> bool {
+        let (pane_idx, found_idx) = match self.results.active_match() {
+            Some(active) => active,
+            None => return false,
+        };
+
+        let pane_id = self.panes[pane_idx].pane.pane_id();
+        let window_id = self.panes[pane_idx].window_id;
+        let result = self.progress[pane_idx].found[found_idx].result.clone();
+        let this_window = self.window.clone();
+
+        promise::spawn::spawn_into_main_thread(async move {
+            let mux = Mux::get();
+            if let Err(err) = mux.focus_pane_and_containing_tab(pane_id) {
+                log::error!("Unable to activate search result: {:#}", err);
+                return;
+            }
+            let window = match crate::frontend::front_end().gui_window_for_mux_window(window_id) {
+                Some(gui_win) => gui_win.window,
+                None => this_window,
+            };
+            window.focus();
+            window.notify(TermWindowNotif::Apply(Box::new(move |term_window| {
+                show_search_result(term_window, pane_id, &result);
+            })));
+        })
+        .detach();
+
+        true
+    }
+
+    fn run_loop(&mut self, term: &mut TermWizTerminal) -> anyhow::Result<()> {
+        loop {
+            let wait = if self.is_searching() {
+                Some(POLL_INTERVAL)
+            } else {
+                None
+            };
+            let event = match term.poll_input(wait) {
+                Ok(Some(event)) => Some(event),
+                Ok(None) if wait.is_some() => None,
+                Ok(None) | Err(_) => break,
+            };
+
+            match event {
+                Some(InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char('G' | 'C'),
+                    modifiers: Modifiers::CTRL,
+                }))
+                | Some(InputEvent::Key(KeyEvent {
+                    key: KeyCode::Escape,
+                    ..
+                })) => {
+                    break;
+                }
+                Some(InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char('R'),
+                    modifiers: Modifiers::CTRL,
+                })) => {
+                    self.match_type = self.match_type.next();
+                    self.query_changed();
+                }
+                Some(InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char('U'),
+                    modifiers: Modifiers::CTRL,
+                })) => {
+                    self.query.clear();
+                    self.query_changed();
+                }
+                Some(InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char('P' | 'K'),
+                    modifiers: Modifiers::CTRL,
+                }))
+                | Some(InputEvent::Key(KeyEvent {
+                    key: KeyCode::UpArrow,
+                    ..
+                })) => {
+                    self.results.move_up();
+                }
+                Some(InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char('N' | 'J'),
+                    modifiers: Modifiers::CTRL,
+                }))
+                | Some(InputEvent::Key(KeyEvent {
+                    key: KeyCode::DownArrow,
+                    ..
+                })) => {
+                    self.results.move_down();
+                }
+                Some(InputEvent::Key(KeyEvent {
+                    key: KeyCode::PageUp,
+                    ..
+                })) => {
+                    for _ in 0..self.results.max_rows / 2 {
+                        self.results.move_up();
+                    }
+                }
+                Some(InputEvent::Key(KeyEvent {
+                    key: KeyCode::PageDown,
+                    ..
+                })) => {
+                    for _ in 0..self.results.max_rows / 2 {
+                        self.results.move_down();
+                    }
+                }
+                Some(InputEvent::Key(KeyEvent {
+                    key: KeyCode::Backspace,
+                    ..
+                })) => {
+                    if self.query.pop().is_some() {
+                        self.query_changed();
+                    }
+                }
+                Some(InputEvent::Key(KeyEvent {
+                    key: KeyCode::Enter,
+                    ..
+                })) => {
+                    if self.activate() {
+                        break;
+                    }
+                }
+                Some(InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char(c),
+                    modifiers: Modifiers::NONE | Modifiers::SHIFT,
+                })) => {
+                    self.query.push(c);
+                    self.query_changed();
+                }
+                Some(InputEvent::Paste(text)) => {
+                    self.query.push_str(text.lines().next().unwrap_or(""));
+                    self.query_changed();
+                }
+                Some(InputEvent::Mouse(MouseEvent { mouse_buttons, .. }))
+                    if mouse_buttons.contains(MouseButtons::VERT_WHEEL) =>
+                {
+                    if mouse_buttons.contains(MouseButtons::WHEEL_POSITIVE) {
+                        self.results.move_up();
+                    } else {
+                        self.results.move_down();
+                    }
+                }
+                Some(InputEvent::Mouse(MouseEvent {
+                    y, mouse_buttons, ..
+                })) if mouse_buttons == MouseButtons::LEFT => {
+                    if let Some(idx) = self.results.match_at(y as usize) {
+                        self.results.active_idx = idx;
+                        if self.activate() {
+                            break;
+                        }
+                    }
+                }
+                _ => {}
+            }
+
+            if let Some(changed) = self.query_changed {
+                if changed.elapsed() >= TYPING_DELAY {
+                    self.start_search();
+                }
+            }
+
+            let mut updated = false;
+            while let Ok(chunk) = self.rx.try_recv() {
+                self.process_chunk(chunk);
+                updated = true;
+            }
+            if updated {
+                self.rebuild_rows();
+            }
+
+            self.render(term)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Builds the result entries for a chunk, capturing the matched
+/// line and the lines around it
+async fn collect_matches(
+    pane: &Arc<dyn Pane>,
+    results: Vec<SearchResult>,
+    scrollback_top: StableRowIndex,
+    context_lines: StableRowIndex,
+    fuzzy: Option<String>,
+) -> anyhow::Result<Vec<Found>> {
+    let fuzzy = fuzzy.map(|query| matcher_pattern(&query));
+    let mut found = vec![];
+
+    for result in results {
+        let context =
+            result.start_y.saturating_sub(context_lines)..result.end_y + 1 + context_lines;
+        let (first, lines) = pane.fetch_lines(context).await?;
+
+        let mut before = vec![];
+        let mut after = vec![];
+        let mut matched = (String::new(), String::new(), String::new());
+        let mut score = 0;
+
+        for (idx, line) in lines.iter().enumerate() {
+            let row = first + idx as StableRowIndex;
+            if row < result.start_y {
+                before.push(line.as_str().trim_end().to_string());
+            } else if row == result.start_y {
+                let len = line.len();
+                let start = result.start_x.min(len);
+                let end = if result.end_y == result.start_y {
+                    result.end_x.min(len).max(start)
+                } else {
+                    len
+                };
+                matched = (
+                    line.columns_as_str(0..start),
+                    line.columns_as_str(start..end),
+                    line.columns_as_str(end..len).trim_end().to_string(),
+                );
+                if let Some(pattern) = &fuzzy {
+                    score = matcher_score(pattern, &line.as_str()).unwrap_or(0);
+                }
+            } else if row > result.end_y {
+                after.push(line.as_str().trim_end().to_string());
+            }
+        }
+
+        found.push(Found {
+            line_number: (result.start_y - scrollback_top).max(0) as usize + 1,
+            result,
+            before,
+            matched,
+            after,
+            score,
+        });
+    }
+
+    Ok(found)
+}
+
+/// Scrolls the viewport of the pane so that the search result is
+/// visible, and selects the matched text
+fn show_search_result(term_window: &mut TermWindow, pane_id: PaneId, result: &SearchResult) {
+    let pane = match Mux::get().get_pane(pane_id) {
+        Some(pane) => pane,
+        None => return,
+    };
+    let dims = pane.get_dimensions();
+    let top = result
+        .start_y
+        .saturating_sub(dims.viewport_rows as StableRowIndex / 2);
+    term_window.set_viewport(pane_id, Some(top), dims);
+
+    let start = SelectionCoordinate::x_y(result.start_x, result.start_y);
+    let end = SelectionCoordinate::x_y(result.end_x.saturating_sub(1), result.end_y);
+    let mut selection = term_window.selection(pane_id);
+    selection.origin = Some(start);
+    selection.range = Some(SelectionRange { start, end });
+    selection.rectangular = false;
+    selection.seqno = pane.get_current_seqno();
+    drop(selection);
+
+    if let Some(window) = term_window.window.as_ref() {
+        window.invalidate();
+    }
+}
+
+pub fn global_search(
+    mut term: TermWizTerminal,
+    panes: Vec<SearchablePane>,
+    pattern: Pattern,
+    args: SearchAllPanesArguments,
+    window: ::window::Window,
+) -> anyhow::Result<()> {
+    let match_type = if args.fuzzy {
+        MatchType::Fuzzy
+    } else {
+        match &pattern {
+            Pattern::CaseSensitiveString(_) => MatchType::CaseSensitive,
+            Pattern::CaseInSensitiveString(_) => MatchType::CaseInsensitive,
+            Pattern::Regex(_) => MatchType::Regex,
+        }
+    };
+    let query = match pattern {
+        Pattern::CaseSensitiveString(s) | Pattern::CaseInSensitiveString(s) | Pattern::Regex(s) => {
+            s
+        }
+    };
+
+    let generation = Arc::new(AtomicUsize::new(0));
+    let (requests, request_rx) = channel();
+    let (tx, rx) = channel();
+    {
+        let generation = Arc::clone(&generation);
+        std::thread::Builder::new()
+            .name("search all panes".to_string())
+            .spawn(move || search_thread(request_rx, tx, generation))?;
+    }
+
+    let mut state = GlobalSearchState {
+        panes,
+        progress: vec![],
+        query,
+        match_type,
+        generation,
+        query_changed: None,
+        error: None,
+        results: ResultRows {
+            context_lines: args.context_lines,
+            ..Default::default()
+        },
+        requests,
+        rx,
+        window,
+    };
+
+    term.set_raw_mode()?;
+    term.render(&[Change::Title("Search all panes".to_string())])?;
+    state.start_search();
+    state.render(&mut term)?;
+    state.run_loop(&mut term)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn fuzzy_regex_matches_in_order() {
+        assert_eq!(fuzzy_regex("a b."), "(?i)a.*?b.*?\\.");
+        let re = regex::Regex::new(&fuzzy_regex("ab.")).unwrap();
+        assert!(re.is_match("xAyyB."));
+        assert!(!re.is_match("xAyyBz"));
+        assert!(!re.is_match("b.a"));
+    }
+
+    fn found(y: StableRowIndex, score: u32) -> Found {
+        Found {
+            result: SearchResult {
+                start_y: y,
+                start_x: 0,
+                end_y: y,
+                end_x: 1,
+                match_id: 0,
+            },
+            line_number: y as usize + 1,
+            before: vec![format!("before {y}")],
+            matched: (String::new(), format!("match {y}"), String::new()),
+            after: vec![format!("after {y}")],
+            score,
+        }
+    }
+
+    fn progress(found: Vec<Found>) -> PaneProgress {
+        PaneProgress {
+            next_end: 0,
+            scrollback_top: 0,
+            done: true,
+            found,
+        }
+    }
+
+    fn describe(results: &ResultRows) -> Vec<String> {
+        results
+            .rows
+            .iter()
+            .map(|row| match row {
+                Row::Header(pane_idx) => format!("pane {pane_idx}"),
+                Row::Context(text) => text.clone(),
+                Row::Match {
+                    pane_idx,
+                    found_idx,
+                } => format!("match {pane_idx}/{found_idx}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rebuild_rows() {
+        let progress = vec![
+            progress(vec![found(10, 1), found(5, 3)]),
+            progress(vec![]),
+            progress(vec![found(7, 2)]),
+        ];
+        let mut results = ResultRows {
+            context_lines: 1,
+            max_rows: 4,
+            ..Default::default()
+        };
+
+        results.rebuild(&progress, false);
+        assert_eq!(
+            describe(&results),
+            vec![
+                "pane 0",
+                "before 10",
+                "match 0/0",
+                "after 10",
+                "before 5",
+                "match 0/1",
+                "after 5",
+                "pane 2",
+                "before 7",
+                "match 2/0",
+                "after 7",
+            ]
+        );
+        assert_eq!(results.match_rows, vec![2, 5, 9]);
+
+        // Selecting the second match scrolls its context into view
+        results.move_down();
+        assert_eq!(results.active_match(), Some((0, 1)));
+        assert_eq!(results.top_row, 3);
+
+        // Fuzzy matches are ranked by score within each pane, and
+        // the selected match stays selected
+        results.rebuild(&progress, true);
+        assert_eq!(
+            describe(&results)[0..7],
+            [
+                "pane 0",
+                "before 5",
+                "match 0/1",
+                "after 5",
+                "before 10",
+                "match 0/0",
+                "after 10",
+            ]
+        );
+        assert_eq!(results.active_idx, 0);
+        assert_eq!(results.active_match(), Some((0, 1)));
+        assert_eq!(results.top_row, 1);
+
+        results.rebuild(&[], false);
+        assert!(results.rows.is_empty());
+        assert_eq!(results.active_idx, 0);
+        assert_eq!(results.active_match(), None);
+    }
+
+    #[test]
+    fn match_at_screen_row() {
+        let progress = vec![progress(vec![found(10, 0), found(5, 0)])];
+        let mut results = ResultRows {
+            context_lines: 1,
+            max_rows: 4,
+            ..Default::default()
+        };
+        results.rebuild(&progress, false);
+        results.top_row = 1;
+
+        // The title and input rows aren't results
+        assert_eq!(results.match_at(0), None);
+        assert_eq!(results.match_at(1), None);
+        // Nor are the context rows
+        assert_eq!(results.match_at(FIRST_RESULT_ROW), None);
+        assert_eq!(results.match_at(FIRST_RESULT_ROW + 1), Some(0));
+        assert_eq!(results.match_at(FIRST_RESULT_ROW + 3), None);
+        // The second match is scrolled out of view, below the results
+        assert_eq!(results.match_at(FIRST_RESULT_ROW + 4), None);
+    }
+}
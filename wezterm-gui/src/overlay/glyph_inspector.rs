@@ -0,0 +1,494 @@
+//! An overlay that explains how the cells of a pane were rendered:
+//! which fonts were considered, which one was used and how the text
+//! was shaped.  The reports are computed on the gui thread by
+//! `TermWindow::show_glyph_inspector`, as they need access to the
+//! fonts, and this module presents them.
+use mux::termwiztermtab::TermWizTerminal;
+use termwiz::cell::AttributeChange;
+use termwiz::color::{AnsiColor, ColorAttribute};
+use termwiz::input::{InputEvent, KeyCode, KeyEvent};
+use termwiz::surface::{Change, CursorVisibility, Position};
+use termwiz::terminal::Terminal;
+use wezterm_char_props::emoji::Presentation;
+use wezterm_char_props::nerdfonts::NERD_FONT_GLYPHS;
+use wezterm_char_props::white_space::WHITE_SPACE;
+use wezterm_char_props::widechar_width::WcWidth;
+
+const TITLE: &str = "Glyph inspector: arrows move, n: next last resort glyph, Escape closes";
+
+/// Where the font that rendered a cell came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FontSource {
+    /// The first font in the font configuration
+    Primary,
+    /// One of the other fonts listed in the font configuration
+    Configured,
+    /// One of the fonts that are bundled with wezterm
+    BuiltIn,
+    /// A font found by searching the system for a font that has
+    /// the glyph, because none of the configured fonts have it
+    LastResort,
+    /// No font has a glyph for the text
+    Missing,
+    /// Drawn by wezterm because `custom_block_glyphs` is enabled
+    CustomGlyph,
+}
+
+impl FontSource {
+    /// Whether the cell should be highlighted as having been
+    /// rendered without the help of the font configuration
+    pub fn is_last_resort(self) -> bool {
+        matches!(self, Self::LastResort | Self::Missing)
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Primary => "primary font",
+            Self::Configured => "configured fallback",
+            Self::BuiltIn => "built-in fallback",
+            Self::LastResort => "last resort system fallback",
+            Self::Missing => "no font has this glyph",
+            Self::CustomGlyph => "drawn by wezterm (custom_block_glyphs)",
+        }
+    }
+}
+
+/// A font in the fallback list that was consulted for a cell
+#[derive(Debug, Clone)]
+pub struct FallbackCandidate {
+    pub name: String,
+    /// Whether the font has glyphs for all of the codepoints
+    pub covers: bool,
+}
+
+/// A shaped glyph that contributes to a cell
+#[derive(Debug, Clone)]
+pub struct GlyphDetail {
+    pub glyph_pos: u32,
+    pub glyph_name: Option<String>,
+    pub font_idx: usize,
+    /// The byte offset of the glyph within the shaped text
+    pub cluster: u32,
+    pub num_cells: u8,
+    pub x_advance: f64,
+    pub x_offset: f64,
+    pub y_offset: f64,
+    pub bearing_x: f64,
+    pub bearing_y: f64,
+    pub bitmap_width: isize,
+    pub bitmap_height: isize,
+}
+
+/// Everything we know about how a single cell was rendered
+#[derive(Debug, Clone)]
+pub struct CellReport {
+    /// The column of the cell within its line
+    pub col: usize,
+    pub grapheme: String,
+    pub width: usize,
+    pub source: FontSource,
+    /// The lua expression that selects the font that was used
+    pub font: String,
+    /// Where the font was loaded from
+    pub font_location: String,
+    /// The fonts that were considered, in order, up to and including
+    /// the one that was used
+    pub chain: Vec<FallbackCandidate>,
+    /// The run of text that was passed to the shaper
+    pub shaped_text: String,
+    pub glyphs: Vec<GlyphDetail>,
+}
+
+/// Describes the unicode properties of a codepoint that influence
+/// font selection and cell width
+pub fn describe_codepoint(c: char) -> String {
+    let mut props = vec![
+        format!("U+{:04X}", c as u32),
+        format!("width={:?}", WcWidth::from_char(c)),
+    ];
+    if Presentation::for_char(c) == Presentation::Emoji {
+        props.push("emoji-presentation".to_string());
+    }
+    if WHITE_SPACE.contains_u32(c as u32) {
+        props.push("white-space".to_string());
+    }
+    if let Some((name, _)) = NERD_FONT_GLYPHS.iter().find(|(_, glyph)| *glyph == c) {
+        props.push(format!("nerdfont={name}"));
+    }
+    props.join(" ")
+}
+
+impl CellReport {
+    pub fn describe(&self) -> Vec<String> {
+        let mut lines = vec![];
+
+        let (presentation, explicit) = Presentation::for_grapheme(&self.grapheme);
+        let presentation = explicit.unwrap_or(presentation);
+        lines.push(format!(
+            "Cell {}: {:?} cells={} presentation={:?}",
+            self.col, self.grapheme, self.width, presentation
+        ));
+        for c in self.grapheme.chars() {
+            lines.push(format!("  {}", describe_codepoint(c)));
+        }
+
+        lines.push(format!("Font: {} ({})", self.font, self.source.label()));
+        lines.push(format!("  {}", self.font_location));
+
+        lines.push("Fallback chain:".to_string());
+        for (idx, candidate) in self.chain.iter().enumerate() {
+            lines.push(format!(
+                "  {idx:2} {} {}",
+                if candidate.covers {
+                    "has glyph "
+                } else {
+                    "no glyph  "
+                },
+                candidate.name
+            ));
+        }
+
+        lines.push(format!(
+            "Shaped {:?} into {} glyph(s) for this cell:",
+            self.shaped_text,
+            self.glyphs.len()
+        ));
+        for glyph in &self.glyphs {
+            lines.push(format!(
+                "  glyph={}{} font_idx={} cluster={} cells={}",
+                glyph.glyph_pos,
+                glyph
+                    .glyph_name
+                    .as_ref()
+                    .map(|name| format!(" ({name})"))
+                    .unwrap_or_default(),
+                glyph.font_idx,
+                glyph.cluster,
+                glyph.num_cells,
+            ));
+            lines.push(format!(
+                "    x_advance={:.2} offset=({:.2}, {:.2}) bearing=({:.2}, {:.2}) bitmap={}x{}",
+                glyph.x_advance,
+                glyph.x_offset,
+                glyph.y_offset,
+                glyph.bearing_x,
+                glyph.bearing_y,
+                glyph.bitmap_width,
+                glyph.bitmap_height,
+            ));
+        }
+
+        lines
+    }
+}
+
+struct Inspector {
+    lines: Vec<Vec<CellReport>>,
+    /// Index into `lines`
+    row: usize,
+    /// Index into `lines[row]`
+    cell: usize,
+    /// The first line that is shown
+    top: usize,
+}
+
+impl Inspector {
+    fn current(&self) -> Option<&CellReport> {
+        self.lines
+            .get(self.row)
+            .and_then(|line| line.get(self.cell))
+    }
+
+    /// Selects the cell on `row` that is closest to column `col`
+    fn select(&mut self, row: usize, col: usize) {
+        let row = row.min(self.lines.len().saturating_sub(1));
+        self.row = row;
+        self.cell = self.lines[row]
+            .iter()
+            .rposition(|cell| cell.col <= col)
+            .unwrap_or(0);
+    }
+
+    fn current_col(&self) -> usize {
+        self.current().map(|cell| cell.col).unwrap_or(0)
+    }
+
+    fn move_by(&mut self, delta: isize) {
+        let len = self.lines.get(self.row).map(|line| line.len()).unwrap_or(0);
+        if len > 0 {
+            self.cell = (self.cell as isize + delta).clamp(0, len as isize - 1) as usize;
+        }
+    }
+
+    fn next_last_resort(&mut self) {
+        let positions = self
+            .lines
+            .iter()
+            .enumerate()
+            .flat_map(|(row, line)| (0..line.len()).map(move |cell| (row, cell)));
+        let candidates: Vec<(usize, usize)> = positions
+            .filter(|&(row, cell)| self.lines[row][cell].source.is_last_resort())
+            .collect();
+        if let Some(&(row, cell)) = candidates
+            .iter()
+            .find(|&&pos| pos > (self.row, self.cell))
+            .or_else(|| candidates.first())
+        {
+            self.row = row;
+            self.cell = cell;
+        }
+    }
+
+    fn render(&mut self, term: &mut TermWizTerminal) -> termwiz::Result<()> {
+        let size = term.get_screen_size()?;
+        // Leave at least half of the screen for the report
+        let visible_rows = self
+            .lines
+            .len()
+            .min((size.rows.saturating_sub(2) / 2).max(1));
+        if self.row < self.top {
+            self.top = self.row;
+        } else if self.row >= self.top + visible_rows {
+            self.top = self.row + 1 - visible_rows;
+        }
+
+        let mut changes = vec![
+            Change::ClearScreen(ColorAttribute::Default),
+            Change::CursorVisibility(CursorVisibility::Hidden),
+            Change::Text(TITLE.to_string()),
+        ];
+
+        for (y, row) in (self.top..self.top + visible_rows).enumerate() {
+            changes.push(Change::CursorPosition {
+                x: Position::Absolute(0),
+                y: Position::Absolute(y + 1),
+            });
+            for (idx, cell) in self.lines[row].iter().enumerate() {
+                if cell.col >= size.cols {
+                    break;
+                }
+                let selected = row == self.row && idx == self.cell;
+                let highlight = cell.source.is_last_resort();
+                if selected {
+                    changes.push(AttributeChange::Reverse(true).into());
+                }
+                if highlight {
+                    changes.push(AttributeChange::Background(AnsiColor::Maroon.into()).into());
+                    changes.push(AttributeChange::Foreground(AnsiColor::White.into()).into());
+                }
+                changes.push(Change::CursorPosition {
+                    x: Position::Absolute(cell.col),
+                    y: Position::Absolute(y + 1),
+                });
+                changes.push(Change::Text(cell.grapheme.clone()));
+                if selected || highlight {
+                    changes.push(Change::AllAttributes(Default::default()));
+                }
+            }
+        }
+
+        let mut y = visible_rows + 1;
+        changes.push(Change::CursorPosition {
+            x: Position::Absolute(0),
+            y: Position::Absolute(y),
+        });
+        changes.push(Change::Text("\u{2500}".repeat(size.cols)));
+
+        if let Some(report) = self.current() {
+            for line in report.describe() {
+                y += 1;
+                if y >= size.rows {
+                    break;
+                }
+                changes.push(Change::CursorPosition {
+                    x: Position::Absolute(0),
+                    y: Position::Absolute(y),
+                });
+                changes.push(Change::Text(line.chars().take(size.cols).collect()));
+            }
+        }
+
+        term.render(&changes)?;
+        term.flush()
+    }
+}
+
+/// Presents the glyph inspector, starting with the cell at
+/// `cursor`, which is a (row, column) tuple relative to `lines`
+pub fn glyph_inspector(
+    mut term: TermWizTerminal,
+    lines: Vec<Vec<CellReport>>,
+    cursor: (usize, usize),
+) -> anyhow::Result<()> {
+    term.set_raw_mode()?;
+
+    if lines.iter().all(|line| line.is_empty()) {
+        return Ok(());
+    }
+
+    let mut inspector = Inspector {
+        lines,
+        row: 0,
+        cell: 0,
+        top: 0,
+    };
+    inspector.select(cursor.0, cursor.1);
+    inspector.render(&mut term)?;
+
+    while let Ok(Some(event)) = term.poll_input(None) {
+        match event {
+            InputEvent::Key(KeyEvent {
+                key: KeyCode::Escape | KeyCode::Char('q'),
+                ..
+            }) => break,
+            InputEvent::Key(KeyEvent {
+                key: KeyCode::LeftArrow | KeyCode::Char('h'),
+                ..
+            }) => inspector.move_by(-1),
+            InputEvent::Key(KeyEvent {
+                key: KeyCode::RightArrow | KeyCode::Char('l'),
+                ..
+            }) => inspector.move_by(1),
+            InputEvent::Key(KeyEvent {
+                key: KeyCode::UpArrow | KeyCode::Char('k'),
+                ..
+            }) => {
+                let col = inspector.current_col();
+                inspector.select(inspector.row.saturating_sub(1), col);
+            }
+            InputEvent::Key(KeyEvent {
+                key: KeyCode::DownArrow | KeyCode::Char('j'),
+                ..
+            }) => {
+                let col = inspector.current_col();
+                inspector.select(inspector.row + 1, col);
+            }
+            InputEvent::Key(KeyEvent {
+                key: KeyCode::Home | KeyCode::Char('0'),
+                ..
+            }) => inspector.cell = 0,
+            InputEvent::Key(KeyEvent {
+                key: KeyCode::End | KeyCode::Char('$'),
+                ..
+            }) => {
+                let len = inspector.lines[inspector.row].len();
+                inspector.cell = len.saturating_sub(1);
+            }
+            InputEvent::Key(KeyEvent {
+                key: KeyCode::Char('n'),
+                ..
+            }) => inspector.next_last_resort(),
+            InputEvent::Resized { .. } => {}
+            _ => continue,
+        }
+        inspector.render(&mut term)?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn codepoint_properties() {
+        assert_eq!(describe_codepoint('a'), "U+0061 width=One");
+        assert_eq!(describe_codepoint('字'), "U+5B57 width=Two");
+        assert_eq!(
+            describe_codepoint('\u{1F600}'),
+            "U+1F600 width=WidenedIn9 emoji-presentation"
+        );
+        assert_eq!(describe_codepoint(' '), "U+0020 width=One white-space");
+        assert!(describe_codepoint('\u{e0b0}').contains("nerdfont=pl_left_hard_divider"));
+    }
+
+    fn cell(col: usize, source: FontSource) -> CellReport {
+        CellReport {
+            col,
+            grapheme: "x".to_string(),
+            width: 1,
+            source,
+            font: String::new(),
+            font_location: String::new(),
+            chain: vec![],
+            shaped_text: "x".to_string(),
+            glyphs: vec![],
+        }
+    }
+
+    fn new_inspector(lines: Vec<Vec<CellReport>>) -> Inspector {
+        Inspector {
+            lines,
+            row: 0,
+            cell: 0,
+            top: 0,
+        }
+    }
+
+    #[test]
+    fn select_closest_cell() {
+        use FontSource::Primary;
+        // The second row has a double width cell at column 1
+        let mut inspector = new_inspector(vec![
+            vec![cell(0, Primary), cell(1, Primary), cell(2, Primary)],
+            vec![cell(0, Primary), cell(1, Primary), cell(3, Primary)],
+            vec![],
+        ]);
+
+        inspector.select(1, 2);
+        assert_eq!((inspector.row, inspector.current_col()), (1, 1));
+
+        inspector.select(0, 10);
+        assert_eq!((inspector.row, inspector.current_col()), (0, 2));
+
+        // Rows past the end select the last row, even if it is empty
+        inspector.select(5, 1);
+        assert_eq!(inspector.row, 2);
+        assert!(inspector.current().is_none());
+    }
+
+    #[test]
+    fn move_within_row() {
+        use FontSource::Primary;
+        let mut inspector = new_inspector(vec![
+            vec![cell(0, Primary), cell(1, Primary), cell(2, Primary)],
+            vec![],
+        ]);
+
+        inspector.move_by(1);
+        assert_eq!(inspector.cell, 1);
+        inspector.move_by(5);
+        assert_eq!(inspector.cell, 2);
+        inspector.move_by(-5);
+        assert_eq!(inspector.cell, 0);
+
+        inspector.select(1, 0);
+        inspector.move_by(1);
+        assert_eq!(inspector.cell, 0);
+    }
+
+    #[test]
+    fn next_last_resort_wraps() {
+        use FontSource::{LastResort, Missing, Primary};
+        let mut inspector = new_inspector(vec![
+            vec![cell(0, Primary), cell(1, LastResort)],
+            vec![cell(0, Primary)],
+            vec![cell(0, Missing), cell(1, Primary)],
+        ]);
+
+        inspector.next_last_resort();
+        assert_eq!((inspector.row, inspector.cell), (0, 1));
+        inspector.next_last_resort();
+        assert_eq!((inspector.row, inspector.cell), (2, 0));
+        // Wraps around to the first one
+        inspector.next_last_resort();
+        assert_eq!((inspector.row, inspector.cell), (0, 1));
+
+        // Nothing to find leaves the selection alone
+        let mut inspector = new_inspector(vec![vec![cell(0, Primary), cell(1, Primary)]]);
+        inspector.move_by(1);
+        inspector.next_last_resort();
+        assert_eq!((inspector.row, inspector.cell), (0, 1));
+    }
+}
@@ -0,0 +1,178 @@
+//! An overlay that shows a live hex dump of the data that a
+//! serial port pane receives
+use mux::pane::Pane;
+use mux::termwiztermtab::TermWizTerminal;
+use smol::channel::{Receiver, TryRecvError};
+use std::collections::VecDeque;
+use std::io::Write;
+use std::sync::Arc;
+use std::time::Duration;
+use termwiz::color::ColorAttribute;
+use termwiz::input::{InputEvent, KeyCode, KeyEvent};
+use termwiz::surface::{Change, CursorVisibility, Position};
+use termwiz::terminal::Terminal;
+
+const BYTES_PER_ROW: usize = 16;
+
+const TITLE: &str = "Serial hex view: press Escape to close";
+
+/// How long to wait for input before checking for more data
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+struct HexView {
+    /// The most recently received bytes
+    bytes: VecDeque<u8>,
+    /// The offset of the first byte in `bytes` within the stream
+    offset: usize,
+}
+
+impl HexView {
+    fn push(&mut self, data: &[u8], capacity: usize) {
+        self.bytes.extend(data);
+        if self.bytes.len() > capacity {
+            // Drop whole rows so that the offsets stay aligned
+            let rows = (self.bytes.len() - capacity + BYTES_PER_ROW - 1) / BYTES_PER_ROW;
+            let excess = (rows * BYTES_PER_ROW).min(self.bytes.len());
+            self.bytes.drain(..excess);
+            self.offset += excess;
+        }
+    }
+
+    fn render(&self, term: &mut TermWizTerminal) -> termwiz::Result<()> {
+        let size = term.get_screen_size()?;
+        let mut changes = vec![
+            Change::ClearScreen(ColorAttribute::Default),
+            Change::CursorVisibility(CursorVisibility::Hidden),
+            Change::Text(TITLE.to_string()),
+        ];
+
+        let bytes: Vec<u8> = self.bytes.iter().copied().collect();
+        for (idx, row) in bytes.chunks(BYTES_PER_ROW).enumerate() {
+            let mut hex = String::with_capacity(BYTES_PER_ROW * 3);
+            for (i, b) in row.iter().enumerate() {
+                if i == BYTES_PER_ROW / 2 {
+                    hex.push(' ');
+                }
+                hex.push_str(&format!("{b:02x} "));
+            }
+            let ascii: String = row
+                .iter()
+                .map(|&b| {
+                    if b.is_ascii_graphic() || b == b' ' {
+                        b as char
+                    } else {
+                        '.'
+                    }
+                })
+                .collect();
+            changes.push(Change::CursorPosition {
+                x: Position::Absolute(0),
+                y: Position::Absolute(idx + 1),
+            });
+            changes.push(Change::Text(format!(
+                "{:08x}  {hex:<width$} |{ascii}|",
+                self.offset + idx * BYTES_PER_ROW,
+                width = BYTES_PER_ROW * 3 + 1,
+            )));
+            if idx + 2 >= size.rows {
+                break;
+            }
+        }
+
+        term.render(&changes)?;
+        term.flush()
+    }
+}
+
+/// Shows the data that arrives on `data` until the user presses
+/// Escape.  Other keys are sent through to `pane`, so that the
+/// device can still be interacted with while the hex dump is shown.
+pub fn show_hex_view(
+    mut term: TermWizTerminal,
+    pane: Arc<dyn Pane>,
+    data: Receiver<Arc<Vec<u8>>>,
+) -> anyhow::Result<()> {
+    term.set_raw_mode()?;
+
+    let mut view = HexView {
+        bytes: VecDeque::new(),
+        offset: 0,
+    };
+    view.render(&mut term)?;
+
+    loop {
+        let mut changed = false;
+        loop {
+            match data.try_recv() {
+                Ok(chunk) => {
+                    let size = term.get_screen_size()?;
+                    let capacity = size.rows.saturating_sub(1).max(1) * BYTES_PER_ROW;
+                    view.push(&chunk, capacity);
+                    changed = true;
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Closed) => return Ok(()),
+            }
+        }
+        if changed {
+            view.render(&mut term)?;
+        }
+
+        match term.poll_input(Some(POLL_INTERVAL))? {
+            Some(InputEvent::Key(KeyEvent {
+                key: KeyCode::Escape,
+                ..
+            })) => return Ok(()),
+            Some(InputEvent::Key(KeyEvent {
+                key: KeyCode::Char(c),
+                ..
+            })) => {
+                let mut buf = [0u8; 4];
+                pane.writer()
+                    .write_all(c.encode_utf8(&mut buf).as_bytes())?;
+            }
+            Some(InputEvent::Key(KeyEvent {
+                key: KeyCode::Enter,
+                ..
+            })) => {
+                pane.writer().write_all(b"\r")?;
+            }
+            Some(InputEvent::Resized { .. }) => {
+                view.render(&mut term)?;
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn push_keeps_whole_rows() {
+        let mut view = HexView {
+            bytes: VecDeque::new(),
+            offset: 0,
+        };
+        let capacity = 2 * BYTES_PER_ROW;
+
+        view.push(&[0u8; 20], capacity);
+        assert_eq!(view.bytes.len(), 20);
+        assert_eq!(view.offset, 0);
+
+        // Exceeding the capacity drops the oldest whole row
+        let data: Vec<u8> = (0..20).collect();
+        view.push(&data, capacity);
+        assert_eq!(view.offset, BYTES_PER_ROW);
+        assert_eq!(view.bytes.len(), 40 - BYTES_PER_ROW);
+        assert_eq!(view.offset % BYTES_PER_ROW, 0);
+        assert_eq!(view.bytes.back(), Some(&19));
+
+        // A large chunk leaves at most the capacity
+        view.push(&[1u8; 100], capacity);
+        assert!(view.bytes.len() <= capacity);
+        assert_eq!(view.offset + view.bytes.len(), 140);
+        assert_eq!(view.offset % BYTES_PER_ROW, 0);
+    }
+}
@@ -278,6 +278,21 @@ impl LauncherState {
                         action: KeyAssignment::SwitchToWorkspace {
                             name: Some(ws.clone()),
                             spawn: None,
+                            layout: None,
+                        },
+                    });
+                }
+            }
+            let mut layouts: Vec<_> = config.workspace_layouts.iter().collect();
+            layouts.sort_by(|a, b| a.0.cmp(b.0));
+            for (name, layout) in layouts {
+                if !args.workspaces.contains(name) {
+                    self.entries.push(Entry {
+                        label: format!("Create workspace from layout: {}", layout.label(name)),
+                        action: KeyAssignment::SwitchToWorkspace {
+                            name: Some(name.clone()),
+                            spawn: None,
+                            layout: Some(name.clone()),
                         },
                     });
                 }
@@ -290,6 +305,7 @@ impl LauncherState {
                 action: KeyAssignment::SwitchToWorkspace {
                     name: None,
                     spawn: None,
+                    layout: None,
                 },
             });
         }
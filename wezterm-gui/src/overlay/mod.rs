@@ -10,10 +10,14 @@ pub mod confirm;
 pub mod confirm_close_pane;
 pub mod copy;
 pub mod debug;
+pub mod global_search;
+pub mod glyph_inspector;
+pub mod hexdump;
 pub mod launcher;
 pub mod prompt;
 pub mod quickselect;
 pub mod selector;
+pub mod transfer;
 
 pub use confirm_close_pane::{
     confirm_close_pane, confirm_close_tab, confirm_close_window, confirm_quit_program,
@@ -68,11 +72,13 @@ where
 {
     let pane_id = pane.pane_id();
     let dims = pane.get_dimensions();
+    // Use the cells of the pane, which may have its own font size
+    let render_metrics = term_window.fonts_for_pane(pane_id).1;
     let size = TerminalSize {
         cols: dims.cols,
         rows: dims.viewport_rows,
-        pixel_width: term_window.render_metrics.cell_size.width as usize * dims.cols,
-        pixel_height: term_window.render_metrics.cell_size.height as usize * dims.viewport_rows,
+        pixel_width: render_metrics.cell_size.width as usize * dims.cols,
+        pixel_height: render_metrics.cell_size.height as usize * dims.viewport_rows,
         dpi: dims.dpi,
     };
     let term_config: Arc<dyn TerminalConfiguration + Send + Sync> =
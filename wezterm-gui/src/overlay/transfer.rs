@@ -0,0 +1,202 @@
+//! Overlays that show the progress of transferring files to and
+//! from the remote host of an ssh domain pane
+use mux::pane::Pane;
+use mux::termwiztermtab::TermWizTerminal;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use termwiz::color::ColorAttribute;
+use termwiz::input::InputEvent;
+use termwiz::surface::{Change, CursorVisibility, Position};
+use termwiz::terminal::Terminal;
+use wezterm_ssh::{Sftp, Utf8PathBuf};
+
+/// Limit how often the progress is rendered, so that
+/// fast transfers don't spend their time drawing
+const RENDER_INTERVAL: Duration = Duration::from_millis(100);
+
+struct ProgressRenderer {
+    verb: &'static str,
+    last_render: Option<Instant>,
+}
+
+impl ProgressRenderer {
+    fn new(verb: &'static str) -> Self {
+        Self {
+            verb,
+            last_render: None,
+        }
+    }
+
+    fn update(&mut self, term: &mut TermWizTerminal, name: &str, transferred: u64, total: u64) {
+        let now = Instant::now();
+        let done = total > 0 && transferred >= total;
+        if let Some(last) = self.last_render {
+            if !done && now.duration_since(last) < RENDER_INTERVAL {
+                return;
+            }
+        }
+        self.last_render.replace(now);
+        if let Err(err) = self.render(term, name, transferred, total) {
+            log::error!("rendering transfer progress: {err:#}");
+        }
+    }
+
+    fn render(
+        &self,
+        term: &mut TermWizTerminal,
+        name: &str,
+        transferred: u64,
+        total: u64,
+    ) -> termwiz::Result<()> {
+        let size = term.get_screen_size()?;
+        // Render 80% wide, centered
+        let width = size.cols * 80 / 100;
+        let x_pos = size.cols * 10 / 100;
+        let top_row = size.rows.saturating_sub(3) / 2;
+
+        let status = if total > 0 {
+            format!(
+                "{} / {} ({}%)",
+                mux::sftp::format_size(transferred),
+                mux::sftp::format_size(total),
+                transferred * 100 / total
+            )
+        } else {
+            mux::sftp::format_size(transferred)
+        };
+
+        // The bar is drawn between a pair of brackets
+        let bar_width = width.saturating_sub(2);
+        let filled = if total > 0 {
+            (bar_width as u64 * transferred.min(total) / total) as usize
+        } else {
+            0
+        };
+
+        let changes = vec![
+            Change::ClearScreen(ColorAttribute::Default),
+            Change::CursorVisibility(CursorVisibility::Hidden),
+            Change::CursorPosition {
+                x: Position::Absolute(x_pos),
+                y: Position::Absolute(top_row),
+            },
+            Change::Text(format!("{} {name}", self.verb)),
+            Change::CursorPosition {
+                x: Position::Absolute(x_pos),
+                y: Position::Absolute(top_row + 1),
+            },
+            Change::Text(format!(
+                "[{}{}]",
+                "#".repeat(filled),
+                " ".repeat(bar_width - filled)
+            )),
+            Change::CursorPosition {
+                x: Position::Absolute(x_pos),
+                y: Position::Absolute(top_row + 2),
+            },
+            Change::Text(status),
+        ];
+
+        term.render(&changes)?;
+        term.flush()
+    }
+}
+
+/// Shows the error and waits for a key press so that
+/// the user has a chance to read it
+fn show_error(term: &mut TermWizTerminal, verb: &str, err: &anyhow::Error) -> anyhow::Result<()> {
+    let size = term.get_screen_size()?;
+    let message = format!("{verb} failed: {err:#}\n\nPress any key to close");
+    let wrapped = textwrap::fill(&message, size.cols * 80 / 100);
+
+    let mut changes = vec![
+        Change::ClearScreen(ColorAttribute::Default),
+        Change::CursorVisibility(CursorVisibility::Hidden),
+    ];
+    for (y, row) in wrapped.split('\n').enumerate() {
+        changes.push(Change::CursorPosition {
+            x: Position::Absolute(size.cols * 10 / 100),
+            y: Position::Absolute(1 + y),
+        });
+        changes.push(Change::Text(row.trim_end().to_string()));
+    }
+    term.render(&changes)?;
+    term.flush()?;
+
+    while let Ok(Some(event)) = term.poll_input(None) {
+        if let InputEvent::Key(_) = event {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Uploads `paths` into the working directory of `pane` on its remote
+/// host, showing the progress as it goes.
+/// Returns the remote paths of the uploaded files.
+pub fn upload_files(
+    mut term: TermWizTerminal,
+    sftp: Sftp,
+    pane: Arc<dyn Pane>,
+    paths: Vec<PathBuf>,
+) -> anyhow::Result<Vec<Utf8PathBuf>> {
+    term.set_raw_mode()?;
+    let mut progress = ProgressRenderer::new("Uploading");
+
+    let result: anyhow::Result<Vec<Utf8PathBuf>> = smol::block_on(async {
+        let dir = mux::sftp::remote_cwd(&sftp, &*pane).await?;
+        let mut uploaded = vec![];
+        for path in &paths {
+            let name = path.display().to_string();
+            let remote = mux::sftp::upload_file(&sftp, path, &dir, &mut |transferred, total| {
+                progress.update(&mut term, &name, transferred, total)
+            })
+            .await?;
+            uploaded.push(remote);
+        }
+        Ok(uploaded)
+    });
+
+    if let Err(err) = &result {
+        show_error(&mut term, "Upload", err)?;
+    }
+    result
+}
+
+/// Downloads the remote file `path`, resolved relative to the working
+/// directory of `pane`, into the downloads folder, showing the
+/// progress as it goes.
+pub fn download_file(
+    mut term: TermWizTerminal,
+    sftp: Sftp,
+    pane: Arc<dyn Pane>,
+    path: String,
+) -> anyhow::Result<()> {
+    term.set_raw_mode()?;
+    let mut progress = ProgressRenderer::new("Downloading");
+
+    let mut download = || -> anyhow::Result<()> {
+        let remote = smol::block_on(async {
+            let cwd = mux::sftp::remote_cwd(&sftp, &*pane).await?;
+            anyhow::Result::<_>::Ok(mux::sftp::resolve_remote_path(&cwd, &path))
+        })?;
+        let name = remote.to_string();
+        // Stream the file into place, rather than buffering all of it
+        crate::download::stream_to_downloads(remote.file_name().map(|s| s.to_string()), |file| {
+            smol::block_on(mux::sftp::download_file(
+                &sftp,
+                &remote,
+                file,
+                &mut |transferred, total| progress.update(&mut term, &name, transferred, total),
+            ))?;
+            Ok(())
+        })
+    };
+    let result = download();
+
+    if let Err(err) = &result {
+        show_error(&mut term, "Download", err)?;
+    }
+    result
+}
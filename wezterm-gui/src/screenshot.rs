@@ -0,0 +1,799 @@
+//! A software renderer that lays out lines of a terminal and draws them
+//! into an RGBA image on the CPU.  It shapes and rasterizes text with the
+//! same fonts and glyph cache as the gpu renderer, including the custom
+//! block and line glyphs, but doesn't need a window or a gpu, which makes
+//! it suitable for screenshots and for rendering tests that run in CI.
+use crate::customglyph::BlockKey;
+use crate::glyphcache::GlyphCache;
+use crate::termwindow::render::resolve_fg_color_attr;
+use crate::utilsprites::RenderMetrics;
+use ::window::bitmaps::atlas::{OutOfTextureSpace, Sprite};
+use ::window::bitmaps::{BitmapImage, ImageTexture};
+use ::window::color::LinearRgba;
+use anyhow::Context;
+use config::{ConfigHandle, DimensionContext};
+use std::path::Path;
+use std::rc::Rc;
+use termwiz::surface::{CursorShape, CursorVisibility, Line, SEQ_ZERO};
+use wezterm_font::shaper::PresentationWidth;
+use wezterm_font::FontConfiguration;
+use wezterm_gui_subcommands::{GuiCliCommand, GuiCliSubCommand, ScreenshotCommand};
+use wezterm_term::color::{ColorAttribute, ColorPalette};
+use wezterm_term::StableRowIndex;
+
+/// The size of the glyph atlas to start with; it is grown as needed
+const INITIAL_ATLAS_SIZE: usize = 1024;
+
+/// The position of the cursor relative to the rendered lines
+#[derive(Debug, Clone, Copy)]
+pub struct HeadlessCursor {
+    pub x: usize,
+    pub y: usize,
+    pub shape: CursorShape,
+}
+
+/// An RGBA framebuffer in linear color space
+pub struct Canvas {
+    pub width: usize,
+    pub height: usize,
+    pixels: Vec<LinearRgba>,
+}
+
+impl Canvas {
+    fn new(width: usize, height: usize, color: LinearRgba) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![color; width * height],
+        }
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> LinearRgba {
+        self.pixels[y * self.width + x]
+    }
+
+    fn fill_rect(&mut self, x: isize, y: isize, width: isize, height: isize, color: LinearRgba) {
+        let x0 = x.max(0) as usize;
+        let y0 = y.max(0) as usize;
+        let x1 = ((x + width).max(0) as usize).min(self.width);
+        let y1 = ((y + height).max(0) as usize).min(self.height);
+        for y in y0..y1 {
+            for x in x0..x1 {
+                self.pixels[y * self.width + x] = color;
+            }
+        }
+    }
+
+    /// Composites a sprite from the in-memory glyph atlas with its top left
+    /// corner at x, y.  When `color` is specified, the alpha channel of the
+    /// sprite is used as the coverage for that color, which is how text and
+    /// the custom glyphs are drawn.  Otherwise the sprite is a color image,
+    /// such as an emoji, and is composited as-is.
+    fn draw_sprite(&mut self, sprite: &Sprite, x: isize, y: isize, color: Option<LinearRgba>) {
+        let texture = match sprite.texture.downcast_ref::<ImageTexture>() {
+            Some(texture) => texture,
+            None => return,
+        };
+        let image = texture.image.borrow();
+        let (tex_width, _) = image.image_dimensions();
+        let data = image.pixel_data_slice();
+
+        let coords = sprite.coords;
+        for sy in 0..coords.size.height {
+            let dy = y + sy;
+            if dy < 0 || dy as usize >= self.height {
+                continue;
+            }
+            for sx in 0..coords.size.width {
+                let dx = x + sx;
+                if dx < 0 || dx as usize >= self.width {
+                    continue;
+                }
+                let offset = (((coords.origin.y + sy) as usize * tex_width)
+                    + (coords.origin.x + sx) as usize)
+                    * 4;
+                let texel = &data[offset..offset + 4];
+                let alpha = texel[3];
+                if alpha == 0 {
+                    continue;
+                }
+
+                // The atlas holds premultiplied srgb
+                let src = match color {
+                    Some(color) => color,
+                    None => {
+                        let demultiply = |c: u8| (c as u32 * 255 / alpha as u32).min(255) as u8;
+                        LinearRgba::with_srgba(
+                            demultiply(texel[0]),
+                            demultiply(texel[1]),
+                            demultiply(texel[2]),
+                            255,
+                        )
+                    }
+                };
+                let coverage = alpha as f32 / 255.;
+
+                let dest = &mut self.pixels[dy as usize * self.width + dx as usize];
+                let (sr, sg, sb, _) = src.tuple();
+                let (dr, dg, db, da) = dest.tuple();
+                *dest = LinearRgba(
+                    sr * coverage + dr * (1. - coverage),
+                    sg * coverage + dg * (1. - coverage),
+                    sb * coverage + db * (1. - coverage),
+                    da,
+                );
+            }
+        }
+    }
+
+    /// Converts the canvas to 8-bit srgb
+    pub fn to_rgba_image(&self) -> image::RgbaImage {
+        let mut data = Vec::with_capacity(self.width * self.height * 4);
+        for pixel in &self.pixels {
+            let (r, g, b, a) = pixel.to_srgb().to_srgb_u8();
+            data.extend_from_slice(&[r, g, b, a]);
+        }
+        image::RgbaImage::from_raw(self.width as u32, self.height as u32, data)
+            .expect("buffer size to match dimensions")
+    }
+
+    pub fn save_png(&self, path: &Path) -> anyhow::Result<()> {
+        self.to_rgba_image()
+            .save_with_format(path, image::ImageFormat::Png)
+            .with_context(|| format!("saving {}", path.display()))
+    }
+}
+
+pub struct HeadlessRenderer {
+    config: ConfigHandle,
+    fonts: Rc<FontConfiguration>,
+    pub render_metrics: RenderMetrics,
+    glyph_cache: GlyphCache,
+}
+
+impl HeadlessRenderer {
+    pub fn new(config: &ConfigHandle) -> anyhow::Result<Self> {
+        let dpi = config.dpi.unwrap_or_else(|| ::window::default_dpi()) as usize;
+        let fonts = Rc::new(FontConfiguration::new(Some(config.clone()), dpi)?);
+        let render_metrics = RenderMetrics::new(&fonts)?;
+        let glyph_cache = GlyphCache::new_in_memory(&fonts, INITIAL_ATLAS_SIZE)?;
+        Ok(Self {
+            config: config.clone(),
+            fonts,
+            render_metrics,
+            glyph_cache,
+        })
+    }
+
+    fn padding(&self, cols: usize, rows: usize) -> (isize, isize, isize, isize) {
+        let dpi = self.fonts.get_dpi() as f32;
+        let cell = &self.render_metrics.cell_size;
+        let h_context = DimensionContext {
+            dpi,
+            pixel_max: (cols as isize * cell.width) as f32,
+            pixel_cell: cell.width as f32,
+        };
+        let v_context = DimensionContext {
+            dpi,
+            pixel_max: (rows as isize * cell.height) as f32,
+            pixel_cell: cell.height as f32,
+        };
+        let padding = &self.config.window_padding;
+        (
+            padding.left.evaluate_as_pixels(h_context) as isize,
+            padding.top.evaluate_as_pixels(v_context) as isize,
+            padding.right.evaluate_as_pixels(h_context) as isize,
+            padding.bottom.evaluate_as_pixels(v_context) as isize,
+        )
+    }
+
+    /// Renders the lines into a canvas that is `cols` cells wide,
+    /// surrounded by the configured window padding
+    pub fn render(
+        &mut self,
+        lines: &[Line],
+        cols: usize,
+        palette: &ColorPalette,
+        cursor: Option<HeadlessCursor>,
+    ) -> anyhow::Result<Canvas> {
+        loop {
+            match self.render_impl(lines, cols, palette, cursor) {
+                Ok(canvas) => return Ok(canvas),
+                Err(err) => {
+                    if let Some(&OutOfTextureSpace {
+                        size: Some(size), ..
+                    }) = err.root_cause().downcast_ref::<OutOfTextureSpace>()
+                    {
+                        log::trace!("grow texture atlas to {size}");
+                        self.glyph_cache = GlyphCache::new_in_memory(&self.fonts, size)?;
+                        continue;
+                    }
+                    return Err(err);
+                }
+            }
+        }
+    }
+
+    fn render_impl(
+        &mut self,
+        lines: &[Line],
+        cols: usize,
+        palette: &ColorPalette,
+        cursor: Option<HeadlessCursor>,
+    ) -> anyhow::Result<Canvas> {
+        let metrics = self.render_metrics.clone();
+        let cell_width = metrics.cell_size.width;
+        let cell_height = metrics.cell_size.height;
+        let cols = lines.iter().map(|line| line.len()).fold(cols, usize::max);
+        let (left, top, right, bottom) = self.padding(cols, lines.len());
+
+        let mut canvas = Canvas::new(
+            (left + right + cols as isize * cell_width) as usize,
+            (top + bottom + lines.len() as isize * cell_height) as usize,
+            palette.background.to_linear(),
+        );
+
+        for (row, line) in lines.iter().enumerate() {
+            let row_top = top + row as isize * cell_height;
+            let (bidi_enabled, bidi_direction) = line.bidi_info();
+            let bidi_hint = if bidi_enabled {
+                Some(bidi_direction)
+            } else {
+                None
+            };
+            let cursor = cursor.filter(|c| c.y == row);
+            let cursor_is_block = matches!(
+                cursor.map(|c| c.shape),
+                Some(CursorShape::Default | CursorShape::BlinkingBlock | CursorShape::SteadyBlock)
+            );
+
+            for cluster in line.cluster(bidi_hint) {
+                let attrs = &cluster.attrs;
+                let style = self.fonts.match_style(&self.config, attrs);
+                let mut fg =
+                    resolve_fg_color_attr(attrs, attrs.foreground(), palette, &self.config, style);
+                let mut bg = palette.resolve_bg(attrs.background()).to_linear();
+                if attrs.reverse() {
+                    std::mem::swap(&mut fg, &mut bg);
+                }
+                let underline_color = match attrs.underline_color() {
+                    ColorAttribute::Default => fg,
+                    color => resolve_fg_color_attr(attrs, color, palette, &self.config, style),
+                };
+                let cluster_left = left + cluster.first_cell_idx as isize * cell_width;
+
+                if bg != palette.background.to_linear() {
+                    canvas.fill_rect(
+                        cluster_left,
+                        row_top,
+                        cluster.width as isize * cell_width,
+                        cell_height,
+                        bg,
+                    );
+                }
+
+                if let Some(cursor) = cursor {
+                    let cursor_cells =
+                        cluster.first_cell_idx..cluster.first_cell_idx + cluster.width;
+                    if cursor_cells.contains(&cursor.x) {
+                        let sprite =
+                            self.glyph_cache
+                                .cursor_sprite(Some(cursor.shape), &metrics, 1)?;
+                        canvas.draw_sprite(
+                            &sprite,
+                            left + cursor.x as isize * cell_width,
+                            row_top,
+                            Some(palette.cursor_bg.to_linear()),
+                        );
+                    }
+                }
+
+                if attrs.underline() != termwiz::cell::Underline::None
+                    || attrs.strikethrough()
+                    || attrs.overline()
+                {
+                    let sprite = self.glyph_cache.cached_line_sprite(
+                        false,
+                        attrs.strikethrough(),
+                        attrs.underline(),
+                        attrs.overline(),
+                        &metrics,
+                    )?;
+                    for idx in 0..cluster.width {
+                        canvas.draw_sprite(
+                            &sprite,
+                            cluster_left + idx as isize * cell_width,
+                            row_top,
+                            Some(underline_color),
+                        );
+                    }
+                }
+
+                if attrs.invisible() {
+                    continue;
+                }
+
+                let font = self.fonts.resolve_font(style)?;
+                let presentation_width = PresentationWidth::with_cluster(&cluster);
+                let infos = font.blocking_shape(
+                    &cluster.text,
+                    Some(cluster.presentation),
+                    cluster.direction,
+                    None,
+                    Some(&presentation_width),
+                )?;
+
+                let mut pen_x = cluster_left;
+                for info in &infos {
+                    let cell_idx = cluster.byte_to_cell_idx(info.cluster as usize);
+                    let num_cells = cluster.byte_to_cell_width(info.cluster as usize);
+                    let followed_by_space = match line.get_cell(cell_idx + 1) {
+                        Some(cell) => cell.str() == " ",
+                        None => false,
+                    };
+                    let glyph = self.glyph_cache.cached_glyph(
+                        info,
+                        style,
+                        followed_by_space,
+                        &font,
+                        &metrics,
+                        num_cells,
+                    )?;
+
+                    let glyph_fg = match cursor {
+                        Some(cursor) if cursor_is_block && cursor.x == cell_idx => {
+                            palette.cursor_fg.to_linear()
+                        }
+                        _ => fg,
+                    };
+
+                    let block = if self.config.custom_block_glyphs {
+                        info.only_char.and_then(BlockKey::from_char)
+                    } else {
+                        None
+                    };
+
+                    match block {
+                        Some(block) => {
+                            // Custom glyphs are drawn relative to the top
+                            // left of the cell rather than the baseline
+                            let sprite = self.glyph_cache.cached_block(block, &metrics)?;
+                            canvas.draw_sprite(&sprite, pen_x, row_top, Some(glyph_fg));
+                        }
+                        None => {
+                            if let Some(sprite) = &glyph.texture {
+                                let x = pen_x + (glyph.x_offset + glyph.bearing_x).get() as isize;
+                                let y = row_top
+                                    + cell_height
+                                    + (metrics.descender - (glyph.y_offset + glyph.bearing_y)).get()
+                                        as isize;
+                                let color = if glyph.has_color {
+                                    None
+                                } else {
+                                    Some(glyph_fg)
+                                };
+                                canvas.draw_sprite(sprite, x, y, color);
+                            }
+                        }
+                    }
+
+                    pen_x += num_cells as isize * cell_width;
+                }
+            }
+
+            // The cursor may be beyond the end of the line
+            if let Some(cursor) = cursor {
+                if cursor.x >= line.len() {
+                    let sprite = self
+                        .glyph_cache
+                        .cursor_sprite(Some(cursor.shape), &metrics, 1)?;
+                    canvas.draw_sprite(
+                        &sprite,
+                        left + cursor.x as isize * cell_width,
+                        row_top,
+                        Some(palette.cursor_bg.to_linear()),
+                    );
+                }
+            }
+        }
+
+        Ok(canvas)
+    }
+}
+
+/// Runs the `wezterm cli` subcommands that `wezterm` delegates to us
+pub fn run_cli(config: ConfigHandle, cli: GuiCliCommand) -> anyhow::Result<()> {
+    // The config error window needs a gui, which we don't have
+    config::assign_error_callback(|err| eprintln!("{}", err));
+
+    match cli.sub {
+        GuiCliSubCommand::Screenshot(cmd) => {
+            let class = cli
+                .class
+                .unwrap_or_else(|| wezterm_gui_subcommands::DEFAULT_WINDOW_CLASS.to_string());
+            let no_auto_start = cli.no_auto_start;
+            let prefer_mux = cli.prefer_mux;
+            let request = cmd.clone();
+
+            let executor = promise::spawn::ScopedExecutor::new();
+            let screen = promise::spawn::block_on(executor.run(async move {
+                fetch_screen(&request, no_auto_start, prefer_mux, &class).await
+            }))?;
+
+            let mut renderer = HeadlessRenderer::new(&config)?;
+            let canvas =
+                renderer.render(&screen.lines, screen.cols, &screen.palette, screen.cursor)?;
+            canvas.save_png(&cmd.output)?;
+            println!("{}", cmd.output.display());
+            Ok(())
+        }
+    }
+}
+
+/// The content of a pane, as retrieved from the mux
+struct Screen {
+    lines: Vec<Line>,
+    cols: usize,
+    palette: ColorPalette,
+    cursor: Option<HeadlessCursor>,
+}
+
+async fn fetch_screen(
+    cmd: &ScreenshotCommand,
+    no_auto_start: bool,
+    prefer_mux: bool,
+    class: &str,
+) -> anyhow::Result<Screen> {
+    let mut ui = mux::connui::ConnectionUI::new_headless();
+    let client = wezterm_client::client::Client::new_default_unix_domain(
+        true,
+        &mut ui,
+        no_auto_start,
+        prefer_mux,
+        class,
+    )?;
+    let pane_id = client.resolve_pane_id(cmd.pane_id).await?;
+
+    let info = client
+        .get_dimensions(codec::GetPaneRenderableDimensions { pane_id })
+        .await?;
+    let dims = info.dimensions;
+
+    let resolve_line =
+        |n: isize| -> StableRowIndex { (dims.physical_top + n).max(dims.scrollback_top) };
+    let start_line = cmd
+        .start_line
+        .map(resolve_line)
+        .unwrap_or(dims.physical_top);
+    let end_line = cmd
+        .end_line
+        .map(resolve_line)
+        .unwrap_or(dims.physical_top + dims.viewport_rows as StableRowIndex - 1);
+    anyhow::ensure!(
+        end_line >= start_line,
+        "the end line must not be before the start line"
+    );
+
+    let response = client
+        .get_lines(codec::GetLines {
+            pane_id,
+            lines: vec![start_line..end_line + 1],
+        })
+        .await?;
+    let mut lines = vec![Line::with_width(0, SEQ_ZERO); (end_line + 1 - start_line) as usize];
+    for (row, line) in response.lines.extract_data().0 {
+        if let Some(slot) = lines.get_mut((row - start_line) as usize) {
+            *slot = line;
+        }
+    }
+
+    let palette = client
+        .get_pane_palette(codec::GetPanePalette { pane_id })
+        .await?
+        .palette;
+
+    let cursor_position = info.cursor_position;
+    let cursor = if cmd.no_cursor
+        || cursor_position.visibility == CursorVisibility::Hidden
+        || cursor_position.y < start_line
+        || cursor_position.y > end_line
+    {
+        None
+    } else {
+        Some(HeadlessCursor {
+            x: cursor_position.x,
+            y: (cursor_position.y - start_line) as usize,
+            shape: cursor_position.shape,
+        })
+    };
+
+    Ok(Screen {
+        lines,
+        cols: dims.cols,
+        palette,
+        cursor,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tabbar::TabBarState;
+    use crate::termwindow::{PaneInformation, TabInformation};
+    use image::RgbaImage;
+    use std::collections::HashMap;
+    use termwiz::cell::CellAttributes;
+    use termwiz::color::AnsiColor;
+    use wezterm_term::Progress;
+
+    /// The test configuration, without any window padding.  It is
+    /// passed to the renderer rather than made the current
+    /// configuration, as tests run concurrently.
+    fn test_config() -> config::Config {
+        let mut config = config::test_configuration();
+        config.window_padding = config::WindowPadding {
+            left: config::Dimension::Pixels(0.),
+            top: config::Dimension::Pixels(0.),
+            right: config::Dimension::Pixels(0.),
+            bottom: config::Dimension::Pixels(0.),
+        };
+        config
+    }
+
+    fn renderer() -> HeadlessRenderer {
+        HeadlessRenderer::new(&test_config().into()).unwrap()
+    }
+
+    /// Glyphs are rasterized slightly differently by different
+    /// versions of freetype, so channels may differ by a little,
+    /// and a small proportion of the pixels may differ by more.
+    /// Returns a description of the difference if the images
+    /// don't match.
+    fn compare_images(golden: &RgbaImage, actual: &RgbaImage) -> Option<String> {
+        const CHANNEL_TOLERANCE: u8 = 8;
+        if golden.dimensions() != actual.dimensions() {
+            return Some(format!(
+                "the size is {:?} rather than {:?}",
+                actual.dimensions(),
+                golden.dimensions()
+            ));
+        }
+        let different = golden
+            .pixels()
+            .zip(actual.pixels())
+            .filter(|(a, b)| {
+                a.0.iter()
+                    .zip(b.0.iter())
+                    .any(|(a, b)| a.abs_diff(*b) > CHANNEL_TOLERANCE)
+            })
+            .count();
+        let allowed = (golden.width() * golden.height()) as usize / 200;
+        if different > allowed {
+            Some(format!("{different} pixels differ"))
+        } else {
+            None
+        }
+    }
+
+    /// Compares the canvas with the golden image `golden/{name}.png`.
+    /// When they differ, the canvas is saved as `golden/{name}.actual.png`
+    /// for inspection.  Run the tests with `WEZTERM_UPDATE_GOLDEN=1` in
+    /// the environment to save the canvas as the golden image instead.
+    fn assert_golden(name: &str, canvas: &Canvas) {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("golden");
+        let golden_path = dir.join(format!("{name}.png"));
+        let actual_path = dir.join(format!("{name}.actual.png"));
+
+        if std::env::var_os("WEZTERM_UPDATE_GOLDEN").is_some() {
+            std::fs::create_dir_all(&dir).unwrap();
+            canvas.save_png(&golden_path).unwrap();
+            return;
+        }
+
+        let problem = match image::open(&golden_path) {
+            Ok(golden) => compare_images(&golden.to_rgba8(), &canvas.to_rgba_image()),
+            Err(err) => Some(format!(
+                "{} can't be loaded: {err:#}",
+                golden_path.display()
+            )),
+        };
+        match problem {
+            Some(problem) => {
+                canvas.save_png(&actual_path).unwrap();
+                panic!(
+                    "{name} doesn't match its golden image: {problem}.\n\
+                     The rendered image was saved to {}.\n\
+                     If the change is expected, run the test with \
+                     WEZTERM_UPDATE_GOLDEN=1 to update the golden image.",
+                    actual_path.display()
+                );
+            }
+            None => {
+                std::fs::remove_file(&actual_path).ok();
+            }
+        }
+    }
+
+    #[test]
+    fn compare_images_tolerance() {
+        let golden = RgbaImage::from_pixel(20, 20, image::Rgba([10, 20, 30, 255]));
+
+        let mut actual = golden.clone();
+        assert_eq!(compare_images(&golden, &actual), None);
+        // Small differences in every pixel are tolerated
+        for pixel in actual.pixels_mut() {
+            pixel.0[0] += 8;
+        }
+        assert_eq!(compare_images(&golden, &actual), None);
+        // as are larger differences in a couple of pixels
+        actual.put_pixel(0, 0, image::Rgba([255, 255, 255, 255]));
+        actual.put_pixel(1, 0, image::Rgba([255, 255, 255, 255]));
+        assert_eq!(compare_images(&golden, &actual), None);
+        // but not in more than that
+        actual.put_pixel(2, 0, image::Rgba([255, 255, 255, 255]));
+        assert_eq!(
+            compare_images(&golden, &actual),
+            Some("3 pixels differ".to_string())
+        );
+
+        let smaller = RgbaImage::from_pixel(20, 10, image::Rgba([10, 20, 30, 255]));
+        assert!(compare_images(&golden, &smaller).is_some());
+    }
+
+    #[test]
+    fn golden_custom_glyphs() {
+        let mut renderer = renderer();
+        let palette = ColorPalette::default();
+        let mut attrs = CellAttributes::blank();
+        attrs.set_foreground(AnsiColor::Lime);
+        let lines: Vec<Line> = [
+            // Block elements and shades
+            "\u{2588}\u{2580}\u{2584}\u{258c}\u{2590}\u{2591}\u{2592}\u{2593}",
+            // Box drawing, including rounded corners and heavy lines
+            "\u{250c}\u{2500}\u{252c}\u{2510}\u{256d}\u{2501}\u{256e}\u{2503}",
+            "\u{2514}\u{2500}\u{2534}\u{2518}\u{2570}\u{2501}\u{256f}\u{254b}",
+            // Powerline separators and braille
+            "\u{e0b0}\u{e0b2}\u{e0b4}\u{e0b6}\u{28ff}\u{2847}\u{28b8}\u{2800}",
+        ]
+        .iter()
+        .map(|text| Line::from_text(text, &attrs, SEQ_ZERO, None))
+        .collect();
+        let canvas = renderer.render(&lines, 8, &palette, None).unwrap();
+        assert_golden("custom_glyphs", &canvas);
+    }
+
+    #[test]
+    fn golden_text_and_cursor() {
+        let mut renderer = renderer();
+        let palette = ColorPalette::default();
+        let mut bold = CellAttributes::blank();
+        bold.set_intensity(termwiz::cell::Intensity::Bold);
+        let mut underlined = CellAttributes::blank();
+        underlined.set_underline(termwiz::cell::Underline::Double);
+        underlined.set_foreground(AnsiColor::Aqua);
+        let lines = vec![
+            Line::from_text("$ wezterm", &CellAttributes::blank(), SEQ_ZERO, None),
+            Line::from_text("bold text", &bold, SEQ_ZERO, None),
+            Line::from_text("underlined", &underlined, SEQ_ZERO, None),
+        ];
+        let cursor = HeadlessCursor {
+            x: 9,
+            y: 0,
+            shape: CursorShape::Default,
+        };
+        let canvas = renderer.render(&lines, 12, &palette, Some(cursor)).unwrap();
+        assert_golden("text_and_cursor", &canvas);
+    }
+
+    fn pane_info(pane_id: usize, title: &str) -> PaneInformation {
+        PaneInformation {
+            pane_id,
+            pane_index: 0,
+            is_active: true,
+            is_zoomed: false,
+            has_unseen_output: false,
+            left: 0,
+            top: 0,
+            width: 80,
+            height: 24,
+            pixel_width: 640,
+            pixel_height: 384,
+            title: title.to_string(),
+            user_vars: HashMap::new(),
+            progress: Progress::None,
+            is_recording: false,
+        }
+    }
+
+    fn tab_info(tab_index: usize, is_active: bool, pane: PaneInformation) -> TabInformation {
+        TabInformation {
+            tab_id: tab_index,
+            tab_index,
+            is_active,
+            is_last_active: false,
+            active_pane: Some(pane),
+            window_id: 0,
+            tab_title: String::new(),
+        }
+    }
+
+    #[test]
+    fn golden_tab_bar() {
+        let mut config = test_config();
+        config.use_fancy_tab_bar = false;
+        let config: ConfigHandle = config.into();
+        let mut renderer = HeadlessRenderer::new(&config).unwrap();
+
+        let panes = vec![pane_info(0, "zsh"), pane_info(1, "vim")];
+        let tabs = vec![
+            tab_info(0, true, panes[0].clone()),
+            tab_info(1, false, panes[1].clone()),
+        ];
+        let cols = 40;
+        let tab_bar =
+            TabBarState::new(cols, None, &tabs, &panes[0..1], None, &config, "", "status");
+        let canvas = renderer
+            .render(
+                &[tab_bar.line().clone()],
+                cols,
+                &ColorPalette::default(),
+                None,
+            )
+            .unwrap();
+        assert_golden("tab_bar", &canvas);
+    }
+
+    #[test]
+    fn block_glyphs() {
+        let mut renderer = renderer();
+        let palette = ColorPalette::default();
+        let mut attrs = CellAttributes::blank();
+        attrs.set_foreground(AnsiColor::Maroon);
+        let lines = vec![Line::from_text("\u{2588}\u{2580} ", &attrs, SEQ_ZERO, None)];
+        let canvas = renderer.render(&lines, 3, &palette, None).unwrap();
+
+        let cell = renderer.render_metrics.cell_size;
+        assert_eq!(canvas.width, 3 * cell.width as usize);
+        assert_eq!(canvas.height, cell.height as usize);
+
+        let red = palette.colors.0[1].to_linear();
+        let background = palette.background.to_linear();
+        let (w, h) = (cell.width as usize, cell.height as usize);
+
+        // Full block fills the first cell
+        assert_eq!(canvas.pixel(0, 0), red);
+        assert_eq!(canvas.pixel(w - 1, h - 1), red);
+        // Upper half block fills the top of the second cell
+        assert_eq!(canvas.pixel(w, 0), red);
+        assert_eq!(canvas.pixel(2 * w - 1, h - 1), background);
+        // The space is left as the background
+        assert_eq!(canvas.pixel(2 * w + w / 2, h / 2), background);
+    }
+
+    #[test]
+    fn cursor_and_background() {
+        let mut renderer = renderer();
+        let palette = ColorPalette::default();
+        let mut attrs = CellAttributes::blank();
+        attrs.set_background(AnsiColor::Navy);
+        let lines = vec![
+            Line::from_text("  ", &attrs, SEQ_ZERO, None),
+            Line::from_text("", &CellAttributes::blank(), SEQ_ZERO, None),
+        ];
+        let cursor = HeadlessCursor {
+            x: 1,
+            y: 1,
+            shape: CursorShape::Default,
+        };
+        let canvas = renderer.render(&lines, 2, &palette, Some(cursor)).unwrap();
+
+        let cell = renderer.render_metrics.cell_size;
+        let (w, h) = (cell.width as usize, cell.height as usize);
+        assert_eq!(canvas.pixel(w + 1, 1), palette.colors.0[4].to_linear());
+        assert_eq!(canvas.pixel(1, h + 1), palette.background.to_linear());
+        assert_eq!(
+            canvas.pixel(w + w / 2, h + h / 2),
+            palette.cursor_bg.to_linear()
+        );
+    }
+}
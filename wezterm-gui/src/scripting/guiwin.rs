@@ -9,6 +9,7 @@ use mux::pane::PaneId;
 use mux::window::WindowId as MuxWindowId;
 use mux::Mux;
 use mux_lua::MuxPane;
+use termwiz_funcs::export::{lines_to_html_fragment, ExportOptions};
 use termwiz_funcs::lines_to_escapes;
 use wezterm_dynamic::{FromDynamic, ToDynamic};
 use wezterm_toast_notification::ToastNotification;
@@ -309,6 +310,19 @@ impl UserData for GuiWin {
                 Ok(())
             },
         );
+        methods.add_method(
+            "copy_html_to_clipboard",
+            |_,
+             this,
+             (html, text, clipboard): (String, String, Option<ClipboardCopyDestination>)| {
+                let clipboard = clipboard.unwrap_or_default();
+                this.window
+                    .notify(TermWindowNotif::Apply(Box::new(move |term_window| {
+                        term_window.copy_html_to_clipboard(clipboard, text, html);
+                    })));
+                Ok(())
+            },
+        );
         methods.add_async_method(
             "get_selection_escapes_for_pane",
             |_, this, pane: UserDataRef<MuxPane>| async move {
@@ -332,6 +346,70 @@ impl UserData for GuiWin {
                     })));
                 let result = rx.recv().await.map_err(mlua::Error::external)?;
 
+                Ok(result)
+            },
+        );
+        methods.add_async_method(
+            "get_selection_as_html_for_pane",
+            |_, this, pane: UserDataRef<MuxPane>| async move {
+                let (tx, rx) = smol::channel::bounded(1);
+                let pane_id = pane.0;
+                this.window
+                    .notify(TermWindowNotif::Apply(Box::new(move |term_window| {
+                        fn do_it(
+                            pane_id: PaneId,
+                            term_window: &mut TermWindow,
+                        ) -> anyhow::Result<String> {
+                            let mux = Mux::try_get().ok_or_else(|| anyhow::anyhow!("no mux"))?;
+                            let pane = mux
+                                .get_pane(pane_id)
+                                .ok_or_else(|| anyhow::anyhow!("invalid pane {pane_id}"))?;
+                            let lines = term_window.selection_lines(&pane);
+                            Ok(lines_to_html_fragment(
+                                &lines,
+                                &pane.palette(),
+                                &ExportOptions::from_config(&term_window.config),
+                            ))
+                        }
+                        tx.try_send(do_it(pane_id, term_window).map_err(|err| format!("{err:#}")))
+                            .ok();
+                    })));
+                let result = rx.recv().await.map_err(mlua::Error::external)?;
+
+                Ok(result)
+            },
+        );
+        methods.add_async_method(
+            "set_pane_font_scale",
+            |_, this, (pane, scale): (UserDataRef<MuxPane>, f64)| async move {
+                let (tx, rx) = smol::channel::bounded(1);
+                let pane_id = pane.0;
+                this.window
+                    .notify(TermWindowNotif::Apply(Box::new(move |term_window| {
+                        tx.try_send(
+                            term_window
+                                .set_pane_font_scale(pane_id, scale)
+                                .map_err(|err| format!("{err:#}")),
+                        )
+                        .ok();
+                    })));
+                rx.recv()
+                    .await
+                    .map_err(mlua::Error::external)?
+                    .map_err(mlua::Error::external)
+            },
+        );
+        methods.add_async_method(
+            "get_pane_font_scale",
+            |_, this, pane: UserDataRef<MuxPane>| async move {
+                let (tx, rx) = smol::channel::bounded(1);
+                let pane_id = pane.0;
+                this.window
+                    .notify(TermWindowNotif::Apply(Box::new(move |term_window| {
+                        tx.try_send(term_window.pane_font_scale(pane_id)).ok();
+                    })));
+                let result = rx.recv().await.map_err(mlua::Error::external)?;
+
                 Ok(result)
             },
         );
@@ -112,6 +112,7 @@ pub async fn spawn_command_internal(
                         SplitSource::Spawn {
                             command: cmd_builder,
                             command_dir: cwd,
+                            initial_output: None,
                         },
                         spawn.domain,
                     )
@@ -132,6 +133,7 @@ pub async fn spawn_command_internal(
                     spawn.domain,
                     cmd_builder,
                     cwd,
+                    None,
                     size,
                     current_pane_id,
                     workspace,
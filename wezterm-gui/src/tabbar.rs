@@ -152,6 +152,16 @@ fn compute_tab_title(
                     title = format!("{}{classic_spacing}", title);
                 }
 
+                if pane.is_recording {
+                    let graphic = "\u{f044b} ";
+                    len += unicode_column_width(graphic, None);
+                    items.push(FormatItem::Foreground(FormatColor::AnsiColor(
+                        AnsiColor::Red,
+                    )));
+                    items.push(FormatItem::Text(graphic.to_string()));
+                    items.push(FormatItem::Foreground(FormatColor::Default));
+                }
+
                 match pane.progress {
                     Progress::None => {}
                     Progress::Percentage(pct) | Progress::Error(pct) => {
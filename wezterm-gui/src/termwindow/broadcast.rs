@@ -0,0 +1,201 @@
+use crate::termwindow::TermWindow;
+use config::keyassignment::BroadcastInputScope;
+use mux::pane::{Pane, PaneId};
+use mux::Mux;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+/// Tracks which panes receive a copy of the input that is
+/// sent to the active pane of the window
+#[derive(Default)]
+pub struct BroadcastState {
+    pub scope: Option<BroadcastInputScope>,
+    pub marked: HashSet<PaneId>,
+    /// The opacity of the frame drawn around each pane that has one,
+    /// computed once per paint by `update_broadcast_indicators`
+    indicators: HashMap<PaneId, f32>,
+}
+
+impl BroadcastState {
+    /// Forgets the marks of panes that no longer exist
+    fn prune_marked<F: Fn(PaneId) -> bool>(&mut self, exists: F) {
+        self.marked.retain(|pane_id| exists(*pane_id));
+    }
+
+    /// Recomputes the frames of the panes, given the panes that
+    /// are included in the current broadcast scope.
+    /// Marked panes are shown more faintly while they aren't
+    /// being broadcast to.
+    fn update_indicators(&mut self, in_scope: &[PaneId]) {
+        self.indicators.clear();
+        for pane_id in &self.marked {
+            self.indicators.insert(*pane_id, 0.4);
+        }
+        if self.scope.is_some() {
+            for pane_id in in_scope {
+                self.indicators.insert(*pane_id, 1.0);
+            }
+        }
+    }
+
+    fn indicator_alpha(&self, pane_id: PaneId) -> Option<f32> {
+        self.indicators.get(&pane_id).copied()
+    }
+}
+
+impl TermWindow {
+    pub fn toggle_broadcast_input(&mut self, scope: BroadcastInputScope) {
+        if self.broadcast.scope == Some(scope) {
+            self.broadcast.scope = None;
+        } else {
+            self.broadcast.scope = Some(scope);
+        }
+        if let Some(window) = self.window.as_ref() {
+            window.invalidate();
+        }
+    }
+
+    pub fn toggle_pane_broadcast_mark(&mut self, pane: &Arc<dyn Pane>) {
+        let pane_id = pane.pane_id();
+        if !self.broadcast.marked.remove(&pane_id) {
+            self.broadcast.marked.insert(pane_id);
+        }
+        if let Some(window) = self.window.as_ref() {
+            window.invalidate();
+        }
+    }
+
+    /// Returns the ids of all of the panes that are included
+    /// in the current broadcast scope
+    pub fn broadcast_pane_ids(&self) -> Vec<PaneId> {
+        let scope = match self.broadcast.scope {
+            Some(scope) => scope,
+            None => return vec![],
+        };
+        let mux = Mux::get();
+        let tabs = match scope {
+            BroadcastInputScope::CurrentTab => mux
+                .get_active_tab_for_window(self.mux_window_id)
+                .into_iter()
+                .collect(),
+            BroadcastInputScope::CurrentWorkspace => {
+                let workspace = mux.active_workspace();
+                let mut tabs = vec![];
+                for window_id in mux.iter_windows_in_workspace(&workspace) {
+                    if let Some(window) = mux.get_window(window_id) {
+                        tabs.extend(window.iter().cloned());
+                    }
+                }
+                tabs
+            }
+            BroadcastInputScope::MarkedPanes => {
+                let mut pane_ids: Vec<PaneId> = self
+                    .broadcast
+                    .marked
+                    .iter()
+                    .copied()
+                    .filter(|pane_id| mux.get_pane(*pane_id).is_some())
+                    .collect();
+                pane_ids.sort();
+                return pane_ids;
+            }
+        };
+        tabs.iter()
+            .flat_map(|tab| tab.iter_panes_ignoring_zoom())
+            .map(|pos| pos.pane.pane_id())
+            .collect()
+    }
+
+    /// Called once per paint, ahead of painting the panes, to prune
+    /// the marks of panes that have gone away and to work out which
+    /// panes are framed by `broadcast_indicator_alpha`
+    pub fn update_broadcast_indicators(&mut self) {
+        let mux = Mux::get();
+        self.broadcast
+            .prune_marked(|pane_id| mux.get_pane(pane_id).is_some());
+        let in_scope = self.broadcast_pane_ids();
+        self.broadcast.update_indicators(&in_scope);
+    }
+
+    /// Returns the opacity of the frame that is drawn around the pane
+    /// to indicate that it receives broadcast input
+    pub fn broadcast_indicator_alpha(&self, pane_id: PaneId) -> Option<f32> {
+        self.broadcast.indicator_alpha(pane_id)
+    }
+
+    /// Returns true if the pane is hidden behind an overlay in this
+    /// window, such as copy mode or the launcher.  Input broadcast to
+    /// it would go to a program that the user can't see.
+    fn is_covered_by_overlay(&self, pane_id: PaneId) -> bool {
+        if self.pane_state(pane_id).overlay.is_some() {
+            return true;
+        }
+        match Mux::get().resolve_pane_id(pane_id) {
+            Some((_domain_id, _window_id, tab_id)) => self.tab_state(tab_id).overlay.is_some(),
+            None => false,
+        }
+    }
+
+    /// Returns the panes, other than `pane`, that should receive a copy
+    /// of the input sent to `pane`.  Nothing is broadcast when `pane`
+    /// is an overlay, such as copy mode or the launcher, and nothing is
+    /// broadcast to panes that are covered by an overlay.
+    pub fn broadcast_targets(&self, pane: &Arc<dyn Pane>) -> Vec<Arc<dyn Pane>> {
+        if self.broadcast.scope.is_none() {
+            return vec![];
+        }
+        let source_id = pane.pane_id();
+        match self.get_active_pane_no_overlay() {
+            Some(active) if active.pane_id() == source_id => {}
+            _ => return vec![],
+        }
+        let mux = Mux::get();
+        self.broadcast_pane_ids()
+            .into_iter()
+            .filter(|pane_id| *pane_id != source_id)
+            .filter(|pane_id| !self.is_covered_by_overlay(*pane_id))
+            .filter_map(|pane_id| mux.get_pane(pane_id))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn indicators() {
+        let mut state = BroadcastState::default();
+        state.marked.insert(1);
+        state.marked.insert(2);
+
+        // Marks are shown faintly while not broadcasting
+        state.update_indicators(&[]);
+        assert_eq!(state.indicator_alpha(1), Some(0.4));
+        assert_eq!(state.indicator_alpha(3), None);
+
+        // Panes in scope are shown in full, whether marked or not
+        state.scope = Some(BroadcastInputScope::CurrentTab);
+        state.update_indicators(&[2, 3]);
+        assert_eq!(state.indicator_alpha(1), Some(0.4));
+        assert_eq!(state.indicator_alpha(2), Some(1.0));
+        assert_eq!(state.indicator_alpha(3), Some(1.0));
+        assert_eq!(state.indicator_alpha(4), None);
+
+        // Nothing is in scope once broadcasting is toggled off
+        state.scope = None;
+        state.update_indicators(&[2, 3]);
+        assert_eq!(state.indicator_alpha(2), Some(0.4));
+        assert_eq!(state.indicator_alpha(3), None);
+    }
+
+    #[test]
+    fn prune_marked() {
+        let mut state = BroadcastState::default();
+        state.marked.extend([1, 2, 3]);
+        state.prune_marked(|pane_id| pane_id != 2);
+        let mut marked: Vec<PaneId> = state.marked.iter().copied().collect();
+        marked.sort();
+        assert_eq!(marked, vec![1, 3]);
+    }
+}
@@ -8,17 +8,33 @@ use window::{Clipboard, WindowOps};
 
 impl TermWindow {
     pub fn copy_to_clipboard(&self, clipboard: ClipboardCopyDestination, text: String) {
-        let clipboard = match clipboard {
-            ClipboardCopyDestination::Clipboard => [Some(Clipboard::Clipboard), None],
-            ClipboardCopyDestination::PrimarySelection => [Some(Clipboard::PrimarySelection), None],
-            ClipboardCopyDestination::ClipboardAndPrimarySelection => [
-                Some(Clipboard::Clipboard),
-                Some(Clipboard::PrimarySelection),
-            ],
-        };
-        for &c in &clipboard {
-            if let Some(c) = c {
-                self.window.as_ref().unwrap().set_clipboard(c, text.clone());
+        for c in Self::clipboards_for_destination(clipboard) {
+            self.window.as_ref().unwrap().set_clipboard(c, text.clone());
+        }
+    }
+
+    /// Copies `html` to the clipboard for applications that accept
+    /// rich text, with `text` as the fallback for those that don't
+    pub fn copy_html_to_clipboard(
+        &self,
+        clipboard: ClipboardCopyDestination,
+        text: String,
+        html: String,
+    ) {
+        for c in Self::clipboards_for_destination(clipboard) {
+            self.window
+                .as_ref()
+                .unwrap()
+                .set_clipboard_html(c, text.clone(), html.clone());
+        }
+    }
+
+    fn clipboards_for_destination(clipboard: ClipboardCopyDestination) -> Vec<Clipboard> {
+        match clipboard {
+            ClipboardCopyDestination::Clipboard => vec![Clipboard::Clipboard],
+            ClipboardCopyDestination::PrimarySelection => vec![Clipboard::PrimarySelection],
+            ClipboardCopyDestination::ClipboardAndPrimarySelection => {
+                vec![Clipboard::Clipboard, Clipboard::PrimarySelection]
             }
         }
     }
@@ -50,6 +66,9 @@ impl TermWindow {
                         })
                     {
                         pane.send_paste(&clip).ok();
+                        for target in myself.broadcast_targets(&pane) {
+                            target.send_paste(&clip).ok();
+                        }
                     }
                 })));
             }
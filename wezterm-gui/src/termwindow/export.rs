@@ -0,0 +1,71 @@
+use crate::termwindow::TermWindow;
+use config::keyassignment::{ExportContent, ExportFormat, ExportPaneArgs};
+use mux::pane::Pane;
+use std::sync::Arc;
+use termwiz::surface::Line;
+use termwiz_funcs::export::{export_lines, lines_to_html_fragment, ExportOptions};
+
+impl TermWindow {
+    fn export_content_lines(&self, pane: &Arc<dyn Pane>, content: ExportContent) -> Vec<Line> {
+        let dims = pane.get_dimensions();
+        let top = match content {
+            ExportContent::Selection => {
+                if self.selection(pane.pane_id()).range.is_some() {
+                    return self.selection_lines(pane);
+                }
+                self.get_viewport(pane.pane_id())
+                    .unwrap_or(dims.physical_top)
+            }
+            ExportContent::Viewport => self
+                .get_viewport(pane.pane_id())
+                .unwrap_or(dims.physical_top),
+            ExportContent::Scrollback => dims.scrollback_top,
+        };
+        let bottom = match content {
+            ExportContent::Scrollback => dims.physical_top + dims.viewport_rows as isize,
+            _ => top + dims.viewport_rows as isize,
+        };
+        pane.get_lines(top..bottom).1
+    }
+
+    /// Renders a portion of the pane in the requested format and
+    /// copies it to the clipboard or saves it to a file
+    pub fn export_pane(&mut self, pane: &Arc<dyn Pane>, args: &ExportPaneArgs) {
+        let lines = self.export_content_lines(pane, args.content);
+        let palette = pane.palette();
+
+        match &args.path {
+            Some(path) => match export_lines(lines, args.format, &palette)
+                .and_then(|text| Ok(std::fs::write(path, text)?))
+            {
+                Ok(()) => log::info!("exported pane to {path}"),
+                Err(err) => log::error!("exporting pane to {path}: {err:#}"),
+            },
+            None => {
+                // When pasting into a document, a fragment is more useful
+                // than a complete html document.  It is offered as html
+                // alongside the plain text, so that applications that
+                // don't accept rich text still get something sensible.
+                let result = match args.format {
+                    ExportFormat::Html => {
+                        let html = lines_to_html_fragment(
+                            &lines,
+                            &palette,
+                            &ExportOptions::from_config(&self.config),
+                        );
+                        export_lines(lines, ExportFormat::Text, &palette)
+                            .map(|text| (text, Some(html)))
+                    }
+                    format => export_lines(lines, format, &palette).map(|text| (text, None)),
+                };
+                match result {
+                    Ok((text, Some(html))) => {
+                        self.copy_html_to_clipboard(args.destination, text, html)
+                    }
+                    Ok((text, None)) => self.copy_to_clipboard(args.destination, text),
+                    Err(err) => log::error!("exporting pane: {err:#}"),
+                }
+            }
+        }
+    }
+}
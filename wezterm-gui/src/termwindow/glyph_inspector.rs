@@ -0,0 +1,273 @@
+use crate::customglyph::BlockKey;
+use crate::overlay::glyph_inspector::{
+    glyph_inspector, CellReport, FallbackCandidate, FontSource, GlyphDetail,
+};
+use crate::overlay::start_overlay_pane;
+use crate::termwindow::TermWindow;
+use crate::utilsprites::RenderMetrics;
+use ::window::bitmaps::atlas::OutOfTextureSpace;
+use mux::pane::Pane;
+use rangeset::RangeSet;
+use std::collections::HashMap;
+use std::sync::Arc;
+use termwiz::surface::Line;
+use wezterm_font::shaper::PresentationWidth;
+use wezterm_font::{ftwrap, FontConfiguration};
+
+/// Codepoints that select or join glyphs rather than having
+/// glyphs of their own; we don't expect fonts to cover them
+fn is_glyph_modifier(c: char) -> bool {
+    matches!(c, '\u{200d}' | '\u{fe0e}' | '\u{fe0f}')
+}
+
+impl TermWindow {
+    /// Shapes `line` in the same way as the renderer and reports
+    /// on how each of its cells is rendered
+    fn inspect_line(
+        &self,
+        line: &Line,
+        fonts: &FontConfiguration,
+        render_metrics: &RenderMetrics,
+        ft_lib: &ftwrap::Library,
+        faces: &mut HashMap<(usize, usize), Option<ftwrap::Face>>,
+    ) -> anyhow::Result<Vec<CellReport>> {
+        let render_state = match self.render_state.as_ref() {
+            Some(state) => state,
+            None => anyhow::bail!("the window has not been rendered yet"),
+        };
+        let mut glyph_cache = render_state.glyph_cache.borrow_mut();
+
+        let (bidi_enabled, bidi_direction) = line.bidi_info();
+        let bidi_hint = if bidi_enabled {
+            Some(bidi_direction)
+        } else {
+            None
+        };
+
+        let mut reports = vec![];
+        for cluster in line.cluster(bidi_hint) {
+            let style = fonts.match_style(&self.config, &cluster.attrs);
+            let font = fonts.resolve_font(style)?;
+            let presentation_width = PresentationWidth::with_cluster(&cluster);
+            let infos = font.blocking_shape(
+                &cluster.text,
+                Some(cluster.presentation),
+                cluster.direction,
+                None,
+                Some(&presentation_width),
+            )?;
+            // Grab the handles after shaping, so that they include
+            // any fallback fonts that shaping discovered
+            let handles = font.clone_handles();
+
+            let glyph_cells: Vec<std::ops::Range<usize>> = infos
+                .iter()
+                .map(|info| {
+                    let start = cluster.byte_to_cell_idx(info.cluster as usize);
+                    start..start + (info.num_cells as usize).max(1)
+                })
+                .collect();
+
+            for cell in line.visible_cells() {
+                let col = cell.cell_index();
+                if col < cluster.first_cell_idx || col >= cluster.first_cell_idx + cluster.width {
+                    continue;
+                }
+
+                let mut glyphs = vec![];
+                let mut primary = None;
+                for (info, cells) in infos.iter().zip(glyph_cells.iter()) {
+                    if !cells.contains(&col) {
+                        continue;
+                    }
+                    if primary.is_none() {
+                        primary.replace(info);
+                    }
+
+                    let followed_by_space = match line.get_cell(cells.end) {
+                        Some(cell) => cell.str() == " ",
+                        None => false,
+                    };
+                    let cached = glyph_cache.cached_glyph(
+                        info,
+                        style,
+                        followed_by_space,
+                        &font,
+                        render_metrics,
+                        info.num_cells,
+                    )?;
+                    let glyph_name = faces
+                        .entry((font.id(), info.font_idx))
+                        .or_insert_with(|| {
+                            handles
+                                .get(info.font_idx)
+                                .and_then(|p| ft_lib.face_from_locator(&p.handle).ok())
+                        })
+                        .as_ref()
+                        .and_then(|face| face.get_glyph_name(info.glyph_pos))
+                        .map(|name| name.trim_end_matches('\0').to_string());
+                    let (bitmap_width, bitmap_height) = cached
+                        .texture
+                        .as_ref()
+                        .map(|t| (t.coords.size.width, t.coords.size.height))
+                        .unwrap_or((0, 0));
+
+                    glyphs.push(GlyphDetail {
+                        glyph_pos: info.glyph_pos,
+                        glyph_name,
+                        font_idx: info.font_idx,
+                        cluster: info.cluster,
+                        num_cells: info.num_cells,
+                        x_advance: cached.x_advance.get(),
+                        x_offset: cached.x_offset.get(),
+                        y_offset: cached.y_offset.get(),
+                        bearing_x: cached.bearing_x.get(),
+                        bearing_y: cached.bearing_y.get(),
+                        bitmap_width,
+                        bitmap_height,
+                    });
+                }
+
+                let primary = match primary {
+                    Some(info) => info,
+                    None => continue,
+                };
+                let used = handles.get(primary.font_idx);
+
+                let source = if self.config.custom_block_glyphs
+                    && primary.only_char.and_then(BlockKey::from_char).is_some()
+                {
+                    FontSource::CustomGlyph
+                } else if primary.glyph_pos == 0 {
+                    FontSource::Missing
+                } else if primary.font_idx == 0 {
+                    FontSource::Primary
+                } else {
+                    match used {
+                        Some(p) if style.font.iter().any(|attr| p.matches_name(attr)) => {
+                            FontSource::Configured
+                        }
+                        Some(p) if p.is_built_in_fallback => FontSource::BuiltIn,
+                        _ => FontSource::LastResort,
+                    }
+                };
+
+                let mut wanted = RangeSet::new();
+                for c in cell.str().chars().filter(|&c| !is_glyph_modifier(c)) {
+                    wanted.add(c as u32);
+                }
+                // When no font has the glyph, all of them were tried
+                let tried = if source == FontSource::Missing {
+                    handles.len()
+                } else {
+                    primary.font_idx + 1
+                };
+                let chain = handles
+                    .iter()
+                    .take(tried)
+                    .map(|p| FallbackCandidate {
+                        name: p.lua_name(),
+                        covers: p
+                            .coverage_intersection(&wanted)
+                            .map(|covered| covered == wanted)
+                            .unwrap_or(false),
+                    })
+                    .collect();
+
+                reports.push(CellReport {
+                    col,
+                    grapheme: cell.str().to_string(),
+                    width: cell.width(),
+                    source,
+                    font: used.map(|p| p.lua_name()).unwrap_or_default(),
+                    font_location: used
+                        .map(|p| p.handle.diagnostic_string())
+                        .unwrap_or_default(),
+                    chain,
+                    shaped_text: cluster.text.clone(),
+                    glyphs,
+                });
+            }
+        }
+
+        reports.sort_by_key(|report| report.col);
+        Ok(reports)
+    }
+
+    /// Reports on each of `lines`, in the same way as `inspect_line`
+    fn inspect_lines(
+        &self,
+        lines: &[Line],
+        fonts: &FontConfiguration,
+        render_metrics: &RenderMetrics,
+        ft_lib: &ftwrap::Library,
+    ) -> anyhow::Result<Vec<Vec<CellReport>>> {
+        let mut faces = HashMap::new();
+        lines
+            .iter()
+            .map(|line| self.inspect_line(line, fonts, render_metrics, ft_lib, &mut faces))
+            .collect()
+    }
+
+    /// Opens the glyph inspector over `pane`, starting at its cursor,
+    /// which is the copy mode cursor when copy mode is active.
+    /// Copy mode is hidden while the inspector is showing, rather
+    /// than being closed.
+    pub fn show_glyph_inspector(&mut self, pane: &Arc<dyn Pane>) {
+        let dims = pane.get_dimensions();
+        let top = self
+            .get_viewport(pane.pane_id())
+            .unwrap_or(dims.physical_top);
+        let (first_row, lines) = pane.get_lines(top..top + dims.viewport_rows as isize);
+
+        let ft_lib = match ftwrap::Library::new() {
+            Ok(lib) => lib,
+            Err(err) => {
+                log::error!("glyph inspector: {err:#}");
+                return;
+            }
+        };
+        // The pane may be rendered with its own font size
+        let (fonts, render_metrics) = self.fonts_for_pane(pane.pane_id());
+        let reports = loop {
+            let err = match self.inspect_lines(&lines, &fonts, &render_metrics, &ft_lib) {
+                Ok(reports) => break reports,
+                Err(err) => err,
+            };
+            // The glyphs are rasterized into the same texture atlas as
+            // the renderer uses; grow it when it fills up, just as
+            // painting does, and start over
+            match err.root_cause().downcast_ref::<OutOfTextureSpace>() {
+                Some(&OutOfTextureSpace {
+                    size: Some(size), ..
+                }) => {
+                    if let Err(err) = self.recreate_texture_atlas(Some(size)) {
+                        log::error!("glyph inspector: {err:#}");
+                        return;
+                    }
+                    self.invalidate_fancy_tab_bar();
+                    self.invalidate_modal();
+                    if let Some(window) = self.window.as_ref() {
+                        window.invalidate();
+                    }
+                }
+                _ => {
+                    log::error!("glyph inspector: {err:#}");
+                    return;
+                }
+            }
+        };
+
+        let cursor = pane.get_cursor_position();
+        let cursor = (
+            (cursor.y - first_row).clamp(0, reports.len().saturating_sub(1) as isize) as usize,
+            cursor.x,
+        );
+
+        let (overlay, future) = start_overlay_pane(self, pane, move |_pane_id, term| {
+            glyph_inspector(term, reports, cursor)
+        });
+        self.push_overlay_for_pane(pane.pane_id(), overlay);
+        promise::spawn::spawn(future).detach();
+    }
+}
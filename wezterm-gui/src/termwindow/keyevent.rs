@@ -197,6 +197,43 @@ impl super::TermWindow {
         key.encode_win32_input_mode()
     }
 
+    /// Sends a key to the pane, using the keyboard encoding that
+    /// the pane has requested if the original key event is known
+    fn send_key_to_pane(
+        &self,
+        pane: &Arc<dyn Pane>,
+        key_event: Option<&KeyEvent>,
+        key: ::termwiz::input::KeyCode,
+        modifiers: Modifiers,
+        is_down: bool,
+    ) -> anyhow::Result<()> {
+        if let Some(key_event) = key_event {
+            if let Some(encoded) = self.encode_win32_input(pane, key_event) {
+                if self.config.debug_key_events {
+                    log::info!("win32: Encoded input as {:?}", encoded);
+                }
+                return pane
+                    .writer()
+                    .write_all(encoded.as_bytes())
+                    .context("sending win32-input-mode encoded data");
+            }
+            if let Some(encoded) = self.encode_kitty_input(pane, key_event) {
+                if self.config.debug_key_events {
+                    log::info!("kitty: Encoded input as {:?}", encoded);
+                }
+                return pane
+                    .writer()
+                    .write_all(encoded.as_bytes())
+                    .context("sending kitty encoded data");
+            }
+        }
+        if is_down {
+            pane.key_down(key, modifiers)
+        } else {
+            pane.key_up(key, modifiers)
+        }
+    }
+
     fn encode_kitty_input(&self, pane: &Arc<dyn Pane>, key: &KeyEvent) -> Option<String> {
         if !self.config.enable_kitty_keyboard {
             return None;
@@ -360,48 +397,30 @@ impl super::TermWindow {
                 if let Key::Code(term_key) = self.win_key_code_to_termwiz_key_code(keycode) {
                     let tw_raw_modifiers = raw_modifiers;
 
-                    let mut did_encode = false;
-                    if let Some(key_event) = key_event {
-                        if let Some(encoded) = self.encode_win32_input(&pane, &key_event) {
-                            if self.config.debug_key_events {
-                                log::info!("win32: Encoded input as {:?}", encoded);
-                            }
-                            pane.writer()
-                                .write_all(encoded.as_bytes())
-                                .context("sending win32-input-mode encoded data")
-                                .ok();
-                            did_encode = true;
-                        } else if let Some(encoded) = self.encode_kitty_input(&pane, &key_event) {
-                            if self.config.debug_key_events {
-                                log::info!("kitty: Encoded input as {:?}", encoded);
-                            }
-                            pane.writer()
-                                .write_all(encoded.as_bytes())
-                                .context("sending kitty encoded data")
-                                .ok();
-                            did_encode = true;
-                        }
-                    };
-                    if !did_encode {
-                        if self.config.debug_key_events {
-                            log::info!(
-                                "{:?} {:?} -> send to pane {:?} {:?}",
-                                keycode,
-                                raw_modifiers,
-                                term_key,
-                                tw_raw_modifiers
-                            );
-                        }
-
-                        did_encode = if is_down {
-                            pane.key_down(term_key, tw_raw_modifiers)
-                        } else {
-                            pane.key_up(term_key, tw_raw_modifiers)
-                        }
+                    if self.config.debug_key_events {
+                        log::info!(
+                            "{:?} {:?} -> send to pane {:?} {:?}",
+                            keycode,
+                            raw_modifiers,
+                            term_key,
+                            tw_raw_modifiers
+                        );
+                    }
+                    let did_encode = self
+                        .send_key_to_pane(pane, key_event, term_key, tw_raw_modifiers, is_down)
                         .is_ok();
-                    };
 
                     if did_encode {
+                        for target in self.broadcast_targets(pane) {
+                            self.send_key_to_pane(
+                                &target,
+                                key_event,
+                                term_key,
+                                tw_raw_modifiers,
+                                is_down,
+                            )
+                            .ok();
+                        }
                         if is_down
                             && !keycode.is_modifier()
                             && self.pane_state(pane.pane_id()).overlay.is_none()
@@ -671,38 +690,33 @@ impl super::TermWindow {
                     return;
                 }
 
-                let res = if let Some(encoded) = self.encode_win32_input(&pane, &window_key) {
-                    if self.config.debug_key_events {
-                        log::info!("win32: Encoded input as {:?}", encoded);
-                    }
-                    pane.writer()
-                        .write_all(encoded.as_bytes())
-                        .context("sending win32-input-mode encoded data")
-                } else if let Some(encoded) = self.encode_kitty_input(&pane, &window_key) {
-                    if self.config.debug_key_events {
-                        log::info!("kitty: Encoded input as {:?}", encoded);
-                    }
-                    pane.writer()
-                        .write_all(encoded.as_bytes())
-                        .context("sending kitty encoded data")
-                } else {
-                    if self.config.debug_key_events {
-                        log::info!(
-                            "send to pane {} key={:?} mods={:?}",
-                            if window_key.key_is_down { "DOWN" } else { "UP" },
-                            key,
-                            modifiers
-                        );
-                    }
-
-                    if window_key.key_is_down {
-                        pane.key_down(key, modifiers)
-                    } else {
-                        pane.key_up(key, modifiers)
-                    }
-                };
+                if self.config.debug_key_events {
+                    log::info!(
+                        "send to pane {} key={:?} mods={:?}",
+                        if window_key.key_is_down { "DOWN" } else { "UP" },
+                        key,
+                        modifiers
+                    );
+                }
+                let res = self.send_key_to_pane(
+                    &pane,
+                    Some(&window_key),
+                    key,
+                    modifiers,
+                    window_key.key_is_down,
+                );
 
                 if res.is_ok() {
+                    for target in self.broadcast_targets(&pane) {
+                        self.send_key_to_pane(
+                            &target,
+                            Some(&window_key),
+                            key,
+                            modifiers,
+                            window_key.key_is_down,
+                        )
+                        .ok();
+                    }
                     if window_key.key_is_down
                         && !key.is_modifier()
                         && self.pane_state(pane.pane_id()).overlay.is_none()
@@ -736,6 +750,9 @@ impl super::TermWindow {
                     log::info!("send to pane string={:?}", s);
                 }
                 pane.writer().write_all(s.as_bytes()).ok();
+                for target in self.broadcast_targets(&pane) {
+                    target.writer().write_all(s.as_bytes()).ok();
+                }
                 self.maybe_scroll_to_bottom_for_input(&pane);
                 context.invalidate();
             }
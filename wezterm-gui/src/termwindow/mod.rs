@@ -32,7 +32,7 @@ use ::window::*;
 use anyhow::{anyhow, ensure, Context};
 use config::keyassignment::{
     Confirmation, KeyAssignment, LauncherActionArgs, PaneDirection, Pattern, PromptInputLine,
-    QuickSelectArguments, RotationDirection, SpawnCommand, SplitSize,
+    QuickSelectArguments, RotationDirection, SearchAllPanesArguments, SpawnCommand, SplitSize,
 };
 use config::window::WindowLevel;
 use config::{
@@ -67,22 +67,31 @@ use wezterm_dynamic::Value;
 use wezterm_font::FontConfiguration;
 use wezterm_term::color::ColorPalette;
 use wezterm_term::input::LastMouseClick;
-use wezterm_term::{Alert, Progress, StableRowIndex, TerminalConfiguration, TerminalSize};
+use wezterm_term::{
+    Alert, CommandBlock, Progress, StableRowIndex, TerminalConfiguration, TerminalSize,
+};
 
 pub mod background;
 pub mod box_model;
+mod broadcast;
 pub mod charselect;
 pub mod clipboard;
+mod export;
+mod glyph_inspector;
 pub mod keyevent;
 pub mod modal;
 mod mouseevent;
 pub mod palette;
 pub mod paneselect;
 mod prevcursor;
+mod recording;
 pub mod render;
 pub mod resize;
 mod selection;
+mod serial;
+mod session;
 pub mod spawn;
+mod transfer;
 pub mod webgpu;
 use crate::spawn::SpawnWhere;
 use prevcursor::PrevCursorPos;
@@ -169,6 +178,7 @@ pub enum UIItemType {
     CodeBlockScrollbar(String),  // code_block_id
     CodeBlockContent(String),    // code_block_id
     CodeBlockCopyButton(String), // code_block_id
+    CommandGutter(PaneId, CommandBlock),
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -195,6 +205,12 @@ pub struct SemanticZoneCache {
     zones: Vec<StableRowIndex>,
 }
 
+#[derive(Clone, Default)]
+pub struct CommandBlockCache {
+    seqno: SequenceNo,
+    blocks: Vec<CommandBlock>,
+}
+
 pub struct OverlayState {
     pub pane: Arc<dyn Pane>,
     pub key_table_state: KeyTableState,
@@ -211,9 +227,18 @@ pub struct PaneState {
     /// contents, we're overlaying a little internal application
     /// tab.  We'll also route input to it.
     pub overlay: Option<OverlayState>,
+    /// Overlays that are hidden by `overlay`, such as copy mode while
+    /// the glyph inspector is showing.  The most recent one is shown
+    /// again when `overlay` is closed.
+    suspended_overlays: Vec<OverlayState>,
 
     bell_start: Option<Instant>,
     pub mouse_terminal_coords: Option<(ClickPosition, StableRowIndex)>,
+    /// If is_some(), the pane is rendered with its own font size
+    font: Option<resize::PaneFont>,
+    /// The id of the overlay pane that shows the serial hex view,
+    /// so that toggling it leaves other overlays alone
+    hex_view: Option<PaneId>,
 }
 
 /// Data used when synchronously formatting pane and window titles
@@ -282,6 +307,7 @@ pub struct PaneInformation {
     pub title: String,
     pub user_vars: HashMap<String, String>,
     pub progress: Progress,
+    pub is_recording: bool,
 }
 
 impl UserData for PaneInformation {
@@ -298,6 +324,7 @@ impl UserData for PaneInformation {
         fields.add_field_method_get("pixel_width", |_, this| Ok(this.pixel_width));
         fields.add_field_method_get("pixel_height", |_, this| Ok(this.pixel_height));
         fields.add_field_method_get("progress", |lua, this| lua.to_value(&this.progress));
+        fields.add_field_method_get("is_recording", |_, this| Ok(this.is_recording));
         fields.add_field_method_get("title", |_, this| Ok(this.title.clone()));
         fields.add_field_method_get("user_vars", |_, this| Ok(this.user_vars.clone()));
         fields.add_field_method_get("foreground_process_name", |_, this| {
@@ -416,6 +443,10 @@ pub struct TermWindow {
     tab_state: RefCell<HashMap<TabId, TabState>>,
     pane_state: RefCell<HashMap<PaneId, PaneState>>,
     semantic_zones: HashMap<PaneId, SemanticZoneCache>,
+    command_blocks: HashMap<PaneId, CommandBlockCache>,
+    /// The command gutter marker under the mouse, if any
+    hovered_command_gutter: Option<UIItem>,
+    broadcast: broadcast::BroadcastState,
 
     window_background: Vec<LoadedBackgroundLayer>,
 
@@ -791,7 +822,7 @@ impl TermWindow {
             pixel_max: terminal_size.pixel_width as f32,
             pixel_cell: render_metrics.cell_size.width as f32,
         };
-        let padding_left = config.window_padding.left.evaluate_as_pixels(h_context) as usize;
+        let padding_left = resize::effective_left_padding(&config, h_context);
         let padding_right = resize::effective_right_padding(&config, h_context) as usize;
         let v_context = DimensionContext {
             dpi: dpi as f32,
@@ -932,6 +963,9 @@ impl TermWindow {
             scheduled_animation: RefCell::new(None),
             allow_images: AllowImage::Yes,
             semantic_zones: HashMap::new(),
+            command_blocks: HashMap::new(),
+            hovered_command_gutter: None,
+            broadcast: broadcast::BroadcastState::default(),
             ui_items: vec![],
             dragging: None,
             last_ui_item: None,
@@ -1219,6 +1253,9 @@ impl TermWindow {
                     None => return Ok(true),
                 };
                 pane.send_paste(text.as_str())?;
+                for target in self.broadcast_targets(&pane) {
+                    target.send_paste(text.as_str()).ok();
+                }
                 Ok(true)
             }
             WindowEvent::DroppedUrl(urls) => {
@@ -1240,6 +1277,9 @@ impl TermWindow {
                     Some(pane) => pane,
                     None => return Ok(true),
                 };
+                if self.upload_dropped_files(&pane, &paths) {
+                    return Ok(true);
+                }
                 let paths = paths
                     .iter()
                     .map(|path| {
@@ -1400,6 +1440,7 @@ impl TermWindow {
                     self.emit_user_var_event(pane_id, name, value);
                 }
                 MuxNotification::WindowTitleChanged { .. }
+                | MuxNotification::PaneRecordingChanged(_)
                 | MuxNotification::Alert {
                     alert:
                         Alert::OutputSinceFocusLost
@@ -1422,6 +1463,13 @@ impl TermWindow {
                     self.dispatch_notif(TermWindowNotif::InvalidateShapeCache, window)?;
                     self.mux_pane_output_event(pane_id);
                 }
+                MuxNotification::Alert {
+                    alert: Alert::CommandBlocksChanged,
+                    pane_id,
+                } => {
+                    self.command_blocks.remove(&pane_id);
+                    self.mux_pane_output_event(pane_id);
+                }
                 MuxNotification::Alert {
                     alert: Alert::Bell,
                     pane_id,
@@ -1507,6 +1555,8 @@ impl TermWindow {
                     self.update_title_post_status();
                 }
                 MuxNotification::PaneAdded(_)
+                | MuxNotification::PaneRawOutput { .. }
+                | MuxNotification::PaneRawInput { .. }
                 | MuxNotification::WorkspaceRenamed { .. }
                 | MuxNotification::PaneRemoved(_)
                 | MuxNotification::WindowWorkspaceChanged(_)
@@ -1664,10 +1714,12 @@ impl TermWindow {
                     | Alert::IconTitleChanged(_)
                     | Alert::Progress(_)
                     | Alert::SetUserVar { .. }
+                    | Alert::CommandBlocksChanged
                     | Alert::Bell,
             }
             | MuxNotification::PaneFocused(pane_id)
             | MuxNotification::PaneRemoved(pane_id)
+            | MuxNotification::PaneRecordingChanged(pane_id)
             | MuxNotification::PaneOutput(pane_id) => {
                 // Ideally we'd check to see if pane_id is part of this window,
                 // but overlays may not be 100% associated with the window
@@ -1716,6 +1768,8 @@ impl TermWindow {
             }
             | MuxNotification::AssignClipboard { .. }
             | MuxNotification::SaveToDownloads { .. }
+            | MuxNotification::PaneRawOutput { .. }
+            | MuxNotification::PaneRawInput { .. }
             | MuxNotification::WindowCreated(_)
             | MuxNotification::ActiveWorkspaceChanged(_)
             | MuxNotification::WorkspaceRenamed { .. }
@@ -2378,17 +2432,19 @@ impl TermWindow {
                 0.0
             };
             let (padding_left, padding_top) = self.padding_left_top();
+            let cell_size = self.fonts_for_pane(pos.pane.pane_id()).1.cell_size;
 
             let r = Rect::new(
                 Point::new(
-                    (((cursor.x + pos.left) as isize).max(0) * self.render_metrics.cell_size.width)
+                    (pos.left as isize * self.render_metrics.cell_size.width)
+                        .add(cursor.x as isize * cell_size.width)
                         .add(padding_left as isize),
-                    ((cursor.y + pos.top as isize - top).max(0)
-                        * self.render_metrics.cell_size.height)
+                    (pos.top as isize * self.render_metrics.cell_size.height)
+                        .add((cursor.y - top).max(0) * cell_size.height)
                         .add(tab_bar_height as isize)
                         .add(padding_top as isize),
                 ),
-                self.render_metrics.cell_size,
+                cell_size,
             );
             win.set_text_cursor_position(r);
         }
@@ -2607,6 +2663,49 @@ impl TermWindow {
         promise::spawn::spawn(future).detach();
     }
 
+    fn show_search_all_panes(&mut self, args: &SearchAllPanesArguments) {
+        let mux = Mux::get();
+        let tab = match mux.get_active_tab_for_window(self.mux_window_id) {
+            Some(tab) => tab,
+            None => return,
+        };
+
+        let pane = match self.get_active_pane_no_overlay() {
+            Some(pane) => pane,
+            None => return,
+        };
+        let pattern = self.resolve_search_pattern(args.pattern.clone(), &pane);
+
+        let workspace = mux.active_workspace();
+        let mut panes = vec![];
+        for window_id in mux.iter_windows_in_workspace(&workspace) {
+            let window = match mux.get_window(window_id) {
+                Some(window) => window,
+                None => continue,
+            };
+            for (tab_idx, tab) in window.iter().enumerate() {
+                for pos in tab.iter_panes_ignoring_zoom() {
+                    panes.push(crate::overlay::global_search::SearchablePane {
+                        window_id,
+                        tab_idx,
+                        tab_title: tab.get_title(),
+                        pane_idx: pos.index,
+                        pane: pos.pane,
+                    });
+                }
+            }
+        }
+
+        let args = args.clone();
+        let window = self.window.clone().unwrap();
+
+        let (overlay, future) = start_overlay(self, &tab, move |_tab_id, term| {
+            crate::overlay::global_search::global_search(term, panes, pattern, args, window)
+        });
+        self.assign_overlay(tab.tab_id(), overlay);
+        promise::spawn::spawn(future).detach();
+    }
+
     fn show_debug_overlay(&mut self) {
         let mux = Mux::get();
         let tab = match mux.get_active_tab_for_window(self.mux_window_id) {
@@ -2756,6 +2855,21 @@ impl TermWindow {
         &cache.zones
     }
 
+    /// Returns the command blocks recorded by shell integration
+    fn get_command_blocks(&mut self, pane: &Arc<dyn Pane>) -> &[CommandBlock] {
+        let cache = self
+            .command_blocks
+            .entry(pane.pane_id())
+            .or_insert_with(CommandBlockCache::default);
+
+        let seqno = pane.get_current_seqno();
+        if cache.seqno != seqno {
+            cache.blocks = pane.get_command_blocks().unwrap_or_else(|_| vec![]);
+            cache.seqno = seqno;
+        }
+        &cache.blocks
+    }
+
     fn scroll_to_prompt(&mut self, amount: isize, pane: &Arc<dyn Pane>) -> anyhow::Result<()> {
         let dims = pane.get_dimensions();
         let position = self
@@ -2985,6 +3099,9 @@ impl TermWindow {
             DecreaseFontSize => self.decrease_font_size(),
             IncreaseFontSize => self.increase_font_size(),
             ResetFontSize => self.reset_font_size(),
+            DecreasePaneFontSize => self.decrease_pane_font_size(pane)?,
+            IncreasePaneFontSize => self.increase_pane_font_size(pane)?,
+            ResetPaneFontSize => self.reset_pane_font_size(pane)?,
             ResetFontAndWindowSize => {
                 if let Some(w) = window.as_ref() {
                     self.reset_font_and_window_size(&w)?
@@ -3002,7 +3119,12 @@ impl TermWindow {
             ActivateWindowRelativeNoWrap(n) => {
                 self.activate_window_relative(*n, false)?;
             }
-            SendString(s) => pane.writer().write_all(s.as_bytes())?,
+            SendString(s) => {
+                pane.writer().write_all(s.as_bytes())?;
+                for target in self.broadcast_targets(pane) {
+                    target.writer().write_all(s.as_bytes()).ok();
+                }
+            }
             SendKey(key) => {
                 use keyevent::Key;
                 let mods = key.mods;
@@ -3010,6 +3132,9 @@ impl TermWindow {
                     &key.key.resolve(self.config.key_map_preference),
                 ) {
                     pane.key_down(key, mods)?;
+                    for target in self.broadcast_targets(pane) {
+                        target.key_down(key, mods).ok();
+                    }
                 }
             }
             Hide => {
@@ -3026,6 +3151,15 @@ impl TermWindow {
             CloseCurrentPane { confirm } => self.close_current_pane(*confirm),
             Nop | DisableDefaultAssignment => {}
             ReloadConfiguration => config::reload(),
+            ToggleBroadcastInput(scope) => self.toggle_broadcast_input(*scope),
+            TogglePaneBroadcastMark => self.toggle_pane_broadcast_mark(pane),
+            SerialPort(action) => self.perform_serial_port_action(action),
+            TogglePaneRecording(args) => self.toggle_pane_recording(pane, args),
+            PlayAsciicast(args) => self.play_asciicast(args),
+            ExportPane(args) => self.export_pane(pane, args),
+            ShowGlyphInspector => self.show_glyph_inspector(pane),
+            SaveSession => self.save_session(),
+            RestoreSession => self.restore_session(),
             MoveTab(n) => self.move_tab(*n)?,
             MoveTabRelative(n) => self.move_tab_relative(*n)?,
             ScrollByPage(n) => self.scroll_by_page(**n, pane)?,
@@ -3277,7 +3411,11 @@ impl TermWindow {
                     front_end().switch_workspace(w);
                 }
             }
-            SwitchToWorkspace { name, spawn } => {
+            SwitchToWorkspace {
+                name,
+                spawn,
+                layout,
+            } => {
                 let activity = crate::Activity::new();
                 let mux = Mux::get();
                 let name = name
@@ -3288,8 +3426,33 @@ impl TermWindow {
                 mux.set_active_workspace(&name);
 
                 if mux.iter_windows_in_workspace(&name).is_empty() {
-                    let spawn = spawn.as_ref().map(|s| s.clone()).unwrap_or_default();
                     let size = self.terminal_size;
+
+                    // A layout, if specified, takes precedence over `spawn`
+                    let workspace_layout = layout.as_ref().and_then(|layout| {
+                        let workspace_layout = self.config.workspace_layouts.get(layout).cloned();
+                        if workspace_layout.is_none() {
+                            log::error!("SwitchToWorkspace: there is no layout named {layout}");
+                        }
+                        workspace_layout
+                    });
+
+                    if let Some(workspace_layout) = workspace_layout {
+                        promise::spawn::spawn(async move {
+                            if let Err(err) =
+                                mux::layout::spawn_workspace_layout(&workspace_layout, name, size)
+                                    .await
+                            {
+                                log::error!("Failed to spawn layout: {:#}", err);
+                            }
+                            switcher.do_switch();
+                            drop(activity);
+                        })
+                        .detach();
+                        return Ok(PerformAssignmentResult::Handled);
+                    }
+
+                    let spawn = spawn.as_ref().map(|s| s.clone()).unwrap_or_default();
                     let term_config = Arc::new(TermConfig::with_config(self.config.clone()));
                     let src_window_id = self.mux_window_id;
 
@@ -3421,6 +3584,7 @@ impl TermWindow {
             PromptInputLine(args) => self.show_prompt_input_line(args),
             InputSelector(args) => self.show_input_selector(args),
             Confirmation(args) => self.show_confirmation(args),
+            SearchAllPanes(args) => self.show_search_all_panes(args),
         };
         Ok(PerformAssignmentResult::Handled)
     }
@@ -3568,16 +3732,19 @@ impl TermWindow {
             if let Some(overlay) = state.overlay.as_ref().map(|o| &o.pane) {
                 if let Some(pane) = mux.get_pane(*pane_id) {
                     let dims = pane.get_dimensions();
+                    let render_metrics =
+                        match state.font.as_ref().filter(|font| font.is_current(*pane_id)) {
+                            Some(font) => font.render_metrics,
+                            None => self.render_metrics,
+                        };
                     overlay
                         .resize(TerminalSize {
                             cols: dims.cols,
                             rows: dims.viewport_rows,
                             dpi: self.terminal_size.dpi,
-                            pixel_height: (self.terminal_size.pixel_height
-                                / self.terminal_size.rows)
+                            pixel_height: render_metrics.cell_size.height as usize
                                 * dims.viewport_rows,
-                            pixel_width: (self.terminal_size.pixel_width / self.terminal_size.cols)
-                                * dims.cols,
+                            pixel_width: render_metrics.cell_size.width as usize * dims.cols,
                         })
                         .ok();
                 }
@@ -3710,6 +3877,7 @@ impl TermWindow {
             title: pos.pane.get_title(),
             user_vars: pos.pane.copy_user_vars(),
             progress: pos.pane.get_progress(),
+            is_recording: pos.pane.is_recording(),
         }
     }
 
@@ -3822,7 +3990,13 @@ impl TermWindow {
     }
 
     fn cancel_overlay_for_pane(&mut self, pane_id: PaneId) {
-        if let Some(overlay) = self.pane_state(pane_id).overlay.take() {
+        let overlay = {
+            let mut state = self.pane_state(pane_id);
+            let overlay = state.overlay.take();
+            state.overlay = state.suspended_overlays.pop();
+            overlay
+        };
+        if let Some(overlay) = overlay {
             // Ungh, when I built the CopyOverlay, its pane doesn't get
             // added to the mux and instead it reports the overlaid
             // pane id.  Take care to avoid killing ourselves off
@@ -3841,7 +4015,11 @@ impl TermWindow {
     }
 
     pub fn assign_overlay_for_pane(&mut self, pane_id: PaneId, pane: Arc<dyn Pane>) {
-        self.cancel_overlay_for_pane(pane_id);
+        // Closing an overlay reveals any that it was hiding, so keep
+        // going until they are all gone
+        while self.pane_state(pane_id).overlay.is_some() {
+            self.cancel_overlay_for_pane(pane_id);
+        }
         self.pane_state(pane_id).overlay.replace(OverlayState {
             pane,
             key_table_state: KeyTableState::default(),
@@ -3849,6 +4027,23 @@ impl TermWindow {
         self.update_title();
     }
 
+    /// Like `assign_overlay_for_pane`, but rather than closing an
+    /// overlay that is already showing over the pane, hides it until
+    /// the new overlay is closed
+    pub fn push_overlay_for_pane(&mut self, pane_id: PaneId, pane: Arc<dyn Pane>) {
+        {
+            let mut state = self.pane_state(pane_id);
+            if let Some(previous) = state.overlay.take() {
+                state.suspended_overlays.push(previous);
+            }
+            state.overlay.replace(OverlayState {
+                pane,
+                key_table_state: KeyTableState::default(),
+            });
+        }
+        self.update_title();
+    }
+
     pub fn assign_overlay(&mut self, tab_id: TabId, overlay: Arc<dyn Pane>) {
         self.cancel_overlay_for_tab(tab_id, None);
         self.tab_state(tab_id).overlay.replace(OverlayState {
@@ -1,3 +1,4 @@
+use crate::selection::{SelectionCoordinate, SelectionRange};
 use crate::tabbar::TabBarItem;
 use crate::termwindow::{
     GuiWin, MouseCapture, PositionedSplit, ScrollHit, TermWindowNotif, UIItem, UIItemType, TMB,
@@ -8,7 +9,7 @@ use ::window::{
 };
 use config::keyassignment::{KeyAssignment, MouseEventTrigger, SpawnTabDomain};
 use config::MouseEventAltScreen;
-use mux::pane::{Pane, WithPaneLines};
+use mux::pane::{Pane, PaneId, WithPaneLines};
 use mux::tab::SplitDirection;
 use mux::Mux;
 use mux_lua::MuxPane;
@@ -21,7 +22,7 @@ use termwiz::hyperlink::Hyperlink;
 use termwiz::surface::Line;
 use wezterm_dynamic::ToDynamic;
 use wezterm_term::input::{MouseButton, MouseEventKind as TMEK};
-use wezterm_term::{ClickPosition, LastMouseClick, StableRowIndex};
+use wezterm_term::{ClickPosition, CommandBlock, LastMouseClick, StableRowIndex};
 
 /// Horizontal scroll speed multiplier for mouse wheel events
 const HORIZONTAL_SCROLL_SPEED: f32 = 30.0;
@@ -42,6 +43,9 @@ impl super::TermWindow {
             UIItemType::TabBar(_) => {
                 self.update_title_post_status();
             }
+            UIItemType::CommandGutter(..) => {
+                self.hovered_command_gutter.take();
+            }
             UIItemType::CloseTab(_)
             | UIItemType::AboveScrollThumb
             | UIItemType::BelowScrollThumb
@@ -62,6 +66,9 @@ impl super::TermWindow {
     fn enter_ui_item(&mut self, item: &UIItem) {
         match item.item_type {
             UIItemType::TabBar(_) => {}
+            UIItemType::CommandGutter(..) => {
+                self.hovered_command_gutter.replace(item.clone());
+            }
             UIItemType::CloseTab(_)
             | UIItemType::AboveScrollThumb
             | UIItemType::BelowScrollThumb
@@ -79,15 +86,10 @@ impl super::TermWindow {
         }
     }
 
-    pub fn mouse_event_impl(&mut self, event: MouseEvent, context: &dyn WindowOps) {
-        log::trace!("{:?}", event);
-        let pane = match self.get_active_pane_or_overlay() {
-            Some(pane) => pane,
-            None => return,
-        };
-
-        self.current_mouse_event.replace(event.clone());
-
+    /// Returns the pixel position of the top left of the terminal
+    /// area of the window, taking the tab bar, border and padding
+    /// into account
+    fn terminal_origin(&self) -> (isize, isize) {
         let border = self.get_os_border();
 
         let first_line_offset = if self.show_tab_bar && !self.config.tab_bar_at_bottom {
@@ -98,19 +100,26 @@ impl super::TermWindow {
 
         let (padding_left, padding_top) = self.padding_left_top();
 
-        let y = (event
-            .coords
-            .y
-            .sub(padding_top as isize)
-            .sub(first_line_offset)
-            .max(0)
-            / self.render_metrics.cell_size.height) as i64;
+        (
+            (padding_left + border.left.get() as f32) as isize,
+            padding_top as isize + first_line_offset,
+        )
+    }
 
-        let x = (event
-            .coords
-            .x
-            .sub((padding_left + border.left.get() as f32) as isize)
-            .max(0) as f32)
+    pub fn mouse_event_impl(&mut self, event: MouseEvent, context: &dyn WindowOps) {
+        log::trace!("{:?}", event);
+        let pane = match self.get_active_pane_or_overlay() {
+            Some(pane) => pane,
+            None => return,
+        };
+
+        self.current_mouse_event.replace(event.clone());
+
+        let (origin_x, origin_y) = self.terminal_origin();
+
+        let y = (event.coords.y.sub(origin_y).max(0) / self.render_metrics.cell_size.height) as i64;
+
+        let x = (event.coords.x.sub(origin_x).max(0) as f32)
             / self.render_metrics.cell_size.width as f32;
         let x = if !pane.is_mouse_grabbed() {
             // Round the x coordinate so that we're a bit more forgiving of
@@ -121,19 +130,12 @@ impl super::TermWindow {
         }
         .trunc() as usize;
 
-        let mut y_pixel_offset = event
-            .coords
-            .y
-            .sub(padding_top as isize)
-            .sub(first_line_offset);
+        let mut y_pixel_offset = event.coords.y.sub(origin_y);
         if y > 0 {
             y_pixel_offset = y_pixel_offset.max(0) % self.render_metrics.cell_size.height;
         }
 
-        let mut x_pixel_offset = event
-            .coords
-            .x
-            .sub((padding_left + border.left.get() as f32) as isize);
+        let mut x_pixel_offset = event.coords.x.sub(origin_x);
         if x > 0 {
             x_pixel_offset = x_pixel_offset.max(0) % self.render_metrics.cell_size.width;
         }
@@ -517,6 +519,42 @@ impl super::TermWindow {
             UIItemType::CodeBlockCopyButton(block_id) => {
                 self.mouse_event_code_block_copy_button(block_id.clone(), event, context);
             }
+            UIItemType::CommandGutter(pane_id, block) => {
+                self.mouse_event_command_gutter(*pane_id, block.clone(), event, context);
+            }
+        }
+    }
+
+    pub fn mouse_event_command_gutter(
+        &mut self,
+        pane_id: PaneId,
+        block: CommandBlock,
+        event: MouseEvent,
+        context: &dyn WindowOps,
+    ) {
+        context.set_cursor(Some(MouseCursor::Arrow));
+
+        if let WMEK::Press(MousePress::Left) = event.kind {
+            let pane = match Mux::get().get_pane(pane_id) {
+                Some(pane) => pane,
+                None => return,
+            };
+            let dims = pane.get_dimensions();
+            let last_row = dims.physical_top + dims.viewport_rows as StableRowIndex;
+            match block.output_range(last_row) {
+                Some(range) if !range.is_empty() => {
+                    let start = SelectionCoordinate::x_y(0, range.start);
+                    let end = SelectionCoordinate::x_y(usize::max_value(), range.end - 1);
+                    let mut selection = self.selection(pane_id);
+                    selection.origin = Some(start);
+                    selection.range = Some(SelectionRange { start, end });
+                    selection.rectangular = false;
+                    selection.seqno = pane.get_current_seqno();
+                    drop(selection);
+                    context.invalidate();
+                }
+                _ => {}
+            }
         }
     }
 
@@ -905,6 +943,8 @@ impl super::TermWindow {
             self.current_mouse_capture,
             Some(MouseCapture::TerminalPane(_))
         );
+        // The window cell position of the pane under the mouse
+        let mut pane_origin = None;
 
         for pos in self.get_panes_to_render() {
             if !is_already_captured
@@ -945,6 +985,7 @@ impl super::TermWindow {
                 }
                 column = column.saturating_sub(pos.left);
                 row = row.saturating_sub(pos.top as i64);
+                pane_origin.replace((pos.left, pos.top));
                 break;
             } else if is_already_captured && pane.pane_id() == pos.pane.pane_id() {
                 column = column.saturating_sub(pos.left);
@@ -959,10 +1000,37 @@ impl super::TermWindow {
                         * (pos.top as isize - position.row as isize);
                 }
 
+                pane_origin.replace((pos.left, pos.top));
                 break;
             }
         }
 
+        // A pane with its own font size has differently sized cells,
+        // so work out which of its cells is under the mouse
+        if let Some((left, top)) = pane_origin {
+            let (_, metrics) = self.fonts_for_pane(pane.pane_id());
+            if metrics.cell_size != self.render_metrics.cell_size {
+                let (origin_x, origin_y) = self.terminal_origin();
+                let x =
+                    event.coords.x - origin_x - left as isize * self.render_metrics.cell_size.width;
+                let y =
+                    event.coords.y - origin_y - top as isize * self.render_metrics.cell_size.height;
+
+                column = (x.max(0) / metrics.cell_size.width) as usize;
+                row = (y.max(0) / metrics.cell_size.height) as i64;
+                x_pixel_offset = if column > 0 {
+                    x % metrics.cell_size.width
+                } else {
+                    x
+                };
+                y_pixel_offset = if row > 0 {
+                    y % metrics.cell_size.height
+                } else {
+                    y
+                };
+            }
+        }
+
         if capture_mouse {
             self.current_mouse_capture = Some(MouseCapture::TerminalPane(pane.pane_id()));
         }
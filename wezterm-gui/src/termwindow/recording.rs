@@ -0,0 +1,45 @@
+use crate::termwindow::TermWindow;
+use config::keyassignment::{PaneRecordingArgs, PlayAsciicast};
+use mux::pane::Pane;
+use mux::recording::RecordingOptions;
+use std::sync::Arc;
+
+impl TermWindow {
+    /// Starts recording the output of the pane to an asciicast
+    /// file, or stops recording it if it is already being recorded
+    pub fn toggle_pane_recording(&mut self, pane: &Arc<dyn Pane>, args: &PaneRecordingArgs) {
+        let pane = Arc::clone(pane);
+        let options = RecordingOptions {
+            path: None,
+            capture_input: args.capture_input,
+        };
+        promise::spawn::spawn(async move {
+            if pane.is_recording() {
+                match pane.stop_recording().await {
+                    Ok(Some(path)) => log::info!("saved recording to {path}"),
+                    Ok(None) => {}
+                    Err(err) => log::error!("stopping recording: {err:#}"),
+                }
+            } else {
+                match pane.start_recording(options).await {
+                    Ok(path) => log::info!("recording pane to {path}"),
+                    Err(err) => log::error!("starting recording: {err:#}"),
+                }
+            }
+        })
+        .detach();
+    }
+
+    /// Opens a new tab in this window that plays back an
+    /// asciicast recording
+    pub fn play_asciicast(&mut self, args: &PlayAsciicast) {
+        let window_id = self.mux_window_id;
+        let args = args.clone();
+        promise::spawn::spawn(async move {
+            if let Err(err) = mux::player::spawn_player_tab(window_id, &args).await {
+                log::error!("playing {}: {err:#}", args.path);
+            }
+        })
+        .detach();
+    }
+}
@@ -1,12 +1,13 @@
 use crate::colorease::ColorEase;
 use crate::customglyph::{BlockKey, *};
-use crate::glyphcache::{CachedGlyph, GlyphCache};
+use crate::glyphcache::{CachedGlyph, CellMetricKey, GlyphCache};
 use crate::quad::{
     HeapQuadAllocator, QuadAllocator, QuadImpl, QuadTrait, TripleLayerQuadAllocator,
     TripleLayerQuadAllocatorTrait,
 };
 use crate::shapecache::*;
 use crate::termwindow::render::paint::AllowImage;
+use crate::termwindow::resize::effective_left_padding;
 use crate::termwindow::{BorrowedShapeCacheKey, RenderState, ShapedInfo, TermWindowNotif};
 use crate::utilsprites::RenderMetrics;
 use ::window::bitmaps::{TextureCoord, TextureRect, TextureSize};
@@ -29,7 +30,7 @@ use termwiz::hyperlink::Hyperlink;
 use termwiz::surface::{CursorShape, CursorVisibility, SequenceNo};
 use wezterm_font::shaper::PresentationWidth;
 use wezterm_font::units::{IntPixelLength, PixelLength};
-use wezterm_font::{ClearShapeCache, GlyphInfo, LoadedFont};
+use wezterm_font::{ClearShapeCache, FontConfiguration, GlyphInfo, LoadedFont};
 use wezterm_term::color::{ColorAttribute, ColorPalette};
 use wezterm_term::{CellAttributes, Line, StableRowIndex};
 use window::color::LinearRgba;
@@ -93,6 +94,8 @@ pub struct LineQuadCacheValue {
 pub struct LineToElementParams<'a> {
     pub line: &'a Line,
     pub config: &'a ConfigHandle,
+    pub fonts: &'a FontConfiguration,
+    pub render_metrics: &'a RenderMetrics,
     pub palette: &'a ColorPalette,
     pub window_is_transparent: bool,
     pub reverse_video: bool,
@@ -104,6 +107,7 @@ pub struct LineToEleShapeCacheKey {
     pub shape_hash: [u8; 16],
     pub composing: Option<(usize, String)>,
     pub shape_generation: usize,
+    pub metric: CellMetricKey,
 }
 
 pub struct LineToElementShapeItem {
@@ -167,6 +171,9 @@ pub struct RenderScreenLineParams<'a> {
     /// the resolved title font
     pub font: Option<Rc<LoadedFont>>,
     pub style: Option<&'a TextStyle>,
+    /// The fonts with which to resolve styles; these differ from
+    /// the fonts of the window for panes with their own font size
+    pub fonts: &'a FontConfiguration,
 
     /// If true, use the shaper-determined pixel positions,
     /// rather than using monospace cell based positions.
@@ -357,11 +364,7 @@ impl crate::TermWindow {
             pixel_cell: self.render_metrics.cell_size.height as f32,
         };
 
-        let mut padding_left = self
-            .config
-            .window_padding
-            .left
-            .evaluate_as_pixels(h_context);
+        let mut padding_left = effective_left_padding(&self.config, h_context) as f32;
 
         // Add left button bar width if left sidebar is enabled
         let sidebar_manager = self.sidebar_manager.borrow();
@@ -415,13 +418,15 @@ impl crate::TermWindow {
         style: &TextStyle,
         attrs: &CellAttributes,
         font: Option<&Rc<LoadedFont>>,
+        fonts: &FontConfiguration,
         gl_state: &RenderState,
         metrics: &RenderMetrics,
     ) -> anyhow::Result<Rc<CachedGlyph>> {
         let fa_lock = "\u{f023}";
         let line = Line::from_text(fa_lock, attrs, 0, None);
         let cluster = line.cluster(None);
-        let shape_info = self.cached_cluster_shape(style, &cluster[0], gl_state, font, metrics)?;
+        let shape_info =
+            self.cached_cluster_shape(style, &cluster[0], gl_state, font, fonts, metrics)?;
         Ok(Rc::clone(&shape_info[0].glyph))
     }
 
@@ -793,19 +798,22 @@ impl crate::TermWindow {
         Ok(glyphs)
     }
 
-    /// Shape the printable text from a cluster
+    /// Shape the printable text from a cluster.
+    /// `font` overrides the font that `fonts` would resolve for `style`.
     fn cached_cluster_shape(
         &self,
         style: &TextStyle,
         cluster: &CellCluster,
         gl_state: &RenderState,
         font: Option<&Rc<LoadedFont>>,
+        fonts: &FontConfiguration,
         metrics: &RenderMetrics,
     ) -> anyhow::Result<Rc<Vec<ShapedInfo>>> {
         let shape_resolve_start = Instant::now();
         let key = BorrowedShapeCacheKey {
             style,
             text: &cluster.text,
+            metric: metrics.into(),
         };
         let glyph_info = match self.lookup_cached_shape(&key) {
             Some(Ok(info)) => info,
@@ -813,7 +821,7 @@ impl crate::TermWindow {
             None => {
                 let font = match font {
                     Some(f) => Rc::clone(f),
-                    None => self.fonts.resolve_font(style)?,
+                    None => fonts.resolve_font(style)?,
                 };
                 let window = self.window.as_ref().unwrap().clone();
 
@@ -920,7 +928,7 @@ impl crate::TermWindow {
     }
 }
 
-fn resolve_fg_color_attr(
+pub(crate) fn resolve_fg_color_attr(
     attrs: &CellAttributes,
     fg: ColorAttribute,
     palette: &ColorPalette,
@@ -265,6 +265,7 @@ impl crate::TermWindow {
             .context("filled_rectangle for window background")?;
         }
 
+        self.update_broadcast_indicators();
         for pos in panes {
             if pos.is_active {
                 self.update_text_cursor(&pos);
@@ -297,6 +298,8 @@ impl crate::TermWindow {
         self.paint_window_borders(&mut layers)
             .context("paint_window_borders")?;
         drop(layers);
+        self.paint_command_gutter_tooltip()
+            .context("paint_command_gutter_tooltip")?;
         self.paint_modal().context("paint_modal")?;
 
         log::trace!("paint_pass: completed successfully");
@@ -6,18 +6,22 @@ use crate::termwindow::render::{
     RenderScreenLineParams,
 };
 use crate::termwindow::{ScrollHit, UIItem, UIItemType};
+use crate::utilsprites::RenderMetrics;
 use ::window::bitmaps::TextureRect;
 use ::window::DeadKeyStatus;
 use anyhow::Context;
-use config::VisualBellTarget;
+use chrono::TimeZone;
+use config::{Dimension, DimensionContext, VisualBellTarget};
 use mux::pane::{PaneId, WithPaneLines};
 use mux::renderable::{RenderableDimensions, StableCursorPosition};
 use mux::tab::PositionedPane;
 use ordered_float::NotNan;
-use std::time::Instant;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
 use wezterm_dynamic::Value;
+use wezterm_font::FontConfiguration;
 use wezterm_term::color::{ColorAttribute, ColorPalette};
-use wezterm_term::{Line, StableRowIndex};
+use wezterm_term::{CommandBlock, CommandBlockStatus, Line, StableRowIndex};
 use window::color::LinearRgba;
 
 impl crate::TermWindow {
@@ -85,6 +89,9 @@ impl crate::TermWindow {
         let pane_id = pos.pane.pane_id();
         let current_viewport = self.get_viewport(pane_id);
         let dims = pos.pane.get_dimensions();
+        // The position of the pane is measured in the cells of the window,
+        // but its content may use a different font size
+        let (fonts, render_metrics) = self.fonts_for_pane(pane_id);
 
         let gl_state = self.render_state.as_ref().unwrap();
 
@@ -231,6 +238,45 @@ impl crate::TermWindow {
             }
         }
 
+        if let Some(alpha) = self.broadcast_indicator_alpha(pane_id) {
+            // Frame the panes that receive broadcast input
+            let color = config
+                .resolved_palette
+                .broadcast_input
+                .as_deref()
+                .unwrap_or(&palette.colors.0[1])
+                .to_linear()
+                .mul_alpha(alpha);
+            let thickness = (cell_width / 4.).max(1.);
+            let rect = background_rect;
+            for edge in [
+                euclid::rect(rect.min_x(), rect.min_y(), rect.width(), thickness),
+                euclid::rect(
+                    rect.min_x(),
+                    rect.max_y() - thickness,
+                    rect.width(),
+                    thickness,
+                ),
+                euclid::rect(rect.min_x(), rect.min_y(), thickness, rect.height()),
+                euclid::rect(
+                    rect.max_x() - thickness,
+                    rect.min_y(),
+                    thickness,
+                    rect.height(),
+                ),
+            ] {
+                self.filled_rectangle(layers, 2, edge, color)
+                    .context("filled_rectangle for broadcast indicator")?;
+            }
+        }
+
+        // Space for the gutter is only reserved in the window padding,
+        // so a pane with another pane to its left has nowhere to put it
+        if config.enable_command_gutter && pos.left == 0 {
+            self.paint_command_gutter(pos, layers, &palette, top_pixel_y, current_viewport)
+                .context("paint_command_gutter")?;
+        }
+
         // TODO: we only have a single scrollbar in a single position.
         // We only update it for the active pane, but we should probably
         // do a per-pane scrollbar.  That will require more extensive
@@ -413,6 +459,8 @@ impl crate::TermWindow {
                 white_space: TextureRect,
                 filled_box: TextureRect,
                 window_is_transparent: bool,
+                fonts: Rc<FontConfiguration>,
+                render_metrics: RenderMetrics,
                 layers: &'a mut TripleLayerQuadAllocator<'b>,
                 error: Option<anyhow::Error>,
             }
@@ -443,6 +491,8 @@ impl crate::TermWindow {
                 white_space,
                 filled_box,
                 window_is_transparent,
+                fonts,
+                render_metrics,
                 layers,
                 error: None,
             };
@@ -515,8 +565,9 @@ impl crate::TermWindow {
                         cursor,
                         shape_hash,
                         top_pixel_y: NotNan::new(self.top_pixel_y).unwrap()
-                            + (line_idx + self.pos.top) as f32
-                                * self.term_window.render_metrics.cell_size.height as f32,
+                            + self.pos.top as f32
+                                * self.term_window.render_metrics.cell_size.height as f32
+                            + line_idx as f32 * self.render_metrics.cell_size.height as f32,
                         left_pixel_x: NotNan::new(self.left_pixel_x).unwrap(),
                         phys_line_idx: line_idx,
                         reverse_video: self.dims.reverse_video,
@@ -553,6 +604,7 @@ impl crate::TermWindow {
                     let shape_key = LineToEleShapeCacheKey {
                         shape_hash,
                         shape_generation: quad_key.shape_generation,
+                        metric: (&self.render_metrics).into(),
                         composing: if self.cursor.y == stable_row && self.pos.is_active {
                             if let DeadKeyStatus::Composing(composing) =
                                 &self.term_window.dead_key_status
@@ -573,7 +625,7 @@ impl crate::TermWindow {
                                 top_pixel_y: *quad_key.top_pixel_y,
                                 left_pixel_x: self.left_pixel_x,
                                 pixel_width: self.dims.cols as f32
-                                    * self.term_window.render_metrics.cell_size.width as f32,
+                                    * self.render_metrics.cell_size.width as f32,
                                 stable_line_idx: Some(stable_row),
                                 line: &line,
                                 selection: selrange.clone(),
@@ -596,11 +648,12 @@ impl crate::TermWindow {
                                 default_bg: self.default_bg,
                                 font: None,
                                 style: None,
+                                fonts: &self.fonts,
                                 use_pixel_positioning: self
                                     .term_window
                                     .config
                                     .experimental_pixel_positioning,
-                                render_metrics: self.term_window.render_metrics,
+                                render_metrics: self.render_metrics,
                                 shape_key: Some(shape_key),
                                 password_input,
                             },
@@ -662,6 +715,222 @@ impl crate::TermWindow {
         Ok(())
     }
 
+    /// Paints a marker to the left of each command prompt, colored
+    /// by the exit status of the command that was run from it.
+    fn paint_command_gutter(
+        &mut self,
+        pos: &PositionedPane,
+        layers: &mut TripleLayerQuadAllocator,
+        palette: &ColorPalette,
+        top_pixel_y: f32,
+        current_viewport: Option<StableRowIndex>,
+    ) -> anyhow::Result<()> {
+        let dims = pos.pane.get_dimensions();
+        let top = current_viewport.unwrap_or(dims.physical_top);
+        let visible = top..top + dims.viewport_rows as StableRowIndex;
+
+        let blocks: Vec<CommandBlock> = self
+            .get_command_blocks(&pos.pane)
+            .iter()
+            .filter(|block| {
+                block.prompt_y < visible.end
+                    && block
+                        .output_range(visible.end)
+                        .map_or(block.prompt_y + 1, |range| {
+                            range.end.max(block.prompt_y + 1)
+                        })
+                        > visible.start
+            })
+            .cloned()
+            .collect();
+        if blocks.is_empty() {
+            return Ok(());
+        }
+
+        let cell_width = self.render_metrics.cell_size.width as f32;
+        let cell_height = self.render_metrics.cell_size.height as f32;
+        let row_height = self.fonts_for_pane(pos.pane.pane_id()).1.cell_size.height as f32;
+        let gutter_width = self
+            .config
+            .command_gutter_width
+            .evaluate_as_pixels(DimensionContext {
+                dpi: self.dimensions.dpi as f32,
+                pixel_max: self.terminal_size.pixel_width as f32,
+                pixel_cell: cell_width,
+            });
+        let (padding_left, _padding_top) = self.padding_left_top();
+        let border = self.get_os_border();
+        let left_pixel_x = padding_left + border.left.get() as f32;
+        let gutter_x = (left_pixel_x - gutter_width).max(border.left.get() as f32);
+        let marker_x = gutter_x + gutter_width / 4.;
+        let marker_width = (gutter_width / 2.).max(1.);
+
+        let resolved = &self.config.resolved_palette;
+        let success = resolved
+            .command_gutter_success
+            .as_deref()
+            .unwrap_or(&palette.colors.0[2])
+            .to_linear();
+        let failure = resolved
+            .command_gutter_failure
+            .as_deref()
+            .unwrap_or(&palette.colors.0[1])
+            .to_linear();
+        let running = resolved
+            .command_gutter_running
+            .as_deref()
+            .unwrap_or(&palette.colors.0[3])
+            .to_linear();
+        let unknown = palette.foreground.to_linear().mul_alpha(0.5);
+
+        // Converts a stable row into the y coordinate of that row
+        let row_to_y = |row: StableRowIndex| {
+            top_pixel_y + (pos.top as f32 * cell_height) + ((row - top) as f32 * row_height)
+        };
+
+        for block in blocks {
+            let color = match block.status() {
+                CommandBlockStatus::Prompt => continue,
+                CommandBlockStatus::Running => running,
+                CommandBlockStatus::Succeeded => success,
+                CommandBlockStatus::Failed(_) => failure,
+                CommandBlockStatus::Unknown => unknown,
+            };
+
+            let first_row = block.prompt_y.max(visible.start);
+            let end_row = block
+                .output_range(visible.end)
+                .map_or(block.prompt_y + 1, |range| {
+                    range.end.max(block.prompt_y + 1)
+                })
+                .min(visible.end);
+
+            if visible.contains(&block.prompt_y) {
+                let y = row_to_y(block.prompt_y);
+                self.filled_rectangle(
+                    layers,
+                    2,
+                    euclid::rect(marker_x, y, marker_width, row_height),
+                    color,
+                )?;
+
+                if let CommandBlockStatus::Failed(_) = block.status() {
+                    // Tint the prompt line so that failures stand out
+                    // when scanning back through the scrollback
+                    self.filled_rectangle(
+                        layers,
+                        0,
+                        euclid::rect(left_pixel_x, y, pos.width as f32 * cell_width, row_height),
+                        failure.mul_alpha(0.15),
+                    )?;
+                }
+            }
+
+            let output_start = (block.prompt_y + 1).max(visible.start);
+            if output_start < end_row {
+                self.filled_rectangle(
+                    layers,
+                    2,
+                    euclid::rect(
+                        marker_x + marker_width / 4.,
+                        row_to_y(output_start),
+                        marker_width / 2.,
+                        (end_row - output_start) as f32 * row_height,
+                    ),
+                    color.mul_alpha(0.35),
+                )?;
+            }
+
+            let y = row_to_y(first_row);
+            self.ui_items.push(UIItem {
+                x: gutter_x as usize,
+                width: gutter_width as usize,
+                y: y as usize,
+                height: ((end_row - first_row) as f32 * row_height) as usize,
+                item_type: UIItemType::CommandGutter(pos.pane.pane_id(), block),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Shows the status, duration and completion time of the command
+    /// whose gutter marker is under the mouse
+    pub fn paint_command_gutter_tooltip(&mut self) -> anyhow::Result<()> {
+        let item = match self.hovered_command_gutter.as_ref() {
+            Some(item) if self.config.enable_command_gutter => item.clone(),
+            _ => return Ok(()),
+        };
+        let block = match &item.item_type {
+            UIItemType::CommandGutter(_, block) => block,
+            _ => return Ok(()),
+        };
+
+        let mut caption = match block.status() {
+            CommandBlockStatus::Prompt => return Ok(()),
+            CommandBlockStatus::Running => "running".to_string(),
+            CommandBlockStatus::Succeeded => "exit 0".to_string(),
+            CommandBlockStatus::Failed(status) => format!("exit {status}"),
+            CommandBlockStatus::Unknown => "finished".to_string(),
+        };
+        if let Some(duration) = block.duration() {
+            caption.push_str(&format!(" in {}", format_command_duration(duration)));
+        }
+        if let Some(when) = block
+            .end_time_ms
+            .or(block.start_time_ms)
+            .and_then(|ms| chrono::Local.timestamp_millis_opt(ms as i64).single())
+        {
+            caption.push_str(&format!(" at {}", when.format("%H:%M:%S")));
+        }
+
+        let font = self.fonts.title_font()?;
+        let metrics = RenderMetrics::with_font_metrics(&font.metrics());
+        let palette = self.palette().clone();
+        let element = Element::new(&font, ElementContent::Text(caption))
+            .colors(ElementColors {
+                border: BorderColor::new(palette.foreground.to_linear().into()),
+                bg: palette.foreground.to_linear().into(),
+                text: palette.background.to_linear().into(),
+            })
+            .padding(BoxDimension {
+                left: Dimension::Cells(0.25),
+                right: Dimension::Cells(0.25),
+                top: Dimension::Cells(0.),
+                bottom: Dimension::Cells(0.),
+            })
+            .border(BoxDimension::new(Dimension::Pixels(1.)));
+
+        let dimensions = self.dimensions;
+        let computed = self.compute_element(
+            &LayoutContext {
+                height: DimensionContext {
+                    dpi: dimensions.dpi as f32,
+                    pixel_max: dimensions.pixel_height as f32,
+                    pixel_cell: metrics.cell_size.height as f32,
+                },
+                width: DimensionContext {
+                    dpi: dimensions.dpi as f32,
+                    pixel_max: dimensions.pixel_width as f32,
+                    pixel_cell: metrics.cell_size.width as f32,
+                },
+                bounds: euclid::rect(
+                    (item.x + item.width) as f32 + 2.,
+                    item.y as f32,
+                    dimensions.pixel_width.saturating_sub(item.x + item.width) as f32,
+                    metrics.cell_size.height as f32 * 2.,
+                ),
+                metrics: &metrics,
+                gl_state: self.render_state.as_ref().unwrap(),
+                zindex: 100,
+            },
+            &element,
+        )?;
+
+        let gl_state = self.render_state.as_ref().unwrap();
+        self.render_element(&computed, gl_state, None)
+    }
+
     pub fn build_pane(&mut self, pos: &PositionedPane) -> anyhow::Result<ComputedElement> {
         // First compute the bounds for the pane background
 
@@ -778,3 +1047,14 @@ impl crate::TermWindow {
         })
     }
 }
+
+fn format_command_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 3600 {
+        format!("{}h{:02}m", secs / 3600, (secs % 3600) / 60)
+    } else if secs >= 60 {
+        format!("{}m{:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{:.1}s", duration.as_secs_f32())
+    }
+}
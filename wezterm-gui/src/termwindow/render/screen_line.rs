@@ -143,6 +143,8 @@ impl crate::TermWindow {
         } else {
             let params = LineToElementParams {
                 config: params.config,
+                fonts: params.fonts,
+                render_metrics: &params.render_metrics,
                 line: params.line,
                 palette: params.palette,
                 window_is_transparent: params.window_is_transparent,
@@ -372,6 +374,7 @@ impl crate::TermWindow {
                             &TextStyle::default(),
                             &attrs,
                             params.font.as_ref(),
+                            params.fonts,
                             gl_state,
                             &params.render_metrics,
                         )
@@ -750,7 +753,7 @@ impl crate::TermWindow {
             if !matches!(last_style.as_ref(), Some(ClusterStyleCache{attrs,..}) if *attrs == &cluster.attrs)
             {
                 let attrs = &cluster.attrs;
-                let style = self.fonts.match_style(params.config, attrs);
+                let style = params.fonts.match_style(params.config, attrs);
                 let hyperlink = attrs.hyperlink();
                 let is_highlited_hyperlink =
                     same_hyperlink(hyperlink, self.current_highlight.as_ref());
@@ -766,7 +769,7 @@ impl crate::TermWindow {
                         attrs.strikethrough(),
                         attrs.underline(),
                         attrs.overline(),
-                        &self.render_metrics,
+                        params.render_metrics,
                     )?
                     .texture_coords();
                 let bg_is_default = attrs.background() == ColorAttribute::Default;
@@ -860,7 +863,8 @@ impl crate::TermWindow {
                 &cluster,
                 &gl_state,
                 None,
-                &self.render_metrics,
+                params.fonts,
+                params.render_metrics,
             )?;
             let pixel_width = glyph_info
                 .iter()
@@ -2,8 +2,11 @@ use crate::resize_increment_calculator::ResizeIncrementCalculator;
 use crate::utilsprites::RenderMetrics;
 use ::window::{Dimensions, ResizeIncrement, Window, WindowOps, WindowState};
 use config::{ConfigHandle, DimensionContext};
+use mux::pane::{Pane, PaneId};
+use mux::tab::TabId;
 use mux::Mux;
 use std::rc::Rc;
+use std::sync::Arc;
 use wezterm_font::FontConfiguration;
 use wezterm_term::TerminalSize;
 
@@ -19,6 +22,40 @@ pub enum ScaleChange {
     Relative(f64),
 }
 
+/// The fonts for a pane that is rendered with a different font size
+/// from the rest of the window
+pub struct PaneFont {
+    /// The font scale, relative to that of the window
+    pub scale: f64,
+    /// The tab in which the pane's cell size was recorded
+    pub tab_id: TabId,
+    pub fonts: Rc<FontConfiguration>,
+    pub render_metrics: RenderMetrics,
+}
+
+impl PaneFont {
+    /// The size of a cell, expressed in the form expected by
+    /// `Tab::set_pane_cell_size`
+    fn cell_size(&self) -> TerminalSize {
+        TerminalSize {
+            rows: 1,
+            cols: 1,
+            pixel_width: self.render_metrics.cell_size.width as usize,
+            pixel_height: self.render_metrics.cell_size.height as usize,
+            dpi: self.fonts.get_dpi() as u32,
+        }
+    }
+
+    /// Returns true if the tab still lays out the pane in these cells.
+    /// The tab forgets the cell size when the pane moves elsewhere,
+    /// after which the pane is laid out and rendered in window cells.
+    pub fn is_current(&self, pane_id: PaneId) -> bool {
+        Mux::get()
+            .get_tab(self.tab_id)
+            .map_or(false, |tab| tab.get_pane_cell_size(pane_id).is_some())
+    }
+}
+
 impl super::TermWindow {
     pub fn resize(
         &mut self,
@@ -124,6 +161,7 @@ impl super::TermWindow {
         if let Err(err) = self.recreate_texture_atlas(None) {
             log::error!("recreate_texture_atlas: {:#}", err);
         }
+        self.reload_pane_fonts();
         self.invalidate_fancy_tab_bar();
         self.invalidate_modal();
     }
@@ -202,8 +240,7 @@ impl super::TermWindow {
                 pixel_max: size.pixel_height as f32,
                 pixel_cell: self.render_metrics.cell_size.height as f32,
             };
-            let mut padding_left =
-                config.window_padding.left.evaluate_as_pixels(h_context) as usize;
+            let mut padding_left = effective_left_padding(&config, h_context);
             padding_left += self.left_button_bar_width() as usize;
             let padding_top = config.window_padding.top.evaluate_as_pixels(v_context) as usize;
             let padding_bottom =
@@ -259,8 +296,7 @@ impl super::TermWindow {
                 pixel_max: self.terminal_size.pixel_height as f32,
                 pixel_cell: self.render_metrics.cell_size.height as f32,
             };
-            let mut padding_left =
-                config.window_padding.left.evaluate_as_pixels(h_context) as usize;
+            let mut padding_left = effective_left_padding(&config, h_context);
             padding_left += self.left_button_bar_width() as usize;
             let padding_top = config.window_padding.top.evaluate_as_pixels(v_context) as usize;
             let padding_bottom =
@@ -501,6 +537,150 @@ impl super::TermWindow {
         self.apply_pending_scale_changes();
     }
 
+    /// Overlays are rendered using the fonts of the pane that they
+    /// cover; this maps the id of an overlay to the id of that pane.
+    fn pane_id_beneath_overlay(&self, pane_id: PaneId) -> PaneId {
+        self.pane_state
+            .borrow()
+            .iter()
+            .find(|(_, state)| {
+                state.overlay.as_ref().map(|overlay| overlay.pane.pane_id()) == Some(pane_id)
+            })
+            .map(|(id, _)| *id)
+            .unwrap_or(pane_id)
+    }
+
+    /// Returns the fonts and metrics with which to render the specified pane
+    pub fn fonts_for_pane(&self, pane_id: PaneId) -> (Rc<FontConfiguration>, RenderMetrics) {
+        let pane_id = self.pane_id_beneath_overlay(pane_id);
+        match self
+            .pane_state
+            .borrow()
+            .get(&pane_id)
+            .and_then(|state| state.font.as_ref())
+            .filter(|font| font.is_current(pane_id))
+        {
+            Some(font) => (Rc::clone(&font.fonts), font.render_metrics),
+            None => (Rc::clone(&self.fonts), self.render_metrics),
+        }
+    }
+
+    /// Returns the font scale of the pane, relative to that of the window
+    pub fn pane_font_scale(&self, pane_id: PaneId) -> f64 {
+        let pane_id = self.pane_id_beneath_overlay(pane_id);
+        self.pane_state
+            .borrow()
+            .get(&pane_id)
+            .and_then(|state| state.font.as_ref())
+            .filter(|font| font.is_current(pane_id))
+            .map_or(1.0, |font| font.scale)
+    }
+
+    fn load_pane_font(&self, scale: f64, tab_id: TabId) -> anyhow::Result<PaneFont> {
+        let dpi = self.dimensions.dpi;
+        let font_scale = self.fonts.get_font_scale() * scale;
+        let theoretical_height = self.config.font_size * font_scale * dpi as f64 / 72.0;
+        anyhow::ensure!(
+            theoretical_height >= 2.0,
+            "refusing to go to an unreasonably small font scale {} \
+             which would yield font_height {}",
+            scale,
+            theoretical_height
+        );
+
+        let fonts = Rc::new(FontConfiguration::new(Some(self.config.clone()), dpi)?);
+        fonts.change_scaling(font_scale, dpi);
+        let render_metrics = RenderMetrics::new(&fonts)?;
+        Ok(PaneFont {
+            scale,
+            tab_id,
+            fonts,
+            render_metrics,
+        })
+    }
+
+    /// Reloads the fonts of panes that have their own font scale,
+    /// following a change to the fonts of the window.
+    /// The caller is responsible for resizing the tabs to match.
+    fn reload_pane_fonts(&mut self) {
+        let scales: Vec<(PaneId, f64, TabId)> = self
+            .pane_state
+            .borrow()
+            .iter()
+            .filter_map(|(pane_id, state)| {
+                state
+                    .font
+                    .as_ref()
+                    .map(|font| (*pane_id, font.scale, font.tab_id))
+            })
+            .collect();
+        let mux = Mux::get();
+        for (pane_id, scale, tab_id) in scales {
+            let tab = match mux.get_tab(tab_id) {
+                Some(tab) if tab.get_pane_cell_size(pane_id).is_some() => tab,
+                _ => {
+                    // The pane has left the tab, which forgot its cells
+                    self.pane_state(pane_id).font = None;
+                    continue;
+                }
+            };
+            let font = match self.load_pane_font(scale, tab_id) {
+                Ok(font) => Some(font),
+                Err(err) => {
+                    log::error!("{:#} while reloading the font of pane {}", err, pane_id);
+                    None
+                }
+            };
+            tab.set_pane_cell_size(pane_id, font.as_ref().map(PaneFont::cell_size));
+            self.pane_state(pane_id).font = font;
+        }
+    }
+
+    /// Changes the font scale of a pane, relative to that of the window,
+    /// and then resizes the pane to fit as many of its cells as it can
+    /// into the space that it occupies in its tab
+    pub fn set_pane_font_scale(&mut self, pane_id: PaneId, scale: f64) -> anyhow::Result<()> {
+        let pane_id = self.pane_id_beneath_overlay(pane_id);
+        let mux = Mux::get();
+        let tab = match mux.resolve_pane_id(pane_id) {
+            Some((_domain, _window, tab_id)) => mux.get_tab(tab_id),
+            None => None,
+        }
+        .ok_or_else(|| anyhow::anyhow!("pane {} is not in a tab", pane_id))?;
+
+        // Snap back to the window font after a series of relative changes,
+        // so that we don't keep separate fonts around for no reason
+        let font = if (scale - 1.0).abs() < 0.001 {
+            None
+        } else {
+            Some(self.load_pane_font(scale, tab.tab_id())?)
+        };
+
+        tab.set_pane_cell_size(pane_id, font.as_ref().map(PaneFont::cell_size));
+        self.pane_state(pane_id).font = font;
+        tab.resize(tab.get_size());
+        self.resize_overlays();
+        self.quad_generation += 1;
+        if let Some(window) = self.window.as_ref() {
+            window.invalidate();
+        }
+        Ok(())
+    }
+
+    pub fn decrease_pane_font_size(&mut self, pane: &Arc<dyn Pane>) -> anyhow::Result<()> {
+        let scale = self.pane_font_scale(pane.pane_id());
+        self.set_pane_font_scale(pane.pane_id(), scale / 1.1)
+    }
+
+    pub fn increase_pane_font_size(&mut self, pane: &Arc<dyn Pane>) -> anyhow::Result<()> {
+        let scale = self.pane_font_scale(pane.pane_id());
+        self.set_pane_font_scale(pane.pane_id(), scale * 1.1)
+    }
+
+    pub fn reset_pane_font_size(&mut self, pane: &Arc<dyn Pane>) -> anyhow::Result<()> {
+        self.set_pane_font_scale(pane.pane_id(), 1.0)
+    }
+
     pub fn set_window_size(&mut self, size: TerminalSize, window: &Window) -> anyhow::Result<()> {
         let config = &self.config;
         let fontconfig = Rc::new(FontConfiguration::new(
@@ -534,7 +714,7 @@ impl super::TermWindow {
             pixel_max: self.dimensions.pixel_height as f32,
             pixel_cell: render_metrics.cell_size.height as f32,
         };
-        let mut padding_left = config.window_padding.left.evaluate_as_pixels(h_context) as usize;
+        let mut padding_left = effective_left_padding(&config, h_context);
         padding_left += self.left_button_bar_width() as usize;
         let padding_top = config.window_padding.top.evaluate_as_pixels(v_context) as usize;
         let padding_bottom = config.window_padding.bottom.evaluate_as_pixels(v_context) as usize;
@@ -610,6 +790,18 @@ impl super::TermWindow {
     }
 }
 
+/// Computes the effective padding for the LHS.
+/// If the command gutter is enabled then the padding must be at least
+/// wide enough to hold it.
+pub fn effective_left_padding(config: &ConfigHandle, context: DimensionContext) -> usize {
+    let padding = config.window_padding.left.evaluate_as_pixels(context);
+    if config.enable_command_gutter {
+        padding.max(config.command_gutter_width.evaluate_as_pixels(context)) as usize
+    } else {
+        padding as usize
+    }
+}
+
 /// Computes the effective padding for the RHS.
 /// This is needed because the default is 0, but if the user has
 /// enabled the scroll bar then they will expect it to have a reasonable
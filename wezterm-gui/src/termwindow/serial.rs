@@ -0,0 +1,120 @@
+use crate::overlay::start_overlay_pane;
+use crate::termwindow::TermWindow;
+use config::keyassignment::SerialPortAction;
+use mux::pane::Pane;
+use mux::{Mux, MuxNotification};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+impl TermWindow {
+    /// Applies `action` to the serial port of the active pane.
+    /// The overlay of the pane, if any, is ignored so that the
+    /// port can be controlled while the hex view is shown.
+    pub fn perform_serial_port_action(&mut self, action: &SerialPortAction) {
+        let pane = match self.get_active_pane_no_overlay() {
+            Some(pane) => pane,
+            None => return,
+        };
+        if let Err(err) = self.serial_port_action(&pane, action) {
+            log::error!("{err:#}");
+        }
+    }
+
+    fn serial_port_action(
+        &mut self,
+        pane: &Arc<dyn Pane>,
+        action: &SerialPortAction,
+    ) -> anyhow::Result<()> {
+        let control = mux::serial::serial_control_for_pane(&**pane)?;
+        match action {
+            SerialPortAction::Configure(settings) => {
+                mux::serial::apply_line_settings(&control, settings)?;
+                log::info!("serial port settings are now {}", control.settings()?);
+            }
+            SerialPortAction::ToggleDtr => control.set_dtr(!control.dtr())?,
+            SerialPortAction::ToggleRts => control.set_rts(!control.rts())?,
+            SerialPortAction::SendBreak(duration) => {
+                let duration = Duration::from_millis(*duration);
+                std::thread::spawn(move || {
+                    if let Err(err) = control.send_break(duration) {
+                        log::error!("sending break: {err:#}");
+                    }
+                });
+            }
+            SerialPortAction::ToggleLogging => {
+                let pane_id = pane.pane_id();
+                match mux::serial::stop_logging(pane_id) {
+                    Some(path) => log::info!("stopped logging to {}", path.display()),
+                    None => {
+                        let path = mux::serial::default_log_path(&**pane);
+                        mux::serial::start_logging(pane_id, &path)?;
+                        log::info!("logging serial data to {}", path.display());
+                    }
+                }
+            }
+            SerialPortAction::ToggleHexView => self.toggle_serial_hex_view(pane),
+        }
+        Ok(())
+    }
+
+    fn toggle_serial_hex_view(&mut self, pane: &Arc<dyn Pane>) {
+        let pane_id = pane.pane_id();
+        let (overlay, hex_view) = {
+            let state = self.pane_state(pane_id);
+            (
+                state.overlay.as_ref().map(|overlay| overlay.pane.pane_id()),
+                state.hex_view,
+            )
+        };
+        match overlay {
+            Some(overlay) if Some(overlay) == hex_view => {
+                self.cancel_overlay_for_pane(pane_id);
+                return;
+            }
+            Some(_) => {
+                // Don't replace copy mode or whatever else is shown
+                log::warn!("the serial hex view can't be shown over another overlay");
+                return;
+            }
+            None => {}
+        }
+
+        let (tx, rx) = smol::channel::unbounded();
+        let closed = Arc::new(AtomicBool::new(false));
+
+        let mux = Mux::get();
+        mux.add_output_tap(pane_id);
+        {
+            let closed = Arc::clone(&closed);
+            mux.subscribe(move |n| {
+                if closed.load(Ordering::Relaxed) {
+                    return false;
+                }
+                match n {
+                    MuxNotification::PaneRawOutput { pane_id: id, data } if id == pane_id => {
+                        tx.try_send(data).is_ok()
+                    }
+                    _ => true,
+                }
+            });
+        }
+
+        let target = Arc::clone(pane);
+        let (overlay, future) = start_overlay_pane(self, pane, move |_pane_id, term| {
+            crate::overlay::hexdump::show_hex_view(term, target, rx)
+        });
+        let overlay_id = overlay.pane_id();
+        self.assign_overlay_for_pane(pane_id, overlay);
+        self.pane_state(pane_id).hex_view = Some(overlay_id);
+
+        promise::spawn::spawn(async move {
+            if let Err(err) = future.await {
+                log::error!("serial hex view: {err:#}");
+            }
+            closed.store(true, Ordering::Relaxed);
+            Mux::get().remove_output_tap(pane_id);
+        })
+        .detach();
+    }
+}
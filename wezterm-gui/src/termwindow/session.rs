@@ -0,0 +1,28 @@
+use crate::TermWindow;
+use mux::session_state::{self, DEFAULT_SESSION};
+
+impl TermWindow {
+    /// Saves the layout of the mux as the default session
+    pub fn save_session(&mut self) {
+        match session_state::save_session(DEFAULT_SESSION, self.config.session_save_scrollback) {
+            Ok(path) => log::info!("saved session to {}", path.display()),
+            Err(err) => log::error!("saving session: {err:#}"),
+        }
+    }
+
+    /// Re-creates the windows of the default session
+    /// alongside the existing windows
+    pub fn restore_session(&mut self) {
+        promise::spawn::spawn(async move {
+            let result = async {
+                let session = session_state::load_session(DEFAULT_SESSION)?;
+                session_state::restore_session(&session).await
+            }
+            .await;
+            if let Err(err) = result {
+                log::error!("restoring session: {err:#}");
+            }
+        })
+        .detach();
+    }
+}
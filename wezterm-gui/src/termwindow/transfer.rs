@@ -0,0 +1,78 @@
+use crate::overlay::start_overlay_pane;
+use crate::TermWindow;
+use mux::pane::Pane;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+impl TermWindow {
+    /// If the pane belongs to an ssh domain with an established session,
+    /// uploads the dropped files to its working directory on the remote
+    /// host and then pastes the remote paths into the pane.
+    /// Returns false if the pane is not associated with an ssh session,
+    /// in which case the caller should handle the drop itself.
+    pub fn upload_dropped_files(&mut self, pane: &Arc<dyn Pane>, paths: &[PathBuf]) -> bool {
+        if !self.config.upload_dropped_files_over_ssh {
+            return false;
+        }
+        let sftp = match mux::sftp::sftp_for_pane(&**pane) {
+            Some(sftp) => sftp,
+            None => return false,
+        };
+
+        let paths = paths.to_vec();
+        let target = Arc::clone(pane);
+        let (overlay, future) = start_overlay_pane(self, pane, move |_pane_id, term| {
+            crate::overlay::transfer::upload_files(term, sftp, target, paths)
+        });
+        self.assign_overlay_for_pane(pane.pane_id(), overlay);
+
+        let pane = Arc::clone(pane);
+        let quoting = self.config.quote_dropped_files;
+        promise::spawn::spawn(async move {
+            match future.await {
+                Ok(remote) => {
+                    let paths = remote
+                        .iter()
+                        .map(|path| quoting.escape(path.as_str()))
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                        + " ";
+                    if let Err(err) = pane.send_paste(&paths) {
+                        log::error!("pasting uploaded paths: {err:#}");
+                    }
+                }
+                Err(err) => log::error!("uploading dropped files: {err:#}"),
+            }
+        })
+        .detach();
+
+        true
+    }
+
+    /// Downloads the remote file at `path`, which may be relative to the
+    /// working directory of the pane, to the downloads folder.
+    pub fn download_remote_path(&mut self, pane: &Arc<dyn Pane>, path: &str) {
+        let path = path.trim();
+        if path.is_empty() {
+            return;
+        }
+        let sftp = match mux::sftp::sftp_for_pane(&**pane) {
+            Some(sftp) => sftp,
+            None => {
+                log::error!(
+                    "cannot download {path}: pane {} is not part of an ssh domain",
+                    pane.pane_id()
+                );
+                return;
+            }
+        };
+
+        let path = path.to_string();
+        let target = Arc::clone(pane);
+        let (overlay, future) = start_overlay_pane(self, pane, move |_pane_id, term| {
+            crate::overlay::transfer::download_file(term, sftp, target, path)
+        });
+        self.assign_overlay_for_pane(pane.pane_id(), overlay);
+        promise::spawn::spawn(future).detach();
+    }
+}
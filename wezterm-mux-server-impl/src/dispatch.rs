@@ -2,6 +2,7 @@ use crate::sessionhandler::{PduSender, SessionHandler};
 use anyhow::Context;
 use async_ossl::AsyncSslStream;
 use codec::{DecodedPdu, Pdu};
+use config::ClientAccess;
 use futures::FutureExt;
 use mux::{Mux, MuxNotification};
 use smol::prelude::*;
@@ -23,7 +24,7 @@ enum Item {
     Readable,
 }
 
-pub async fn process<T>(stream: T) -> anyhow::Result<()>
+pub async fn process<T>(stream: T, access: ClientAccess) -> anyhow::Result<()>
 where
     T: 'static,
     T: std::io::Read,
@@ -33,10 +34,10 @@ where
     T: async_io::IoSafe,
 {
     let stream = smol::Async::new(stream)?;
-    process_async(stream).await
+    process_async(stream, access).await
 }
 
-pub async fn process_async<T>(mut stream: Async<T>) -> anyhow::Result<()>
+pub async fn process_async<T>(mut stream: Async<T>, access: ClientAccess) -> anyhow::Result<()>
 where
     T: 'static,
     T: std::io::Read,
@@ -56,7 +57,7 @@ where
                 .map_err(|e| anyhow::anyhow!("{:?}", e))
         }
     });
-    let mut handler = SessionHandler::new(pdu_sender);
+    let mut handler = SessionHandler::new(pdu_sender, access);
 
     {
         let mux = Mux::get();
@@ -110,15 +111,28 @@ where
             }
             Ok(Item::Notif(MuxNotification::PaneOutput(pane_id))) => {
                 handler.schedule_pane_push(pane_id);
+                handler.schedule_pane_output_push(pane_id);
             }
+            Ok(Item::Notif(MuxNotification::PaneRawOutput { pane_id, data })) => {
+                handler.push_raw_pane_output(pane_id, &data);
+            }
+            Ok(Item::Notif(MuxNotification::PaneRawInput { .. })) => {}
+            Ok(Item::Notif(MuxNotification::PaneRecordingChanged(_))) => {}
             Ok(Item::Notif(MuxNotification::PaneAdded(_pane_id))) => {}
             Ok(Item::Notif(MuxNotification::PaneRemoved(pane_id))) => {
+                if !handler.knew_removed_pane(pane_id) {
+                    continue;
+                }
                 Pdu::PaneRemoved(codec::PaneRemoved { pane_id })
                     .encode_async(&mut stream, 0)
                     .await?;
                 stream.flush().await.context("flushing PDU to client")?;
+                handler.pane_output_closed(pane_id);
             }
             Ok(Item::Notif(MuxNotification::Alert { pane_id, alert })) => {
+                if !handler.can_see_pane(pane_id) {
+                    continue;
+                }
                 {
                     let per_pane = handler.per_pane(pane_id);
                     let mut per_pane = per_pane.lock().unwrap();
@@ -132,6 +146,9 @@ where
                 selection,
                 clipboard,
             })) => {
+                if !handler.can_see_pane(pane_id) {
+                    continue;
+                }
                 Pdu::SetClipboard(codec::SetClipboard {
                     pane_id,
                     clipboard,
@@ -142,6 +159,9 @@ where
                 stream.flush().await.context("flushing PDU to client")?;
             }
             Ok(Item::Notif(MuxNotification::TabAddedToWindow { tab_id, window_id })) => {
+                if !handler.can_see_window(window_id) {
+                    continue;
+                }
                 Pdu::TabAddedToWindow(codec::TabAddedToWindow { tab_id, window_id })
                     .encode_async(&mut stream, 0)
                     .await?;
@@ -156,7 +176,7 @@ where
                     mux.get_window(window_id)
                         .map(|w| w.get_workspace().to_string())
                 };
-                if let Some(workspace) = workspace {
+                if let Some(workspace) = workspace.filter(|w| handler.can_see_workspace(w)) {
                     Pdu::WindowWorkspaceChanged(codec::WindowWorkspaceChanged {
                         window_id,
                         workspace,
@@ -167,24 +187,36 @@ where
                 }
             }
             Ok(Item::Notif(MuxNotification::PaneFocused(pane_id))) => {
+                if !handler.can_see_pane(pane_id) {
+                    continue;
+                }
                 Pdu::PaneFocused(codec::PaneFocused { pane_id })
                     .encode_async(&mut stream, 0)
                     .await?;
                 stream.flush().await.context("flushing PDU to client")?;
             }
             Ok(Item::Notif(MuxNotification::TabResized(tab_id))) => {
+                if !handler.can_see_tab(tab_id) {
+                    continue;
+                }
                 Pdu::TabResized(codec::TabResized { tab_id })
                     .encode_async(&mut stream, 0)
                     .await?;
                 stream.flush().await.context("flushing PDU to client")?;
             }
             Ok(Item::Notif(MuxNotification::TabTitleChanged { tab_id, title })) => {
+                if !handler.can_see_tab(tab_id) {
+                    continue;
+                }
                 Pdu::TabTitleChanged(codec::TabTitleChanged { tab_id, title })
                     .encode_async(&mut stream, 0)
                     .await?;
                 stream.flush().await.context("flushing PDU to client")?;
             }
             Ok(Item::Notif(MuxNotification::WindowTitleChanged { window_id, title })) => {
+                if !handler.can_see_window(window_id) {
+                    continue;
+                }
                 Pdu::WindowTitleChanged(codec::WindowTitleChanged { window_id, title })
                     .encode_async(&mut stream, 0)
                     .await?;
@@ -194,6 +226,11 @@ where
                 old_workspace,
                 new_workspace,
             })) => {
+                if !handler.can_see_workspace(&old_workspace)
+                    && !handler.can_see_workspace(&new_workspace)
+                {
+                    continue;
+                }
                 Pdu::RenameWorkspace(codec::RenameWorkspace {
                     old_workspace,
                     new_workspace,
@@ -1,28 +1,30 @@
 use anyhow::{anyhow, Context as _};
-use config::{create_user_owned_dirs, UnixDomain};
+use config::{create_user_owned_dirs, ClientAccess, UnixDomain};
 use promise::spawn::spawn_into_main_thread;
 use wezterm_uds::UnixListener;
 
 pub struct LocalListener {
     listener: UnixListener,
+    access: ClientAccess,
 }
 
 impl LocalListener {
-    pub fn new(listener: UnixListener) -> Self {
-        Self { listener }
+    pub fn new(listener: UnixListener, access: ClientAccess) -> Self {
+        Self { listener, access }
     }
 
     pub fn with_domain(unix_dom: &UnixDomain) -> anyhow::Result<Self> {
         let listener = safely_create_sock_path(unix_dom)?;
-        Ok(Self::new(listener))
+        Ok(Self::new(listener, unix_dom.client_access.clone()))
     }
 
     pub fn run(&mut self) {
         for stream in self.listener.incoming() {
             match stream {
                 Ok(stream) => {
+                    let access = self.access.clone();
                     spawn_into_main_thread(async move {
-                        crate::dispatch::process(stream).await.map_err(|e| {
+                        crate::dispatch::process(stream, access).await.map_err(|e| {
                             log::error!("{:#}", e);
                             e
                         })
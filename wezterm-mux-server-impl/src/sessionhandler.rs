@@ -1,15 +1,17 @@
 use crate::PKI;
 use anyhow::{anyhow, Context};
 use codec::*;
-use config::TermConfig;
-use mux::client::ClientId;
+use config::{ClientAccess, TermConfig};
+use mux::client::{ClientId, ClientInfo};
 use mux::domain::SplitSource;
-use mux::pane::{CachePolicy, Pane, PaneId};
-use mux::renderable::{RenderableDimensions, StableCursorPosition};
+use mux::pane::{get_text_from_semantic_zone, resolve_semantic_zone, CachePolicy, Pane, PaneId};
+use mux::renderable::{lines_to_text, RenderableDimensions, StableCursorPosition};
+use mux::ssh::SshForward;
 use mux::tab::TabId;
+use mux::window::WindowId;
 use mux::{Mux, MuxNotification};
 use promise::spawn::spawn_into_main_thread;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, Mutex};
 use std::time::Instant;
 use termwiz::surface::SequenceNo;
@@ -196,15 +198,156 @@ fn maybe_push_pane_changes(
     Ok(())
 }
 
+/// Tracks a client subscription to the output of a pane
+pub(crate) struct OutputSubscription {
+    format: PaneOutputFormat,
+    /// The next row to be reported by a text subscription
+    next_row: StableRowIndex,
+}
+
+fn maybe_push_pane_output(
+    pane: &Arc<dyn Pane>,
+    sender: PduSender,
+    subscription: Arc<Mutex<OutputSubscription>>,
+) -> anyhow::Result<()> {
+    let mut subscription = subscription.lock().unwrap();
+    if subscription.format == PaneOutputFormat::Raw || pane.is_alt_screen_active() {
+        return Ok(());
+    }
+
+    let dims = pane.get_dimensions();
+    let cursor = pane.get_cursor_position();
+
+    // Rows that have already left the scrollback cannot be reported
+    subscription.next_row = subscription.next_row.max(dims.scrollback_top);
+    // If the cursor moved back up, eg: the screen was cleared, then
+    // the rows will be reported again as they are re-written
+    if cursor.y < subscription.next_row {
+        subscription.next_row = cursor.y;
+    }
+    // Rows above the cursor are considered to be complete
+    if cursor.y == subscription.next_row {
+        return Ok(());
+    }
+
+    let (first_row, lines) = pane.get_lines(subscription.next_row..cursor.y);
+    let (text, consumed) = lines_to_text(
+        &lines,
+        subscription.format == PaneOutputFormat::SemanticOutput,
+    );
+    subscription.next_row = first_row + consumed as StableRowIndex;
+
+    if !text.is_empty() {
+        sender.send(DecodedPdu {
+            pdu: Pdu::PaneOutput(PaneOutput {
+                pane_id: pane.pane_id(),
+                data: PaneOutputData::Lines(text),
+            }),
+            serial: 0,
+        })?;
+    }
+    Ok(())
+}
+
 pub struct SessionHandler {
     to_write_tx: PduSender,
     per_pane: HashMap<TabId, Arc<Mutex<PerPane>>>,
+    output_subscriptions: HashMap<PaneId, Arc<Mutex<OutputSubscription>>>,
     client_id: Option<Arc<ClientId>>,
     proxy_client_id: Option<ClientId>,
+    access: ClientAccess,
+}
+
+/// Returns true if a client with the specified access is permitted
+/// to see something that lives in the workspace produced by `workspace`.
+/// `workspace` is only evaluated for clients with a restricted view,
+/// and returns None when the thing is no longer part of a window.
+fn workspace_is_visible<F>(access: &ClientAccess, workspace: F) -> bool
+where
+    F: FnOnce() -> Option<String>,
+{
+    match access {
+        ClientAccess::Full | ClientAccess::ReadOnly => true,
+        ClientAccess::ViewWorkspaces(_) => workspace()
+            .map(|workspace| access.can_view_workspace(&workspace))
+            .unwrap_or(false),
+    }
+}
+
+fn window_workspace(window_id: WindowId) -> Option<String> {
+    let mux = Mux::get();
+    let window = mux.get_window(window_id)?;
+    Some(window.get_workspace().to_string())
+}
+
+/// Returns true if a client with the specified access
+/// is permitted to see the pane
+fn pane_is_visible(access: &ClientAccess, pane_id: PaneId) -> bool {
+    workspace_is_visible(access, || {
+        let (_domain_id, window_id, _tab_id) = Mux::get().resolve_pane_id(pane_id)?;
+        window_workspace(window_id)
+    })
+}
+
+/// Returns true if a client with the specified access
+/// is permitted to see the tab
+fn tab_is_visible(access: &ClientAccess, tab_id: TabId) -> bool {
+    workspace_is_visible(access, || {
+        let window_id = Mux::get().window_containing_tab(tab_id)?;
+        window_workspace(window_id)
+    })
+}
+
+/// Returns true if a client with the specified access
+/// is permitted to see the window
+fn window_is_visible(access: &ClientAccess, window_id: WindowId) -> bool {
+    workspace_is_visible(access, || window_workspace(window_id))
+}
+
+/// Removes the clients that are not looking at something that
+/// a client with the specified access is permitted to see
+fn filter_clients<F>(
+    access: &ClientAccess,
+    clients: Vec<ClientInfo>,
+    pane_workspace: F,
+) -> Vec<ClientInfo>
+where
+    F: Fn(PaneId) -> Option<String>,
+{
+    clients
+        .into_iter()
+        .filter(|info| {
+            workspace_is_visible(access, || {
+                info.active_workspace
+                    .clone()
+                    .or_else(|| info.focused_pane_id.and_then(&pane_workspace))
+            })
+        })
+        .collect()
+}
+
+/// Removes the port forwards belonging to ssh domains that have no
+/// panes that a client with the specified access is permitted to see
+fn filter_ssh_forwards(
+    access: &ClientAccess,
+    forwards: Vec<SshForward>,
+    visible_domains: &HashSet<String>,
+) -> Vec<SshForward> {
+    match access {
+        ClientAccess::Full | ClientAccess::ReadOnly => forwards,
+        ClientAccess::ViewWorkspaces(_) => forwards
+            .into_iter()
+            .filter(|forward| visible_domains.contains(&forward.domain))
+            .collect(),
+    }
 }
 
 impl Drop for SessionHandler {
     fn drop(&mut self) {
+        let pane_ids: Vec<PaneId> = self.output_subscriptions.keys().cloned().collect();
+        for pane_id in pane_ids {
+            self.remove_output_subscription(pane_id);
+        }
         if let Some(client_id) = self.client_id.take() {
             let mux = Mux::get();
             mux.unregister_client(&client_id);
@@ -213,12 +356,14 @@ impl Drop for SessionHandler {
 }
 
 impl SessionHandler {
-    pub fn new(to_write_tx: PduSender) -> Self {
+    pub fn new(to_write_tx: PduSender, access: ClientAccess) -> Self {
         Self {
             to_write_tx,
             per_pane: HashMap::new(),
+            output_subscriptions: HashMap::new(),
             client_id: None,
             proxy_client_id: None,
+            access,
         }
     }
 
@@ -230,7 +375,43 @@ impl SessionHandler {
         )
     }
 
+    /// Returns true if this client is permitted to see the pane
+    pub fn can_see_pane(&self, pane_id: PaneId) -> bool {
+        pane_is_visible(&self.access, pane_id)
+    }
+
+    /// Returns true if this client is permitted to see the tab
+    pub fn can_see_tab(&self, tab_id: TabId) -> bool {
+        tab_is_visible(&self.access, tab_id)
+    }
+
+    /// Returns true if this client is permitted to see the window
+    pub fn can_see_window(&self, window_id: WindowId) -> bool {
+        window_is_visible(&self.access, window_id)
+    }
+
+    /// Returns true if this client is permitted to see the workspace
+    pub fn can_see_workspace(&self, workspace: &str) -> bool {
+        self.access.can_view_workspace(workspace)
+    }
+
+    /// Returns true if this client was permitted to see a pane that
+    /// has since been removed from the mux, and which can therefore
+    /// no longer be resolved to a workspace
+    pub fn knew_removed_pane(&self, pane_id: PaneId) -> bool {
+        match self.access {
+            ClientAccess::Full | ClientAccess::ReadOnly => true,
+            ClientAccess::ViewWorkspaces(_) => {
+                self.per_pane.contains_key(&pane_id)
+                    || self.output_subscriptions.contains_key(&pane_id)
+            }
+        }
+    }
+
     pub fn schedule_pane_push(&mut self, pane_id: PaneId) {
+        if !self.can_see_pane(pane_id) {
+            return;
+        }
         let sender = self.to_write_tx.clone();
         let per_pane = self.per_pane(pane_id);
         spawn_into_main_thread(async move {
@@ -244,6 +425,75 @@ impl SessionHandler {
         .detach();
     }
 
+    pub fn schedule_pane_output_push(&mut self, pane_id: PaneId) {
+        if !self.can_see_pane(pane_id) {
+            return;
+        }
+        let subscription = match self.output_subscriptions.get(&pane_id) {
+            Some(subscription) => Arc::clone(subscription),
+            None => return,
+        };
+        let sender = self.to_write_tx.clone();
+        spawn_into_main_thread(async move {
+            let mux = Mux::get();
+            let pane = mux
+                .get_pane(pane_id)
+                .ok_or_else(|| anyhow!("no such pane {}", pane_id))?;
+            maybe_push_pane_output(&pane, sender, subscription)?;
+            Ok::<(), anyhow::Error>(())
+        })
+        .detach();
+    }
+
+    pub fn push_raw_pane_output(&mut self, pane_id: PaneId, data: &[u8]) {
+        if !self.can_see_pane(pane_id) {
+            return;
+        }
+        if let Some(subscription) = self.output_subscriptions.get(&pane_id) {
+            if subscription.lock().unwrap().format == PaneOutputFormat::Raw {
+                self.to_write_tx
+                    .send(DecodedPdu {
+                        pdu: Pdu::PaneOutput(PaneOutput {
+                            pane_id,
+                            data: PaneOutputData::Raw(data.to_vec()),
+                        }),
+                        serial: 0,
+                    })
+                    .ok();
+            }
+        }
+    }
+
+    /// Lets any subscriber to the output of the pane know that
+    /// there won't be any more of it
+    pub fn pane_output_closed(&mut self, pane_id: PaneId) {
+        if self.remove_output_subscription(pane_id) {
+            self.to_write_tx
+                .send(DecodedPdu {
+                    pdu: Pdu::PaneOutput(PaneOutput {
+                        pane_id,
+                        data: PaneOutputData::Closed,
+                    }),
+                    serial: 0,
+                })
+                .ok();
+        }
+    }
+
+    fn remove_output_subscription(&mut self, pane_id: PaneId) -> bool {
+        match self.output_subscriptions.remove(&pane_id) {
+            Some(subscription) => {
+                if subscription.lock().unwrap().format == PaneOutputFormat::Raw {
+                    if let Some(mux) = Mux::try_get() {
+                        mux.remove_output_tap(pane_id);
+                    }
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
     pub fn process_one(&mut self, decoded: DecodedPdu) {
         let start = Instant::now();
         let sender = self.to_write_tx.clone();
@@ -266,6 +516,20 @@ impl SessionHandler {
             sender.send(DecodedPdu { pdu, serial }).ok();
         };
 
+        if !self.access.can_write() && decoded.pdu.requires_full_access() {
+            send_response(Err(anyhow!(
+                "this client has {} access to the mux",
+                self.access
+            )));
+            return;
+        }
+        if let Some(pane_id) = decoded.pdu.viewed_pane_id() {
+            if !pane_is_visible(&self.access, pane_id) {
+                send_response(Err(anyhow!("pane {pane_id} not found")));
+                return;
+            }
+        }
+
         fn catch<F, SND>(f: F, send_response: SND)
         where
             F: FnOnce() -> anyhow::Result<Pdu>,
@@ -320,14 +584,23 @@ impl SessionHandler {
 
                     let client_id = Arc::new(client_id);
                     self.client_id.replace(client_id.clone());
+                    let access = self.access.clone();
                     spawn_into_main_thread(async move {
                         let mux = Mux::get();
-                        mux.register_client(client_id);
+                        mux.register_client(client_id, access);
                     })
                     .detach();
                 }
                 send_response(Ok(Pdu::UnitResponse(UnitResponse {})))
             }
+            Pdu::SetFocusedPane(SetFocusedPane { pane_id }) if !self.access.can_write() => {
+                // Note what the client is looking at for the benefit of
+                // `wezterm cli list-clients`, but leave the focus alone
+                if let Some(client_id) = &self.client_id {
+                    Mux::get().record_view_for_client(client_id, pane_id);
+                }
+                send_response(Ok(Pdu::UnitResponse(UnitResponse {})))
+            }
             Pdu::SetFocusedPane(SetFocusedPane { pane_id }) => {
                 let client_id = self.client_id.clone();
                 spawn_into_main_thread(async move {
@@ -371,11 +644,16 @@ impl SessionHandler {
                 .detach();
             }
             Pdu::GetClientList(GetClientList) => {
+                let access = self.access.clone();
                 spawn_into_main_thread(async move {
                     catch(
                         move || {
                             let mux = Mux::get();
-                            let clients = mux.iter_clients();
+                            let clients = filter_clients(&access, mux.iter_clients(), |pane_id| {
+                                let (_domain_id, window_id, _tab_id) =
+                                    mux.resolve_pane_id(pane_id)?;
+                                window_workspace(window_id)
+                            });
                             Ok(Pdu::GetClientListResponse(GetClientListResponse {
                                 clients,
                             }))
@@ -386,6 +664,7 @@ impl SessionHandler {
                 .detach();
             }
             Pdu::ListPanes(ListPanes {}) => {
+                let access = self.access.clone();
                 spawn_into_main_thread(async move {
                     catch(
                         move || {
@@ -395,6 +674,9 @@ impl SessionHandler {
                             let mut window_titles = HashMap::new();
                             for window_id in mux.iter_windows().into_iter() {
                                 let window = mux.get_window(window_id).unwrap();
+                                if !access.can_view_workspace(window.get_workspace()) {
+                                    continue;
+                                }
                                 window_titles.insert(window_id, window.get_title().to_string());
                                 for tab in window.iter() {
                                     tabs.push(tab.codec_pane_tree());
@@ -610,6 +892,273 @@ impl SessionHandler {
                 .detach();
             }
 
+            Pdu::GetCommandBlocks(GetCommandBlocks { pane_id }) => {
+                spawn_into_main_thread(async move {
+                    catch(
+                        move || {
+                            let mux = Mux::get();
+                            let pane = mux
+                                .get_pane(pane_id)
+                                .ok_or_else(|| anyhow!("no such pane {}", pane_id))?;
+                            let blocks = pane.get_command_blocks()?;
+                            Ok(Pdu::GetCommandBlocksResponse(GetCommandBlocksResponse {
+                                pane_id,
+                                blocks,
+                            }))
+                        },
+                        send_response,
+                    )
+                })
+                .detach();
+            }
+
+            Pdu::GetSemanticZones(GetSemanticZones { pane_id }) => {
+                spawn_into_main_thread(async move {
+                    catch(
+                        move || {
+                            let mux = Mux::get();
+                            let pane = mux
+                                .get_pane(pane_id)
+                                .ok_or_else(|| anyhow!("no such pane {}", pane_id))?;
+                            let zones = pane.get_semantic_zones()?;
+                            Ok(Pdu::GetSemanticZonesResponse(GetSemanticZonesResponse {
+                                pane_id,
+                                zones,
+                            }))
+                        },
+                        send_response,
+                    )
+                })
+                .detach();
+            }
+
+            Pdu::GetSemanticZoneText(GetSemanticZoneText {
+                pane_id,
+                semantic_type,
+                index,
+            }) => {
+                spawn_into_main_thread(async move {
+                    catch(
+                        move || {
+                            let mux = Mux::get();
+                            let pane = mux
+                                .get_pane(pane_id)
+                                .ok_or_else(|| anyhow!("no such pane {}", pane_id))?;
+                            let zone = resolve_semantic_zone(
+                                pane.get_semantic_zones()?,
+                                semantic_type,
+                                index,
+                            )
+                            .with_context(|| format!("pane {}", pane_id))?;
+                            let text = get_text_from_semantic_zone(&*pane, &zone);
+                            Ok(Pdu::GetSemanticZoneTextResponse(
+                                GetSemanticZoneTextResponse {
+                                    pane_id,
+                                    zone,
+                                    text,
+                                },
+                            ))
+                        },
+                        send_response,
+                    )
+                })
+                .detach();
+            }
+
+            Pdu::ListSshForwards(ListSshForwards { domain }) => {
+                let access = self.access.clone();
+                spawn_into_main_thread(async move {
+                    let visible_domains: HashSet<String> = {
+                        let mux = Mux::get();
+                        mux.iter_panes()
+                            .into_iter()
+                            .filter(|pane| pane_is_visible(&access, pane.pane_id()))
+                            .filter_map(|pane| mux.get_domain(pane.domain_id()))
+                            .map(|domain| domain.domain_name().to_string())
+                            .collect()
+                    };
+                    promise::spawn::spawn(async move {
+                        let result =
+                            mux::ssh::list_ssh_forwards(domain.as_deref())
+                                .await
+                                .map(|forwards| {
+                                    Pdu::ListSshForwardsResponse(ListSshForwardsResponse {
+                                        forwards: filter_ssh_forwards(
+                                            &access,
+                                            forwards,
+                                            &visible_domains,
+                                        ),
+                                    })
+                                });
+                        send_response(result);
+                    })
+                    .detach();
+                })
+                .detach();
+            }
+
+            Pdu::AddSshForward(AddSshForward { domain, kind, spec }) => {
+                spawn_into_main_thread(async move {
+                    promise::spawn::spawn(async move {
+                        let result =
+                            mux::ssh::add_ssh_forward(&domain, &kind, &spec)
+                                .await
+                                .map(|forward| {
+                                    Pdu::AddSshForwardResponse(AddSshForwardResponse { forward })
+                                });
+                        send_response(result);
+                    })
+                    .detach();
+                })
+                .detach();
+            }
+
+            Pdu::RemoveSshForward(RemoveSshForward { domain, id }) => {
+                spawn_into_main_thread(async move {
+                    promise::spawn::spawn(async move {
+                        let result = mux::ssh::remove_ssh_forward(&domain, id)
+                            .await
+                            .map(|()| Pdu::UnitResponse(UnitResponse {}));
+                        send_response(result);
+                    })
+                    .detach();
+                })
+                .detach();
+            }
+
+            Pdu::SaveSession(SaveSession { name, scrollback }) => {
+                spawn_into_main_thread(async move {
+                    catch(
+                        move || {
+                            let name = name
+                                .as_deref()
+                                .unwrap_or(mux::session_state::DEFAULT_SESSION);
+                            let scrollback = scrollback
+                                .unwrap_or_else(|| config::configuration().session_save_scrollback);
+                            let path = mux::session_state::save_session(name, scrollback)?;
+                            Ok(Pdu::SaveSessionResponse(SaveSessionResponse {
+                                path: path.display().to_string(),
+                            }))
+                        },
+                        send_response,
+                    )
+                })
+                .detach();
+            }
+
+            Pdu::RestoreSession(RestoreSession { name }) => {
+                spawn_into_main_thread(async move {
+                    promise::spawn::spawn(async move {
+                        let result: anyhow::Result<Pdu> = async {
+                            let name = name
+                                .as_deref()
+                                .unwrap_or(mux::session_state::DEFAULT_SESSION);
+                            let session = mux::session_state::load_session(name)?;
+                            let window_ids = mux::session_state::restore_session(&session).await?;
+                            Ok(Pdu::RestoreSessionResponse(RestoreSessionResponse {
+                                window_ids,
+                            }))
+                        }
+                        .await;
+                        send_response(result);
+                    })
+                    .detach();
+                })
+                .detach();
+            }
+
+            Pdu::ListSessions(ListSessions {}) => {
+                let access = self.access.clone();
+                catch(
+                    move || {
+                        // Saved sessions capture every workspace, so they
+                        // are only listed for clients that can see them all
+                        let names = match access {
+                            ClientAccess::Full | ClientAccess::ReadOnly => {
+                                mux::session_state::list_sessions()?
+                            }
+                            ClientAccess::ViewWorkspaces(_) => vec![],
+                        };
+                        Ok(Pdu::ListSessionsResponse(ListSessionsResponse { names }))
+                    },
+                    send_response,
+                );
+            }
+
+            Pdu::SetPaneRecording(SetPaneRecording { pane_id, start }) => {
+                spawn_into_main_thread(async move {
+                    promise::spawn::spawn(async move {
+                        let result: anyhow::Result<Pdu> = async {
+                            let mux = Mux::get();
+                            let pane = mux
+                                .get_pane(pane_id)
+                                .ok_or_else(|| anyhow!("no such pane {}", pane_id))?;
+                            let path = match start {
+                                Some(options) => Some(pane.start_recording(options).await?),
+                                None => pane.stop_recording().await?,
+                            };
+                            Ok(Pdu::SetPaneRecordingResponse(SetPaneRecordingResponse {
+                                path,
+                            }))
+                        }
+                        .await;
+                        send_response(result);
+                    })
+                    .detach();
+                })
+                .detach();
+            }
+
+            Pdu::SubscribePaneOutput(SubscribePaneOutput {
+                pane_id,
+                format,
+                start_row,
+            }) => {
+                self.remove_output_subscription(pane_id);
+                let subscription = Arc::new(Mutex::new(OutputSubscription {
+                    format,
+                    next_row: 0,
+                }));
+                self.output_subscriptions
+                    .insert(pane_id, Arc::clone(&subscription));
+                if format == PaneOutputFormat::Raw {
+                    Mux::get().add_output_tap(pane_id);
+                }
+
+                let sender = self.to_write_tx.clone();
+                spawn_into_main_thread(async move {
+                    catch(
+                        move || {
+                            let mux = Mux::get();
+                            let pane = mux
+                                .get_pane(pane_id)
+                                .ok_or_else(|| anyhow!("no such pane {}", pane_id))?;
+                            let start_row = {
+                                let mut sub = subscription.lock().unwrap();
+                                let dims = pane.get_dimensions();
+                                sub.next_row = start_row
+                                    .unwrap_or_else(|| pane.get_cursor_position().y)
+                                    .max(dims.scrollback_top);
+                                sub.next_row
+                            };
+                            // Report anything between the requested start
+                            // row and the cursor right away
+                            maybe_push_pane_output(&pane, sender, subscription)?;
+                            Ok(Pdu::SubscribePaneOutputResponse(
+                                SubscribePaneOutputResponse { pane_id, start_row },
+                            ))
+                        },
+                        send_response,
+                    )
+                })
+                .detach();
+            }
+
+            Pdu::UnsubscribePaneOutput(UnsubscribePaneOutput { pane_id }) => {
+                self.remove_output_subscription(pane_id);
+                send_response(Ok(Pdu::UnitResponse(UnitResponse {})))
+            }
+
             Pdu::ActivatePaneDirection(ActivatePaneDirection { pane_id, direction }) => {
                 spawn_into_main_thread(async move {
                     catch(
@@ -634,6 +1183,7 @@ impl SessionHandler {
                 containing_tab_id,
                 pane_id,
                 size,
+                cell_size,
             }) => {
                 spawn_into_main_thread(async move {
                     catch(
@@ -642,10 +1192,13 @@ impl SessionHandler {
                             let pane = mux
                                 .get_pane(pane_id)
                                 .ok_or_else(|| anyhow!("no such pane {}", pane_id))?;
-                            pane.resize(size)?;
                             let tab = mux
                                 .get_tab(containing_tab_id)
                                 .ok_or_else(|| anyhow!("no such tab {}", containing_tab_id))?;
+                            // Record the pane's cell size alongside its size
+                            // in the tab, so that the splits are rebuilt from
+                            // the space that the pane occupies in the tab
+                            tab.resize_pane_in_tab(&pane, size, cell_size)?;
                             tab.rebuild_splits_sizes_from_contained_panes();
                             Ok(Pdu::UnitResponse(UnitResponse {}))
                         },
@@ -757,6 +1310,25 @@ impl SessionHandler {
                 .detach();
             }
 
+            Pdu::GetPanePalette(GetPanePalette { pane_id }) => {
+                spawn_into_main_thread(async move {
+                    catch(
+                        move || {
+                            let mux = Mux::get();
+                            let pane = mux
+                                .get_pane(pane_id)
+                                .ok_or_else(|| anyhow!("no such pane {}", pane_id))?;
+                            let palette = pane.palette();
+                            Ok(Pdu::GetPanePaletteResponse(GetPanePaletteResponse {
+                                palette,
+                            }))
+                        },
+                        send_response,
+                    )
+                })
+                .detach();
+            }
+
             Pdu::GetPaneRenderChanges(GetPaneRenderChanges { pane_id, .. }) => {
                 let sender = self.to_write_tx.clone();
                 let per_pane = self.per_pane(pane_id);
@@ -997,6 +1569,18 @@ impl SessionHandler {
             | Pdu::UnitResponse { .. }
             | Pdu::LivenessResponse { .. }
             | Pdu::GetPaneDirectionResponse { .. }
+            | Pdu::GetCommandBlocksResponse { .. }
+            | Pdu::SubscribePaneOutputResponse { .. }
+            | Pdu::GetSemanticZonesResponse { .. }
+            | Pdu::GetSemanticZoneTextResponse { .. }
+            | Pdu::ListSshForwardsResponse { .. }
+            | Pdu::AddSshForwardResponse { .. }
+            | Pdu::SaveSessionResponse { .. }
+            | Pdu::RestoreSessionResponse { .. }
+            | Pdu::ListSessionsResponse { .. }
+            | Pdu::SetPaneRecordingResponse { .. }
+            | Pdu::GetPanePaletteResponse { .. }
+            | Pdu::PaneOutput { .. }
             | Pdu::SearchScrollbackResponse { .. }
             | Pdu::GetLinesResponse { .. }
             | Pdu::GetCodecVersionResponse { .. }
@@ -1054,6 +1638,7 @@ async fn split_pane(split: SplitPane, client_id: Option<Arc<ClientId>>) -> anyho
         SplitSource::Spawn {
             command: split.command,
             command_dir: split.command_dir,
+            initial_output: None,
         }
     };
 
@@ -1079,6 +1664,7 @@ async fn domain_spawn_v2(spawn: SpawnV2, client_id: Option<Arc<ClientId>>) -> an
             spawn.domain,
             spawn.command,
             spawn.command_dir,
+            None,
             spawn.size,
             None, // optional current pane_id
             spawn.workspace,
@@ -1125,3 +1711,107 @@ async fn move_pane(
         window_id,
     }))
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn view(workspaces: &[&str]) -> ClientAccess {
+        ClientAccess::ViewWorkspaces(workspaces.iter().map(|w| w.to_string()).collect())
+    }
+
+    #[test]
+    fn restricted_clients_are_denied_changes() {
+        let writes = [
+            Pdu::WriteToPane(WriteToPane {
+                pane_id: 1,
+                data: b"ls\n".to_vec(),
+            }),
+            Pdu::KillPane(KillPane { pane_id: 1 }),
+            Pdu::SetPaneRecording(SetPaneRecording {
+                pane_id: 1,
+                start: None,
+            }),
+            Pdu::GetTlsCreds(GetTlsCreds {}),
+        ];
+        for pdu in &writes {
+            assert!(pdu.requires_full_access(), "{pdu:?}");
+        }
+
+        let reads = [
+            Pdu::Ping(Ping {}),
+            Pdu::ListPanes(ListPanes {}),
+            Pdu::GetClientList(GetClientList),
+            Pdu::ListSessions(ListSessions {}),
+            Pdu::GetLines(GetLines {
+                pane_id: 1,
+                lines: vec![],
+            }),
+        ];
+        for pdu in &reads {
+            assert!(!pdu.requires_full_access(), "{pdu:?}");
+        }
+    }
+
+    #[test]
+    fn workspace_visibility() {
+        let lookup_not_expected = || -> Option<String> { panic!("should not resolve") };
+        assert!(workspace_is_visible(
+            &ClientAccess::Full,
+            lookup_not_expected
+        ));
+        assert!(workspace_is_visible(
+            &ClientAccess::ReadOnly,
+            lookup_not_expected
+        ));
+
+        let access = view(&["demo"]);
+        assert!(workspace_is_visible(&access, || Some("demo".to_string())));
+        assert!(!workspace_is_visible(&access, || Some(
+            "default".to_string()
+        )));
+        // Something that is no longer in a window can't be attributed
+        // to a workspace and so is hidden
+        assert!(!workspace_is_visible(&access, || None));
+    }
+
+    #[test]
+    fn client_list_is_filtered() {
+        let mut in_demo = ClientInfo::new(Arc::new(ClientId::new()), ClientAccess::Full);
+        in_demo.active_workspace = Some("demo".to_string());
+        let mut in_default = ClientInfo::new(Arc::new(ClientId::new()), ClientAccess::Full);
+        in_default.active_workspace = Some("default".to_string());
+        let mut focused_in_demo = ClientInfo::new(Arc::new(ClientId::new()), ClientAccess::Full);
+        focused_in_demo.focused_pane_id = Some(3);
+        let idle = ClientInfo::new(Arc::new(ClientId::new()), ClientAccess::Full);
+
+        let clients = vec![in_demo, in_default, focused_in_demo, idle];
+        let pane_workspace = |pane_id| (pane_id == 3).then(|| "demo".to_string());
+
+        let visible = filter_clients(&view(&["demo"]), clients.clone(), pane_workspace);
+        assert_eq!(visible, vec![clients[0].clone(), clients[2].clone()]);
+
+        let visible = filter_clients(&ClientAccess::ReadOnly, clients.clone(), pane_workspace);
+        assert_eq!(visible, clients);
+    }
+
+    #[test]
+    fn ssh_forwards_are_filtered() {
+        let forward = |domain: &str| SshForward {
+            domain: domain.to_string(),
+            id: 1,
+            kind: "Local".to_string(),
+            spec: "8080:localhost:80".to_string(),
+            bound_port: 8080,
+        };
+        let forwards = vec![forward("shown"), forward("hidden")];
+        let visible_domains: HashSet<String> = vec!["shown".to_string()].into_iter().collect();
+
+        let visible = filter_ssh_forwards(&view(&["demo"]), forwards.clone(), &visible_domains);
+        assert_eq!(visible, vec![forward("shown")]);
+
+        let visible =
+            filter_ssh_forwards(&ClientAccess::ReadOnly, forwards.clone(), &visible_domains);
+        assert_eq!(visible, forwards);
+    }
+}
@@ -1,6 +1,6 @@
 use anyhow::{anyhow, Context, Error};
 use async_ossl::AsyncSslStream;
-use config::TlsDomainServer;
+use config::{ClientAccess, TlsDomainServer};
 use openssl::ssl::{SslAcceptor, SslFiletype, SslMethod, SslStream, SslVerifyMode};
 use openssl::x509::X509;
 use promise::spawn::spawn_into_main_thread;
@@ -12,17 +12,19 @@ use wezterm_mux_server_impl::PKI;
 struct OpenSSLNetListener {
     acceptor: Arc<SslAcceptor>,
     listener: TcpListener,
+    tls_server: TlsDomainServer,
 }
 
 impl OpenSSLNetListener {
-    pub fn new(listener: TcpListener, acceptor: SslAcceptor) -> Self {
+    pub fn new(listener: TcpListener, acceptor: SslAcceptor, tls_server: TlsDomainServer) -> Self {
         Self {
             listener,
             acceptor: Arc::new(acceptor),
+            tls_server,
         }
     }
 
-    /// Authenticates the peer.
+    /// Authenticates the peer and returns what it is permitted to do.
     /// The requirements are:
     /// * The peer must have a certificate
     /// * The peer certificate must be trusted
@@ -30,8 +32,12 @@ impl OpenSSLNetListener {
     ///   either an exact match for the unix username of the
     ///   user running this mux server instance, or must match
     ///   a special encoded prefix set up by a proprietary PKI
-    ///   infrastructure in an environment used by the author.
-    fn verify_peer_cert<T>(stream: &SslStream<T>) -> anyhow::Result<()> {
+    ///   infrastructure in an environment used by the author,
+    ///   or must be listed in the `peer_access` of the server.
+    fn verify_peer_cert<T>(
+        stream: &SslStream<T>,
+        tls_server: &TlsDomainServer,
+    ) -> anyhow::Result<ClientAccess> {
         let cert = stream
             .ssl()
             .peer_certificate()
@@ -43,6 +49,11 @@ impl OpenSSLNetListener {
             .ok_or_else(|| anyhow!("cert has no CN"))?;
         let cn_str = cn.data().as_utf8()?.to_string();
 
+        if let Some(access) = tls_server.peer_access.get(&cn_str) {
+            log::info!("Peer certificate CN `{}` has {} access", cn_str, access);
+            return Ok(access.clone());
+        }
+
         let wanted_unix_name = std::env::var("USER")?;
 
         if wanted_unix_name == cn_str {
@@ -51,7 +62,7 @@ impl OpenSSLNetListener {
                 cn_str,
                 wanted_unix_name
             );
-            Ok(())
+            Ok(tls_server.client_access.clone())
         } else {
             // Some environments that are used by the author of this
             // program encode the CN in the form `user:unixname/DATA`
@@ -62,7 +73,7 @@ impl OpenSSLNetListener {
                     cn_str,
                     wanted_unix_name
                 );
-                Ok(())
+                Ok(tls_server.client_access.clone())
             } else {
                 anyhow::bail!("CN `{}` did not match $USER `{}`", cn_str, wanted_unix_name);
             }
@@ -78,15 +89,19 @@ impl OpenSSLNetListener {
 
                     match acceptor.accept(stream) {
                         Ok(stream) => {
-                            if let Err(err) = Self::verify_peer_cert(&stream) {
-                                log::error!("problem with peer cert: {}", err);
-                                break;
-                            }
+                            let access = match Self::verify_peer_cert(&stream, &self.tls_server) {
+                                Ok(access) => access,
+                                Err(err) => {
+                                    log::error!("problem with peer cert: {}", err);
+                                    break;
+                                }
+                            };
                             spawn_into_main_thread(async move {
                                 log::error!("Making new AsyncSslStream");
-                                wezterm_mux_server_impl::dispatch::process(AsyncSslStream::new(
-                                    stream,
-                                ))
+                                wezterm_mux_server_impl::dispatch::process(
+                                    AsyncSslStream::new(stream),
+                                    access,
+                                )
                                 .await
                                 .map_err(|e| {
                                     log::error!("process: {:?}", e);
@@ -180,6 +195,7 @@ pub fn spawn_tls_listener(tls_server: &TlsDomainServer) -> Result<(), Error> {
             )
         })?,
         acceptor,
+        tls_server.clone(),
     );
     std::thread::spawn(move || {
         net_listener.run();
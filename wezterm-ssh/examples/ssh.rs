@@ -7,7 +7,7 @@ use portable_pty::{Child, MasterPty, PtySize};
 use std::io::{Read, Write};
 use termwiz::cell::unicode_column_width;
 use termwiz::lineedit::*;
-use wezterm_ssh::{Config, Session, SessionEvent};
+use wezterm_ssh::{Config, ConnectPrompt, Session, SessionEvent};
 
 #[derive(Default)]
 struct PasswordPromptHost {
@@ -39,6 +39,24 @@ impl LineEditorHost for PasswordPromptHost {
     }
 }
 
+/// Prompts on stderr, using a line editor for input
+struct StderrPrompt;
+impl ConnectPrompt for StderrPrompt {
+    fn output_line(&mut self, line: &str) -> anyhow::Result<()> {
+        eprintln!("{}", line);
+        Ok(())
+    }
+
+    fn read_line(&mut self, prompt: &str, echo: bool) -> anyhow::Result<Option<String>> {
+        let mut terminal = line_editor_terminal()?;
+        let mut editor = LineEditor::new(&mut terminal);
+        let mut host = PasswordPromptHost::default();
+        host.echo = echo;
+        editor.set_prompt(prompt);
+        Ok(editor.read_line(&mut host)?)
+    }
+}
+
 #[derive(Debug, Parser, Default, Clone)]
 struct Opt {
     #[clap(long = "user", short = 'l')]
@@ -70,47 +88,13 @@ fn main() {
                     }
                 }
                 SessionEvent::HostVerify(verify) => {
-                    eprintln!("{}", verify.message);
-                    let mut terminal = line_editor_terminal()?;
-                    let mut editor = LineEditor::new(&mut terminal);
-                    let mut host = PasswordPromptHost::default();
-                    host.echo = true;
-                    editor.set_prompt("Enter [y/n]> ");
-                    let ok = if let Some(line) = editor.read_line(&mut host)? {
-                        match line.as_ref() {
-                            "y" | "Y" | "yes" | "YES" => true,
-                            "n" | "N" | "no" | "NO" | _ => false,
-                        }
-                    } else {
-                        false
-                    };
-                    verify.answer(ok).await.context("send verify response")?;
+                    verify
+                        .prompt_and_answer(&mut StderrPrompt)
+                        .await
+                        .context("send verify response")?;
                 }
                 SessionEvent::Authenticate(auth) => {
-                    if !auth.username.is_empty() {
-                        eprintln!("Authentication for {}", auth.username);
-                    }
-                    if !auth.instructions.is_empty() {
-                        eprintln!("{}", auth.instructions);
-                    }
-                    let mut terminal = line_editor_terminal()?;
-                    let mut editor = LineEditor::new(&mut terminal);
-                    let mut host = PasswordPromptHost::default();
-                    let mut answers = vec![];
-                    for prompt in &auth.prompts {
-                        let mut prompt_lines = prompt.prompt.split('\n').collect::<Vec<_>>();
-                        editor.set_prompt(prompt_lines.pop().unwrap());
-                        host.echo = prompt.echo;
-                        for line in &prompt_lines {
-                            eprintln!("{}", line);
-                        }
-                        if let Some(line) = editor.read_line(&mut host)? {
-                            answers.push(line);
-                        } else {
-                            anyhow::bail!("Authentication was cancelled");
-                        }
-                    }
-                    auth.answer(answers).await?;
+                    auth.prompt_and_answer(&mut StderrPrompt).await?;
                 }
                 SessionEvent::HostVerificationFailed(failed) => {
                     anyhow::bail!("{}", failed);
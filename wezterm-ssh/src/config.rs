@@ -345,13 +345,20 @@ impl ParsedConfigFile {
 
                 fn add_option(options: &mut ConfigMap, k: String, v: &str) {
                     // first option wins in ssh_config, except for identityfile
-                    // which explicitly allows multiple entries to combine together
-                    let is_identity_file = k == "identityfile";
+                    // which explicitly allows multiple entries to combine together,
+                    // and the forwarding options, which may be specified
+                    // multiple times; their values contain spaces, so we
+                    // combine those using newlines.
+                    let separator = match k.as_str() {
+                        "identityfile" => Some(' '),
+                        "localforward" | "remoteforward" | "dynamicforward" => Some('\n'),
+                        _ => None,
+                    };
                     options
                         .entry(k)
                         .and_modify(|e| {
-                            if is_identity_file {
-                                e.push(' ');
+                            if let Some(separator) = separator {
+                                e.push(separator);
                                 e.push_str(v);
                             }
                         })
@@ -550,6 +557,11 @@ impl Config {
             .or_insert_with(|| host.to_string());
         token_map.insert("%h".to_string(), result["hostname"].to_string());
         token_map.insert("%n".to_string(), host.to_string());
+        if let Some(jump) = result.get("proxyjump") {
+            if !jump.eq_ignore_ascii_case("none") {
+                token_map.insert("%j".to_string(), jump.to_string());
+            }
+        }
         token_map.insert("%r".to_string(), target_user.to_string());
         token_map.insert(
             "%p".to_string(),
@@ -703,13 +715,8 @@ impl Config {
                     *value = items.join(" ");
                 }
             } else if t == "%j" {
-                // %j: The contents of the ProxyJump option, or the empty string if this option is unset
-                // We don't directly support ProxyJump, and this %j token referencing
-                // may technically put this into two-phase evaluation territory which
-                // we don't support.
-                // Let's silently gloss over this and treat this token as the empty
-                // string.
-                // Someone in the future will probably curse this.
+                // %j: The contents of the ProxyJump option, or the empty string if this option is unset.
+                // When it is set, it is present in the token_map and handled above.
                 *value = value.replace(t, "");
             } else if t == "%T" {
                 // %T: The local tun(4) or tap(4) network interface assigned if tunnel
@@ -798,11 +805,138 @@ impl Config {
     }
 }
 
+/// One of the hosts in a `ProxyJump` chain
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JumpHost {
+    pub user: Option<String>,
+    pub host: String,
+    pub port: Option<u16>,
+}
+
+impl JumpHost {
+    /// Parse the value of a `ProxyJump` option, which is a comma
+    /// separated list of `[user@]host[:port]` or `ssh://[user@]host[:port]`
+    /// hops, listed in the order in which they are to be connected.
+    /// Returns an empty list for the special value `none`.
+    pub fn parse_list(value: &str) -> anyhow::Result<Vec<Self>> {
+        if value.trim().eq_ignore_ascii_case("none") {
+            return Ok(vec![]);
+        }
+        value.split(',').map(|hop| hop.trim().parse()).collect()
+    }
+}
+
+impl std::str::FromStr for JumpHost {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let spec = s.strip_prefix("ssh://").unwrap_or(s);
+        let (user, host_port) = match spec.rsplit_once('@') {
+            Some((user, host_port)) => (Some(user.to_string()), host_port),
+            None => (None, spec),
+        };
+
+        let (host, port) = if let Some(bracketed) = host_port.strip_prefix('[') {
+            // An IPv6 address, with an optional port following
+            // the closing bracket
+            let (host, rest) = bracketed
+                .split_once(']')
+                .ok_or_else(|| anyhow::anyhow!("missing ] in ProxyJump host {s:?}"))?;
+            match rest.strip_prefix(':') {
+                Some(port) => (host, Some(port)),
+                None if rest.is_empty() => (host, None),
+                None => anyhow::bail!("invalid ProxyJump host {s:?}"),
+            }
+        } else {
+            match host_port.split_once(':') {
+                Some((host, port)) => (host, Some(port)),
+                None => (host_port, None),
+            }
+        };
+
+        if host.is_empty() {
+            anyhow::bail!("missing host name in ProxyJump host {s:?}");
+        }
+        let port =
+            match port {
+                Some(port) => Some(port.parse::<u16>().map_err(|err| {
+                    anyhow::anyhow!("invalid port in ProxyJump host {s:?}: {err}")
+                })?),
+                None => None,
+            };
+
+        Ok(Self {
+            user,
+            host: host.to_string(),
+            port,
+        })
+    }
+}
+
+impl std::fmt::Display for JumpHost {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if let Some(user) = &self.user {
+            write!(fmt, "{user}@")?;
+        }
+        if self.host.contains(':') {
+            write!(fmt, "[{}]", self.host)?;
+        } else {
+            write!(fmt, "{}", self.host)?;
+        }
+        if let Some(port) = self.port {
+            write!(fmt, ":{port}")?;
+        }
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
     use k9::snapshot;
 
+    #[test]
+    fn parse_proxy_jump() {
+        let hops =
+            JumpHost::parse_list("bastion, admin@inner:2222,ssh://me@[fe80::1]:22,[::1]").unwrap();
+        assert_eq!(
+            hops,
+            vec![
+                JumpHost {
+                    user: None,
+                    host: "bastion".to_string(),
+                    port: None,
+                },
+                JumpHost {
+                    user: Some("admin".to_string()),
+                    host: "inner".to_string(),
+                    port: Some(2222),
+                },
+                JumpHost {
+                    user: Some("me".to_string()),
+                    host: "fe80::1".to_string(),
+                    port: Some(22),
+                },
+                JumpHost {
+                    user: None,
+                    host: "::1".to_string(),
+                    port: None,
+                },
+            ]
+        );
+        assert_eq!(
+            hops.iter()
+                .map(|hop| hop.to_string())
+                .collect::<Vec<_>>()
+                .join(","),
+            "bastion,admin@inner:2222,me@[fe80::1]:22,[::1]"
+        );
+
+        assert!(JumpHost::parse_list("none").unwrap().is_empty());
+        assert!(JumpHost::parse_list("bastion:notaport").is_err());
+        assert!(JumpHost::parse_list("user@").is_err());
+    }
+
     #[test]
     fn parse_keepalive() {
         let mut config = Config::new();
@@ -1148,6 +1282,41 @@ Config {
         );
     }
 
+    #[test]
+    fn multiple_forwards() {
+        let mut config = Config::new();
+
+        let mut fake_env = ConfigMap::new();
+        fake_env.insert("HOME".to_string(), "/home/me".to_string());
+        fake_env.insert("USER".to_string(), "me".to_string());
+        config.assign_environment(fake_env);
+
+        config.add_config_string(
+            r#"
+        Host foo
+            LocalForward 8080 localhost:80
+            LocalForward 5432 db:5432
+            DynamicForward 1080
+            "#,
+        );
+
+        let opts = config.for_host("foo");
+        snapshot!(
+            opts,
+            r#"
+{
+    "dynamicforward": "1080",
+    "hostname": "foo",
+    "identityfile": "/home/me/.ssh/id_dsa /home/me/.ssh/id_ecdsa /home/me/.ssh/id_ed25519 /home/me/.ssh/id_rsa",
+    "localforward": "8080 localhost:80\n5432 db:5432",
+    "port": "22",
+    "user": "me",
+    "userknownhostsfile": "/home/me/.ssh/known_hosts /home/me/.ssh/known_hosts2",
+}
+"#
+        );
+    }
+
     #[test]
     fn sub_tilde() {
         let mut config = Config::new();
@@ -0,0 +1,261 @@
+use crate::session::{DeadSession, SessionRequest, SessionSender};
+use smol::channel::{bounded, Sender};
+use std::fmt::Display;
+
+pub type ForwardId = usize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForwardKind {
+    /// Connections to a local port are tunnelled to a host that is
+    /// reachable from the remote host; `ssh -L`
+    Local,
+    /// Connections to a port on the remote host are tunnelled back
+    /// to a host that is reachable from here; `ssh -R`
+    Remote,
+    /// A local SOCKS5 proxy whose connections are made from the
+    /// remote host; `ssh -D`
+    Dynamic,
+}
+
+impl Display for ForwardKind {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Local => write!(fmt, "Local"),
+            Self::Remote => write!(fmt, "Remote"),
+            Self::Dynamic => write!(fmt, "Dynamic"),
+        }
+    }
+}
+
+impl std::str::FromStr for ForwardKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "local" | "l" => Ok(Self::Local),
+            "remote" | "r" => Ok(Self::Remote),
+            "dynamic" | "d" => Ok(Self::Dynamic),
+            _ => anyhow::bail!("invalid forward kind {s:?}, expected Local, Remote or Dynamic"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForwardSpec {
+    pub kind: ForwardKind,
+    /// The address to listen on.  For local and dynamic forwards
+    /// this defaults to the loopback address, and for remote
+    /// forwards to whatever the server prefers.
+    pub listen_host: Option<String>,
+    /// The port to listen on; 0 selects any available port
+    pub listen_port: u16,
+    /// Where connections are made to; always `None` for
+    /// dynamic forwards, where the client specifies the destination
+    pub connect: Option<(String, u16)>,
+}
+
+impl ForwardSpec {
+    /// Parses a forward specification, accepting both the ssh_config
+    /// `LocalForward` form `[bind_address:]port host:hostport` and the
+    /// command line `-L` form `[bind_address:]port:host:hostport`.
+    /// Dynamic forwards are just `[bind_address:]port`.
+    /// IPv6 addresses must be enclosed in square brackets.
+    pub fn parse(kind: ForwardKind, spec: &str) -> anyhow::Result<Self> {
+        let fields = split_fields(spec)?;
+        let parse_port = |port: &str| {
+            port.parse::<u16>()
+                .map_err(|err| anyhow::anyhow!("invalid port {port:?} in {spec:?}: {err}"))
+        };
+
+        let (listen_host, listen_port, connect) = match (kind, fields.as_slice()) {
+            (ForwardKind::Dynamic, [port]) => (None, parse_port(port)?, None),
+            (ForwardKind::Dynamic, [host, port]) => {
+                (Some(host.to_string()), parse_port(port)?, None)
+            }
+            (ForwardKind::Local | ForwardKind::Remote, [port, host, hostport]) => (
+                None,
+                parse_port(port)?,
+                Some((host.to_string(), parse_port(hostport)?)),
+            ),
+            (ForwardKind::Local | ForwardKind::Remote, [bind, port, host, hostport]) => (
+                Some(bind.to_string()),
+                parse_port(port)?,
+                Some((host.to_string(), parse_port(hostport)?)),
+            ),
+            (ForwardKind::Dynamic, _) => {
+                anyhow::bail!("invalid dynamic forward {spec:?}, expected [bind_address:]port")
+            }
+            _ => anyhow::bail!(
+                "invalid {} forward {spec:?}, expected [bind_address:]port:host:hostport",
+                kind.to_string().to_lowercase()
+            ),
+        };
+
+        if let Some((host, _)) = &connect {
+            if host.is_empty() {
+                anyhow::bail!("missing destination host in {spec:?}");
+            }
+        }
+
+        Ok(Self {
+            kind,
+            listen_host: listen_host.filter(|host| !host.is_empty()),
+            listen_port,
+            connect,
+        })
+    }
+}
+
+impl Display for ForwardSpec {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fn host(fmt: &mut std::fmt::Formatter, host: &str) -> std::fmt::Result {
+            if host.contains(':') {
+                write!(fmt, "[{host}]:")
+            } else {
+                write!(fmt, "{host}:")
+            }
+        }
+
+        if let Some(listen) = &self.listen_host {
+            host(fmt, listen)?;
+        }
+        write!(fmt, "{}", self.listen_port)?;
+        if let Some((connect, port)) = &self.connect {
+            write!(fmt, ":")?;
+            host(fmt, connect)?;
+            write!(fmt, "{port}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Splits a forward specification into its colon or whitespace
+/// separated fields, allowing for bracketed IPv6 addresses
+fn split_fields(spec: &str) -> anyhow::Result<Vec<String>> {
+    let mut fields = vec![];
+    let mut current = String::new();
+    let mut in_brackets = false;
+
+    for c in spec.trim().chars() {
+        match c {
+            '[' if !in_brackets && current.is_empty() => in_brackets = true,
+            ']' if in_brackets => in_brackets = false,
+            ':' if !in_brackets => fields.push(std::mem::take(&mut current)),
+            c if c.is_whitespace() && !in_brackets => {
+                if !current.is_empty() {
+                    fields.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if in_brackets {
+        anyhow::bail!("missing ] in forward {spec:?}");
+    }
+    fields.push(current);
+    Ok(fields)
+}
+
+/// Describes an established forward
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ForwardInfo {
+    pub id: ForwardId,
+    pub spec: ForwardSpec,
+    /// The port that is being listened on.  This differs from
+    /// `spec.listen_port` when that was 0.
+    pub bound_port: u16,
+}
+
+#[derive(Debug)]
+pub(crate) enum ForwardRequest {
+    Add(ForwardSpec, Sender<anyhow::Result<ForwardInfo>>),
+    Remove(ForwardId, Sender<anyhow::Result<()>>),
+    List(Sender<Vec<ForwardInfo>>),
+}
+
+/// Manages the port forwards of a session.
+/// Forwards are serviced by the session and end along with it.
+#[derive(Clone, Debug)]
+pub struct Forwarding {
+    pub(crate) tx: SessionSender,
+}
+
+impl Forwarding {
+    /// Establishes a new forward
+    pub async fn add(&self, spec: ForwardSpec) -> anyhow::Result<ForwardInfo> {
+        let (reply, rx) = bounded(1);
+        self.tx
+            .send(SessionRequest::Forward(ForwardRequest::Add(spec, reply)))
+            .await
+            .map_err(|_| DeadSession)?;
+        rx.recv().await?
+    }
+
+    /// Stops listening for new connections for the specified forward.
+    /// Connections that were already established are not affected.
+    pub async fn remove(&self, id: ForwardId) -> anyhow::Result<()> {
+        let (reply, rx) = bounded(1);
+        self.tx
+            .send(SessionRequest::Forward(ForwardRequest::Remove(id, reply)))
+            .await
+            .map_err(|_| DeadSession)?;
+        rx.recv().await?
+    }
+
+    /// Returns the forwards that are currently established
+    pub async fn list(&self) -> anyhow::Result<Vec<ForwardInfo>> {
+        let (reply, rx) = bounded(1);
+        self.tx
+            .send(SessionRequest::Forward(ForwardRequest::List(reply)))
+            .await
+            .map_err(|_| DeadSession)?;
+        Ok(rx.recv().await?)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn local(listen_host: Option<&str>, listen_port: u16, host: &str, port: u16) -> ForwardSpec {
+        ForwardSpec {
+            kind: ForwardKind::Local,
+            listen_host: listen_host.map(|s| s.to_string()),
+            listen_port,
+            connect: Some((host.to_string(), port)),
+        }
+    }
+
+    #[test]
+    fn parse_forward_spec() {
+        let spec = ForwardSpec::parse(ForwardKind::Local, "8080:localhost:80").unwrap();
+        assert_eq!(spec, local(None, 8080, "localhost", 80));
+        assert_eq!(spec.to_string(), "8080:localhost:80");
+
+        let spec = ForwardSpec::parse(ForwardKind::Local, "8080 localhost:80").unwrap();
+        assert_eq!(spec, local(None, 8080, "localhost", 80));
+
+        let spec = ForwardSpec::parse(ForwardKind::Local, "[::1]:8080 [fe80::1]:80").unwrap();
+        assert_eq!(spec, local(Some("::1"), 8080, "fe80::1", 80));
+        assert_eq!(spec.to_string(), "[::1]:8080:[fe80::1]:80");
+
+        let spec = ForwardSpec::parse(ForwardKind::Remote, "*:9000:db:5432").unwrap();
+        assert_eq!(spec.kind, ForwardKind::Remote);
+        assert_eq!(spec.listen_host.as_deref(), Some("*"));
+        assert_eq!(spec.connect, Some(("db".to_string(), 5432)));
+
+        let spec = ForwardSpec::parse(ForwardKind::Dynamic, "1080").unwrap();
+        assert_eq!(spec.listen_port, 1080);
+        assert_eq!(spec.connect, None);
+        assert_eq!(spec.to_string(), "1080");
+
+        let spec = ForwardSpec::parse(ForwardKind::Dynamic, "localhost:1080").unwrap();
+        assert_eq!(spec.listen_host.as_deref(), Some("localhost"));
+
+        assert!(ForwardSpec::parse(ForwardKind::Local, "8080").is_err());
+        assert!(ForwardSpec::parse(ForwardKind::Local, "8080:host:http").is_err());
+        assert!(ForwardSpec::parse(ForwardKind::Local, "8080::80").is_err());
+        assert!(ForwardSpec::parse(ForwardKind::Dynamic, "1080:host:80").is_err());
+        assert!(ForwardSpec::parse(ForwardKind::Local, "[::1:8080:host:80").is_err());
+    }
+}
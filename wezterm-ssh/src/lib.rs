@@ -6,17 +6,22 @@ mod channelwrap;
 mod config;
 mod dirwrap;
 mod filewrap;
+mod forward;
 mod host;
+mod prompt;
 mod pty;
 mod session;
 mod sessioninner;
 mod sessionwrap;
 mod sftp;
 mod sftpwrap;
+mod socks;
 
 pub use auth::*;
 pub use config::*;
+pub use forward::*;
 pub use host::*;
+pub use prompt::*;
 pub use pty::*;
 pub use session::*;
 pub use sftp::error::*;
@@ -0,0 +1,56 @@
+use crate::auth::AuthenticationEvent;
+use crate::host::HostVerificationEvent;
+
+/// Something that can interact with the user while a session is
+/// being connected, such as a terminal or a connection window.
+pub trait ConnectPrompt {
+    /// Displays a line of text to the user
+    fn output_line(&mut self, line: &str) -> anyhow::Result<()>;
+
+    /// Prompts the user for a line of input.  `echo` is false when
+    /// the input is a secret that shouldn't be displayed.
+    /// Returns `None` if the user cancelled the prompt.
+    fn read_line(&mut self, prompt: &str, echo: bool) -> anyhow::Result<Option<String>>;
+}
+
+impl HostVerificationEvent {
+    /// Asks the user whether to trust the host, and answers with
+    /// their response.  Cancelling the prompt declines to trust it.
+    pub async fn prompt_and_answer(self, prompt: &mut dyn ConnectPrompt) -> anyhow::Result<()> {
+        prompt.output_line(&self.message)?;
+        let trust_host = match prompt.read_line("Enter [y/n]> ", true)? {
+            Some(line) => matches!(line.as_ref(), "y" | "Y" | "yes" | "YES"),
+            None => false,
+        };
+        self.answer(trust_host).await
+    }
+}
+
+impl AuthenticationEvent {
+    /// Prompts the user for the answers to the authentication prompts,
+    /// and answers with their responses.
+    /// Cancelling any of the prompts cancels authentication.
+    pub async fn prompt_and_answer(self, prompt: &mut dyn ConnectPrompt) -> anyhow::Result<()> {
+        if !self.username.is_empty() {
+            prompt.output_line(&format!("Authentication for {}", self.username))?;
+        }
+        if !self.instructions.is_empty() {
+            prompt.output_line(&self.instructions)?;
+        }
+        let mut answers = vec![];
+        for auth_prompt in &self.prompts {
+            // Only the last line of a multi-line prompt is used as the
+            // prompt of the line editor
+            let mut prompt_lines = auth_prompt.prompt.split('\n').collect::<Vec<_>>();
+            let editor_prompt = prompt_lines.pop().unwrap_or_default();
+            for line in &prompt_lines {
+                prompt.output_line(line)?;
+            }
+            match prompt.read_line(editor_prompt, auth_prompt.echo)? {
+                Some(line) => answers.push(line),
+                None => anyhow::bail!("Authentication was cancelled"),
+            }
+        }
+        self.answer(answers).await
+    }
+}
@@ -1,5 +1,6 @@
 use crate::auth::*;
 use crate::config::ConfigMap;
+use crate::forward::{ForwardRequest, Forwarding};
 use crate::host::*;
 use crate::pty::*;
 use crate::sessioninner::*;
@@ -7,8 +8,9 @@ use crate::sftp::{Sftp, SftpRequest};
 use filedescriptor::{socketpair, FileDescriptor};
 use portable_pty::PtySize;
 use smol::channel::{bounded, Receiver, Sender};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::io::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
@@ -58,9 +60,18 @@ pub(crate) enum SessionRequest {
     Exec(Exec, Sender<anyhow::Result<ExecResult>>),
     Sftp(SftpRequest),
     SignalChannel(SignalChannel),
+    DirectTcpIp(DirectTcpIp, Sender<anyhow::Result<FileDescriptor>>),
+    Forward(ForwardRequest),
+    CheckAlive(Sender<anyhow::Result<SessionStatus>>),
     SessionDropped,
 }
 
+#[derive(Debug)]
+pub(crate) struct DirectTcpIp {
+    pub host: String,
+    pub port: u16,
+}
+
 #[derive(Debug)]
 pub(crate) struct SignalChannel {
     pub channel: ChannelId,
@@ -73,18 +84,41 @@ pub(crate) struct Exec {
     pub env: Option<HashMap<String, String>>,
 }
 
-#[derive(Clone)]
-pub struct Session {
+static NEXT_SESSION_ID: AtomicUsize = AtomicUsize::new(1);
+
+/// Describes the state of a session, as reported by `Session::check_alive`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SessionStatus {
+    /// The number of open channels: ptys, executed commands
+    /// and forwarded connections
+    pub channels: usize,
+}
+
+/// Tells the session that it is no longer wanted once the
+/// last clone of the `Session` is dropped
+#[derive(Debug)]
+struct SessionDropGuard {
     tx: SessionSender,
 }
 
-impl Drop for Session {
+impl Drop for SessionDropGuard {
     fn drop(&mut self) {
         self.tx.try_send(SessionRequest::SessionDropped).ok();
         log::trace!("Drop Session");
     }
 }
 
+/// A connection to an ssh server.
+/// Clones of a `Session` share the same connection, which is closed
+/// once every clone has been dropped and all of its channels have
+/// been closed.
+#[derive(Clone)]
+pub struct Session {
+    tx: SessionSender,
+    id: usize,
+    _guard: Arc<SessionDropGuard>,
+}
+
 impl Session {
     pub fn connect(config: ConfigMap) -> anyhow::Result<(Self, Receiver<SessionEvent>)> {
         let (tx_event, rx_event) = bounded(8);
@@ -123,9 +157,22 @@ impl Session {
             shown_accept_env_error: false,
             last_keep_alive: now,
             keep_alive,
+            forwards: HashMap::new(),
+            next_forward_id: 1,
+            pending_opens: VecDeque::new(),
+            forward_connector: ForwardConnector::new()?,
         };
         std::thread::spawn(move || inner.run());
-        Ok((Self { tx: session_sender }, rx_event))
+        Ok((
+            Self {
+                id: NEXT_SESSION_ID.fetch_add(1, Ordering::Relaxed),
+                _guard: Arc::new(SessionDropGuard {
+                    tx: session_sender.clone(),
+                }),
+                tx: session_sender,
+            },
+            rx_event,
+        ))
     }
 
     pub async fn request_pty(
@@ -175,6 +222,44 @@ impl Session {
         Ok(exec)
     }
 
+    /// Opens a `direct-tcpip` channel through which the remote host
+    /// connects to `host:port` on our behalf.
+    /// Returns the local end of a socketpair that is relayed to
+    /// and from that channel.
+    pub async fn open_direct_tcpip(&self, host: &str, port: u16) -> anyhow::Result<FileDescriptor> {
+        let (reply, rx) = bounded(1);
+        self.tx
+            .send(SessionRequest::DirectTcpIp(
+                DirectTcpIp {
+                    host: host.to_string(),
+                    port,
+                },
+                reply,
+            ))
+            .await
+            .map_err(|_| DeadSession)?;
+        rx.recv().await?
+    }
+
+    /// Returns an identifier for the connection, which is
+    /// shared by all clones of this `Session`
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    /// Sends a keep alive to the server right away, in order to verify
+    /// that the connection is still usable, and reports the status of
+    /// the session.  An error is returned if the session has ended or
+    /// the keep alive could not be sent.
+    pub async fn check_alive(&self) -> anyhow::Result<SessionStatus> {
+        let (reply, rx) = bounded(1);
+        self.tx
+            .send(SessionRequest::CheckAlive(reply))
+            .await
+            .map_err(|_| DeadSession)?;
+        rx.recv().await.map_err(|_| DeadSession)?
+    }
+
     /// Creates a new reference to the sftp channel for filesystem operations
     ///
     /// ### Note
@@ -187,6 +272,17 @@ impl Session {
             tx: self.tx.clone(),
         }
     }
+
+    /// Creates a new reference to the port forwards of the session,
+    /// through which they can be managed at runtime.
+    /// Forwards specified by `LocalForward`, `RemoteForward` and
+    /// `DynamicForward` in the config are established automatically
+    /// once the session is authenticated.
+    pub fn forwarding(&self) -> Forwarding {
+        Forwarding {
+            tx: self.tx.clone(),
+        }
+    }
 }
 
 #[derive(Debug)]
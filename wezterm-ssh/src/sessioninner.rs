@@ -1,14 +1,19 @@
 use crate::channelwrap::ChannelWrap;
-use crate::config::ConfigMap;
+use crate::config::{Config, ConfigMap, JumpHost};
 use crate::dirwrap::DirWrap;
 use crate::filewrap::FileWrap;
+use crate::forward::{ForwardId, ForwardInfo, ForwardKind, ForwardRequest, ForwardSpec};
 use crate::pty::*;
-use crate::session::{Exec, ExecResult, SessionEvent, SessionRequest, SignalChannel};
+use crate::session::{
+    DirectTcpIp, Exec, ExecResult, Session, SessionEvent, SessionRequest, SessionStatus,
+    SignalChannel,
+};
 use crate::sessionwrap::SessionWrap;
 use crate::sftp::dir::{Dir, DirId, DirRequest};
 use crate::sftp::file::{File, FileId, FileRequest};
 use crate::sftp::{OpenWithMode, SftpChannelResult, SftpRequest};
 use crate::sftpwrap::SftpWrap;
+use crate::socks;
 use anyhow::{anyhow, Context};
 use camino::Utf8PathBuf;
 use filedescriptor::{
@@ -19,9 +24,26 @@ use smol::channel::{bounded, Receiver, Sender, TryRecvError};
 use socket2::{Domain, Socket, Type};
 use std::collections::{HashMap, VecDeque};
 use std::io::{Read, Write};
-use std::net::ToSocketAddrs;
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
 use std::time::{Duration, Instant};
 
+/// Guards against a ProxyJump chain that loops back on itself
+/// via the ssh config of one of its hops
+const MAX_JUMP_DEPTH: usize = 16;
+
+/// Options that describe the target host itself, rather than how to
+/// connect and authenticate, so they aren't applied to ProxyJump hops
+/// even when they were overridden for the target
+const TARGET_ONLY_OPTIONS: &[&str] = &[
+    "hostname",
+    "port",
+    "proxycommand",
+    "proxyjump",
+    "localforward",
+    "remoteforward",
+    "dynamicforward",
+];
+
 #[derive(Debug)]
 pub(crate) struct DescriptorState {
     pub fd: Option<FileDescriptor>,
@@ -38,6 +60,163 @@ pub(crate) struct ChannelInfo {
 
 pub(crate) type ChannelId = usize;
 
+pub(crate) struct ForwardState {
+    pub info: ForwardInfo,
+    /// Local and dynamic forwards accept connections here
+    pub listener: Option<TcpListener>,
+    /// Connections to a dynamic forward that have yet to
+    /// complete the SOCKS handshake
+    pub socks_clients: Vec<SocksClient>,
+}
+
+pub(crate) struct SocksClient {
+    pub stream: TcpStream,
+    pub buf: Vec<u8>,
+    pub greeted: bool,
+}
+
+impl SocksClient {
+    /// Reads whatever is available and advances the handshake,
+    /// returning the requested destination once it is known.
+    fn advance(&mut self) -> anyhow::Result<Option<(String, u16)>> {
+        let mut buf = [0u8; 512];
+        loop {
+            match self.stream.read(&mut buf) {
+                Ok(0) => anyhow::bail!("client disconnected"),
+                Ok(len) => self.buf.extend_from_slice(&buf[..len]),
+                Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => break,
+                Err(err) => return Err(err.into()),
+            }
+            // A well behaved client waits for us before sending more
+            if self.buf.len() > 4096 {
+                anyhow::bail!("client sent too much data during the handshake");
+            }
+        }
+
+        if !self.greeted {
+            match socks::parse_greeting(&self.buf)? {
+                None => return Ok(None),
+                Some(socks::Greeting::Rejected(reply)) => {
+                    self.stream.write_all(&reply).ok();
+                    anyhow::bail!("client requires authentication, which is not supported");
+                }
+                Some(socks::Greeting::Accepted(reply, consumed)) => {
+                    self.stream.write_all(&reply)?;
+                    self.buf.drain(0..consumed);
+                    self.greeted = true;
+                }
+            }
+        }
+
+        match socks::parse_request(&self.buf)? {
+            None => Ok(None),
+            Some(socks::Request::Unsupported(code)) => {
+                self.stream.write_all(&socks::reply(code)).ok();
+                anyhow::bail!("unsupported request");
+            }
+            Some(socks::Request::Connect(host, port, consumed)) => {
+                self.buf.drain(0..consumed);
+                Ok(Some((host, port)))
+            }
+        }
+    }
+}
+
+/// A connection that needs a direct-tcpip channel
+pub(crate) struct PendingConnection {
+    stream: TcpStream,
+    host: String,
+    port: u16,
+    /// Data that was already read from the stream
+    data: Vec<u8>,
+    /// Whether a SOCKS reply is owed to the client
+    socks: bool,
+}
+
+/// A direct-tcpip channel that is being opened for a connection
+/// to a local or dynamic forward
+pub(crate) struct PendingOpen {
+    conn: PendingConnection,
+    /// The state of the open, for backends that need it
+    /// to resume the open
+    opening: Option<ChannelWrap>,
+}
+
+/// Connects to the destinations of remote forwards on helper threads,
+/// so that resolving and connecting to a slow host can't hold up the
+/// session loop.  A helper wakes the loop by writing to `wake_write`
+/// when its connection attempt completes.
+pub(crate) struct ForwardConnector {
+    next_id: usize,
+    /// The channels that are waiting for their connection,
+    /// along with the remote port that they were accepted on
+    connecting: HashMap<usize, (ChannelWrap, u16)>,
+    tx: Sender<(usize, anyhow::Result<TcpStream>)>,
+    rx: Receiver<(usize, anyhow::Result<TcpStream>)>,
+    wake_write: FileDescriptor,
+    wake_read: FileDescriptor,
+}
+
+impl ForwardConnector {
+    pub fn new() -> anyhow::Result<Self> {
+        let (tx, rx) = smol::channel::unbounded();
+        let (mut wake_write, mut wake_read) = socketpair()?;
+        wake_write.set_non_blocking(true)?;
+        wake_read.set_non_blocking(true)?;
+        Ok(Self {
+            next_id: 1,
+            connecting: HashMap::new(),
+            tx,
+            rx,
+            wake_write,
+            wake_read,
+        })
+    }
+
+    /// Starts connecting to `host:port` on behalf of `channel`
+    fn start(
+        &mut self,
+        channel: ChannelWrap,
+        bound_port: u16,
+        host: String,
+        port: u16,
+    ) -> anyhow::Result<()> {
+        let id = self.next_id;
+        self.next_id += 1;
+        let tx = self.tx.clone();
+        let mut wake = self.wake_write.try_clone()?;
+        std::thread::Builder::new()
+            .name(format!("ssh forward to {host}:{port}"))
+            .spawn(move || {
+                let result = TcpStream::connect((host.as_str(), port))
+                    .with_context(|| format!("connecting to {host}:{port}"));
+                if tx.try_send((id, result)).is_ok() {
+                    wake.write(b"x").ok();
+                }
+            })
+            .context("spawning thread to connect")?;
+        self.connecting.insert(id, (channel, bound_port));
+        Ok(())
+    }
+
+    /// Returns the channels whose connection attempts have completed
+    fn completed(&mut self) -> Vec<(ChannelWrap, u16, anyhow::Result<TcpStream>)> {
+        let mut buf = [0u8; 64];
+        while let Ok(len) = self.wake_read.read(&mut buf) {
+            if len == 0 {
+                break;
+            }
+        }
+        let mut completed = vec![];
+        while let Ok((id, result)) = self.rx.try_recv() {
+            if let Some((channel, bound_port)) = self.connecting.remove(&id) {
+                completed.push((channel, bound_port, result));
+            }
+        }
+        completed
+    }
+}
+
 pub(crate) struct SessionInner {
     pub config: ConfigMap,
     pub tx_event: Sender<SessionEvent>,
@@ -52,6 +231,12 @@ pub(crate) struct SessionInner {
     pub shown_accept_env_error: bool,
     pub last_keep_alive: Instant,
     pub keep_alive: Option<Duration>,
+    pub forwards: HashMap<ForwardId, ForwardState>,
+    pub next_forward_id: ForwardId,
+    /// The connections to local and dynamic forwards whose channels
+    /// are being opened; only the first is in flight at any time
+    pub pending_opens: VecDeque<PendingOpen>,
+    pub forward_connector: ForwardConnector,
 }
 
 impl Drop for SessionInner {
@@ -207,7 +392,7 @@ impl SessionInner {
             sess.set_option(libssh_rs::SshOption::HostKeys(host_key.to_string()))?;
         }
 
-        let (sock, _child) = self.connect_to_host(&hostname, port, verbose)?;
+        let (sock, _proxy) = self.connect_to_host(&hostname, port, verbose)?;
         let raw = {
             #[cfg(unix)]
             {
@@ -289,7 +474,7 @@ impl SessionInner {
             ))))
             .context("notifying user of banner")?;
 
-        let (sock, _child) = self.connect_to_host(&hostname, port, verbose)?;
+        let (sock, _proxy) = self.connect_to_host(&hostname, port, verbose)?;
 
         let mut sess = ssh2::Session::new()?;
         if verbose {
@@ -326,12 +511,21 @@ impl SessionInner {
     /// If proxy_command is set, then we execute that process for ourselves
     /// too, as proxy commands are not supported by libssh2 and are not supported
     /// on Windows in libssh.
+    /// If proxy_jump is set, then it takes precedence over proxy_command
+    /// and we tunnel through the jump hosts ourselves, for the same reasons.
     fn connect_to_host(
         &self,
         hostname: &str,
         port: u16,
         verbose: bool,
-    ) -> anyhow::Result<(Socket, Option<KillOnDropChild>)> {
+    ) -> anyhow::Result<(Socket, Option<ConnectionProxy>)> {
+        if let Some(proxy_jump) = self.config.get("proxyjump") {
+            let hops = JumpHost::parse_list(proxy_jump).context("parsing ProxyJump")?;
+            if let Some((jump, earlier)) = hops.split_last() {
+                return self.connect_via_jump_host(hostname, port, jump, earlier, verbose);
+            }
+        }
+
         match self.config.get("proxycommand").map(|s| s.as_str()) {
             Some("none") | None => {}
             Some(proxy_command) => {
@@ -365,14 +559,17 @@ impl SessionInner {
                         _ => raw,
                     };
 
-                    return Ok((Socket::from_raw_fd(dest), Some(KillOnDropChild(child))));
+                    return Ok((
+                        Socket::from_raw_fd(dest),
+                        Some(ConnectionProxy::Command(KillOnDropChild(child))),
+                    ));
                 }
                 #[cfg(windows)]
                 unsafe {
                     use std::os::windows::io::{FromRawSocket, IntoRawSocket};
                     return Ok((
                         Socket::from_raw_socket(a.into_raw_socket()),
-                        Some(KillOnDropChild(child)),
+                        Some(ConnectionProxy::Command(KillOnDropChild(child))),
                     ));
                 }
             }
@@ -403,6 +600,136 @@ impl SessionInner {
         Ok((sock, None))
     }
 
+    /// Connects to `jump`, which is the last of the ProxyJump hops,
+    /// and asks it to open a `direct-tcpip` channel to the target host.
+    /// Each hop is a Session in its own right, resolved from the ssh
+    /// config in the same way as for the target, so it may have its own
+    /// user, port and identities; the `earlier` hops become its ProxyJump.
+    /// Options that were overridden for the target, such as the
+    /// `ssh_option` of an ssh domain, are overridden for the hop too.
+    /// The hop's host verification and authentication prompts are routed
+    /// through our own event channel.
+    fn connect_via_jump_host(
+        &self,
+        hostname: &str,
+        port: u16,
+        jump: &JumpHost,
+        earlier: &[JumpHost],
+        verbose: bool,
+    ) -> anyhow::Result<(Socket, Option<ConnectionProxy>)> {
+        let depth = self
+            .config
+            .get("wezterm_ssh_jump_depth")
+            .and_then(|depth| depth.parse::<usize>().ok())
+            .unwrap_or(0);
+        if depth >= MAX_JUMP_DEPTH {
+            anyhow::bail!(
+                "ProxyJump chain to {hostname} exceeds {MAX_JUMP_DEPTH} hops; \
+                 is there a loop in your ssh config?"
+            );
+        }
+
+        let mut config = Config::new();
+        config.add_default_config_files();
+        let overrides = self
+            .config
+            .get("wezterm_ssh_overrides")
+            .map(|keys| keys.split(',').collect::<Vec<_>>())
+            .unwrap_or_default();
+        for key in overrides {
+            if TARGET_ONLY_OPTIONS.contains(&key) {
+                continue;
+            }
+            if let Some(value) = self.config.get(key) {
+                config.set_option(key, value);
+            }
+        }
+        if let Some(user) = &jump.user {
+            config.set_option("user", user);
+        }
+        if let Some(port) = jump.port {
+            config.set_option("port", port.to_string());
+        }
+        if !earlier.is_empty() {
+            let earlier: Vec<String> = earlier.iter().map(|hop| hop.to_string()).collect();
+            config.set_option("proxyjump", earlier.join(","));
+        }
+        let mut hop_config = config.for_host(&jump.host);
+        for key in [
+            "wezterm_ssh_backend",
+            "wezterm_ssh_verbose",
+            "wezterm_ssh_overrides",
+        ] {
+            if let Some(value) = self.config.get(key) {
+                hop_config.insert(key.to_string(), value.to_string());
+            }
+        }
+        hop_config.insert(
+            "wezterm_ssh_jump_depth".to_string(),
+            (depth + 1).to_string(),
+        );
+        // The forwards are for the target host; as with `ssh -W`,
+        // none are established by the hops on the way there
+        hop_config.insert("clearallforwardings".to_string(), "yes".to_string());
+
+        self.tx_event
+            .try_send(SessionEvent::Banner(Some(format!(
+                "Connecting to {hostname}:{port} via jump host {jump}"
+            ))))
+            .context("notifying user of banner")?;
+
+        let (session, events) = Session::connect(hop_config)?;
+        smol::block_on(async {
+            loop {
+                match events.recv().await {
+                    Ok(SessionEvent::Authenticated) => return Ok(()),
+                    Ok(SessionEvent::Error(err)) => {
+                        anyhow::bail!("jump host {jump}: {err}");
+                    }
+                    Ok(SessionEvent::HostVerificationFailed(failed)) => {
+                        self.tx_event
+                            .send(SessionEvent::HostVerificationFailed(failed))
+                            .await
+                            .context("notifying user of host verification failure")?;
+                        anyhow::bail!("host verification failed for jump host {jump}");
+                    }
+                    Ok(event) => {
+                        self.tx_event
+                            .send(event)
+                            .await
+                            .context("forwarding jump host event")?;
+                    }
+                    Err(_) => {
+                        anyhow::bail!("jump host {jump} disconnected before authenticating");
+                    }
+                }
+            }
+        })?;
+
+        let fd = smol::block_on(session.open_direct_tcpip(hostname, port))
+            .with_context(|| format!("connecting to {hostname}:{port} via jump host {jump}"))?;
+        if verbose {
+            log::info!("tunneled to {hostname}:{port} via jump host {jump}");
+        }
+
+        let sock = {
+            use filedescriptor::IntoRawSocketDescriptor;
+            let raw = fd.into_socket_descriptor();
+            #[cfg(unix)]
+            unsafe {
+                use std::os::unix::io::FromRawFd;
+                Socket::from_raw_fd(raw)
+            }
+            #[cfg(windows)]
+            unsafe {
+                use std::os::windows::io::FromRawSocket;
+                Socket::from_raw_socket(raw)
+            }
+        };
+
+        Ok((sock, Some(ConnectionProxy::Jump(session))))
+    }
+
     /// Used to restrict to_socket_addrs results to the address
     /// family specified by the config
     fn filter_sock_addr(&self, addr: &std::net::SocketAddr) -> bool {
@@ -414,45 +741,50 @@ impl SessionInner {
     }
 
     fn do_keepalive(&mut self, sess: &mut SessionWrap) -> anyhow::Result<()> {
-        match sess {
-            #[cfg(feature = "ssh2")]
-            SessionWrap::Ssh2(_sess) => Ok(()),
-            #[cfg(feature = "libssh-rs")]
-            SessionWrap::LibSsh(sess) => {
-                // We implement a very basic keep alive mechanism here;
-                // every ServerAliveInterval seconds (if non-zero), we will
-                // send an ignore packet.
-                // Unlike the openssh client, we do not have a ServerAliveCountMax
-                // limit (because it is not clear how we could correctly implement
-                // that based on what we can see here in this crate), nor do we
-                // explicitly trigger a disconnect if there is an error with
-                // the ignore packet.
-                if let Some(duration) = self.keep_alive {
-                    if self.last_keep_alive.elapsed() >= duration {
-                        log::trace!("sending keep alive");
-                        self.last_keep_alive = Instant::now();
-                        let ignore_me = [0x42; 128];
-                        if let Err(err) = sess.sess.send_ignore(&ignore_me) {
-                            log::warn!(
-                                "Error sending IGNORE packet: {err:#}. Is peer disconnected?"
-                            );
-                        }
-                    }
+        // We implement a very basic keep alive mechanism here;
+        // every ServerAliveInterval seconds (if non-zero), we will
+        // send an ignore packet.
+        // Unlike the openssh client, we do not have a ServerAliveCountMax
+        // limit (because it is not clear how we could correctly implement
+        // that based on what we can see here in this crate), nor do we
+        // explicitly trigger a disconnect if there is an error with
+        // the ignore packet.
+        if let Some(duration) = self.keep_alive {
+            if self.last_keep_alive.elapsed() >= duration {
+                log::trace!("sending keep alive");
+                self.last_keep_alive = Instant::now();
+                if let Err(err) = sess.send_keepalive() {
+                    log::warn!("Error sending keep alive: {err:#}. Is peer disconnected?");
                 }
-                Ok(())
             }
         }
+        Ok(())
+    }
+
+    /// Sends a keep alive right away, failing if it cannot be sent
+    fn check_alive(&mut self, sess: &mut SessionWrap) -> anyhow::Result<SessionStatus> {
+        self.last_keep_alive = Instant::now();
+        sess.send_keepalive()
+            .context("the connection to the server appears to be broken")?;
+        Ok(SessionStatus {
+            channels: self.channels.len(),
+        })
     }
 
     fn request_loop(&mut self, sess: &mut SessionWrap) -> anyhow::Result<()> {
         let mut sleep_delay = Duration::from_millis(100);
 
+        sess.set_blocking(true);
+        self.start_configured_forwards(sess);
+        sess.set_blocking(false);
+
         loop {
             self.do_keepalive(sess)?;
             self.tick_io()?;
             self.drain_request_pipe();
             self.dispatch_pending_requests(sess)?;
             self.connect_pending_agent_forward_channels(sess);
+            self.connect_pending_forward_connections(sess);
 
             if self.channels.is_empty() && self.session_was_dropped {
                 log::trace!(
@@ -494,6 +826,31 @@ impl SessionInner {
                 }
             }
 
+            // Forward listeners, SOCKS handshakes and connections for
+            // remote forwards are serviced at the top of the loop;
+            // we only need to wake up for them
+            poll_array.push(pollfd {
+                fd: self.forward_connector.wake_read.as_socket_descriptor(),
+                events: POLLIN,
+                revents: 0,
+            });
+            for state in self.forwards.values() {
+                if let Some(listener) = &state.listener {
+                    poll_array.push(pollfd {
+                        fd: listener.as_socket_descriptor(),
+                        events: POLLIN,
+                        revents: 0,
+                    });
+                }
+                for client in &state.socks_clients {
+                    poll_array.push(pollfd {
+                        fd: client.stream.as_socket_descriptor(),
+                        events: POLLIN,
+                        revents: 0,
+                    });
+                }
+            }
+
             poll(&mut poll_array, Some(sleep_delay)).context("poll")?;
             sleep_delay += sleep_delay;
 
@@ -501,7 +858,7 @@ impl SessionInner {
                 if poll.revents != 0 {
                     sleep_delay = Duration::from_millis(100);
                 }
-                if idx == 0 || idx == 1 {
+                if idx == 0 || idx == 1 || idx >= mapping.len() + 2 {
                     // Dealt with at the top of the loop
                 } else if poll.revents != 0 {
                     let (channel_id, fd_num) = mapping[idx - 2];
@@ -637,6 +994,11 @@ impl SessionInner {
     }
 
     fn dispatch_pending_requests(&mut self, sess: &mut SessionWrap) -> anyhow::Result<()> {
+        // Requests may open channels, which libssh2 can't do while
+        // the channel for a forwarded connection is still being opened
+        if !self.pending_opens.is_empty() {
+            return Ok(());
+        }
         while self.dispatch_one_request(sess)? {}
         Ok(())
     }
@@ -673,6 +1035,21 @@ impl SessionInner {
                         }
                         Ok(true)
                     }
+                    SessionRequest::CheckAlive(reply) => {
+                        dispatch(reply, || self.check_alive(sess), "CheckAlive")
+                    }
+                    SessionRequest::DirectTcpIp(req, reply) => {
+                        dispatch(reply, || self.direct_tcpip(sess, req), "DirectTcpIp")
+                    }
+                    SessionRequest::Forward(ForwardRequest::Add(spec, reply)) => {
+                        dispatch(reply, || self.add_forward(sess, spec), "AddForward")
+                    }
+                    SessionRequest::Forward(ForwardRequest::Remove(id, reply)) => {
+                        dispatch(reply, || self.remove_forward(sess, id), "RemoveForward")
+                    }
+                    SessionRequest::Forward(ForwardRequest::List(reply)) => {
+                        dispatch(reply, || self.list_forwards(), "ListForwards")
+                    }
                     SessionRequest::Sftp(SftpRequest::OpenWithMode(msg, reply)) => {
                         dispatch(reply, || self.open_with_mode(sess, &msg), "OpenWithMode")
                     }
@@ -916,6 +1293,325 @@ impl SessionInner {
         }
     }
 
+    /// Opens a direct-tcpip channel and relays it to and from
+    /// one end of a socketpair; the other end is returned.
+    pub fn direct_tcpip(
+        &mut self,
+        sess: &mut SessionWrap,
+        req: DirectTcpIp,
+    ) -> anyhow::Result<FileDescriptor> {
+        let channel = sess
+            .open_direct_tcpip(&req.host, req.port)
+            .with_context(|| {
+                format!("opening direct-tcpip channel to {}:{}", req.host, req.port)
+            })?;
+
+        let (ours, theirs) = socketpair()?;
+        self.register_relay_channel(channel, ours, &[])?;
+        Ok(theirs)
+    }
+
+    /// Registers a channel whose data is relayed to and from `fd`.
+    /// `pending` is data that was already read from `fd` and
+    /// that is to be sent to the channel first.
+    fn register_relay_channel(
+        &mut self,
+        channel: ChannelWrap,
+        mut fd: FileDescriptor,
+        pending: &[u8],
+    ) -> anyhow::Result<ChannelId> {
+        fd.set_non_blocking(true)?;
+
+        let read_from_local = fd;
+        let write_to_local = read_from_local.try_clone()?;
+        let mut to_channel = VecDeque::with_capacity(8192);
+        to_channel.extend(pending);
+
+        let channel_id = self.next_channel_id;
+        self.next_channel_id += 1;
+        let info = ChannelInfo {
+            channel_id,
+            channel,
+            exit: None,
+            exited: false,
+            descriptors: [
+                DescriptorState {
+                    fd: Some(read_from_local),
+                    buf: to_channel,
+                },
+                DescriptorState {
+                    fd: Some(write_to_local),
+                    buf: VecDeque::with_capacity(8192),
+                },
+                DescriptorState {
+                    fd: None,
+                    buf: VecDeque::with_capacity(8192),
+                },
+            ],
+        };
+        self.channels.insert(channel_id, info);
+        Ok(channel_id)
+    }
+
+    /// Establishes the forwards specified by the LocalForward,
+    /// RemoteForward and DynamicForward options, unless they were
+    /// cleared by ClearAllForwardings.  Failing to establish a forward
+    /// is logged but is not fatal to the session.
+    fn start_configured_forwards(&mut self, sess: &mut SessionWrap) {
+        if let Some("yes") = self.config.get("clearallforwardings").map(|s| s.as_str()) {
+            return;
+        }
+        for (key, kind) in [
+            ("localforward", ForwardKind::Local),
+            ("remoteforward", ForwardKind::Remote),
+            ("dynamicforward", ForwardKind::Dynamic),
+        ] {
+            let specs = match self.config.get(key) {
+                Some(specs) => specs.clone(),
+                None => continue,
+            };
+            for spec in specs.lines() {
+                if let Err(err) =
+                    ForwardSpec::parse(kind, spec).and_then(|spec| self.add_forward(sess, spec))
+                {
+                    log::error!("{key} {spec}: {err:#}");
+                }
+            }
+        }
+    }
+
+    pub fn add_forward(
+        &mut self,
+        sess: &mut SessionWrap,
+        spec: ForwardSpec,
+    ) -> anyhow::Result<ForwardInfo> {
+        let (listener, bound_port) = match spec.kind {
+            ForwardKind::Local | ForwardKind::Dynamic => {
+                let host = match spec.listen_host.as_deref() {
+                    None => "localhost",
+                    Some("*") => "0.0.0.0",
+                    Some(host) => host,
+                };
+                let listener = TcpListener::bind((host, spec.listen_port))
+                    .with_context(|| format!("listening on {host}:{}", spec.listen_port))?;
+                listener.set_nonblocking(true)?;
+                let bound_port = listener.local_addr()?.port();
+                (Some(listener), bound_port)
+            }
+            ForwardKind::Remote => {
+                let bound_port = sess
+                    .listen_forward(spec.listen_host.as_deref(), spec.listen_port)
+                    .with_context(|| format!("requesting remote forward {spec}"))?;
+                (None, bound_port)
+            }
+        };
+
+        let id = self.next_forward_id;
+        self.next_forward_id += 1;
+        let info = ForwardInfo {
+            id,
+            spec,
+            bound_port,
+        };
+        log::debug!("established {} forward {}", info.spec.kind, info.spec);
+        self.forwards.insert(
+            id,
+            ForwardState {
+                info: info.clone(),
+                listener,
+                socks_clients: vec![],
+            },
+        );
+        Ok(info)
+    }
+
+    pub fn remove_forward(&mut self, sess: &mut SessionWrap, id: ForwardId) -> anyhow::Result<()> {
+        let state = self
+            .forwards
+            .remove(&id)
+            .ok_or_else(|| anyhow!("invalid forward id {id}"))?;
+        if state.info.spec.kind == ForwardKind::Remote {
+            sess.cancel_forward(state.info.bound_port);
+        }
+        Ok(())
+    }
+
+    pub fn list_forwards(&self) -> Vec<ForwardInfo> {
+        let mut forwards: Vec<ForwardInfo> = self
+            .forwards
+            .values()
+            .map(|state| state.info.clone())
+            .collect();
+        forwards.sort_by_key(|info| info.id);
+        forwards
+    }
+
+    /// Accepts connections to local and dynamic forwards, advances
+    /// their SOCKS handshakes, and accepts connections that the server
+    /// made to our remote forwards, opening and connecting channels
+    /// for each of them.
+    fn connect_pending_forward_connections(&mut self, sess: &mut SessionWrap) {
+        let mut pending = vec![];
+
+        for state in self.forwards.values_mut() {
+            let listener = match &state.listener {
+                Some(listener) => listener,
+                None => continue,
+            };
+            loop {
+                match listener.accept() {
+                    Ok((stream, peer)) => {
+                        log::trace!("forward {} accepted {peer:?}", state.info.spec);
+                        match &state.info.spec.connect {
+                            Some((host, port)) => pending.push(PendingConnection {
+                                stream,
+                                host: host.to_string(),
+                                port: *port,
+                                data: vec![],
+                                socks: false,
+                            }),
+                            None => match stream.set_nonblocking(true) {
+                                Ok(_) => state.socks_clients.push(SocksClient {
+                                    stream,
+                                    buf: vec![],
+                                    greeted: false,
+                                }),
+                                Err(err) => log::error!("SOCKS client: {err:#}"),
+                            },
+                        }
+                    }
+                    Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => break,
+                    Err(err) => {
+                        log::error!("accepting for forward {}: {err:#}", state.info.spec);
+                        break;
+                    }
+                }
+            }
+
+            let mut idx = 0;
+            while idx < state.socks_clients.len() {
+                match state.socks_clients[idx].advance() {
+                    Ok(None) => idx += 1,
+                    Ok(Some((host, port))) => {
+                        let client = state.socks_clients.remove(idx);
+                        pending.push(PendingConnection {
+                            stream: client.stream,
+                            host,
+                            port,
+                            data: client.buf,
+                            socks: true,
+                        });
+                    }
+                    Err(err) => {
+                        log::debug!("SOCKS client: {err:#}");
+                        state.socks_clients.remove(idx);
+                    }
+                }
+            }
+        }
+
+        self.pending_opens
+            .extend(pending.into_iter().map(|conn| PendingOpen {
+                conn,
+                opening: None,
+            }));
+        self.advance_pending_opens(sess);
+
+        while let Some((mut channel, bound_port)) = sess.accept_forward() {
+            let connect = self
+                .forwards
+                .values()
+                .find(|state| {
+                    state.info.spec.kind == ForwardKind::Remote
+                        && state.info.bound_port == bound_port
+                })
+                .and_then(|state| state.info.spec.connect.clone());
+            match connect {
+                Some((host, port)) => {
+                    if let Err(err) = self
+                        .forward_connector
+                        .start(channel, bound_port, host, port)
+                    {
+                        log::error!("remote forward from port {bound_port}: {err:#}");
+                    }
+                }
+                None => {
+                    log::warn!("rejecting connection to port {bound_port}, which is not forwarded");
+                    channel.close();
+                }
+            }
+        }
+
+        for (mut channel, bound_port, result) in self.forward_connector.completed() {
+            let result = match result {
+                Ok(stream) => self.register_relay_channel(channel, tcp_stream_into_fd(stream), &[]),
+                Err(err) => {
+                    channel.close();
+                    Err(err)
+                }
+            };
+            if let Err(err) = result {
+                log::error!("remote forward from port {bound_port}: {err:#}");
+            }
+        }
+    }
+
+    /// Makes progress opening the channels for connections to local
+    /// and dynamic forwards without blocking the session loop.
+    /// The channels are opened one at a time, as libssh2 can only
+    /// have one direct-tcpip open in flight.
+    fn advance_pending_opens(&mut self, sess: &mut SessionWrap) {
+        while let Some(pending) = self.pending_opens.front_mut() {
+            let channel = match sess.poll_open_direct_tcpip(
+                &mut pending.opening,
+                &pending.conn.host,
+                pending.conn.port,
+            ) {
+                Ok(None) => return,
+                Ok(Some(channel)) => Ok(channel),
+                Err(err) => Err(err),
+            };
+            let conn = match self.pending_opens.pop_front() {
+                Some(pending) => pending.conn,
+                None => return,
+            };
+            let (host, port) = (conn.host.clone(), conn.port);
+            if let Err(err) = self.relay_forward_connection(conn, channel) {
+                log::error!("forwarding to {host}:{port}: {err:#}");
+            }
+        }
+    }
+
+    fn relay_forward_connection(
+        &mut self,
+        conn: PendingConnection,
+        channel: anyhow::Result<ChannelWrap>,
+    ) -> anyhow::Result<()> {
+        let PendingConnection {
+            mut stream,
+            data,
+            socks,
+            ..
+        } = conn;
+
+        let channel = match channel {
+            Ok(channel) => channel,
+            Err(err) => {
+                if socks {
+                    stream
+                        .write_all(&socks::reply(socks::REPLY_CONNECTION_REFUSED))
+                        .ok();
+                }
+                return Err(err.context("opening direct-tcpip channel"));
+            }
+        };
+        if socks {
+            stream.write_all(&socks::reply(socks::REPLY_SUCCEEDED))?;
+        }
+        self.register_relay_channel(channel, tcp_stream_into_fd(stream), &data)?;
+        Ok(())
+    }
+
     pub fn signal_channel(&mut self, info: &SignalChannel) -> anyhow::Result<()> {
         let chan_info = self
             .channels
@@ -1114,6 +1810,18 @@ fn read_into_buf<R: Read>(r: &mut R, buf: &mut VecDeque<u8>) -> std::io::Result<
     }
 }
 
+fn tcp_stream_into_fd(stream: TcpStream) -> FileDescriptor {
+    #[cfg(unix)]
+    {
+        FileDescriptor::new(stream)
+    }
+    #[cfg(windows)]
+    unsafe {
+        use std::os::windows::io::{FromRawSocket, IntoRawSocket};
+        FileDescriptor::from_raw_socket(stream.into_raw_socket())
+    }
+}
+
 /// A little helper to ensure that the Result returned by `f()`
 /// is routed via a Sender
 fn dispatch<T, F>(reply: Sender<T>, f: F, what: &str) -> anyhow::Result<bool>
@@ -1127,6 +1835,12 @@ where
     Ok(true)
 }
 
+/// Keeps alive whatever is carrying our connection to the host
+enum ConnectionProxy {
+    Command(KillOnDropChild),
+    Jump(Session),
+}
+
 /// A little helper to ensure the Child process is killed on Drop.
 struct KillOnDropChild(std::process::Child);
 
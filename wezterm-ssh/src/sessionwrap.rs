@@ -6,6 +6,8 @@ use filedescriptor::{AsRawSocketDescriptor, SocketDescriptor, POLLIN, POLLOUT};
 pub(crate) struct Ssh2Session {
     pub sess: ssh2::Session,
     pub sftp: Option<SftpWrap>,
+    /// Remote forward listeners, along with the port that they are bound to
+    pub listeners: Vec<(u16, ssh2::Listener)>,
 }
 
 #[cfg(feature = "libssh-rs")]
@@ -25,7 +27,11 @@ pub(crate) enum SessionWrap {
 impl SessionWrap {
     #[cfg(feature = "ssh2")]
     pub fn with_ssh2(sess: ssh2::Session) -> Self {
-        Self::Ssh2(Ssh2Session { sess, sftp: None })
+        Self::Ssh2(Ssh2Session {
+            sess,
+            sftp: None,
+            listeners: vec![],
+        })
     }
 
     #[cfg(feature = "libssh-rs")]
@@ -33,6 +39,29 @@ impl SessionWrap {
         Self::LibSsh(LibSshSession { sess, sftp: None })
     }
 
+    /// Sends a packet that the server will discard, in order to keep
+    /// the connection active and to find out whether it still works
+    pub fn send_keepalive(&mut self) -> anyhow::Result<()> {
+        match self {
+            #[cfg(feature = "ssh2")]
+            Self::Ssh2(sess) => {
+                // libssh2 won't send anything unless an interval has been
+                // set; we decide when to send keepalives ourselves, so the
+                // value is otherwise unimportant
+                sess.sess.set_keepalive(false, 1);
+                sess.sess.keepalive_send()?;
+                Ok(())
+            }
+
+            #[cfg(feature = "libssh-rs")]
+            Self::LibSsh(sess) => {
+                let ignore_me = [0x42; 128];
+                sess.sess.send_ignore(&ignore_me)?;
+                Ok(())
+            }
+        }
+    }
+
     pub fn set_blocking(&mut self, blocking: bool) {
         match self {
             #[cfg(feature = "ssh2")]
@@ -93,6 +122,118 @@ impl SessionWrap {
         }
     }
 
+    pub fn open_direct_tcpip(&self, host: &str, port: u16) -> anyhow::Result<ChannelWrap> {
+        match self {
+            #[cfg(feature = "ssh2")]
+            Self::Ssh2(sess) => {
+                let channel = sess.sess.channel_direct_tcpip(host, port, None)?;
+                Ok(ChannelWrap::Ssh2(channel))
+            }
+
+            #[cfg(feature = "libssh-rs")]
+            Self::LibSsh(sess) => {
+                let channel = sess.sess.new_channel()?;
+                channel.open_forward(host, port, "127.0.0.1", 0)?;
+                Ok(ChannelWrap::LibSsh(channel))
+            }
+        }
+    }
+
+    /// Makes progress opening a direct-tcpip channel on a non-blocking
+    /// session, returning None while the server has yet to respond.
+    /// The open is resumed by calling this again with the same
+    /// `opening` state, host and port.
+    pub fn poll_open_direct_tcpip(
+        &self,
+        opening: &mut Option<ChannelWrap>,
+        host: &str,
+        port: u16,
+    ) -> anyhow::Result<Option<ChannelWrap>> {
+        match self {
+            #[cfg(feature = "ssh2")]
+            Self::Ssh2(sess) => match sess.sess.channel_direct_tcpip(host, port, None) {
+                Ok(channel) => Ok(Some(ChannelWrap::Ssh2(channel))),
+                Err(err) => {
+                    let err = std::io::Error::from(err);
+                    if err.kind() == std::io::ErrorKind::WouldBlock {
+                        Ok(None)
+                    } else {
+                        Err(err.into())
+                    }
+                }
+            },
+
+            #[cfg(feature = "libssh-rs")]
+            Self::LibSsh(sess) => {
+                let channel = match opening.take() {
+                    Some(ChannelWrap::LibSsh(channel)) => channel,
+                    _ => sess.sess.new_channel()?,
+                };
+                match channel.open_forward(host, port, "127.0.0.1", 0) {
+                    Ok(()) => Ok(Some(ChannelWrap::LibSsh(channel))),
+                    Err(libssh_rs::Error::TryAgain) => {
+                        opening.replace(ChannelWrap::LibSsh(channel));
+                        Ok(None)
+                    }
+                    Err(err) => Err(err.into()),
+                }
+            }
+        }
+    }
+
+    /// Asks the server to listen for connections on our behalf,
+    /// returning the port that it bound
+    pub fn listen_forward(&mut self, host: Option<&str>, port: u16) -> anyhow::Result<u16> {
+        match self {
+            #[cfg(feature = "ssh2")]
+            Self::Ssh2(sess) => {
+                let (listener, bound_port) = sess.sess.channel_forward_listen(port, host, None)?;
+                sess.listeners.push((bound_port, listener));
+                Ok(bound_port)
+            }
+
+            #[cfg(feature = "libssh-rs")]
+            Self::LibSsh(sess) => Ok(sess.sess.listen_forward(host, port)?),
+        }
+    }
+
+    pub fn cancel_forward(&mut self, bound_port: u16) {
+        match self {
+            // Dropping the listener cancels the forward
+            #[cfg(feature = "ssh2")]
+            Self::Ssh2(sess) => sess.listeners.retain(|(port, _)| *port != bound_port),
+
+            // libssh-rs doesn't expose a way to cancel the forward, so the
+            // server will continue to listen; the caller will reject any
+            // further connections that arrive for this port.
+            #[cfg(feature = "libssh-rs")]
+            Self::LibSsh(_sess) => {}
+        }
+    }
+
+    /// Returns a channel for a connection that arrived for a remote
+    /// forward, along with the port that it arrived on
+    pub fn accept_forward(&mut self) -> Option<(ChannelWrap, u16)> {
+        match self {
+            #[cfg(feature = "ssh2")]
+            Self::Ssh2(sess) => {
+                for (port, listener) in &mut sess.listeners {
+                    if let Ok(channel) = listener.accept() {
+                        return Some((ChannelWrap::Ssh2(channel), *port));
+                    }
+                }
+                None
+            }
+
+            #[cfg(feature = "libssh-rs")]
+            Self::LibSsh(sess) => sess
+                .sess
+                .accept_forward(std::time::Duration::ZERO)
+                .ok()
+                .map(|(port, channel)| (ChannelWrap::LibSsh(channel), port)),
+        }
+    }
+
     pub fn accept_agent_forward(&mut self) -> Option<ChannelWrap> {
         match self {
             // Unimplemented for now, an error message was printed earlier when the user tries to
@@ -0,0 +1,176 @@
+//! Just enough of SOCKS5 (RFC 1928) to serve as the local end of
+//! a dynamic port forward: no authentication, and CONNECT only.
+
+const VERSION: u8 = 5;
+const METHOD_NO_AUTH: u8 = 0;
+const METHOD_NONE_ACCEPTABLE: u8 = 0xff;
+const CMD_CONNECT: u8 = 1;
+const ATYP_IPV4: u8 = 1;
+const ATYP_DOMAIN: u8 = 3;
+const ATYP_IPV6: u8 = 4;
+
+pub(crate) const REPLY_SUCCEEDED: u8 = 0;
+pub(crate) const REPLY_CONNECTION_REFUSED: u8 = 5;
+pub(crate) const REPLY_COMMAND_NOT_SUPPORTED: u8 = 7;
+pub(crate) const REPLY_ADDRESS_TYPE_NOT_SUPPORTED: u8 = 8;
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Greeting {
+    /// The client offered to proceed without authentication;
+    /// the method selection reply and the number of bytes
+    /// consumed from the buffer are returned
+    Accepted([u8; 2], usize),
+    /// The client requires some form of authentication; the reply
+    /// should be sent and the connection closed
+    Rejected([u8; 2]),
+}
+
+/// Parses the version identifier/method selection message.
+/// Returns `None` if more data is required.
+pub(crate) fn parse_greeting(buf: &[u8]) -> anyhow::Result<Option<Greeting>> {
+    let (version, nmethods) = match buf {
+        [version, nmethods, ..] => (*version, *nmethods as usize),
+        _ => return Ok(None),
+    };
+    if version != VERSION {
+        anyhow::bail!("unsupported SOCKS version {version}");
+    }
+    let methods = match buf.get(2..2 + nmethods) {
+        Some(methods) => methods,
+        None => return Ok(None),
+    };
+    if methods.contains(&METHOD_NO_AUTH) {
+        Ok(Some(Greeting::Accepted(
+            [VERSION, METHOD_NO_AUTH],
+            2 + nmethods,
+        )))
+    } else {
+        Ok(Some(Greeting::Rejected([VERSION, METHOD_NONE_ACCEPTABLE])))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Request {
+    /// Connect to the host and port; the number of bytes
+    /// consumed from the buffer is also returned
+    Connect(String, u16, usize),
+    /// The request cannot be satisfied; the reply code
+    /// should be sent and the connection closed
+    Unsupported(u8),
+}
+
+/// Parses the request that follows method selection.
+/// Returns `None` if more data is required.
+pub(crate) fn parse_request(buf: &[u8]) -> anyhow::Result<Option<Request>> {
+    let (version, cmd, atyp) = match buf {
+        [version, cmd, _reserved, atyp, ..] => (*version, *cmd, *atyp),
+        _ => return Ok(None),
+    };
+    if version != VERSION {
+        anyhow::bail!("unsupported SOCKS version {version}");
+    }
+
+    let (host, addr_len) = match atyp {
+        ATYP_IPV4 => match buf.get(4..8) {
+            Some(addr) => (
+                std::net::Ipv4Addr::new(addr[0], addr[1], addr[2], addr[3]).to_string(),
+                4,
+            ),
+            None => return Ok(None),
+        },
+        ATYP_IPV6 => match buf.get(4..20) {
+            Some(addr) => {
+                let mut octets = [0u8; 16];
+                octets.copy_from_slice(addr);
+                (std::net::Ipv6Addr::from(octets).to_string(), 16)
+            }
+            None => return Ok(None),
+        },
+        ATYP_DOMAIN => {
+            let len = match buf.get(4) {
+                Some(len) => *len as usize,
+                None => return Ok(None),
+            };
+            match buf.get(5..5 + len) {
+                Some(name) => (String::from_utf8_lossy(name).to_string(), 1 + len),
+                None => return Ok(None),
+            }
+        }
+        _ => return Ok(Some(Request::Unsupported(REPLY_ADDRESS_TYPE_NOT_SUPPORTED))),
+    };
+
+    let port_offset = 4 + addr_len;
+    let port = match buf.get(port_offset..port_offset + 2) {
+        Some(port) => u16::from_be_bytes([port[0], port[1]]),
+        None => return Ok(None),
+    };
+
+    if cmd != CMD_CONNECT {
+        return Ok(Some(Request::Unsupported(REPLY_COMMAND_NOT_SUPPORTED)));
+    }
+
+    Ok(Some(Request::Connect(host, port, port_offset + 2)))
+}
+
+/// Builds a reply to a request.  We don't know the address that
+/// the remote host bound for the connection, so report it as
+/// unspecified, which clients tolerate.
+pub(crate) fn reply(code: u8) -> [u8; 10] {
+    [VERSION, code, 0, ATYP_IPV4, 0, 0, 0, 0, 0, 0]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn greeting() {
+        assert_eq!(parse_greeting(&[5]).unwrap(), None);
+        assert_eq!(parse_greeting(&[5, 2, 0]).unwrap(), None);
+        assert_eq!(
+            parse_greeting(&[5, 2, 2, 0, 5]).unwrap(),
+            Some(Greeting::Accepted([5, 0], 4))
+        );
+        assert_eq!(
+            parse_greeting(&[5, 1, 2]).unwrap(),
+            Some(Greeting::Rejected([5, 0xff]))
+        );
+        assert!(parse_greeting(&[4, 1, 0]).is_err());
+    }
+
+    #[test]
+    fn request() {
+        assert_eq!(parse_request(&[5, 1, 0]).unwrap(), None);
+        assert_eq!(
+            parse_request(&[5, 1, 0, 1, 127, 0, 0, 1, 0x1f, 0x90]).unwrap(),
+            Some(Request::Connect("127.0.0.1".to_string(), 8080, 10))
+        );
+
+        let mut domain = vec![5, 1, 0, 3, 9];
+        domain.extend_from_slice(b"localhost");
+        assert_eq!(parse_request(&domain).unwrap(), None);
+        domain.extend_from_slice(&[0, 80, 42]);
+        assert_eq!(
+            parse_request(&domain).unwrap(),
+            Some(Request::Connect("localhost".to_string(), 80, 16))
+        );
+
+        let mut v6 = vec![5, 1, 0, 4];
+        v6.extend_from_slice(&std::net::Ipv6Addr::LOCALHOST.octets());
+        v6.extend_from_slice(&[0, 22]);
+        assert_eq!(
+            parse_request(&v6).unwrap(),
+            Some(Request::Connect("::1".to_string(), 22, 22))
+        );
+
+        // BIND is not supported
+        assert_eq!(
+            parse_request(&[5, 2, 0, 1, 127, 0, 0, 1, 0, 80]).unwrap(),
+            Some(Request::Unsupported(REPLY_COMMAND_NOT_SUPPORTED))
+        );
+        assert_eq!(
+            parse_request(&[5, 1, 0, 9]).unwrap(),
+            Some(Request::Unsupported(REPLY_ADDRESS_TYPE_NOT_SUPPORTED))
+        );
+    }
+}
@@ -1,13 +1,10 @@
 use anyhow::Context;
-use chrono::serde::ts_seconds_option;
-use chrono::{DateTime, Utc};
 use clap::Parser;
 use config::ConfigHandle;
 use filedescriptor::FileDescriptor;
+use mux::asciicast::{CastWriter, Event, Header};
 use portable_pty::{native_pty_system, PtySize};
-use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::ffi::{OsStr, OsString};
+use std::ffi::OsString;
 use std::io::{BufRead, BufReader, BufWriter, Read, Write};
 use std::path::PathBuf;
 use std::sync::mpsc::channel;
@@ -16,122 +13,9 @@ use termwiz::escape::parser::Parser as TWParser;
 use termwiz::escape::Action;
 #[cfg(unix)]
 use unix::UnixTty as Tty;
-use wezterm_term::color::ColorPalette;
 #[cfg(windows)]
 use win::WinTty as Tty;
 
-/// See <https://github.com/asciinema/asciinema/blob/develop/doc/asciicast-v2.md>
-/// for file format specification
-#[derive(Debug, Default, Serialize, Deserialize, Clone)]
-pub struct Header {
-    /// Must be 2 or higher
-    pub version: u32,
-    /// Initial terminal width (number of columns)
-    pub width: u32,
-    /// Initial terminal height (number of columns)
-    pub height: u32,
-    /// Unix timestamp of starting time of session
-    #[serde(
-        default,
-        with = "ts_seconds_option",
-        skip_serializing_if = "Option::is_none"
-    )]
-    pub timestamp: Option<DateTime<Utc>>,
-    /// Duration of the whole recording in seconds
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub duration: Option<f32>,
-    /// Used to reduce terminal inactivity (delays between frames)
-    /// to a maximum of this amount.
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub idle_time_limit: Option<f32>,
-    /// Command that was recorded
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub command: Option<String>,
-    /// Title of the asciicast
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub title: Option<String>,
-    /// Map of captured environment variables
-    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
-    pub env: HashMap<String, String>,
-    /// Color theme of the recorded terminal
-    #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub theme: Option<Theme>,
-}
-
-impl Header {
-    fn new(config: &ConfigHandle, size: PtySize, prog: &[&OsStr]) -> Self {
-        let mut env = HashMap::new();
-        env.insert("TERM".to_string(), config.term.to_string());
-        env.insert(
-            "WEZTERM_VERSION".to_string(),
-            config::wezterm_version().to_string(),
-        );
-        env.insert(
-            "WEZTERM_TARGET_TRIPLE".to_string(),
-            config::wezterm_target_triple().to_string(),
-        );
-        if let Ok(shell) = std::env::var("SHELL") {
-            env.insert("SHELL".to_string(), shell);
-        }
-        if let Ok(lang) = std::env::var("LANG") {
-            env.insert("LANG".to_string(), lang);
-        }
-
-        let palette: ColorPalette = config.resolved_palette.clone().into();
-        let ansi_colors: Vec<String> = palette.colors.0[0..16]
-            .iter()
-            .map(|c| c.to_rgb_string())
-            .collect();
-
-        let theme = Theme {
-            fg: palette.foreground.to_rgb_string(),
-            bg: palette.background.to_rgb_string(),
-            palette: ansi_colors.join(":"),
-        };
-
-        let command = if prog.is_empty() {
-            None
-        } else {
-            let args: Vec<String> = prog
-                .iter()
-                .map(|s| s.to_string_lossy().to_string())
-                .collect();
-            Some(shell_words::join(&args))
-        };
-
-        Header {
-            version: 2,
-            height: size.rows.into(),
-            width: size.cols.into(),
-            timestamp: Some(Utc::now()),
-            env,
-            command,
-            theme: Some(theme),
-            ..Default::default()
-        }
-    }
-}
-
-#[derive(Debug, Default, Serialize, Deserialize, Clone)]
-pub struct Theme {
-    /// Normal text color
-    pub fg: String,
-    /// Normal background color
-    pub bg: String,
-    /// List of 8 or 16 colors separated by a colon character
-    pub palette: String,
-}
-
-#[derive(Debug, Default, Serialize, Deserialize, Clone)]
-pub struct Event(pub f32, pub String, pub String);
-
-impl Event {
-    fn log_output<W: Write>(mut w: W, elapsed: f32, output: &str) -> std::io::Result<()> {
-        let event = Event(elapsed, "o".to_string(), output.to_string());
-        writeln!(w, "{}", serde_json::to_string(&event)?)
-    }
-}
-
 #[cfg(windows)]
 mod win {
     use super::*;
@@ -380,8 +264,7 @@ impl RecordCommand {
                     .keep()?
             }
         };
-        let mut cast_file = BufWriter::new(cast_file);
-        writeln!(cast_file, "{}", serde_json::to_string(&header)?)?;
+        let mut cast_file = CastWriter::new(BufWriter::new(cast_file), &header)?;
 
         let pty_system = native_pty_system();
         let pair = pty_system.openpty(size)?;
@@ -446,7 +329,6 @@ impl RecordCommand {
 
         let mut child_status = None;
         let first_output = Instant::now();
-        let mut buffer = vec![];
         let mut writer = pair.master.take_writer()?;
 
         for msg in rx {
@@ -454,34 +336,10 @@ impl RecordCommand {
                 Message::Stdin(data) => {
                     writer.write_all(&data)?;
                 }
-                Message::Stdout(mut data) => {
+                Message::Stdout(data) => {
                     let elapsed = first_output.elapsed().as_secs_f32();
                     tty.write_all(&data)?;
-
-                    // The end of the data may be an incomplete utf8 sequence
-                    // that straddles the buffer boundary.  JSON requires strings
-                    // to be utf-8 so we need to send the currently-valid portions
-                    // through to the .cast file and buffer up the remainder
-                    buffer.append(&mut data);
-                    match std::str::from_utf8(&buffer) {
-                        Ok(valid) => {
-                            Event::log_output(&mut cast_file, elapsed, valid)?;
-                            buffer.clear();
-                        }
-                        Err(error) => {
-                            let valid_len = error.valid_up_to();
-                            Event::log_output(&mut cast_file, elapsed, unsafe {
-                                std::str::from_utf8_unchecked(&buffer[0..valid_len])
-                            })?;
-
-                            buffer.drain(0..valid_len);
-
-                            if let Some(invalid_sequence_length) = error.error_len() {
-                                // Invalid sequence: skip it
-                                buffer.drain(0..invalid_sequence_length);
-                            }
-                        }
-                    }
+                    cast_file.output(elapsed, &data)?;
                 }
                 Message::Terminated(status) => {
                     child_status.replace(status);
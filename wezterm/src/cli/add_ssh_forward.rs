@@ -0,0 +1,54 @@
+use clap::{ArgGroup, Parser};
+use wezterm_client::client::Client;
+
+#[derive(Debug, Parser, Clone)]
+#[command(group(ArgGroup::new("forward").required(true).args(["local", "remote", "dynamic"])))]
+pub struct AddSshForward {
+    /// The name of the ssh domain whose session will carry the forward.
+    /// The domain must already be connected.
+    #[arg(long)]
+    domain: String,
+
+    /// Forward connections made to a local port to a host and port
+    /// that are reachable from the remote host, in the same
+    /// `[bind_address:]port:host:hostport` form as `ssh -L`
+    #[arg(long, short = 'L', value_name = "SPEC")]
+    local: Option<String>,
+
+    /// Forward connections made to a port on the remote host to a
+    /// host and port that are reachable from here, in the same
+    /// `[bind_address:]port:host:hostport` form as `ssh -R`
+    #[arg(long, short = 'R', value_name = "SPEC")]
+    remote: Option<String>,
+
+    /// Run a local SOCKS5 proxy whose connections are made from
+    /// the remote host, in the same `[bind_address:]port` form
+    /// as `ssh -D`
+    #[arg(long, short = 'D', value_name = "SPEC")]
+    dynamic: Option<String>,
+}
+
+impl AddSshForward {
+    pub async fn run(&self, client: Client) -> anyhow::Result<()> {
+        let (kind, spec) = if let Some(spec) = &self.local {
+            ("Local", spec)
+        } else if let Some(spec) = &self.remote {
+            ("Remote", spec)
+        } else if let Some(spec) = &self.dynamic {
+            ("Dynamic", spec)
+        } else {
+            anyhow::bail!("one of --local, --remote or --dynamic is required");
+        };
+
+        let forward = client
+            .add_ssh_forward(codec::AddSshForward {
+                domain: self.domain.clone(),
+                kind: kind.to_string(),
+                spec: spec.to_string(),
+            })
+            .await?
+            .forward;
+        println!("{}", forward.id);
+        Ok(())
+    }
+}
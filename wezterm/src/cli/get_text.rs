@@ -1,5 +1,8 @@
+use clap::builder::PossibleValue;
 use clap::Parser;
+use config::keyassignment::ExportFormat;
 use mux::pane::PaneId;
+use termwiz_funcs::export::export_lines;
 use termwiz_funcs::lines_to_escapes;
 use wezterm_client::client::Client;
 use wezterm_term::{ScrollbackOrVisibleRowIndex, StableRowIndex};
@@ -30,8 +33,61 @@ pub struct GetText {
 
     /// Include escape sequences that color and style the text.
     /// If omitted, unattributed text will be returned.
-    #[arg(long)]
+    /// This is equivalent to `--format ansi`.
+    #[arg(long, conflicts_with = "format")]
     escapes: bool,
+
+    /// The format of the output.
+    /// "text" is unattributed text; "ansi" includes escape sequences
+    /// for the colors and styles; "html" and "svg" produce standalone
+    /// documents that use the colors of the pane.
+    #[arg(long, value_parser=ExportFormatParser{})]
+    format: Option<ExportFormat>,
+}
+
+#[derive(Clone, Copy)]
+pub struct ExportFormatParser {}
+
+const EXPORT_FORMATS: &[(&str, ExportFormat)] = &[
+    ("text", ExportFormat::Text),
+    ("ansi", ExportFormat::Ansi),
+    ("html", ExportFormat::Html),
+    ("svg", ExportFormat::Svg),
+];
+
+impl clap::builder::TypedValueParser for ExportFormatParser {
+    type Value = ExportFormat;
+
+    fn parse_ref(
+        &self,
+        _cmd: &clap::Command,
+        _arg: Option<&clap::Arg>,
+        value: &std::ffi::OsStr,
+    ) -> Result<Self::Value, clap::Error> {
+        use clap::error::*;
+
+        let value = value
+            .to_str()
+            .ok_or_else(|| Error::raw(ErrorKind::InvalidUtf8, "value must be a utf8 string\n"))?;
+        EXPORT_FORMATS
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(value))
+            .map(|(_, format)| *format)
+            .ok_or_else(|| {
+                Error::raw(
+                    ErrorKind::InvalidValue,
+                    format!("{value} is not a valid format\n"),
+                )
+            })
+    }
+
+    fn possible_values(&self) -> Option<Box<dyn Iterator<Item = PossibleValue>>> {
+        Some(Box::new(
+            EXPORT_FORMATS
+                .iter()
+                .map(|(name, _)| PossibleValue::new(name)),
+        ))
+    }
 }
 
 impl GetText {
@@ -83,10 +139,22 @@ impl GetText {
             .map(|(_idx, line)| line)
             .collect();
 
-        if self.escapes {
-            println!("{}", lines_to_escapes(lines)?);
-        } else {
-            lines.iter().for_each(|line| println!("{}", line.as_str()));
+        let format = match (self.format, self.escapes) {
+            (Some(format), _) => format,
+            (None, true) => ExportFormat::Ansi,
+            (None, false) => ExportFormat::Text,
+        };
+
+        match format {
+            ExportFormat::Text => lines.iter().for_each(|line| println!("{}", line.as_str())),
+            ExportFormat::Ansi => println!("{}", lines_to_escapes(lines)?),
+            ExportFormat::Html | ExportFormat::Svg => {
+                let palette = client
+                    .get_pane_palette(codec::GetPanePalette { pane_id })
+                    .await?
+                    .palette;
+                print!("{}", export_lines(lines, format, &palette)?);
+            }
         }
         Ok(())
     }
@@ -0,0 +1,42 @@
+use crate::cli::list_zones::SemanticTypeParser;
+use clap::Parser;
+use mux::pane::PaneId;
+use wezterm_client::client::Client;
+use wezterm_term::SemanticType;
+
+#[derive(Debug, Parser, Clone)]
+pub struct GetZoneText {
+    /// Specify the target pane.
+    /// The default is to use the current pane based on the
+    /// environment variable WEZTERM_PANE.
+    #[arg(long)]
+    pane_id: Option<PaneId>,
+
+    /// Only consider zones of the specified type when resolving
+    /// the index.
+    /// "Prompt", "Input" and "Output" are possible types.
+    #[arg(long = "type", value_parser=SemanticTypeParser{})]
+    semantic_type: Option<SemanticType>,
+
+    /// The index of the zone, as reported by `wezterm cli list-zones`
+    /// with the same `--type`.
+    /// Negative numbers count backwards from the most recent zone,
+    /// so -1 is the most recent zone.
+    #[arg(long, allow_hyphen_values = true)]
+    index: isize,
+}
+
+impl GetZoneText {
+    pub async fn run(&self, client: Client) -> anyhow::Result<()> {
+        let pane_id = client.resolve_pane_id(self.pane_id).await?;
+        let response = client
+            .get_semantic_zone_text(codec::GetSemanticZoneText {
+                pane_id,
+                semantic_type: self.semantic_type,
+                index: self.index,
+            })
+            .await?;
+        println!("{}", response.text);
+        Ok(())
+    }
+}
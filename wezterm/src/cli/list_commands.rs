@@ -0,0 +1,119 @@
+use clap::Parser;
+use mux::pane::PaneId;
+use serde::Serializer as _;
+use wezterm_client::client::Client;
+use wezterm_term::{CommandBlock, CommandBlockStatus, StableRowIndex};
+
+#[derive(Debug, Parser, Clone)]
+pub struct ListCommands {
+    /// Specify the target pane.
+    /// The default is to use the current pane based on the
+    /// environment variable WEZTERM_PANE.
+    #[arg(long)]
+    pane_id: Option<PaneId>,
+}
+
+impl ListCommands {
+    pub async fn run(&self, client: Client) -> anyhow::Result<()> {
+        let pane_id = client.resolve_pane_id(self.pane_id).await?;
+        let response = client
+            .get_command_blocks(codec::GetCommandBlocks { pane_id })
+            .await?;
+
+        let blocks = response
+            .blocks
+            .into_iter()
+            .enumerate()
+            .map(|(index, block)| CliCommandBlock::new(index, block));
+
+        let mut writer = serde_json::Serializer::pretty(std::io::stdout().lock());
+        writer.collect_seq(blocks)?;
+        Ok(())
+    }
+}
+
+#[derive(serde::Serialize)]
+struct CliCommandBlock {
+    index: usize,
+    /// One of "prompt", "running", "succeeded", "failed" or "unknown"
+    status: &'static str,
+    exit_status: Option<i32>,
+    cwd: Option<String>,
+    prompt_y: StableRowIndex,
+    output_start_y: Option<StableRowIndex>,
+    end_y: Option<StableRowIndex>,
+    start_time_ms: Option<u64>,
+    end_time_ms: Option<u64>,
+    duration_ms: Option<u64>,
+}
+
+impl CliCommandBlock {
+    fn new(index: usize, block: CommandBlock) -> Self {
+        let status = match block.status() {
+            CommandBlockStatus::Prompt => "prompt",
+            CommandBlockStatus::Running => "running",
+            CommandBlockStatus::Succeeded => "succeeded",
+            CommandBlockStatus::Failed(_) => "failed",
+            CommandBlockStatus::Unknown => "unknown",
+        };
+        Self {
+            index,
+            status,
+            exit_status: block.exit_status,
+            duration_ms: block.duration().map(|d| d.as_millis() as u64),
+            cwd: block.cwd,
+            prompt_y: block.prompt_y,
+            output_start_y: block.output_start_y,
+            end_y: block.end_y,
+            start_time_ms: block.start_time_ms,
+            end_time_ms: block.end_time_ms,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cli_command_block() {
+        let block = CommandBlock {
+            prompt_y: 5,
+            output_start_y: Some(6),
+            end_y: Some(9),
+            exit_status: Some(2),
+            cwd: Some("/tmp".to_string()),
+            start_time_ms: Some(1000),
+            end_time_ms: Some(1500),
+        };
+        let value = serde_json::to_value(CliCommandBlock::new(3, block)).unwrap();
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "index": 3,
+                "status": "failed",
+                "exit_status": 2,
+                "cwd": "/tmp",
+                "prompt_y": 5,
+                "output_start_y": 6,
+                "end_y": 9,
+                "start_time_ms": 1000,
+                "end_time_ms": 1500,
+                "duration_ms": 500,
+            })
+        );
+
+        let running = CommandBlock {
+            prompt_y: 10,
+            output_start_y: Some(11),
+            end_y: None,
+            exit_status: None,
+            cwd: None,
+            start_time_ms: Some(2000),
+            end_time_ms: None,
+        };
+        let cli = CliCommandBlock::new(4, running);
+        assert_eq!(cli.status, "running");
+        assert_eq!(cli.duration_ms, None);
+    }
+}
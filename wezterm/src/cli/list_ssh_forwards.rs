@@ -0,0 +1,75 @@
+use crate::cli::CliOutputFormatKind;
+use clap::Parser;
+use serde::Serializer as _;
+use tabout::{tabulate_output, Alignment, Column};
+use wezterm_client::client::Client;
+
+#[derive(Debug, Parser, Clone)]
+pub struct ListSshForwards {
+    /// Only list the forwards of the named ssh domain.
+    /// The default is to list the forwards of all of the
+    /// ssh domains that are connected.
+    #[arg(long)]
+    domain: Option<String>,
+
+    /// Controls the output format.
+    /// "table" and "json" are possible formats.
+    #[arg(long = "format", default_value = "table")]
+    format: CliOutputFormatKind,
+}
+
+impl ListSshForwards {
+    pub async fn run(&self, client: Client) -> anyhow::Result<()> {
+        let forwards = client
+            .list_ssh_forwards(codec::ListSshForwards {
+                domain: self.domain.clone(),
+            })
+            .await?
+            .forwards;
+
+        match self.format {
+            CliOutputFormatKind::Json => {
+                let mut writer = serde_json::Serializer::pretty(std::io::stdout().lock());
+                writer.collect_seq(forwards)?;
+            }
+            CliOutputFormatKind::Table => {
+                let cols = vec![
+                    Column {
+                        name: "DOMAIN".to_string(),
+                        alignment: Alignment::Left,
+                    },
+                    Column {
+                        name: "ID".to_string(),
+                        alignment: Alignment::Right,
+                    },
+                    Column {
+                        name: "KIND".to_string(),
+                        alignment: Alignment::Left,
+                    },
+                    Column {
+                        name: "PORT".to_string(),
+                        alignment: Alignment::Right,
+                    },
+                    Column {
+                        name: "SPEC".to_string(),
+                        alignment: Alignment::Left,
+                    },
+                ];
+                let data: Vec<Vec<String>> = forwards
+                    .into_iter()
+                    .map(|forward| {
+                        vec![
+                            forward.domain,
+                            forward.id.to_string(),
+                            forward.kind,
+                            forward.bound_port.to_string(),
+                            forward.spec,
+                        ]
+                    })
+                    .collect();
+                tabulate_output(&cols, &data, &mut std::io::stdout().lock())?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,126 @@
+use clap::builder::PossibleValue;
+use clap::Parser;
+use mux::pane::PaneId;
+use serde::Serializer as _;
+use wezterm_client::client::Client;
+use wezterm_term::{SemanticType, SemanticZone, StableRowIndex};
+
+#[derive(Debug, Parser, Clone)]
+pub struct ListZones {
+    /// Specify the target pane.
+    /// The default is to use the current pane based on the
+    /// environment variable WEZTERM_PANE.
+    #[arg(long)]
+    pane_id: Option<PaneId>,
+
+    /// Only list zones of the specified type.
+    /// "Prompt", "Input" and "Output" are possible types.
+    #[arg(long = "type", value_parser=SemanticTypeParser{})]
+    semantic_type: Option<SemanticType>,
+}
+
+impl ListZones {
+    pub async fn run(&self, client: Client) -> anyhow::Result<()> {
+        let pane_id = client.resolve_pane_id(self.pane_id).await?;
+        let response = client
+            .get_semantic_zones(codec::GetSemanticZones { pane_id })
+            .await?;
+
+        let zones = response
+            .zones
+            .into_iter()
+            .filter(|zone| {
+                self.semantic_type
+                    .map_or(true, |semantic_type| zone.semantic_type == semantic_type)
+            })
+            .enumerate()
+            .map(|(index, zone)| CliZone::new(index, zone));
+
+        let mut writer = serde_json::Serializer::pretty(std::io::stdout().lock());
+        writer.collect_seq(zones)?;
+        Ok(())
+    }
+}
+
+#[derive(serde::Serialize)]
+struct CliZone {
+    /// The index that can be passed to `wezterm cli get-zone-text`
+    index: usize,
+    semantic_type: SemanticType,
+    start_y: StableRowIndex,
+    start_x: usize,
+    end_y: StableRowIndex,
+    end_x: usize,
+}
+
+impl CliZone {
+    fn new(index: usize, zone: SemanticZone) -> Self {
+        Self {
+            index,
+            semantic_type: zone.semantic_type,
+            start_y: zone.start_y,
+            start_x: zone.start_x,
+            end_y: zone.end_y,
+            end_x: zone.end_x,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct SemanticTypeParser {}
+
+const SEMANTIC_TYPES: &[(&str, SemanticType)] = &[
+    ("Prompt", SemanticType::Prompt),
+    ("Input", SemanticType::Input),
+    ("Output", SemanticType::Output),
+];
+
+impl clap::builder::TypedValueParser for SemanticTypeParser {
+    type Value = SemanticType;
+
+    fn parse_ref(
+        &self,
+        _cmd: &clap::Command,
+        _arg: Option<&clap::Arg>,
+        value: &std::ffi::OsStr,
+    ) -> Result<Self::Value, clap::Error> {
+        use clap::error::*;
+
+        let value = value
+            .to_str()
+            .ok_or_else(|| Error::raw(ErrorKind::InvalidUtf8, "value must be a utf8 string\n"))?;
+        SEMANTIC_TYPES
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(value))
+            .map(|(_, semantic_type)| *semantic_type)
+            .ok_or_else(|| {
+                Error::raw(
+                    ErrorKind::InvalidValue,
+                    format!("{value} is not a valid semantic zone type\n"),
+                )
+            })
+    }
+
+    fn possible_values(&self) -> Option<Box<dyn Iterator<Item = PossibleValue>>> {
+        Some(Box::new(
+            SEMANTIC_TYPES
+                .iter()
+                .map(|(name, _)| PossibleValue::new(name)),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use clap::builder::TypedValueParser;
+
+    #[test]
+    fn parse_semantic_type() {
+        let cmd = clap::Command::new("test");
+        let parse = |value: &str| SemanticTypeParser {}.parse_ref(&cmd, None, value.as_ref());
+        assert_eq!(parse("Output").unwrap(), SemanticType::Output);
+        assert_eq!(parse("prompt").unwrap(), SemanticType::Prompt);
+        assert!(parse("command").is_err());
+    }
+}
@@ -2,24 +2,35 @@ use anyhow::anyhow;
 use clap::Parser;
 use std::ffi::OsString;
 use wezterm_client::client::Client;
+use wezterm_gui_subcommands::ScreenshotCommand;
 
 mod activate_pane;
 mod activate_pane_direction;
 mod activate_tab;
+mod add_ssh_forward;
 mod adjust_pane_size;
 mod get_pane_direction;
 mod get_text;
+mod get_zone_text;
 mod kill_pane;
 mod list;
 mod list_clients;
+mod list_commands;
+mod list_ssh_forwards;
+mod list_zones;
 mod move_pane_to_new_tab;
 mod proxy;
+mod record;
+mod remove_ssh_forward;
 mod rename_workspace;
+mod scp;
 mod send_text;
+mod session;
 mod set_tab_title;
 mod set_window_title;
 mod spawn_command;
 mod split_pane;
+mod tail;
 mod tls_creds;
 mod zoom_pane;
 
@@ -73,6 +84,14 @@ pub struct CliCommand {
     sub: CliSubCommand,
 }
 
+impl CliCommand {
+    /// Returns true if the subcommand is implemented by wezterm-gui,
+    /// because it needs fonts and rendering
+    pub fn is_delegated_to_gui(&self) -> bool {
+        matches!(self.sub, CliSubCommand::Screenshot(_))
+    }
+}
+
 #[derive(Debug, Parser, Clone)]
 enum CliSubCommand {
     #[command(name = "list", about = "list windows, tabs and panes")]
@@ -121,6 +140,63 @@ Outputs the pane-id for the newly created pane on success"
     #[command(name = "get-text", rename_all = "kebab")]
     GetText(get_text::GetText),
 
+    /// Output the most recent lines of a pane, and optionally
+    /// follow its output as it is produced
+    #[command(name = "tail", rename_all = "kebab")]
+    Tail(tail::Tail),
+
+    /// Start or stop recording the output of a pane to a file
+    /// in the asciicast v2 format.  Prints the path of the recording.
+    #[command(name = "record", rename_all = "kebab")]
+    Record(record::Record),
+
+    /// Render a pane to a PNG image.  The image is drawn on the
+    /// CPU using the configured fonts, so no GPU or display is needed.
+    #[command(name = "screenshot", rename_all = "kebab")]
+    Screenshot(ScreenshotCommand),
+
+    /// List the semantic zones (prompts, input and output) of a pane,
+    /// as reported by shell integration, in JSON format
+    #[command(name = "list-zones", rename_all = "kebab")]
+    ListZones(list_zones::ListZones),
+
+    /// Retrieves the text of a semantic zone of a pane and
+    /// outputs it to stdout
+    #[command(name = "get-zone-text", rename_all = "kebab")]
+    GetZoneText(get_zone_text::GetZoneText),
+
+    /// List the commands that were run at shell prompts in a pane,
+    /// with their exit status, working directory and timing,
+    /// in JSON format
+    #[command(name = "list-commands", rename_all = "kebab")]
+    ListCommands(list_commands::ListCommands),
+
+    /// List the port forwards of connected ssh domains
+    #[command(name = "list-ssh-forwards", rename_all = "kebab")]
+    ListSshForwards(list_ssh_forwards::ListSshForwards),
+
+    /// Add a local, remote or dynamic (SOCKS5) port forward to the
+    /// session of a connected ssh domain.
+    /// Outputs the id of the new forward on success
+    #[command(name = "add-ssh-forward", rename_all = "kebab")]
+    AddSshForward(add_ssh_forward::AddSshForward),
+
+    /// Remove a port forward from the session of a connected ssh domain.
+    /// Connections that were already made through it are not affected.
+    #[command(name = "remove-ssh-forward", rename_all = "kebab")]
+    RemoveSshForward(remove_ssh_forward::RemoveSshForward),
+
+    /// Copy files to or from a remote host using sftp.
+    /// The host may be the name of an ssh domain, or any host
+    /// that can be reached via ssh.
+    #[command(name = "scp", rename_all = "kebab")]
+    Scp(scp::Scp),
+
+    /// Save the layout of the windows, tabs and panes to a file,
+    /// or restore a previously saved layout
+    #[command(name = "session", rename_all = "kebab")]
+    Session(session::SessionCommand),
+
     /// Activate an adjacent pane in the specified direction.
     #[command(name = "activate-pane-direction", rename_all = "kebab")]
     ActivatePaneDirection(activate_pane_direction::ActivatePaneDirection),
@@ -166,6 +242,12 @@ Outputs the pane-id for the newly created pane on success"
 }
 
 async fn run_cli_async(opts: &crate::Opt, cli: CliCommand) -> anyhow::Result<()> {
+    // scp talks to the remote host directly, so it doesn't
+    // need a connection to the mux server
+    if let CliSubCommand::Scp(cmd) = &cli.sub {
+        return cmd.clone().run(&crate::init_config(opts)?).await;
+    }
+
     let mut ui = mux::connui::ConnectionUI::new_headless();
     let initial = true;
 
@@ -186,6 +268,17 @@ async fn run_cli_async(opts: &crate::Opt, cli: CliCommand) -> anyhow::Result<()>
         CliSubCommand::SplitPane(cmd) => cmd.run(client).await,
         CliSubCommand::SendText(cmd) => cmd.run(client).await,
         CliSubCommand::GetText(cmd) => cmd.run(client).await,
+        CliSubCommand::Tail(cmd) => cmd.run(client).await,
+        CliSubCommand::Record(cmd) => cmd.run(client).await,
+        CliSubCommand::Screenshot(_) => unreachable!(),
+        CliSubCommand::ListZones(cmd) => cmd.run(client).await,
+        CliSubCommand::GetZoneText(cmd) => cmd.run(client).await,
+        CliSubCommand::ListCommands(cmd) => cmd.run(client).await,
+        CliSubCommand::ListSshForwards(cmd) => cmd.run(client).await,
+        CliSubCommand::AddSshForward(cmd) => cmd.run(client).await,
+        CliSubCommand::RemoveSshForward(cmd) => cmd.run(client).await,
+        CliSubCommand::Scp(_) => unreachable!(),
+        CliSubCommand::Session(cmd) => cmd.run(client).await,
         CliSubCommand::SpawnCommand(cmd) => cmd.run(client, &crate::init_config(opts)?).await,
         CliSubCommand::Proxy(cmd) => cmd.run(client, &crate::init_config(opts)?).await,
         CliSubCommand::TlsCreds(cmd) => cmd.run(client).await,
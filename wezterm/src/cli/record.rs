@@ -0,0 +1,56 @@
+use clap::Parser;
+use codec::SetPaneRecording;
+use mux::pane::PaneId;
+use mux::recording::RecordingOptions;
+use std::path::PathBuf;
+use wezterm_client::client::Client;
+
+#[derive(Debug, Parser, Clone)]
+pub struct Record {
+    /// Specify the target pane.
+    /// The default is to use the current pane based on the
+    /// environment variable WEZTERM_PANE.
+    #[arg(long)]
+    pane_id: Option<PaneId>,
+
+    /// Stop recording the pane, rather than starting to record it
+    #[arg(long, conflicts_with_all = ["output", "input"])]
+    stop: bool,
+
+    /// Also record the data that is sent to the pane as input
+    #[arg(long)]
+    input: bool,
+
+    /// Where to save the recording.
+    /// The file is written by the mux server; a relative path
+    /// is resolved against the current directory.
+    /// The default is a file in the `recordings` directory of
+    /// the wezterm data directory.
+    #[arg(long, short = 'o', value_hint=clap::ValueHint::FilePath)]
+    output: Option<PathBuf>,
+}
+
+impl Record {
+    pub async fn run(self, client: Client) -> anyhow::Result<()> {
+        let pane_id = client.resolve_pane_id(self.pane_id).await?;
+        let start = if self.stop {
+            None
+        } else {
+            let path = match self.output {
+                Some(path) if path.is_relative() => Some(std::env::current_dir()?.join(path)),
+                path => path,
+            };
+            Some(RecordingOptions {
+                path,
+                capture_input: self.input,
+            })
+        };
+        let response = client
+            .set_pane_recording(SetPaneRecording { pane_id, start })
+            .await?;
+        if let Some(path) = response.path {
+            println!("{path}");
+        }
+        Ok(())
+    }
+}
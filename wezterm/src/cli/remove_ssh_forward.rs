@@ -0,0 +1,25 @@
+use clap::Parser;
+use wezterm_client::client::Client;
+
+#[derive(Debug, Parser, Clone)]
+pub struct RemoveSshForward {
+    /// The name of the ssh domain that the forward belongs to
+    #[arg(long)]
+    domain: String,
+
+    /// The id of the forward, as reported by
+    /// `wezterm cli add-ssh-forward` or `wezterm cli list-ssh-forwards`
+    id: usize,
+}
+
+impl RemoveSshForward {
+    pub async fn run(&self, client: Client) -> anyhow::Result<()> {
+        client
+            .remove_ssh_forward(codec::RemoveSshForward {
+                domain: self.domain.clone(),
+                id: self.id,
+            })
+            .await?;
+        Ok(())
+    }
+}
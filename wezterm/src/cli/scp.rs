@@ -0,0 +1,211 @@
+use clap::Parser;
+use config::{ConfigHandle, SshDomain};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Parser, Clone)]
+pub struct Scp {
+    /// Don't output transfer progress
+    #[arg(long, short = 'q')]
+    quiet: bool,
+
+    /// The files to copy.
+    /// Remote files are specified as HOST:PATH, where HOST is
+    /// either the name of an ssh domain or `[user@]host`, and PATH
+    /// is relative to the home directory of the remote user unless
+    /// it is absolute.
+    /// All sources must be on the same side of the transfer.
+    #[arg(required = true, num_args = 1..)]
+    sources: Vec<String>,
+
+    /// Where to copy the files to, either a local path or HOST:PATH.
+    /// When there are multiple sources, this must be a directory.
+    target: String,
+}
+
+/// A parsed path from the command line
+enum Location {
+    Local(PathBuf),
+    Remote { host: String, path: String },
+}
+
+impl Location {
+    fn parse(spec: &str) -> Self {
+        // Like scp, a colon only designates a remote path if it comes
+        // before any slash.  We also don't treat a single letter as a
+        // host, so that Windows drive letters are taken as local paths.
+        if let Some((host, path)) = spec.split_once(':') {
+            if host.len() > 1 && !host.contains(|c| c == '/' || c == '\\') {
+                return Self::Remote {
+                    host: host.to_string(),
+                    path: path.to_string(),
+                };
+            }
+        }
+        Self::Local(PathBuf::from(spec))
+    }
+}
+
+impl Scp {
+    pub async fn run(self, config: &ConfigHandle) -> anyhow::Result<()> {
+        let sources: Vec<Location> = self.sources.iter().map(|s| Location::parse(s)).collect();
+        let target = Location::parse(&self.target);
+
+        match target {
+            Location::Remote { host, path } => {
+                let mut local = vec![];
+                for source in sources {
+                    match source {
+                        Location::Local(path) => local.push(path),
+                        Location::Remote { .. } => {
+                            anyhow::bail!("cannot copy between two remote hosts")
+                        }
+                    }
+                }
+                self.upload(config, &host, &path, &local).await
+            }
+            Location::Local(target) => {
+                let mut remote: Vec<(String, String)> = vec![];
+                for source in sources {
+                    match source {
+                        Location::Remote { host, path } => remote.push((host, path)),
+                        Location::Local(_) => anyhow::bail!(
+                            "neither the sources nor the target are remote; use cp instead"
+                        ),
+                    }
+                }
+                let host = &remote[0].0;
+                if remote.iter().any(|(h, _)| h != host) {
+                    anyhow::bail!("all sources must be on the same remote host");
+                }
+                let paths: Vec<String> = remote.into_iter().map(|(_, path)| path).collect();
+                self.download(config, host, &paths, &target).await
+            }
+        }
+    }
+
+    async fn upload(
+        &self,
+        config: &ConfigHandle,
+        host: &str,
+        path: &str,
+        sources: &[PathBuf],
+    ) -> anyhow::Result<()> {
+        // The session must outlive the transfer, as dropping it
+        // closes the connection
+        let session = connect(config, host)?;
+        let sftp = session.sftp();
+        let home = sftp.canonicalize(".").await?;
+        let dir = mux::sftp::resolve_remote_path(&home, path);
+
+        for source in sources {
+            let name = source.display().to_string();
+            let remote = mux::sftp::upload_file(&sftp, source, &dir, &mut |transferred, total| {
+                self.progress(&name, transferred, total)
+            })
+            .await?;
+            self.done(&format!("{name} -> {host}:{remote}"));
+        }
+        Ok(())
+    }
+
+    async fn download(
+        &self,
+        config: &ConfigHandle,
+        host: &str,
+        paths: &[String],
+        target: &Path,
+    ) -> anyhow::Result<()> {
+        if paths.len() > 1 && !target.is_dir() {
+            anyhow::bail!("{} is not a directory", target.display());
+        }
+
+        // The session must outlive the transfer, as dropping it
+        // closes the connection
+        let session = connect(config, host)?;
+        let sftp = session.sftp();
+        let home = sftp.canonicalize(".").await?;
+
+        for path in paths {
+            let remote = mux::sftp::resolve_remote_path(&home, path);
+            let name = format!("{host}:{remote}");
+            let local = if target.is_dir() {
+                let file_name = remote
+                    .file_name()
+                    .ok_or_else(|| anyhow::anyhow!("{name} has no file name"))?;
+                target.join(file_name)
+            } else {
+                target.to_path_buf()
+            };
+
+            let mut file = std::fs::File::create(&local)
+                .map_err(|err| anyhow::anyhow!("creating {}: {err:#}", local.display()))?;
+            let result =
+                mux::sftp::download_file(&sftp, &remote, &mut file, &mut |transferred, total| {
+                    self.progress(&name, transferred, total)
+                })
+                .await;
+            if let Err(err) = result {
+                // Don't leave a partial file behind
+                drop(file);
+                std::fs::remove_file(&local).ok();
+                return Err(err.context(format!("writing {}", local.display())));
+            }
+            self.done(&format!("{name} -> {}", local.display()));
+        }
+        Ok(())
+    }
+
+    fn progress(&self, name: &str, transferred: u64, total: u64) {
+        if self.quiet {
+            return;
+        }
+        let mut stderr = std::io::stderr().lock();
+        if total > 0 {
+            write!(
+                stderr,
+                "\r{name} {} / {} ({}%)",
+                mux::sftp::format_size(transferred),
+                mux::sftp::format_size(total),
+                transferred * 100 / total
+            )
+            .ok();
+        } else {
+            write!(stderr, "\r{name} {}", mux::sftp::format_size(transferred)).ok();
+        }
+        stderr.flush().ok();
+    }
+
+    fn done(&self, message: &str) {
+        if !self.quiet {
+            // Clear the progress line before replacing it
+            eprintln!("\r\x1b[K{message}");
+        }
+    }
+}
+
+/// Connects to `host`, which is either the name of an ssh
+/// domain or `[user@]host`
+fn connect(config: &ConfigHandle, host: &str) -> anyhow::Result<wezterm_ssh::Session> {
+    let dom = match config
+        .ssh_domains()
+        .into_iter()
+        .find(|dom| dom.name == host)
+    {
+        Some(dom) => dom,
+        None => {
+            let (username, remote_address) = match host.split_once('@') {
+                Some((user, host)) => (Some(user.to_string()), host.to_string()),
+                None => (None, host.to_string()),
+            };
+            SshDomain {
+                name: format!("scp to {host}"),
+                remote_address,
+                username,
+                ..Default::default()
+            }
+        }
+    };
+    let ssh_config = mux::ssh::ssh_domain_to_ssh_config(&dom)?;
+    mux::ssh_pool::connect_with_terminal(ssh_config, &dom.name)
+}
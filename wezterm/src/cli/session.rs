@@ -0,0 +1,88 @@
+use clap::Parser;
+use wezterm_client::client::Client;
+
+#[derive(Debug, Parser, Clone)]
+pub struct SessionCommand {
+    #[command(subcommand)]
+    sub: SessionSubCommand,
+}
+
+#[derive(Debug, Parser, Clone)]
+enum SessionSubCommand {
+    /// Save the windows, tabs and panes as a session.
+    /// Outputs the path of the session file on success
+    #[command(name = "save", rename_all = "kebab")]
+    Save(SaveSession),
+
+    /// Re-create the windows, tabs and panes of a saved session.
+    /// Outputs the ids of the new windows on success
+    #[command(name = "restore", rename_all = "kebab")]
+    Restore(RestoreSession),
+
+    /// List the names of the saved sessions
+    #[command(name = "list", rename_all = "kebab")]
+    List,
+}
+
+#[derive(Debug, Parser, Clone)]
+struct SaveSession {
+    /// The name of the session
+    #[arg(default_value = "default")]
+    name: String,
+
+    /// Save the scrollback text of each pane as well as the layout.
+    /// The default is controlled by `session_save_scrollback`
+    #[arg(long, conflicts_with = "no_scrollback")]
+    scrollback: bool,
+
+    /// Don't save the scrollback text of the panes
+    #[arg(long)]
+    no_scrollback: bool,
+}
+
+#[derive(Debug, Parser, Clone)]
+struct RestoreSession {
+    /// The name of the session
+    #[arg(default_value = "default")]
+    name: String,
+}
+
+impl SessionCommand {
+    pub async fn run(&self, client: Client) -> anyhow::Result<()> {
+        match &self.sub {
+            SessionSubCommand::Save(save) => {
+                let scrollback = if save.scrollback {
+                    Some(true)
+                } else if save.no_scrollback {
+                    Some(false)
+                } else {
+                    None
+                };
+                let response = client
+                    .save_session(codec::SaveSession {
+                        name: Some(save.name.clone()),
+                        scrollback,
+                    })
+                    .await?;
+                println!("{}", response.path);
+            }
+            SessionSubCommand::Restore(restore) => {
+                let response = client
+                    .restore_session(codec::RestoreSession {
+                        name: Some(restore.name.clone()),
+                    })
+                    .await?;
+                for window_id in response.window_ids {
+                    println!("{window_id}");
+                }
+            }
+            SessionSubCommand::List => {
+                let response = client.list_sessions(codec::ListSessions {}).await?;
+                for name in response.names {
+                    println!("{name}");
+                }
+            }
+        }
+        Ok(())
+    }
+}
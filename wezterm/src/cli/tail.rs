@@ -0,0 +1,160 @@
+use clap::Parser;
+use codec::{PaneOutputData, PaneOutputFormat, SubscribePaneOutput};
+use mux::pane::PaneId;
+use mux::renderable::lines_to_text;
+use std::io::Write;
+use wezterm_client::client::Client;
+use wezterm_term::{Line, StableRowIndex};
+
+/// How many rows to fetch at a time when looking back
+/// through the scrollback for the initial lines
+const CHUNK_ROWS: StableRowIndex = 1024;
+
+#[derive(Debug, Parser, Clone)]
+pub struct Tail {
+    /// Specify the target pane.
+    /// The default is to use the current pane based on the
+    /// environment variable WEZTERM_PANE.
+    #[arg(long)]
+    pane_id: Option<PaneId>,
+
+    /// How many of the most recent lines of the pane to output
+    /// before following.
+    #[arg(long, short = 'n', default_value = "10")]
+    lines: usize,
+
+    /// Keep running and output new lines as they are produced
+    /// by the pane, until the pane is closed.
+    #[arg(long, short = 'f')]
+    follow: bool,
+
+    /// Only output text that was marked as command output by the
+    /// shell integration semantic zones; prompts and input are
+    /// omitted.
+    #[arg(long, conflicts_with = "raw")]
+    semantic: bool,
+
+    /// Instead of text, stream the raw bytes that are read from
+    /// the pty, including escape sequences.
+    /// No prior output is available in this mode.
+    #[arg(long, requires = "follow")]
+    raw: bool,
+}
+
+impl Tail {
+    pub async fn run(self, client: Client) -> anyhow::Result<()> {
+        let pane_id = client.resolve_pane_id(self.pane_id).await?;
+        let mut stdout = std::io::stdout();
+
+        let format = if self.raw {
+            PaneOutputFormat::Raw
+        } else if self.semantic {
+            PaneOutputFormat::SemanticOutput
+        } else {
+            PaneOutputFormat::Text
+        };
+
+        let mut start_row = None;
+        if !self.raw {
+            let info = client
+                .get_dimensions(codec::GetPaneRenderableDimensions { pane_id })
+                .await?;
+            let cursor_row = info.cursor_position.y;
+            // When following, the cursor row is reported by the
+            // subscription once the cursor moves past it
+            let end = if self.follow {
+                cursor_row
+            } else {
+                cursor_row + 1
+            };
+
+            let (lines, consumed) = self
+                .recent_lines(&client, pane_id, info.dimensions.scrollback_top, end)
+                .await?;
+            for line in lines {
+                writeln!(stdout, "{line}")?;
+            }
+            stdout.flush()?;
+            start_row.replace(consumed);
+        }
+
+        if !self.follow {
+            return Ok(());
+        }
+
+        let output = client.pane_output_receiver();
+        client
+            .subscribe_pane_output(SubscribePaneOutput {
+                pane_id,
+                format,
+                start_row,
+            })
+            .await?;
+
+        while let Ok(output) = output.recv().await {
+            if output.pane_id != pane_id {
+                continue;
+            }
+            match output.data {
+                PaneOutputData::Raw(data) => {
+                    stdout.write_all(&data)?;
+                }
+                PaneOutputData::Lines(lines) => {
+                    for line in lines {
+                        writeln!(stdout, "{line}")?;
+                    }
+                }
+                PaneOutputData::Closed => break,
+            }
+            stdout.flush()?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns up to `self.lines` logical lines that end before the
+    /// `end` row, along with the row that follows the last complete
+    /// logical line.
+    async fn recent_lines(
+        &self,
+        client: &Client,
+        pane_id: PaneId,
+        scrollback_top: StableRowIndex,
+        end: StableRowIndex,
+    ) -> anyhow::Result<(Vec<String>, StableRowIndex)> {
+        let mut start = end;
+        let mut rows: Vec<Line> = vec![];
+
+        loop {
+            let (text, consumed) = lines_to_text(&rows, self.semantic);
+            // The first logical line may have started in an earlier
+            // chunk, so we need one more than was asked for in order
+            // to be sure that the lines we keep are complete
+            if self.lines == 0 || text.len() > self.lines || start <= scrollback_top {
+                let skip = text.len().saturating_sub(self.lines);
+                return Ok((
+                    text.into_iter().skip(skip).collect(),
+                    start + consumed as StableRowIndex,
+                ));
+            }
+
+            let chunk_start = (start - CHUNK_ROWS).max(scrollback_top);
+            let lines = client
+                .get_lines(codec::GetLines {
+                    pane_id,
+                    lines: vec![chunk_start..start],
+                })
+                .await?;
+            let mut chunk: Vec<Line> = lines
+                .lines
+                .extract_data()
+                .0
+                .into_iter()
+                .map(|(_idx, line)| line)
+                .collect();
+            chunk.append(&mut rows);
+            rows = chunk;
+            start = chunk_start;
+        }
+    }
+}
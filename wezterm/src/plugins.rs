@@ -0,0 +1,112 @@
+use clap::Parser;
+use config::Config;
+use plugin::{lock_file_path, plugin_status, remove_plugin, update_plugins};
+use std::ffi::OsString;
+use std::path::PathBuf;
+use tabout::{tabulate_output, Alignment, Column};
+
+#[derive(Debug, Parser, Clone)]
+pub struct PluginCommand {
+    #[command(subcommand)]
+    sub: PluginSubCommand,
+}
+
+#[derive(Debug, Parser, Clone)]
+enum PluginSubCommand {
+    /// List the installed plugins, together with the commits that are
+    /// checked out and recorded in the lockfile
+    #[command(name = "list")]
+    List,
+
+    /// Fetch plugins and move them to the newest commit that matches
+    /// their tag, branch or rev, recording the new commits in the lockfile
+    #[command(name = "update")]
+    Update {
+        /// The URLs of the plugins to update.
+        /// The default is to update all plugins.
+        urls: Vec<String>,
+    },
+
+    /// Remove the checkout of a plugin and its entry in the lockfile
+    #[command(name = "remove")]
+    Remove {
+        /// The URL of the plugin, as passed to `wezterm.plugin.require`
+        url: String,
+    },
+}
+
+/// Abbreviates a commit hash in the same way as git
+fn short_commit(commit: &Option<String>) -> String {
+    match commit {
+        Some(commit) => commit.chars().take(10).collect(),
+        None => "-".to_string(),
+    }
+}
+
+impl PluginCommand {
+    pub fn run(&self, config_file: Option<&OsString>, skip_config: bool) -> anyhow::Result<()> {
+        let lock_path: Option<PathBuf> = if skip_config {
+            None
+        } else {
+            config_file
+                .map(PathBuf::from)
+                .or_else(Config::locate_config_file)
+                .map(|path| lock_file_path(&path))
+        };
+        let lock_path = lock_path.as_deref();
+
+        match &self.sub {
+            PluginSubCommand::List => {
+                let cols = vec![
+                    Column {
+                        name: "URL".to_string(),
+                        alignment: Alignment::Left,
+                    },
+                    Column {
+                        name: "PIN".to_string(),
+                        alignment: Alignment::Left,
+                    },
+                    Column {
+                        name: "LOCKED".to_string(),
+                        alignment: Alignment::Left,
+                    },
+                    Column {
+                        name: "CHECKED OUT".to_string(),
+                        alignment: Alignment::Left,
+                    },
+                ];
+                let data: Vec<Vec<String>> = plugin_status(lock_path)?
+                    .into_iter()
+                    .map(|status| {
+                        vec![
+                            status.url,
+                            status.pin.to_string(),
+                            short_commit(&status.locked),
+                            short_commit(&status.checked_out),
+                        ]
+                    })
+                    .collect();
+                tabulate_output(&cols, &data, &mut std::io::stdout().lock())?;
+            }
+            PluginSubCommand::Update { urls } => {
+                let mut failed = 0;
+                for (url, result) in update_plugins(lock_path, urls)? {
+                    match result {
+                        Ok(commit) => println!("{url}: {}", short_commit(&Some(commit))),
+                        Err(err) => {
+                            eprintln!("{url}: {err:#}");
+                            failed += 1;
+                        }
+                    }
+                }
+                if failed > 0 {
+                    anyhow::bail!("{failed} plugin(s) failed to update");
+                }
+            }
+            PluginSubCommand::Remove { url } => {
+                remove_plugin(lock_path, url)?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -319,6 +319,13 @@ pub trait WindowOps {
     /// Set some text in the clipboard
     fn set_clipboard(&self, clipboard: Clipboard, text: String);
 
+    /// Set some text in the clipboard together with an html rendition
+    /// of it, which applications that accept rich text will prefer.
+    /// Backends without html support only set the text.
+    fn set_clipboard_html(&self, clipboard: Clipboard, text: String, _html: String) {
+        self.set_clipboard(clipboard, text)
+    }
+
     /// Set window level. Depending on the environment and user preferences
     fn set_window_level(&self, _level: WindowLevel) {}
 
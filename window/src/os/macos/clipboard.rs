@@ -1,5 +1,8 @@
 use crate::macos::{nsstring, nsstring_to_str};
-use cocoa::appkit::{NSFilenamesPboardType, NSPasteboard, NSStringPboardType};
+use cocoa::appkit::{
+    NSFilenamesPboardType, NSPasteboard, NSPasteboardTypeHTML, NSPasteboardTypeString,
+    NSStringPboardType,
+};
 use cocoa::base::*;
 use cocoa::foundation::NSArray;
 
@@ -48,4 +51,21 @@ impl Clipboard {
             Ok(())
         }
     }
+
+    /// Writes `data` as text, along with `html` for applications
+    /// that accept rich text
+    pub fn write_html(&mut self, data: String, html: String) -> anyhow::Result<()> {
+        unsafe {
+            self.pasteboard.clearContents();
+            let success: BOOL = self
+                .pasteboard
+                .setString_forType(*nsstring(&data), NSPasteboardTypeString);
+            anyhow::ensure!(success == YES, "pasteboard write returned false");
+            let success: BOOL = self
+                .pasteboard
+                .setString_forType(*nsstring(&html), NSPasteboardTypeHTML);
+            anyhow::ensure!(success == YES, "pasteboard write of html returned false");
+            Ok(())
+        }
+    }
 }
@@ -869,6 +869,10 @@ impl WindowOps for Window {
         ClipboardContext::new().write(text).ok();
     }
 
+    fn set_clipboard_html(&self, _clipboard: Clipboard, text: String, html: String) {
+        ClipboardContext::new().write_html(text, html).ok();
+    }
+
     fn toggle_fullscreen(&self) {
         Connection::with_window_inner(self.id, move |inner| {
             inner.toggle_fullscreen();
@@ -12,9 +12,36 @@ use wayland_protocols::wp::primary_selection::zv1::client::zwp_primary_selection
 
 use crate::{Clipboard, ConnectionOps};
 
-use super::data_device::TEXT_MIME_TYPE;
+use super::data_device::{HTML_MIME_TYPE, TEXT_MIME_TYPE};
 use super::state::WaylandState;
 
+/// The text that we offer as the selection, along with
+/// an optional html rendition of it
+#[derive(Debug)]
+pub(super) struct SelectionData {
+    pub text: String,
+    pub html: Option<String>,
+}
+
+impl SelectionData {
+    fn mime_types(&self) -> Vec<&'static str> {
+        let mut mime_types = vec![TEXT_MIME_TYPE];
+        if self.html.is_some() {
+            mime_types.push(HTML_MIME_TYPE);
+        }
+        mime_types
+    }
+
+    /// Returns the data to send for a request of `mime`
+    pub fn for_mime(&self, mime: &str) -> Option<&str> {
+        match mime {
+            TEXT_MIME_TYPE => Some(&self.text),
+            HTML_MIME_TYPE => self.html.as_deref(),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct CopyAndPaste {
     data_offer: Option<SelectionOffer>,
@@ -62,7 +89,7 @@ impl CopyAndPaste {
         }
     }
 
-    pub(super) fn set_clipboard_data(&mut self, clipboard: Clipboard, data: String) {
+    pub(super) fn set_clipboard_data(&mut self, clipboard: Clipboard, data: SelectionData) {
         let conn = crate::Connection::get().unwrap().wayland();
         let qh = conn.event_queue.borrow().handle();
         let mut wayland_state = conn.wayland_state.borrow_mut();
@@ -77,7 +104,7 @@ impl CopyAndPaste {
         match primary_selection {
             Some(primary_selection) => {
                 let manager = wayland_state.primary_selection_manager.as_ref().unwrap();
-                let source = manager.create_selection_source(&qh, [TEXT_MIME_TYPE]);
+                let source = manager.create_selection_source(&qh, data.mime_types());
                 source.set_selection(&primary_selection, last_serial);
                 wayland_state
                     .primary_selection_source
@@ -87,7 +114,7 @@ impl CopyAndPaste {
                 let data_device = &wayland_state.data_device;
                 let source = wayland_state
                     .data_device_manager_state
-                    .create_copy_paste_source(&qh, vec![TEXT_MIME_TYPE]);
+                    .create_copy_paste_source(&qh, data.mime_types());
                 source.set_selection(data_device.as_ref().unwrap(), last_serial);
                 wayland_state.copy_paste_source.replace((source, data));
             }
@@ -171,15 +198,13 @@ impl PrimarySelectionSourceHandler for WaylandState {
         mime: String,
         write_pipe: toolkit::data_device_manager::WritePipe,
     ) {
-        if mime != TEXT_MIME_TYPE {
-            return;
-        };
-
         if let Some((ps_source, data)) = &self.primary_selection_source {
             if ps_source.inner() != source {
                 return;
             }
-            write_selection_to_pipe(write_pipe, data);
+            if let Some(data) = data.for_mime(&mime) {
+                write_selection_to_pipe(write_pipe, data);
+            }
         }
     }
 
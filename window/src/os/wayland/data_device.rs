@@ -16,6 +16,7 @@ use super::drag_and_drop::{DragAndDrop, SurfaceAndPipe};
 use super::state::WaylandState;
 
 pub(super) const TEXT_MIME_TYPE: &str = "text/plain;charset=utf-8";
+pub(super) const HTML_MIME_TYPE: &str = "text/html";
 pub(super) const URI_MIME_TYPE: &str = "text/uri-list";
 
 impl DataDeviceHandler for WaylandState {
@@ -184,15 +185,13 @@ impl DataSourceHandler for WaylandState {
         mime: String,
         fd: WritePipe,
     ) {
-        if mime != TEXT_MIME_TYPE {
-            return;
-        }
-
         if let Some((cp_source, data)) = &self.copy_paste_source {
             if cp_source.inner() != source {
                 return;
             }
-            write_selection_to_pipe(fd, data);
+            if let Some(data) = data.for_mime(&mime) {
+                write_selection_to_pipe(fd, data);
+            }
         }
     }
 
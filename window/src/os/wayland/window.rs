@@ -58,7 +58,7 @@ use crate::{
     WindowEventSender, WindowKeyEvent, WindowOps, WindowState,
 };
 
-use super::copy_and_paste::CopyAndPaste;
+use super::copy_and_paste::{CopyAndPaste, SelectionData};
 use super::pointer::{PendingMouse, PointerUserData};
 use super::state::WaylandState;
 
@@ -457,7 +457,20 @@ impl WindowOps for WaylandWindow {
                 .copy_and_paste
                 .lock()
                 .unwrap()
-                .set_clipboard_data(clipboard, text);
+                .set_clipboard_data(clipboard, SelectionData { text, html: None });
+            Ok(())
+        });
+    }
+
+    fn set_clipboard_html(&self, clipboard: Clipboard, text: String, html: String) {
+        WaylandConnection::with_window_inner(self.0, move |inner| {
+            inner.copy_and_paste.lock().unwrap().set_clipboard_data(
+                clipboard,
+                SelectionData {
+                    text,
+                    html: Some(html),
+                },
+            );
             Ok(())
         });
     }
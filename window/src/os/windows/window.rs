@@ -974,6 +974,34 @@ impl HasWindowHandle for Window {
     }
 }
 
+/// Wraps an html fragment in the header and markers of the CF_HTML
+/// clipboard format, whose offsets are byte offsets into the result
+fn cf_html(fragment: &str) -> String {
+    const PREFIX: &str = "<html><body>\r\n<!--StartFragment-->";
+    const SUFFIX: &str = "<!--EndFragment-->\r\n</body></html>";
+    let header =
+        |start_html: usize, end_html: usize, start_fragment: usize, end_fragment: usize| {
+            format!(
+                "Version:0.9\r\n\
+                 StartHTML:{start_html:010}\r\n\
+                 EndHTML:{end_html:010}\r\n\
+                 StartFragment:{start_fragment:010}\r\n\
+                 EndFragment:{end_fragment:010}\r\n"
+            )
+        };
+
+    // The offsets are padded to a fixed width, so the
+    // length of the header doesn't depend on them
+    let start_html = header(0, 0, 0, 0).len();
+    let start_fragment = start_html + PREFIX.len();
+    let end_fragment = start_fragment + fragment.len();
+    let end_html = end_fragment + SUFFIX.len();
+    format!(
+        "{}{PREFIX}{fragment}{SUFFIX}",
+        header(start_html, end_html, start_fragment, end_fragment)
+    )
+}
+
 #[async_trait(?Send)]
 impl WindowOps for Window {
     async fn enable_opengl(&self) -> anyhow::Result<Rc<glium::backend::Context>> {
@@ -1188,6 +1216,20 @@ impl WindowOps for Window {
         clipboard_win::set_clipboard_string(&text).ok();
     }
 
+    fn set_clipboard_html(&self, _clipboard: Clipboard, text: String, html: String) {
+        fn set(text: &str, html: &str) -> std::io::Result<()> {
+            let format = clipboard_win::register_format("HTML Format")?;
+            let clipboard = clipboard_win::Clipboard::new()?;
+            clipboard.empty()?;
+            clipboard.set_string(text)?;
+            clipboard.set(format, cf_html(html).as_bytes())
+        }
+        if let Err(err) = set(&text, &html) {
+            log::error!("setting html clipboard: {err:#}");
+            clipboard_win::set_clipboard_string(&text).ok();
+        }
+    }
+
     fn set_window_drag_position(&self, coords: ScreenPoint) {
         Connection::with_window_inner(self.0, move |inner| {
             inner.window_drag_position = Some(coords);
@@ -61,6 +61,7 @@ pub struct XConnection {
     pub atom_utf8_string: Atom,
     pub atom_xsel_data: Atom,
     pub atom_targets: Atom,
+    pub atom_text_html: Atom,
     pub atom_clipboard: Atom,
     pub atom_texturilist: Atom,
     pub atom_xmozurl: Atom,
@@ -678,6 +679,7 @@ impl XConnection {
         let atom_utf8_string = Self::intern_atom(&conn, "UTF8_STRING")?;
         let atom_xsel_data = Self::intern_atom(&conn, "XSEL_DATA")?;
         let atom_targets = Self::intern_atom(&conn, "TARGETS")?;
+        let atom_text_html = Self::intern_atom(&conn, "text/html")?;
         let atom_clipboard = Self::intern_atom(&conn, "CLIPBOARD")?;
         let atom_texturilist = Self::intern_atom(&conn, "text/uri-list")?;
         let atom_xmozurl = Self::intern_atom(&conn, "text/x-moz-url")?;
@@ -856,6 +858,7 @@ impl XConnection {
             atom_utf8_string,
             atom_xsel_data,
             atom_targets,
+            atom_text_html,
             windows: RefCell::new(HashMap::new()),
             child_to_parent_id: RefCell::new(HashMap::new()),
             should_terminate: RefCell::new(false),
@@ -408,4 +408,11 @@ impl WindowOps for Window {
             Self::Wayland(w) => w.set_clipboard(clipboard, text),
         }
     }
+    fn set_clipboard_html(&self, clipboard: Clipboard, text: String, html: String) {
+        match self {
+            Self::X11(x) => x.set_clipboard_html(clipboard, text, html),
+            #[cfg(feature = "wayland")]
+            Self::Wayland(w) => w.set_clipboard_html(clipboard, text, html),
+        }
+    }
 }